@@ -0,0 +1,57 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use perpetual_backend::services::{
+    synthetic_monitor, synthetic_positions, EventKind, PriceWalk, ReplayPriceSource,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast::error::RecvError;
+
+const SEED: u64 = 7;
+const REPLAYED_TICKS: usize = 256;
+
+/// One PnL pass over every open position after a replayed price tick
+fn pnl_tick(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("pnl_tick");
+
+    for count in [1_000, 10_000, 50_000] {
+        let price_source = Arc::new(ReplayPriceSource::synthetic());
+        let monitor = synthetic_monitor(Arc::clone(&price_source)).unwrap();
+        runtime.block_on(monitor.seed_positions(synthetic_positions(count, SEED)));
+
+        // Keep a subscriber so updates are actually queued, as with a WebSocket client
+        let mut updates = monitor.events().receiver(EventKind::Position);
+        runtime.spawn(async move { while !matches!(updates.recv().await, Err(RecvError::Closed)) {} });
+
+        let mut walk = PriceWalk::new(SEED, 10);
+        let ticks: Vec<_> = (0..REPLAYED_TICKS).map(|_| walk.next_tick()).collect();
+        let next = AtomicUsize::new(0);
+
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.to_async(&runtime).iter(|| async {
+                let tick = &ticks[next.fetch_add(1, Ordering::Relaxed) % REPLAYED_TICKS];
+                price_source.apply_tick(tick).await;
+                monitor.update_all_pnl().await.unwrap();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Asset index lookups, the read path the API and liquidation checks share
+fn positions_by_asset(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let monitor = synthetic_monitor(Arc::new(ReplayPriceSource::synthetic())).unwrap();
+    runtime.block_on(monitor.seed_positions(synthetic_positions(10_000, SEED)));
+
+    c.bench_function("positions_by_asset/10000", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { monitor.get_positions_by_asset("SOL-USD").await.len() })
+    });
+}
+
+criterion_group!(benches, pnl_tick, positions_by_asset);
+criterion_main!(benches);
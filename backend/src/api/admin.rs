@@ -0,0 +1,73 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use sha2::{Digest, Sha256};
+
+use crate::api::errors::ApiError;
+use crate::api::handlers::AppState;
+
+/// Shared secret for admin routes, sent as `Authorization: Bearer <token>`
+pub struct AdminToken {
+    digest: [u8; 32],
+}
+
+impl AdminToken {
+    pub fn new(token: &str) -> Self {
+        Self {
+            digest: Sha256::digest(token.as_bytes()).into(),
+        }
+    }
+
+    /// Compares digests without short-circuiting, so timing doesn't leak a prefix
+    pub fn verify(&self, presented: &str) -> bool {
+        let presented: [u8; 32] = Sha256::digest(presented.as_bytes()).into();
+
+        self.digest
+            .iter()
+            .zip(presented.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+}
+
+/// Proof the request carried the admin token. Admin routes answer 401 when
+/// ADMIN_API_TOKEN is not set
+pub struct Admin;
+
+#[async_trait]
+impl FromRequestParts<AppState> for Admin {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let Some(admin_token) = &state.admin_token else {
+            return Err(ApiError::Unauthorized("Admin API is not enabled".to_string()));
+        };
+
+        let presented = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        match presented {
+            Some(token) if admin_token.verify(token) => Ok(Admin),
+            _ => Err(ApiError::Unauthorized("Missing or invalid admin token".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_token_verify() {
+        let token = AdminToken::new("s3cret");
+
+        assert!(token.verify("s3cret"));
+        assert!(!token.verify("s3cre"));
+        assert!(!token.verify(""));
+    }
+}
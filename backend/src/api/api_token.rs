@@ -0,0 +1,113 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, Method},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::api::errors::ApiError;
+use crate::api::graphql::GRAPHQL_PATH;
+use crate::api::handlers::AppState;
+use crate::api::tenant::CurrentTenant;
+use crate::services::{ApiToken, API_TOKEN_PREFIX};
+use solana_sdk::pubkey::Pubkey;
+
+/// Reads outside `/users/:owner/` whose handlers learn the owner from the
+/// request or the data, and check it with `authorize_owner`
+const OWNER_CHECKED_ROUTES: [&str; 3] = ["/positions", "/positions/:id", "/ws"];
+
+/// Enforce read-only API tokens. A request carrying one must be a GET under
+/// `/users/:owner/` for the token's owner, a GraphQL query, or a GET of one of
+/// `OWNER_CHECKED_ROUTES`, whose handlers apply the same owner scope; the
+/// token is then handed to the handler as an extension. Other bearer values (the admin token) pass through. With
+/// `REQUIRE_API_TOKEN` set, reads under `/users/:owner/` need a token
+/// Tokens are looked up in the request's tenant, see `tenant::resolve`
+pub async fn authorize(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .filter(|token| token.starts_with(API_TOKEN_PREFIX))
+        .map(str::to_string);
+    let owner = path_owner(request.uri().path()).map(str::to_string);
+    let is_read = request.method() == Method::GET;
+    let is_graphql = request.uri().path() == GRAPHQL_PATH;
+    let owner_checked = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|path| OWNER_CHECKED_ROUTES.contains(&path.as_str()));
+
+    match bearer {
+        Some(token) => {
+            let tenant = request
+                .extensions()
+                .get::<CurrentTenant>()
+                .map(|tenant| tenant.0.clone())
+                .unwrap_or_else(|| crate::services::DEFAULT_TENANT.to_string());
+            let api_token = state
+                .api_tokens
+                .authenticate(&tenant, &token)
+                .await
+                .map_err(|e| ApiError::InternalError(format!("Failed to check API token: {}", e)))?
+                .ok_or_else(|| ApiError::Unauthorized("Invalid, expired or revoked API token".to_string()))?;
+
+            // The GraphQL schema has no mutations and its `User` fields check the owner
+            let in_scope = is_graphql
+                || (is_read && owner_checked)
+                || (is_read && owner.as_deref() == Some(api_token.owner.to_string().as_str()));
+            if !in_scope {
+                return Err(ApiError::Forbidden(format!(
+                    "API token only allows reads under /users/{}/",
+                    api_token.owner
+                )));
+            }
+
+            request.extensions_mut().insert(api_token);
+        }
+        None if state.require_api_token && is_read && owner.is_some() => {
+            return Err(ApiError::Unauthorized("API token required".to_string()));
+        }
+        None => {}
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// The rule of reads under `/users/:owner/` for routes that find the owner
+/// elsewhere: an API token only reads its own owner, and REQUIRE_API_TOKEN
+/// makes one mandatory
+pub fn authorize_owner(state: &AppState, api_token: Option<&ApiToken>, owner: &Pubkey) -> Result<(), ApiError> {
+    match api_token {
+        Some(api_token) if api_token.owner == *owner => Ok(()),
+        Some(api_token) => Err(ApiError::Forbidden(format!("API token is scoped to {}", api_token.owner))),
+        None if state.require_api_token => Err(ApiError::Unauthorized("API token required".to_string())),
+        None => Ok(()),
+    }
+}
+
+/// `owner` of a `/users/:owner/...` path
+fn path_owner(path: &str) -> Option<&str> {
+    let mut segments = path.trim_start_matches('/').split('/');
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some("users"), Some(owner), Some(_)) => Some(owner),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_owner() {
+        assert_eq!(path_owner("/users/abc/positions"), Some("abc"));
+        assert_eq!(path_owner("/users/abc/reports/tax"), Some("abc"));
+        assert_eq!(path_owner("/users/initialize"), None);
+        assert_eq!(path_owner("/positions/abc"), None);
+    }
+}
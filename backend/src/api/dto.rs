@@ -1,8 +1,16 @@
 use serde::{Deserialize, Serialize};
 use rust_decimal::Decimal;
 use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 
-use crate::domain::{Side, PositionStatus, Risk};
+use crate::domain::{MarginMode, Side, PositionStatus, Risk};
+use crate::api::precision;
+use crate::infrastructure::{CachedQuote, PriceTick, RpcEndpointHealth};
+use crate::services::{
+    AlertCondition, DemoUser, ExternalPosition, ExternalPositionInput, HaltReason, HaltStatus, LiquidationAlert, LiquidationBucket,
+    LiquidationLadder, MarginCalculator, PositionHistoryData, PriceDirection, SizingBucket, StrategyTotals, SymbolExposure, SymbolOverrides, SymbolSettings, TimelineEntry,
+    PnlPathPoint, PurgedUserData, TimelineEventKind, TradeSimulation, UserSettingsData, VaultNav,
+};
 use solana_sdk::pubkey::Pubkey;
 
 // Request DTOs
@@ -12,9 +20,25 @@ pub struct OpenPositionRequest {
     pub symbol: String,
     pub side: Side,
     pub size: Decimal,
-    pub leverage: u16,
+    /// Defaults to the owner's saved leverage, required when they have none
+    pub leverage: Option<u16>,
     pub entry_price: Decimal,
     pub maintenance_margin_ratio: Option<Decimal>,
+    /// Max entry price deviation from the oracle price, defaults to the
+    /// owner's saved slippage, then 500 (5%)
+    pub max_slippage_bps: Option<u32>,
+}
+
+/// An order to size up before placing it, `entry_price` defaults to the oracle price
+#[derive(Debug, Deserialize)]
+pub struct PositionPreviewRequest {
+    pub owner: String,
+    pub symbol: String,
+    pub side: Side,
+    pub leverage: u16,
+    pub size: Option<Decimal>,
+    pub entry_price: Option<Decimal>,
+    pub maintenance_margin_ratio: Option<Decimal>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,6 +62,132 @@ pub struct AddCollateralRequest {
     pub amount: u64,
 }
 
+/// Body of PUT /users/:id/settings
+#[derive(Debug, Deserialize)]
+pub struct UserSettingsRequest {
+    pub default_slippage_bps: u16,
+    pub default_leverage: u16,
+    #[serde(default = "default_margin_mode")]
+    pub margin_mode: MarginMode,
+}
+
+/// Body of PUT /users/:id/risk-limits, both limits unset removes them.
+/// `signature` is the owner's base58 signature of "Set risk limits for
+/// <owner>: position risk <pct>, portfolio leverage <leverage>, block opens
+/// <block_opens> at <timestamp>", an unset limit written `none`
+#[derive(Debug, Deserialize)]
+pub struct RiskLimitsRequest {
+    /// Percent of collateral one position's margin may put at risk
+    pub max_position_risk_pct: Option<Decimal>,
+    pub max_portfolio_leverage: Option<Decimal>,
+    #[serde(default)]
+    pub block_opens: bool,
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+fn default_margin_mode() -> MarginMode {
+    MarginMode::Isolated
+}
+
+/// Body of POST /positions/:id/alerts, e.g. {"type": "pnl_below", "threshold": "-500"}
+/// and the position owner's base58 signature of "Create alert on position
+/// <position> at <timestamp>"
+#[derive(Debug, Deserialize)]
+pub struct CreatePositionAlertRequest {
+    #[serde(flatten)]
+    pub condition: AlertCondition,
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+/// Body of DELETE /positions/:id/alerts/:alert_id, `signature` is the position
+/// owner's base58 signature of "Delete alert <alert_id> on position <position> at <timestamp>"
+#[derive(Debug, Deserialize)]
+pub struct DeletePositionAlertRequest {
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+/// Body of POST /alerts/price and PUT /alerts/price/:id, `signature` is the
+/// owner's base58 signature of "Create price alert <owner> at <timestamp>",
+/// or "Update price alert <id> for <owner> at <timestamp>"
+#[derive(Debug, Deserialize)]
+pub struct PriceAlertRequest {
+    pub symbol: String,
+    pub direction: PriceDirection,
+    pub price: Decimal,
+    pub owner: String,
+    /// POSTed the triggered alert as JSON, https only
+    pub webhook_url: Option<String>,
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+/// Body of DELETE /alerts/price/:id, `signature` is the owner's base58
+/// signature of "Delete price alert <id> for <owner> at <timestamp>"
+#[derive(Debug, Deserialize)]
+pub struct DeletePriceAlertRequest {
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+/// Body of PUT /users/:id/strategies/:name, the position accounts of every leg
+#[derive(Debug, Deserialize)]
+pub struct StrategyRequest {
+    pub positions: Vec<String>,
+    pub timestamp: i64,
+    /// Owner's base58 signature of "Save strategy <name> for <owner> at <timestamp>"
+    pub signature: String,
+}
+
+/// Body of DELETE /users/:id/strategies/:name and POST
+/// /users/:id/strategies/:name/close, `signature` is the owner's base58
+/// signature of "Delete strategy <name> for <owner> at <timestamp>" or
+/// "Close strategy <name> for <owner> at <timestamp>"
+#[derive(Debug, Deserialize)]
+pub struct SignedStrategyRequest {
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+/// JSON body of POST /users/:id/external-positions, replaces the user's external book
+#[derive(Debug, Deserialize)]
+pub struct ExternalPositionsRequest {
+    pub positions: Vec<ExternalPositionInput>,
+}
+
+/// Body of POST /users/:id/tokens, `signature` is the owner's base58 signature
+/// of "Create read-only API token for <owner> at <timestamp>"
+#[derive(Debug, Deserialize)]
+pub struct CreateApiTokenRequest {
+    /// Unix seconds
+    pub timestamp: i64,
+    pub signature: String,
+    pub label: Option<String>,
+    pub ttl_secs: Option<u64>,
+}
+
+/// Body of DELETE /users/:id/tokens/:token_id, `signature` is the owner's
+/// base58 signature of "Revoke API token <token_id> for <owner> at <timestamp>"
+#[derive(Debug, Deserialize)]
+pub struct RevokeApiTokenRequest {
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+/// Body of DELETE /users/:id, `signature` is the owner's base58 signature
+/// of "Close user account <owner> at <timestamp>"
+#[derive(Debug, Deserialize)]
+pub struct CloseUserAccountRequest {
+    pub timestamp: i64,
+    pub signature: String,
+    /// Withdraw the free collateral left in the same transaction, otherwise
+    /// an account with collateral is refused
+    #[serde(default)]
+    pub withdraw_collateral: bool,
+}
+
 // Response DTOs
 #[derive(Debug, Serialize)]
 pub struct OpenPositionResponse {
@@ -45,6 +195,186 @@ pub struct OpenPositionResponse {
     pub signature: String,
 }
 
+/// Body of POST /simulate/trade
+#[derive(Debug, Deserialize)]
+pub struct SimulateTradeRequest {
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub leverage: u16,
+    pub entry_time: DateTime<Utc>,
+    pub exit_time: DateTime<Utc>,
+    /// Defaults to the symbol's current maintenance margin ratio
+    pub maintenance_margin_ratio: Option<Decimal>,
+    /// Spacing of `pnl_path` points, like "1m" or "1h"
+    pub interval: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PnlPathPointDto {
+    pub at: DateTime<Utc>,
+    #[serde(serialize_with = "precision::price")]
+    pub price: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub unrealized_pnl: Decimal,
+}
+
+impl From<PnlPathPoint> for PnlPathPointDto {
+    fn from(point: PnlPathPoint) -> Self {
+        Self {
+            at: point.at,
+            price: point.price,
+            unrealized_pnl: point.unrealized_pnl,
+        }
+    }
+}
+
+/// Replay of a position that was never opened, from archived prices
+#[derive(Debug, Serialize)]
+pub struct TradeSimulationDto {
+    pub symbol: String,
+    pub side: Side,
+    #[serde(serialize_with = "precision::size")]
+    pub size: Decimal,
+    pub leverage: u16,
+    #[serde(serialize_with = "precision::price")]
+    pub entry_price: Decimal,
+    pub entry_at: DateTime<Utc>,
+    #[serde(serialize_with = "precision::price")]
+    pub exit_price: Decimal,
+    pub exit_at: DateTime<Utc>,
+    #[serde(serialize_with = "precision::price")]
+    pub initial_margin: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub liquidation_price: Decimal,
+    pub liquidated: bool,
+    #[serde(serialize_with = "precision::price")]
+    pub realized_pnl: Decimal,
+    #[serde(serialize_with = "precision::pct")]
+    pub roi_pct: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub peak_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub max_drawdown: Decimal,
+    #[serde(serialize_with = "precision::pct")]
+    pub max_drawdown_pct: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub funding_paid: Decimal,
+    pub ticks: usize,
+    pub pnl_path: Vec<PnlPathPointDto>,
+}
+
+impl TradeSimulationDto {
+    pub fn new(symbol: String, side: Side, size: Decimal, leverage: u16, simulation: TradeSimulation) -> Self {
+        Self {
+            symbol,
+            side,
+            size,
+            leverage,
+            entry_price: simulation.entry_price,
+            entry_at: simulation.entry_at,
+            exit_price: simulation.exit_price,
+            exit_at: simulation.exit_at,
+            initial_margin: simulation.initial_margin,
+            liquidation_price: simulation.liquidation_price,
+            liquidated: simulation.liquidated,
+            realized_pnl: simulation.realized_pnl,
+            roi_pct: simulation.roi,
+            peak_pnl: simulation.peak_pnl,
+            max_drawdown: simulation.max_drawdown,
+            max_drawdown_pct: simulation.max_drawdown_pct,
+            funding_paid: simulation.funding_paid,
+            ticks: simulation.ticks,
+            pnl_path: simulation.pnl_path.into_iter().map(PnlPathPointDto::from).collect(),
+        }
+    }
+}
+
+/// Order sizing limits, with margin and liquidation price when a size was given
+#[derive(Debug, Serialize)]
+pub struct PositionPreviewResponse {
+    pub symbol: String,
+    pub side: Side,
+    pub leverage: u16,
+    #[serde(serialize_with = "precision::price")]
+    pub entry_price: Decimal,
+    #[serde(serialize_with = "precision::size_opt")]
+    pub size: Option<Decimal>,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub notional_value: Option<Decimal>,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub initial_margin: Option<Decimal>,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub liquidation_price: Option<Decimal>,
+    /// Largest size the leverage tier allows, none when uncapped
+    #[serde(serialize_with = "precision::size_opt")]
+    pub tier_max_size: Option<Decimal>,
+    #[serde(serialize_with = "precision::size")]
+    pub open_interest: Decimal,
+    #[serde(serialize_with = "precision::size_opt")]
+    pub max_open_interest: Option<Decimal>,
+    /// Size that can still open before the cap, none when uncapped
+    #[serde(serialize_with = "precision::size_opt")]
+    pub open_interest_headroom: Option<Decimal>,
+    /// Free collateral, in collateral units
+    #[serde(serialize_with = "precision::price")]
+    pub available_collateral: Decimal,
+    /// Largest size the free collateral covers at this leverage
+    #[serde(serialize_with = "precision::size")]
+    pub max_affordable_size: Decimal,
+    /// Smallest of the limits above
+    #[serde(serialize_with = "precision::size")]
+    pub max_size: Decimal,
+    /// Taker fee rate of the owner's fee tier
+    pub taker_fee_bps: u16,
+    /// Taker fee the open pays from free collateral, with a size
+    #[serde(serialize_with = "precision::price_opt")]
+    pub taker_fee: Option<Decimal>,
+}
+
+/// A one-way order reported as one fill
+#[derive(Debug, Serialize)]
+pub struct OrderFillResponse {
+    pub symbol: String,
+    pub side: Side,
+    #[serde(serialize_with = "precision::size")]
+    pub size: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub price: Decimal,
+    /// Closed against the opposite side
+    #[serde(serialize_with = "precision::size")]
+    pub netted_size: Decimal,
+    /// Opened as a new position
+    #[serde(serialize_with = "precision::size")]
+    pub opened_size: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub realized_pnl: Decimal,
+    /// Direction held afterwards, null when flat
+    pub resulting_side: Option<Side>,
+    #[serde(serialize_with = "precision::size")]
+    pub resulting_size: Decimal,
+    pub position: Option<PositionDto>,
+    pub signatures: Vec<String>,
+}
+
+impl From<crate::services::NettedFill> for OrderFillResponse {
+    fn from(fill: crate::services::NettedFill) -> Self {
+        Self {
+            symbol: fill.symbol,
+            side: fill.side,
+            size: fill.size,
+            price: fill.price,
+            netted_size: fill.netted_size,
+            opened_size: fill.opened_size,
+            realized_pnl: fill.realized_pnl,
+            resulting_side: fill.resulting_side,
+            resulting_size: fill.resulting_size,
+            position: fill.position.map(PositionDto::from),
+            signatures: fill.signatures.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ModifyPositionResponse {
     pub signature: String,
@@ -53,6 +383,7 @@ pub struct ModifyPositionResponse {
 
 #[derive(Debug, Serialize)]
 pub struct ClosePositionResponse {
+    #[serde(serialize_with = "precision::price")]
     pub pnl: Decimal,
     pub signature: String,
     pub message: String,
@@ -70,6 +401,35 @@ pub struct AddCollateralResponse {
     pub message: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct CloseUserAccountResponse {
+    pub signature: String,
+    pub withdrawn_collateral: u64,
+    /// None when the account closed but purging the backend's data failed
+    pub purged: Option<PurgedUserDataDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurgedUserDataDto {
+    pub api_tokens: usize,
+    pub strategies: usize,
+    pub external_positions: usize,
+    pub price_alerts: usize,
+    pub alerted_positions: usize,
+}
+
+impl From<PurgedUserData> for PurgedUserDataDto {
+    fn from(purged: PurgedUserData) -> Self {
+        Self {
+            api_tokens: purged.api_tokens,
+            strategies: purged.strategies,
+            external_positions: purged.external_positions,
+            price_alerts: purged.price_alerts,
+            alerted_positions: purged.alerted_positions,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserAccountDto {
     pub owner: String,
@@ -81,6 +441,151 @@ pub struct UserAccountDto {
     pub position_count_total: u32,
 }
 
+/// A user's defaults for new positions, `updated_at` is none until first set
+#[derive(Debug, Serialize)]
+pub struct UserSettingsDto {
+    pub owner: String,
+    pub default_slippage_bps: u16,
+    pub default_leverage: Option<u16>,
+    pub margin_mode: MarginMode,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserSettingsData> for UserSettingsDto {
+    fn from(settings: UserSettingsData) -> Self {
+        Self {
+            owner: settings.owner.to_string(),
+            default_slippage_bps: settings.default_slippage_bps,
+            default_leverage: Some(settings.default_leverage),
+            margin_mode: settings.margin_mode,
+            updated_at: Some(settings.updated_at),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateUserSettingsResponse {
+    pub settings: UserSettingsDto,
+    pub signature: String,
+}
+
+/// Liquidator set response
+#[derive(Debug, Serialize)]
+pub struct LiquidatorSetDto {
+    pub admin: String,
+    pub whitelist_enabled: bool,
+    pub liquidators: Vec<String>,
+    pub partial_liquidation_enabled: bool,
+    pub liquidation_buffer_bps: u16,
+}
+
+/// Kill switch state, `since` is when the current halt or trading period began
+#[derive(Debug, Serialize)]
+pub struct TradingHaltDto {
+    pub halted: bool,
+    pub reason: Option<HaltReason>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl From<HaltStatus> for TradingHaltDto {
+    fn from(status: HaltStatus) -> Self {
+        Self {
+            halted: status.is_halted(),
+            reason: status.reason,
+            since: status.since,
+        }
+    }
+}
+
+/// Backstop vault state, amounts in collateral units
+#[derive(Debug, Serialize)]
+pub struct BackstopVaultDto {
+    pub address: String,
+    /// Total value locked: LP deposits plus realized PnL
+    #[serde(serialize_with = "precision::price")]
+    pub tvl: Decimal,
+    #[serde(serialize_with = "precision::size")]
+    pub total_shares: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub share_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub locked_margin: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub available_assets: Decimal,
+    /// Share of TVL backing absorbed positions
+    #[serde(serialize_with = "precision::ratio")]
+    pub utilization: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub realized_pnl: Decimal,
+    pub discount_bps: u16,
+    pub open_positions: u32,
+}
+
+/// Backstop vault marked to market, streamed as `vault_nav` over WebSocket
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultNavDto {
+    #[serde(serialize_with = "precision::price")]
+    pub total_assets: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub unrealized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub nav: Decimal,
+    #[serde(serialize_with = "precision::size")]
+    pub total_shares: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub share_price: Decimal,
+    pub open_positions: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<VaultNav> for VaultNavDto {
+    fn from(nav: VaultNav) -> Self {
+        Self {
+            total_assets: nav.total_assets,
+            unrealized_pnl: nav.unrealized_pnl,
+            nav: nav.nav,
+            total_shares: nav.total_shares,
+            share_price: nav.share_price,
+            open_positions: nav.open_positions,
+            timestamp: nav.computed_at,
+        }
+    }
+}
+
+/// Vault return query, e.g. ?window=30d
+#[derive(Debug, Deserialize)]
+pub struct VaultReturnsQuery {
+    pub window: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultReturnDto {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    #[serde(serialize_with = "precision::price")]
+    pub start_share_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub end_share_price: Decimal,
+    #[serde(serialize_with = "precision::ratio")]
+    pub period_return: Decimal,
+    #[serde(serialize_with = "precision::ratio")]
+    pub annualized_return: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LpAccountDto {
+    pub owner: String,
+    #[serde(serialize_with = "precision::size")]
+    pub shares: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub value: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub cost_basis: Decimal,
+    /// Value above the cost basis, what `claim_backstop` pays out
+    #[serde(serialize_with = "precision::price")]
+    pub earnings: Decimal,
+}
+
 /// Position response DTO
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PositionDto {
@@ -88,23 +593,68 @@ pub struct PositionDto {
     pub owner: String,
     pub symbol: String,
     pub side: Side,
+    #[serde(serialize_with = "precision::size")]
     pub size: Decimal,
+    #[serde(serialize_with = "precision::price")]
     pub entry_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
     pub mark_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
     pub margin: Decimal,
     pub leverage: u16,
+    #[serde(serialize_with = "precision::price")]
     pub unrealized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
     pub realized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
     pub funding_accrued: Decimal,
+    #[serde(serialize_with = "precision::price")]
     pub liquidation_price: Decimal,
+    /// Liquidation price net of accrued funding and the close fee, null
+    /// until the monitor has marked the position
+    #[serde(serialize_with = "precision::price_opt")]
+    pub effective_liquidation_price: Option<Decimal>,
+    /// size × mark_price
+    #[serde(serialize_with = "precision::price")]
+    pub notional_value: Decimal,
+    /// Unrealized PnL over margin while open, realized once closed
+    #[serde(serialize_with = "precision::pct_opt")]
+    pub roi_pct: Option<Decimal>,
+    /// Open positions only, floored at 0
+    #[serde(serialize_with = "precision::ratio_opt")]
+    pub margin_ratio: Option<Decimal>,
+    /// Losses beyond the margin, open positions only
+    #[serde(serialize_with = "precision::price_opt")]
+    pub margin_deficit: Option<Decimal>,
+    /// Adverse mark move to `liquidation_price`, open positions only
+    #[serde(serialize_with = "precision::pct_opt")]
+    pub distance_to_liquidation_pct: Option<Decimal>,
     pub status: PositionStatus,
     pub opened_at: DateTime<Utc>,
     pub last_update: DateTime<Utc>,
     pub closed_at: Option<DateTime<Utc>>,
+    /// When the running margin call was issued
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin_call_at: Option<DateTime<Utc>>,
 }
 
 impl From<crate::domain::Position> for PositionDto {
     fn from(pos: crate::domain::Position) -> Self {
+        let open = pos.is_open();
+        let pnl = if open { pos.unrealized_pnl } else { pos.realized_pnl };
+        let margin_ratio = open
+            .then(|| {
+                MarginCalculator::calculate_margin_ratio(pos.margin, pos.unrealized_pnl, pos.size, pos.mark_price)
+                    .ok()
+            })
+            .flatten()
+            .map(|ratio| ratio.max(Decimal::ZERO));
+        let margin_deficit =
+            open.then(|| MarginCalculator::calculate_margin_deficit(pos.margin, pos.unrealized_pnl));
+        let distance_to_liquidation_pct = open
+            .then(|| distance_to_liquidation_pct(pos.side, pos.mark_price, pos.liquidation_price))
+            .flatten();
+
         Self {
             position_account: pos.position_account.to_string(),
             owner: pos.owner.to_string(),
@@ -119,10 +669,17 @@ impl From<crate::domain::Position> for PositionDto {
             realized_pnl: pos.realized_pnl,
             funding_accrued: pos.funding_accrued,
             liquidation_price: pos.liquidation_price,
+            effective_liquidation_price: pos.effective_liquidation_price,
+            notional_value: pos.size * pos.mark_price,
+            roi_pct: MarginCalculator::calculate_roi(pnl, pos.margin).ok(),
+            margin_ratio,
+            margin_deficit,
+            distance_to_liquidation_pct,
             status: pos.status,
             opened_at: pos.opened_at,
             last_update: pos.last_update,
             closed_at: pos.closed_at,
+            margin_call_at: pos.margin_call_at,
         }
     }
 }
@@ -137,46 +694,1546 @@ pub struct ListPositionsQuery {
     pub offset: Option<usize>,
 }
 
-/// Statistics response
+/// One page of closed positions
 #[derive(Debug, Serialize)]
-pub struct StatisticsDto {
-    pub total_positions: usize,
-    pub open_positions: usize,
-    pub assets_monitored: usize,
-    pub total_unrealized_pnl: Decimal,
+pub struct PositionPageDto {
+    pub positions: Vec<PositionDto>,
+    /// Pass as `cursor` for the next page, none on the last one
+    pub next_cursor: Option<String>,
 }
 
-/// Price update DTO
+/// Closed position history query, `cursor` is the previous page's `next_cursor`
+#[derive(Debug, Deserialize)]
+pub struct PositionHistoryQuery {
+    pub owner: Option<String>,
+    pub symbol: Option<String>,
+    pub status: Option<PositionStatus>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+/// PnL history query, e.g. ?interval=5m&limit=288
+#[derive(Debug, Deserialize)]
+pub struct PnlHistoryQuery {
+    pub interval: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// One bucket of a position's PnL history
 #[derive(Debug, Serialize)]
-pub struct PriceDto {
-    pub symbol: String,
-    pub price: Decimal,
+pub struct PnlPointDto {
     pub timestamp: DateTime<Utc>,
+    #[serde(serialize_with = "precision::price")]
+    pub unrealized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub realized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub funding_accrued: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub mark_price: Decimal,
 }
 
-/// Position update DTO (for WebSocket)
+impl From<crate::infrastructure::PnlPoint> for PnlPointDto {
+    fn from(point: crate::infrastructure::PnlPoint) -> Self {
+        Self {
+            timestamp: point.bucket,
+            unrealized_pnl: point.unrealized_pnl,
+            realized_pnl: point.realized_pnl,
+            funding_accrued: point.funding_accrued,
+            mark_price: point.mark_price,
+        }
+    }
+}
+
+/// A position's size and margin changes as its account records them
 #[derive(Debug, Serialize)]
-pub struct PositionUpdateDto {
+pub struct OnChainHistoryDto {
     pub position_account: String,
-    pub symbol: String,
-    pub side: Side,
+    /// Changes ever made, only the latest `changes.len()` are kept
+    pub total_changes: u32,
+    pub changes: Vec<OnChainChangeDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OnChainChangeDto {
+    pub slot: u64,
+    /// Size, margin and leverage after the change
+    #[serde(serialize_with = "precision::size")]
     pub size: Decimal,
-    pub entry_price: Decimal,
-    pub mark_price: Decimal,
-    pub unrealized_pnl: Decimal,
-    pub margin_ratio: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub margin: Decimal,
+    pub leverage: u16,
+    /// Entry price for opens and modifies, mark price for liquidations
+    #[serde(serialize_with = "precision::price")]
+    pub price: Decimal,
+}
+
+impl From<PositionHistoryData> for OnChainHistoryDto {
+    fn from(history: PositionHistoryData) -> Self {
+        Self {
+            position_account: history.position_account.to_string(),
+            total_changes: history.total_changes,
+            changes: history
+                .changes
+                .into_iter()
+                .map(|change| OnChainChangeDto {
+                    slot: change.slot,
+                    size: change.size,
+                    margin: change.margin,
+                    leverage: change.leverage,
+                    price: change.price,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// One line of a position's timeline
+#[derive(Debug, Serialize)]
+pub struct TimelineEntryDto {
+    pub kind: TimelineEventKind,
     pub timestamp: DateTime<Utc>,
+    /// Size after the event
+    #[serde(serialize_with = "precision::size_opt")]
+    pub size: Option<Decimal>,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub margin: Option<Decimal>,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub price: Option<Decimal>,
+    #[serde(serialize_with = "precision::price")]
+    pub pnl_delta: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub cumulative_pnl: Decimal,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub funding: Option<Decimal>,
+    pub detail: Option<String>,
+    pub signature: Option<String>,
+}
+
+impl From<TimelineEntry> for TimelineEntryDto {
+    fn from(entry: TimelineEntry) -> Self {
+        Self {
+            kind: entry.kind,
+            timestamp: entry.at,
+            size: entry.size,
+            margin: entry.margin,
+            price: entry.price,
+            pnl_delta: entry.pnl_delta,
+            cumulative_pnl: entry.cumulative_pnl,
+            funding: entry.funding,
+            detail: entry.detail,
+            signature: entry.signature,
+        }
+    }
+}
+
+/// Realized PnL period filter, RFC 3339 timestamps
+#[derive(Debug, Deserialize)]
+pub struct RealizedPnlQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Page size, the whole period in one response when neither this nor `cursor` is set
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
 }
 
-/// Liquidation alert DTO
 #[derive(Debug, Serialize)]
-pub struct LiquidationAlertDto {
-    pub risk_type: Risk,
-    pub(crate) position_account: Pubkey,
+pub struct RealizedPnlDto {
+    pub position_account: String,
     pub symbol: String,
     pub side: Side,
-    pub liquidation_price: Decimal,
-    pub current_price: Decimal,
+    #[serde(serialize_with = "precision::size")]
+    pub size_closed: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub entry_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub close_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub fee: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub funding: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub amount: Decimal,
+    pub liquidation: bool,
+    pub signature: String,
+    pub closed_at: DateTime<Utc>,
+}
+
+impl From<crate::domain::RealizedPnL> for RealizedPnlDto {
+    fn from(record: crate::domain::RealizedPnL) -> Self {
+        Self {
+            position_account: record.position_account.to_string(),
+            symbol: record.symbol,
+            side: record.side,
+            size_closed: record.size_closed,
+            entry_price: record.entry_price,
+            close_price: record.close_price,
+            fee: record.fee,
+            funding: record.funding,
+            amount: record.amount,
+            liquidation: record.liquidation,
+            signature: record.signature,
+            closed_at: record.closed_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RealizedPnlResponse {
+    pub owner: String,
+    /// Net of `records`, so of this page when paginating
+    #[serde(serialize_with = "precision::price")]
+    pub total: Decimal,
+    pub records: Vec<RealizedPnlDto>,
+    /// Pass as `cursor` for the next page, none on the last one
+    pub next_cursor: Option<String>,
+}
+
+/// Tax report query, e.g. ?year=2025&format=csv
+#[derive(Debug, Deserialize)]
+pub struct TaxReportQuery {
+    pub year: Option<i32>,
+    pub format: Option<String>,
+}
+
+/// Statistics response
+#[derive(Debug, Serialize)]
+pub struct StatisticsDto {
+    pub total_positions: usize,
+    pub open_positions: usize,
+    /// Open positions under a margin call
+    pub margin_call_positions: usize,
+    pub closed_positions_in_memory: usize,
+    pub assets_monitored: usize,
+    #[serde(serialize_with = "precision::price")]
+    pub total_unrealized_pnl: Decimal,
+    /// Open positions whose losses exceed their margin
+    pub underwater_positions: usize,
+    #[serde(serialize_with = "precision::price")]
+    pub total_margin_deficit: Decimal,
+    /// Closed positions moved out of memory since startup, by reason
+    pub evicted_expired: u64,
+    pub evicted_over_cap: u64,
+    pub eviction_persist_failures: u64,
+}
+
+/// Open position whose losses exceed its margin
+#[derive(Debug, Serialize)]
+pub struct UnderwaterPositionDto {
+    pub position_account: String,
+    pub owner: String,
+    pub symbol: String,
+    #[serde(serialize_with = "precision::price")]
+    pub mark_price: Decimal,
+    /// Signed, the only place it isn't floored at 0
+    #[serde(serialize_with = "precision::ratio")]
+    pub margin_ratio: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub margin_deficit: Decimal,
+}
+
+impl From<crate::domain::UnderwaterPosition> for UnderwaterPositionDto {
+    fn from(position: crate::domain::UnderwaterPosition) -> Self {
+        Self {
+            position_account: position.position_account.to_string(),
+            owner: position.owner.to_string(),
+            symbol: position.symbol,
+            mark_price: position.mark_price,
+            margin_ratio: position.margin_ratio,
+            margin_deficit: position.margin_deficit,
+        }
+    }
+}
+
+/// RPC call counters for one method
+#[derive(Debug, Serialize)]
+pub struct RpcCallStatsDto {
+    pub method: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub items: u64,
+    pub bytes: u64,
+}
+
+/// One circuit breaker around a Hermes symbol or the chain scan
+#[derive(Debug, Serialize)]
+pub struct CircuitBreakerDto {
+    /// `hermes:<symbol>` or `rpc:scan`
+    pub key: String,
+    /// `closed`, `open` or `half_open`
+    pub state: String,
+    pub consecutive_failures: u32,
+    pub trips: u64,
+    pub backoff_ms: u64,
+    /// Until the next probe while open
+    pub retry_in_ms: Option<u64>,
+}
+
+/// Health of one RPC endpoint in the pool
+#[derive(Debug, Serialize)]
+pub struct RpcEndpointHealthDto {
+    pub url: String,
+    /// False while the endpoint is skipped after repeated failures
+    pub healthy: bool,
+    /// Moving average, none until the first response
+    pub latency_ms: Option<f64>,
+    pub error_rate: f64,
+    pub consecutive_failures: u32,
+    pub calls: u64,
+    pub errors: u64,
+}
+
+impl From<RpcEndpointHealth> for RpcEndpointHealthDto {
+    fn from(health: RpcEndpointHealth) -> Self {
+        Self {
+            url: health.url,
+            healthy: health.healthy,
+            latency_ms: health.latency_ms,
+            error_rate: health.error_rate,
+            consecutive_failures: health.consecutive_failures,
+            calls: health.calls,
+            errors: health.errors,
+        }
+    }
+}
+
+/// Price update DTO
+#[derive(Debug, Serialize)]
+pub struct PriceDto {
+    pub symbol: String,
+    #[serde(serialize_with = "precision::price")]
+    pub price: Decimal,
+    /// Oracle publish time
+    pub timestamp: DateTime<Utc>,
+    /// When the backend fetched the price
+    pub fetched_at: DateTime<Utc>,
+    /// Milliseconds since publish, for rejecting stale prices
+    pub age_ms: i64,
+}
+
+impl PriceDto {
+    pub fn new(symbol: String, price: Decimal, published_at: DateTime<Utc>, fetched_at: DateTime<Utc>) -> Self {
+        Self {
+            symbol,
+            price,
+            timestamp: published_at,
+            fetched_at,
+            age_ms: (Utc::now() - published_at).num_milliseconds(),
+        }
+    }
+}
+
+impl From<(String, CachedQuote)> for PriceDto {
+    fn from((symbol, cached): (String, CachedQuote)) -> Self {
+        Self::new(symbol, cached.quote.price, cached.quote.publish_time, cached.fetched_at)
+    }
+}
+
+/// Suggested position sizes for a symbol, from GET /markets/:symbol/sizing
+#[derive(Debug, Serialize)]
+pub struct MarketSizingDto {
+    pub symbol: String,
+    #[serde(serialize_with = "precision::price")]
+    pub price: Decimal,
+    /// Pyth confidence interval
+    #[serde(serialize_with = "precision::price")]
+    pub confidence: Decimal,
+    /// Standard deviation of one-minute returns
+    #[serde(serialize_with = "precision::ratio")]
+    pub volatility: Decimal,
+    pub candles: usize,
+    /// Move against a new position the sizes allow for
+    #[serde(serialize_with = "precision::ratio")]
+    pub adverse_move: Decimal,
+    pub buckets: Vec<SizingBucketDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SizingBucketDto {
+    pub leverage: u16,
+    #[serde(serialize_with = "precision::ratio")]
+    pub liquidation_buffer: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub max_notional: Decimal,
+    #[serde(serialize_with = "precision::size")]
+    pub max_size: Decimal,
+}
+
+impl From<SizingBucket> for SizingBucketDto {
+    fn from(bucket: SizingBucket) -> Self {
+        Self {
+            leverage: bucket.leverage,
+            liquidation_buffer: bucket.liquidation_buffer,
+            max_notional: bucket.max_notional,
+            max_size: bucket.max_size,
+        }
+    }
+}
+
+/// Query of GET /markets/:symbol/liquidation-map
+#[derive(Debug, Deserialize)]
+pub struct LiquidationMapQuery {
+    /// Buckets per side, defaults to 50
+    pub buckets: Option<usize>,
+}
+
+/// Open positions on a symbol by liquidation price, from GET /markets/:symbol/liquidation-map
+#[derive(Debug, Serialize)]
+pub struct LiquidationMapDto {
+    pub symbol: String,
+    #[serde(serialize_with = "precision::price")]
+    pub mark_price: Decimal,
+    /// Liquidate below the mark, buckets nearest it first
+    pub longs: LiquidationLadderDto,
+    /// Liquidate above the mark, buckets nearest it first
+    pub shorts: LiquidationLadderDto,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LiquidationLadderDto {
+    #[serde(serialize_with = "precision::price")]
+    pub bucket_width: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub total_notional: Decimal,
+    pub buckets: Vec<LiquidationBucketDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LiquidationBucketDto {
+    #[serde(serialize_with = "precision::price")]
+    pub price_low: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub price_high: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub notional: Decimal,
+    pub positions: usize,
+    /// Liquidated by a move from the mark to the bucket's far edge
+    #[serde(serialize_with = "precision::price")]
+    pub cumulative_notional: Decimal,
+}
+
+impl From<LiquidationLadder> for LiquidationLadderDto {
+    fn from(ladder: LiquidationLadder) -> Self {
+        Self {
+            bucket_width: ladder.bucket_width,
+            total_notional: ladder.total_notional,
+            buckets: ladder.buckets.into_iter().map(LiquidationBucketDto::from).collect(),
+        }
+    }
+}
+
+impl From<LiquidationBucket> for LiquidationBucketDto {
+    fn from(bucket: LiquidationBucket) -> Self {
+        Self {
+            price_low: bucket.price_low,
+            price_high: bucket.price_high,
+            notional: bucket.notional,
+            positions: bucket.positions,
+            cumulative_notional: bucket.cumulative_notional,
+        }
+    }
+}
+
+/// Bulk price query, e.g. ?symbols=BTC-USD,ETH-USD
+#[derive(Debug, Deserialize)]
+pub struct PricesQuery {
+    pub symbols: Option<String>,
+}
+
+/// Price history query, e.g. ?from=2024-05-01T12:00:00Z&to=2024-05-01T13:00:00Z&resolution=1s
+#[derive(Debug, Deserialize)]
+pub struct PriceHistoryQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub resolution: Option<PriceResolution>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum PriceResolution {
+    /// Every archived tick
+    #[serde(rename = "raw")]
+    Raw,
+    /// Last tick received in each second
+    #[serde(rename = "1s")]
+    OneSecond,
+}
+
+/// One archived oracle tick
+#[derive(Debug, Serialize)]
+pub struct PriceTickDto {
+    #[serde(serialize_with = "precision::price")]
+    pub price: Decimal,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub conf: Option<Decimal>,
+    pub publish_time: Option<DateTime<Utc>>,
+    pub receive_time: DateTime<Utc>,
+}
+
+impl From<PriceTick> for PriceTickDto {
+    fn from(tick: PriceTick) -> Self {
+        Self {
+            price: tick.price,
+            conf: tick.conf,
+            publish_time: tick.publish_time,
+            receive_time: tick.recorded_at,
+        }
+    }
+}
+
+/// Oracle feed health for one symbol
+#[derive(Debug, Serialize)]
+pub struct PriceFeedStatsDto {
+    pub symbol: String,
+    pub ticks: u64,
+    pub gaps: u64,
+    pub longest_gap_ms: i64,
+    pub out_of_order: u64,
+    pub repeated: u64,
+    pub last_publish_time: Option<DateTime<Utc>>,
+}
+
+/// Time from the oracle response to one stage of the liquidation check,
+/// percentiles over the most recent ticks
+#[derive(Debug, Serialize)]
+pub struct LatencyStatsDto {
+    pub stage: String,
+    pub samples: u64,
+    pub over_budget: u64,
+    pub budget_ms: u64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Overrides for one symbol, unset fields use the monitor defaults
+#[derive(Debug, Deserialize)]
+pub struct SymbolConfigRequest {
+    pub pnl_update_interval_ms: Option<u64>,
+    pub maintenance_margin_ratio: Option<Decimal>,
+    pub alert_threshold_pct: Option<Decimal>,
+    pub max_open_interest: Option<Decimal>,
+}
+
+impl From<SymbolConfigRequest> for SymbolOverrides {
+    fn from(request: SymbolConfigRequest) -> Self {
+        Self {
+            pnl_update_interval_ms: request.pnl_update_interval_ms,
+            maintenance_margin_ratio: request.maintenance_margin_ratio,
+            alert_threshold_pct: request.alert_threshold_pct,
+            max_open_interest: request.max_open_interest,
+        }
+    }
+}
+
+/// Monitor settings in effect for one symbol
+#[derive(Debug, Serialize)]
+pub struct SymbolConfigDto {
+    pub symbol: String,
+    pub pnl_update_interval_ms: u64,
+    #[serde(serialize_with = "precision::ratio")]
+    pub maintenance_margin_ratio: Decimal,
+    #[serde(serialize_with = "precision::ratio")]
+    pub alert_threshold_pct: Decimal,
+    /// Open interest cap in base units, none when uncapped
+    #[serde(serialize_with = "precision::size_opt")]
+    pub max_open_interest: Option<Decimal>,
+    /// Fields set for this symbol, the rest are the defaults
+    pub overridden: Vec<&'static str>,
+}
+
+impl SymbolConfigDto {
+    pub fn new(symbol: String, settings: SymbolSettings, overrides: Option<&SymbolOverrides>) -> Self {
+        let overridden = overrides
+            .map(|overrides| {
+                [
+                    ("pnl_update_interval_ms", overrides.pnl_update_interval_ms.is_some()),
+                    ("maintenance_margin_ratio", overrides.maintenance_margin_ratio.is_some()),
+                    ("alert_threshold_pct", overrides.alert_threshold_pct.is_some()),
+                    ("max_open_interest", overrides.max_open_interest.is_some()),
+                ]
+                .into_iter()
+                .filter_map(|(field, set)| set.then_some(field))
+                .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            symbol,
+            pnl_update_interval_ms: settings.pnl_update_interval_ms,
+            maintenance_margin_ratio: settings.maintenance_margin_ratio,
+            alert_threshold_pct: settings.alert_threshold_pct,
+            max_open_interest: settings.max_open_interest,
+            overridden,
+        }
+    }
+}
+
+/// Body of POST /admin/markets/:symbol, the bounds the crank keeps the
+/// maintenance margin within
+#[derive(Debug, Deserialize)]
+pub struct InitializeMarketRequest {
+    pub min_maintenance_margin_bps: u16,
+    pub max_maintenance_margin_bps: u16,
+}
+
+/// A symbol's on-chain Market and where the crank would take it
+#[derive(Debug, Serialize)]
+pub struct MarketDto {
+    pub symbol: String,
+    pub address: String,
+    pub maintenance_margin_bps: u16,
+    pub min_maintenance_margin_bps: u16,
+    pub max_maintenance_margin_bps: u16,
+    pub updated_at: DateTime<Utc>,
+    /// Daily realized volatility, none without dynamic margin or enough history
+    #[serde(serialize_with = "precision::ratio_opt")]
+    pub volatility: Option<Decimal>,
+    /// Rate the crank would set at that volatility
+    pub target_maintenance_margin_bps: Option<u16>,
+    /// Slots each user waits between opens, 0 when off
+    pub open_cooldown_slots: u64,
+    /// Opens per slot across users, 0 when off
+    pub max_opens_per_slot: u16,
+}
+
+/// Margin rates of one program leverage tier
+#[derive(Debug, Serialize)]
+pub struct LeverageTierDto {
+    pub max_leverage: u16,
+    pub initial_margin_bps: u64,
+    pub maintenance_margin_bps: u64,
+    /// Largest notional the tier takes, none when uncapped
+    #[serde(serialize_with = "precision::price_opt")]
+    pub max_notional: Option<Decimal>,
+}
+
+/// One tradable symbol as a client needs it to place orders
+#[derive(Debug, Serialize)]
+pub struct ExchangeMarketDto {
+    pub symbol: String,
+    /// `trading`, or `halted` while the kill switch is set
+    pub status: String,
+    /// Pyth price feed ID, hex without 0x
+    pub oracle_feed_id: Option<String>,
+    /// Smallest price increment
+    #[serde(serialize_with = "precision::price")]
+    pub tick_size: Decimal,
+    /// Smallest size increment
+    #[serde(serialize_with = "precision::size")]
+    pub step_size: Decimal,
+    /// Market account, none before Initialize Market
+    pub market: Option<String>,
+    /// The Market's maintenance floor and its crank bounds, none without a Market
+    pub maintenance_margin_bps: Option<u16>,
+    pub min_maintenance_margin_bps: Option<u16>,
+    pub max_maintenance_margin_bps: Option<u16>,
+    /// Maintenance ratio the monitor alerts and liquidates at
+    #[serde(serialize_with = "precision::ratio")]
+    pub maintenance_margin_ratio: Decimal,
+    /// None when open interest is uncapped
+    #[serde(serialize_with = "precision::price_opt")]
+    pub max_open_interest: Option<Decimal>,
+    /// Slots each user waits between opens, 0 when off
+    pub open_cooldown_slots: u64,
+    /// Opens per slot across users, 0 when off
+    pub max_opens_per_slot: u16,
+}
+
+/// Everything a trading client loads at startup, in one response
+#[derive(Debug, Serialize)]
+pub struct ExchangeInfoDto {
+    pub server_time: DateTime<Utc>,
+    pub halt: TradingHaltDto,
+    pub markets: Vec<ExchangeMarketDto>,
+    pub leverage_tiers: Vec<LeverageTierDto>,
+    /// Taker fee per volume tier, thresholds of the request's tenant
+    pub fee_tiers: Vec<FeeTierLevelDto>,
+    /// Always none, the program charges no funding on an interval; what a
+    /// position has accrued settles on close and liquidation
+    pub funding_interval_secs: Option<u64>,
+    /// Oldest oracle price opens, closes and liquidations accept, 0 when unlimited
+    pub max_price_age_secs: u32,
+}
+
+/// Body of PUT /admin/fee-schedule, one rate per fee tier from tier 0
+#[derive(Debug, Deserialize)]
+pub struct SetFeeScheduleRequest {
+    pub taker_fee_bps: Vec<u16>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetFeeScheduleResponse {
+    pub taker_fee_bps: Vec<u16>,
+    pub signature: String,
+}
+
+/// Body of PUT /admin/markets/:symbol/throttle
+#[derive(Debug, Deserialize)]
+pub struct SetMarketThrottleRequest {
+    pub open_cooldown_slots: u64,
+    pub max_opens_per_slot: u16,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetMarketThrottleResponse {
+    pub market: String,
+    pub open_cooldown_slots: u64,
+    pub max_opens_per_slot: u16,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MigratePositionResponse {
+    pub position_account: String,
+    pub signature: String,
+}
+
+/// Body of POST /admin/lookup-table, the configured table when no address is given
+#[derive(Debug, Default, Deserialize)]
+pub struct PrepareLookupTableRequest {
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrepareLookupTableResponse {
+    pub address: String,
+    pub created: bool,
+    pub addresses: usize,
+    pub signatures: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InitializeMarketResponse {
+    pub market: String,
+    pub signature: String,
+    pub message: String,
+}
+
+/// From GET /admin/outbox
+#[derive(Debug, Serialize)]
+pub struct OutboxStatsDto {
+    pub stream: String,
+    /// Entries kept, acknowledged or not
+    pub length: usize,
+    pub groups: Vec<OutboxGroupDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutboxGroupDto {
+    pub name: String,
+    pub consumers: usize,
+    /// Delivered to a consumer and not yet acknowledged
+    pub pending: usize,
+    pub last_delivered_id: String,
+}
+
+/// Body of POST /admin/bootstrap-demo, every field defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BootstrapDemoRequest {
+    pub users: Option<usize>,
+    pub positions_per_user: Option<usize>,
+    /// Collateral per user, 6 decimals
+    pub collateral: Option<u64>,
+    /// USD notional of each position
+    pub notional: Option<Decimal>,
+}
+
+/// Body of POST /admin/simulate-price
+#[derive(Debug, Deserialize)]
+pub struct SimulatePriceRequest {
+    pub symbol: String,
+    pub price: Decimal,
+    /// Seconds until the oracle takes over again, defaults to 300
+    pub ttl_secs: Option<u64>,
+}
+
+/// A price injected over a symbol's oracle
+#[derive(Debug, Serialize)]
+pub struct SimulatedPriceDto {
+    pub symbol: String,
+    #[serde(serialize_with = "precision::price")]
+    pub price: Decimal,
+    /// Last oracle price, none before the first fetch
+    #[serde(serialize_with = "precision::price_opt")]
+    pub oracle_price: Option<Decimal>,
+    pub injected_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A demo wallet and what was set up for it
+#[derive(Debug, Serialize)]
+pub struct DemoUserDto {
+    pub owner: String,
+    /// Solana CLI keypair file contents, the service keeps no copy
+    pub keypair: Vec<u8>,
+    pub airdrop_signature: Option<String>,
+    #[serde(serialize_with = "precision::price")]
+    pub collateral: Decimal,
+    pub positions: Vec<PositionDto>,
+    /// Steps that failed, the user is set up up to the first of them
+    pub errors: Vec<String>,
+}
+
+impl From<DemoUser> for DemoUserDto {
+    fn from(user: DemoUser) -> Self {
+        Self {
+            owner: user.owner.to_string(),
+            keypair: user.keypair,
+            airdrop_signature: user.airdrop_signature.map(|signature| signature.to_string()),
+            collateral: Decimal::new(user.collateral as i64, 6),
+            positions: user.positions.into_iter().map(PositionDto::from).collect(),
+            errors: user.errors,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BootstrapDemoResponse {
+    pub users: Vec<DemoUserDto>,
+}
+
+/// Body of POST /admin/backfill
+#[derive(Debug, Deserialize)]
+pub struct BackfillRequest {
+    /// First slot replayed
+    pub from_slot: u64,
+}
+
+/// The running or last history backfill
+#[derive(Debug, Serialize)]
+pub struct BackfillProgressDto {
+    pub from_slot: u64,
+    /// `scanning`, `replaying`, `completed` or `failed`
+    pub state: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub transactions_found: u64,
+    pub transactions_replayed: u64,
+    /// Slot of the last transaction replayed
+    pub current_slot: Option<u64>,
+    pub opens_written: u64,
+    pub realized_pnl_written: u64,
+    pub events_written: u64,
+    /// Closes of positions with no known open
+    pub skipped_closes: u64,
+    pub error: Option<String>,
+}
+
+/// Raw and minute tick windows, in seconds
+#[derive(Debug, Serialize)]
+pub struct TickRetentionDto {
+    pub raw_secs: i64,
+    pub minute_secs: i64,
+}
+
+/// The running or last history prune pass
+#[derive(Debug, Serialize)]
+pub struct PruneRunDto {
+    /// `scheduled` or `manual`
+    pub trigger: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub ticks_deleted: u64,
+    pub pnl_snapshots_deleted: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryTableDto {
+    pub table: String,
+    pub rows: i64,
+    /// On-disk size with indexes, none on SQLite
+    pub bytes: Option<i64>,
+}
+
+/// Retention policy, prune totals and table sizes of the history store
+#[derive(Debug, Serialize)]
+pub struct HistoryRetentionDto {
+    /// Whether the leader prunes on a timer
+    pub scheduled: bool,
+    pub prune_interval_secs: u64,
+    pub ticks: TickRetentionDto,
+    /// Symbols with windows of their own
+    pub symbol_ticks: BTreeMap<String, TickRetentionDto>,
+    /// None keeps PnL snapshots forever
+    pub pnl_snapshot_retention_secs: Option<i64>,
+    pub last_run: Option<PruneRunDto>,
+    /// Since startup
+    pub passes: u64,
+    pub failed_passes: u64,
+    pub ticks_deleted: u64,
+    pub pnl_snapshots_deleted: u64,
+    pub tables: Vec<HistoryTableDto>,
+}
+
+/// Position update DTO (for WebSocket)
+#[derive(Debug, Serialize)]
+pub struct PositionUpdateDto {
+    pub position_account: String,
+    pub symbol: String,
+    pub side: Side,
+    #[serde(serialize_with = "precision::size")]
+    pub size: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub entry_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub mark_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub unrealized_pnl: Decimal,
+    /// Floored at 0, `margin_deficit` carries how far under it is
+    #[serde(serialize_with = "precision::ratio")]
+    pub margin_ratio: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub margin_deficit: Decimal,
+    /// Liquidation price net of accrued funding and the close fee
+    #[serde(serialize_with = "precision::price_opt")]
+    pub effective_liquidation_price: Option<Decimal>,
+    #[serde(serialize_with = "precision::price")]
+    pub notional_value: Decimal,
+    #[serde(serialize_with = "precision::pct_opt")]
+    pub roi_pct: Option<Decimal>,
+    #[serde(serialize_with = "precision::pct_opt")]
+    pub distance_to_liquidation_pct: Option<Decimal>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<&crate::services::PositionUpdate> for PositionUpdateDto {
+    fn from(update: &crate::services::PositionUpdate) -> Self {
+        Self {
+            position_account: update.position_account.to_string(),
+            symbol: update.symbol.clone(),
+            side: update.side,
+            size: update.size,
+            entry_price: update.entry_price,
+            mark_price: update.mark_price,
+            unrealized_pnl: update.unrealized_pnl,
+            margin_ratio: update.margin_ratio.max(Decimal::ZERO),
+            margin_deficit: MarginCalculator::calculate_margin_deficit(update.margin, update.unrealized_pnl),
+            effective_liquidation_price: update.effective_liquidation_price,
+            notional_value: update.size * update.mark_price,
+            roi_pct: MarginCalculator::calculate_roi(update.unrealized_pnl, update.margin).ok(),
+            distance_to_liquidation_pct: distance_to_liquidation_pct(
+                update.side,
+                update.mark_price,
+                update.liquidation_price,
+            ),
+            timestamp: update.timestamp,
+        }
+    }
+}
+
+/// Percent the mark can move against `side` before reaching `liquidation_price`,
+/// none when there is no liquidation price
+fn distance_to_liquidation_pct(side: Side, mark_price: Decimal, liquidation_price: Decimal) -> Option<Decimal> {
+    if liquidation_price <= Decimal::ZERO {
+        return None;
+    }
+
+    MarginCalculator::distance_to_liquidation(mark_price, liquidation_price, side)
+        .ok()
+        .map(|distance| distance * Decimal::ONE_HUNDRED)
+}
+
+/// Position diff DTO, only fields that moved past the connection's thresholds are set
+#[derive(Debug, Serialize)]
+pub struct PositionDiffDto {
+    pub position_account: String,
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "precision::size_opt")]
+    pub size: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "precision::price_opt")]
+    pub entry_price: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "precision::price_opt")]
+    pub effective_liquidation_price: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "precision::price_opt")]
+    pub mark_price: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "precision::price_opt")]
+    pub unrealized_pnl: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "precision::ratio_opt")]
+    pub margin_ratio: Option<Decimal>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Readiness probe response
+#[derive(Debug, Serialize)]
+pub struct ReadinessDto {
+    pub ready: bool,
+    /// Chain the program is deployed on, "solana" or "evm"
+    pub chain: String,
+    pub chain_synced: bool,
+    pub last_chain_sync: Option<DateTime<Utc>>,
+    pub snapshot_age_secs: Option<i64>,
+    /// Age of the cache by its last chain sync or snapshot, none before either
+    pub sync_lag_secs: Option<i64>,
+    /// Share of accounts a running chunked full scan has applied, none between scans
+    pub scan_progress_pct: Option<f64>,
+    /// At least one RPC endpoint is in rotation
+    pub rpc_healthy: bool,
+    pub rpc_endpoints: Vec<RpcEndpointHealthDto>,
+}
+
+/// Liquidation alert DTO
+#[derive(Debug, Serialize)]
+pub struct LiquidationAlertDto {
+    pub risk_type: Risk,
+    pub position_account: String,
+    pub symbol: String,
+    pub side: Side,
+    #[serde(serialize_with = "precision::price")]
+    pub liquidation_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub current_price: Decimal,
+    /// Set on MarginCall alerts, liquidation waits until then
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin_call_deadline: Option<DateTime<Utc>>,
+    /// Null when the position is no longer monitored
+    pub owner: Option<String>,
+    /// Adverse move left to `liquidation_price`, negative once past it
+    #[serde(serialize_with = "precision::pct_opt")]
+    pub distance_to_liquidation_pct: Option<Decimal>,
+    /// Margin lost if liquidated at `liquidation_price`
+    #[serde(serialize_with = "precision::price_opt")]
+    pub estimated_loss: Option<Decimal>,
+    /// Set on MarginWarning alerts
+    #[serde(serialize_with = "precision::ratio_opt", skip_serializing_if = "Option::is_none")]
+    pub margin_ratio: Option<Decimal>,
+    /// Multiple of the maintenance ratio the margin ratio fell below
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin_ratio_tier: Option<Decimal>,
+}
+
+impl From<LiquidationAlert> for LiquidationAlertDto {
+    fn from(alert: LiquidationAlert) -> Self {
+        Self {
+            distance_to_liquidation_pct: alert.distance_to_liquidation_pct(),
+            risk_type: alert.risk_type,
+            position_account: alert.position_account.to_string(),
+            symbol: alert.symbol,
+            side: alert.side,
+            liquidation_price: alert.liquidation_price,
+            current_price: alert.current_price,
+            margin_call_deadline: alert.margin_call_deadline,
+            owner: alert.owner.map(|owner| owner.to_string()),
+            estimated_loss: alert.estimated_loss,
+            margin_ratio: alert.margin_ratio,
+            margin_ratio_tier: alert.margin_ratio_tier,
+        }
+    }
+}
+
+/// Alert rule on a position
+#[derive(Debug, Serialize)]
+pub struct PositionAlertRuleDto {
+    pub id: String,
+    pub position_account: String,
+    #[serde(flatten)]
+    pub condition: AlertCondition,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<crate::services::PositionAlertRule> for PositionAlertRuleDto {
+    fn from(rule: crate::services::PositionAlertRule) -> Self {
+        Self {
+            id: rule.id.to_string(),
+            position_account: rule.position_account.to_string(),
+            condition: rule.condition,
+            created_at: rule.created_at,
+        }
+    }
+}
+
+/// API token metadata, never the secret
+#[derive(Debug, Serialize)]
+pub struct ApiTokenDto {
+    pub id: String,
+    pub owner: String,
+    pub label: Option<String>,
+    pub scope: &'static str,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<crate::services::ApiToken> for ApiTokenDto {
+    fn from(token: crate::services::ApiToken) -> Self {
+        Self {
+            id: token.id.to_string(),
+            owner: token.owner.to_string(),
+            label: token.label,
+            scope: "read",
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+        }
+    }
+}
+
+/// A new API token, the only response that carries the secret
+#[derive(Debug, Serialize)]
+pub struct CreateApiTokenResponse {
+    pub token: String,
+    #[serde(flatten)]
+    pub api_token: ApiTokenDto,
+}
+
+/// A strategy with totals over its legs
+#[derive(Debug, Serialize)]
+pub struct StrategyDto {
+    pub name: String,
+    pub owner: String,
+    pub open_legs: usize,
+    #[serde(serialize_with = "precision::price")]
+    pub unrealized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub realized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub margin: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub gross_exposure: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub net_exposure: Decimal,
+    pub legs: Vec<PositionDto>,
+    /// Legs the monitor no longer tracks, left out of the totals
+    pub missing_positions: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StrategyDto {
+    /// `legs` are the strategy's positions the monitor still tracks
+    pub fn new(strategy: crate::services::Strategy, legs: Vec<crate::domain::Position>) -> Self {
+        let totals = StrategyTotals::from_legs(&legs);
+        let missing_positions = strategy
+            .positions
+            .iter()
+            .filter(|account| !legs.iter().any(|leg| leg.position_account == **account))
+            .map(|account| account.to_string())
+            .collect();
+
+        Self {
+            name: strategy.name,
+            owner: strategy.owner.to_string(),
+            open_legs: totals.open_legs,
+            unrealized_pnl: totals.unrealized_pnl,
+            realized_pnl: totals.realized_pnl,
+            margin: totals.margin,
+            gross_exposure: totals.gross_exposure,
+            net_exposure: totals.net_exposure,
+            legs: legs.into_iter().map(PositionDto::from).collect(),
+            missing_positions,
+            created_at: strategy.created_at,
+            updated_at: strategy.updated_at,
+        }
+    }
+}
+
+/// Outcome of closing one leg of a strategy
+#[derive(Debug, Serialize)]
+pub struct StrategyLegCloseDto {
+    pub position_account: String,
+    pub symbol: String,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub pnl: Option<Decimal>,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CloseStrategyResponse {
+    pub name: String,
+    /// PnL of the legs that closed
+    #[serde(serialize_with = "precision::price")]
+    pub pnl: Decimal,
+    pub legs: Vec<StrategyLegCloseDto>,
+}
+
+/// A position held off-platform, marked at the oracle price when the symbol has a feed
+#[derive(Debug, Serialize)]
+pub struct ExternalPositionDto {
+    pub id: String,
+    pub symbol: String,
+    pub side: Side,
+    #[serde(serialize_with = "precision::size")]
+    pub size: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub entry_price: Decimal,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub mark_price: Option<Decimal>,
+    /// At the mark price, or at entry when the symbol has no price
+    #[serde(serialize_with = "precision::price")]
+    pub notional: Decimal,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub unrealized_pnl: Option<Decimal>,
+    pub venue: Option<String>,
+    /// Always true, tells these apart from on-chain positions
+    pub external: bool,
+    pub imported_at: DateTime<Utc>,
+}
+
+impl ExternalPositionDto {
+    pub fn new(position: ExternalPosition, mark_price: Option<Decimal>) -> Self {
+        Self {
+            id: position.id.to_string(),
+            notional: position.size * mark_price.unwrap_or(position.entry_price),
+            unrealized_pnl: mark_price.and_then(|mark_price| position.unrealized_pnl(mark_price).ok()),
+            symbol: position.symbol,
+            side: position.side,
+            size: position.size,
+            entry_price: position.entry_price,
+            mark_price,
+            venue: position.venue,
+            external: true,
+            imported_at: position.imported_at,
+        }
+    }
+}
+
+/// Exposure on one symbol, notional signed long minus short
+#[derive(Debug, Serialize)]
+pub struct SymbolExposureDto {
+    pub symbol: String,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub mark_price: Option<Decimal>,
+    #[serde(serialize_with = "precision::price")]
+    pub net_exposure: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub on_chain_net_exposure: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub external_net_exposure: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub gross_exposure: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub on_chain_unrealized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub external_unrealized_pnl: Decimal,
+    pub external_positions: usize,
+}
+
+impl From<SymbolExposure> for SymbolExposureDto {
+    fn from(exposure: SymbolExposure) -> Self {
+        Self {
+            net_exposure: exposure.net(),
+            symbol: exposure.symbol,
+            mark_price: exposure.mark_price,
+            on_chain_net_exposure: exposure.on_chain_net,
+            external_net_exposure: exposure.external_net,
+            gross_exposure: exposure.gross,
+            on_chain_unrealized_pnl: exposure.on_chain_unrealized_pnl,
+            external_unrealized_pnl: exposure.external_unrealized_pnl,
+            external_positions: exposure.external_positions,
+        }
+    }
+}
+
+/// A user's on-chain and external books side by side
+#[derive(Debug, Serialize)]
+pub struct UserRiskDto {
+    pub owner: String,
+    #[serde(serialize_with = "precision::price")]
+    pub gross_exposure: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub net_exposure: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub on_chain_unrealized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub external_unrealized_pnl: Decimal,
+    pub symbols: Vec<SymbolExposureDto>,
+    pub positions: Vec<PositionDto>,
+    pub external_positions: Vec<ExternalPositionDto>,
+}
+
+impl UserRiskDto {
+    pub fn new(
+        owner: Pubkey,
+        exposure: Vec<SymbolExposure>,
+        positions: Vec<PositionDto>,
+        external_positions: Vec<ExternalPositionDto>,
+    ) -> Self {
+        Self {
+            owner: owner.to_string(),
+            gross_exposure: exposure.iter().map(|e| e.gross).sum(),
+            net_exposure: exposure.iter().map(SymbolExposure::net).sum(),
+            on_chain_unrealized_pnl: exposure.iter().map(|e| e.on_chain_unrealized_pnl).sum(),
+            external_unrealized_pnl: exposure.iter().map(|e| e.external_unrealized_pnl).sum(),
+            symbols: exposure.into_iter().map(SymbolExposureDto::from).collect(),
+            positions,
+            external_positions,
+        }
+    }
+}
+
+/// A user's 30-day taker volume, the fee tier it earns and the one they pay
+#[derive(Debug, Serialize)]
+pub struct FeeTierDto {
+    pub owner: String,
+    #[serde(serialize_with = "precision::price")]
+    pub volume_30d: Decimal,
+    /// Tier the volume earns
+    pub tier: u8,
+    pub taker_fee_bps: u16,
+    /// Tier on chain, what opens and closes are charged at until the keeper
+    /// next assigns the earned one
+    pub assigned_tier: u8,
+    pub assigned_taker_fee_bps: u16,
+    /// Taker fees paid since the tier account was created
+    #[serde(serialize_with = "precision::price")]
+    pub fees_paid: Decimal,
+    pub assigned_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// None at the top tier
+    pub next_tier: Option<u8>,
+    pub next_tier_taker_fee_bps: Option<u16>,
+    #[serde(serialize_with = "precision::price_opt")]
+    pub volume_to_next_tier: Option<Decimal>,
+    pub tiers: Vec<FeeTierLevelDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeeTierLevelDto {
+    pub tier: u8,
+    #[serde(serialize_with = "precision::price")]
+    pub min_volume: Decimal,
+    pub taker_fee_bps: u16,
+}
+
+/// The tenant a request was made for
+#[derive(Debug, Serialize)]
+pub struct TenantDto {
+    pub id: String,
+    /// None for the default tenant
+    pub name: Option<String>,
+    /// Minimum 30-day volume of each fee tier for the tenant's users
+    pub fee_tier_volumes: Vec<Decimal>,
+    pub webhooks: Vec<TenantWebhookDto>,
+    /// Users the tenant onboarded, none for the default tenant
+    pub users: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TenantWebhookDto {
+    pub url: String,
+    pub events: Vec<crate::services::WebhookEventType>,
+}
+
+impl From<&crate::services::WebhookEndpoint> for TenantWebhookDto {
+    fn from(endpoint: &crate::services::WebhookEndpoint) -> Self {
+        Self {
+            url: endpoint.url.clone(),
+            events: endpoint.events.clone(),
+        }
+    }
+}
+
+/// A user's risk limits and where their account stands against them
+#[derive(Debug, Serialize)]
+pub struct RiskLimitsDto {
+    pub owner: String,
+    #[serde(serialize_with = "precision::pct_opt")]
+    pub max_position_risk_pct: Option<Decimal>,
+    #[serde(serialize_with = "precision::ratio_opt")]
+    pub max_portfolio_leverage: Option<Decimal>,
+    pub block_opens: bool,
+    pub utilization: RiskUtilizationDto,
+    pub breaches: Vec<RiskLimitBreachDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RiskUtilizationDto {
+    #[serde(serialize_with = "precision::price")]
+    pub total_collateral: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub equity: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub notional: Decimal,
+    /// Null once equity is gone
+    #[serde(serialize_with = "precision::ratio_opt")]
+    pub portfolio_leverage: Option<Decimal>,
+    pub positions: Vec<PositionRiskDto>,
+}
+
+impl From<crate::services::RiskUtilization> for RiskUtilizationDto {
+    fn from(utilization: crate::services::RiskUtilization) -> Self {
+        Self {
+            total_collateral: utilization.total_collateral,
+            equity: utilization.equity,
+            notional: utilization.notional,
+            portfolio_leverage: utilization.portfolio_leverage,
+            positions: utilization.positions.into_iter().map(PositionRiskDto::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PositionRiskDto {
+    pub position_account: Option<String>,
+    pub symbol: String,
+    #[serde(serialize_with = "precision::price")]
+    pub margin: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub notional: Decimal,
+    #[serde(serialize_with = "precision::pct_opt")]
+    pub risk_pct: Option<Decimal>,
+}
+
+impl From<crate::services::PositionRisk> for PositionRiskDto {
+    fn from(risk: crate::services::PositionRisk) -> Self {
+        Self {
+            position_account: risk.position_account.map(|account| account.to_string()),
+            symbol: risk.symbol,
+            margin: risk.margin,
+            notional: risk.notional,
+            risk_pct: risk.risk_pct,
+        }
+    }
+}
+
+/// A risk limit the user is over (also sent over WebSocket)
+#[derive(Debug, Serialize)]
+pub struct RiskLimitBreachDto {
+    pub owner: String,
+    pub kind: crate::services::RiskLimitKind,
+    pub position_account: Option<String>,
+    pub symbol: Option<String>,
+    #[serde(serialize_with = "precision::ratio")]
+    pub limit: Decimal,
+    #[serde(serialize_with = "precision::ratio_opt")]
+    pub value: Option<Decimal>,
+    #[serde(serialize_with = "precision::price")]
+    pub excess: Decimal,
+    pub message: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl From<crate::services::RiskLimitBreach> for RiskLimitBreachDto {
+    fn from(breach: crate::services::RiskLimitBreach) -> Self {
+        Self {
+            message: breach.message(),
+            owner: breach.owner.to_string(),
+            kind: breach.kind,
+            position_account: breach.position_account.map(|account| account.to_string()),
+            symbol: breach.symbol,
+            limit: breach.limit,
+            value: breach.value,
+            excess: breach.excess,
+            detected_at: breach.detected_at,
+        }
+    }
+}
+
+/// An owner's totals across their open positions (WebSocket)
+#[derive(Debug, Serialize)]
+pub struct PortfolioUpdateDto {
+    pub owner: String,
+    #[serde(serialize_with = "precision::price")]
+    pub total_margin: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub total_unrealized_pnl: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub total_notional: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub equity: Decimal,
+    #[serde(serialize_with = "precision::ratio_opt")]
+    pub margin_usage: Option<Decimal>,
+    pub open_positions: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<crate::services::PortfolioUpdate> for PortfolioUpdateDto {
+    fn from(portfolio: crate::services::PortfolioUpdate) -> Self {
+        Self {
+            owner: portfolio.owner.to_string(),
+            total_margin: portfolio.total_margin,
+            total_unrealized_pnl: portfolio.total_unrealized_pnl,
+            total_notional: portfolio.total_notional,
+            equity: portfolio.equity,
+            margin_usage: portfolio.margin_usage,
+            open_positions: portfolio.open_positions,
+            timestamp: portfolio.timestamp,
+        }
+    }
+}
+
+/// Position alert DTO (for WebSocket), the rule that fired and the values that fired it
+#[derive(Debug, Serialize)]
+pub struct PositionAlertDto {
+    pub rule_id: String,
+    pub position_account: String,
+    pub symbol: String,
+    pub rule: AlertCondition,
+    #[serde(serialize_with = "precision::price")]
+    pub mark_price: Decimal,
+    #[serde(serialize_with = "precision::price")]
+    pub unrealized_pnl: Decimal,
+    #[serde(serialize_with = "precision::ratio")]
+    pub margin_ratio: Decimal,
+    pub triggered_at: DateTime<Utc>,
+}
+
+impl From<crate::services::PositionAlert> for PositionAlertDto {
+    fn from(alert: crate::services::PositionAlert) -> Self {
+        Self {
+            rule_id: alert.rule_id.to_string(),
+            position_account: alert.position_account.to_string(),
+            symbol: alert.symbol,
+            rule: alert.condition,
+            mark_price: alert.mark_price,
+            unrealized_pnl: alert.unrealized_pnl,
+            margin_ratio: alert.margin_ratio,
+            triggered_at: alert.triggered_at,
+        }
+    }
+}
+
+/// Price alert query parameters
+#[derive(Debug, Deserialize)]
+pub struct ListPriceAlertsQuery {
+    pub symbol: Option<String>,
+    pub owner: Option<String>,
+}
+
+/// Pending price alert
+#[derive(Debug, Serialize)]
+pub struct PriceAlertDto {
+    pub id: String,
+    pub symbol: String,
+    pub direction: PriceDirection,
+    #[serde(serialize_with = "precision::price")]
+    pub price: Decimal,
+    pub owner: Option<String>,
+    pub webhook_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<crate::services::PriceAlert> for PriceAlertDto {
+    fn from(alert: crate::services::PriceAlert) -> Self {
+        Self {
+            id: alert.id.to_string(),
+            symbol: alert.spec.symbol,
+            direction: alert.spec.direction,
+            price: alert.spec.price,
+            owner: alert.spec.owner,
+            webhook_url: alert.spec.webhook_url,
+            created_at: alert.created_at,
+        }
+    }
+}
+
+/// Triggered price alert DTO (for WebSocket)
+#[derive(Debug, Serialize)]
+pub struct PriceAlertTriggeredDto {
+    pub alert: PriceAlertDto,
+    #[serde(serialize_with = "precision::price")]
+    pub current_price: Decimal,
+    pub triggered_at: DateTime<Utc>,
+}
+
+impl From<crate::services::PriceAlertTriggered> for PriceAlertTriggeredDto {
+    fn from(triggered: crate::services::PriceAlertTriggered) -> Self {
+        Self {
+            alert: PriceAlertDto::from(triggered.alert),
+            current_price: triggered.current_price,
+            triggered_at: triggered.triggered_at,
+        }
+    }
 }
 
 /// Error response
@@ -5,32 +5,194 @@ use axum::{
 };
 use serde_json::json;
 
+use crate::api::validation::FieldError;
+use crate::error::Error;
+use crate::infrastructure::ProgramError;
+use crate::services::PartialExecution;
+
 #[derive(Debug)]
 pub enum ApiError {
     NotFound(String),
     BadRequest(String),
+    /// Admin route called without a valid admin token, or a bad API token
+    Unauthorized(String),
+    /// API token used outside its owner's read-only scope
+    Forbidden(String),
     InternalError(String),
+    /// Request fields rejected before any instruction was built
+    Validation(Vec<FieldError>),
+    /// Price feed unavailable
+    Oracle(String),
+    /// Kill switch set or RPC degraded, trade-mutating calls are refused
+    TradingHalted(String),
+    /// RPC node failure or an undecodable transaction failure
+    Rpc {
+        message: String,
+        signature: Option<String>,
+        logs: Vec<String>,
+    },
+    /// Transaction rejected by the on-chain program
+    ProgramError {
+        error: ProgramError,
+        signature: Option<String>,
+        logs: Vec<String>,
+    },
+    /// A multi-transaction call that failed after some transactions landed,
+    /// answered as `error` with the landed signatures added
+    PartiallyExecuted {
+        error: Box<ApiError>,
+        signatures: Vec<String>,
+    },
+}
+
+impl ApiError {
+    /// Classify a failed service call, prefixing `context` to untyped errors
+    pub fn transaction_failed(context: &str, err: anyhow::Error) -> Self {
+        let err = match err.downcast::<PartialExecution>() {
+            Ok(partial) => {
+                return ApiError::PartiallyExecuted {
+                    error: Box::new(Self::transaction_failed(context, partial.source)),
+                    signatures: partial.completed.iter().map(ToString::to_string).collect(),
+                };
+            }
+            Err(err) => err,
+        };
+
+        match Error::from_anyhow(err) {
+            Error::Internal(message) => ApiError::InternalError(format!("{}: {}", context, message)),
+            error => error.into(),
+        }
+    }
+
+    /// Stable machine-readable code, independent of the message wording
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) => "NOT_FOUND",
+            ApiError::BadRequest(_) => "BAD_REQUEST",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::Forbidden(_) => "FORBIDDEN",
+            ApiError::InternalError(_) => "INTERNAL_ERROR",
+            ApiError::Validation(_) => "VALIDATION_FAILED",
+            ApiError::Oracle(_) => "ORACLE_UNAVAILABLE",
+            ApiError::TradingHalted(_) => "TRADING_HALTED",
+            ApiError::Rpc { .. } => "RPC_ERROR",
+            ApiError::ProgramError { error, .. } if error.is_rate_limit() => "RATE_LIMITED",
+            ApiError::ProgramError { .. } => "PROGRAM_ERROR",
+            ApiError::PartiallyExecuted { error, .. } => error.code(),
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::ProgramError { error, .. } if error.is_rate_limit() => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            ApiError::Validation(_) | ApiError::ProgramError { .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ApiError::Oracle(_) | ApiError::TradingHalted(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Rpc { .. } => StatusCode::BAD_GATEWAY,
+            ApiError::PartiallyExecuted { error, .. } => error.status(),
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Oracle(message) => ApiError::Oracle(message),
+            Error::Rpc {
+                message,
+                signature,
+                logs,
+            } => ApiError::Rpc {
+                message,
+                signature,
+                logs,
+            },
+            Error::Program {
+                error,
+                signature,
+                logs,
+            } => ApiError::ProgramError {
+                error,
+                signature,
+                logs,
+            },
+            Error::NotFound(message) => ApiError::NotFound(message),
+            Error::Validation(errors) => ApiError::Validation(errors),
+            Error::Internal(message) => ApiError::InternalError(message),
+        }
+    }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            ApiError::InternalError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-        };
+        let status = self.status();
+        (status, Json(self.into_body())).into_response()
+    }
+}
 
-        let body = Json(json!({
+impl ApiError {
+    fn into_body(self) -> serde_json::Value {
+        let status = self.status();
+        let code = self.code();
+
+        let mut body = json!({
             "error": status.canonical_reason().unwrap_or("Unknown"),
-            "message": error_message,
-        }));
+            "code": code,
+        });
+
+        match self {
+            ApiError::NotFound(message)
+            | ApiError::BadRequest(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::InternalError(message)
+            | ApiError::Oracle(message)
+            | ApiError::TradingHalted(message) => {
+                body["message"] = json!(message);
+            }
+            ApiError::Validation(errors) => {
+                body["message"] = json!("Request validation failed");
+                body["fields"] = json!(errors);
+            }
+            ApiError::Rpc {
+                message,
+                signature,
+                logs,
+            } => {
+                body["message"] = json!(message);
+                body["signature"] = json!(signature);
+                body["logs"] = json!(logs);
+            }
+            ApiError::ProgramError {
+                error,
+                signature,
+                logs,
+            } => {
+                body["message"] = json!(error.message);
+                body["program_error"] = json!({ "name": error.code, "number": error.number });
+                body["signature"] = json!(signature);
+                body["logs"] = json!(logs);
+            }
+            ApiError::PartiallyExecuted { error, signatures } => {
+                body = error.into_body();
+                body["completed_signatures"] = json!(signatures);
+            }
+        }
 
-        (status, body).into_response()
+        body
     }
 }
 
 impl From<anyhow::Error> for ApiError {
     fn from(err: anyhow::Error) -> Self {
-        ApiError::InternalError(err.to_string())
+        Error::from_anyhow(err).into()
     }
 }
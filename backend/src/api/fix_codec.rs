@@ -0,0 +1,294 @@
+/// FIX 4.4 tag=value codec
+/// Frames messages out of a byte stream, checks BeginString, BodyLength and
+/// CheckSum, and encodes outgoing messages with the standard header and
+/// trailer. Only what the order entry gateway needs, no repeating groups
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+
+pub const BEGIN_STRING: &str = "FIX.4.4";
+pub const SOH: u8 = 0x01;
+
+// Header and trailer
+pub const TAG_BEGIN_STRING: u32 = 8;
+pub const TAG_BODY_LENGTH: u32 = 9;
+pub const TAG_CHECKSUM: u32 = 10;
+pub const TAG_MSG_SEQ_NUM: u32 = 34;
+pub const TAG_MSG_TYPE: u32 = 35;
+pub const TAG_POSS_DUP_FLAG: u32 = 43;
+pub const TAG_SENDER_COMP_ID: u32 = 49;
+pub const TAG_SENDING_TIME: u32 = 52;
+pub const TAG_TARGET_COMP_ID: u32 = 56;
+
+// Session and application fields
+pub const TAG_ACCOUNT: u32 = 1;
+pub const TAG_AVG_PX: u32 = 6;
+pub const TAG_CL_ORD_ID: u32 = 11;
+pub const TAG_CUM_QTY: u32 = 14;
+pub const TAG_EXEC_ID: u32 = 17;
+pub const TAG_LAST_PX: u32 = 31;
+pub const TAG_LAST_QTY: u32 = 32;
+pub const TAG_ORDER_ID: u32 = 37;
+pub const TAG_ORDER_QTY: u32 = 38;
+pub const TAG_ORD_STATUS: u32 = 39;
+pub const TAG_ORD_TYPE: u32 = 40;
+pub const TAG_ORIG_CL_ORD_ID: u32 = 41;
+pub const TAG_PRICE: u32 = 44;
+pub const TAG_REF_SEQ_NUM: u32 = 45;
+pub const TAG_SIDE: u32 = 54;
+pub const TAG_SYMBOL: u32 = 55;
+pub const TAG_TEXT: u32 = 58;
+pub const TAG_TIME_IN_FORCE: u32 = 59;
+pub const TAG_TRANSACT_TIME: u32 = 60;
+pub const TAG_ENCRYPT_METHOD: u32 = 98;
+pub const TAG_CXL_REJ_REASON: u32 = 102;
+pub const TAG_HEART_BT_INT: u32 = 108;
+pub const TAG_TEST_REQ_ID: u32 = 112;
+pub const TAG_RESET_SEQ_NUM_FLAG: u32 = 141;
+pub const TAG_REF_MSG_TYPE: u32 = 372;
+pub const TAG_BUSINESS_REJECT_REASON: u32 = 380;
+pub const TAG_CXL_REJ_RESPONSE_TO: u32 = 434;
+pub const TAG_EXEC_TYPE: u32 = 150;
+pub const TAG_LEAVES_QTY: u32 = 151;
+pub const TAG_USERNAME: u32 = 553;
+pub const TAG_PASSWORD: u32 = 554;
+/// User-defined, the position leverage of a NewOrderSingle
+pub const TAG_LEVERAGE: u32 = 5001;
+
+pub mod msg_type {
+    pub const HEARTBEAT: &str = "0";
+    pub const TEST_REQUEST: &str = "1";
+    pub const REJECT: &str = "3";
+    pub const LOGOUT: &str = "5";
+    pub const EXECUTION_REPORT: &str = "8";
+    pub const ORDER_CANCEL_REJECT: &str = "9";
+    pub const LOGON: &str = "A";
+    pub const NEW_ORDER_SINGLE: &str = "D";
+    pub const ORDER_CANCEL_REQUEST: &str = "F";
+    pub const BUSINESS_MESSAGE_REJECT: &str = "j";
+}
+
+// Longest frame accepted, orders are a few hundred bytes
+const MAX_MESSAGE_LEN: usize = 8192;
+
+/// One message, fields in wire order, header and trailer included
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixMessage {
+    fields: Vec<(u32, String)>,
+}
+
+impl FixMessage {
+    /// First value of `tag`
+    pub fn get(&self, tag: u32) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == tag)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn msg_type(&self) -> &str {
+        self.get(TAG_MSG_TYPE).unwrap_or_default()
+    }
+
+    pub fn seq_num(&self) -> Option<u64> {
+        self.get(TAG_MSG_SEQ_NUM).and_then(|seq| seq.parse().ok())
+    }
+
+    /// Parse one complete frame, as cut by `next_frame`
+    pub fn parse(frame: &[u8]) -> Result<Self> {
+        let body_end = frame
+            .len()
+            .checked_sub(7)
+            .filter(|end| frame[*end..].starts_with(b"10="))
+            .ok_or_else(|| anyhow!("Missing CheckSum"))?;
+        let expected = checksum(&frame[..body_end]);
+
+        let text = std::str::from_utf8(frame).map_err(|_| anyhow!("Message is not UTF-8"))?;
+        let mut fields = Vec::new();
+        for field in text.split(SOH as char).filter(|field| !field.is_empty()) {
+            let (tag, value) = field
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Malformed field {:?}", field))?;
+            let tag: u32 = tag.parse().map_err(|_| anyhow!("Malformed tag {:?}", tag))?;
+            fields.push((tag, value.to_string()));
+        }
+
+        let message = Self { fields };
+        if message.get(TAG_BEGIN_STRING) != Some(BEGIN_STRING) {
+            return Err(anyhow!("BeginString must be {}", BEGIN_STRING));
+        }
+        if message.get(TAG_CHECKSUM) != Some(format!("{:03}", expected).as_str()) {
+            return Err(anyhow!("CheckSum mismatch"));
+        }
+        if message.get(TAG_MSG_TYPE).is_none() {
+            return Err(anyhow!("Missing MsgType"));
+        }
+
+        Ok(message)
+    }
+}
+
+/// Cut the next complete message off the front of `buffer`, none until one
+/// has fully arrived. Errors mean the stream can't be resynchronized
+pub fn next_frame(buffer: &mut Vec<u8>) -> Result<Option<Vec<u8>>> {
+    let prefix = format!("{}={}{}{}=", TAG_BEGIN_STRING, BEGIN_STRING, SOH as char, TAG_BODY_LENGTH);
+    if buffer.len() < prefix.len() {
+        return Ok(None);
+    }
+    if !buffer.starts_with(prefix.as_bytes()) {
+        return Err(anyhow!("Message does not start with BeginString"));
+    }
+
+    let Some(length_end) = buffer[prefix.len()..].iter().position(|byte| *byte == SOH) else {
+        return if buffer.len() > prefix.len() + 8 {
+            Err(anyhow!("Malformed BodyLength"))
+        } else {
+            Ok(None)
+        };
+    };
+    let length_end = prefix.len() + length_end;
+    let body_length: usize = std::str::from_utf8(&buffer[prefix.len()..length_end])
+        .ok()
+        .and_then(|length| length.parse().ok())
+        .ok_or_else(|| anyhow!("Malformed BodyLength"))?;
+
+    // Body starts after BodyLength's SOH, then the 7 byte "10=nnn<SOH>" trailer
+    let frame_len = length_end + 1 + body_length + 7;
+    if frame_len > MAX_MESSAGE_LEN {
+        return Err(anyhow!("Message of {} bytes is too long", frame_len));
+    }
+    if buffer.len() < frame_len {
+        return Ok(None);
+    }
+
+    Ok(Some(buffer.drain(..frame_len).collect()))
+}
+
+/// Byte sum modulo 256
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// UTCTimestamp with milliseconds
+pub fn format_timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%d-%H:%M:%S%.3f").to_string()
+}
+
+/// An outgoing message's body fields, the header and trailer are added by `encode`
+#[derive(Debug, Clone, Default)]
+pub struct FixBody {
+    fields: Vec<(u32, String)>,
+}
+
+impl FixBody {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, tag: u32, value: impl ToString) -> Self {
+        self.fields.push((tag, value.to_string()));
+        self
+    }
+
+    pub fn optional(self, tag: u32, value: Option<impl ToString>) -> Self {
+        match value {
+            Some(value) => self.field(tag, value),
+            None => self,
+        }
+    }
+}
+
+/// Serialize a message from us (`sender`) to the counterparty (`target`)
+pub fn encode(
+    msg_type: &str,
+    body: &FixBody,
+    sender: &str,
+    target: &str,
+    seq_num: u64,
+    sending_time: DateTime<Utc>,
+) -> Vec<u8> {
+    let mut content = String::new();
+    let mut push = |tag: u32, value: &str| {
+        content.push_str(&tag.to_string());
+        content.push('=');
+        content.push_str(value);
+        content.push(SOH as char);
+    };
+
+    push(TAG_MSG_TYPE, msg_type);
+    push(TAG_SENDER_COMP_ID, sender);
+    push(TAG_TARGET_COMP_ID, target);
+    push(TAG_MSG_SEQ_NUM, &seq_num.to_string());
+    push(TAG_SENDING_TIME, &format_timestamp(sending_time));
+    for (tag, value) in &body.fields {
+        push(*tag, value);
+    }
+
+    let mut message = format!(
+        "{}={}{}{}={}{}",
+        TAG_BEGIN_STRING,
+        BEGIN_STRING,
+        SOH as char,
+        TAG_BODY_LENGTH,
+        content.len(),
+        SOH as char
+    )
+    .into_bytes();
+    message.extend_from_slice(content.as_bytes());
+
+    let sum = checksum(&message);
+    message.extend_from_slice(format!("{}={:03}{}", TAG_CHECKSUM, sum, SOH as char).as_bytes());
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wire(message: &str) -> Vec<u8> {
+        message.replace('|', "\x01").into_bytes()
+    }
+
+    #[test]
+    fn test_encode_round_trips() {
+        let body = FixBody::new()
+            .field(TAG_CL_ORD_ID, "ord-1")
+            .field(TAG_SYMBOL, "SOL-USD")
+            .optional(TAG_TEXT, None::<String>);
+        let at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let bytes = encode(msg_type::NEW_ORDER_SINGLE, &body, "PERPS", "CLIENT", 7, at);
+
+        let mut buffer = bytes.clone();
+        buffer.extend_from_slice(b"8=FIX.4.4\x019=");
+        let frame = next_frame(&mut buffer).unwrap().unwrap();
+        assert_eq!(frame, bytes);
+        // The start of the next message stays buffered
+        assert_eq!(buffer, b"8=FIX.4.4\x019=");
+
+        let message = FixMessage::parse(&frame).unwrap();
+        assert_eq!(message.msg_type(), msg_type::NEW_ORDER_SINGLE);
+        assert_eq!(message.seq_num(), Some(7));
+        assert_eq!(message.get(TAG_SENDING_TIME), Some("20231114-22:13:20.000"));
+        assert_eq!(message.get(TAG_SYMBOL), Some("SOL-USD"));
+        assert_eq!(message.get(TAG_TEXT), None);
+    }
+
+    #[test]
+    fn test_parse_checks_checksum() {
+        let heartbeat = wire("8=FIX.4.4|9=54|35=0|49=CLIENT|56=PERPS|34=2|52=20231114-22:13:20.000|10=007|");
+        let frame = next_frame(&mut heartbeat.clone()).unwrap().unwrap();
+        assert_eq!(FixMessage::parse(&frame).unwrap().msg_type(), msg_type::HEARTBEAT);
+
+        // Same length, different sequence number
+        let tampered = wire("8=FIX.4.4|9=54|35=0|49=CLIENT|56=PERPS|34=3|52=20231114-22:13:20.000|10=007|");
+        assert!(FixMessage::parse(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_next_frame_waits_and_rejects_garbage() {
+        let mut partial = wire("8=FIX.4.4|9=55|35=0|49=CLIENT");
+        assert_eq!(next_frame(&mut partial).unwrap(), None);
+
+        let mut garbage = b"GET / HTTP/1.1\r\n".to_vec();
+        assert!(next_frame(&mut garbage).is_err());
+    }
+}
@@ -0,0 +1,723 @@
+/// FIX Gateway
+/// Optional FIX 4.4 acceptor for institutional order entry. NewOrderSingle
+/// goes through the same one-way order flow as POST /orders, so halts,
+/// validation and open interest limits apply alike, and each order is
+/// answered with a New ExecutionReport then a Trade or Rejected one
+/// Orders fill or fail right away, nothing rests: market orders fill at the
+/// oracle price, limit orders only when marketable, and OrderCancelRequest
+/// is always answered with an OrderCancelReject
+/// Sequence numbers live in memory and restart with every logon, and resend
+/// requests are answered with a gap fill since nothing is stored to replay
+use anyhow::{anyhow, Result};
+use axum::extract::State;
+use axum::Json;
+use chrono::Utc;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::api::admin::AdminToken;
+use crate::api::dto::{OpenPositionRequest, OrderFillResponse};
+use crate::api::errors::ApiError;
+use crate::api::fix_codec::*;
+use crate::api::handlers::{place_order, AppState};
+use crate::api::paper::PaperSession;
+use crate::domain::{symbols, Side};
+
+// Heartbeat interval bounds a counterparty may ask for at logon
+const MIN_HEARTBEAT_SECS: u64 = 5;
+const MAX_HEARTBEAT_SECS: u64 = 300;
+// How long a connection may stay silent before its first message
+const LOGON_TIMEOUT: Duration = Duration::from_secs(10);
+// Session timer resolution
+const TICK: Duration = Duration::from_secs(1);
+
+const TAG_GAP_FILL_FLAG: u32 = 123;
+const TAG_NEW_SEQ_NO: u32 = 36;
+const MSG_TYPE_RESEND_REQUEST: &str = "2";
+const MSG_TYPE_SEQUENCE_RESET: &str = "4";
+
+pub struct FixGatewayConfig {
+    /// Listen address, e.g. 0.0.0.0:9878
+    pub addr: String,
+    /// Our CompID, counterparties send it as TargetCompID
+    pub comp_id: String,
+    /// Logon Password(554) required when set
+    pub password: Option<AdminToken>,
+}
+
+/// Accept FIX sessions until the listener fails
+pub async fn serve(config: FixGatewayConfig, state: AppState) -> Result<()> {
+    let listener = TcpListener::bind(&config.addr).await?;
+    info!("FIX gateway listening on {} as {}", config.addr, config.comp_id);
+
+    let config = Arc::new(config);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let config = Arc::clone(&config);
+        let state = state.clone();
+
+        tokio::spawn(async move {
+            match run_session(stream, config, state).await {
+                Ok(()) => info!("FIX session from {} ended", peer),
+                Err(e) => warn!("FIX session from {} dropped: {:#}", peer, e),
+            }
+        });
+    }
+}
+
+/// A message for the writer task, which stamps the header
+enum Outgoing {
+    Message(&'static str, FixBody),
+    /// Gap fill up to the writer's next sequence number
+    GapFill,
+    /// Send then close the connection
+    Logout(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OrdStatus {
+    New,
+    Filled,
+    Rejected,
+}
+
+impl OrdStatus {
+    fn code(&self) -> &'static str {
+        match self {
+            OrdStatus::New => "0",
+            OrdStatus::Filled => "2",
+            OrdStatus::Rejected => "8",
+        }
+    }
+
+    /// ExecType of the report that moves an order into this status, Trade for fills
+    fn exec_type(&self) -> &'static str {
+        match self {
+            OrdStatus::Filled => "F",
+            status => status.code(),
+        }
+    }
+}
+
+/// A NewOrderSingle's fields, checked
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixOrder {
+    pub cl_ord_id: String,
+    pub account: String,
+    pub symbol: String,
+    pub side: Side,
+    pub qty: Decimal,
+    /// None for market orders
+    pub limit_price: Option<Decimal>,
+    pub leverage: Option<u16>,
+}
+
+impl FixOrder {
+    /// Reject text on fields this gateway can't take
+    pub fn parse(message: &FixMessage) -> Result<Self, String> {
+        let required = |tag: u32, name: &str| {
+            message
+                .get(tag)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+                .ok_or_else(|| format!("Missing {}({})", name, tag))
+        };
+
+        let cl_ord_id = required(TAG_CL_ORD_ID, "ClOrdID")?;
+        let account = required(TAG_ACCOUNT, "Account")?;
+        let symbol = required(TAG_SYMBOL, "Symbol")?;
+        let side = match required(TAG_SIDE, "Side")?.as_str() {
+            "1" => Side::Long,
+            "2" => Side::Short,
+            other => return Err(format!("Unsupported Side(54) {}", other)),
+        };
+        let qty = Decimal::from_str(&required(TAG_ORDER_QTY, "OrderQty")?)
+            .ok()
+            .filter(|qty| *qty > Decimal::ZERO)
+            .ok_or_else(|| "OrderQty(38) must be a positive number".to_string())?;
+
+        let limit_price = match required(TAG_ORD_TYPE, "OrdType")?.as_str() {
+            "1" => None,
+            "2" => {
+                // Nothing rests, so a limit is immediate-or-cancel at best
+                if let Some(tif) = message.get(TAG_TIME_IN_FORCE).filter(|tif| !matches!(*tif, "3" | "4")) {
+                    return Err(format!("Unsupported TimeInForce(59) {}, limit orders are IOC or FOK", tif));
+                }
+                let price = Decimal::from_str(&required(TAG_PRICE, "Price")?)
+                    .ok()
+                    .filter(|price| *price > Decimal::ZERO)
+                    .ok_or_else(|| "Price(44) must be a positive number".to_string())?;
+                Some(price)
+            }
+            other => return Err(format!("Unsupported OrdType(40) {}", other)),
+        };
+
+        let leverage = message
+            .get(TAG_LEVERAGE)
+            .map(|leverage| {
+                leverage
+                    .parse()
+                    .map_err(|_| format!("Leverage({}) must be a whole number", TAG_LEVERAGE))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            cl_ord_id,
+            account,
+            symbol,
+            side,
+            qty,
+            limit_price,
+            leverage,
+        })
+    }
+
+    /// Whether the order fills at `oracle_price`: a buy limit at or above it, a sell at or below
+    pub fn is_marketable(&self, oracle_price: Decimal) -> bool {
+        match (self.limit_price, self.side) {
+            (None, _) => true,
+            (Some(limit), Side::Long) => limit >= oracle_price,
+            (Some(limit), Side::Short) => limit <= oracle_price,
+        }
+    }
+}
+
+/// ExecutionReport fields common to every report of an order
+fn order_report(order_id: &str, order: &FixOrder, status: OrdStatus) -> FixBody {
+    FixBody::new()
+        .field(TAG_ORDER_ID, order_id)
+        .field(TAG_CL_ORD_ID, &order.cl_ord_id)
+        .field(TAG_ACCOUNT, &order.account)
+        .field(TAG_SYMBOL, &order.symbol)
+        .field(TAG_SIDE, side_code(order.side))
+        .field(TAG_ORDER_QTY, order.qty)
+        .field(TAG_EXEC_TYPE, status.exec_type())
+        .field(TAG_ORD_STATUS, status.code())
+}
+
+fn side_code(side: Side) -> &'static str {
+    match side {
+        Side::Long => "1",
+        Side::Short => "2",
+    }
+}
+
+/// One line for Text(58), the same code and message the REST API answers with
+fn reject_text(err: &ApiError) -> String {
+    let message = match err {
+        ApiError::NotFound(message)
+        | ApiError::BadRequest(message)
+        | ApiError::Unauthorized(message)
+        | ApiError::Forbidden(message)
+        | ApiError::InternalError(message)
+        | ApiError::Oracle(message)
+        | ApiError::TradingHalted(message)
+        | ApiError::Rpc { message, .. } => message.clone(),
+        ApiError::Validation(errors) => errors
+            .iter()
+            .map(|error| format!("{} {}", error.field, error.message))
+            .collect::<Vec<_>>()
+            .join("; "),
+        ApiError::ProgramError { error, .. } => error.to_string(),
+        ApiError::PartiallyExecuted { error, signatures } => {
+            return format!("{} (already landed: {})", reject_text(error), signatures.join(", "));
+        }
+    };
+
+    format!("{}: {}", err.code(), message)
+}
+
+struct Session {
+    config: Arc<FixGatewayConfig>,
+    state: AppState,
+    outgoing: mpsc::UnboundedSender<Outgoing>,
+    /// ClOrdID -> status, for duplicate checks and cancel replies
+    orders: Arc<Mutex<HashMap<String, OrdStatus>>>,
+    next_in_seq: u64,
+}
+
+async fn run_session(stream: TcpStream, config: Arc<FixGatewayConfig>, state: AppState) -> Result<()> {
+    let (mut reader, writer) = stream.into_split();
+    let mut buffer = Vec::new();
+
+    let logon = tokio::time::timeout(LOGON_TIMEOUT, read_message(&mut reader, &mut buffer))
+        .await
+        .map_err(|_| anyhow!("No logon within {:?}", LOGON_TIMEOUT))??
+        .ok_or_else(|| anyhow!("Closed before logon"))?;
+    let (target, heartbeat) = accept_logon(&logon, &config)?;
+    info!("FIX session {} logged on, heartbeat {:?}", target, heartbeat);
+
+    let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+    let writer_task = tokio::spawn(write_loop(
+        writer,
+        outgoing_rx,
+        config.comp_id.clone(),
+        target,
+        heartbeat,
+    ));
+
+    let mut logon_reply = FixBody::new()
+        .field(TAG_ENCRYPT_METHOD, 0)
+        .field(TAG_HEART_BT_INT, heartbeat.as_secs());
+    if logon.get(TAG_RESET_SEQ_NUM_FLAG) == Some("Y") {
+        logon_reply = logon_reply.field(TAG_RESET_SEQ_NUM_FLAG, "Y");
+    }
+    let _ = outgoing.send(Outgoing::Message(msg_type::LOGON, logon_reply));
+
+    let mut session = Session {
+        config,
+        state,
+        outgoing,
+        orders: Arc::new(Mutex::new(HashMap::new())),
+        next_in_seq: logon.seq_num().unwrap_or(1) + 1,
+    };
+
+    let mut last_received = Instant::now();
+    let mut test_request_sent = false;
+    let mut ticker = tokio::time::interval(TICK);
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        // Includes anything sent right behind the logon
+        while let Some(frame) = next_frame(&mut buffer)? {
+            // Garbled messages are ignored, the counterparty resends on the gap
+            let message = match FixMessage::parse(&frame) {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("Ignoring garbled FIX message: {}", e);
+                    continue;
+                }
+            };
+            if !session.handle(message) {
+                return finish(session.outgoing, writer_task).await;
+            }
+        }
+
+        tokio::select! {
+            read = reader.read(&mut chunk) => {
+                let read = read?;
+                if read == 0 {
+                    break;
+                }
+                buffer.extend_from_slice(&chunk[..read]);
+                last_received = Instant::now();
+                test_request_sent = false;
+            }
+            _ = ticker.tick() => {
+                let silent = last_received.elapsed();
+                if silent > heartbeat * 2 {
+                    let _ = session.outgoing.send(Outgoing::Logout("Heartbeat timeout".to_string()));
+                    return finish(session.outgoing, writer_task).await;
+                }
+                if silent > heartbeat + heartbeat / 5 && !test_request_sent {
+                    let body = FixBody::new().field(TAG_TEST_REQ_ID, Uuid::new_v4());
+                    let _ = session.outgoing.send(Outgoing::Message(msg_type::TEST_REQUEST, body));
+                    test_request_sent = true;
+                }
+            }
+        }
+    }
+
+    finish(session.outgoing, writer_task).await
+}
+
+/// Let the writer drain what is queued, then return
+async fn finish(
+    outgoing: mpsc::UnboundedSender<Outgoing>,
+    writer_task: tokio::task::JoinHandle<Result<()>>,
+) -> Result<()> {
+    drop(outgoing);
+    writer_task.await?
+}
+
+async fn read_message(reader: &mut (impl AsyncReadExt + Unpin), buffer: &mut Vec<u8>) -> Result<Option<FixMessage>> {
+    let mut chunk = [0u8; 4096];
+    loop {
+        if let Some(frame) = next_frame(buffer)? {
+            return FixMessage::parse(&frame).map(Some);
+        }
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+}
+
+/// The counterparty's CompID and the heartbeat interval, for a valid logon
+fn accept_logon(logon: &FixMessage, config: &FixGatewayConfig) -> Result<(String, Duration)> {
+    if logon.msg_type() != msg_type::LOGON {
+        return Err(anyhow!("First message must be a Logon"));
+    }
+    if logon.get(TAG_TARGET_COMP_ID) != Some(config.comp_id.as_str()) {
+        return Err(anyhow!("Logon TargetCompID must be {}", config.comp_id));
+    }
+    let target = logon
+        .get(TAG_SENDER_COMP_ID)
+        .filter(|sender| !sender.is_empty())
+        .ok_or_else(|| anyhow!("Logon without SenderCompID"))?
+        .to_string();
+
+    if let Some(password) = &config.password {
+        if !logon.get(TAG_PASSWORD).is_some_and(|presented| password.verify(presented)) {
+            return Err(anyhow!("Logon from {} with a bad password", target));
+        }
+    }
+
+    // Nothing persists across connections, so each logon starts a new sequence
+    if logon.get(TAG_RESET_SEQ_NUM_FLAG) != Some("Y") && logon.seq_num() != Some(1) {
+        return Err(anyhow!("Logon from {} must reset sequence numbers", target));
+    }
+
+    let heartbeat = logon
+        .get(TAG_HEART_BT_INT)
+        .and_then(|interval| interval.parse::<u64>().ok())
+        .ok_or_else(|| anyhow!("Logon without HeartBtInt"))?
+        .clamp(MIN_HEARTBEAT_SECS, MAX_HEARTBEAT_SECS);
+
+    Ok((target, Duration::from_secs(heartbeat)))
+}
+
+/// Owns the socket's write half and the outgoing sequence, sending a
+/// Heartbeat whenever nothing else went out for an interval
+async fn write_loop(
+    mut writer: OwnedWriteHalf,
+    mut outgoing: mpsc::UnboundedReceiver<Outgoing>,
+    sender: String,
+    target: String,
+    heartbeat: Duration,
+) -> Result<()> {
+    let mut seq_num = 1;
+    let mut last_sent = Instant::now();
+    let mut ticker = tokio::time::interval(TICK);
+
+    loop {
+        let (msg_type, body, close) = tokio::select! {
+            message = outgoing.recv() => match message {
+                Some(Outgoing::Message(msg_type, body)) => (msg_type, body, false),
+                Some(Outgoing::GapFill) => (
+                    MSG_TYPE_SEQUENCE_RESET,
+                    FixBody::new().field(TAG_GAP_FILL_FLAG, "Y").field(TAG_NEW_SEQ_NO, seq_num + 1),
+                    false,
+                ),
+                Some(Outgoing::Logout(text)) => (msg_type::LOGOUT, FixBody::new().field(TAG_TEXT, text), true),
+                None => return Ok(()),
+            },
+            _ = ticker.tick() => {
+                if last_sent.elapsed() < heartbeat {
+                    continue;
+                }
+                (msg_type::HEARTBEAT, FixBody::new(), false)
+            }
+        };
+
+        let bytes = encode(msg_type, &body, &sender, &target, seq_num, Utc::now());
+        writer.write_all(&bytes).await?;
+        seq_num += 1;
+        last_sent = Instant::now();
+
+        if close {
+            writer.shutdown().await?;
+            return Ok(());
+        }
+    }
+}
+
+impl Session {
+    fn send(&self, msg_type: &'static str, body: FixBody) {
+        let _ = self.outgoing.send(Outgoing::Message(msg_type, body));
+    }
+
+    /// Returns false once the session should end
+    fn handle(&mut self, message: FixMessage) -> bool {
+        let seq_num = message.seq_num().unwrap_or(0);
+        if seq_num < self.next_in_seq {
+            if message.get(TAG_POSS_DUP_FLAG) == Some("Y") {
+                return true;
+            }
+            let text = format!("MsgSeqNum too low, expected {} got {}", self.next_in_seq, seq_num);
+            let _ = self.outgoing.send(Outgoing::Logout(text));
+            return false;
+        }
+        // Gaps are accepted as is, there is no store to ask a resend from
+        self.next_in_seq = seq_num + 1;
+
+        match message.msg_type() {
+            msg_type::HEARTBEAT => {}
+            msg_type::TEST_REQUEST => {
+                let body = FixBody::new().optional(TAG_TEST_REQ_ID, message.get(TAG_TEST_REQ_ID));
+                self.send(msg_type::HEARTBEAT, body);
+            }
+            MSG_TYPE_RESEND_REQUEST => {
+                let _ = self.outgoing.send(Outgoing::GapFill);
+            }
+            MSG_TYPE_SEQUENCE_RESET => {
+                if let Some(new_seq) = message.get(TAG_NEW_SEQ_NO).and_then(|seq| seq.parse().ok()) {
+                    self.next_in_seq = new_seq;
+                }
+            }
+            msg_type::LOGOUT => {
+                let _ = self.outgoing.send(Outgoing::Logout("Logout acknowledged".to_string()));
+                return false;
+            }
+            msg_type::LOGON => {
+                self.send(
+                    msg_type::REJECT,
+                    FixBody::new()
+                        .field(TAG_REF_SEQ_NUM, seq_num)
+                        .field(TAG_TEXT, "Already logged on"),
+                );
+            }
+            msg_type::NEW_ORDER_SINGLE => self.new_order(&message),
+            msg_type::ORDER_CANCEL_REQUEST => self.cancel_order(&message),
+            other => {
+                self.send(
+                    msg_type::BUSINESS_MESSAGE_REJECT,
+                    FixBody::new()
+                        .field(TAG_REF_SEQ_NUM, seq_num)
+                        .field(TAG_REF_MSG_TYPE, other)
+                        // Unsupported Message Type
+                        .field(TAG_BUSINESS_REJECT_REASON, 3)
+                        .field(TAG_TEXT, "Unsupported message type"),
+                );
+            }
+        }
+
+        true
+    }
+
+    fn new_order(&self, message: &FixMessage) {
+        let order = match FixOrder::parse(message) {
+            Ok(order) => order,
+            Err(text) => {
+                let body = FixBody::new()
+                    .field(TAG_ORDER_ID, "NONE")
+                    .field(TAG_CL_ORD_ID, message.get(TAG_CL_ORD_ID).unwrap_or("NONE"))
+                    .field(TAG_EXEC_ID, Uuid::new_v4())
+                    .field(TAG_EXEC_TYPE, OrdStatus::Rejected.exec_type())
+                    .field(TAG_ORD_STATUS, OrdStatus::Rejected.code())
+                    .optional(TAG_SYMBOL, message.get(TAG_SYMBOL))
+                    .optional(TAG_SIDE, message.get(TAG_SIDE))
+                    .field(TAG_LEAVES_QTY, 0)
+                    .field(TAG_CUM_QTY, 0)
+                    .field(TAG_AVG_PX, 0)
+                    .field(TAG_TEXT, text);
+                self.send(msg_type::EXECUTION_REPORT, body);
+                return;
+            }
+        };
+
+        let order_id = Uuid::new_v4().to_string();
+
+        {
+            let mut orders = self.orders.lock().unwrap();
+            if orders.contains_key(&order.cl_ord_id) {
+                drop(orders);
+                let body = order_report(&order_id, &order, OrdStatus::Rejected)
+                    .field(TAG_EXEC_ID, Uuid::new_v4())
+                    .field(TAG_LEAVES_QTY, 0)
+                    .field(TAG_CUM_QTY, 0)
+                    .field(TAG_AVG_PX, 0)
+                    .field(TAG_TEXT, "Duplicate ClOrdID");
+                self.send(msg_type::EXECUTION_REPORT, body);
+                return;
+            }
+            orders.insert(order.cl_ord_id.clone(), OrdStatus::New);
+        }
+
+        self.send(
+            msg_type::EXECUTION_REPORT,
+            order_report(&order_id, &order, OrdStatus::New)
+                .field(TAG_EXEC_ID, Uuid::new_v4())
+                .field(TAG_LEAVES_QTY, order.qty)
+                .field(TAG_CUM_QTY, 0)
+                .field(TAG_AVG_PX, 0)
+                .field(TAG_TRANSACT_TIME, format_timestamp(Utc::now())),
+        );
+
+        // Fills wait on confirmation, the session keeps reading meanwhile
+        let state = self.state.clone();
+        let outgoing = self.outgoing.clone();
+        let orders = Arc::clone(&self.orders);
+        let comp_id = self.config.comp_id.clone();
+        tokio::spawn(async move {
+            let result = execute(&state, &order).await;
+
+            let (status, body) = match result {
+                Ok(fill) => {
+                    info!(
+                        "{} filled FIX order {} for {}: {} {} at {}",
+                        comp_id, order.cl_ord_id, order.account, fill.size, fill.symbol, fill.price
+                    );
+                    let body = order_report(&order_id, &order, OrdStatus::Filled)
+                        .field(TAG_EXEC_ID, fill.signatures.last().cloned().unwrap_or_else(|| Uuid::new_v4().to_string()))
+                        .field(TAG_LAST_QTY, fill.size)
+                        .field(TAG_LAST_PX, fill.price)
+                        .field(TAG_LEAVES_QTY, 0)
+                        .field(TAG_CUM_QTY, fill.size)
+                        .field(TAG_AVG_PX, fill.price)
+                        .field(TAG_TRANSACT_TIME, format_timestamp(Utc::now()))
+                        .field(
+                            TAG_TEXT,
+                            format!(
+                                "netted {} opened {} realized_pnl {}",
+                                fill.netted_size, fill.opened_size, fill.realized_pnl
+                            ),
+                        );
+                    (OrdStatus::Filled, body)
+                }
+                Err(e) => {
+                    let body = order_report(&order_id, &order, OrdStatus::Rejected)
+                        .field(TAG_EXEC_ID, Uuid::new_v4())
+                        .field(TAG_LEAVES_QTY, 0)
+                        .field(TAG_CUM_QTY, 0)
+                        .field(TAG_AVG_PX, 0)
+                        .field(TAG_TEXT, reject_text(&e));
+                    (OrdStatus::Rejected, body)
+                }
+            };
+
+            orders.lock().unwrap().insert(order.cl_ord_id.clone(), status);
+            let _ = outgoing.send(Outgoing::Message(msg_type::EXECUTION_REPORT, body));
+        });
+    }
+
+    /// Orders never rest, so there is nothing left to cancel
+    fn cancel_order(&self, message: &FixMessage) {
+        let orig_cl_ord_id = message.get(TAG_ORIG_CL_ORD_ID).unwrap_or("NONE");
+        let status = self.orders.lock().unwrap().get(orig_cl_ord_id).copied();
+
+        let (ord_status, reason, text) = match status {
+            Some(status) => (status, 0, "Too late to cancel, orders fill or reject immediately"),
+            None => (OrdStatus::Rejected, 1, "Unknown order"),
+        };
+
+        self.send(
+            msg_type::ORDER_CANCEL_REJECT,
+            FixBody::new()
+                .field(TAG_ORDER_ID, "NONE")
+                .field(TAG_CL_ORD_ID, message.get(TAG_CL_ORD_ID).unwrap_or("NONE"))
+                .field(TAG_ORIG_CL_ORD_ID, orig_cl_ord_id)
+                .field(TAG_ORD_STATUS, ord_status.code())
+                // Order Cancel Request
+                .field(TAG_CXL_REJ_RESPONSE_TO, 1)
+                .field(TAG_CXL_REJ_REASON, reason)
+                .field(TAG_TEXT, text),
+        );
+    }
+}
+
+/// Price the order off the oracle and place it as a one-way order
+async fn execute(state: &AppState, order: &FixOrder) -> Result<OrderFillResponse, ApiError> {
+    let symbol = symbols::canonicalize(&order.symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let oracle_price = state
+        .monitor
+        .get_cached_price(&symbol)
+        .await
+        .ok_or_else(|| ApiError::Oracle(format!("No price for {} yet", symbol)))?;
+
+    if !order.is_marketable(oracle_price) {
+        return Err(ApiError::BadRequest(format!(
+            "Limit {} is not marketable against the oracle price {}",
+            order.limit_price.unwrap_or_default(),
+            oracle_price
+        )));
+    }
+
+    let request = OpenPositionRequest {
+        owner: order.account.clone(),
+        symbol,
+        side: order.side,
+        size: order.qty,
+        leverage: order.leverage,
+        entry_price: oracle_price,
+        maintenance_margin_ratio: None,
+        max_slippage_bps: None,
+    };
+
+    let Json(fill) = place_order(State(state.clone()), PaperSession(None), Json(request)).await?;
+    Ok(fill)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn message_of(msg_type: &str, fields: &str) -> FixMessage {
+        let body = fields
+            .split('|')
+            .filter(|field| !field.is_empty())
+            .fold(FixBody::new(), |body, field| {
+                let (tag, value) = field.split_once('=').unwrap();
+                body.field(tag.parse().unwrap(), value)
+            });
+        let mut bytes = encode(msg_type, &body, "CLIENT", "PERPS", 1, Utc::now());
+        FixMessage::parse(&next_frame(&mut bytes).unwrap().unwrap()).unwrap()
+    }
+
+    fn message(fields: &str) -> FixMessage {
+        message_of(msg_type::NEW_ORDER_SINGLE, fields)
+    }
+
+    #[test]
+    fn test_parse_market_order() {
+        let order = FixOrder::parse(&message("11=ord-1|1=6z6EVx9ZVbHkZ3SmNuCmDEYvQmyUiQ2GtJxJ7KFQoYpz|55=SOL-USD|54=2|38=1.5|40=1|5001=10|")).unwrap();
+        assert_eq!(order.cl_ord_id, "ord-1");
+        assert_eq!(order.side, Side::Short);
+        assert_eq!(order.qty, dec!(1.5));
+        assert_eq!(order.limit_price, None);
+        assert_eq!(order.leverage, Some(10));
+        assert!(order.is_marketable(dec!(1_000_000)));
+    }
+
+    #[test]
+    fn test_limit_orders_fill_only_when_marketable() {
+        let buy = FixOrder::parse(&message("11=b|1=acct|55=SOL-USD|54=1|38=1|40=2|44=100|59=3|")).unwrap();
+        assert!(buy.is_marketable(dec!(99.5)));
+        assert!(!buy.is_marketable(dec!(100.5)));
+
+        let sell = FixOrder::parse(&message("11=s|1=acct|55=SOL-USD|54=2|38=1|40=2|44=100|")).unwrap();
+        assert!(sell.is_marketable(dec!(100.5)));
+        assert!(!sell.is_marketable(dec!(99.5)));
+    }
+
+    #[test]
+    fn test_parse_rejects_unsupported_orders() {
+        // Good-till-cancel limit would have to rest
+        assert!(FixOrder::parse(&message("11=a|1=acct|55=SOL-USD|54=1|38=1|40=2|44=100|59=1|")).is_err());
+        // Stop order
+        assert!(FixOrder::parse(&message("11=a|1=acct|55=SOL-USD|54=1|38=1|40=3|")).is_err());
+        // Sell short (5) isn't a side here
+        assert!(FixOrder::parse(&message("11=a|1=acct|55=SOL-USD|54=5|38=1|40=1|")).is_err());
+        assert!(FixOrder::parse(&message("11=a|1=acct|55=SOL-USD|54=1|38=0|40=1|")).is_err());
+        assert!(FixOrder::parse(&message("1=acct|55=SOL-USD|54=1|38=1|40=1|")).is_err());
+    }
+
+    #[test]
+    fn test_logon_checks() {
+        let config = FixGatewayConfig {
+            addr: "127.0.0.1:0".to_string(),
+            comp_id: "PERPS".to_string(),
+            password: Some(AdminToken::new("hunter2")),
+        };
+        let logon = |fields: &str| message_of(msg_type::LOGON, fields);
+
+        let (target, heartbeat) = accept_logon(&logon("98=0|108=1|141=Y|554=hunter2|"), &config).unwrap();
+        assert_eq!(target, "CLIENT");
+        // Clamped to the minimum
+        assert_eq!(heartbeat, Duration::from_secs(MIN_HEARTBEAT_SECS));
+
+        assert!(accept_logon(&logon("98=0|108=30|141=Y|554=wrong|"), &config).is_err());
+        assert!(accept_logon(&logon("98=0|141=Y|554=hunter2|"), &config).is_err());
+    }
+}
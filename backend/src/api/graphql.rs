@@ -0,0 +1,721 @@
+/// GraphQL API
+/// One endpoint for dashboards that would otherwise make several REST calls
+/// per page. Resolvers read the same monitor, position manager and history
+/// store as the REST handlers, and subscriptions bridge the broadcast channels
+/// behind the WebSocket stream. Decimals follow the REST precision rules
+use async_graphql::{
+    http::GraphiQLSource, Context, EmptyMutation, Error, Object, Result, Schema, SimpleObject,
+    Subscription,
+};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::{
+    response::{Html, IntoResponse},
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use futures::{future, Stream, StreamExt};
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+use crate::api::handlers::{AppState, MAX_PRICE_HISTORY_RANGE_HOURS, MAX_PRICE_HISTORY_TICKS};
+use crate::api::precision::{normalize, PCT_DP, PRICE_DP, RATIO_DP, SIZE_DP};
+use crate::domain::{symbols, Position, PositionStatus};
+use crate::infrastructure::{candles, parse_interval, Candle, ClosedPositionFilter};
+use crate::services::{
+    ApiToken, DomainEvent, EventKind, LiquidationAlert, MarginCalculator, PositionUpdate,
+    PriceUpdate,
+};
+
+pub const GRAPHQL_PATH: &str = "/graphql";
+pub const GRAPHQL_WS_PATH: &str = "/graphql/ws";
+
+// Bounds on what one query may ask for
+const MAX_QUERY_DEPTH: usize = 8;
+const MAX_QUERY_COMPLEXITY: usize = 2_000;
+const DEFAULT_LIST_LIMIT: i32 = 100;
+const MAX_LIST_LIMIT: i32 = 1_000;
+
+pub type PerpsSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+pub fn build_schema(state: AppState) -> PerpsSchema {
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(state)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+/// POST /graphql - Execute a query, the caller's API token scopes `User` fields
+pub async fn graphql_handler(
+    Extension(schema): Extension<PerpsSchema>,
+    api_token: Option<Extension<ApiToken>>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    let mut request = request.into_inner();
+    if let Some(Extension(api_token)) = api_token {
+        request = request.data(api_token);
+    }
+
+    schema.execute(request).await.into()
+}
+
+/// GET /graphql - GraphiQL explorer
+pub async fn graphiql() -> impl IntoResponse {
+    Html(
+        GraphiQLSource::build()
+            .endpoint(GRAPHQL_PATH)
+            .subscription_endpoint(GRAPHQL_WS_PATH)
+            .finish(),
+    )
+}
+
+fn state<'a>(ctx: &Context<'a>) -> &'a AppState {
+    ctx.data_unchecked::<AppState>()
+}
+
+/// Same rule as the REST routes under /users/:owner/: an API token only reads
+/// its own owner, and REQUIRE_API_TOKEN makes one mandatory
+fn authorize_owner(ctx: &Context<'_>, owner: &Pubkey) -> Result<()> {
+    match ctx.data_opt::<ApiToken>() {
+        Some(api_token) if api_token.owner == *owner => Ok(()),
+        Some(api_token) => Err(Error::new(format!("API token is scoped to {}", api_token.owner))),
+        None if state(ctx).require_api_token => Err(Error::new("API token required")),
+        None => Ok(()),
+    }
+}
+
+fn parse_pubkey(value: &str, what: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|e| Error::new(format!("Invalid {}: {}", what, e)))
+}
+
+fn list_limit(limit: Option<i32>) -> i64 {
+    limit.unwrap_or(DEFAULT_LIST_LIMIT).clamp(1, MAX_LIST_LIMIT) as i64
+}
+
+/// Canonical symbols to filter a stream on, none streams every symbol
+fn symbol_filter(requested: Option<Vec<String>>) -> Result<Option<HashSet<String>>> {
+    requested
+        .map(|requested| {
+            requested
+                .iter()
+                .map(|symbol| symbols::canonicalize(symbol).map_err(|e| Error::new(e.to_string())))
+                .collect()
+        })
+        .transpose()
+}
+
+/// Broadcast receiver as a stream, skipping what a slow subscriber missed
+fn broadcast_stream<T: Clone + Send + 'static>(receiver: broadcast::Receiver<T>) -> impl Stream<Item = T> {
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(item) => return Some((item, receiver)),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("GraphQL subscriber lagged, skipped {} messages", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// A user's account, open positions and position history
+    async fn user(&self, owner: String) -> Result<UserNode> {
+        Ok(UserNode(parse_pubkey(&owner, "owner")?))
+    }
+
+    /// A position the monitor tracks, or an evicted one from the history store
+    async fn position(&self, ctx: &Context<'_>, account: String) -> Result<Option<PositionNode>> {
+        let state = state(ctx);
+        let account = parse_pubkey(&account, "position account")?;
+
+        let position = match state.monitor.get_position(account).await {
+            Some(position) => Some(position),
+            None => match &state.history_store {
+                Some(history_store) => history_store.closed_position(&account).await?,
+                None => None,
+            },
+        };
+
+        if let Some(position) = &position {
+            authorize_owner(ctx, &position.owner)?;
+        }
+        Ok(position.map(PositionNode))
+    }
+
+    /// Positions the monitor tracks, optionally for one symbol or owner. An
+    /// API token only lists its own owner's
+    async fn positions(
+        &self,
+        ctx: &Context<'_>,
+        symbol: Option<String>,
+        owner: Option<String>,
+    ) -> Result<Vec<PositionNode>> {
+        let monitor = &state(ctx).monitor;
+
+        let mut owner = owner.map(|owner| parse_pubkey(&owner, "owner")).transpose()?;
+        if let Some(api_token) = ctx.data_opt::<ApiToken>() {
+            owner.get_or_insert(api_token.owner);
+        }
+        if let Some(owner) = &owner {
+            authorize_owner(ctx, owner)?;
+        }
+
+        let mut positions = match symbol {
+            Some(symbol) => {
+                let symbol = symbols::canonicalize(&symbol).map_err(|e| Error::new(e.to_string()))?;
+                monitor.get_positions_by_asset(&symbol).await
+            }
+            None => monitor.get_all_positions().await,
+        };
+        if let Some(owner) = owner {
+            positions.retain(|position| position.owner == owner);
+        }
+
+        Ok(positions.into_iter().map(PositionNode).collect())
+    }
+
+    /// Latest oracle price of every monitored symbol
+    async fn prices(&self, ctx: &Context<'_>) -> Vec<PriceNode> {
+        let monitor = &state(ctx).monitor;
+
+        let mut prices = Vec::new();
+        for symbol in monitor.get_monitored_symbols().await {
+            if let Some(cached) = monitor.get_cached_quote(&symbol).await {
+                prices.push(PriceNode::new(symbol, cached.quote.price, cached.quote.publish_time));
+            }
+        }
+        prices
+    }
+
+    async fn price(&self, ctx: &Context<'_>, symbol: String) -> Result<Option<PriceNode>> {
+        let symbol = symbols::canonicalize(&symbol).map_err(|e| Error::new(e.to_string()))?;
+        let cached = state(ctx).monitor.get_cached_quote(&symbol).await;
+
+        Ok(cached.map(|cached| PriceNode::new(symbol, cached.quote.price, cached.quote.publish_time)))
+    }
+
+    /// OHLC candles built from archived oracle ticks, `interval` like "1m" or
+    /// "1h". Defaults to the last hour, at most 24h per query
+    async fn candles(
+        &self,
+        ctx: &Context<'_>,
+        symbol: String,
+        interval: String,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<CandleNode>> {
+        let state = state(ctx);
+        let history_store = state
+            .history_store
+            .as_ref()
+            .ok_or_else(|| Error::new("History store not configured"))?;
+
+        let symbol = symbols::canonicalize(&symbol).map_err(|e| Error::new(e.to_string()))?;
+        let interval = parse_interval(&interval)?;
+
+        let to = to.unwrap_or_else(Utc::now);
+        let from = from.unwrap_or(to - chrono::Duration::hours(1));
+        if from >= to {
+            return Err(Error::new("from must be before to"));
+        }
+        if to - from > chrono::Duration::hours(MAX_PRICE_HISTORY_RANGE_HOURS) {
+            return Err(Error::new(format!(
+                "Range must be at most {}h",
+                MAX_PRICE_HISTORY_RANGE_HOURS
+            )));
+        }
+
+        let ticks = history_store
+            .symbol_price_ticks(&symbol, from, to, MAX_PRICE_HISTORY_TICKS)
+            .await?;
+
+        Ok(candles(&ticks, interval).into_iter().map(CandleNode::from).collect())
+    }
+
+    /// Liquidated positions from the history store, newest first
+    async fn liquidations(
+        &self,
+        ctx: &Context<'_>,
+        symbol: Option<String>,
+        limit: Option<i32>,
+    ) -> Result<Vec<PositionNode>> {
+        let history_store = state(ctx)
+            .history_store
+            .as_ref()
+            .ok_or_else(|| Error::new("History store not configured"))?;
+
+        let filter = ClosedPositionFilter {
+            owner: None,
+            symbol: symbol
+                .map(|symbol| symbols::canonicalize(&symbol))
+                .transpose()
+                .map_err(|e| Error::new(e.to_string()))?,
+            status: Some(PositionStatus::Liquidated),
+            before: None,
+        };
+        let positions = history_store.closed_positions(&filter, list_limit(limit)).await?;
+
+        Ok(positions.into_iter().map(PositionNode).collect())
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Oracle price updates, optionally for some symbols
+    async fn prices(
+        &self,
+        ctx: &Context<'_>,
+        symbols: Option<Vec<String>>,
+    ) -> Result<impl Stream<Item = PriceNode>> {
+        let filter = symbol_filter(symbols)?;
+        let state = state(ctx);
+        let receiver = state.event_bus.receiver(EventKind::Price);
+
+        Ok(broadcast_stream(receiver)
+            .filter_map(|event| {
+                future::ready(match event {
+                    DomainEvent::Price(update) => Some(update),
+                    _ => None,
+                })
+            })
+            .filter(move |update: &PriceUpdate| {
+                future::ready(filter.as_ref().is_none_or(|symbols| symbols.contains(&update.symbol)))
+            })
+            .map(|update| {
+                let published_at = update.publish_time.unwrap_or(update.timestamp);
+                PriceNode::new(update.symbol, update.price, published_at)
+            }))
+    }
+
+    /// PnL updates from each monitor pass, optionally for some symbols or positions
+    async fn position_updates(
+        &self,
+        ctx: &Context<'_>,
+        symbols: Option<Vec<String>>,
+        position_accounts: Option<Vec<String>>,
+    ) -> Result<impl Stream<Item = PositionUpdateNode>> {
+        let filter = symbol_filter(symbols)?;
+        let accounts: Option<HashSet<Pubkey>> = position_accounts
+            .map(|accounts| {
+                accounts
+                    .iter()
+                    .map(|account| parse_pubkey(account, "position account"))
+                    .collect()
+            })
+            .transpose()?;
+
+        let state = state(ctx);
+        let receiver = state.event_bus.receiver(EventKind::Position);
+
+        Ok(broadcast_stream(receiver)
+            .filter_map(|event| {
+                future::ready(match event {
+                    DomainEvent::Position(update) => Some(update),
+                    _ => None,
+                })
+            })
+            .filter(move |update: &PositionUpdate| {
+                future::ready(
+                    filter.as_ref().is_none_or(|symbols| symbols.contains(&update.symbol))
+                        && accounts
+                            .as_ref()
+                            .is_none_or(|accounts| accounts.contains(&update.position_account)),
+                )
+            })
+            .map(PositionUpdateNode))
+    }
+
+    /// Positions nearing or crossing their liquidation price
+    async fn liquidation_alerts(
+        &self,
+        ctx: &Context<'_>,
+        symbols: Option<Vec<String>>,
+    ) -> Result<impl Stream<Item = LiquidationAlertNode>> {
+        let filter = symbol_filter(symbols)?;
+        let state = state(ctx);
+        let receiver = state.event_bus.receiver(EventKind::Liquidation);
+
+        Ok(broadcast_stream(receiver)
+            .filter_map(|event| {
+                future::ready(match event {
+                    DomainEvent::Liquidation(alert) => Some(alert),
+                    _ => None,
+                })
+            })
+            .filter(move |alert: &LiquidationAlert| {
+                future::ready(filter.as_ref().is_none_or(|symbols| symbols.contains(&alert.symbol)))
+            })
+            .map(LiquidationAlertNode))
+    }
+}
+
+/// A wallet, its fields need the owner's API token when one is required
+pub struct UserNode(Pubkey);
+
+#[Object(name = "User")]
+impl UserNode {
+    async fn owner(&self) -> String {
+        self.0.to_string()
+    }
+
+    /// On-chain user account
+    async fn account(&self, ctx: &Context<'_>) -> Result<UserAccountNode> {
+        authorize_owner(ctx, &self.0)?;
+        let account = state(ctx).position_manager.get_user_account(&self.0).await?;
+
+        Ok(UserAccountNode {
+            total_collateral: account.total_collateral,
+            locked_collateral: account.locked_collateral,
+            available_collateral: account.total_collateral - account.locked_collateral,
+            total_pnl: account.total_pnl,
+            position_count: account.position_count,
+            position_count_total: account.position_count_total,
+        })
+    }
+
+    /// Positions the monitor tracks for this owner
+    async fn positions(&self, ctx: &Context<'_>) -> Result<Vec<PositionNode>> {
+        authorize_owner(ctx, &self.0)?;
+        let positions = state(ctx).monitor.get_user_positions(&self.0).await?;
+
+        Ok(positions.into_iter().map(PositionNode).collect())
+    }
+
+    /// Closed and liquidated positions from the history store, newest first
+    async fn closed_positions(&self, ctx: &Context<'_>, limit: Option<i32>) -> Result<Vec<PositionNode>> {
+        authorize_owner(ctx, &self.0)?;
+        let history_store = state(ctx)
+            .history_store
+            .as_ref()
+            .ok_or_else(|| Error::new("History store not configured"))?;
+
+        let filter = ClosedPositionFilter {
+            owner: Some(self.0),
+            symbol: None,
+            status: None,
+            before: None,
+        };
+        let positions = history_store.closed_positions(&filter, list_limit(limit)).await?;
+
+        Ok(positions.into_iter().map(PositionNode).collect())
+    }
+
+    /// Off-platform positions the owner imported, marked at cached oracle prices
+    async fn external_positions(&self, ctx: &Context<'_>) -> Result<Vec<ExternalPositionNode>> {
+        authorize_owner(ctx, &self.0)?;
+        let monitor = &state(ctx).monitor;
+        let positions = monitor.external_positions().list(&self.0).await?;
+
+        let mut nodes = Vec::with_capacity(positions.len());
+        for position in positions {
+            let mark_price = monitor.get_cached_price(&position.symbol).await;
+            nodes.push(ExternalPositionNode {
+                id: position.id.to_string(),
+                symbol: position.symbol.clone(),
+                side: format!("{:?}", position.side),
+                size: normalize(position.size, SIZE_DP),
+                entry_price: normalize(position.entry_price, PRICE_DP),
+                mark_price: mark_price.map(|price| normalize(price, PRICE_DP)),
+                unrealized_pnl: mark_price
+                    .and_then(|price| position.unrealized_pnl(price).ok())
+                    .map(|pnl| normalize(pnl, PRICE_DP)),
+                venue: position.venue,
+                imported_at: position.imported_at,
+            });
+        }
+
+        Ok(nodes)
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "ExternalPosition")]
+pub struct ExternalPositionNode {
+    id: String,
+    symbol: String,
+    side: String,
+    size: Decimal,
+    entry_price: Decimal,
+    /// Null when the symbol has no price feed
+    mark_price: Option<Decimal>,
+    unrealized_pnl: Option<Decimal>,
+    venue: Option<String>,
+    imported_at: DateTime<Utc>,
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "UserAccount")]
+pub struct UserAccountNode {
+    total_collateral: u64,
+    locked_collateral: u64,
+    available_collateral: u64,
+    total_pnl: i64,
+    position_count: u32,
+    position_count_total: u32,
+}
+
+pub struct PositionNode(Position);
+
+#[Object(name = "Position")]
+impl PositionNode {
+    async fn position_account(&self) -> String {
+        self.0.position_account.to_string()
+    }
+
+    async fn owner(&self) -> String {
+        self.0.owner.to_string()
+    }
+
+    /// The owner, for nested account and position queries
+    async fn user(&self) -> UserNode {
+        UserNode(self.0.owner)
+    }
+
+    async fn symbol(&self) -> &str {
+        &self.0.symbol
+    }
+
+    /// "Long" or "Short"
+    async fn side(&self) -> String {
+        format!("{:?}", self.0.side)
+    }
+
+    async fn size(&self) -> Decimal {
+        normalize(self.0.size, SIZE_DP)
+    }
+
+    async fn entry_price(&self) -> Decimal {
+        normalize(self.0.entry_price, PRICE_DP)
+    }
+
+    async fn mark_price(&self) -> Decimal {
+        normalize(self.0.mark_price, PRICE_DP)
+    }
+
+    async fn margin(&self) -> Decimal {
+        normalize(self.0.margin, PRICE_DP)
+    }
+
+    async fn leverage(&self) -> u16 {
+        self.0.leverage
+    }
+
+    async fn unrealized_pnl(&self) -> Decimal {
+        normalize(self.0.unrealized_pnl, PRICE_DP)
+    }
+
+    async fn realized_pnl(&self) -> Decimal {
+        normalize(self.0.realized_pnl, PRICE_DP)
+    }
+
+    async fn funding_accrued(&self) -> Decimal {
+        normalize(self.0.funding_accrued, PRICE_DP)
+    }
+
+    async fn liquidation_price(&self) -> Decimal {
+        normalize(self.0.liquidation_price, PRICE_DP)
+    }
+
+    async fn effective_liquidation_price(&self) -> Option<Decimal> {
+        self.0
+            .effective_liquidation_price
+            .map(|price| normalize(price, PRICE_DP))
+    }
+
+    async fn notional_value(&self) -> Decimal {
+        normalize(self.0.size * self.0.mark_price, PRICE_DP)
+    }
+
+    /// Unrealized PnL over margin while open, realized once closed
+    async fn roi_pct(&self) -> Option<Decimal> {
+        let pnl = if self.0.is_open() {
+            self.0.unrealized_pnl
+        } else {
+            self.0.realized_pnl
+        };
+
+        MarginCalculator::calculate_roi(pnl, self.0.margin)
+            .ok()
+            .map(|roi| normalize(roi, PCT_DP))
+    }
+
+    async fn status(&self) -> String {
+        format!("{:?}", self.0.status)
+    }
+
+    async fn opened_at(&self) -> DateTime<Utc> {
+        self.0.opened_at
+    }
+
+    async fn last_update(&self) -> DateTime<Utc> {
+        self.0.last_update
+    }
+
+    async fn closed_at(&self) -> Option<DateTime<Utc>> {
+        self.0.closed_at
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Price")]
+pub struct PriceNode {
+    symbol: String,
+    price: Decimal,
+    /// Oracle publish time
+    timestamp: DateTime<Utc>,
+    /// Milliseconds since publish
+    age_ms: i64,
+}
+
+impl PriceNode {
+    fn new(symbol: String, price: Decimal, published_at: DateTime<Utc>) -> Self {
+        Self {
+            symbol,
+            price: normalize(price, PRICE_DP),
+            timestamp: published_at,
+            age_ms: (Utc::now() - published_at).num_milliseconds(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Candle")]
+pub struct CandleNode {
+    start: DateTime<Utc>,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    /// Oracle ticks in the candle
+    ticks: u32,
+}
+
+impl From<Candle> for CandleNode {
+    fn from(candle: Candle) -> Self {
+        Self {
+            start: candle.start,
+            open: normalize(candle.open, PRICE_DP),
+            high: normalize(candle.high, PRICE_DP),
+            low: normalize(candle.low, PRICE_DP),
+            close: normalize(candle.close, PRICE_DP),
+            ticks: candle.ticks,
+        }
+    }
+}
+
+pub struct PositionUpdateNode(PositionUpdate);
+
+#[Object(name = "PositionUpdate")]
+impl PositionUpdateNode {
+    async fn position_account(&self) -> String {
+        self.0.position_account.to_string()
+    }
+
+    async fn symbol(&self) -> &str {
+        &self.0.symbol
+    }
+
+    async fn side(&self) -> String {
+        format!("{:?}", self.0.side)
+    }
+
+    async fn size(&self) -> Decimal {
+        normalize(self.0.size, SIZE_DP)
+    }
+
+    async fn mark_price(&self) -> Decimal {
+        normalize(self.0.mark_price, PRICE_DP)
+    }
+
+    async fn unrealized_pnl(&self) -> Decimal {
+        normalize(self.0.unrealized_pnl, PRICE_DP)
+    }
+
+    /// Floored at 0, `marginDeficit` carries how far under it is
+    async fn margin_ratio(&self) -> Decimal {
+        normalize(self.0.margin_ratio.max(Decimal::ZERO), RATIO_DP)
+    }
+
+    async fn margin_deficit(&self) -> Decimal {
+        normalize(
+            MarginCalculator::calculate_margin_deficit(self.0.margin, self.0.unrealized_pnl),
+            PRICE_DP,
+        )
+    }
+
+    async fn timestamp(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+}
+
+pub struct LiquidationAlertNode(LiquidationAlert);
+
+#[Object(name = "LiquidationAlert")]
+impl LiquidationAlertNode {
+    async fn position_account(&self) -> String {
+        self.0.position_account.to_string()
+    }
+
+    async fn symbol(&self) -> &str {
+        &self.0.symbol
+    }
+
+    async fn side(&self) -> String {
+        format!("{:?}", self.0.side)
+    }
+
+    async fn liquidation_price(&self) -> Decimal {
+        normalize(self.0.liquidation_price, PRICE_DP)
+    }
+
+    async fn current_price(&self) -> Decimal {
+        normalize(self.0.current_price, PRICE_DP)
+    }
+
+    /// Risk level, as `risk_type` in the WebSocket alert
+    async fn risk(&self) -> String {
+        format!("{:?}", self.0.risk_type)
+    }
+
+    async fn margin_call_deadline(&self) -> Option<DateTime<Utc>> {
+        self.0.margin_call_deadline
+    }
+
+    /// Null when the position is no longer monitored
+    async fn owner(&self) -> Option<String> {
+        self.0.owner.map(|owner| owner.to_string())
+    }
+
+    /// Adverse move left to the liquidation price, negative once past it
+    async fn distance_to_liquidation_pct(&self) -> Option<Decimal> {
+        self.0
+            .distance_to_liquidation_pct()
+            .map(|distance| normalize(distance, PCT_DP))
+    }
+
+    /// Margin lost if liquidated at the liquidation price
+    async fn estimated_loss(&self) -> Option<Decimal> {
+        self.0.estimated_loss.map(|loss| normalize(loss, PRICE_DP))
+    }
+
+    /// Set on MarginWarning alerts
+    async fn margin_ratio(&self) -> Option<Decimal> {
+        self.0.margin_ratio.map(|ratio| normalize(ratio, RATIO_DP))
+    }
+
+    /// Multiple of the maintenance ratio the margin ratio fell below
+    async fn margin_ratio_tier(&self) -> Option<Decimal> {
+        self.0.margin_ratio_tier
+    }
+}
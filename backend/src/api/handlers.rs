@@ -1,20 +1,223 @@
 use axum::{
-    extract::{Path, Query, State},
+    body::Body,
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
+use chrono::Datelike;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
 
-use crate::api::{dto::*, errors::ApiError};
-use crate::services::{PositionManager, PositionMonitor};
-use rust_decimal::Decimal;
+use crate::api::cursor::{Cursor, CursorSigner};
+use crate::api::admin::{Admin, AdminToken};
+use crate::api::api_token::authorize_owner;
+use crate::api::precision::{PRICE_DP, SIZE_DP};
+use crate::api::tenant::CurrentTenant;
+use crate::api::ws_rooms::WsRooms;
+use crate::api::{dto::*, errors::ApiError, paper::PaperSession, validation};
+use crate::domain::{max_notional_for_leverage, symbols, LEVERAGE_TIERS, MarginMode, PositionStatus, Side};
+use crate::error::FieldError;
+use crate::infrastructure::{
+    last_tick_per_bucket, Chain, parse_interval, ClosedPositionFilter, HistoryStore, SimulatedPriceSource,
+};
+use crate::services::{
+    replay_trade, TradeSimulationParams, build_ladder, build_position_timeline, build_tax_lots, clear_external_positions_message, close_user_message, close_strategy_message, delete_strategy_message, put_strategy_message, collateral_to_withdraw, create_position_alert_message, delete_position_alert_message, import_external_positions_message, consolidate_exposure, create_price_alert_message, create_token_message, delete_price_alert_message, update_price_alert_message, parse_external_positions_csv, purge_user_data, revoke_token_message, set_risk_limits_message, vault_return, verify_wallet_signature,
+    taker_fee, taker_fee_bps, taker_volumes, year_range, ApiToken, Tenants, ApiTokenService, BackfillProgress, BackfillService, HistoryRetentionService, PruneRun, TickRetention, BackstopVaultService, DemoBootstrap, DemoBootstrapConfig, EventBus, ExternalPosition, MarginCalculator, PaperLedger,
+    DynamicMarginService, FeeTierProgress, Outbox, PositionManager, PositionMonitor, RiskLimitService, RiskLimits, RiskUtilization, TradingHalt,
+    PriceAlert, PriceAlertSpec, SizingModel, Strategy, SymbolOverrides, DEFAULT_API_TOKEN_TTL_SECS, MAX_API_TOKENS_PER_OWNER, MAX_PRICE_ALERTS_PER_OWNER, WALLET_SIGNATURE_HEADER, WALLET_TIMESTAMP_HEADER,
+    MAX_RULES_PER_POSITION, MAX_STRATEGIES_PER_USER, FEE_TIER_COUNT, FEE_TIER_WINDOW_DAYS, TAX_CSV_HEADER,
+};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
+use uuid::Uuid;
+
+// Widest window and most ticks one price history request reads
+pub(crate) const MAX_PRICE_HISTORY_RANGE_HOURS: i64 = 24;
+pub(crate) const MAX_PRICE_HISTORY_TICKS: i64 = 100_000;
+// Points in a trade simulation's PnL path
+const MAX_PNL_PATH_POINTS: i64 = 10_000;
+
+// Candles the sizing suggestion measures volatility over
+const SIZING_LOOKBACK_MINUTES: i64 = 60;
+
+// Liquidation map buckets per side, by default and at most
+const DEFAULT_LIQUIDATION_MAP_BUCKETS: usize = 50;
+const MAX_LIQUIDATION_MAP_BUCKETS: usize = 500;
 
 /// Application state shared across handlers
 #[derive(Clone)]
 pub struct AppState {
     pub monitor: Arc<PositionMonitor>,
     pub position_manager: Arc<PositionManager>,
+    /// Chain the program is deployed on, selected by CHAIN
+    pub chain: Arc<dyn Chain>,
+    /// Redis fan-out for WebSocket streams when running multiple replicas
+    pub event_bus: Arc<dyn EventBus>,
+    /// Per-symbol WebSocket rooms, fed from the event bus or the monitor
+    pub ws_rooms: Arc<WsRooms>,
+    /// Postgres history, none when DATABASE_URL is not set
+    pub history_store: Option<Arc<dyn HistoryStore>>,
+    /// Sandbox ledger for requests carrying x-paper-session, none when paper trading is off
+    pub paper_ledger: Option<Arc<PaperLedger>>,
+    /// Signs and verifies `next_cursor` tokens of paginated history endpoints
+    pub cursor_signer: Arc<CursorSigner>,
+    /// Backstop vault NAV, none when BACKSTOP_VAULT_ENABLED is off
+    pub backstop_vault: Option<Arc<BackstopVaultService>>,
+    /// Market maintenance crank, none when DYNAMIC_MARGIN_ENABLED is off
+    pub dynamic_margin: Option<Arc<DynamicMarginService>>,
+    /// Rebuilds history from program transactions, none without a history store
+    pub backfill: Option<Arc<BackfillService>>,
+    /// Prunes price ticks and PnL snapshots, none without a history store
+    pub history_retention: Option<Arc<HistoryRetentionService>>,
+    /// Populates demo users and positions, none unless DEMO_BOOTSTRAP_ENABLED
+    pub demo_bootstrap: Option<Arc<DemoBootstrap>>,
+    /// Prices injected over the monitor's oracle, none unless PRICE_SIMULATION_ENABLED
+    pub price_simulator: Option<Arc<SimulatedPriceSource>>,
+    /// Redis Stream of domain events, none unless OUTBOX_ENABLED
+    pub outbox: Option<Arc<Outbox>>,
+    /// Kill switch consulted before trade-mutating calls
+    pub trading_halt: Arc<TradingHalt>,
+    /// Users' collateral-at-risk and leverage limits, some block opens
+    pub risk_limits: Arc<RiskLimitService>,
+    /// White-label operators from TENANTS_FILE, with the default tenant's
+    /// fee tier thresholds
+    pub tenants: Arc<Tenants>,
+    /// Guards /admin routes, none disables them
+    pub admin_token: Option<Arc<AdminToken>>,
+    /// Owner-scoped read-only tokens, checked by `api_token::authorize`
+    pub api_tokens: Arc<ApiTokenService>,
+    /// Reads under /users/:id/ need an API token
+    pub require_api_token: bool,
+}
+
+impl AppState {
+    fn history_store(&self) -> Result<&Arc<dyn HistoryStore>, ApiError> {
+        self.history_store
+            .as_ref()
+            .ok_or_else(|| ApiError::InternalError("History store not configured".to_string()))
+    }
+
+    fn decode_cursor(&self, scope: &str, cursor: Option<&str>) -> Result<Option<Cursor>, ApiError> {
+        cursor
+            .map(|token| {
+                self.cursor_signer
+                    .decode(scope, token)
+                    .ok_or_else(|| ApiError::BadRequest("Invalid cursor".to_string()))
+            })
+            .transpose()
+    }
+
+    fn paper_ledger(&self) -> Result<&Arc<PaperLedger>, ApiError> {
+        self.paper_ledger
+            .as_ref()
+            .ok_or_else(|| ApiError::BadRequest("Paper trading is not enabled".to_string()))
+    }
+
+    /// Refuse on-chain trades while halted, paper sessions are unaffected
+    fn ensure_trading(&self) -> Result<(), ApiError> {
+        match self.trading_halt.status().reason {
+            Some(reason) => Err(ApiError::TradingHalted(format!("Trading is halted: {}", reason))),
+            None => Ok(()),
+        }
+    }
+
+    /// Refuse opening `size` more on `symbol` past its open interest cap
+    async fn ensure_open_interest(&self, symbol: &str, size: Decimal) -> Result<(), ApiError> {
+        let Some(max_open_interest) = self.monitor.symbol_configs().settings(symbol).max_open_interest else {
+            return Ok(());
+        };
+
+        let open_interest = self.monitor.open_interest(symbol).await;
+        if open_interest + size > max_open_interest {
+            return Err(ApiError::Validation(vec![FieldError {
+                field: "size",
+                message: format!(
+                    "{} open interest is {} of a {} cap, at most {} more can open",
+                    symbol,
+                    open_interest,
+                    max_open_interest,
+                    (max_open_interest - open_interest).max(Decimal::ZERO)
+                ),
+            }]));
+        }
+
+        Ok(())
+    }
+
+    /// Refuse a trade that would leave `owner` over a risk limit they set to
+    /// block opens. `after` applies the trade to their current utilization
+    async fn ensure_risk_limits(
+        &self,
+        owner: &Pubkey,
+        after: impl FnOnce(RiskUtilization) -> RiskUtilization,
+    ) -> Result<(), ApiError> {
+        let limits = match self.risk_limits.get(owner).await {
+            Ok(Some(limits)) if limits.block_opens => limits,
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                return Err(ApiError::InternalError(format!("Failed to read risk limits: {}", e)))
+            }
+        };
+
+        let positions = self.monitor.get_user_positions(owner).await?;
+        let user_account = self.position_manager.get_user_account(owner).await?;
+        let utilization = after(RiskUtilization::measure(&positions, user_account.total_collateral));
+
+        let breaches = limits.breaches(*owner, &utilization, chrono::Utc::now());
+        if breaches.is_empty() {
+            return Ok(());
+        }
+
+        Err(ApiError::Validation(
+            breaches
+                .iter()
+                .map(|breach| FieldError {
+                    field: "size",
+                    message: format!("Over your risk limits after this trade: {}", breach.message()),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Fill the leverage and slippage an open request left out from the
+    /// owner's on-chain settings. Unreadable settings leave the API defaults
+    async fn apply_user_defaults(&self, request: &mut OpenPositionRequest) {
+        if request.leverage.is_some() && request.max_slippage_bps.is_some() {
+            return;
+        }
+        let Ok(owner) = Pubkey::from_str(&request.owner) else {
+            return;
+        };
+
+        match self.position_manager.get_user_settings(&owner).await {
+            Ok(Some(settings)) => {
+                request.leverage.get_or_insert(settings.default_leverage);
+                request
+                    .max_slippage_bps
+                    .get_or_insert(u32::from(settings.default_slippage_bps));
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to read settings for {}: {}", owner, e),
+        }
+    }
+
+    fn backstop_vault(&self) -> Result<&Arc<BackstopVaultService>, ApiError> {
+        self.backstop_vault
+            .as_ref()
+            .ok_or_else(|| ApiError::BadRequest("Backstop vault is not enabled".to_string()))
+    }
+}
+
+/// Page size of cursor-paginated history endpoints
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Stands in for a transaction signature in paper trading responses
+fn paper_signature() -> String {
+    format!("paper-{}", Uuid::new_v4())
 }
 
 /// GET /health - Health check
@@ -25,22 +228,121 @@ pub async fn health_check() -> Json<serde_json::Value> {
     }))
 }
 
+/// GET /halt - Whether trading is halted and why
+pub async fn get_trading_halt(State(state): State<AppState>) -> Json<TradingHaltDto> {
+    Json(TradingHaltDto::from(state.trading_halt.status()))
+}
+
+/// GET /tenant - The tenant of the request's `X-Tenant-Key`, the default one without
+pub async fn get_tenant(
+    State(state): State<AppState>,
+    tenant: CurrentTenant,
+) -> Result<Json<TenantDto>, ApiError> {
+    let fee_tier_volumes = state.tenants.fee_tier_volumes(&tenant.0).to_vec();
+    let Some(config) = state.tenants.get(&tenant.0) else {
+        return Ok(Json(TenantDto {
+            id: tenant.0,
+            name: None,
+            fee_tier_volumes,
+            webhooks: Vec::new(),
+            users: None,
+        }));
+    };
+
+    let users = state
+        .tenants
+        .owners(&config.id)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch tenant users: {}", e)))?
+        .len();
+
+    Ok(Json(TenantDto {
+        id: config.id.clone(),
+        name: Some(config.name.clone()),
+        fee_tier_volumes,
+        webhooks: config.webhooks.iter().map(TenantWebhookDto::from).collect(),
+        users: Some(users),
+    }))
+}
+
+/// GET /ready - Readiness probe, ready once state is restored from a snapshot or synced from chain
+/// and at least one RPC endpoint is up
+pub async fn readiness_check(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<ReadinessDto>) {
+    let now = chrono::Utc::now();
+    let sync_status = state.monitor.sync_status().await;
+    let rpc_healthy = state.monitor.rpc_healthy();
+    let ready = sync_status.is_ready(now, state.monitor.max_sync_lag()) && rpc_healthy;
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(ReadinessDto {
+            ready,
+            chain: state.chain.kind().to_string(),
+            chain_synced: sync_status.last_chain_sync.is_some(),
+            last_chain_sync: sync_status.last_chain_sync,
+            snapshot_age_secs: sync_status
+                .snapshot_taken_at
+                .map(|taken_at| (now - taken_at).num_seconds()),
+            sync_lag_secs: sync_status.lag(now).map(|lag| lag.num_seconds()),
+            scan_progress_pct: sync_status.scan_progress.map(|progress| progress.pct()),
+            rpc_healthy,
+            rpc_endpoints: state
+                .monitor
+                .rpc_endpoints()
+                .into_iter()
+                .map(RpcEndpointHealthDto::from)
+                .collect(),
+        }),
+    )
+}
+
 /// GET /positions - List all positions
+/// Closed and liquidated positions evicted from the monitor are read from the history store
+/// With x-paper-session, lists the session's simulated positions instead
 pub async fn list_positions(
     State(state): State<AppState>,
+    PaperSession(session): PaperSession,
+    api_token: Option<Extension<ApiToken>>,
     Query(query): Query<ListPositionsQuery>,
 ) -> Result<Json<Vec<PositionDto>>, ApiError> {
-    let mut positions = if let Some(symbol) = query.symbol {
-        state.monitor.get_positions_by_asset(&symbol).await
+    let mut positions = if let Some(session) = &session {
+        let mut positions = state.paper_ledger()?.get_positions(session).await;
+        if let Some(symbol) = &query.symbol {
+            let symbol = symbols::normalize(symbol);
+            positions.retain(|p| p.symbol == symbol);
+        }
+        positions.sort_by_key(|p| p.position_index);
+        positions
+    } else if let Some(symbol) = &query.symbol {
+        state.monitor.get_positions_by_asset(symbol).await
     } else {
         state.monitor.get_all_positions().await
     };
 
+    let mut owner = query
+        .owner
+        .as_deref()
+        .and_then(|owner| owner.parse::<Pubkey>().ok());
+
+    // An API token only lists its own owner's positions
+    if let Some(api_token) = &api_token {
+        owner.get_or_insert(api_token.owner);
+    }
+    if let Some(owner) = &owner {
+        authorize_owner(&state, api_token.as_deref(), owner)?;
+    }
+
     // Filter by owner if provided
-    if let Some(owner_str) = query.owner {
-        if let Ok(owner_pubkey) = owner_str.parse::<solana_sdk::pubkey::Pubkey>() {
-            positions.retain(|p| p.owner == owner_pubkey);
-        }
+    if let Some(owner_pubkey) = owner {
+        positions.retain(|p| p.owner == owner_pubkey);
     }
 
     // Filter by status if provided
@@ -52,6 +354,39 @@ pub async fn list_positions(
     let offset = query.offset.unwrap_or(0);
     let limit = query.limit.unwrap_or(100).min(1000); // Max 1000
 
+    let closed_status = matches!(
+        query.status,
+        Some(PositionStatus::Closed | PositionStatus::Liquidated)
+    );
+
+    let history_store = state
+        .history_store
+        .as_ref()
+        .filter(|_| closed_status && session.is_none());
+
+    if let Some(history_store) = history_store {
+        let filter = ClosedPositionFilter {
+            owner,
+            symbol: query.symbol.as_deref().map(symbols::normalize),
+            status: query.status,
+            ..Default::default()
+        };
+
+        let stored = history_store
+            .closed_positions(&filter, (offset + limit) as i64)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to fetch closed positions: {}", e)))?;
+
+        // A position can be in both while an eviction is in flight
+        let cached: HashSet<Pubkey> = positions.iter().map(|p| p.position_account).collect();
+        positions.extend(
+            stored
+                .into_iter()
+                .filter(|p| !cached.contains(&p.position_account)),
+        );
+        positions.sort_by_key(|p| Reverse(p.closed_at));
+    }
+
     let paginated: Vec<PositionDto> = positions
         .into_iter()
         .skip(offset)
@@ -76,126 +411,1889 @@ pub async fn get_position(
     Ok(Json(PositionDto::from(position)))
 }
 
-/// GET /positions/by-asset/:symbol - Get positions for specific asset
-pub async fn get_positions_by_asset(
+/// POST /positions/:id/alerts - Add an alert rule to an open position,
+/// authorized by the position owner's wallet signature
+pub async fn create_position_alert(
     State(state): State<AppState>,
-    Path(symbol): Path<String>,
-) -> Result<Json<Vec<PositionDto>>, ApiError> {
-    let positions = state.monitor.get_positions_by_asset(&symbol).await;
+    Path(id): Path<Pubkey>,
+    Json(payload): Json<CreatePositionAlertRequest>,
+) -> Result<Json<PositionAlertRuleDto>, ApiError> {
+    validation::validate_alert_condition(&payload.condition).map_err(ApiError::Validation)?;
 
-    let dtos: Vec<PositionDto> = positions
+    let position = state
+        .monitor
+        .get_position(id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Position {} not found", id)))?;
+
+    if !position.is_open() {
+        return Err(ApiError::BadRequest(format!("Position {} is not open", id)));
+    }
+
+    let message = create_position_alert_message(&id, payload.timestamp);
+    verify_owner_signature(&state, &position.owner, &message, &payload.signature, payload.timestamp).await?;
+
+    let alerts = state.monitor.position_alerts();
+    if alerts
+        .rules_for(&id)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch alert rules: {}", e)))?
+        .len()
+        >= MAX_RULES_PER_POSITION
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Position {} already has {} alert rules",
+            id, MAX_RULES_PER_POSITION
+        )));
+    }
+
+    let rule = alerts
+        .add_rule(id, payload.condition)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to add alert rule: {}", e)))?;
+
+    Ok(Json(PositionAlertRuleDto::from(rule)))
+}
+
+/// GET /positions/:id/alerts - List a position's alert rules
+pub async fn list_position_alerts(
+    State(state): State<AppState>,
+    Path(id): Path<Pubkey>,
+) -> Result<Json<Vec<PositionAlertRuleDto>>, ApiError> {
+    let rules = state
+        .monitor
+        .position_alerts()
+        .rules_for(&id)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch alert rules: {}", e)))?;
+
+    Ok(Json(rules.into_iter().map(PositionAlertRuleDto::from).collect()))
+}
+
+/// DELETE /positions/:id/alerts/:alert_id - Remove an alert rule, authorized
+/// by the position owner's wallet signature. Returns the removed rule
+pub async fn delete_position_alert(
+    State(state): State<AppState>,
+    Path((id, alert_id)): Path<(Pubkey, Uuid)>,
+    Json(payload): Json<DeletePositionAlertRequest>,
+) -> Result<Json<PositionAlertRuleDto>, ApiError> {
+    // Rules outlive the position in memory until it's evicted, the chain
+    // still knows whose it was
+    let owner = match state.monitor.get_position(id).await {
+        Some(position) => position.owner,
+        None => {
+            state
+                .position_manager
+                .get_position(id)
+                .await
+                .map_err(|_| ApiError::NotFound(format!("Position {} not found", id)))?
+                .owner
+        }
+    };
+    let message = delete_position_alert_message(&id, alert_id, payload.timestamp);
+    verify_owner_signature(&state, &owner, &message, &payload.signature, payload.timestamp).await?;
+
+    let rule = state
+        .monitor
+        .position_alerts()
+        .remove_rule(&id, alert_id)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to remove alert rule: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("Alert rule {} not found", alert_id)))?;
+
+    Ok(Json(PositionAlertRuleDto::from(rule)))
+}
+
+/// Validate a price alert request and convert it to what the service stores
+async fn price_alert_spec(
+    state: &AppState,
+    request: PriceAlertRequest,
+) -> Result<PriceAlertSpec, ApiError> {
+    let known_symbols = state.monitor.get_monitored_symbols().await;
+    validation::validate_price_alert(&request, &known_symbols).map_err(ApiError::Validation)?;
+
+    Ok(PriceAlertSpec {
+        symbol: symbols::canonicalize(&request.symbol)
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?,
+        direction: request.direction,
+        price: request.price,
+        owner: Some(request.owner),
+        webhook_url: request.webhook_url,
+    })
+}
+
+/// Owner of a stored price alert. Alerts stored before owners were required
+/// have nobody to sign for them, they stay until they fire
+fn price_alert_owner(alert: &PriceAlert) -> Result<Pubkey, ApiError> {
+    alert
+        .spec
+        .owner
+        .as_deref()
+        .and_then(|owner| Pubkey::from_str(owner).ok())
+        .ok_or_else(|| ApiError::Forbidden(format!("Price alert {} has no owner", alert.id)))
+}
+
+async fn fetch_price_alert(state: &AppState, id: Uuid) -> Result<PriceAlert, ApiError> {
+    state
+        .monitor
+        .price_alerts()
+        .get(id)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch price alert: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("Price alert {} not found", id)))
+}
+
+/// POST /alerts/price - Create a price alert, authorized by the owner's wallet signature
+pub async fn create_price_alert(
+    State(state): State<AppState>,
+    Json(payload): Json<PriceAlertRequest>,
+) -> Result<Json<PriceAlertDto>, ApiError> {
+    let owner = Pubkey::from_str(&payload.owner)
+        .map_err(|_| ApiError::BadRequest("owner must be a base58 public key".to_string()))?;
+    let message = create_price_alert_message(&owner, payload.timestamp);
+    let (signature, timestamp) = (payload.signature.clone(), payload.timestamp);
+    let spec = price_alert_spec(&state, payload).await?;
+    verify_owner_signature(&state, &owner, &message, &signature, timestamp).await?;
+
+    let alerts = state.monitor.price_alerts();
+    let existing = alerts
+        .count_for_owner(&owner.to_string())
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch price alerts: {}", e)))?;
+    if existing >= MAX_PRICE_ALERTS_PER_OWNER {
+        return Err(ApiError::BadRequest(format!(
+            "User {} already has {} price alerts",
+            owner, MAX_PRICE_ALERTS_PER_OWNER
+        )));
+    }
+
+    let alert = alerts
+        .create(spec)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to create price alert: {}", e)))?;
+
+    Ok(Json(PriceAlertDto::from(alert)))
+}
+
+/// GET /alerts/price - List every pending price alert, for operators
+pub async fn list_price_alerts(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Query(query): Query<ListPriceAlertsQuery>,
+) -> Result<Json<Vec<PriceAlertDto>>, ApiError> {
+    let symbol = query.symbol.as_deref().map(symbols::normalize);
+    let alerts = state
+        .monitor
+        .price_alerts()
+        .list(symbol.as_deref(), query.owner.as_deref())
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch price alerts: {}", e)))?;
+
+    Ok(Json(alerts.into_iter().map(PriceAlertDto::from).collect()))
+}
+
+/// GET /users/:id/price-alerts - The owner's pending price alerts
+pub async fn list_user_price_alerts(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+    Query(query): Query<ListPriceAlertsQuery>,
+) -> Result<Json<Vec<PriceAlertDto>>, ApiError> {
+    let symbol = query.symbol.as_deref().map(symbols::normalize);
+    let alerts = state
+        .monitor
+        .price_alerts()
+        .list(symbol.as_deref(), Some(&owner.to_string()))
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch price alerts: {}", e)))?;
+
+    Ok(Json(alerts.into_iter().map(PriceAlertDto::from).collect()))
+}
+
+/// GET /alerts/price/:id - Get a pending price alert, for operators
+pub async fn get_price_alert(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PriceAlertDto>, ApiError> {
+    let alert = fetch_price_alert(&state, id).await?;
+
+    Ok(Json(PriceAlertDto::from(alert)))
+}
+
+/// PUT /alerts/price/:id - Replace a pending price alert's fields, authorized
+/// by its owner's wallet signature. The owner can't be changed
+pub async fn update_price_alert(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<PriceAlertRequest>,
+) -> Result<Json<PriceAlertDto>, ApiError> {
+    let owner = price_alert_owner(&fetch_price_alert(&state, id).await?)?;
+    if payload.owner != owner.to_string() {
+        return Err(ApiError::Forbidden(format!("Price alert {} belongs to {}", id, owner)));
+    }
+    let message = update_price_alert_message(&owner, id, payload.timestamp);
+    let (signature, timestamp) = (payload.signature.clone(), payload.timestamp);
+    let spec = price_alert_spec(&state, payload).await?;
+    verify_owner_signature(&state, &owner, &message, &signature, timestamp).await?;
+
+    let alert = state
+        .monitor
+        .price_alerts()
+        .update(id, spec)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to update price alert: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("Price alert {} not found", id)))?;
+
+    Ok(Json(PriceAlertDto::from(alert)))
+}
+
+/// DELETE /alerts/price/:id - Delete a pending price alert, authorized by its
+/// owner's wallet signature. Returns the deleted alert
+pub async fn delete_price_alert(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<DeletePriceAlertRequest>,
+) -> Result<Json<PriceAlertDto>, ApiError> {
+    let owner = price_alert_owner(&fetch_price_alert(&state, id).await?)?;
+    let message = delete_price_alert_message(&owner, id, payload.timestamp);
+    verify_owner_signature(&state, &owner, &message, &payload.signature, payload.timestamp).await?;
+
+    let alert = state
+        .monitor
+        .price_alerts()
+        .remove(id)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to delete price alert: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("Price alert {} not found", id)))?;
+
+    Ok(Json(PriceAlertDto::from(alert)))
+}
+
+/// GET /admin/symbols - Monitor settings for every monitored or overridden symbol
+pub async fn list_symbol_configs(
+    _admin: Admin,
+    State(state): State<AppState>,
+) -> Json<Vec<SymbolConfigDto>> {
+    let symbol_configs = state.monitor.symbol_configs();
+    let mut symbols: BTreeSet<String> = state.monitor.get_monitored_symbols().await.into_iter().collect();
+    symbols.extend(symbol_configs.overrides().into_keys());
+
+    let configs = symbols
         .into_iter()
-        .map(PositionDto::from)
+        .map(|symbol| {
+            let overrides = symbol_configs.overrides_for(&symbol);
+            let settings = symbol_configs.settings(&symbol);
+            SymbolConfigDto::new(symbol, settings, overrides.as_ref())
+        })
         .collect();
 
-    Ok(Json(dtos))
+    Json(configs)
 }
 
-/// GET /statistics - Get monitoring statistics
-pub async fn get_statistics(
+/// PUT /admin/symbols/:symbol - Replace a symbol's overrides
+pub async fn update_symbol_config(
+    _admin: Admin,
     State(state): State<AppState>,
-) -> Result<Json<StatisticsDto>, ApiError> {
-    let stats = state.monitor.get_statistics().await;
+    Path(symbol): Path<String>,
+    Json(payload): Json<SymbolConfigRequest>,
+) -> Result<Json<SymbolConfigDto>, ApiError> {
+    let symbol = symbols::canonicalize(&symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    validation::validate_symbol_config(&payload).map_err(ApiError::Validation)?;
 
-    let dto = StatisticsDto {
-        total_positions: stats.total_positions,
-        open_positions: stats.open_positions,
-        assets_monitored: stats.assets_monitored,
-        total_unrealized_pnl: stats.total_unrealized_pnl,
-    };
+    let symbol_configs = state.monitor.symbol_configs();
+    let overrides = SymbolOverrides::from(payload);
+    symbol_configs
+        .set(&symbol, overrides.clone())
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to store symbol config: {}", e)))?;
 
-    Ok(Json(dto))
+    tracing::info!("Symbol config for {} set to {:?}", symbol, overrides);
+
+    let settings = symbol_configs.settings(&symbol);
+    Ok(Json(SymbolConfigDto::new(symbol, settings, Some(&overrides))))
 }
 
-/// GET /prices - Get current prices for all monitored assets
-pub async fn get_prices(
+/// DELETE /admin/symbols/:symbol - Drop a symbol's runtime overrides
+pub async fn delete_symbol_config(
+    _admin: Admin,
     State(state): State<AppState>,
-) -> Result<Json<Vec<PriceDto>>, ApiError> {
-    let symbols = state.monitor.get_monitored_symbols().await;
+    Path(symbol): Path<String>,
+) -> Result<Json<SymbolConfigDto>, ApiError> {
+    let symbol = symbols::canonicalize(&symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
 
-    let mut prices = Vec::new();
-    for symbol in symbols {
-        if let Some(price) = state.monitor.get_cached_price(&symbol).await {
-            prices.push(PriceDto {
-                symbol,
-                price,
-                timestamp: chrono::Utc::now(),
-            });
-        }
+    let symbol_configs = state.monitor.symbol_configs();
+    let removed = symbol_configs
+        .remove(&symbol)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to delete symbol config: {}", e)))?;
+
+    if !removed {
+        return Err(ApiError::NotFound(format!("No runtime config for {}", symbol)));
     }
 
-    Ok(Json(prices))
+    tracing::info!("Symbol config for {} reset", symbol);
+
+    let overrides = symbol_configs.overrides_for(&symbol);
+    let settings = symbol_configs.settings(&symbol);
+    Ok(Json(SymbolConfigDto::new(symbol, settings, overrides.as_ref())))
 }
 
-/// GET /prices/:symbol - Get current price for specific asset
-pub async fn get_price(
+/// GET /admin/markets/:symbol - A symbol's Market and the crank's target for it
+pub async fn get_market(
+    _admin: Admin,
     State(state): State<AppState>,
     Path(symbol): Path<String>,
-) -> Result<Json<PriceDto>, ApiError> {
-    let price = state
-        .monitor
-        .get_cached_price(&symbol)
+) -> Result<Json<MarketDto>, ApiError> {
+    let symbol = symbols::canonicalize(&symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    let market = state
+        .position_manager
+        .get_market(&symbol)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch market: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("No Market for {}", symbol)))?;
+
+    let (volatility, target_maintenance_margin_bps) = match &state.dynamic_margin {
+        Some(dynamic_margin) => {
+            let volatility = dynamic_margin
+                .volatility(&symbol)
+                .await
+                .map_err(|e| ApiError::InternalError(format!("Failed to read price history: {}", e)))?;
+            let target = volatility.map(|volatility| dynamic_margin.target_for(&market, volatility));
+            (volatility, target)
+        }
+        None => (None, None),
+    };
+
+    Ok(Json(MarketDto {
+        symbol: market.symbol,
+        address: market.address.to_string(),
+        maintenance_margin_bps: market.maintenance_margin_bps,
+        min_maintenance_margin_bps: market.min_maintenance_margin_bps,
+        max_maintenance_margin_bps: market.max_maintenance_margin_bps,
+        updated_at: market.updated_at,
+        volatility,
+        target_maintenance_margin_bps,
+        open_cooldown_slots: market.open_cooldown_slots,
+        max_opens_per_slot: market.max_opens_per_slot,
+    }))
+}
+
+/// POST /admin/markets/:symbol - Create a symbol's Market, its rate starts at the lower bound
+pub async fn initialize_market(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    Json(payload): Json<InitializeMarketRequest>,
+) -> Result<Json<InitializeMarketResponse>, ApiError> {
+    let symbol = symbols::canonicalize(&symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    validation::validate_initialize_market(&payload).map_err(ApiError::Validation)?;
+
+    let signature = state
+        .position_manager
+        .initialize_market(
+            &symbol,
+            payload.min_maintenance_margin_bps,
+            payload.max_maintenance_margin_bps,
+        )
+        .await
+        .map_err(|e| ApiError::transaction_failed("Failed to initialize market", e))?;
+
+    let market = state.position_manager.market_address(&symbol);
+
+    tracing::info!(
+        "Market for {} initialized with bounds {}-{} bps",
+        symbol,
+        payload.min_maintenance_margin_bps,
+        payload.max_maintenance_margin_bps
+    );
+
+    Ok(Json(InitializeMarketResponse {
+        market: market.to_string(),
+        signature: signature.to_string(),
+        message: format!("Market for {} initialized", symbol),
+    }))
+}
+
+/// PUT /admin/markets/:symbol/throttle - Set a Market's open cooldown and per-slot open limit
+pub async fn set_market_throttle(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    Json(payload): Json<SetMarketThrottleRequest>,
+) -> Result<Json<SetMarketThrottleResponse>, ApiError> {
+    let symbol = symbols::canonicalize(&symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    let signature = state
+        .position_manager
+        .set_market_throttle(&symbol, payload.open_cooldown_slots, payload.max_opens_per_slot)
+        .await
+        .map_err(|e| ApiError::transaction_failed("Failed to set market throttle", e))?;
+
+    Ok(Json(SetMarketThrottleResponse {
+        market: state.position_manager.market_address(&symbol).to_string(),
+        open_cooldown_slots: payload.open_cooldown_slots,
+        max_opens_per_slot: payload.max_opens_per_slot,
+        signature: signature.to_string(),
+    }))
+}
+
+/// PUT /admin/fee-schedule - Set the program's taker fee per volume tier
+pub async fn set_fee_schedule(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Json(payload): Json<SetFeeScheduleRequest>,
+) -> Result<Json<SetFeeScheduleResponse>, ApiError> {
+    validation::validate_fee_schedule(&payload).map_err(ApiError::Validation)?;
+    let schedule: [u16; FEE_TIER_COUNT] = payload
+        .taker_fee_bps
+        .clone()
+        .try_into()
+        .map_err(|_| ApiError::BadRequest("One rate per fee tier".to_string()))?;
+
+    let signature = state
+        .position_manager
+        .set_fee_schedule(schedule)
+        .await
+        .map_err(|e| ApiError::transaction_failed("Failed to set fee schedule", e))?;
+
+    Ok(Json(SetFeeScheduleResponse {
+        taker_fee_bps: payload.taker_fee_bps,
+        signature: signature.to_string(),
+    }))
+}
+
+/// POST /admin/lookup-table - Create and fill the address lookup table used for batches
+pub async fn prepare_lookup_table(
+    _admin: Admin,
+    State(state): State<AppState>,
+    payload: Option<Json<PrepareLookupTableRequest>>,
+) -> Result<Json<PrepareLookupTableResponse>, ApiError> {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let address = payload
+        .address
+        .as_deref()
+        .map(Pubkey::from_str)
+        .transpose()
+        .map_err(|e| ApiError::BadRequest(format!("Invalid lookup table address: {}", e)))?;
+    let symbols: Vec<String> = state.monitor.get_monitored_symbols().await;
+
+    let setup = state
+        .position_manager
+        .prepare_lookup_table(address, &symbols)
+        .await
+        .map_err(|e| ApiError::transaction_failed("Failed to prepare lookup table", e))?;
+
+    Ok(Json(PrepareLookupTableResponse {
+        address: setup.address.to_string(),
+        created: setup.created,
+        addresses: setup.addresses,
+        signatures: setup.signatures.iter().map(ToString::to_string).collect(),
+    }))
+}
+
+/// POST /admin/positions/:id/migrate - Grow a position account to the program's current layout
+pub async fn migrate_position(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Path(position_account): Path<String>,
+) -> Result<Json<MigratePositionResponse>, ApiError> {
+    let position_account = Pubkey::from_str(&position_account)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid position account: {}", e)))?;
+
+    let signature = state
+        .position_manager
+        .migrate_position(&position_account)
+        .await
+        .map_err(|e| ApiError::transaction_failed("Failed to migrate position", e))?;
+
+    Ok(Json(MigratePositionResponse {
+        position_account: position_account.to_string(),
+        signature: signature.to_string(),
+    }))
+}
+
+/// POST /admin/backfill - Rebuild position history from program transactions since a slot
+pub async fn start_backfill(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Json(payload): Json<BackfillRequest>,
+) -> Result<(StatusCode, Json<BackfillProgressDto>), ApiError> {
+    let backfill = state
+        .backfill
+        .as_ref()
+        .ok_or_else(|| ApiError::BadRequest("Backfill needs DATABASE_URL".to_string()))?;
+
+    let progress = backfill
+        .start(payload.from_slot)
+        .await
+        .ok_or_else(|| ApiError::BadRequest("A backfill is already running".to_string()))?;
+
+    tracing::info!("History backfill from slot {} requested", payload.from_slot);
+
+    Ok((StatusCode::ACCEPTED, Json(backfill_progress_dto(progress))))
+}
+
+/// GET /admin/backfill - Progress of the running or last backfill
+pub async fn get_backfill(
+    _admin: Admin,
+    State(state): State<AppState>,
+) -> Result<Json<BackfillProgressDto>, ApiError> {
+    let progress = match &state.backfill {
+        Some(backfill) => backfill.progress().await,
+        None => None,
+    };
+
+    progress
+        .map(|progress| Json(backfill_progress_dto(progress)))
+        .ok_or_else(|| ApiError::NotFound("No backfill has run".to_string()))
+}
+
+/// GET /admin/history - Retention policy, prune totals and table sizes of the history store
+pub async fn get_history_retention(
+    _admin: Admin,
+    State(state): State<AppState>,
+) -> Result<Json<HistoryRetentionDto>, ApiError> {
+    let retention = state
+        .history_retention
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("History retention needs DATABASE_URL".to_string()))?;
+
+    let tables = retention
+        .table_sizes()
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to read history table sizes: {}", e)))?;
+    let config = retention.config();
+    let stats = retention.stats();
+
+    Ok(Json(HistoryRetentionDto {
+        scheduled: config.scheduled,
+        prune_interval_secs: config.prune_interval_secs,
+        ticks: tick_retention_dto(config.ticks),
+        symbol_ticks: config
+            .symbol_ticks
+            .iter()
+            .map(|(symbol, ticks)| (symbol.clone(), tick_retention_dto(*ticks)))
+            .collect(),
+        pnl_snapshot_retention_secs: config.pnl_snapshots.map(|retention| retention.num_seconds()),
+        last_run: retention.last_run().await.map(prune_run_dto),
+        passes: stats.passes,
+        failed_passes: stats.failed_passes,
+        ticks_deleted: stats.ticks_deleted,
+        pnl_snapshots_deleted: stats.pnl_snapshots_deleted,
+        tables: tables
+            .into_iter()
+            .map(|size| HistoryTableDto {
+                table: size.table,
+                rows: size.rows,
+                bytes: size.bytes,
+            })
+            .collect(),
+    }))
+}
+
+/// POST /admin/history/compact - Prune the history store now instead of at the next scheduled pass
+pub async fn compact_history(
+    _admin: Admin,
+    State(state): State<AppState>,
+) -> Result<(StatusCode, Json<PruneRunDto>), ApiError> {
+    let retention = state
+        .history_retention
+        .as_ref()
+        .ok_or_else(|| ApiError::BadRequest("History retention needs DATABASE_URL".to_string()))?;
+
+    let run = retention
+        .start()
+        .await
+        .ok_or_else(|| ApiError::BadRequest("A history prune is already running".to_string()))?;
+
+    tracing::info!("History compaction requested");
+
+    Ok((StatusCode::ACCEPTED, Json(prune_run_dto(run))))
+}
+
+/// POST /admin/bootstrap-demo - Fund demo wallets from the faucet and give
+/// them accounts, collateral and positions across symbols
+pub async fn bootstrap_demo(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Json(payload): Json<BootstrapDemoRequest>,
+) -> Result<Json<BootstrapDemoResponse>, ApiError> {
+    let demo_bootstrap = state
+        .demo_bootstrap
+        .as_ref()
+        .ok_or_else(|| ApiError::BadRequest("Demo bootstrap is not enabled".to_string()))?;
+
+    validation::validate_bootstrap_demo(&payload).map_err(ApiError::Validation)?;
+
+    let defaults = DemoBootstrapConfig::default();
+    let config = DemoBootstrapConfig {
+        users: payload.users.unwrap_or(defaults.users),
+        positions_per_user: payload.positions_per_user.unwrap_or(defaults.positions_per_user),
+        collateral: payload.collateral.unwrap_or(defaults.collateral),
+        notional: payload.notional.unwrap_or(defaults.notional),
+        ..defaults
+    };
+
+    tracing::info!("Demo bootstrap of {} users requested", config.users);
+
+    let users = demo_bootstrap.run(&config).await?;
+
+    Ok(Json(BootstrapDemoResponse {
+        users: users.into_iter().map(DemoUserDto::from).collect(),
+    }))
+}
+
+/// POST /admin/simulate-price - Price a symbol by hand for a while on devnet.
+/// The next price tick broadcasts it and runs alert and liquidation checks
+pub async fn simulate_price(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Json(payload): Json<SimulatePriceRequest>,
+) -> Result<Json<SimulatedPriceDto>, ApiError> {
+    let price_simulator = price_simulator(&state)?;
+
+    let known_symbols = state.monitor.get_monitored_symbols().await;
+    validation::validate_simulate_price(&payload, &known_symbols).map_err(ApiError::Validation)?;
+
+    let symbol = symbols::canonicalize(&payload.symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let ttl_secs = payload.ttl_secs.unwrap_or(validation::DEFAULT_SIMULATED_PRICE_TTL_SECS);
+    let simulated = price_simulator
+        .inject(&symbol, payload.price, chrono::Duration::seconds(ttl_secs as i64))
+        .await;
+
+    tracing::warn!(
+        "Simulated price for {}: {} until {}",
+        symbol,
+        simulated.price,
+        simulated.expires_at
+    );
+
+    Ok(Json(SimulatedPriceDto {
+        oracle_price: price_simulator.oracle_price(&symbol).await,
+        symbol,
+        price: simulated.price,
+        injected_at: simulated.injected_at,
+        expires_at: simulated.expires_at,
+    }))
+}
+
+/// DELETE /admin/simulate-price/:symbol - Hand a symbol back to the oracle
+pub async fn clear_simulated_price(
+    _admin: Admin,
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let price_simulator = price_simulator(&state)?;
+    let symbol = symbols::normalize(&symbol);
+
+    if !price_simulator.clear(&symbol).await {
+        return Err(ApiError::NotFound(format!("No simulated price for {}", symbol)));
+    }
+
+    tracing::info!("Simulated price for {} cleared", symbol);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn price_simulator(state: &AppState) -> Result<&Arc<SimulatedPriceSource>, ApiError> {
+    state
+        .price_simulator
+        .as_ref()
+        .ok_or_else(|| ApiError::BadRequest("Price simulation is not enabled".to_string()))
+}
+
+/// GET /admin/outbox - Outbox stream length and each consumer group's backlog
+pub async fn get_outbox(
+    _admin: Admin,
+    State(state): State<AppState>,
+) -> Result<Json<OutboxStatsDto>, ApiError> {
+    let outbox = state
+        .outbox
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("Outbox is not enabled".to_string()))?;
+
+    let stats = outbox
+        .stats()
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to read outbox: {}", e)))?;
+
+    Ok(Json(OutboxStatsDto {
+        stream: stats.stream,
+        length: stats.length,
+        groups: stats
+            .groups
+            .into_iter()
+            .map(|group| OutboxGroupDto {
+                name: group.name,
+                consumers: group.consumers,
+                pending: group.pending,
+                last_delivered_id: group.last_delivered_id,
+            })
+            .collect(),
+    }))
+}
+
+fn backfill_progress_dto(progress: BackfillProgress) -> BackfillProgressDto {
+    BackfillProgressDto {
+        from_slot: progress.from_slot,
+        state: progress.state.as_str().to_string(),
+        started_at: progress.started_at,
+        finished_at: progress.finished_at,
+        transactions_found: progress.transactions_found,
+        transactions_replayed: progress.transactions_replayed,
+        current_slot: progress.current_slot,
+        opens_written: progress.opens_written,
+        realized_pnl_written: progress.realized_pnl_written,
+        events_written: progress.events_written,
+        skipped_closes: progress.skipped_closes,
+        error: progress.error,
+    }
+}
+
+fn tick_retention_dto(retention: TickRetention) -> TickRetentionDto {
+    TickRetentionDto {
+        raw_secs: retention.raw.num_seconds(),
+        minute_secs: retention.minute.num_seconds(),
+    }
+}
+
+fn prune_run_dto(run: PruneRun) -> PruneRunDto {
+    PruneRunDto {
+        trigger: run.trigger.as_str().to_string(),
+        started_at: run.started_at,
+        finished_at: run.finished_at,
+        ticks_deleted: run.ticks_deleted,
+        pnl_snapshots_deleted: run.pnl_snapshots_deleted,
+        error: run.error,
+    }
+}
+
+/// GET /positions/by-asset/:symbol - Get positions for specific asset
+pub async fn get_positions_by_asset(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+) -> Result<Json<Vec<PositionDto>>, ApiError> {
+    let positions = state.monitor.get_positions_by_asset(&symbol).await;
+
+    let dtos: Vec<PositionDto> = positions
+        .into_iter()
+        .map(PositionDto::from)
+        .collect();
+
+    Ok(Json(dtos))
+}
+
+/// Users a tenant's requests are limited to, none for the default tenant
+async fn tenant_owners(state: &AppState, tenant: &CurrentTenant) -> Result<Option<HashSet<Pubkey>>, ApiError> {
+    if tenant.is_default() {
+        return Ok(None);
+    }
+
+    state
+        .tenants
+        .owners(&tenant.0)
+        .await
+        .map(Some)
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch tenant users: {}", e)))
+}
+
+/// GET /statistics - Get monitoring statistics
+/// A tenant's key limits them to its own users' positions
+pub async fn get_statistics(
+    State(state): State<AppState>,
+    tenant: CurrentTenant,
+) -> Result<Json<StatisticsDto>, ApiError> {
+    let owners = tenant_owners(&state, &tenant).await?;
+    let stats = state.monitor.get_statistics_for(owners.as_ref()).await;
+
+    let dto = StatisticsDto {
+        total_positions: stats.total_positions,
+        open_positions: stats.open_positions,
+        margin_call_positions: stats.margin_call_positions,
+        closed_positions_in_memory: stats.closed_positions,
+        assets_monitored: stats.assets_monitored,
+        total_unrealized_pnl: stats.total_unrealized_pnl,
+        underwater_positions: stats.underwater_positions,
+        total_margin_deficit: stats.total_margin_deficit,
+        evicted_expired: stats.retention.evicted_expired,
+        evicted_over_cap: stats.retention.evicted_over_cap,
+        eviction_persist_failures: stats.retention.persist_failures,
+    };
+
+    Ok(Json(dto))
+}
+
+/// GET /positions/underwater - Open positions whose losses exceed their
+/// margin, most severe first. A tenant's key limits them to its own users
+pub async fn get_underwater_positions(
+    State(state): State<AppState>,
+    tenant: CurrentTenant,
+) -> Result<Json<Vec<UnderwaterPositionDto>>, ApiError> {
+    let owners = tenant_owners(&state, &tenant).await?;
+    let positions = state.monitor.underwater_positions(owners.as_ref()).await;
+
+    Ok(Json(positions.into_iter().map(UnderwaterPositionDto::from).collect()))
+}
+
+/// GET /program/idl - The program's IDL as published on chain, for
+/// integrators to build their clients from
+pub async fn get_program_idl(State(state): State<AppState>) -> Result<Response, ApiError> {
+    let idl = state
+        .position_manager
+        .get_program_idl()
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch program IDL: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("No IDL published for the program".to_string()))?;
+
+    // The signer that can upgrade it, so integrators can tell who published it
+    Ok(([("x-idl-authority", idl.authority.to_string())], Json(idl.idl)).into_response())
+}
+
+/// GET /statistics/rpc - RPC calls made by the monitor since startup
+pub async fn get_rpc_statistics(State(state): State<AppState>) -> Json<Vec<RpcCallStatsDto>> {
+    let stats = state
+        .monitor
+        .rpc_stats()
+        .into_iter()
+        .map(|(method, stats)| RpcCallStatsDto {
+            method: method.to_string(),
+            calls: stats.calls,
+            errors: stats.errors,
+            items: stats.items,
+            bytes: stats.bytes,
+        })
+        .collect();
+
+    Json(stats)
+}
+
+/// GET /statistics/breakers - Circuit breakers around oracle quotes and chain scans
+pub async fn get_breaker_statistics(State(state): State<AppState>) -> Json<Vec<CircuitBreakerDto>> {
+    let stats = state
+        .monitor
+        .breaker_stats()
+        .into_iter()
+        .map(|(key, stats)| CircuitBreakerDto {
+            key,
+            state: stats.state.as_str().to_string(),
+            consecutive_failures: stats.consecutive_failures,
+            trips: stats.trips,
+            backoff_ms: stats.backoff.as_millis() as u64,
+            retry_in_ms: stats.retry_in.map(|retry_in| retry_in.as_millis() as u64),
+        })
+        .collect();
+
+    Json(stats)
+}
+
+/// GET /statistics/rpc/endpoints - Health of each RPC endpoint in the pool
+pub async fn get_rpc_endpoint_statistics(State(state): State<AppState>) -> Json<Vec<RpcEndpointHealthDto>> {
+    Json(
+        state
+            .monitor
+            .rpc_endpoints()
+            .into_iter()
+            .map(RpcEndpointHealthDto::from)
+            .collect(),
+    )
+}
+
+/// GET /prices - Cached prices of every monitored asset, or of `?symbols=`
+pub async fn get_prices(
+    State(state): State<AppState>,
+    Query(query): Query<PricesQuery>,
+) -> Result<Json<Vec<PriceDto>>, ApiError> {
+    let symbols = match query.symbols.as_deref() {
+        Some(requested) => requested
+            .split(',')
+            .map(str::trim)
+            .filter(|symbol| !symbol.is_empty())
+            .map(|symbol| symbols::canonicalize(symbol).map_err(|e| ApiError::BadRequest(e.to_string())))
+            .collect::<Result<BTreeSet<_>, _>>()?
+            .into_iter()
+            .collect(),
+        None => state.monitor.get_monitored_symbols().await,
+    };
+
+    let mut prices = Vec::new();
+    for symbol in symbols {
+        if let Some(cached) = state.monitor.get_cached_quote(&symbol).await {
+            prices.push(PriceDto::from((symbol, cached)));
+        }
+    }
+
+    Ok(Json(prices))
+}
+
+/// GET /statistics/prices - Oracle feed gaps and reordering since startup
+pub async fn get_price_feed_statistics(State(state): State<AppState>) -> Json<Vec<PriceFeedStatsDto>> {
+    let stats = state
+        .monitor
+        .price_feed_stats()
+        .into_iter()
+        .map(|(symbol, stats)| PriceFeedStatsDto {
+            symbol,
+            ticks: stats.ticks,
+            gaps: stats.gaps,
+            longest_gap_ms: stats.longest_gap_ms,
+            out_of_order: stats.out_of_order,
+            repeated: stats.repeated,
+            last_publish_time: stats.last_publish_time,
+        })
+        .collect();
+
+    Json(stats)
+}
+
+/// GET /statistics/latency - Oracle response to ZSET query and alert broadcast
+pub async fn get_latency_statistics(State(state): State<AppState>) -> Json<Vec<LatencyStatsDto>> {
+    let budget_ms = state.monitor.alert_latency_budget().as_millis() as u64;
+    let millis = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+
+    let stats = state
+        .monitor
+        .latency_stats()
+        .into_iter()
+        .map(|(stage, stats)| LatencyStatsDto {
+            stage: stage.as_str().to_string(),
+            samples: stats.samples,
+            over_budget: stats.over_budget,
+            budget_ms,
+            p50_ms: millis(stats.p50),
+            p99_ms: millis(stats.p99),
+            max_ms: millis(stats.max),
+        })
+        .collect();
+
+    Json(stats)
+}
+
+/// GET /prices/:symbol - Get current price for specific asset
+pub async fn get_price(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+) -> Result<Json<PriceDto>, ApiError> {
+    let symbol = symbols::canonicalize(&symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    let cached = state
+        .monitor
+        .get_cached_quote(&symbol)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Price for {} not found", symbol)))?;
+
+    Ok(Json(PriceDto::from((symbol, cached))))
+}
+
+/// GET /prices/:symbol/history - Archived oracle ticks, for auditing liquidation prices
+pub async fn get_price_history(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<PriceHistoryQuery>,
+) -> Result<Json<Vec<PriceTickDto>>, ApiError> {
+    let symbol = symbols::canonicalize(&symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let history_store = state.history_store()?;
+
+    let to = query.to.unwrap_or_else(chrono::Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::hours(1));
+    if from >= to {
+        return Err(ApiError::BadRequest("from must be before to".to_string()));
+    }
+    if to - from > chrono::Duration::hours(MAX_PRICE_HISTORY_RANGE_HOURS) {
+        return Err(ApiError::BadRequest(format!(
+            "Range must be at most {}h",
+            MAX_PRICE_HISTORY_RANGE_HOURS
+        )));
+    }
+
+    let ticks = history_store
+        .symbol_price_ticks(&symbol, from, to, MAX_PRICE_HISTORY_TICKS)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch price history: {}", e)))?;
+
+    let ticks = match query.resolution.unwrap_or(PriceResolution::Raw) {
+        PriceResolution::Raw => ticks,
+        PriceResolution::OneSecond => last_tick_per_bucket(ticks, chrono::Duration::seconds(1)),
+    };
+
+    Ok(Json(ticks.into_iter().map(PriceTickDto::from).collect()))
+}
+
+/// POST /simulate/trade - Replay archived prices against a position opened at
+/// `entry_time` and closed at `exit_time`: its PnL path, drawdown and whether
+/// it would have been liquidated
+pub async fn simulate_trade(
+    State(state): State<AppState>,
+    Json(payload): Json<SimulateTradeRequest>,
+) -> Result<Json<TradeSimulationDto>, ApiError> {
+    validation::validate_simulate_trade(&payload, chrono::Utc::now()).map_err(ApiError::Validation)?;
+    let history_store = state.history_store()?;
+
+    let symbol = symbols::canonicalize(&payload.symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let window = payload.exit_time - payload.entry_time;
+    let interval = match &payload.interval {
+        Some(interval) => parse_interval(interval).map_err(|e| ApiError::BadRequest(e.to_string()))?,
+        None if window <= chrono::Duration::days(1) => chrono::Duration::minutes(1),
+        None => chrono::Duration::hours(1),
+    };
+    if window.num_seconds() / interval.num_seconds().max(1) > MAX_PNL_PATH_POINTS {
+        return Err(ApiError::BadRequest(format!(
+            "Interval gives more than {} PnL path points, use a longer one",
+            MAX_PNL_PATH_POINTS
+        )));
+    }
+
+    let params = TradeSimulationParams {
+        side: payload.side,
+        size: payload.size,
+        leverage: payload.leverage,
+        maintenance_margin_ratio: payload
+            .maintenance_margin_ratio
+            .unwrap_or_else(|| state.monitor.maintenance_margin_ratio(&symbol)),
+        interval,
+    };
+
+    let simulation = replay_trade(
+        history_store.as_ref(),
+        &symbol,
+        payload.entry_time,
+        payload.exit_time,
+        params,
+    )
+    .await?;
+
+    Ok(Json(TradeSimulationDto::new(
+        symbol,
+        payload.side,
+        payload.size,
+        payload.leverage,
+        simulation,
+    )))
+}
+
+/// GET /markets/:symbol/sizing - Suggested max position size per leverage,
+/// from the oracle confidence and the last hour of one-minute candles
+pub async fn get_market_sizing(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+) -> Result<Json<MarketSizingDto>, ApiError> {
+    let symbol = symbols::canonicalize(&symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let cached = state
+        .monitor
+        .get_cached_quote(&symbol)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Price for {} not found", symbol)))?;
+    let history_store = state.history_store()?;
+
+    let to = chrono::Utc::now();
+    let from = to - chrono::Duration::minutes(SIZING_LOOKBACK_MINUTES);
+    let ticks = history_store
+        .symbol_price_ticks(&symbol, from, to, MAX_PRICE_HISTORY_TICKS)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch price history: {}", e)))?;
+    let closes: Vec<Decimal> = last_tick_per_bucket(ticks, chrono::Duration::minutes(1))
+        .into_iter()
+        .map(|tick| tick.price)
+        .collect();
+
+    let model = SizingModel::default();
+    let price = cached.quote.price;
+    let volatility = MarginCalculator::candle_volatility(&closes);
+    let adverse_move =
+        MarginCalculator::expected_adverse_move(&model, price, cached.quote.conf, volatility)?;
+    let buckets = MarginCalculator::suggest_position_sizes(
+        &model,
+        price,
+        adverse_move,
+        state.monitor.maintenance_margin_ratio(&symbol),
+    )?;
+
+    Ok(Json(MarketSizingDto {
+        symbol,
+        price,
+        confidence: cached.quote.conf,
+        volatility,
+        candles: closes.len(),
+        adverse_move,
+        buckets: buckets.into_iter().map(SizingBucketDto::from).collect(),
+    }))
+}
+
+/// GET /markets/:symbol/liquidation-map - Open positions' notional by
+/// liquidation price, a ladder per side from the monitor's index
+pub async fn get_liquidation_map(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<LiquidationMapQuery>,
+) -> Result<Json<LiquidationMapDto>, ApiError> {
+    let symbol = symbols::canonicalize(&symbol).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let buckets = query.buckets.unwrap_or(DEFAULT_LIQUIDATION_MAP_BUCKETS);
+    if !(1..=MAX_LIQUIDATION_MAP_BUCKETS).contains(&buckets) {
+        return Err(ApiError::BadRequest(format!(
+            "buckets must be between 1 and {}",
+            MAX_LIQUIDATION_MAP_BUCKETS
+        )));
+    }
+
+    let mark_price = state
+        .monitor
+        .get_cached_price(&symbol)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Price for {} not found", symbol)))?;
+    let positions = state.monitor.get_positions_by_asset(&symbol).await;
+
+    Ok(Json(LiquidationMapDto {
+        symbol,
+        mark_price,
+        longs: build_ladder(&positions, Side::Long, mark_price, buckets).into(),
+        shorts: build_ladder(&positions, Side::Short, mark_price, buckets).into(),
+    }))
+}
+
+/// POST /positions/open - Open new position
+pub async fn open_position(
+    State(state): State<AppState>,
+    PaperSession(session): PaperSession,
+    Json(mut payload): Json<OpenPositionRequest>,
+) -> Result<Json<OpenPositionResponse>, ApiError> {
+    state.apply_user_defaults(&mut payload).await;
+
+    let known_symbols = state.monitor.get_monitored_symbols().await;
+    let oracle_price = match symbols::canonicalize(&payload.symbol) {
+        Ok(symbol) => state.monitor.get_cached_price(&symbol).await,
+        Err(_) => None,
+    };
+    validation::validate_open_position(&payload, &known_symbols, oracle_price)
+        .map_err(ApiError::Validation)?;
+
+    let owner = Pubkey::from_str(&payload.owner)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+    let symbol = symbols::canonicalize(&payload.symbol)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let maintenance_margin_ratio = payload
+        .maintenance_margin_ratio
+        .unwrap_or_else(|| state.monitor.maintenance_margin_ratio(&symbol));
+    // Validation rejects a missing leverage
+    let leverage = payload.leverage.unwrap_or(validation::MIN_LEVERAGE);
+
+    if let Some(session) = session {
+        let position = state
+            .paper_ledger()?
+            .open_position(
+                &session,
+                owner,
+                symbol,
+                payload.side,
+                payload.size,
+                leverage,
+                payload.entry_price,
+                maintenance_margin_ratio,
+            )
+            .await
+            .map_err(|e| ApiError::transaction_failed("Failed to open paper position", e))?;
+
+        return Ok(Json(OpenPositionResponse {
+            position: PositionDto::from(position),
+            signature: paper_signature(),
+        }));
+    }
+
+    state.ensure_trading()?;
+    state.ensure_open_interest(&symbol, payload.size).await?;
+    let notional = payload.size * payload.entry_price;
+    state
+        .ensure_risk_limits(&owner, |utilization| {
+            utilization.with_open(&symbol, notional / Decimal::from(leverage), notional)
+        })
+        .await?;
+
+    let (position, signature) = state
+        .position_manager
+        .open_position(
+            owner,
+            symbol,
+            payload.side,
+            payload.size,
+            leverage,
+            payload.entry_price,
+            maintenance_margin_ratio,
+        )
+        .await
+        .map_err(|e| ApiError::transaction_failed("Failed to open position", e))?;
+
+    Ok(Json(OpenPositionResponse {
+        position: PositionDto::from(position),
+        signature: signature.to_string(),
+    }))
+}
+
+/// POST /orders - One-way order: nets against the owner's opposite position on
+/// the symbol before opening the rest, reported as a single fill. Takes the open
+/// position body, `entry_price` is the order price
+pub async fn place_order(
+    State(state): State<AppState>,
+    PaperSession(session): PaperSession,
+    Json(mut payload): Json<OpenPositionRequest>,
+) -> Result<Json<OrderFillResponse>, ApiError> {
+    if session.is_some() {
+        return Err(ApiError::BadRequest(
+            "One-way orders are not available in paper sessions".to_string(),
+        ));
+    }
+
+    state.apply_user_defaults(&mut payload).await;
+
+    let known_symbols = state.monitor.get_monitored_symbols().await;
+    let oracle_price = match symbols::canonicalize(&payload.symbol) {
+        Ok(symbol) => state.monitor.get_cached_price(&symbol).await,
+        Err(_) => None,
+    };
+    validation::validate_open_position(&payload, &known_symbols, oracle_price)
+        .map_err(ApiError::Validation)?;
+
+    let owner = Pubkey::from_str(&payload.owner)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+    let symbol = symbols::canonicalize(&payload.symbol)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let maintenance_margin_ratio = payload
+        .maintenance_margin_ratio
+        .unwrap_or_else(|| state.monitor.maintenance_margin_ratio(&symbol));
+    // Validation rejects a missing leverage
+    let leverage = payload.leverage.unwrap_or(validation::MIN_LEVERAGE);
+
+    // Only the part left after netting the owner's opposite side adds open
+    // interest, an order that only reduces goes through a halt
+    let opposite_size: Decimal = state
+        .monitor
+        .get_user_positions(&owner)
+        .await?
+        .iter()
+        .filter(|position| position.is_open() && position.symbol == symbol && position.side != payload.side)
+        .map(|position| position.size)
+        .sum();
+    let opened_size = (payload.size - opposite_size).max(Decimal::ZERO);
+    if opened_size > Decimal::ZERO {
+        state.ensure_trading()?;
+        state.ensure_open_interest(&symbol, opened_size).await?;
+        let notional = opened_size * payload.entry_price;
+        state
+            .ensure_risk_limits(&owner, |utilization| {
+            utilization.with_open(&symbol, notional / Decimal::from(leverage), notional)
+        })
+            .await?;
+    }
+
+    let fill = state
+        .position_manager
+        .place_one_way_order(
+            owner,
+            symbol,
+            payload.side,
+            payload.size,
+            leverage,
+            payload.entry_price,
+            maintenance_margin_ratio,
+        )
+        .await
+        .map_err(|e| ApiError::transaction_failed("Failed to place order", e))?;
+
+    Ok(Json(OrderFillResponse::from(fill)))
+}
+
+/// POST /positions/preview - Size limits for an order before placing it: the
+/// leverage tier's cap, the market's open interest headroom and what the
+/// owner's free collateral covers after the taker fee. With a size, also its
+/// margin, taker fee and liquidation price
+pub async fn preview_position(
+    State(state): State<AppState>,
+    Json(payload): Json<PositionPreviewRequest>,
+) -> Result<Json<PositionPreviewResponse>, ApiError> {
+    let known_symbols = state.monitor.get_monitored_symbols().await;
+    validation::validate_position_preview(&payload, &known_symbols).map_err(ApiError::Validation)?;
+
+    let owner = Pubkey::from_str(&payload.owner)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+    let symbol = symbols::canonicalize(&payload.symbol)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let entry_price = match payload.entry_price {
+        Some(entry_price) => entry_price,
+        None => state
+            .monitor
+            .get_cached_price(&symbol)
+            .await
+            .ok_or_else(|| ApiError::Oracle(format!("No price for {} yet", symbol)))?,
+    };
+    let maintenance_margin_ratio = payload
+        .maintenance_margin_ratio
+        .unwrap_or_else(|| state.monitor.maintenance_margin_ratio(&symbol));
+
+    // Limits round down, so the size shown is one the program accepts
+    let floor_size = |size: Decimal| size.round_dp_with_strategy(SIZE_DP, RoundingStrategy::ToZero);
+
+    let tier_max_size = max_notional_for_leverage(payload.leverage).map(|notional| floor_size(notional / entry_price));
+
+    let max_open_interest = state.monitor.symbol_configs().settings(&symbol).max_open_interest;
+    let open_interest = state.monitor.open_interest(&symbol).await;
+    let open_interest_headroom = max_open_interest.map(|cap| (cap - open_interest).max(Decimal::ZERO));
+
+    let user_account = state.position_manager.get_user_account(&owner).await?;
+    let available_collateral = Decimal::new(
+        user_account.total_collateral.saturating_sub(user_account.locked_collateral) as i64,
+        6,
+    );
+    let fee_tier = state.position_manager.get_user_fee_tier(&owner).await?;
+    let fee_bps = taker_fee_bps(
+        &state.position_manager.get_fee_schedule().await?,
+        fee_tier.map(|fee_tier| fee_tier.tier),
+    );
+    // Margin plus fee is the margin grown by leverage times the fee rate
+    let fee_load = Decimal::ONE + Decimal::from(payload.leverage) * Decimal::from(fee_bps) / Decimal::from(10_000);
+    let max_affordable_size =
+        MarginCalculator::calculate_max_position_size(available_collateral / fee_load, entry_price, payload.leverage)
+            .map(floor_size)
+            .unwrap_or(Decimal::ZERO);
+
+    let max_size = [tier_max_size, open_interest_headroom]
+        .into_iter()
+        .flatten()
+        .fold(max_affordable_size, Decimal::min);
+
+    let (notional_value, initial_margin, liquidation_price) = match payload.size {
+        Some(size) => (
+            Some(size * entry_price),
+            Some(MarginCalculator::calculate_initial_margin(size, entry_price, payload.leverage)?),
+            Some(MarginCalculator::calculate_liquidation_price(
+                payload.side,
+                entry_price,
+                payload.leverage,
+                maintenance_margin_ratio,
+            )?),
+        ),
+        None => (None, None, None),
+    };
+
+    Ok(Json(PositionPreviewResponse {
+        symbol,
+        side: payload.side,
+        leverage: payload.leverage,
+        entry_price,
+        size: payload.size,
+        notional_value,
+        initial_margin,
+        liquidation_price,
+        tier_max_size,
+        open_interest,
+        max_open_interest,
+        open_interest_headroom,
+        available_collateral,
+        max_affordable_size,
+        max_size,
+        taker_fee_bps: fee_bps,
+        taker_fee: payload.size.map(|size| taker_fee(size, entry_price, fee_bps)),
+    }))
+}
+
+/// GET /users/:id/positions - Get user's positions
+pub async fn get_user_positions(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+) -> Result<Json<Vec<PositionDto>>, ApiError> {
+    let owner = Pubkey::from_str(&owner)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+
+    let positions = state
+        .position_manager
+        .get_user_positions(&owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch positions: {}", e)))?;
+
+    let dtos: Vec<PositionDto> = positions.into_iter().map(PositionDto::from).collect();
+
+    Ok(Json(dtos))
+}
+
+/// A strategy with its legs the monitor still tracks
+async fn strategy_dto(state: &AppState, strategy: Strategy) -> StrategyDto {
+    let mut legs = Vec::with_capacity(strategy.positions.len());
+    for account in &strategy.positions {
+        if let Some(position) = state.monitor.get_position(*account).await {
+            legs.push(position);
+        }
+    }
+
+    StrategyDto::new(strategy, legs)
+}
+
+/// GET /users/:id/strategies - A user's strategies with PnL, margin and exposure per group
+pub async fn list_strategies(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+) -> Result<Json<Vec<StrategyDto>>, ApiError> {
+    let strategies = state
+        .monitor
+        .strategies()
+        .list(&owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch strategies: {}", e)))?;
+
+    let mut dtos = Vec::with_capacity(strategies.len());
+    for strategy in strategies {
+        dtos.push(strategy_dto(&state, strategy).await);
+    }
+
+    Ok(Json(dtos))
+}
+
+/// PUT /users/:id/strategies/:name - Create a strategy or replace its legs,
+/// authorized by the owner's wallet signature
+/// Legs must be the user's positions and belong to no other strategy
+pub async fn put_strategy(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(Pubkey, String)>,
+    Json(payload): Json<StrategyRequest>,
+) -> Result<Json<StrategyDto>, ApiError> {
+    validation::validate_strategy(&name, &payload).map_err(ApiError::Validation)?;
+
+    let message = put_strategy_message(&owner, &name, payload.timestamp);
+    verify_owner_signature(&state, &owner, &message, &payload.signature, payload.timestamp).await?;
+
+    let positions: Vec<Pubkey> = payload
+        .positions
+        .iter()
+        .map(|account| Pubkey::from_str(account))
+        .collect::<Result<_, _>>()
+        .map_err(|e| ApiError::BadRequest(format!("Invalid position account: {}", e)))?;
+
+    for account in &positions {
+        let position = state
+            .monitor
+            .get_position(*account)
+            .await
+            .ok_or_else(|| ApiError::NotFound(format!("Position {} not found", account)))?;
+
+        if position.owner != owner {
+            return Err(ApiError::BadRequest(format!(
+                "Position {} is not owned by {}",
+                account, owner
+            )));
+        }
+    }
+
+    let service = state.monitor.strategies();
+    let existing = service
+        .list(&owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch strategies: {}", e)))?;
+
+    if existing.len() >= MAX_STRATEGIES_PER_USER && !existing.iter().any(|s| s.name == name) {
+        return Err(ApiError::BadRequest(format!(
+            "User {} already has {} strategies",
+            owner, MAX_STRATEGIES_PER_USER
+        )));
+    }
+
+    if let Some((other, account)) = existing
+        .iter()
+        .filter(|s| s.name != name)
+        .find_map(|s| {
+            positions
+                .iter()
+                .find(|account| s.positions.contains(account))
+                .map(|account| (&s.name, account))
+        })
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Position {} already belongs to strategy {}",
+            account, other
+        )));
+    }
+
+    let strategy = service
+        .save(owner, &name, positions)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to save strategy: {}", e)))?;
+
+    Ok(Json(strategy_dto(&state, strategy).await))
+}
+
+/// DELETE /users/:id/strategies/:name - Ungroup a strategy, its positions
+/// stay open. Authorized by the owner's wallet signature
+pub async fn delete_strategy(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(Pubkey, String)>,
+    Json(payload): Json<SignedStrategyRequest>,
+) -> Result<Json<StrategyDto>, ApiError> {
+    let message = delete_strategy_message(&owner, &name, payload.timestamp);
+    verify_owner_signature(&state, &owner, &message, &payload.signature, payload.timestamp).await?;
+
+    let strategy = state
+        .monitor
+        .strategies()
+        .remove(&owner, &name)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to remove strategy: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("Strategy {} not found", name)))?;
+
+    Ok(Json(strategy_dto(&state, strategy).await))
+}
+
+/// POST /users/:id/strategies/:name/close - Close every open leg at the cached
+/// oracle price, authorized by the owner's wallet signature. Legs are closed
+/// one by one, a failed leg doesn't stop the rest
+pub async fn close_strategy(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(Pubkey, String)>,
+    Json(payload): Json<SignedStrategyRequest>,
+) -> Result<Json<CloseStrategyResponse>, ApiError> {
+    let message = close_strategy_message(&owner, &name, payload.timestamp);
+    verify_owner_signature(&state, &owner, &message, &payload.signature, payload.timestamp).await?;
+
+    // Closing only takes risk off, so it goes through a halt
+    let strategy = state
+        .monitor
+        .strategies()
+        .get(&owner, &name)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch strategy: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("Strategy {} not found", name)))?;
+
+    let mut pnl = Decimal::ZERO;
+    let mut legs = Vec::new();
+
+    for account in &strategy.positions {
+        let Some(position) = state.monitor.get_position(*account).await else {
+            continue;
+        };
+        if !position.is_open() {
+            continue;
+        }
+
+        let mut leg = StrategyLegCloseDto {
+            position_account: account.to_string(),
+            symbol: position.symbol.clone(),
+            pnl: None,
+            signature: None,
+            error: None,
+        };
+
+        let Some(price) = state.monitor.get_cached_price(&position.symbol).await else {
+            leg.error = Some(format!("Price for {} not found", position.symbol));
+            legs.push(leg);
+            continue;
+        };
+
+        match state.position_manager.close_position(*account, price).await {
+            Ok((leg_pnl, signature)) => {
+                pnl += leg_pnl;
+                leg.pnl = Some(leg_pnl);
+                leg.signature = Some(signature.to_string());
+            }
+            Err(e) => leg.error = Some(format!("Failed to close position: {}", e)),
+        }
+        legs.push(leg);
+    }
+
+    Ok(Json(CloseStrategyResponse { name, pnl, legs }))
+}
+
+/// Cached oracle prices of the given symbols, symbols without one are left out
+async fn cached_marks<'a>(state: &AppState, symbols: impl IntoIterator<Item = &'a str>) -> HashMap<String, Decimal> {
+    let mut marks = HashMap::new();
+    for symbol in symbols.into_iter().collect::<BTreeSet<_>>() {
+        if let Some(price) = state.monitor.get_cached_price(symbol).await {
+            marks.insert(symbol.to_string(), price);
+        }
+    }
+
+    marks
+}
+
+fn mark_external_positions(positions: Vec<ExternalPosition>, marks: &HashMap<String, Decimal>) -> Vec<ExternalPositionDto> {
+    positions
+        .into_iter()
+        .map(|position| {
+            let mark_price = marks.get(&position.symbol).copied();
+            ExternalPositionDto::new(position, mark_price)
+        })
+        .collect()
+}
+
+async fn external_position_dtos(state: &AppState, positions: Vec<ExternalPosition>) -> Vec<ExternalPositionDto> {
+    let marks = cached_marks(state, positions.iter().map(|p| p.symbol.as_str())).await;
+    mark_external_positions(positions, &marks)
+}
+
+/// GET /users/:id/external-positions - A user's off-platform positions, marked to market
+pub async fn list_external_positions(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+) -> Result<Json<Vec<ExternalPositionDto>>, ApiError> {
+    let positions = state
+        .monitor
+        .external_positions()
+        .list(&owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch external positions: {}", e)))?;
+
+    Ok(Json(external_position_dtos(&state, positions).await))
+}
+
+/// Check a request signed in its `WALLET_SIGNATURE_HEADER` and
+/// `WALLET_TIMESTAMP_HEADER` headers over `message(owner, timestamp)`
+async fn verify_signed_headers(
+    state: &AppState,
+    owner: &Pubkey,
+    headers: &HeaderMap,
+    message: fn(&Pubkey, i64) -> String,
+) -> Result<(), ApiError> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| ApiError::Unauthorized(format!("Missing {} header", name)))
+    };
+    let signature = header(WALLET_SIGNATURE_HEADER)?;
+    let timestamp: i64 = header(WALLET_TIMESTAMP_HEADER)?
+        .parse()
+        .map_err(|_| ApiError::Unauthorized(format!("Invalid {} header", WALLET_TIMESTAMP_HEADER)))?;
+
+    verify_owner_signature(state, owner, &message(owner, timestamp), signature, timestamp).await
+}
+
+/// POST /users/:id/external-positions - Replace a user's off-platform positions,
+/// authorized by a wallet signature in the request headers.
+/// Takes {"positions": [...]} as JSON, or a CSV with a header row when the
+/// content type is text/csv. Nothing is sent to the chain
+pub async fn import_external_positions(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<Vec<ExternalPositionDto>>, ApiError> {
+    verify_signed_headers(&state, &owner, &headers, import_external_positions_message).await?;
+
+    let is_csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/csv"));
+
+    let mut request = if is_csv {
+        ExternalPositionsRequest {
+            positions: parse_external_positions_csv(&body)?,
+        }
+    } else {
+        serde_json::from_str(&body)
+            .map_err(|e| ApiError::BadRequest(format!("Invalid external positions: {}", e)))?
+    };
+
+    validation::validate_external_positions(&request).map_err(ApiError::Validation)?;
+    for position in &mut request.positions {
+        position.symbol = symbols::canonicalize(&position.symbol)?;
+    }
+
+    let positions = state
+        .monitor
+        .external_positions()
+        .replace(owner, request.positions)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to store external positions: {}", e)))?;
+
+    Ok(Json(external_position_dtos(&state, positions).await))
+}
+
+/// DELETE /users/:id/external-positions - Drop a user's off-platform positions,
+/// authorized by a wallet signature in the request headers
+pub async fn clear_external_positions(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    verify_signed_headers(&state, &owner, &headers, clear_external_positions_message).await?;
+
+    state
+        .monitor
+        .external_positions()
+        .clear(&owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to clear external positions: {}", e)))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /users/:id/risk - Exposure and unrealized PnL per symbol across the
+/// user's open on-chain positions and their external book
+pub async fn get_user_risk(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+) -> Result<Json<UserRiskDto>, ApiError> {
+    let positions: Vec<_> = state
+        .position_manager
+        .get_user_positions(&owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch positions: {}", e)))?
+        .into_iter()
+        .filter(|position| position.is_open())
+        .collect();
+
+    let external = state
+        .monitor
+        .external_positions()
+        .list(&owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch external positions: {}", e)))?;
+
+    let marks = cached_marks(&state, external.iter().map(|p| p.symbol.as_str())).await;
+    let exposure = consolidate_exposure(&positions, &external, &marks);
+
+    Ok(Json(UserRiskDto::new(
+        owner,
+        exposure,
+        positions.into_iter().map(PositionDto::from).collect(),
+        mark_external_positions(external, &marks),
+    )))
+}
+
+/// Check a wallet-signed request and burn its signature
+async fn verify_owner_signature(
+    state: &AppState,
+    owner: &Pubkey,
+    message: &str,
+    signature: &str,
+    timestamp: i64,
+) -> Result<(), ApiError> {
+    let signature = verify_wallet_signature(owner, message, signature, timestamp, chrono::Utc::now())
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+
+    let fresh = state
+        .api_tokens
+        .consume_signature(&signature)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to record signature: {}", e)))?;
+    if !fresh {
+        return Err(ApiError::Unauthorized("Signature already used".to_string()));
+    }
+
+    Ok(())
+}
+
+/// POST /users/:id/tokens - Mint a read-only API token, authorized by a wallet signature
+pub async fn create_api_token(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+    tenant: CurrentTenant,
+    Json(payload): Json<CreateApiTokenRequest>,
+) -> Result<Json<CreateApiTokenResponse>, ApiError> {
+    validation::validate_api_token_request(&payload).map_err(ApiError::Validation)?;
+
+    let message = create_token_message(&owner, payload.timestamp);
+    verify_owner_signature(&state, &owner, &message, &payload.signature, payload.timestamp).await?;
+
+    let existing = state
+        .api_tokens
+        .list(&tenant.0, &owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch API tokens: {}", e)))?;
+    if existing.len() >= MAX_API_TOKENS_PER_OWNER {
+        return Err(ApiError::BadRequest(format!(
+            "User {} already has {} API tokens",
+            owner, MAX_API_TOKENS_PER_OWNER
+        )));
+    }
+
+    let (api_token, token) = state
+        .api_tokens
+        .create(&tenant.0, owner, payload.label, payload.ttl_secs.unwrap_or(DEFAULT_API_TOKEN_TTL_SECS))
         .await
-        .ok_or_else(|| ApiError::NotFound(format!("Price for {} not found", symbol)))?;
+        .map_err(|e| ApiError::InternalError(format!("Failed to create API token: {}", e)))?;
 
-    Ok(Json(PriceDto {
-        symbol,
-        price,
-        timestamp: chrono::Utc::now(),
+    Ok(Json(CreateApiTokenResponse {
+        token,
+        api_token: ApiTokenDto::from(api_token),
     }))
 }
 
+/// GET /users/:id/tokens - The owner's live API tokens, called with one of them
+pub async fn list_api_tokens(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+    tenant: CurrentTenant,
+    api_token: Option<Extension<ApiToken>>,
+) -> Result<Json<Vec<ApiTokenDto>>, ApiError> {
+    if api_token.is_none() {
+        return Err(ApiError::Unauthorized("API token required".to_string()));
+    }
 
-/// POST /positions/open - Open new position
-pub async fn open_position(
+    let tokens = state
+        .api_tokens
+        .list(&tenant.0, &owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch API tokens: {}", e)))?;
+
+    Ok(Json(tokens.into_iter().map(ApiTokenDto::from).collect()))
+}
+
+/// DELETE /users/:id/tokens/:token_id - Revoke an API token, authorized by a
+/// wallet signature. Returns the revoked token
+pub async fn revoke_api_token(
     State(state): State<AppState>,
-    Json(payload): Json<OpenPositionRequest>,
-) -> Result<Json<OpenPositionResponse>, ApiError> {
-    let owner = Pubkey::from_str(&payload.owner)
-        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+    Path((owner, token_id)): Path<(Pubkey, Uuid)>,
+    tenant: CurrentTenant,
+    Json(payload): Json<RevokeApiTokenRequest>,
+) -> Result<Json<ApiTokenDto>, ApiError> {
+    let message = revoke_token_message(&owner, token_id, payload.timestamp);
+    verify_owner_signature(&state, &owner, &message, &payload.signature, payload.timestamp).await?;
 
-    let (position, signature) = state
-        .position_manager
-        .open_position(
-            owner,
-            payload.symbol,
-            payload.side,
-            payload.size,
-            payload.leverage,
-            payload.entry_price,
-            Decimal::new(25, 3), // Default 2.5%
-        )
+    let api_token = state
+        .api_tokens
+        .revoke(&tenant.0, &owner, token_id)
         .await
-        .map_err(|e| ApiError::InternalError(format!("Failed to open position: {}", e)))?;
+        .map_err(|e| ApiError::InternalError(format!("Failed to revoke API token: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("API token {} not found", token_id)))?;
 
-    Ok(Json(OpenPositionResponse {
-        position: PositionDto::from(position),
-        signature: signature.to_string(),
-    }))
+    Ok(Json(ApiTokenDto::from(api_token)))
 }
 
-/// GET /users/:id/positions - Get user's positions
-pub async fn get_user_positions(
+/// DELETE /users/:id - Close the user account, authorized by a wallet
+/// signature. Needs no open positions, and collateral withdrawn or
+/// `withdraw_collateral` set. The owner's backend data is purged after
+pub async fn close_user_account(
     State(state): State<AppState>,
-    Path(owner): Path<String>,
-) -> Result<Json<Vec<PositionDto>>, ApiError> {
-    let owner = Pubkey::from_str(&owner)
-        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+    Path(owner): Path<Pubkey>,
+    Json(payload): Json<CloseUserAccountRequest>,
+) -> Result<Json<CloseUserAccountResponse>, ApiError> {
+    let message = close_user_message(&owner, payload.timestamp);
+    verify_owner_signature(&state, &owner, &message, &payload.signature, payload.timestamp).await?;
 
-    let positions = state
+    let user_account = state.position_manager.get_user_account(&owner).await?;
+    let withdraw = collateral_to_withdraw(&user_account, payload.withdraw_collateral)?;
+
+    let signature = state
         .position_manager
-        .get_user_positions(&owner)
+        .close_user_account(&owner, withdraw)
         .await
-        .map_err(|e| ApiError::InternalError(format!("Failed to fetch positions: {}", e)))?;
+        .map_err(|e| ApiError::transaction_failed("Failed to close user account", e))?;
 
-    let dtos: Vec<PositionDto> = positions.into_iter().map(PositionDto::from).collect();
+    // The account is gone either way, a failed purge is only logged
+    let purged = match purge_user_data(&state.monitor, &state.api_tokens, &state.risk_limits, &state.tenants, &owner).await {
+        Ok(purged) => Some(PurgedUserDataDto::from(purged)),
+        Err(e) => {
+            tracing::warn!("Failed to purge data of closed user {}: {}", owner, e);
+            None
+        }
+    };
 
-    Ok(Json(dtos))
+    Ok(Json(CloseUserAccountResponse {
+        signature: signature.to_string(),
+        withdrawn_collateral: withdraw,
+        purged,
+    }))
 }
 
-/// POST /users/initialize - Initialize user account
+/// POST /users/initialize - Initialize user account. Under a tenant's key
+/// the user becomes that tenant's, unless another tenant has them
 pub async fn initialize_user(
     State(state): State<AppState>,
+    tenant: CurrentTenant,
     Json(payload): Json<InitializeUserRequest>,
 ) -> Result<Json<InitializeUserResponse>, ApiError> {
     let owner = Pubkey::from_str(&payload.owner)
@@ -205,7 +2303,16 @@ pub async fn initialize_user(
         .position_manager
         .initialize_user(&owner)
         .await
-        .map_err(|e| ApiError::InternalError(format!("Failed to initialize user: {}", e)))?;
+        .map_err(|e| ApiError::transaction_failed("Failed to initialize user", e))?;
+
+    // The account exists either way, a failed claim is only logged
+    if !tenant.is_default() {
+        match state.tenants.claim_owner(&tenant.0, &owner).await {
+            Ok(true) => {}
+            Ok(false) => tracing::info!("User {} already belongs to another tenant", owner),
+            Err(e) => tracing::warn!("Failed to assign user {} to tenant {}: {}", owner, tenant.0, e),
+        }
+    }
 
     Ok(Json(InitializeUserResponse {
         signature: signature.to_string(),
@@ -227,7 +2334,7 @@ pub async fn add_collateral(
         .position_manager
         .add_collateral(&owner, payload.amount)
         .await
-        .map_err(|e| ApiError::InternalError(format!("Failed to add collateral: {}", e)))?;
+        .map_err(|e| ApiError::transaction_failed("Failed to add collateral", e))?;
 
     Ok(Json(AddCollateralResponse {
         signature: signature.to_string(),
@@ -236,6 +2343,186 @@ pub async fn add_collateral(
 }
 
 
+/// GET /users/:id/settings - Defaults filled into the user's open requests,
+/// the API defaults until they set their own
+pub async fn get_user_settings(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+) -> Result<Json<UserSettingsDto>, ApiError> {
+    let owner = Pubkey::from_str(&owner)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+
+    let settings = match state.position_manager.get_user_settings(&owner).await? {
+        Some(settings) => UserSettingsDto::from(settings),
+        None => UserSettingsDto {
+            owner: owner.to_string(),
+            default_slippage_bps: validation::DEFAULT_MAX_SLIPPAGE_BPS as u16,
+            default_leverage: None,
+            margin_mode: MarginMode::Isolated,
+            updated_at: None,
+        },
+    };
+
+    Ok(Json(settings))
+}
+
+
+/// PUT /users/:id/settings - Save the user's defaults on chain
+pub async fn put_user_settings(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Json(payload): Json<UserSettingsRequest>,
+) -> Result<Json<UpdateUserSettingsResponse>, ApiError> {
+    let owner = Pubkey::from_str(&owner)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+    validation::validate_user_settings(&payload).map_err(ApiError::Validation)?;
+
+    let signature = state
+        .position_manager
+        .set_user_settings(
+            &owner,
+            payload.default_slippage_bps,
+            payload.default_leverage,
+            payload.margin_mode,
+        )
+        .await
+        .map_err(|e| ApiError::transaction_failed("Failed to save user settings", e))?;
+
+    Ok(Json(UpdateUserSettingsResponse {
+        settings: UserSettingsDto {
+            owner: owner.to_string(),
+            default_slippage_bps: payload.default_slippage_bps,
+            default_leverage: Some(payload.default_leverage),
+            margin_mode: payload.margin_mode,
+            updated_at: Some(chrono::Utc::now()),
+        },
+        signature: signature.to_string(),
+    }))
+}
+
+
+/// GET /users/:id/fee-tier - The user's 30-day taker volume from the fill
+/// history, the fee tier it earns, the tier they're charged at on chain and
+/// the volume left to the next one
+pub async fn get_fee_tier(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+) -> Result<Json<FeeTierDto>, ApiError> {
+    let history_store = state.history_store()?;
+    let now = chrono::Utc::now();
+    let from = now - chrono::Duration::days(FEE_TIER_WINDOW_DAYS);
+
+    let opens = history_store
+        .position_opens(&owner, now)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch opens: {}", e)))?;
+    let closes = history_store
+        .realized_pnl(&owner, Some(from), Some(now))
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch realized PnL: {}", e)))?;
+    let volume = taker_volumes(&opens, &closes, from)
+        .remove(&owner)
+        .unwrap_or_default();
+
+    let tenant = state
+        .tenants
+        .owner_tenant(&owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch the user's tenant: {}", e)))?;
+    let tier_volumes = state.tenants.fee_tier_volumes(&tenant);
+
+    let schedule = state.position_manager.get_fee_schedule().await?;
+    let assigned = state.position_manager.get_user_fee_tier(&owner).await?;
+    let progress = FeeTierProgress::new(volume, &tier_volumes, &schedule);
+    let assigned_tier = assigned.as_ref().map_or(0, |fee_tier| fee_tier.tier);
+
+    Ok(Json(FeeTierDto {
+        owner: owner.to_string(),
+        volume_30d: progress.volume_30d,
+        tier: progress.tier,
+        taker_fee_bps: progress.taker_fee_bps,
+        assigned_tier,
+        assigned_taker_fee_bps: taker_fee_bps(&schedule, Some(assigned_tier)),
+        fees_paid: assigned.as_ref().map_or(Decimal::ZERO, |fee_tier| fee_tier.fees_paid),
+        assigned_at: assigned.as_ref().map(|fee_tier| fee_tier.updated_at),
+        next_tier: progress.next_tier,
+        next_tier_taker_fee_bps: progress.next_tier_taker_fee_bps,
+        volume_to_next_tier: progress.volume_to_next_tier,
+        tiers: tier_volumes
+            .iter()
+            .zip(schedule)
+            .enumerate()
+            .map(|(tier, (min_volume, taker_fee_bps))| FeeTierLevelDto {
+                tier: tier as u8,
+                min_volume: *min_volume,
+                taker_fee_bps,
+            })
+            .collect(),
+    }))
+}
+
+/// GET /users/:id/risk-limits - The user's risk limits, their utilization
+/// and the limits currently breached
+pub async fn get_risk_limits(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+) -> Result<Json<RiskLimitsDto>, ApiError> {
+    let limits = state
+        .risk_limits
+        .get(&owner)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch risk limits: {}", e)))?
+        .unwrap_or_default();
+
+    risk_limits_dto(&state, owner, limits).await.map(Json)
+}
+
+/// PUT /users/:id/risk-limits - Replace the user's risk limits, authorized
+/// by the owner's wallet signature over them
+pub async fn put_risk_limits(
+    State(state): State<AppState>,
+    Path(owner): Path<Pubkey>,
+    Json(payload): Json<RiskLimitsRequest>,
+) -> Result<Json<RiskLimitsDto>, ApiError> {
+    validation::validate_risk_limits(&payload).map_err(ApiError::Validation)?;
+
+    let limits = RiskLimits {
+        max_position_risk_pct: payload.max_position_risk_pct,
+        max_portfolio_leverage: payload.max_portfolio_leverage,
+        block_opens: payload.block_opens,
+    };
+    let message = set_risk_limits_message(&owner, &limits, payload.timestamp);
+    verify_owner_signature(&state, &owner, &message, &payload.signature, payload.timestamp).await?;
+
+    state
+        .risk_limits
+        .set(&owner, &limits)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to save risk limits: {}", e)))?;
+
+    risk_limits_dto(&state, owner, limits).await.map(Json)
+}
+
+async fn risk_limits_dto(
+    state: &AppState,
+    owner: Pubkey,
+    limits: RiskLimits,
+) -> Result<RiskLimitsDto, ApiError> {
+    let positions = state.monitor.get_user_positions(&owner).await?;
+    let user_account = state.position_manager.get_user_account(&owner).await?;
+    let utilization = RiskUtilization::measure(&positions, user_account.total_collateral);
+    let breaches = limits.breaches(owner, &utilization, chrono::Utc::now());
+
+    Ok(RiskLimitsDto {
+        owner: owner.to_string(),
+        max_position_risk_pct: limits.max_position_risk_pct,
+        max_portfolio_leverage: limits.max_portfolio_leverage,
+        block_opens: limits.block_opens,
+        utilization: RiskUtilizationDto::from(utilization),
+        breaches: breaches.into_iter().map(RiskLimitBreachDto::from).collect(),
+    })
+}
+
 /// GET /users/:id/account - Get user account details
 pub async fn get_user_account(
     State(state): State<AppState>,
@@ -247,8 +2534,7 @@ pub async fn get_user_account(
     let user_account = state
         .position_manager
         .get_user_account(&owner)
-        .await
-        .map_err(|e| ApiError::NotFound(format!("User account not found: {}", e)))?;
+        .await?;
 
     Ok(Json(UserAccountDto {
         owner: user_account.owner.to_string(),
@@ -265,34 +2551,382 @@ pub async fn get_user_account(
 /// GET /positions/:id - Get position details
 pub async fn get_position_details(
     State(state): State<AppState>,
+    PaperSession(session): PaperSession,
+    api_token: Option<Extension<ApiToken>>,
     Path(position_account): Path<String>,  // Now expects pubkey string
 ) -> Result<Json<PositionDto>, ApiError> {
     let position_account = Pubkey::from_str(&position_account)
         .map_err(|e| ApiError::BadRequest(format!("Invalid position account: {}", e)))?;
 
-    let position = state
+    if let Some(session) = session {
+        let position = state
+            .paper_ledger()?
+            .get_position(&session, position_account)
+            .await
+            .ok_or_else(|| ApiError::NotFound(format!("Position {} not found", position_account)))?;
+
+        return Ok(Json(PositionDto::from(position)));
+    }
+
+    let position = match state.position_manager.get_position(position_account).await {
+        Ok(position) => position,
+        // Closed positions evicted from the monitor are read back from the store
+        Err(e) => match &state.history_store {
+            Some(history_store) => history_store
+                .closed_position(&position_account)
+                .await
+                .map_err(|e| ApiError::InternalError(format!("Failed to fetch closed position: {}", e)))?
+                .ok_or(e)?,
+            None => return Err(e.into()),
+        },
+    };
+    authorize_owner(&state, api_token.as_deref(), &position.owner)?;
+
+    Ok(Json(PositionDto::from(position)))
+}
+
+/// GET /users/:id/realized-pnl - Realized PnL events, optionally within ?from=&to=
+pub async fn get_realized_pnl(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(query): Query<RealizedPnlQuery>,
+) -> Result<Json<RealizedPnlResponse>, ApiError> {
+    let owner = Pubkey::from_str(&owner)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+
+    let history_store = state.history_store()?;
+
+    // Unpaginated unless the client asks for pages
+    if query.limit.is_none() && query.cursor.is_none() {
+        let records = history_store
+            .realized_pnl(&owner, query.from, query.to)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to fetch realized PnL: {}", e)))?;
+
+        return Ok(Json(realized_pnl_response(owner, records, None)));
+    }
+
+    let scope = format!("realized_pnl:{}", owner);
+    let after = state
+        .decode_cursor(&scope, query.cursor.as_deref())?
+        .map(|cursor| {
+            let id = cursor
+                .id
+                .parse::<Uuid>()
+                .map_err(|_| ApiError::BadRequest("Invalid cursor".to_string()))?;
+            Ok::<_, ApiError>((cursor.timestamp, id))
+        })
+        .transpose()?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    // One extra row tells whether there is a next page
+    let mut records = history_store
+        .realized_pnl_page(&owner, query.from, query.to, after, limit + 1)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch realized PnL: {}", e)))?;
+
+    let next_cursor = if records.len() as i64 > limit {
+        records.truncate(limit as usize);
+        records.last().map(|last| {
+            state.cursor_signer.encode(
+                &scope,
+                &Cursor {
+                    timestamp: last.closed_at,
+                    id: last.id.to_string(),
+                },
+            )
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(realized_pnl_response(owner, records, next_cursor)))
+}
+
+fn realized_pnl_response(
+    owner: Pubkey,
+    records: Vec<crate::domain::RealizedPnL>,
+    next_cursor: Option<String>,
+) -> RealizedPnlResponse {
+    let total = records.iter().map(|record| record.amount).sum();
+
+    RealizedPnlResponse {
+        owner: owner.to_string(),
+        total,
+        records: records.into_iter().map(RealizedPnlDto::from).collect(),
+        next_cursor,
+    }
+}
+
+/// GET /positions/history - Closed and liquidated positions from the history
+/// store, newest first, in cursor pages
+pub async fn get_position_history(
+    State(state): State<AppState>,
+    Query(query): Query<PositionHistoryQuery>,
+) -> Result<Json<PositionPageDto>, ApiError> {
+    let settled = query
+        .status
+        .is_none_or(|status| matches!(status, PositionStatus::Closed | PositionStatus::Liquidated));
+
+    if !settled {
+        return Err(ApiError::BadRequest(
+            "History only holds closed and liquidated positions".to_string(),
+        ));
+    }
+
+    closed_position_page(&state, "positions_history", query).await.map(Json)
+}
+
+/// GET /liquidations - Liquidated positions from the history store, newest
+/// first, in cursor pages
+pub async fn get_liquidations(
+    State(state): State<AppState>,
+    Query(mut query): Query<PositionHistoryQuery>,
+) -> Result<Json<PositionPageDto>, ApiError> {
+    query.status = Some(PositionStatus::Liquidated);
+
+    closed_position_page(&state, "liquidations", query).await.map(Json)
+}
+
+async fn closed_position_page(
+    state: &AppState,
+    scope: &str,
+    query: PositionHistoryQuery,
+) -> Result<PositionPageDto, ApiError> {
+    let history_store = state.history_store()?;
+
+    let owner = query
+        .owner
+        .as_deref()
+        .map(Pubkey::from_str)
+        .transpose()
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+
+    let before = state
+        .decode_cursor(scope, query.cursor.as_deref())?
+        .map(|cursor| {
+            let account = cursor
+                .id
+                .parse::<Pubkey>()
+                .map_err(|_| ApiError::BadRequest("Invalid cursor".to_string()))?;
+            Ok::<_, ApiError>((cursor.timestamp, account))
+        })
+        .transpose()?;
+
+    let filter = ClosedPositionFilter {
+        owner,
+        symbol: query.symbol.as_deref().map(symbols::normalize),
+        status: query.status,
+        before,
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    // One extra row tells whether there is a next page
+    let mut positions = history_store
+        .closed_positions(&filter, limit + 1)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch closed positions: {}", e)))?;
+
+    let next_cursor = if positions.len() as i64 > limit {
+        positions.truncate(limit as usize);
+        positions.last().and_then(|last| {
+            let closed_at = last.closed_at?;
+            Some(state.cursor_signer.encode(
+                scope,
+                &Cursor {
+                    timestamp: closed_at,
+                    id: last.position_account.to_string(),
+                },
+            ))
+        })
+    } else {
+        None
+    };
+
+    Ok(PositionPageDto {
+        positions: positions.into_iter().map(PositionDto::from).collect(),
+        next_cursor,
+    })
+}
+
+/// GET /users/:id/reports/tax - Per-lot opens, closes, fees, funding and
+/// realized PnL for a calendar year, streamed as CSV
+pub async fn get_tax_report(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(query): Query<TaxReportQuery>,
+) -> Result<Response, ApiError> {
+    let owner = Pubkey::from_str(&owner)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+
+    let format = query.format.as_deref().unwrap_or("csv");
+    if format != "csv" {
+        return Err(ApiError::BadRequest(format!("Unsupported report format: {}", format)));
+    }
+
+    let history_store = state
+        .history_store
+        .as_ref()
+        .ok_or_else(|| ApiError::InternalError("History store not configured".to_string()))?;
+
+    let year = query.year.unwrap_or_else(|| chrono::Utc::now().year());
+    let (start, end) = year_range(year).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    let opens = history_store
+        .position_opens(&owner, end)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch opens: {}", e)))?;
+    let closes = history_store
+        .realized_pnl(&owner, Some(start), Some(end))
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch realized PnL: {}", e)))?;
+
+    let lots = build_tax_lots(opens, closes, year)
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+
+    let rows = std::iter::once(format!("{}\n", TAX_CSV_HEADER))
+        .chain(lots.into_iter().map(|lot| lot.csv_row()))
+        .map(Ok::<_, std::convert::Infallible>);
+
+    let filename = format!("attachment; filename=\"tax-{}-{}.csv\"", owner, year);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv".to_string()),
+            (header::CONTENT_DISPOSITION, filename),
+        ],
+        Body::from_stream(futures::stream::iter(rows)),
+    )
+        .into_response())
+}
+
+/// GET /positions/:id/pnl-history - Bucketed PnL series for charts
+pub async fn get_pnl_history(
+    State(state): State<AppState>,
+    Path(position_account): Path<String>,
+    Query(query): Query<PnlHistoryQuery>,
+) -> Result<Json<Vec<PnlPointDto>>, ApiError> {
+    let position_account = Pubkey::from_str(&position_account)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid position account: {}", e)))?;
+
+    let history_store = state
+        .history_store
+        .as_ref()
+        .ok_or_else(|| ApiError::InternalError("History store not configured".to_string()))?;
+
+    let interval = parse_interval(query.interval.as_deref().unwrap_or("5m"))
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let limit = query.limit.unwrap_or(288).clamp(1, 2000);
+
+    let points = history_store
+        .pnl_history(&position_account, interval, limit)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch PnL history: {}", e)))?;
+
+    Ok(Json(points.into_iter().map(PnlPointDto::from).collect()))
+}
+
+/// GET /positions/:id/timeline - Everything recorded for a position, oldest first
+pub async fn get_position_timeline(
+    State(state): State<AppState>,
+    Path(position_account): Path<String>,
+) -> Result<Json<Vec<TimelineEntryDto>>, ApiError> {
+    let position_account = Pubkey::from_str(&position_account)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid position account: {}", e)))?;
+
+    let history_store = state.history_store()?;
+
+    let (open, realized, events) = tokio::try_join!(
+        history_store.position_open(&position_account),
+        history_store.position_realized_pnl(&position_account),
+        history_store.position_events(&position_account),
+    )
+    .map_err(|e| ApiError::InternalError(format!("Failed to fetch position history: {}", e)))?;
+
+    if open.is_none() && realized.is_empty() && events.is_empty() {
+        return Err(ApiError::NotFound(format!("No history for position {}", position_account)));
+    }
+
+    let timeline = build_position_timeline(open, realized, events);
+
+    Ok(Json(timeline.into_iter().map(TimelineEntryDto::from).collect()))
+}
+
+/// GET /positions/:id/onchain-history - Size and margin changes kept in the position account
+pub async fn get_position_onchain_history(
+    State(state): State<AppState>,
+    Path(position_account): Path<String>,
+) -> Result<Json<OnChainHistoryDto>, ApiError> {
+    let position_account = Pubkey::from_str(&position_account)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid position account: {}", e)))?;
+
+    let history = state
         .position_manager
-        .get_position(position_account)  // Uses Pubkey
+        .get_position_history(&position_account)
         .await
-        .map_err(|e| ApiError::NotFound(format!("Position not found: {}", e)))?;
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch position account: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound(format!("No position account {}", position_account)))?;
 
-    Ok(Json(PositionDto::from(position)))
+    Ok(Json(OnChainHistoryDto::from(history)))
 }
 
 /// PUT /positions/:id/modify - Modify position
 pub async fn modify_position(
     State(state): State<AppState>,
+    PaperSession(session): PaperSession,
     Path(position_account): Path<String>,  // Now expects pubkey string
     Json(payload): Json<ModifyPositionRequest>,
 ) -> Result<Json<ModifyPositionResponse>, ApiError> {
     let position_account = Pubkey::from_str(&position_account)
         .map_err(|e| ApiError::BadRequest(format!("Invalid position account: {}", e)))?;
 
+    validation::validate_modify_position(&payload).map_err(ApiError::Validation)?;
+
+    if let Some(session) = session {
+        state
+            .paper_ledger()?
+            .modify_position(&session, position_account, payload.new_size, payload.margin_delta)
+            .await
+            .map_err(|e| ApiError::transaction_failed("Failed to modify paper position", e))?;
+
+        return Ok(Json(ModifyPositionResponse {
+            signature: paper_signature(),
+            message: "Paper position modified successfully".to_string(),
+        }));
+    }
+
+    let position = state.position_manager.get_position(position_account).await?;
+    let added_size = payload
+        .new_size
+        .map_or(Decimal::ZERO, |new_size| (new_size - position.size).max(Decimal::ZERO));
+    let withdrawn_margin = payload.margin_delta.is_some_and(|delta| delta < 0);
+
+    // Shrinking or topping up goes through a halt, as it does on chain
+    if added_size > Decimal::ZERO || withdrawn_margin {
+        state.ensure_trading()?;
+    }
+    if added_size > Decimal::ZERO {
+        state.ensure_open_interest(&position.symbol, added_size).await?;
+    }
+
+    // More size, or more margin at stake, is more exposure
+    let added_margin = payload.margin_delta.is_some_and(|delta| delta > 0);
+    if added_size > Decimal::ZERO || added_margin {
+        let margin = position.margin + payload.margin_delta.map_or(Decimal::ZERO, |delta| Decimal::new(delta, 6));
+        let notional = payload.new_size.unwrap_or(position.size) * position.mark_price;
+        state
+            .ensure_risk_limits(&position.owner, |utilization| {
+                utilization.with_modified(position_account, margin, notional)
+            })
+            .await?;
+    }
+
     let signature = state
         .position_manager
         .modify_position(position_account, payload.new_size, payload.margin_delta)
         .await
-        .map_err(|e| ApiError::InternalError(format!("Failed to modify position: {}", e)))?;
+        .map_err(|e| ApiError::transaction_failed("Failed to modify position", e))?;
 
     Ok(Json(ModifyPositionResponse {
         signature: signature.to_string(),
@@ -303,21 +2937,214 @@ pub async fn modify_position(
 /// DELETE /positions/:id/close - Close position
 pub async fn close_position(
     State(state): State<AppState>,
+    PaperSession(session): PaperSession,
     Path(position_account): Path<String>,  // Now expects pubkey string
     Json(payload): Json<ClosePositionRequest>,
 ) -> Result<Json<ClosePositionResponse>, ApiError> {
     let position_account = Pubkey::from_str(&position_account)
         .map_err(|e| ApiError::BadRequest(format!("Invalid position account: {}", e)))?;
 
+    if let Some(session) = session {
+        let pnl = state
+            .paper_ledger()?
+            .close_position(&session, position_account, payload.final_price)
+            .await
+            .map_err(|e| ApiError::transaction_failed("Failed to close paper position", e))?;
+
+        return Ok(Json(ClosePositionResponse {
+            pnl,
+            signature: paper_signature(),
+            message: "Paper position closed successfully".to_string(),
+        }));
+    }
+
+    // Closing only takes risk off, so it goes through a halt
     let (pnl, signature) = state
         .position_manager
         .close_position(position_account, payload.final_price)
         .await
-        .map_err(|e| ApiError::InternalError(format!("Failed to close position: {}", e)))?;
+        .map_err(|e| ApiError::transaction_failed("Failed to close position", e))?;
 
     Ok(Json(ClosePositionResponse {
         pnl,
         signature: signature.to_string(),
         message: "Position closed successfully".to_string(),
     }))
-}
\ No newline at end of file
+}
+
+/// GET /liquidators - Get the active liquidator set
+pub async fn get_liquidators(
+    State(state): State<AppState>,
+) -> Result<Json<LiquidatorSetDto>, ApiError> {
+    let liquidator_set = state
+        .position_manager
+        .get_liquidator_set()
+        .await?;
+
+    Ok(Json(LiquidatorSetDto {
+        admin: liquidator_set.admin.to_string(),
+        whitelist_enabled: liquidator_set.whitelist_enabled,
+        liquidators: liquidator_set
+            .liquidators
+            .iter()
+            .map(|key| key.to_string())
+            .collect(),
+        partial_liquidation_enabled: liquidator_set.partial_liquidation_enabled,
+        liquidation_buffer_bps: liquidator_set.liquidation_buffer_bps,
+    }))
+}
+
+/// GET /exchange-info - Markets, their sizes, margins and oracle feeds, the
+/// leverage tiers, the fee schedule and the halt status in one response
+pub async fn get_exchange_info(
+    State(state): State<AppState>,
+    tenant: CurrentTenant,
+) -> Result<Json<ExchangeInfoDto>, ApiError> {
+    let halt = state.trading_halt.status();
+    let status = if halt.is_halted() { "halted" } else { "trading" };
+
+    let mut symbols = state.monitor.get_monitored_symbols().await;
+    symbols.sort();
+
+    let mut markets = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let market = state
+            .position_manager
+            .get_market(&symbol)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to fetch market: {}", e)))?;
+        let settings = state.monitor.symbol_configs().settings(&symbol);
+
+        markets.push(ExchangeMarketDto {
+            oracle_feed_id: state.monitor.oracle_feed_id(&symbol).await,
+            status: status.to_string(),
+            tick_size: Decimal::new(1, PRICE_DP),
+            step_size: Decimal::new(1, SIZE_DP),
+            market: market.as_ref().map(|market| market.address.to_string()),
+            maintenance_margin_bps: market.as_ref().map(|market| market.maintenance_margin_bps),
+            min_maintenance_margin_bps: market.as_ref().map(|market| market.min_maintenance_margin_bps),
+            max_maintenance_margin_bps: market.as_ref().map(|market| market.max_maintenance_margin_bps),
+            maintenance_margin_ratio: settings.maintenance_margin_ratio,
+            max_open_interest: settings.max_open_interest,
+            open_cooldown_slots: market.as_ref().map_or(0, |market| market.open_cooldown_slots),
+            max_opens_per_slot: market.as_ref().map_or(0, |market| market.max_opens_per_slot),
+            symbol,
+        });
+    }
+
+    let schedule = state.position_manager.get_fee_schedule().await?;
+    let max_price_age_secs = state.position_manager.get_max_price_age_secs().await?;
+
+    Ok(Json(ExchangeInfoDto {
+        server_time: chrono::Utc::now(),
+        halt: TradingHaltDto::from(halt),
+        markets,
+        leverage_tiers: LEVERAGE_TIERS
+            .iter()
+            .map(|tier| LeverageTierDto {
+                max_leverage: tier.max_leverage,
+                initial_margin_bps: tier.initial_margin_rate,
+                maintenance_margin_bps: tier.maintenance_margin_rate,
+                max_notional: tier.max_notional(),
+            })
+            .collect(),
+        fee_tiers: state
+            .tenants
+            .fee_tier_volumes(&tenant.0)
+            .iter()
+            .zip(schedule)
+            .enumerate()
+            .map(|(tier, (min_volume, taker_fee_bps))| FeeTierLevelDto {
+                tier: tier as u8,
+                min_volume: *min_volume,
+                taker_fee_bps,
+            })
+            .collect(),
+        funding_interval_secs: None,
+        max_price_age_secs,
+    }))
+}
+
+/// GET /vault - Backstop vault TVL and utilization
+pub async fn get_backstop_vault(
+    State(state): State<AppState>,
+) -> Result<Json<BackstopVaultDto>, ApiError> {
+    let vault = state.position_manager.get_backstop_vault().await?;
+
+    Ok(Json(BackstopVaultDto {
+        address: vault.address.to_string(),
+        tvl: vault.total_assets,
+        total_shares: vault.total_shares,
+        share_price: vault.share_price(),
+        locked_margin: vault.locked_margin,
+        available_assets: vault.available_assets(),
+        utilization: vault.utilization(),
+        realized_pnl: vault.realized_pnl,
+        discount_bps: vault.discount_bps,
+        open_positions: vault.open_positions,
+    }))
+}
+
+/// GET /vault/nav - Vault NAV including unrealized PnL of absorbed positions
+pub async fn get_vault_nav(
+    State(state): State<AppState>,
+) -> Result<Json<VaultNavDto>, ApiError> {
+    let nav = state
+        .backstop_vault()?
+        .latest_nav()
+        .await
+        .ok_or_else(|| ApiError::NotFound("Vault NAV not computed yet".to_string()))?;
+
+    Ok(Json(VaultNavDto::from(nav)))
+}
+
+/// GET /vault/returns - Share price return over a trailing window
+pub async fn get_vault_returns(
+    State(state): State<AppState>,
+    Query(query): Query<VaultReturnsQuery>,
+) -> Result<Json<VaultReturnDto>, ApiError> {
+    let history_store = state.history_store()?;
+
+    let window = parse_interval(query.window.as_deref().unwrap_or("30d"))
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let to = chrono::Utc::now();
+
+    let snapshots = history_store
+        .vault_snapshots(to - window, to)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Failed to fetch vault snapshots: {}", e)))?;
+
+    let vault_return = vault_return(&snapshots)
+        .ok_or_else(|| ApiError::NotFound("Not enough vault history for this window".to_string()))?;
+
+    Ok(Json(VaultReturnDto {
+        from: vault_return.from,
+        to: vault_return.to,
+        start_share_price: vault_return.start_share_price,
+        end_share_price: vault_return.end_share_price,
+        period_return: vault_return.period_return,
+        annualized_return: vault_return.annualized_return,
+    }))
+}
+
+/// GET /vault/lp/:owner - An LP's vault shares and earnings
+pub async fn get_lp_account(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+) -> Result<Json<LpAccountDto>, ApiError> {
+    let owner = Pubkey::from_str(&owner)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid owner pubkey: {}", e)))?;
+
+    let vault = state.position_manager.get_backstop_vault().await?;
+    let lp_account = state.position_manager.get_lp_account(&owner).await?;
+
+    let value = lp_account.shares * vault.share_price();
+
+    Ok(Json(LpAccountDto {
+        owner: lp_account.owner.to_string(),
+        shares: lp_account.shares,
+        value,
+        cost_basis: lp_account.cost_basis,
+        earnings: (value - lp_account.cost_basis).max(Decimal::ZERO),
+    }))
+}
@@ -3,6 +3,20 @@ pub mod routes;
 pub mod websocket;
 pub mod dto;
 pub mod errors;
+pub mod position_diff;
+pub mod throttle;
+pub mod ws_rooms;
+pub mod validation;
+pub mod request_id;
+pub mod paper;
+pub mod cursor;
+pub mod precision;
+pub mod admin;
+pub mod api_token;
+pub mod tenant;
+pub mod graphql;
+pub mod fix_codec;
+pub mod fix_gateway;
 
 pub use routes::create_router;
 pub use errors::ApiError;
@@ -0,0 +1,55 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+
+use crate::api::errors::ApiError;
+
+/// Header that routes a request to the caller's paper trading session
+pub const PAPER_SESSION_HEADER: &str = "x-paper-session";
+
+const MAX_SESSION_LENGTH: usize = 64;
+
+/// Paper trading session named by the request, none for live trading
+pub struct PaperSession(pub Option<String>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for PaperSession {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(PAPER_SESSION_HEADER) else {
+            return Ok(PaperSession(None));
+        };
+
+        value
+            .to_str()
+            .ok()
+            .filter(|session| is_valid_session(session))
+            .map(|session| PaperSession(Some(session.to_string())))
+            .ok_or_else(|| {
+                ApiError::BadRequest(format!(
+                    "{} must be 1-{} characters of [A-Za-z0-9_-]",
+                    PAPER_SESSION_HEADER, MAX_SESSION_LENGTH
+                ))
+            })
+    }
+}
+
+pub fn is_valid_session(session: &str) -> bool {
+    !session.is_empty()
+        && session.len() <= MAX_SESSION_LENGTH
+        && session
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_session() {
+        assert!(is_valid_session("strategy-test_01"));
+        assert!(!is_valid_session(""));
+        assert!(!is_valid_session("has:colon"));
+        assert!(!is_valid_session(&"a".repeat(MAX_SESSION_LENGTH + 1)));
+    }
+}
@@ -0,0 +1,285 @@
+/// Position Diff Tracker
+/// Per-connection state for diff-mode position streams: remembers what the
+/// client was last sent and only emits fields that moved past a threshold,
+/// with a periodic full frame so clients can resync
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+use crate::api::dto::{PositionDiffDto, PositionUpdateDto};
+use crate::services::PositionUpdate;
+
+#[derive(Debug, Clone)]
+pub struct DiffThresholds {
+    /// Minimum PnL move in USD
+    pub unrealized_pnl: Decimal,
+    /// Minimum margin ratio move (0.0001 = 1bp)
+    pub margin_ratio: Decimal,
+    /// How often a full frame is sent per position
+    pub full_refresh_interval: Duration,
+}
+
+impl Default for DiffThresholds {
+    fn default() -> Self {
+        Self {
+            unrealized_pnl: Decimal::new(1, 2), // $0.01
+            margin_ratio: Decimal::new(1, 4),   // 1bp
+            full_refresh_interval: Duration::seconds(30),
+        }
+    }
+}
+
+// Longest full refresh interval a client can ask for
+const MAX_FULL_REFRESH_SECS: i64 = 3600;
+
+impl DiffThresholds {
+    /// Thresholds a client asked for, defaults for the ones it left out
+    pub fn from_client(
+        unrealized_pnl: Option<Decimal>,
+        margin_ratio: Option<Decimal>,
+        full_refresh_secs: Option<i64>,
+    ) -> Result<Self, String> {
+        let defaults = Self::default();
+
+        if unrealized_pnl.is_some_and(|threshold| threshold.is_sign_negative()) {
+            return Err("pnl_threshold must not be negative".to_string());
+        }
+        if margin_ratio.is_some_and(|threshold| threshold.is_sign_negative()) {
+            return Err("margin_ratio_threshold must not be negative".to_string());
+        }
+        if full_refresh_secs.is_some_and(|secs| !(1..=MAX_FULL_REFRESH_SECS).contains(&secs)) {
+            return Err(format!(
+                "full_refresh_secs must be between 1 and {}",
+                MAX_FULL_REFRESH_SECS
+            ));
+        }
+
+        Ok(Self {
+            unrealized_pnl: unrealized_pnl.unwrap_or(defaults.unrealized_pnl),
+            margin_ratio: margin_ratio.unwrap_or(defaults.margin_ratio),
+            full_refresh_interval: full_refresh_secs
+                .map(Duration::seconds)
+                .unwrap_or(defaults.full_refresh_interval),
+        })
+    }
+}
+
+pub enum PositionFrame {
+    Full(PositionUpdateDto),
+    Diff(PositionDiffDto),
+}
+
+struct LastSent {
+    symbol: String,
+    size: Decimal,
+    entry_price: Decimal,
+    effective_liquidation_price: Option<Decimal>,
+    unrealized_pnl: Decimal,
+    margin_ratio: Decimal,
+    full_sent_at: DateTime<Utc>,
+}
+
+pub struct PositionDiffTracker {
+    thresholds: DiffThresholds,
+    last_sent: HashMap<Pubkey, LastSent>,
+}
+
+impl PositionDiffTracker {
+    pub fn new(thresholds: DiffThresholds) -> Self {
+        Self {
+            thresholds,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// Frame to send for this update, None when nothing moved enough
+    pub fn next_frame(&mut self, update: &PositionUpdate) -> Option<PositionFrame> {
+        let Some(last) = self.last_sent.get_mut(&update.position_account) else {
+            self.forget_stale(update.timestamp);
+            self.last_sent
+                .insert(update.position_account, LastSent::full(update));
+            return Some(PositionFrame::Full(PositionUpdateDto::from(update)));
+        };
+
+        if update.timestamp - last.full_sent_at >= self.thresholds.full_refresh_interval {
+            *last = LastSent::full(update);
+            return Some(PositionFrame::Full(PositionUpdateDto::from(update)));
+        }
+
+        let size = (update.size != last.size).then_some(update.size);
+        let entry_price = (update.entry_price != last.entry_price).then_some(update.entry_price);
+        // Moves with margin, size and funding, not with the mark
+        let effective_liquidation_price = update
+            .effective_liquidation_price
+            .filter(|price| last.effective_liquidation_price != Some(*price));
+        let unrealized_pnl = ((update.unrealized_pnl - last.unrealized_pnl).abs()
+            > self.thresholds.unrealized_pnl)
+            .then_some(update.unrealized_pnl);
+        // Floored at 0 as in full frames
+        let display_ratio = update.margin_ratio.max(Decimal::ZERO);
+        let margin_ratio = ((display_ratio - last.margin_ratio).abs()
+            > self.thresholds.margin_ratio)
+            .then_some(display_ratio);
+
+        if size.is_none()
+            && entry_price.is_none()
+            && effective_liquidation_price.is_none()
+            && unrealized_pnl.is_none()
+            && margin_ratio.is_none()
+        {
+            return None;
+        }
+
+        // Compare against what was sent, not what was seen, so slow drift still goes out
+        last.size = size.unwrap_or(last.size);
+        last.entry_price = entry_price.unwrap_or(last.entry_price);
+        last.effective_liquidation_price =
+            effective_liquidation_price.or(last.effective_liquidation_price);
+        last.unrealized_pnl = unrealized_pnl.unwrap_or(last.unrealized_pnl);
+        last.margin_ratio = margin_ratio.unwrap_or(last.margin_ratio);
+
+        Some(PositionFrame::Diff(PositionDiffDto {
+            position_account: update.position_account.to_string(),
+            symbol: update.symbol.clone(),
+            size,
+            entry_price,
+            effective_liquidation_price,
+            mark_price: Some(update.mark_price),
+            unrealized_pnl,
+            margin_ratio,
+            timestamp: update.timestamp,
+        }))
+    }
+
+    /// Drop a position the client no longer follows, its next update is a full frame
+    pub fn forget(&mut self, position_account: &Pubkey) {
+        self.last_sent.remove(position_account);
+    }
+
+    /// Drop every position of a symbol the client unsubscribed from
+    pub fn forget_symbol(&mut self, symbol: &str) {
+        self.last_sent.retain(|_, last| last.symbol != symbol);
+    }
+
+    /// Open positions get a full frame every interval, so one without for two
+    /// intervals was closed or is no longer followed
+    fn forget_stale(&mut self, now: DateTime<Utc>) {
+        let horizon = self.thresholds.full_refresh_interval * 2;
+        self.last_sent.retain(|_, last| now - last.full_sent_at <= horizon);
+    }
+
+    #[cfg(test)]
+    fn tracked(&self) -> usize {
+        self.last_sent.len()
+    }
+}
+
+impl LastSent {
+    fn full(update: &PositionUpdate) -> Self {
+        Self {
+            symbol: update.symbol.clone(),
+            size: update.size,
+            entry_price: update.entry_price,
+            effective_liquidation_price: update.effective_liquidation_price,
+            unrealized_pnl: update.unrealized_pnl,
+            margin_ratio: update.margin_ratio.max(Decimal::ZERO),
+            full_sent_at: update.timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Side;
+
+    fn update(unrealized_pnl: Decimal, margin_ratio: Decimal, seconds: i64) -> PositionUpdate {
+        PositionUpdate {
+            position_account: Pubkey::default(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size: Decimal::ONE,
+            entry_price: Decimal::new(100, 0),
+            mark_price: Decimal::new(100, 0) + unrealized_pnl,
+            unrealized_pnl,
+            margin: Decimal::new(10, 0),
+            margin_ratio,
+            liquidation_price: Decimal::new(92, 0),
+            effective_liquidation_price: Some(Decimal::new(91, 0)),
+            timestamp: DateTime::<Utc>::from_timestamp(1_700_000_000 + seconds, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_first_frame_is_full_then_small_moves_are_dropped() {
+        let mut tracker = PositionDiffTracker::new(DiffThresholds::default());
+
+        assert!(matches!(
+            tracker.next_frame(&update(Decimal::ZERO, Decimal::new(1, 1), 0)),
+            Some(PositionFrame::Full(_))
+        ));
+
+        // $0.005 and 0.5bp are below the default thresholds
+        assert!(tracker
+            .next_frame(&update(Decimal::new(5, 3), Decimal::new(10005, 5), 2))
+            .is_none());
+    }
+
+    #[test]
+    fn test_diff_carries_only_changed_fields() {
+        let mut tracker = PositionDiffTracker::new(DiffThresholds::default());
+        tracker.next_frame(&update(Decimal::ZERO, Decimal::new(1, 1), 0));
+
+        match tracker.next_frame(&update(Decimal::new(5, 1), Decimal::new(1, 1), 2)) {
+            Some(PositionFrame::Diff(diff)) => {
+                assert_eq!(diff.unrealized_pnl, Some(Decimal::new(5, 1)));
+                assert_eq!(diff.margin_ratio, None);
+                assert_eq!(diff.size, None);
+                assert_eq!(diff.effective_liquidation_price, None);
+            }
+            _ => panic!("expected a diff frame"),
+        }
+    }
+
+    #[test]
+    fn test_periodic_full_refresh() {
+        let mut tracker = PositionDiffTracker::new(DiffThresholds::default());
+        tracker.next_frame(&update(Decimal::ZERO, Decimal::new(1, 1), 0));
+
+        assert!(matches!(
+            tracker.next_frame(&update(Decimal::ZERO, Decimal::new(1, 1), 30)),
+            Some(PositionFrame::Full(_))
+        ));
+    }
+
+    #[test]
+    fn test_closed_and_unsubscribed_positions_are_forgotten() {
+        let mut tracker = PositionDiffTracker::new(DiffThresholds::default());
+        tracker.next_frame(&update(Decimal::ZERO, Decimal::new(1, 1), 0));
+
+        tracker.forget_symbol("SOL-USD");
+        assert_eq!(tracker.tracked(), 0);
+
+        // A position that stopped updating goes once another one shows up
+        tracker.next_frame(&update(Decimal::ZERO, Decimal::new(1, 1), 0));
+        let other = PositionUpdate {
+            position_account: Pubkey::new_unique(),
+            ..update(Decimal::ZERO, Decimal::new(1, 1), 61)
+        };
+        tracker.next_frame(&other);
+        assert_eq!(tracker.tracked(), 1);
+    }
+
+    #[test]
+    fn test_client_thresholds_are_checked() {
+        assert!(DiffThresholds::from_client(Some(Decimal::new(-1, 2)), None, None).is_err());
+        assert!(DiffThresholds::from_client(None, Some(Decimal::new(-1, 4)), None).is_err());
+        assert!(DiffThresholds::from_client(None, None, Some(0)).is_err());
+        assert!(DiffThresholds::from_client(None, None, Some(i64::MAX)).is_err());
+
+        let thresholds = DiffThresholds::from_client(Some(Decimal::ONE), None, Some(5)).unwrap();
+        assert_eq!(thresholds.unrealized_pnl, Decimal::ONE);
+        assert_eq!(thresholds.full_refresh_interval, Duration::seconds(5));
+    }
+}
@@ -0,0 +1,127 @@
+/// Decimal Precision
+/// Response decimals are rounded and padded to a fixed scale per kind of
+/// value, so a price is always 6 decimal places whatever division produced it.
+/// Output never depends on locale: `.` separator, no grouping
+/// `?raw=true` on any request (or the WebSocket URL) turns this off for the
+/// response, giving the full internal scale
+use axum::{extract::Request, middleware::Next, response::Response};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Serializer;
+use std::future::Future;
+
+/// Prices and quote-currency amounts (PnL, margin, fees)
+pub const PRICE_DP: u32 = 6;
+/// Position sizes in base units
+pub const SIZE_DP: u32 = 8;
+/// Margin ratios and other fractions, 0.0525 = 5.25%
+pub const RATIO_DP: u32 = 4;
+/// Values already in percent, 5.25 = 5.25%
+pub const PCT_DP: u32 = 2;
+
+tokio::task_local! {
+    static RAW_DECIMALS: bool;
+}
+
+/// Run `future` with normalization turned off when `raw`
+pub async fn with_raw_decimals<F: Future>(raw: bool, future: F) -> F::Output {
+    RAW_DECIMALS.scope(raw, future).await
+}
+
+/// Read `?raw=true` for the request, responses serialize inside the scope
+pub async fn raw_decimals(request: Request, next: Next) -> Response {
+    let raw = request.uri().query().is_some_and(is_raw_query);
+    with_raw_decimals(raw, next.run(request)).await
+}
+
+pub fn is_raw_query(query: &str) -> bool {
+    query
+        .split('&')
+        .any(|pair| matches!(pair.split_once('='), Some(("raw", "true" | "1"))))
+}
+
+/// `value` at exactly `dp` places, half away from zero like the program's display
+pub fn normalize(value: Decimal, dp: u32) -> Decimal {
+    if RAW_DECIMALS.try_with(|raw| *raw).unwrap_or(false) {
+        return value;
+    }
+
+    let mut value = value.round_dp_with_strategy(dp, RoundingStrategy::MidpointAwayFromZero);
+    value.rescale(dp);
+    value
+}
+
+fn serialize<S: Serializer>(value: Decimal, dp: u32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&normalize(value, dp))
+}
+
+fn serialize_opt<S: Serializer>(
+    value: &Option<Decimal>,
+    dp: u32,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serialize(*value, dp, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// `#[serde(serialize_with = "precision::price")]`
+pub fn price<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize(*value, PRICE_DP, serializer)
+}
+
+pub fn size<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize(*value, SIZE_DP, serializer)
+}
+
+pub fn ratio<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize(*value, RATIO_DP, serializer)
+}
+
+pub fn pct<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize(*value, PCT_DP, serializer)
+}
+
+pub fn price_opt<S: Serializer>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_opt(value, PRICE_DP, serializer)
+}
+
+pub fn size_opt<S: Serializer>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_opt(value, SIZE_DP, serializer)
+}
+
+pub fn ratio_opt<S: Serializer>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_opt(value, RATIO_DP, serializer)
+}
+
+pub fn pct_opt<S: Serializer>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_opt(value, PCT_DP, serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_normalize_fixes_scale() {
+        assert_eq!(normalize(dec!(150), PRICE_DP).to_string(), "150.000000");
+        assert_eq!(
+            normalize(dec!(1) / dec!(3), RATIO_DP).to_string(),
+            "0.3333"
+        );
+        assert_eq!(normalize(dec!(-0.000000005), SIZE_DP).to_string(), "-0.00000001");
+        assert_eq!(normalize(dec!(2.00005), RATIO_DP).to_string(), "2.0001");
+    }
+
+    #[tokio::test]
+    async fn test_raw_keeps_full_scale() {
+        let third = dec!(1) / dec!(3);
+        let raw = with_raw_decimals(true, async { normalize(third, RATIO_DP) }).await;
+        assert_eq!(raw, third);
+
+        assert!(is_raw_query("symbol=SOL-USD&raw=true"));
+        assert!(!is_raw_query("raw=false"));
+        assert!(!is_raw_query("draw=true"));
+    }
+}
@@ -0,0 +1,67 @@
+use axum::{
+    extract::Request,
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use tracing::{info_span, warn, Instrument};
+
+use crate::infrastructure::{with_request_id, REQUEST_ID_HEADER};
+
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Assign each request a correlation id, reusing the caller's `x-request-id`
+/// when it is sane, and echo it back so clients can quote it in reports
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+
+    let mut response = with_request_id(request_id.clone(), next.run(request))
+        .instrument(span.clone())
+        .await;
+
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        span.in_scope(|| warn!("Request failed with {}", status));
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_request_id() {
+        assert!(is_valid_request_id("3f2b9c1e-7a4d-4c1b-9e0f-2a6b8d4c1e7f"));
+        assert!(is_valid_request_id("client:retry_2.1"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("has space"));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LENGTH + 1)));
+    }
+}
@@ -1,36 +1,129 @@
+use async_graphql_axum::GraphQLSubscription;
 use axum::{
+    middleware,
     routing::{get, post, put, delete},
-    Router,
+    Extension, Router,
 };
 
+use super::graphql::{self, GRAPHQL_PATH, GRAPHQL_WS_PATH};
 use super::handlers::*;
 
 pub fn create_router(state: AppState) -> Router {
+    let schema = graphql::build_schema(state.clone());
+
     Router::new()
         // Health check
         .route("/health", get(health_check))
+        .route("/ready", get(readiness_check))
+        .route("/halt", get(get_trading_halt))
+        .route("/tenant", get(get_tenant))
         
         // User routes
         .route("/users/initialize", post(initialize_user))
+        .route("/users/:id", delete(close_user_account))
         .route("/users/:id/account", get(get_user_account))
         .route("/users/:id/collateral", post(add_collateral))
+        .route("/users/:id/settings", get(get_user_settings).put(put_user_settings))
         .route("/users/:id/positions", get(get_user_positions))
+        .route("/users/:id/realized-pnl", get(get_realized_pnl))
+        .route("/users/:id/reports/tax", get(get_tax_report))
+        .route("/users/:id/risk", get(get_user_risk))
+        .route("/users/:id/risk-limits", get(get_risk_limits).put(put_risk_limits))
+        .route("/users/:id/fee-tier", get(get_fee_tier))
+        .route("/users/:id/price-alerts", get(list_user_price_alerts))
+        .route(
+            "/users/:id/external-positions",
+            post(import_external_positions)
+                .get(list_external_positions)
+                .delete(clear_external_positions),
+        )
+        .route("/users/:id/strategies", get(list_strategies))
+        .route(
+            "/users/:id/strategies/:name",
+            put(put_strategy).delete(delete_strategy),
+        )
+        .route("/users/:id/strategies/:name/close", post(close_strategy))
+        .route("/users/:id/tokens", post(create_api_token).get(list_api_tokens))
+        .route("/users/:id/tokens/:token_id", delete(revoke_api_token))
         
         // Position routes
         .route("/positions/open", post(open_position))
+        .route("/positions/preview", post(preview_position))
+        .route("/simulate/trade", post(simulate_trade))
+        .route("/orders", post(place_order))
         .route("/positions/:id", get(get_position_details))
+        .route("/positions/:id/pnl-history", get(get_pnl_history))
+        .route("/positions/:id/timeline", get(get_position_timeline))
+        .route("/positions/:id/onchain-history", get(get_position_onchain_history))
         .route("/positions/:id/modify", put(modify_position))
         .route("/positions/:id/close", delete(close_position))
+        .route("/positions/:id/alerts", post(create_position_alert).get(list_position_alerts))
+        .route("/positions/:id/alerts/:alert_id", delete(delete_position_alert))
         
+        // Price alert routes, listing every alert needs ADMIN_API_TOKEN
+        .route("/alerts/price", post(create_price_alert).get(list_price_alerts))
+        .route(
+            "/alerts/price/:id",
+            get(get_price_alert).put(update_price_alert).delete(delete_price_alert),
+        )
+
         // Monitoring routes
         .route("/positions", get(list_positions))
+        .route("/positions/history", get(get_position_history))
+        .route("/positions/underwater", get(get_underwater_positions))
+        .route("/liquidations", get(get_liquidations))
         .route("/positions/by-asset/:symbol", get(get_positions_by_asset))
         .route("/statistics", get(get_statistics))
+        .route("/statistics/rpc", get(get_rpc_statistics))
+        .route("/statistics/rpc/endpoints", get(get_rpc_endpoint_statistics))
+        .route("/statistics/breakers", get(get_breaker_statistics))
+        .route("/statistics/prices", get(get_price_feed_statistics))
+        .route("/statistics/latency", get(get_latency_statistics))
         .route("/prices", get(get_prices))
         .route("/prices/:symbol", get(get_price))
+        .route("/prices/:symbol/history", get(get_price_history))
+        .route("/markets/:symbol/sizing", get(get_market_sizing))
+        .route("/markets/:symbol/liquidation-map", get(get_liquidation_map))
+        .route("/liquidators", get(get_liquidators))
+        .route("/exchange-info", get(get_exchange_info))
+        .route("/program/idl", get(get_program_idl))
+
+        // Admin routes, need ADMIN_API_TOKEN
+        .route("/admin/symbols", get(list_symbol_configs))
+        .route(
+            "/admin/symbols/:symbol",
+            put(update_symbol_config).delete(delete_symbol_config),
+        )
+        .route("/admin/markets/:symbol", get(get_market).post(initialize_market))
+        .route("/admin/markets/:symbol/throttle", put(set_market_throttle))
+        .route("/admin/fee-schedule", put(set_fee_schedule))
+        .route("/admin/positions/:id/migrate", post(migrate_position))
+        .route("/admin/lookup-table", post(prepare_lookup_table))
+        .route("/admin/backfill", get(get_backfill).post(start_backfill))
+        .route("/admin/history", get(get_history_retention))
+        .route("/admin/history/compact", post(compact_history))
+        .route("/admin/outbox", get(get_outbox))
+        .route("/admin/bootstrap-demo", post(bootstrap_demo))
+        .route("/admin/simulate-price", post(simulate_price))
+        .route("/admin/simulate-price/:symbol", delete(clear_simulated_price))
+
+        // Backstop vault routes
+        .route("/vault", get(get_backstop_vault))
+        .route("/vault/nav", get(get_vault_nav))
+        .route("/vault/returns", get(get_vault_returns))
+        .route("/vault/lp/:owner", get(get_lp_account))
         
         // WebSocket route
         .route("/ws", get(super::websocket::ws_handler))
+
+        // GraphQL routes
+        .route(GRAPHQL_PATH, post(graphql::graphql_handler).get(graphql::graphiql))
+        .route_service(GRAPHQL_WS_PATH, GraphQLSubscription::new(schema.clone()))
+        .layer(Extension(schema))
         
+        .layer(middleware::from_fn_with_state(state.clone(), super::api_token::authorize))
+        .layer(middleware::from_fn_with_state(state.clone(), super::tenant::resolve))
+        .layer(middleware::from_fn(super::precision::raw_decimals))
+        .layer(middleware::from_fn(super::request_id::request_id))
         .with_state(state)
 }
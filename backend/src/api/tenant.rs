@@ -0,0 +1,59 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::request::Parts,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::api::errors::ApiError;
+use crate::api::handlers::AppState;
+use crate::services::{DEFAULT_TENANT, TENANT_KEY_HEADER};
+
+/// Tenant a request is made for
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrentTenant(pub String);
+
+impl CurrentTenant {
+    pub fn is_default(&self) -> bool {
+        self.0 == DEFAULT_TENANT
+    }
+}
+
+/// Resolve the request's tenant from `X-Tenant-Key`, the default tenant
+/// without one. Runs ahead of the API token check, whose tokens are per tenant
+pub async fn resolve(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let key = request
+        .headers()
+        .get(TENANT_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    let tenant = match key {
+        Some(key) => state
+            .tenants
+            .authenticate(key)
+            .map(|tenant| tenant.id.clone())
+            .ok_or_else(|| ApiError::Unauthorized("Invalid tenant key".to_string()))?,
+        None => DEFAULT_TENANT.to_string(),
+    };
+
+    request.extensions_mut().insert(CurrentTenant(tenant));
+    Ok(next.run(request).await)
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for CurrentTenant {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<CurrentTenant>()
+            .cloned()
+            .unwrap_or_else(|| CurrentTenant(DEFAULT_TENANT.to_string())))
+    }
+}
@@ -0,0 +1,137 @@
+/// Outbound Message Throttle
+/// Per-connection, per-symbol conflation: each symbol is flushed at most
+/// `max_per_second` times, and within a window only the latest value per key
+/// (symbol for prices, position account for positions) is kept
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_MAX_MESSAGES_PER_SECOND: u32 = 4;
+
+struct SymbolSlot<T> {
+    last_flush: Option<Instant>,
+    /// Pending values in arrival order, replaced in place by key
+    pending: Vec<(String, T)>,
+}
+
+pub struct SymbolThrottle<T> {
+    /// None disables throttling and every value is passed straight through
+    min_interval: Option<Duration>,
+    symbols: HashMap<String, SymbolSlot<T>>,
+}
+
+impl<T> Default for SymbolThrottle<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MESSAGES_PER_SECOND)
+    }
+}
+
+impl<T> SymbolThrottle<T> {
+    /// `max_per_second` of 0 disables throttling
+    pub fn new(max_per_second: u32) -> Self {
+        Self {
+            min_interval: (max_per_second > 0)
+                .then(|| Duration::from_secs(1) / max_per_second),
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// Queue a value, returning whatever is ready to send for its symbol now
+    pub fn offer(&mut self, symbol: &str, key: String, value: T, now: Instant) -> Vec<T> {
+        let Some(min_interval) = self.min_interval else {
+            return vec![value];
+        };
+
+        let slot = self
+            .symbols
+            .entry(symbol.to_string())
+            .or_insert_with(|| SymbolSlot {
+                last_flush: None,
+                pending: Vec::new(),
+            });
+
+        match slot.pending.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, pending)) => *pending = value,
+            None => slot.pending.push((key, value)),
+        }
+
+        slot.flush_if_ready(min_interval, now)
+    }
+
+    /// Flush every symbol whose window has elapsed, called on a timer
+    pub fn drain_ready(&mut self, now: Instant) -> Vec<T> {
+        let Some(min_interval) = self.min_interval else {
+            return Vec::new();
+        };
+
+        self.symbols
+            .values_mut()
+            .flat_map(|slot| slot.flush_if_ready(min_interval, now))
+            .collect()
+    }
+}
+
+impl<T> SymbolSlot<T> {
+    fn flush_if_ready(&mut self, min_interval: Duration, now: Instant) -> Vec<T> {
+        let ready = self
+            .last_flush
+            .is_none_or(|last| now.duration_since(last) >= min_interval);
+
+        if !ready || self.pending.is_empty() {
+            return Vec::new();
+        }
+
+        self.last_flush = Some(now);
+        self.pending.drain(..).map(|(_, value)| value).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_value_wins_within_window() {
+        let mut throttle = SymbolThrottle::new(4);
+        let start = Instant::now();
+
+        assert_eq!(throttle.offer("SOL-USD", "SOL-USD".into(), 1, start), vec![1]);
+        assert!(throttle
+            .offer("SOL-USD", "SOL-USD".into(), 2, start + Duration::from_millis(50))
+            .is_empty());
+        assert!(throttle
+            .offer("SOL-USD", "SOL-USD".into(), 3, start + Duration::from_millis(100))
+            .is_empty());
+
+        // Other symbols have their own budget
+        assert_eq!(
+            throttle.offer("BTC-USD", "BTC-USD".into(), 10, start + Duration::from_millis(100)),
+            vec![10]
+        );
+
+        assert!(throttle.drain_ready(start + Duration::from_millis(200)).is_empty());
+        assert_eq!(throttle.drain_ready(start + Duration::from_millis(250)), vec![3]);
+    }
+
+    #[test]
+    fn test_keys_are_conflated_separately() {
+        let mut throttle = SymbolThrottle::new(4);
+        let start = Instant::now();
+
+        throttle.offer("SOL-USD", "a".into(), 1, start);
+        throttle.offer("SOL-USD", "a".into(), 2, start + Duration::from_millis(10));
+        throttle.offer("SOL-USD", "b".into(), 3, start + Duration::from_millis(20));
+        throttle.offer("SOL-USD", "a".into(), 4, start + Duration::from_millis(30));
+
+        assert_eq!(throttle.drain_ready(start + Duration::from_millis(250)), vec![4, 3]);
+    }
+
+    #[test]
+    fn test_disabled_passes_through() {
+        let mut throttle = SymbolThrottle::new(0);
+        let now = Instant::now();
+
+        assert_eq!(throttle.offer("SOL-USD", "a".into(), 1, now), vec![1]);
+        assert_eq!(throttle.offer("SOL-USD", "a".into(), 2, now), vec![2]);
+        assert!(throttle.drain_ready(now).is_empty());
+    }
+}
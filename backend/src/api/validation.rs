@@ -0,0 +1,938 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::api::dto::{
+    BootstrapDemoRequest, CreateApiTokenRequest, ExternalPositionsRequest, InitializeMarketRequest, ModifyPositionRequest, OpenPositionRequest, PositionPreviewRequest, PriceAlertRequest,
+    RiskLimitsRequest, SetFeeScheduleRequest, SimulatePriceRequest, SimulateTradeRequest, StrategyRequest,
+    SymbolConfigRequest, UserSettingsRequest,
+};
+use crate::domain::symbols;
+use crate::services::{
+    is_public_ip, AlertCondition, FEE_TIER_COUNT, MAX_API_TOKEN_TTL_SECS, MAX_TAKER_FEE_BPS, MAX_DEMO_POSITIONS_PER_USER, MAX_DEMO_USERS, MAX_EXTERNAL_POSITIONS_PER_USER,
+    MAX_LEGS_PER_STRATEGY,
+};
+
+pub use crate::error::FieldError;
+
+pub const MIN_LEVERAGE: u16 = 1;
+/// Highest leverage tier the program accepts
+pub const MAX_LEVERAGE: u16 = 1000;
+
+/// Decimals the instruction encoding keeps for size and price
+const SIZE_DECIMALS: u32 = 8;
+const PRICE_DECIMALS: u32 = 6;
+
+/// Entry price deviation from the oracle allowed when the request sets no limit
+pub const DEFAULT_MAX_SLIPPAGE_BPS: u32 = 500;
+pub const MAX_SLIPPAGE_BPS: u32 = 2_000;
+
+/// Bounds on a symbol's PnL cadence, faster passes starve the other tasks
+pub const MIN_PNL_UPDATE_INTERVAL_MS: u64 = 100;
+pub const MAX_PNL_UPDATE_INTERVAL_MS: u64 = 60_000;
+
+/// Highest maintenance margin a Market may be bounded to, as the program allows
+pub const MAX_MARKET_MAINTENANCE_MARGIN_BPS: u16 = 5_000;
+
+/// Strategy names appear in URLs, so they stay short and path-safe
+pub const MAX_STRATEGY_NAME_LEN: usize = 64;
+
+pub const MAX_API_TOKEN_LABEL_LEN: usize = 64;
+
+pub const MAX_EXTERNAL_VENUE_LEN: usize = 64;
+pub const MIN_API_TOKEN_TTL_SECS: u64 = 60;
+
+/// How long an injected price lasts when the request doesn't say, and at most
+pub const DEFAULT_SIMULATED_PRICE_TTL_SECS: u64 = 300;
+pub const MAX_SIMULATED_PRICE_TTL_SECS: u64 = 3_600;
+
+/// Longest window a trade simulation replays
+pub const MAX_TRADE_SIMULATION_DAYS: i64 = 30;
+
+#[derive(Debug, Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn add(&mut self, field: &'static str, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field,
+            message: message.into(),
+        });
+    }
+
+    fn finish(self) -> Result<(), Vec<FieldError>> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self.0)
+        }
+    }
+}
+
+/// Check a user's defaults against the same bounds as an open request
+pub fn validate_user_settings(request: &UserSettingsRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if u32::from(request.default_slippage_bps) > MAX_SLIPPAGE_BPS {
+        errors.add(
+            "default_slippage_bps",
+            format!("must be at most {}", MAX_SLIPPAGE_BPS),
+        );
+    }
+
+    if !(MIN_LEVERAGE..=MAX_LEVERAGE).contains(&request.default_leverage) {
+        errors.add(
+            "default_leverage",
+            format!("must be between {} and {}", MIN_LEVERAGE, MAX_LEVERAGE),
+        );
+    }
+
+    errors.finish()
+}
+
+/// Check a user's risk limits, a percent of collateral and a leverage multiple
+pub fn validate_risk_limits(request: &RiskLimitsRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if let Some(pct) = request.max_position_risk_pct {
+        if pct <= Decimal::ZERO || pct > Decimal::ONE_HUNDRED {
+            errors.add("max_position_risk_pct", "must be above 0 and at most 100");
+        }
+    }
+
+    if let Some(leverage) = request.max_portfolio_leverage {
+        if leverage <= Decimal::ZERO || leverage > Decimal::from(MAX_LEVERAGE) {
+            errors.add(
+                "max_portfolio_leverage",
+                format!("must be above 0 and at most {}", MAX_LEVERAGE),
+            );
+        }
+    }
+
+    errors.finish()
+}
+
+/// Check a taker fee schedule the way the program would: one rate per tier,
+/// capped, and no tier dearer than the one below
+pub fn validate_fee_schedule(request: &SetFeeScheduleRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    let rates = &request.taker_fee_bps;
+
+    if rates.len() != FEE_TIER_COUNT {
+        errors.add("taker_fee_bps", format!("must have {} rates, one per tier", FEE_TIER_COUNT));
+    } else if rates.iter().any(|bps| *bps > MAX_TAKER_FEE_BPS) {
+        errors.add("taker_fee_bps", format!("rates must be at most {} bps", MAX_TAKER_FEE_BPS));
+    } else if rates.windows(2).any(|pair| pair[1] > pair[0]) {
+        errors.add("taker_fee_bps", "rates must not rise with the tier");
+    }
+
+    errors.finish()
+}
+
+/// Check an open request before building the instruction. `known_symbols` are
+/// the oracle feeds (empty skips the check); `oracle_price` enables the slippage check
+pub fn validate_open_position(
+    request: &OpenPositionRequest,
+    known_symbols: &[String],
+    oracle_price: Option<Decimal>,
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if Pubkey::from_str(&request.owner).is_err() {
+        errors.add("owner", "must be a base58 public key");
+    }
+
+    match symbols::canonicalize(&request.symbol) {
+        Ok(symbol) if !known_symbols.is_empty() && !known_symbols.contains(&symbol) => {
+            errors.add("symbol", format!("no price feed for {}", symbol));
+        }
+        Ok(_) => {}
+        Err(e) => errors.add("symbol", e.to_string()),
+    }
+
+    check_amount(&mut errors, "size", request.size, SIZE_DECIMALS);
+    check_amount(&mut errors, "entry_price", request.entry_price, PRICE_DECIMALS);
+
+    match request.leverage {
+        None => errors.add("leverage", "is required when no default leverage is set"),
+        Some(leverage) if !(MIN_LEVERAGE..=MAX_LEVERAGE).contains(&leverage) => errors.add(
+            "leverage",
+            format!("must be between {} and {}", MIN_LEVERAGE, MAX_LEVERAGE),
+        ),
+        Some(_) => {}
+    }
+
+    if let Some(ratio) = request.maintenance_margin_ratio {
+        if ratio <= Decimal::ZERO || ratio >= Decimal::ONE {
+            errors.add("maintenance_margin_ratio", "must be between 0 and 1 exclusive");
+        }
+    }
+
+    let max_slippage_bps = request.max_slippage_bps.unwrap_or(DEFAULT_MAX_SLIPPAGE_BPS);
+    if max_slippage_bps > MAX_SLIPPAGE_BPS {
+        errors.add(
+            "max_slippage_bps",
+            format!("must be at most {}", MAX_SLIPPAGE_BPS),
+        );
+    } else if let Some(oracle_price) = oracle_price.filter(|p| *p > Decimal::ZERO) {
+        if request.entry_price > Decimal::ZERO {
+            let deviation_bps = ((request.entry_price - oracle_price).abs() / oracle_price)
+                * Decimal::from(10_000);
+            if deviation_bps > Decimal::from(max_slippage_bps) {
+                errors.add(
+                    "entry_price",
+                    format!(
+                        "{} is {} bps from the oracle price {}, limit is {} bps",
+                        request.entry_price,
+                        deviation_bps.round_dp(0),
+                        oracle_price,
+                        max_slippage_bps
+                    ),
+                );
+            }
+        }
+    }
+
+    errors.finish()
+}
+
+/// Like an open request, but size and price are optional
+pub fn validate_position_preview(
+    request: &PositionPreviewRequest,
+    known_symbols: &[String],
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if Pubkey::from_str(&request.owner).is_err() {
+        errors.add("owner", "must be a base58 public key");
+    }
+
+    match symbols::canonicalize(&request.symbol) {
+        Ok(symbol) if !known_symbols.is_empty() && !known_symbols.contains(&symbol) => {
+            errors.add("symbol", format!("no price feed for {}", symbol));
+        }
+        Ok(_) => {}
+        Err(e) => errors.add("symbol", e.to_string()),
+    }
+
+    if let Some(size) = request.size {
+        check_amount(&mut errors, "size", size, SIZE_DECIMALS);
+    }
+    if let Some(entry_price) = request.entry_price {
+        check_amount(&mut errors, "entry_price", entry_price, PRICE_DECIMALS);
+    }
+
+    if !(MIN_LEVERAGE..=MAX_LEVERAGE).contains(&request.leverage) {
+        errors.add(
+            "leverage",
+            format!("must be between {} and {}", MIN_LEVERAGE, MAX_LEVERAGE),
+        );
+    }
+
+    if let Some(ratio) = request.maintenance_margin_ratio {
+        if ratio <= Decimal::ZERO || ratio >= Decimal::ONE {
+            errors.add("maintenance_margin_ratio", "must be between 0 and 1 exclusive");
+        }
+    }
+
+    errors.finish()
+}
+
+pub fn validate_modify_position(request: &ModifyPositionRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if request.new_size.is_none() && request.margin_delta.is_none() {
+        errors.add("new_size", "either new_size or margin_delta is required");
+    }
+
+    if let Some(size) = request.new_size {
+        check_amount(&mut errors, "new_size", size, SIZE_DECIMALS);
+    }
+
+    if request.margin_delta == Some(0) {
+        errors.add("margin_delta", "must not be zero");
+    }
+
+    errors.finish()
+}
+
+/// PnL floors may be any value, ratio and price levels must be positive
+pub fn validate_alert_condition(condition: &AlertCondition) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    match condition {
+        AlertCondition::PnlBelow { .. } => {}
+        AlertCondition::MarginRatioBelow { threshold } if *threshold <= Decimal::ZERO => {
+            errors.add("threshold", "must be positive");
+        }
+        AlertCondition::MarginRatioBelow { .. } => {}
+        AlertCondition::PriceCrosses { price } => {
+            check_amount(&mut errors, "price", *price, PRICE_DECIMALS)
+        }
+    }
+
+    errors.finish()
+}
+
+/// Check a price alert before storing it. `known_symbols` are the oracle feeds (empty skips the check)
+pub fn validate_price_alert(
+    request: &PriceAlertRequest,
+    known_symbols: &[String],
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    match symbols::canonicalize(&request.symbol) {
+        Ok(symbol) if !known_symbols.is_empty() && !known_symbols.contains(&symbol) => {
+            errors.add("symbol", format!("no price feed for {}", symbol));
+        }
+        Ok(_) => {}
+        Err(e) => errors.add("symbol", e.to_string()),
+    }
+
+    check_amount(&mut errors, "price", request.price, PRICE_DECIMALS);
+
+    if Pubkey::from_str(&request.owner).is_err() {
+        errors.add("owner", "must be a base58 public key");
+    }
+
+    // Hostnames are checked again when the webhook is sent, after resolving
+    if let Some(url) = &request.webhook_url {
+        let literal_ip = |url: &reqwest::Url| {
+            url.host_str()
+                .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+                .and_then(|host| host.parse::<std::net::IpAddr>().ok())
+        };
+        match reqwest::Url::parse(url) {
+            Ok(url) if url.scheme() != "https" || url.host_str().is_none() => {
+                errors.add("webhook_url", "must be an https URL")
+            }
+            Ok(url) if literal_ip(&url).is_some_and(|ip| !is_public_ip(ip)) => {
+                errors.add("webhook_url", "must not point to a private address")
+            }
+            Ok(_) => {}
+            Err(_) => errors.add("webhook_url", "must be an https URL"),
+        }
+    }
+
+    errors.finish()
+}
+
+/// Symbol overrides must set something, ratios are fractions of notional
+pub fn validate_symbol_config(request: &SymbolConfigRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if request.pnl_update_interval_ms.is_none()
+        && request.maintenance_margin_ratio.is_none()
+        && request.alert_threshold_pct.is_none()
+        && request.max_open_interest.is_none()
+    {
+        errors.add(
+            "pnl_update_interval_ms",
+            "set at least one of pnl_update_interval_ms, maintenance_margin_ratio, alert_threshold_pct or max_open_interest",
+        );
+    }
+
+    if let Some(interval_ms) = request.pnl_update_interval_ms {
+        if !(MIN_PNL_UPDATE_INTERVAL_MS..=MAX_PNL_UPDATE_INTERVAL_MS).contains(&interval_ms) {
+            errors.add(
+                "pnl_update_interval_ms",
+                format!(
+                    "must be between {} and {}",
+                    MIN_PNL_UPDATE_INTERVAL_MS, MAX_PNL_UPDATE_INTERVAL_MS
+                ),
+            );
+        }
+    }
+
+    for (field, ratio) in [
+        ("maintenance_margin_ratio", request.maintenance_margin_ratio),
+        ("alert_threshold_pct", request.alert_threshold_pct),
+    ] {
+        if ratio.is_some_and(|ratio| ratio <= Decimal::ZERO || ratio >= Decimal::ONE) {
+            errors.add(field, "must be between 0 and 1 exclusive");
+        }
+    }
+
+    if let Some(max_open_interest) = request.max_open_interest {
+        check_amount(&mut errors, "max_open_interest", max_open_interest, SIZE_DECIMALS);
+    }
+
+    errors.finish()
+}
+
+/// Bounds are ordered and at most the program's ceiling
+pub fn validate_initialize_market(request: &InitializeMarketRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if request.max_maintenance_margin_bps > MAX_MARKET_MAINTENANCE_MARGIN_BPS {
+        errors.add(
+            "max_maintenance_margin_bps",
+            format!("must be at most {}", MAX_MARKET_MAINTENANCE_MARGIN_BPS),
+        );
+    } else if request.max_maintenance_margin_bps < request.min_maintenance_margin_bps {
+        errors.add("max_maintenance_margin_bps", "must be at least min_maintenance_margin_bps");
+    }
+
+    errors.finish()
+}
+
+/// Counts are at least one and capped, amounts positive
+pub fn validate_bootstrap_demo(request: &BootstrapDemoRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if let Some(users) = request.users {
+        if users == 0 || users > MAX_DEMO_USERS {
+            errors.add("users", format!("must be 1 to {}", MAX_DEMO_USERS));
+        }
+    }
+
+    if let Some(positions) = request.positions_per_user {
+        if positions == 0 || positions > MAX_DEMO_POSITIONS_PER_USER {
+            errors.add(
+                "positions_per_user",
+                format!("must be 1 to {}", MAX_DEMO_POSITIONS_PER_USER),
+            );
+        }
+    }
+
+    if request.collateral == Some(0) {
+        errors.add("collateral", "must be positive");
+    }
+
+    if let Some(notional) = request.notional {
+        if notional <= Decimal::ZERO {
+            errors.add("notional", "must be positive");
+        }
+    }
+
+    errors.finish()
+}
+
+/// Names are letters, digits, `-` and `_`; legs are distinct position accounts
+pub fn validate_strategy(name: &str, request: &StrategyRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if name.is_empty() || name.len() > MAX_STRATEGY_NAME_LEN {
+        errors.add("name", format!("must be 1 to {} characters", MAX_STRATEGY_NAME_LEN));
+    } else if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        errors.add("name", "may only contain letters, digits, '-' and '_'");
+    }
+
+    if request.positions.is_empty() || request.positions.len() > MAX_LEGS_PER_STRATEGY {
+        errors.add(
+            "positions",
+            format!("must list 1 to {} positions", MAX_LEGS_PER_STRATEGY),
+        );
+    } else if request.positions.iter().any(|account| Pubkey::from_str(account).is_err()) {
+        errors.add("positions", "must be base58 public keys");
+    } else {
+        let mut seen = HashSet::new();
+        if !request.positions.iter().all(|account| seen.insert(account)) {
+            errors.add("positions", "must not repeat a position");
+        }
+    }
+
+    errors.finish()
+}
+
+/// Label and lifetime of a new API token, the signature is checked by the handler
+pub fn validate_api_token_request(request: &CreateApiTokenRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if request.label.as_ref().is_some_and(|label| label.len() > MAX_API_TOKEN_LABEL_LEN) {
+        errors.add("label", format!("at most {} characters", MAX_API_TOKEN_LABEL_LEN));
+    }
+
+    if let Some(ttl_secs) = request.ttl_secs {
+        if !(MIN_API_TOKEN_TTL_SECS..=MAX_API_TOKEN_TTL_SECS).contains(&ttl_secs) {
+            errors.add(
+                "ttl_secs",
+                format!(
+                    "must be between {} and {}",
+                    MIN_API_TOKEN_TTL_SECS, MAX_API_TOKEN_TTL_SECS
+                ),
+            );
+        }
+    }
+
+    errors.finish()
+}
+
+/// An external book import. Symbols need not have a price feed, positions on
+/// symbols without one are reported at entry price. Rows are numbered from 1
+pub fn validate_external_positions(request: &ExternalPositionsRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if request.positions.len() > MAX_EXTERNAL_POSITIONS_PER_USER {
+        errors.add(
+            "positions",
+            format!("at most {} positions", MAX_EXTERNAL_POSITIONS_PER_USER),
+        );
+        return errors.finish();
+    }
+
+    for (index, position) in request.positions.iter().enumerate() {
+        let row = index + 1;
+        let mut row_errors = Errors::default();
+
+        if let Err(e) = symbols::canonicalize(&position.symbol) {
+            row_errors.add("symbol", e.to_string());
+        }
+        check_amount(&mut row_errors, "size", position.size, SIZE_DECIMALS);
+        check_amount(&mut row_errors, "entry_price", position.entry_price, PRICE_DECIMALS);
+        if position.venue.as_ref().is_some_and(|venue| venue.len() > MAX_EXTERNAL_VENUE_LEN) {
+            row_errors.add("venue", format!("at most {} characters", MAX_EXTERNAL_VENUE_LEN));
+        }
+
+        for error in row_errors.0 {
+            errors.add(error.field, format!("row {}: {}", row, error.message));
+        }
+    }
+
+    errors.finish()
+}
+
+/// A trade simulation window ends after it starts, within the last
+/// `MAX_TRADE_SIMULATION_DAYS` worth of history
+pub fn validate_simulate_trade(request: &SimulateTradeRequest, now: DateTime<Utc>) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    if let Err(e) = symbols::canonicalize(&request.symbol) {
+        errors.add("symbol", e.to_string());
+    }
+
+    check_amount(&mut errors, "size", request.size, SIZE_DECIMALS);
+
+    if !(MIN_LEVERAGE..=MAX_LEVERAGE).contains(&request.leverage) {
+        errors.add(
+            "leverage",
+            format!("must be between {} and {}", MIN_LEVERAGE, MAX_LEVERAGE),
+        );
+    }
+
+    if request.entry_time >= now {
+        errors.add("entry_time", "must be in the past");
+    }
+    if request.exit_time <= request.entry_time {
+        errors.add("exit_time", "must be after entry_time");
+    } else if request.exit_time - request.entry_time > chrono::Duration::days(MAX_TRADE_SIMULATION_DAYS) {
+        errors.add(
+            "exit_time",
+            format!("at most {} days after entry_time", MAX_TRADE_SIMULATION_DAYS),
+        );
+    }
+
+    if let Some(ratio) = request.maintenance_margin_ratio {
+        if ratio <= Decimal::ZERO || ratio >= Decimal::ONE {
+            errors.add("maintenance_margin_ratio", "must be between 0 and 1 exclusive");
+        }
+    }
+
+    errors.finish()
+}
+
+/// Injected prices are for symbols the monitor prices, and expire within the hour
+pub fn validate_simulate_price(
+    request: &SimulatePriceRequest,
+    known_symbols: &[String],
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    match symbols::canonicalize(&request.symbol) {
+        Ok(symbol) if !known_symbols.contains(&symbol) => {
+            errors.add("symbol", format!("no price feed for {}", symbol));
+        }
+        Ok(_) => {}
+        Err(e) => errors.add("symbol", e.to_string()),
+    }
+
+    check_amount(&mut errors, "price", request.price, PRICE_DECIMALS);
+
+    if let Some(ttl_secs) = request.ttl_secs {
+        if ttl_secs == 0 || ttl_secs > MAX_SIMULATED_PRICE_TTL_SECS {
+            errors.add("ttl_secs", format!("must be 1 to {}", MAX_SIMULATED_PRICE_TTL_SECS));
+        }
+    }
+
+    errors.finish()
+}
+
+fn check_amount(errors: &mut Errors, field: &'static str, value: Decimal, decimals: u32) {
+    if value <= Decimal::ZERO {
+        errors.add(field, "must be positive");
+    } else if value.normalize().scale() > decimals {
+        errors.add(field, format!("at most {} decimal places", decimals));
+    } else if value
+        .checked_mul(Decimal::from(10u64.pow(decimals)))
+        .is_none_or(|units| units > Decimal::from(u64::MAX))
+    {
+        errors.add(field, "too large");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{MarginMode, Side};
+    use crate::services::ExternalPositionInput;
+    use rust_decimal_macros::dec;
+
+    fn request() -> OpenPositionRequest {
+        OpenPositionRequest {
+            owner: Pubkey::new_unique().to_string(),
+            symbol: "sol/usdt".to_string(),
+            side: Side::Long,
+            size: dec!(1.5),
+            leverage: Some(10),
+            entry_price: dec!(150),
+            maintenance_margin_ratio: None,
+            max_slippage_bps: None,
+        }
+    }
+
+    #[test]
+    fn test_open_position_accepts_valid_request() {
+        let known = vec!["SOL-USD".to_string()];
+        assert!(validate_open_position(&request(), &known, Some(dec!(151))).is_ok());
+    }
+
+    #[test]
+    fn test_open_position_collects_field_errors() {
+        let mut bad = request();
+        bad.owner = "nope".to_string();
+        bad.symbol = "SOL-EUR".to_string();
+        bad.size = dec!(0);
+        bad.leverage = Some(1001);
+        bad.entry_price = dec!(1.1234567);
+
+        let fields: Vec<_> = validate_open_position(&bad, &[], None)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["owner", "symbol", "size", "entry_price", "leverage"]);
+    }
+
+    #[test]
+    fn test_open_position_rejects_amounts_past_u64() {
+        let known = vec!["SOL-USD".to_string()];
+        let mut bad = request();
+        // Scaling these to base units overflows a Decimal, not just a u64
+        bad.size = Decimal::MAX;
+        bad.entry_price = Decimal::MAX;
+
+        let errors = validate_open_position(&bad, &known, None).unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["size", "entry_price"]);
+        assert!(errors.iter().all(|e| e.message == "too large"));
+    }
+
+    #[test]
+    fn test_open_position_slippage_and_unknown_symbol() {
+        let known = vec!["BTC-USD".to_string()];
+        let errors = validate_open_position(&request(), &known, Some(dec!(100))).unwrap_err();
+        assert_eq!(errors[0].field, "symbol");
+        assert_eq!(errors[1].field, "entry_price");
+
+        let mut wide = request();
+        wide.max_slippage_bps = Some(MAX_SLIPPAGE_BPS);
+        assert!(validate_open_position(&wide, &[], Some(dec!(130))).is_ok());
+    }
+
+    #[test]
+    fn test_open_position_requires_leverage_without_default() {
+        let mut unset = request();
+        unset.leverage = None;
+
+        let errors = validate_open_position(&unset, &[], None).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "leverage");
+    }
+
+    #[test]
+    fn test_user_settings_bounds() {
+        let mut settings = UserSettingsRequest {
+            default_slippage_bps: 50,
+            default_leverage: 5,
+            margin_mode: MarginMode::Isolated,
+        };
+        assert!(validate_user_settings(&settings).is_ok());
+
+        settings.default_slippage_bps = 2_001;
+        settings.default_leverage = 0;
+        let fields: Vec<_> = validate_user_settings(&settings)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["default_slippage_bps", "default_leverage"]);
+    }
+
+    #[test]
+    fn test_risk_limits_bounds() {
+        let mut limits = RiskLimitsRequest {
+            max_position_risk_pct: Some(Decimal::new(25, 0)),
+            max_portfolio_leverage: None,
+            block_opens: true,
+            timestamp: 1_763_393_400,
+            signature: String::new(),
+        };
+        assert!(validate_risk_limits(&limits).is_ok());
+
+        limits.max_position_risk_pct = Some(Decimal::new(101, 0));
+        limits.max_portfolio_leverage = Some(Decimal::ZERO);
+        let fields: Vec<_> = validate_risk_limits(&limits)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["max_position_risk_pct", "max_portfolio_leverage"]);
+    }
+
+    #[test]
+    fn test_fee_schedule_bounds() {
+        let schedule = |taker_fee_bps: Vec<u16>| SetFeeScheduleRequest { taker_fee_bps };
+
+        assert!(validate_fee_schedule(&schedule(vec![5, 4, 3, 2])).is_ok());
+        assert!(validate_fee_schedule(&schedule(vec![5, 4, 3])).is_err());
+        assert!(validate_fee_schedule(&schedule(vec![101, 4, 3, 2])).is_err());
+        assert!(validate_fee_schedule(&schedule(vec![5, 6, 3, 2])).is_err());
+    }
+
+    #[test]
+    fn test_bootstrap_demo_bounds() {
+        assert!(validate_bootstrap_demo(&BootstrapDemoRequest::default()).is_ok());
+
+        let request = BootstrapDemoRequest {
+            users: Some(MAX_DEMO_USERS + 1),
+            positions_per_user: Some(0),
+            collateral: Some(0),
+            notional: Some(dec!(-5)),
+        };
+        let fields: Vec<_> = validate_bootstrap_demo(&request)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["users", "positions_per_user", "collateral", "notional"]);
+    }
+
+    #[test]
+    fn test_position_preview_optional_size_and_price() {
+        let mut preview = PositionPreviewRequest {
+            owner: Pubkey::new_unique().to_string(),
+            symbol: "sol/usdt".to_string(),
+            side: Side::Long,
+            leverage: 10,
+            size: None,
+            entry_price: None,
+            maintenance_margin_ratio: None,
+        };
+        assert!(validate_position_preview(&preview, &["SOL-USD".to_string()]).is_ok());
+
+        preview.size = Some(dec!(-1));
+        preview.leverage = 0;
+        let fields: Vec<_> = validate_position_preview(&preview, &[])
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["size", "leverage"]);
+    }
+
+    #[test]
+    fn test_external_positions_report_rows() {
+        let position = |symbol: &str, size| ExternalPositionInput {
+            symbol: symbol.to_string(),
+            side: Side::Short,
+            size,
+            entry_price: dec!(3000),
+            venue: Some("okx".to_string()),
+        };
+
+        let mut request = ExternalPositionsRequest {
+            positions: vec![position("eth/usdt", dec!(2)), position("XYZ-USD", dec!(1))],
+        };
+        assert!(validate_external_positions(&request).is_ok());
+
+        request.positions.push(position("ETH-EUR", dec!(0)));
+        let errors = validate_external_positions(&request).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, "symbol");
+        assert!(errors[1].message.starts_with("row 3:"));
+    }
+
+    #[test]
+    fn test_modify_position_requires_a_change() {
+        let empty = ModifyPositionRequest {
+            new_size: None,
+            margin_delta: None,
+        };
+        assert!(validate_modify_position(&empty).is_err());
+
+        let zero = ModifyPositionRequest {
+            new_size: Some(dec!(-1)),
+            margin_delta: Some(0),
+        };
+        assert_eq!(validate_modify_position(&zero).unwrap_err().len(), 2);
+    }
+
+    #[test]
+    fn test_price_alert_fields() {
+        let mut request = PriceAlertRequest {
+            symbol: "btc/usd".to_string(),
+            direction: crate::services::PriceDirection::Above,
+            price: dec!(100000),
+            owner: Pubkey::new_unique().to_string(),
+            webhook_url: Some("https://hooks.example.com/alerts".to_string()),
+            timestamp: 0,
+            signature: String::new(),
+        };
+        assert!(validate_price_alert(&request, &["BTC-USD".to_string()]).is_ok());
+
+        request.owner = "nope".to_string();
+        request.webhook_url = Some("ftp://example.com".to_string());
+        let fields: Vec<_> = validate_price_alert(&request, &[])
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["owner", "webhook_url"]);
+
+        request.owner = Pubkey::new_unique().to_string();
+        for url in ["http://hooks.example.com/alerts", "https://127.0.0.1/", "https://[::1]/", "https://169.254.169.254/"] {
+            request.webhook_url = Some(url.to_string());
+            assert!(validate_price_alert(&request, &[]).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_strategy_name_and_legs() {
+        let leg = Pubkey::new_unique().to_string();
+        let request = StrategyRequest {
+            positions: vec![leg.clone(), Pubkey::new_unique().to_string()],
+            timestamp: 1_763_393_400,
+            signature: String::new(),
+        };
+        assert!(validate_strategy("btc-basis_1", &request).is_ok());
+
+        let repeated = StrategyRequest {
+            positions: vec![leg.clone(), leg],
+            ..request
+        };
+        let fields: Vec<_> = validate_strategy("btc basis", &repeated)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["name", "positions"]);
+
+        let empty = StrategyRequest {
+            positions: vec![],
+            ..repeated
+        };
+        assert!(validate_strategy("hedge", &empty).is_err());
+    }
+
+    #[test]
+    fn test_alert_condition_levels() {
+        assert!(validate_alert_condition(&AlertCondition::PnlBelow { threshold: dec!(-500) }).is_ok());
+        assert!(validate_alert_condition(&AlertCondition::MarginRatioBelow { threshold: dec!(0) }).is_err());
+        assert!(validate_alert_condition(&AlertCondition::PriceCrosses { price: dec!(95000.5) }).is_ok());
+        assert_eq!(
+            validate_alert_condition(&AlertCondition::PriceCrosses { price: dec!(-1) }).unwrap_err()[0].field,
+            "price"
+        );
+    }
+
+    #[test]
+    fn test_symbol_config_bounds() {
+        let mut request = SymbolConfigRequest {
+            pnl_update_interval_ms: None,
+            maintenance_margin_ratio: None,
+            alert_threshold_pct: None,
+            max_open_interest: None,
+        };
+        assert!(validate_symbol_config(&request).is_err());
+
+        request.maintenance_margin_ratio = Some(dec!(0.05));
+        assert!(validate_symbol_config(&request).is_ok());
+
+        request.pnl_update_interval_ms = Some(50);
+        request.alert_threshold_pct = Some(dec!(1));
+        request.max_open_interest = Some(Decimal::ZERO);
+        let fields: Vec<_> = validate_symbol_config(&request)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["pnl_update_interval_ms", "alert_threshold_pct", "max_open_interest"]);
+    }
+
+    #[test]
+    fn test_initialize_market_bounds() {
+        let mut request = InitializeMarketRequest {
+            min_maintenance_margin_bps: 250,
+            max_maintenance_margin_bps: 1_000,
+        };
+        assert!(validate_initialize_market(&request).is_ok());
+
+        request.max_maintenance_margin_bps = 200;
+        assert!(validate_initialize_market(&request).is_err());
+
+        request.max_maintenance_margin_bps = 5_001;
+        assert_eq!(
+            validate_initialize_market(&request).unwrap_err()[0].field,
+            "max_maintenance_margin_bps"
+        );
+    }
+
+    #[test]
+    fn test_simulate_price() {
+        let known_symbols = vec![symbols::canonicalize("SOL-USD").unwrap()];
+        let mut request = SimulatePriceRequest {
+            symbol: "sol-usd".to_string(),
+            price: dec!(95.5),
+            ttl_secs: None,
+        };
+        assert!(validate_simulate_price(&request, &known_symbols).is_ok());
+
+        request.symbol = "DOGE-USD".to_string();
+        request.price = dec!(-1);
+        request.ttl_secs = Some(MAX_SIMULATED_PRICE_TTL_SECS + 1);
+        let fields: Vec<_> = validate_simulate_price(&request, &known_symbols)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["symbol", "price", "ttl_secs"]);
+    }
+
+    #[test]
+    fn test_simulate_trade_window() {
+        let now = chrono::Utc::now();
+        let mut request = SimulateTradeRequest {
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size: dec!(1.5),
+            leverage: 10,
+            entry_time: now - chrono::Duration::days(2),
+            exit_time: now - chrono::Duration::days(1),
+            maintenance_margin_ratio: None,
+            interval: None,
+        };
+        assert!(validate_simulate_trade(&request, now).is_ok());
+
+        request.exit_time = request.entry_time;
+        assert_eq!(validate_simulate_trade(&request, now).unwrap_err()[0].field, "exit_time");
+
+        request.exit_time = request.entry_time + chrono::Duration::days(MAX_TRADE_SIMULATION_DAYS + 1);
+        assert_eq!(validate_simulate_trade(&request, now).unwrap_err()[0].field, "exit_time");
+
+        request.entry_time = now + chrono::Duration::minutes(1);
+        request.exit_time = now + chrono::Duration::hours(1);
+        assert_eq!(validate_simulate_trade(&request, now).unwrap_err()[0].field, "entry_time");
+    }
+}
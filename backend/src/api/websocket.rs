@@ -1,56 +1,397 @@
 use axum::{
-    extract::{State, WebSocketUpgrade},
-    response::Response,
+    extract::{Extension, Query, State, WebSocketUpgrade},
+    response::{IntoResponse, Response},
 };
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use chrono::{DateTime, Utc};
 use futures::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    StreamMap,
+};
 use tracing::{error, info, warn};
-use std::{collections::HashSet, sync::Arc};
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashSet, str::FromStr, sync::Arc, time::{Duration, Instant}};
 
+use crate::api::api_token::authorize_owner;
+use crate::api::errors::ApiError;
 use crate::api::handlers::AppState;
-use crate::api::dto::{PriceDto, PositionUpdateDto, LiquidationAlertDto};
+use crate::api::{paper, precision, ws_rooms};
+use crate::api::dto::{
+    LiquidationAlertDto, PortfolioUpdateDto, PositionAlertDto, PositionDiffDto, PositionDto, PositionUpdateDto,
+    PriceAlertTriggeredDto, PriceDto, RiskLimitBreachDto, VaultNavDto,
+};
+use crate::api::position_diff::{DiffThresholds, PositionDiffTracker, PositionFrame};
+use crate::api::throttle::SymbolThrottle;
+use crate::domain::symbols;
+use crate::services::{
+    ApiToken, DomainEvent, EventKind, LiquidationAlert, PaperPositionUpdate, PortfolioUpdate, PositionAlert, PositionUpdate,
+    PriceAlertTriggered, HaltReason, HaltStatus, PriceUpdate, RiskLimitBreach, VaultNav,
+};
+
+// How often throttled symbols are checked for pending values
+const THROTTLE_FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Protocol versions the server speaks, a client picks one in `hello`
+const MIN_PROTOCOL_VERSION: u32 = 1;
+const PROTOCOL_VERSION: u32 = 1;
+
+/// Optional commands a client can negotiate in `hello`
+const FEATURES: [&str; 4] = ["diff_mode", "throttle", "get_position", "user_portfolio"];
+
+/// Update queued for a client, converted to a WsMessage when it is sent
+enum Outbound {
+    Price(PriceUpdate),
+    Position(PositionUpdate),
+    Alert(LiquidationAlert),
+    PositionAlert(PositionAlert),
+    PriceAlert(PriceAlertTriggered),
+    RiskLimit(RiskLimitBreach),
+    VaultNav(VaultNav),
+    Halt(HaltStatus),
+    Portfolio(PortfolioUpdate),
+}
+
+/// Room membership change, applied by the send task which owns the receivers
+enum RoomCommand {
+    Join(String),
+    Leave(String),
+    FollowUser(Pubkey),
+    UnfollowUser(Pubkey),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum UpdateMode {
+    Full,
+    Diff,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum ClientCommand {
+    /// Version handshake, answered with the server's `hello`
+    Hello {
+        protocol_version: u32,
+        #[serde(default)]
+        features: Vec<String>,
+    },
     SubscribeSymbol { symbol: String },
     UnsubscribeSymbol { symbol: String },
+    SetUpdateMode {
+        mode: UpdateMode,
+        pnl_threshold: Option<Decimal>,
+        margin_ratio_threshold: Option<Decimal>,
+        full_refresh_secs: Option<i64>,
+    },
+    /// Max messages per second per symbol, 0 disables throttling
+    SetThrottle { max_per_second: u32 },
+    /// Reply with the position marked to the latest cached price, `request_id`
+    /// is echoed back on the reply
+    GetPosition {
+        position_account: String,
+        request_id: Option<String>,
+    },
+    /// Stream the owner's portfolio totals, starting with their current ones
+    SubscribeUser { owner: String },
+    UnsubscribeUser { owner: String },
+}
+
+impl ClientCommand {
+    /// Feature the client must have negotiated in `hello` to send this
+    fn feature(&self) -> Option<&'static str> {
+        match self {
+            ClientCommand::SetUpdateMode { .. } => Some("diff_mode"),
+            ClientCommand::SetThrottle { .. } => Some("throttle"),
+            ClientCommand::GetPosition { .. } => Some("get_position"),
+            ClientCommand::SubscribeUser { .. } | ClientCommand::UnsubscribeUser { .. } => {
+                Some("user_portfolio")
+            }
+            ClientCommand::Hello { .. }
+            | ClientCommand::SubscribeSymbol { .. }
+            | ClientCommand::UnsubscribeSymbol { .. } => None,
+        }
+    }
+
+    fn request_id(&self) -> Option<String> {
+        match self {
+            ClientCommand::GetPosition { request_id, .. } => request_id.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// What a connection agreed on in `hello`
+#[derive(Debug)]
+struct Negotiated {
+    protocol_version: u32,
+    features: Vec<String>,
+}
+
+/// Why a command is refused on this connection, None when it may run
+fn command_refusal(command: &ClientCommand, negotiated: Option<&Negotiated>) -> Option<String> {
+    if matches!(command, ClientCommand::Hello { .. }) {
+        return None;
+    }
+
+    let Some(negotiated) = negotiated else {
+        return Some("Send hello before any other command".to_string());
+    };
+
+    command
+        .feature()
+        .filter(|feature| !negotiated.features.iter().any(|f| f == feature))
+        .map(|feature| format!("Feature {} was not negotiated in hello", feature))
 }
 
 #[derive(Debug, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum WsMessage {
     Connected { message: String },
+    /// Reply to `hello`: the version in use, the requested features the server
+    /// has and the update types this connection can receive
+    Hello {
+        protocol_version: u32,
+        features: Vec<String>,
+        channels: Vec<&'static str>,
+    },
+    /// Reply to a `hello` for a version outside the range, the socket closes after it
+    UnsupportedProtocol {
+        protocol_version: u32,
+        min_version: u32,
+        max_version: u32,
+    },
     PriceUpdate(PriceDto),
     PositionUpdate(PositionUpdateDto),
+    PositionDiff(PositionDiffDto),
     LiquidationAlert(LiquidationAlertDto),
-    Error { message: String },
+    PositionAlert(PositionAlertDto),
+    PriceAlert(PriceAlertTriggeredDto),
+    RiskLimitBreached(RiskLimitBreachDto),
+    VaultNav(VaultNavDto),
+    PortfolioUpdate(PortfolioUpdateDto),
+    /// Trade-mutating calls are refused until `trading_resumed`
+    TradingHalted {
+        reason: HaltReason,
+        since: Option<DateTime<Utc>>,
+    },
+    TradingResumed { timestamp: Option<DateTime<Utc>> },
+    /// Reply to `get_position`
+    Position {
+        request_id: Option<String>,
+        position: PositionDto,
+    },
+    Error {
+        message: String,
+        /// Set when the error answers a command that carried one
+        #[serde(skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WsQuery {
+    /// Also stream this paper trading session's positions
+    paper_session: Option<String>,
+    /// Also stream the backstop vault's NAV
+    #[serde(default)]
+    vault_nav: bool,
+    /// Full internal decimal scale instead of fixed precision
+    #[serde(default)]
+    raw: bool,
 }
 
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    api_token: Option<Extension<ApiToken>>,
+    Query(query): Query<WsQuery>,
 ) -> Response {
-    ws.on_upgrade(|socket| websocket_handler(socket, state))
+    let paper_rx = match query.paper_session {
+        Some(session) => {
+            let Some(ledger) = &state.paper_ledger else {
+                return ApiError::BadRequest("Paper trading is not enabled".to_string()).into_response();
+            };
+            if !paper::is_valid_session(&session) {
+                return ApiError::BadRequest("Invalid paper_session".to_string()).into_response();
+            }
+            Some((session, ledger.subscribe()))
+        }
+        None => None,
+    };
+
+    let nav_rx = if query.vault_nav {
+        let Some(backstop_vault) = &state.backstop_vault else {
+            return ApiError::BadRequest("Backstop vault is not enabled".to_string()).into_response();
+        };
+        Some(backstop_vault.subscribe_nav())
+    } else {
+        None
+    };
+
+    let raw = query.raw;
+    let api_token = api_token.map(|Extension(api_token)| api_token);
+    ws.on_upgrade(move |socket| websocket_handler(socket, state, api_token, paper_rx, nav_rx, raw))
+}
+
+/// `authorize_owner` for socket commands, the refusal as an error message
+fn owner_refusal(state: &AppState, api_token: Option<&ApiToken>, owner: &Pubkey) -> Option<String> {
+    match authorize_owner(state, api_token, owner) {
+        Ok(()) => None,
+        Err(ApiError::Forbidden(message) | ApiError::Unauthorized(message)) => Some(message),
+        Err(_) => Some(format!("Not authorized for {}", owner)),
+    }
+}
+
+/// Reply to `get_position`: the on-chain position from the monitor cache, or
+/// the socket's paper session position of that account
+async fn position_snapshot(
+    state: &AppState,
+    api_token: Option<&ApiToken>,
+    paper_session: Option<&str>,
+    position_account: &str,
+    request_id: Option<String>,
+) -> WsMessage {
+    let position_account = match Pubkey::from_str(position_account) {
+        Ok(position_account) => position_account,
+        Err(e) => {
+            return WsMessage::Error {
+                message: format!("Invalid position account: {}", e),
+                request_id,
+            }
+        }
+    };
+
+    let position = match state.monitor.get_position_marked(position_account).await {
+        Some(position) => Some(position),
+        None => match (&state.paper_ledger, paper_session) {
+            (Some(ledger), Some(session)) => ledger.get_position(session, position_account).await,
+            _ => None,
+        },
+    };
+
+    if let Some(message) = position
+        .as_ref()
+        .and_then(|position| owner_refusal(state, api_token, &position.owner))
+    {
+        return WsMessage::Error { message, request_id };
+    }
+
+    match position {
+        Some(position) => WsMessage::Position {
+            request_id,
+            position: PositionDto::from(position),
+        },
+        None => WsMessage::Error {
+            message: format!("Position {} not found", position_account),
+            request_id,
+        },
+    }
+}
+
+/// Update types the server sends, `vault_nav` (opted into with `?vault_nav=true`)
+/// only when the backstop vault is enabled
+fn channels(state: &AppState) -> Vec<&'static str> {
+    let mut channels = vec![
+        "price_update",
+        "position_update",
+        "position_diff",
+        "liquidation_alert",
+        "position_alert",
+        "price_alert",
+        "risk_limit_breached",
+        "portfolio_update",
+        "trading_halted",
+        "trading_resumed",
+    ];
+    if state.backstop_vault.is_some() {
+        channels.push("vault_nav");
+    }
+    channels
+}
+
+/// Reply to `hello`, keeping the requested features the server knows
+fn negotiate(protocol_version: u32, features: &[String], channels: Vec<&'static str>) -> WsMessage {
+    if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&protocol_version) {
+        return WsMessage::UnsupportedProtocol {
+            protocol_version,
+            min_version: MIN_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+        };
+    }
+
+    let mut accepted: Vec<String> = Vec::new();
+    for feature in features {
+        if FEATURES.contains(&feature.as_str()) && !accepted.contains(feature) {
+            accepted.push(feature.clone());
+        }
+    }
+
+    WsMessage::Hello {
+        protocol_version,
+        features: accepted,
+        channels,
+    }
+}
+
+/// Next portfolio update, never resolves until the client follows a user
+async fn recv_portfolio(
+    portfolio_rx: &mut Option<broadcast::Receiver<DomainEvent>>,
+) -> Result<DomainEvent, broadcast::error::RecvError> {
+    match portfolio_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
 }
 
-async fn websocket_handler(socket: WebSocket, state: AppState) {
+/// Next update from the paper ledger, never resolves without a session
+async fn recv_paper(
+    paper_rx: &mut Option<(String, broadcast::Receiver<PaperPositionUpdate>)>,
+) -> Result<PaperPositionUpdate, broadcast::error::RecvError> {
+    match paper_rx {
+        Some((_, rx)) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Next vault NAV, never resolves unless the client asked for it
+async fn recv_nav(
+    nav_rx: &mut Option<broadcast::Receiver<VaultNav>>,
+) -> Result<VaultNav, broadcast::error::RecvError> {
+    match nav_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn websocket_handler(
+    socket: WebSocket,
+    state: AppState,
+    api_token: Option<ApiToken>,
+    mut paper_rx: Option<(String, broadcast::Receiver<PaperPositionUpdate>)>,
+    mut nav_rx: Option<broadcast::Receiver<VaultNav>>,
+    raw: bool,
+) {
     let (sender, mut receiver) = socket.split();
 
     // Wrap sender in Arc<Mutex> so it can be shared safely
     let sender = Arc::new(Mutex::new(sender));
 
-    // Subscribe to the broadcast channels for price, position, and liquidation
-    let mut price_rx = state.monitor.subscribe_prices();
-    let mut position_rx = state.monitor.subscribe_positions();
-    let mut liquidation_rx = state.monitor.subscribe_liquidation_alerts();
+    let mut halt_rx = state.trading_halt.subscribe();
 
     info!("WebSocket client connected");
 
-    // Track subscribed symbols; empty means subscribe to all
-    let subscribed_symbols: Arc<RwLock<HashSet<String>>> = Arc::new(RwLock::new(HashSet::new()));
+    // Symbol subscriptions are forwarded to the send task, which joins the rooms
+    let (room_tx, mut room_rx) = mpsc::unbounded_channel::<RoomCommand>();
+
+    // Set when the client switches to diff mode; None streams full updates
+    let diff_tracker: Arc<Mutex<Option<PositionDiffTracker>>> = Arc::new(Mutex::new(None));
+
+    // Per-symbol conflation of price and position updates
+    let throttle: Arc<Mutex<SymbolThrottle<Outbound>>> = Arc::new(Mutex::new(SymbolThrottle::default()));
 
     // Send welcome message
     {
@@ -70,26 +411,212 @@ async fn websocket_handler(socket: WebSocket, state: AppState) {
 
     // Task to handle incoming client messages
     let recv_sender = Arc::clone(&sender);
-    let recv_subscriptions = Arc::clone(&subscribed_symbols);
-    let recv_task = tokio::spawn(async move {
+    let recv_diff_tracker = Arc::clone(&diff_tracker);
+    let recv_throttle = Arc::clone(&throttle);
+    let recv_state = state.clone();
+    let paper_session = paper_rx.as_ref().map(|(session, _)| session.clone());
+    // Replies are serialized here, so this task sets the precision scope too
+    let recv_task = tokio::spawn(precision::with_raw_decimals(raw, async move {
+        // Set by `hello`, commands are refused until then
+        let mut negotiated: Option<Negotiated> = None;
+
         while let Some(Ok(msg)) = receiver.next().await {
             if let Message::Text(text) = msg {
                 match serde_json::from_str::<ClientCommand>(&text) {
                     Ok(cmd) => {
+                        if let Some(message) = command_refusal(&cmd, negotiated.as_ref()) {
+                            let error_msg = WsMessage::Error {
+                                message,
+                                request_id: cmd.request_id(),
+                            };
+                            let mut sender_lock = recv_sender.lock().await;
+                            if let Err(e) =
+                                sender_lock.send(Message::Text(serde_json::to_string(&error_msg).unwrap())).await
+                            {
+                                error!("Failed to send error message: {}", e);
+                                break;
+                            }
+                            continue;
+                        }
+
                         match cmd {
+                            ClientCommand::Hello {
+                                protocol_version,
+                                features,
+                            } => {
+                                let channels = channels(&recv_state);
+                                let reply = negotiate(protocol_version, &features, channels);
+                                let unsupported = matches!(reply, WsMessage::UnsupportedProtocol { .. });
+                                if let WsMessage::Hello { protocol_version, features, .. } = &reply {
+                                    negotiated = Some(Negotiated {
+                                        protocol_version: *protocol_version,
+                                        features: features.clone(),
+                                    });
+                                }
+
+                                let mut sender_lock = recv_sender.lock().await;
+                                if let Err(e) =
+                                    sender_lock.send(Message::Text(serde_json::to_string(&reply).unwrap())).await
+                                {
+                                    error!("Failed to send hello: {}", e);
+                                    break;
+                                }
+
+                                if unsupported {
+                                    warn!("Client asked for protocol version {}, closing", protocol_version);
+                                    let _ = sender_lock
+                                        .send(Message::Close(Some(CloseFrame {
+                                            code: close_code::PROTOCOL,
+                                            reason: "unsupported protocol version".into(),
+                                        })))
+                                        .await;
+                                    break;
+                                }
+
+                                if let Some(negotiated) = &negotiated {
+                                    info!(
+                                        "Client speaks protocol version {} with {:?}",
+                                        negotiated.protocol_version, negotiated.features
+                                    );
+                                }
+                            }
                             ClientCommand::SubscribeSymbol { symbol } => {
-                                info!("Client subscribed to symbol: {}", symbol);
-                                recv_subscriptions.write().await.insert(symbol);
+                                let markets = recv_state.monitor.get_monitored_symbols().await;
+                                match ws_rooms::room_symbol(&symbol, &markets) {
+                                    Ok(symbol) => {
+                                        info!("Client subscribed to symbol: {}", symbol);
+                                        let _ = room_tx.send(RoomCommand::Join(symbol));
+                                    }
+                                    Err(e) => {
+                                        let error_msg = WsMessage::Error {
+                                            message: e.to_string(),
+                                            request_id: None,
+                                        };
+                                        let mut sender_lock = recv_sender.lock().await;
+                                        if let Err(e) = sender_lock
+                                            .send(Message::Text(serde_json::to_string(&error_msg).unwrap()))
+                                            .await
+                                        {
+                                            error!("Failed to send error message: {}", e);
+                                            break;
+                                        }
+                                    }
+                                }
                             }
                             ClientCommand::UnsubscribeSymbol { symbol } => {
+                                let symbol = symbols::normalize(&symbol);
                                 info!("Client unsubscribed from symbol: {}", symbol);
-                                recv_subscriptions.write().await.remove(&symbol);
+                                if let Some(tracker) = recv_diff_tracker.lock().await.as_mut() {
+                                    tracker.forget_symbol(&symbol);
+                                }
+                                let _ = room_tx.send(RoomCommand::Leave(symbol));
+                            }
+                            ClientCommand::SetUpdateMode {
+                                mode,
+                                pnl_threshold,
+                                margin_ratio_threshold,
+                                full_refresh_secs,
+                            } => {
+                                let tracker = match mode {
+                                    UpdateMode::Full => Ok(None),
+                                    UpdateMode::Diff => DiffThresholds::from_client(
+                                        pnl_threshold,
+                                        margin_ratio_threshold,
+                                        full_refresh_secs,
+                                    )
+                                    .map(|thresholds| Some(PositionDiffTracker::new(thresholds))),
+                                };
+                                match tracker {
+                                    Ok(tracker) => {
+                                        info!("Client switched position updates to {:?} mode", mode);
+                                        *recv_diff_tracker.lock().await = tracker;
+                                    }
+                                    // The current mode stays in place
+                                    Err(message) => {
+                                        let error_msg = WsMessage::Error { message, request_id: None };
+                                        let mut sender_lock = recv_sender.lock().await;
+                                        if let Err(e) = sender_lock
+                                            .send(Message::Text(serde_json::to_string(&error_msg).unwrap()))
+                                            .await
+                                        {
+                                            error!("Failed to send error message: {}", e);
+                                            break;
+                                        }
+                                    }
+                                }
+                            }
+                            ClientCommand::SetThrottle { max_per_second } => {
+                                info!("Client set throttle to {} msgs/sec per symbol", max_per_second);
+                                *recv_throttle.lock().await = SymbolThrottle::new(max_per_second);
+                            }
+                            ClientCommand::SubscribeUser { owner } | ClientCommand::UnsubscribeUser { owner }
+                                if Pubkey::from_str(&owner).is_err() =>
+                            {
+                                let error_msg = WsMessage::Error {
+                                    message: format!("Invalid owner: {}", owner),
+                                    request_id: None,
+                                };
+                                let mut sender_lock = recv_sender.lock().await;
+                                if let Err(e) =
+                                    sender_lock.send(Message::Text(serde_json::to_string(&error_msg).unwrap())).await
+                                {
+                                    error!("Failed to send error message: {}", e);
+                                    break;
+                                }
+                            }
+                            ClientCommand::SubscribeUser { owner } => {
+                                let owner = Pubkey::from_str(&owner).expect("checked above");
+                                if let Some(message) = owner_refusal(&recv_state, api_token.as_ref(), &owner) {
+                                    let error_msg = WsMessage::Error { message, request_id: None };
+                                    let mut sender_lock = recv_sender.lock().await;
+                                    if let Err(e) =
+                                        sender_lock.send(Message::Text(serde_json::to_string(&error_msg).unwrap())).await
+                                    {
+                                        error!("Failed to send error message: {}", e);
+                                        break;
+                                    }
+                                    continue;
+                                }
+                                info!("Client subscribed to user: {}", owner);
+                                let _ = room_tx.send(RoomCommand::FollowUser(owner));
+                            }
+                            ClientCommand::UnsubscribeUser { owner } => {
+                                info!("Client unsubscribed from user: {}", owner);
+                                let owner = Pubkey::from_str(&owner).expect("checked above");
+                                if let Some(tracker) = recv_diff_tracker.lock().await.as_mut() {
+                                    for position in recv_state.monitor.get_user_positions(&owner).await.unwrap_or_default() {
+                                        tracker.forget(&position.position_account);
+                                    }
+                                }
+                                let _ = room_tx.send(RoomCommand::UnfollowUser(owner));
+                            }
+                            // Answered on this task, ahead of any queued updates
+                            ClientCommand::GetPosition {
+                                position_account,
+                                request_id,
+                            } => {
+                                let reply = position_snapshot(
+                                    &recv_state,
+                                    api_token.as_ref(),
+                                    paper_session.as_deref(),
+                                    &position_account,
+                                    request_id,
+                                )
+                                .await;
+                                let mut sender_lock = recv_sender.lock().await;
+                                if let Err(e) =
+                                    sender_lock.send(Message::Text(serde_json::to_string(&reply).unwrap())).await
+                                {
+                                    error!("Failed to send position snapshot: {}", e);
+                                    break;
+                                }
                             }
                         }
                     }
                     Err(e) => {
                         let error_msg = WsMessage::Error {
                             message: format!("Invalid command: {}", e),
+                            request_id: None,
                         };
                         let mut sender_lock = recv_sender.lock().await;
                         if let Err(e) =
@@ -105,86 +632,270 @@ async fn websocket_handler(socket: WebSocket, state: AppState) {
                 break;
             }
         }
-    });
+    }));
 
-    // Task to send updates to client filtered by subscribed symbols
+    // Task to send updates from the joined rooms to the client
     let send_sender = Arc::clone(&sender);
-    let send_subscriptions = Arc::clone(&subscribed_symbols);
-    let send_task = tokio::spawn(async move {
-        loop {
-            tokio::select! {
-                Ok(price_update) = price_rx.recv() => {
-                    let subs = send_subscriptions.read().await;
-                    if subs.is_empty() || subs.contains(&price_update.symbol) {
-                        let dto = PriceDto {
-                            symbol: price_update.symbol.clone(),
-                            price: price_update.price,
-                            timestamp: price_update.timestamp,
-                        };
-                        let msg = WsMessage::PriceUpdate(dto);
-                        let mut sender_lock = send_sender.lock().await;
-                        if let Err(e) = sender_lock.send(Message::Text(serde_json::to_string(&msg).unwrap())).await {
-                            warn!("Failed to send price update: {}", e);
-                            break;
+    let ws_rooms = Arc::clone(&state.ws_rooms);
+    let send_diff_tracker = Arc::clone(&diff_tracker);
+    let send_throttle = Arc::clone(&throttle);
+    let send_state = state.clone();
+    // Spawned tasks don't inherit the request's scope, so the sender sets its own
+    let send_task = tokio::spawn(precision::with_raw_decimals(raw, async move {
+        let mut flush_tick = tokio::time::interval(THROTTLE_FLUSH_INTERVAL);
+
+        // Keyed by symbol, `None` is the all-symbols room clients start in
+        let mut rooms: StreamMap<Option<String>, BroadcastStream<DomainEvent>> = StreamMap::new();
+        rooms.insert(None, BroadcastStream::new(ws_rooms.join_all()));
+
+        // Owners whose portfolio updates the client follows, the receiver is
+        // only held while there are any
+        let mut followed: HashSet<Pubkey> = HashSet::new();
+        let mut portfolio_rx: Option<broadcast::Receiver<DomainEvent>> = None;
+
+        'send: loop {
+            let ready = tokio::select! {
+                Some(command) = room_rx.recv() => {
+                    match command {
+                        RoomCommand::Join(symbol) => {
+                            if !rooms.contains_key(&Some(symbol.clone())) {
+                                let markets = send_state.monitor.get_monitored_symbols().await;
+                                match ws_rooms.join(&symbol, &markets) {
+                                    Ok(room) => {
+                                        rooms.remove(&None);
+                                        rooms.insert(Some(symbol), BroadcastStream::new(room));
+                                    }
+                                    // Checked on subscribe, only if the market list changed since
+                                    Err(e) => warn!("Not joining room: {}", e),
+                                }
+                            }
+                            vec![]
+                        }
+                        RoomCommand::Leave(symbol) => {
+                            if rooms.remove(&Some(symbol.clone())).is_some() {
+                                ws_rooms.leave(&symbol);
+                            }
+                            if rooms.is_empty() {
+                                rooms.insert(None, BroadcastStream::new(ws_rooms.join_all()));
+                            }
+                            vec![]
+                        }
+                        RoomCommand::FollowUser(owner) => {
+                            if portfolio_rx.is_none() {
+                                portfolio_rx = Some(send_state.event_bus.receiver(EventKind::Portfolio));
+                            }
+                            followed.insert(owner);
+                            // Current totals now, updates follow as they change
+                            vec![Outbound::Portfolio(send_state.monitor.portfolio(&owner))]
+                        }
+                        RoomCommand::UnfollowUser(owner) => {
+                            followed.remove(&owner);
+                            if followed.is_empty() {
+                                portfolio_rx = None;
+                            }
+                            vec![]
                         }
                     }
                 },
-                Ok(position_update) = position_rx.recv() => {
-                    let subs = send_subscriptions.read().await;
-                    if subs.is_empty() || subs.contains(&position_update.symbol) {
-                        let dto = PositionUpdateDto {
-                            position_account: position_update.position_account.to_string(),
-                            symbol: position_update.symbol.clone(),
-                            side: position_update.side,
-                            size: position_update.size,
-                            entry_price: position_update.entry_price,
-                            mark_price: position_update.mark_price,
-                            unrealized_pnl: position_update.unrealized_pnl,
-                            margin_ratio: position_update.margin_ratio,
-                            timestamp: position_update.timestamp,
-                        };
-                        let msg = WsMessage::PositionUpdate(dto);
-                        let mut sender_lock = send_sender.lock().await;
-                        if let Err(e) = sender_lock.send(Message::Text(serde_json::to_string(&msg).unwrap())).await {
-                            warn!("Failed to send position update: {}", e);
-                            break;
+                Some((_, event)) = rooms.next() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                            warn!("WebSocket client lagged, skipped {} updates", skipped);
+                            continue;
+                        }
+                    };
+                    match event {
+                        DomainEvent::Price(price_update) => {
+                            let symbol = price_update.symbol.clone();
+                            send_throttle.lock().await.offer(
+                                &symbol,
+                                symbol.clone(),
+                                Outbound::Price(price_update),
+                                Instant::now(),
+                            )
                         }
+                        DomainEvent::Position(position_update) => {
+                            let symbol = position_update.symbol.clone();
+                            send_throttle.lock().await.offer(
+                                &symbol,
+                                position_update.position_account.to_string(),
+                                Outbound::Position(position_update),
+                                Instant::now(),
+                            )
+                        }
+                        // Alerts are never conflated
+                        DomainEvent::Liquidation(alert) => vec![Outbound::Alert(alert)],
+                        DomainEvent::PositionAlert(alert) => vec![Outbound::PositionAlert(alert)],
+                        DomainEvent::PriceAlert(alert) => vec![Outbound::PriceAlert(alert)],
+                        DomainEvent::RiskLimitBreached(breach) => vec![Outbound::RiskLimit(breach)],
+                        // Not fanned out to rooms, see below
+                        DomainEvent::Portfolio(_) => continue,
+                    }
+                },
+                // Per owner rather than per symbol, once per PnL pass so never conflated
+                event = recv_portfolio(&mut portfolio_rx) => match event {
+                    Ok(DomainEvent::Portfolio(portfolio)) if followed.contains(&portfolio.owner) => {
+                        vec![Outbound::Portfolio(portfolio)]
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("WebSocket client lagged, skipped {} portfolio updates", skipped);
+                        continue;
+                    }
+                    _ => continue,
+                },
+                Ok(paper_update) = recv_paper(&mut paper_rx) => {
+                    let session = paper_rx.as_ref().map(|(session, _)| session);
+                    if session != Some(&paper_update.session) {
+                        continue;
                     }
+                    // Per session rather than per symbol, so filtered here
+                    let position_update = paper_update.update;
+                    if !rooms.contains_key(&None) && !rooms.contains_key(&Some(position_update.symbol.clone())) {
+                        continue;
+                    }
+                    let symbol = position_update.symbol.clone();
+                    send_throttle.lock().await.offer(
+                        &symbol,
+                        position_update.position_account.to_string(),
+                        Outbound::Position(position_update),
+                        Instant::now(),
+                    )
+                },
+                // Vault-wide, not filtered by symbol
+                Ok(nav) = recv_nav(&mut nav_rx) => vec![Outbound::VaultNav(nav)],
+                // Sent to every client, never conflated
+                Ok(()) = halt_rx.changed() => {
+                    let status = halt_rx.borrow_and_update().clone();
+                    vec![Outbound::Halt(status)]
                 },
-                Ok(alert) = liquidation_rx.recv() => {
-                    let subs = send_subscriptions.read().await;
-                    if subs.is_empty() || subs.contains(&alert.symbol) {
-                        let dto = LiquidationAlertDto {
-                            risk_type: alert.risk_type,
-                            position_account: alert.position_account,
-                            symbol: alert.symbol.clone(),
-                            side: alert.side,
-                            liquidation_price: alert.liquidation_price,
-                            current_price: alert.current_price,
+                _ = flush_tick.tick() => {
+                    send_throttle.lock().await.drain_ready(Instant::now())
+                },
+                // Every source closed, nothing more will come
+                else => break 'send,
+            };
+
+            for outbound in ready {
+                let msg = match outbound {
+                    Outbound::Price(price_update) => WsMessage::PriceUpdate(PriceDto::new(
+                        price_update.symbol,
+                        price_update.price,
+                        price_update.publish_time.unwrap_or(price_update.timestamp),
+                        price_update.timestamp,
+                    )),
+                    // Diff after conflation so dropped frames can't lose changed fields
+                    Outbound::Position(position_update) => {
+                        let frame = match send_diff_tracker.lock().await.as_mut() {
+                            Some(tracker) => tracker.next_frame(&position_update),
+                            None => Some(PositionFrame::Full(PositionUpdateDto::from(&position_update))),
                         };
-                        let msg = WsMessage::LiquidationAlert(dto);
-                        let mut sender_lock = send_sender.lock().await;
-                        if let Err(e) = sender_lock.send(Message::Text(serde_json::to_string(&msg).unwrap())).await {
-                            warn!("Failed to send liquidation alert: {}", e);
-                            break;
+                        match frame {
+                            Some(PositionFrame::Full(dto)) => WsMessage::PositionUpdate(dto),
+                            Some(PositionFrame::Diff(dto)) => WsMessage::PositionDiff(dto),
+                            None => continue,
                         }
                     }
-                },
-                else => {
-                    break;
+                    Outbound::Alert(alert) => WsMessage::LiquidationAlert(LiquidationAlertDto::from(alert)),
+                    Outbound::PositionAlert(alert) => WsMessage::PositionAlert(PositionAlertDto::from(alert)),
+                    Outbound::PriceAlert(alert) => WsMessage::PriceAlert(PriceAlertTriggeredDto::from(alert)),
+                    Outbound::RiskLimit(breach) => WsMessage::RiskLimitBreached(RiskLimitBreachDto::from(breach)),
+                    Outbound::VaultNav(nav) => WsMessage::VaultNav(VaultNavDto::from(nav)),
+                    Outbound::Portfolio(portfolio) => WsMessage::PortfolioUpdate(PortfolioUpdateDto::from(portfolio)),
+                    Outbound::Halt(status) => match status.reason {
+                        Some(reason) => WsMessage::TradingHalted {
+                            reason,
+                            since: status.since,
+                        },
+                        None => WsMessage::TradingResumed {
+                            timestamp: status.since,
+                        },
+                    },
+                };
+
+                let mut sender_lock = send_sender.lock().await;
+                if let Err(e) = sender_lock.send(Message::Text(serde_json::to_string(&msg).unwrap())).await {
+                    warn!("Failed to send update: {}", e);
+                    break 'send;
                 }
             }
         }
-    });
+    }));
 
     // Pin the tasks
     tokio::pin!(recv_task);
     tokio::pin!(send_task);
 
     tokio::select! {
-        _ = &mut recv_task => send_task.abort(),
+        _ = &mut recv_task => {
+            send_task.abort();
+            // Wait for the abort so the send task's room receivers are dropped
+            let _ = (&mut send_task).await;
+        }
         _ = &mut send_task => recv_task.abort(),
     }
 
+    // Close the rooms this socket was the last member of
+    state.ws_rooms.prune();
+
     info!("WebSocket connection closed");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_keeps_known_features() {
+        let features = vec!["diff_mode".to_string(), "binary_frames".to_string(), "throttle".to_string(), "diff_mode".to_string()];
+
+        match negotiate(PROTOCOL_VERSION, &features, vec!["price_update"]) {
+            WsMessage::Hello {
+                protocol_version,
+                features,
+                channels,
+            } => {
+                assert_eq!(protocol_version, PROTOCOL_VERSION);
+                assert_eq!(features, vec!["diff_mode".to_string(), "throttle".to_string()]);
+                assert_eq!(channels, vec!["price_update"]);
+            }
+            other => panic!("expected hello, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_negotiate_rejects_unsupported_version() {
+        let reply = negotiate(PROTOCOL_VERSION + 1, &[], vec![]);
+        let json = serde_json::to_value(&reply).unwrap();
+
+        assert_eq!(json["type"], "unsupported_protocol");
+        assert_eq!(json["min_version"], MIN_PROTOCOL_VERSION);
+        assert_eq!(json["max_version"], PROTOCOL_VERSION);
+        assert!(matches!(negotiate(0, &[], vec![]), WsMessage::UnsupportedProtocol { .. }));
+    }
+
+    #[test]
+    fn test_commands_need_hello_and_their_feature() {
+        let subscribe = ClientCommand::SubscribeSymbol {
+            symbol: "SOL-USD".to_string(),
+        };
+        let throttle = ClientCommand::SetThrottle { max_per_second: 2 };
+        let hello = ClientCommand::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            features: vec![],
+        };
+
+        assert!(command_refusal(&hello, None).is_none());
+        assert!(command_refusal(&subscribe, None).is_some());
+
+        let negotiated = Negotiated {
+            protocol_version: PROTOCOL_VERSION,
+            features: vec!["diff_mode".to_string()],
+        };
+        assert!(command_refusal(&subscribe, Some(&negotiated)).is_none());
+        assert_eq!(
+            command_refusal(&throttle, Some(&negotiated)),
+            Some("Feature throttle was not negotiated in hello".to_string())
+        );
+    }
+}
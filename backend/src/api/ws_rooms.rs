@@ -0,0 +1,227 @@
+/// WebSocket Rooms
+/// Per-symbol broadcast channels WebSocket connections join instead of each
+/// filtering the global streams. One fan-out task per node routes every
+/// event once, to its symbol's room and to the room of clients following all
+/// symbols, so a connection only ever receives what it will send
+/// Rooms only open for configured markets and close with their last member,
+/// so the room map stays bounded by the market list
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{info, warn};
+
+use crate::domain::symbols;
+use crate::services::{DomainEvent, EventBus, EventKind};
+
+// Per room, a connection further behind than this skips ahead
+const ROOM_CAPACITY: usize = 1024;
+
+/// Canonical form of `symbol` when it is one of the configured `markets`,
+/// the only symbols a room can be opened for
+pub fn room_symbol(symbol: &str, markets: &[String]) -> Result<String> {
+    let symbol = symbols::canonicalize(symbol)?;
+    if !markets.contains(&symbol) {
+        return Err(anyhow!("Unknown market: {}", symbol));
+    }
+    Ok(symbol)
+}
+
+pub struct WsRooms {
+    /// Clients with no symbol subscriptions get everything
+    all: broadcast::Sender<DomainEvent>,
+    rooms: RwLock<HashMap<String, broadcast::Sender<DomainEvent>>>,
+}
+
+impl Default for WsRooms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WsRooms {
+    pub fn new() -> Self {
+        let (all, _) = broadcast::channel(ROOM_CAPACITY);
+
+        Self {
+            all,
+            rooms: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Receiver for `symbol`'s room, created on first join, fails for
+    /// symbols that are not one of the configured `markets`
+    pub fn join(&self, symbol: &str, markets: &[String]) -> Result<broadcast::Receiver<DomainEvent>> {
+        let symbol = room_symbol(symbol, markets)?;
+        if let Some(room) = self.rooms.read().unwrap().get(&symbol) {
+            return Ok(room.subscribe());
+        }
+
+        Ok(self
+            .rooms
+            .write()
+            .unwrap()
+            .entry(symbol)
+            .or_insert_with(|| broadcast::channel(ROOM_CAPACITY).0)
+            .subscribe())
+    }
+
+    /// Drop `symbol`'s room if a member leaving it was the last one,
+    /// call after the member's receiver is dropped
+    pub fn leave(&self, symbol: &str) {
+        let mut rooms = self.rooms.write().unwrap();
+        if rooms.get(symbol).is_some_and(|room| room.receiver_count() == 0) {
+            rooms.remove(symbol);
+        }
+    }
+
+    /// Drop every room without members, for a disconnected socket whose
+    /// rooms are not tracked individually
+    pub fn prune(&self) {
+        self.rooms
+            .write()
+            .unwrap()
+            .retain(|_, room| room.receiver_count() > 0);
+    }
+
+    /// Receiver for every symbol
+    pub fn join_all(&self) -> broadcast::Receiver<DomainEvent> {
+        self.all.subscribe()
+    }
+
+    /// Open rooms, each dropped once its last member leaves or disconnects
+    pub fn room_count(&self) -> usize {
+        self.rooms.read().unwrap().len()
+    }
+
+    /// Route an event to its symbol's room and the all-symbols room, events
+    /// without a symbol only reach the latter
+    /// A room whose members all left is dropped
+    pub fn publish(&self, event: DomainEvent) {
+        if let Some(symbol) = event.symbol() {
+            let empty = {
+                let rooms = self.rooms.read().unwrap();
+                match rooms.get(symbol) {
+                    Some(room) => room.send(event.clone()).is_err(),
+                    None => false,
+                }
+            };
+
+            if empty {
+                let mut rooms = self.rooms.write().unwrap();
+                // Someone may have joined since the send
+                if rooms.get(symbol).is_some_and(|room| room.receiver_count() == 0) {
+                    rooms.remove(symbol);
+                }
+            }
+        }
+
+        let _ = self.all.send(event);
+    }
+
+    /// Fan events from the event bus out to the rooms in the background
+    /// Portfolio updates are per owner, sockets following one take them from
+    /// the bus directly
+    pub fn spawn(self: &Arc<Self>, event_bus: &Arc<dyn EventBus>) {
+        let kinds: Vec<EventKind> = EventKind::ALL
+            .into_iter()
+            .filter(|kind| *kind != EventKind::Portfolio)
+            .collect();
+        let mut events = event_bus.subscribe(&kinds);
+
+        let rooms = Arc::clone(self);
+        tokio::spawn(async move {
+            info!("WebSocket room fan-out started");
+
+            loop {
+                match events.recv().await {
+                    Ok(event) => rooms.publish(event),
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("WebSocket room fan-out lagged, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::PriceUpdate;
+    use chrono::Utc;
+    use rust_decimal::Decimal;
+
+    fn price(symbol: &str) -> DomainEvent {
+        DomainEvent::Price(PriceUpdate {
+            symbol: symbol.to_string(),
+            price: Decimal::new(150, 0),
+            timestamp: Utc::now(),
+            publish_time: None,
+        })
+    }
+
+    fn markets() -> Vec<String> {
+        vec!["SOL-USD".to_string(), "BTC-USD".to_string()]
+    }
+
+    #[test]
+    fn test_publish_routes_by_symbol() {
+        let rooms = WsRooms::new();
+        let mut sol = rooms.join("SOL-USD", &markets()).unwrap();
+        let mut btc = rooms.join("btcusdt", &markets()).unwrap();
+        let mut all = rooms.join_all();
+
+        rooms.publish(price("SOL-USD"));
+
+        assert_eq!(sol.try_recv().unwrap().symbol(), Some("SOL-USD"));
+        assert!(btc.try_recv().is_err());
+        assert_eq!(all.try_recv().unwrap().symbol(), Some("SOL-USD"));
+    }
+
+    #[test]
+    fn test_empty_rooms_are_dropped() {
+        let rooms = WsRooms::new();
+        let sol = rooms.join("SOL-USD", &markets()).unwrap();
+        assert_eq!(rooms.room_count(), 1);
+
+        drop(sol);
+        rooms.publish(price("SOL-USD"));
+        assert_eq!(rooms.room_count(), 0);
+
+        // Rejoining opens a fresh room
+        let mut sol = rooms.join("SOL-USD", &markets()).unwrap();
+        rooms.publish(price("SOL-USD"));
+        assert!(sol.try_recv().is_ok());
+    }
+
+    #[test]
+    fn test_unknown_markets_open_no_room() {
+        let rooms = WsRooms::new();
+
+        assert!(rooms.join("DOGE-USD", &markets()).is_err());
+        assert!(rooms.join("not a symbol", &markets()).is_err());
+        assert_eq!(rooms.room_count(), 0);
+    }
+
+    #[test]
+    fn test_leave_and_prune_drop_empty_rooms() {
+        let rooms = WsRooms::new();
+        let sol = rooms.join("SOL-USD", &markets()).unwrap();
+        let btc = rooms.join("BTC-USD", &markets()).unwrap();
+        let other_sol = rooms.join("SOL-USD", &markets()).unwrap();
+
+        drop(sol);
+        rooms.leave("SOL-USD");
+        assert_eq!(rooms.room_count(), 2, "another member is still in the room");
+
+        drop(other_sol);
+        rooms.leave("SOL-USD");
+        assert_eq!(rooms.room_count(), 1);
+
+        drop(btc);
+        rooms.prune();
+        assert_eq!(rooms.room_count(), 0);
+    }
+}
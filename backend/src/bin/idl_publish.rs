@@ -0,0 +1,153 @@
+//! Publish the program's IDL on chain
+//!
+//! Runs `anchor idl init` the first time and `anchor idl upgrade` after that,
+//! so integrators and `GET /program/idl` read the IDL of the deployed program.
+//! Refuses an IDL whose instruction discriminators differ from the ones the
+//! backend encodes, and does nothing when the published IDL is already current.
+//! Needs the anchor CLI on PATH, signs with the wallet from Anchor.toml unless
+//! `--wallet` is given, which must be the IDL authority on upgrades
+//!
+//! cargo run --bin idl-publish -- --filepath ../position-management-system/target/idl/position_management_system.json
+
+use anyhow::{anyhow, bail, Context, Result};
+use perpetual_backend::infrastructure::{decode_idl_account, idl_address, mismatched_instructions};
+use perpetual_backend::services::INSTRUCTION_DISCRIMINATORS;
+use serde_json::Value;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::process::Command;
+
+const DEFAULT_IDL_PATH: &str = "../position-management-system/target/idl/position_management_system.json";
+
+struct PublishArgs {
+    filepath: String,
+    rpc_url: String,
+    program_id: Pubkey,
+    wallet: Option<String>,
+    /// Publish even when the IDL doesn't match the backend's encoding
+    force: bool,
+    dry_run: bool,
+}
+
+impl PublishArgs {
+    fn from_args() -> Result<Self> {
+        let mut args = Self {
+            filepath: DEFAULT_IDL_PATH.to_string(),
+            rpc_url: std::env::var("RPC_URL")
+                .unwrap_or_else(|_| "https://api.devnet.solana.com".to_string()),
+            program_id: std::env::var("PROGRAM_ID")
+                .context("PROGRAM_ID is not set")?
+                .parse()
+                .context("Invalid PROGRAM_ID")?,
+            wallet: None,
+            force: false,
+            dry_run: false,
+        };
+
+        let mut flags = std::env::args().skip(1);
+        while let Some(flag) = flags.next() {
+            match flag.as_str() {
+                "--force" => {
+                    args.force = true;
+                    continue;
+                }
+                "--dry-run" => {
+                    args.dry_run = true;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let value = flags
+                .next()
+                .ok_or_else(|| anyhow!("Missing value for {}", flag))?;
+
+            match flag.as_str() {
+                "--filepath" => args.filepath = value,
+                "--rpc-url" => args.rpc_url = value,
+                "--wallet" => args.wallet = Some(value),
+                _ => return Err(anyhow!("Unknown flag {}", flag)),
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+
+    let args = PublishArgs::from_args()?;
+    let local: Value = serde_json::from_slice(
+        &std::fs::read(&args.filepath).with_context(|| format!("Failed to read {}", args.filepath))?,
+    )
+    .with_context(|| format!("{} is not valid JSON", args.filepath))?;
+
+    if let Some(address) = local.get("address").and_then(Value::as_str) {
+        if address != args.program_id.to_string() {
+            bail!("IDL is for program {}, not {}", address, args.program_id);
+        }
+    }
+
+    let mismatched = mismatched_instructions(&local, &INSTRUCTION_DISCRIMINATORS);
+    if !mismatched.is_empty() {
+        let message = format!("IDL encodes {} differently from the backend", mismatched.join(", "));
+        if !args.force {
+            bail!("{}, pass --force to publish it anyway", message);
+        }
+        println!("Warning: {}", message);
+    }
+
+    let address = idl_address(&args.program_id);
+    let rpc_client = RpcClient::new_with_commitment(args.rpc_url.clone(), CommitmentConfig::confirmed());
+    let published = rpc_client
+        .get_account_with_commitment(&address, CommitmentConfig::confirmed())
+        .context("Failed to fetch the IDL account")?
+        .value
+        .map(|account| decode_idl_account(address, &account.data))
+        .transpose()?;
+
+    let action = match &published {
+        Some(published) if published.idl == local => {
+            println!("IDL at {} is up to date", address);
+            return Ok(());
+        }
+        Some(published) => {
+            println!("Upgrading IDL at {} (authority {})", address, published.authority);
+            "upgrade"
+        }
+        None => {
+            println!("Initializing IDL at {}", address);
+            "init"
+        }
+    };
+
+    let mut command = Command::new("anchor");
+    command.args(["--provider.cluster", &args.rpc_url]);
+    if let Some(wallet) = &args.wallet {
+        command.args(["--provider.wallet", wallet]);
+    }
+    command.args(["idl", action, "--filepath", &args.filepath, &args.program_id.to_string()]);
+
+    if args.dry_run {
+        println!("Dry run: {:?}", command);
+        return Ok(());
+    }
+
+    let status = command.status().context("Failed to run anchor, is it installed?")?;
+    if !status.success() {
+        bail!("anchor idl {} failed with {}", action, status);
+    }
+
+    let account = rpc_client
+        .get_account_with_commitment(&address, CommitmentConfig::confirmed())?
+        .value
+        .ok_or_else(|| anyhow!("IDL account {} missing after publishing", address))?;
+    if decode_idl_account(address, &account.data)?.idl != local {
+        bail!("Published IDL at {} differs from {}", address, args.filepath);
+    }
+
+    println!("Published IDL at {}", address);
+    Ok(())
+}
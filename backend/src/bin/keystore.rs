@@ -0,0 +1,79 @@
+//! Create the encrypted keystore used by SIGNER_TYPE=keystore
+//!
+//! Encrypts an existing Solana CLI keypair file, or a freshly generated
+//! keypair, with the passphrase read from `--passphrase-file` (defaults to
+//! SIGNER_KEYSTORE_PASSPHRASE_FILE), the same file the service reads it from.
+//! Never overwrites an existing keystore
+//!
+//! cargo run --bin keystore -- --out /etc/perps/payer.keystore.json --keypair ~/.config/solana/id.json
+
+use anyhow::{anyhow, bail, Context, Result};
+use perpetual_backend::infrastructure::{read_secret_file, EncryptedKeystore};
+use solana_sdk::signature::{read_keypair_file, Keypair};
+use solana_sdk::signer::Signer;
+use std::path::Path;
+
+struct KeystoreArgs {
+    out: String,
+    /// Solana CLI keypair to encrypt, a new one is generated without it
+    keypair: Option<String>,
+    passphrase_file: Option<String>,
+}
+
+impl KeystoreArgs {
+    fn from_args() -> Result<Self> {
+        let mut args = Self {
+            out: String::new(),
+            keypair: None,
+            passphrase_file: std::env::var("SIGNER_KEYSTORE_PASSPHRASE_FILE").ok(),
+        };
+
+        let mut flags = std::env::args().skip(1);
+        while let Some(flag) = flags.next() {
+            let value = flags
+                .next()
+                .ok_or_else(|| anyhow!("Missing value for {}", flag))?;
+
+            match flag.as_str() {
+                "--out" => args.out = value,
+                "--keypair" => args.keypair = Some(value),
+                "--passphrase-file" => args.passphrase_file = Some(value),
+                _ => return Err(anyhow!("Unknown flag {}", flag)),
+            }
+        }
+
+        if args.out.is_empty() {
+            bail!("--out is required");
+        }
+
+        Ok(args)
+    }
+}
+
+fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+
+    let args = KeystoreArgs::from_args()?;
+    if Path::new(&args.out).exists() {
+        bail!("{} already exists, refusing to overwrite it", args.out);
+    }
+
+    let passphrase_file = args
+        .passphrase_file
+        .as_deref()
+        .context("Pass --passphrase-file or set SIGNER_KEYSTORE_PASSPHRASE_FILE")?;
+    let passphrase = read_secret_file(passphrase_file)?;
+
+    let keypair = match &args.keypair {
+        Some(path) => read_keypair_file(path).map_err(|e| anyhow!("Failed to read keypair {}: {}", path, e))?,
+        None => Keypair::new(),
+    };
+
+    let keystore = EncryptedKeystore::encrypt(&keypair, &passphrase)?;
+    keystore.decrypt(&passphrase).context("Keystore does not decrypt")?;
+    keystore.write(&args.out)?;
+
+    println!("Wrote keystore for {} to {}", keypair.pubkey(), args.out);
+
+    Ok(())
+}
@@ -0,0 +1,296 @@
+//! Third-party liquidator driven by the backend's alert stream
+//!
+//! Connects to the backend WebSocket, and for every `Liquidated` alert reads
+//! the position from chain, checks the keeper reward against the transaction
+//! fee and sends a full liquidation signed with its own wallet. The wallet has
+//! to be on the program's liquidator whitelist when the whitelist is enabled.
+//! Uses the same signer env vars as the backend (SIGNER_TYPE, SOLANA_PRIVATE_KEY, ...)
+//!
+//! cargo run --bin liquidator-bot -- --ws-url ws://localhost:3000/ws --reward-bps 50 --dry-run
+
+use anyhow::{anyhow, Context, Result};
+use futures::StreamExt;
+use perpetual_backend::domain::Risk;
+use perpetual_backend::infrastructure::{init_tracing, SignerConfig, SolanaClient, TelemetryConfig};
+use perpetual_backend::services::{
+    deserialize_position_account, liquidate_position_instruction, migrate_position_instruction,
+    LiquidationCostModel, OnChainPosition,
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{error, info, warn};
+
+/// Fees are paid in SOL, priced from the backend's own price stream
+const SOL_SYMBOL: &str = "SOL-USD";
+/// Full liquidation, the program takes the close size in basis points
+const CLOSE_ALL_BPS: u16 = 10_000;
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+struct BotConfig {
+    ws_url: String,
+    rpc_url: String,
+    program_id: Pubkey,
+    cost_model: LiquidationCostModel,
+    /// Skip liquidations expected to earn less than this, in USD
+    min_profit: Decimal,
+    /// Before trying the same position again
+    cooldown: Duration,
+    dry_run: bool,
+}
+
+impl BotConfig {
+    fn from_args() -> Result<Self> {
+        let mut config = Self {
+            ws_url: "ws://localhost:3000/ws".to_string(),
+            rpc_url: std::env::var("RPC_URL")
+                .unwrap_or_else(|_| "https://api.devnet.solana.com".to_string()),
+            program_id: std::env::var("PROGRAM_ID")
+                .context("PROGRAM_ID is not set")?
+                .parse()
+                .context("Invalid PROGRAM_ID")?,
+            cost_model: LiquidationCostModel::default(),
+            min_profit: Decimal::ZERO,
+            cooldown: Duration::from_secs(30),
+            dry_run: false,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            if flag == "--dry-run" {
+                config.dry_run = true;
+                continue;
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("Missing value for {}", flag))?;
+
+            match flag.as_str() {
+                "--ws-url" => config.ws_url = value,
+                "--rpc-url" => config.rpc_url = value,
+                "--reward-bps" => config.cost_model.reward_bps = value.parse()?,
+                "--compute-units" => config.cost_model.compute_unit_limit = value.parse()?,
+                "--priority-fee" => config.cost_model.compute_unit_price_micro_lamports = value.parse()?,
+                "--min-profit" => config.min_profit = value.parse()?,
+                "--cooldown-secs" => config.cooldown = Duration::from_secs(value.parse()?),
+                _ => return Err(anyhow!("Unknown flag {}", flag)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// The parts of the backend's WebSocket messages the bot reads
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamMessage {
+    PriceUpdate {
+        symbol: String,
+        price: Decimal,
+    },
+    LiquidationAlert {
+        risk_type: Risk,
+        position_account: String,
+        symbol: String,
+        current_price: Decimal,
+    },
+    #[serde(other)]
+    Other,
+}
+
+struct Bot {
+    config: BotConfig,
+    solana_client: SolanaClient,
+    /// Latest streamed price of each symbol
+    prices: Mutex<HashMap<String, Decimal>>,
+    /// Last attempt per position, so repeated alerts don't stack transactions
+    attempts: Mutex<HashMap<Pubkey, Instant>>,
+}
+
+impl Bot {
+    /// Stream alerts until the connection drops
+    async fn run_connection(self: &Arc<Self>) -> Result<()> {
+        let (stream, _) = connect_async(self.config.ws_url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.config.ws_url))?;
+        info!("Connected to {}", self.config.ws_url);
+
+        let (_, mut receiver) = stream.split();
+        while let Some(message) = receiver.next().await {
+            let Message::Text(text) = message? else {
+                continue;
+            };
+
+            match serde_json::from_str::<StreamMessage>(&text) {
+                Ok(StreamMessage::PriceUpdate { symbol, price }) => {
+                    self.prices.lock().await.insert(symbol, price);
+                }
+                Ok(StreamMessage::LiquidationAlert {
+                    risk_type: Risk::Liquidated,
+                    position_account,
+                    symbol,
+                    current_price,
+                }) => {
+                    let Ok(position_account) = position_account.parse::<Pubkey>() else {
+                        warn!("Alert with invalid position account {}", position_account);
+                        continue;
+                    };
+                    if !self.claim(position_account).await {
+                        continue;
+                    }
+
+                    let bot = Arc::clone(self);
+                    tokio::spawn(async move {
+                        if let Err(e) = bot.liquidate(position_account, &symbol, current_price).await {
+                            error!("Failed to liquidate {} {}: {}", symbol, position_account, e);
+                        }
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Skipping unreadable message: {}", e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the position is due another attempt, recording this one
+    async fn claim(&self, position_account: Pubkey) -> bool {
+        let now = Instant::now();
+        let mut attempts = self.attempts.lock().await;
+        attempts.retain(|_, at| now.duration_since(*at) < self.config.cooldown);
+
+        if attempts.contains_key(&position_account) {
+            return false;
+        }
+        attempts.insert(position_account, now);
+        true
+    }
+
+    async fn liquidate(&self, position_account: Pubkey, symbol: &str, mark_price: Decimal) -> Result<()> {
+        let rpc_client = RpcClient::new(&self.config.rpc_url);
+        let account = rpc_client.get_account(&position_account)?;
+        let (position_index, on_chain) = deserialize_position_account(position_account, &account)?;
+        let position = on_chain.to_domain_position(position_account, position_index)?;
+
+        if !position.is_open() {
+            info!("{} {} is already {:?}", symbol, position_account, position.status);
+            return Ok(());
+        }
+
+        let sol_price = *self
+            .prices
+            .lock()
+            .await
+            .get(SOL_SYMBOL)
+            .ok_or_else(|| anyhow!("No {} price yet to value the fee", SOL_SYMBOL))?;
+
+        let estimate = self
+            .config
+            .cost_model
+            .estimate(position.size * mark_price, sol_price);
+
+        if estimate.profit < self.config.min_profit {
+            info!(
+                "Skipping {} {}: reward ${} against fee ${}",
+                symbol,
+                position_account,
+                estimate.reward.round_dp(6),
+                estimate.fee.round_dp(6)
+            );
+            return Ok(());
+        }
+
+        if self.config.dry_run {
+            info!(
+                "Would liquidate {} {} at ${} for ${} profit",
+                symbol,
+                position_account,
+                mark_price,
+                estimate.profit.round_dp(6)
+            );
+            return Ok(());
+        }
+
+        let mut instructions = self.config.cost_model.compute_budget_instructions();
+        // The program can't load a Position from before its latest fields
+        if account.data.len() < OnChainPosition::MAX_SIZE {
+            instructions.push(migrate_position_instruction(
+                &self.config.program_id,
+                &position_account,
+                &self.solana_client.payer_pubkey(),
+            ));
+        }
+        instructions.push(liquidate_position_instruction(
+            &self.config.program_id,
+            &position,
+            &self.solana_client.payer_pubkey(),
+            CLOSE_ALL_BPS,
+        )?);
+
+        let signature = self.solana_client.send_transaction(&instructions)?;
+        info!(
+            "Liquidated {} {} at ${} for ${} expected profit: {}",
+            symbol,
+            position_account,
+            mark_price,
+            estimate.profit.round_dp(6),
+            signature
+        );
+
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+    init_tracing(&TelemetryConfig {
+        otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok(),
+        service_name: "liquidator-bot".to_string(),
+    })?;
+
+    let config = BotConfig::from_args()?;
+    let payer = SignerConfig::from_env()?.load()?;
+    info!("Liquidator: {}", payer.pubkey());
+    info!(
+        "Reward {} bps, fee {} lamports per liquidation{}",
+        config.cost_model.reward_bps,
+        config.cost_model.fee_lamports(),
+        if config.dry_run { ", dry run" } else { "" }
+    );
+
+    let solana_client = SolanaClient::new(config.program_id, payer, config.rpc_url.clone());
+    let bot = Arc::new(Bot {
+        config,
+        solana_client,
+        prices: Mutex::new(HashMap::new()),
+        attempts: Mutex::new(HashMap::new()),
+    });
+
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let started = Instant::now();
+        match bot.run_connection().await {
+            Ok(()) => warn!("Alert stream closed"),
+            Err(e) => error!("Alert stream failed: {}", e),
+        }
+
+        // A connection that held for a while starts the backoff over
+        if started.elapsed() > MAX_RECONNECT_BACKOFF {
+            backoff = Duration::from_secs(1);
+        }
+        info!("Reconnecting in {:?}", backoff);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+    }
+}
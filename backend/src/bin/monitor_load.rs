@@ -0,0 +1,164 @@
+//! Synthetic load run against the position monitor
+//!
+//! Seeds N fake positions, replays random-walk price ticks and reports PnL
+//! pass latency, broadcast throughput and read-lock contention
+//!
+//! cargo run --release --bin monitor_load -- --positions 50000 --ticks 200 --readers 8
+
+use anyhow::{anyhow, Result};
+use perpetual_backend::infrastructure::percentile;
+use perpetual_backend::services::{
+    synthetic_monitor, synthetic_positions, EventKind, PriceWalk, ReplayPriceSource,
+    SYNTHETIC_MARKETS,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+
+struct LoadConfig {
+    positions: usize,
+    ticks: usize,
+    readers: usize,
+    tick_interval: Duration,
+    seed: u64,
+}
+
+impl LoadConfig {
+    fn from_args() -> Result<Self> {
+        let mut config = Self {
+            positions: 10_000,
+            ticks: 100,
+            readers: 4,
+            tick_interval: Duration::ZERO,
+            seed: 7,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("Missing value for {}", flag))?;
+
+            match flag.as_str() {
+                "--positions" => config.positions = value.parse()?,
+                "--ticks" => config.ticks = value.parse()?,
+                "--readers" => config.readers = value.parse()?,
+                "--tick-ms" => config.tick_interval = Duration::from_millis(value.parse()?),
+                "--seed" => config.seed = value.parse()?,
+                _ => return Err(anyhow!("Unknown flag {}", flag)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[tokio::main(flavor = "multi_thread", worker_threads = 8)]
+async fn main() -> Result<()> {
+    let config = LoadConfig::from_args()?;
+
+    let price_source = Arc::new(ReplayPriceSource::synthetic());
+    let monitor = Arc::new(synthetic_monitor(Arc::clone(&price_source))?);
+
+    let seeding = Instant::now();
+    monitor
+        .seed_positions(synthetic_positions(config.positions, config.seed))
+        .await;
+    println!("Seeded {} positions in {:?}", config.positions, seeding.elapsed());
+
+    let stop = Arc::new(AtomicBool::new(false));
+
+    // One broadcast subscriber, standing in for a WebSocket client
+    let mut updates = monitor.events().receiver(EventKind::Position);
+    let subscriber = tokio::spawn(async move {
+        let (mut received, mut lagged) = (0u64, 0u64);
+        loop {
+            match updates.recv().await {
+                Ok(_) => received += 1,
+                Err(RecvError::Lagged(skipped)) => lagged += skipped,
+                Err(RecvError::Closed) => break,
+            }
+        }
+        (received, lagged)
+    });
+
+    // API-style readers contending with the PnL pass for the position locks
+    let readers: Vec<_> = (0..config.readers)
+        .map(|reader| {
+            let monitor = Arc::clone(&monitor);
+            let stop = Arc::clone(&stop);
+            tokio::spawn(async move {
+                let symbol = SYNTHETIC_MARKETS[reader % SYNTHETIC_MARKETS.len()].0;
+                let mut latencies = Vec::new();
+                while !stop.load(Ordering::Relaxed) {
+                    let started = Instant::now();
+                    monitor.get_positions_by_asset(symbol).await;
+                    latencies.push(started.elapsed());
+                    tokio::task::yield_now().await;
+                }
+                latencies
+            })
+        })
+        .collect();
+
+    let mut walk = PriceWalk::new(config.seed, 10);
+    let mut tick_latencies = Vec::with_capacity(config.ticks);
+    let run = Instant::now();
+
+    for _ in 0..config.ticks {
+        price_source.apply_tick(&walk.next_tick()).await;
+
+        let started = Instant::now();
+        monitor.update_all_pnl().await?;
+        tick_latencies.push(started.elapsed());
+
+        if !config.tick_interval.is_zero() {
+            tokio::time::sleep(config.tick_interval).await;
+        }
+    }
+
+    let elapsed = run.elapsed();
+    stop.store(true, Ordering::Relaxed);
+
+    let mut read_latencies = Vec::new();
+    for reader in readers {
+        read_latencies.extend(reader.await?);
+    }
+
+    // Dropping the last sender closes the channel so the subscriber reports
+    drop(monitor);
+    let (received, lagged) = subscriber.await?;
+
+    tick_latencies.sort();
+    read_latencies.sort();
+
+    println!();
+    println!("PnL pass ({} ticks x {} positions)", config.ticks, config.positions);
+    report_latencies(&tick_latencies);
+    println!(
+        "  positions/sec: {:.0}",
+        (config.ticks * config.positions) as f64 / elapsed.as_secs_f64()
+    );
+
+    println!();
+    println!("Broadcast");
+    println!("  received: {} ({:.0}/sec)", received, received as f64 / elapsed.as_secs_f64());
+    println!("  lagged:   {}", lagged);
+
+    println!();
+    println!("Readers ({} tasks, {} reads)", config.readers, read_latencies.len());
+    report_latencies(&read_latencies);
+
+    Ok(())
+}
+
+fn report_latencies(sorted: &[Duration]) {
+    println!(
+        "  p50 {:?}  p95 {:?}  p99 {:?}  max {:?}",
+        percentile(sorted, 50.0),
+        percentile(sorted, 95.0),
+        percentile(sorted, 99.0),
+        sorted.last().copied().unwrap_or_default()
+    );
+}
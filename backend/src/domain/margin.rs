@@ -1,5 +1,6 @@
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarginRequirement {
@@ -22,3 +23,131 @@ pub struct LeverageTier {
     pub maintenance_margin_rate: u64,
     pub max_position_size: u64,
 }
+
+impl LeverageTier {
+    /// `max_position_size` as notional, none when uncapped
+    pub fn max_notional(&self) -> Option<Decimal> {
+        (self.max_position_size != u64::MAX).then(|| Decimal::new(self.max_position_size as i64, 6))
+    }
+}
+
+/// An open position whose losses exceed its margin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnderwaterPosition {
+    pub position_account: Pubkey,
+    pub owner: Pubkey,
+    pub symbol: String,
+    pub mark_price: Decimal,
+    /// Signed, below zero by the share of notional the losses aren't covered
+    pub margin_ratio: Decimal,
+    /// Losses beyond the margin, what the backstop covers on a close now
+    pub margin_deficit: Decimal,
+}
+
+/// Most severe first: lowest margin ratio, then the larger deficit
+pub fn rank_by_severity(positions: &mut [UnderwaterPosition]) {
+    positions.sort_by(|a, b| {
+        a.margin_ratio
+            .cmp(&b.margin_ratio)
+            .then_with(|| b.margin_deficit.cmp(&a.margin_deficit))
+    });
+}
+
+/// The program's leverage tiers (constants.rs). `max_position_size` is
+/// notional in collateral base units, 6 decimals
+pub const LEVERAGE_TIERS: [LeverageTier; 5] = [
+    LeverageTier {
+        max_leverage: 20,
+        initial_margin_rate: 500,
+        maintenance_margin_rate: 250,
+        max_position_size: u64::MAX,
+    },
+    LeverageTier {
+        max_leverage: 50,
+        initial_margin_rate: 200,
+        maintenance_margin_rate: 100,
+        max_position_size: 100_000_000_000,
+    },
+    LeverageTier {
+        max_leverage: 100,
+        initial_margin_rate: 100,
+        maintenance_margin_rate: 50,
+        max_position_size: 50_000_000_000,
+    },
+    LeverageTier {
+        max_leverage: 500,
+        initial_margin_rate: 50,
+        maintenance_margin_rate: 25,
+        max_position_size: 20_000_000_000,
+    },
+    LeverageTier {
+        max_leverage: 1000,
+        initial_margin_rate: 20,
+        maintenance_margin_rate: 10,
+        max_position_size: 5_000_000_000,
+    },
+];
+
+/// Largest notional the program accepts at `leverage`, none when uncapped.
+/// The program takes the first tier that fits both leverage and notional, so
+/// this is the widest cap among the tiers allowing `leverage`
+pub fn max_notional_for_leverage(leverage: u16) -> Option<Decimal> {
+    LEVERAGE_TIERS
+        .iter()
+        .filter(|tier| leverage <= tier.max_leverage)
+        .map(|tier| tier.max_position_size)
+        .max()
+        .map_or(Some(Decimal::ZERO), |max| {
+            (max != u64::MAX).then(|| Decimal::new(max as i64, 6))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn underwater(margin_ratio: Decimal, margin_deficit: Decimal) -> UnderwaterPosition {
+        UnderwaterPosition {
+            position_account: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            mark_price: dec!(100),
+            margin_ratio,
+            margin_deficit,
+        }
+    }
+
+    #[test]
+    fn test_rank_by_severity() {
+        let mut positions = vec![
+            underwater(dec!(-0.01), dec!(50)),
+            underwater(dec!(-0.2), dec!(10)),
+            underwater(dec!(-0.01), dec!(500)),
+        ];
+
+        rank_by_severity(&mut positions);
+
+        let ranked: Vec<_> = positions.iter().map(|p| (p.margin_ratio, p.margin_deficit)).collect();
+        assert_eq!(
+            ranked,
+            vec![(dec!(-0.2), dec!(10)), (dec!(-0.01), dec!(500)), (dec!(-0.01), dec!(50))]
+        );
+    }
+
+    #[test]
+    fn test_max_notional_for_leverage() {
+        assert_eq!(max_notional_for_leverage(20), None);
+        assert_eq!(max_notional_for_leverage(21), Some(dec!(100000)));
+        assert_eq!(max_notional_for_leverage(100), Some(dec!(50000)));
+        assert_eq!(max_notional_for_leverage(1000), Some(dec!(5000)));
+        assert_eq!(max_notional_for_leverage(1001), Some(Decimal::ZERO));
+    }
+
+    #[test]
+    fn test_leverage_tier_max_notional() {
+        assert_eq!(LEVERAGE_TIERS[0].max_notional(), None);
+        assert_eq!(LEVERAGE_TIERS[1].max_notional(), Some(dec!(100000)));
+        assert_eq!(LEVERAGE_TIERS[4].max_notional(), Some(dec!(5000)));
+    }
+}
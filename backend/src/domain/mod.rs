@@ -1,6 +1,7 @@
 pub mod position;
 pub mod margin;
 pub mod pnl;
+pub mod symbols;
 
 pub use position::*;
 pub use margin::*;
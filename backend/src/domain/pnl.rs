@@ -1,11 +1,14 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::domain::Side;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PnLSnapshot {
     pub id: uuid::Uuid,
-    pub position_id: uuid::Uuid,
+    pub position_account: Pubkey,
     pub unrealized_pnl: Decimal,
     pub realized_pnl: Decimal,
     pub funding_accrued: Decimal,
@@ -13,10 +16,23 @@ pub struct PnLSnapshot {
     pub timestamp: DateTime<Utc>,
 }
 
+/// A realized PnL event, recorded when a position is closed or liquidated
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RealizedPnL {
-    pub position_id: uuid::Uuid,
-    pub amount: Decimal,
+    pub id: uuid::Uuid,
+    pub position_account: Pubkey,
+    pub owner: Pubkey,
+    pub symbol: String,
+    pub side: Side,
+    pub size_closed: Decimal,
+    pub entry_price: Decimal,
     pub close_price: Decimal,
+    pub fee: Decimal,
+    /// Funding component included in `amount`
+    pub funding: Decimal,
+    /// Net realized PnL: price PnL + funding - fee
+    pub amount: Decimal,
+    pub liquidation: bool,
+    pub signature: String,
     pub closed_at: DateTime<Utc>,
 }
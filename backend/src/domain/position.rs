@@ -19,19 +19,58 @@ pub struct Position {
     pub realized_pnl: Decimal,
     pub funding_accrued: Decimal,
     pub liquidation_price: Decimal,
+    /// Liquidation price net of accrued funding and the close fee, set by
+    /// the PnL pass while the position is open
+    #[serde(default)]
+    pub effective_liquidation_price: Option<Decimal>,
     pub status: PositionStatus,
     pub opened_at: DateTime<Utc>,
     pub last_update: DateTime<Utc>,
     pub closed_at: Option<DateTime<Utc>>,
+    /// When the current margin call was issued, set while status is MarginCall
+    #[serde(default)]
+    pub margin_call_at: Option<DateTime<Utc>>,
 }
 
 impl Position {
     pub fn is_open(&self) -> bool {
-        matches!(self.status, PositionStatus::Open | PositionStatus::Opening)
+        matches!(
+            self.status,
+            PositionStatus::Open | PositionStatus::Opening | PositionStatus::MarginCall
+        )
     }
 
     pub fn is_closed(&self) -> bool {
-        matches!(self.status, PositionStatus::Closed)
+        matches!(self.status, PositionStatus::Closed | PositionStatus::Liquidated)
+    }
+
+    /// Fixture for unit tests: an open 10 SOL-USD long at 100 with 100 margin
+    /// at 10x under fresh keys. Tests override fields with struct update syntax
+    #[cfg(test)]
+    pub fn for_test() -> Self {
+        let now = Utc::now();
+        Self {
+            position_index: 0,
+            owner: Pubkey::new_unique(),
+            position_account: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size: Decimal::TEN,
+            entry_price: Decimal::ONE_HUNDRED,
+            mark_price: Decimal::ONE_HUNDRED,
+            margin: Decimal::ONE_HUNDRED,
+            leverage: 10,
+            unrealized_pnl: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
+            funding_accrued: Decimal::ZERO,
+            liquidation_price: Decimal::ZERO,
+            effective_liquidation_price: None,
+            status: PositionStatus::Open,
+            opened_at: now,
+            last_update: now,
+            closed_at: None,
+            margin_call_at: None,
+        }
     }
 }
 
@@ -41,10 +80,24 @@ pub enum Side {
     Short,
 }
 
+/// A user's preferred margining. Positions are all isolated on chain today,
+/// the preference is stored for clients
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum MarginMode {
+    Isolated,
+    Cross,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum Risk {
     Liquidated,
     Liquidating,
+    /// Below maintenance plus the margin call buffer, grace period running
+    MarginCall,
+    /// Topped up past the margin call buffer
+    MarginCallCleared,
+    /// Margin ratio fell below a tier multiple of the maintenance ratio
+    MarginWarning,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -54,4 +107,7 @@ pub enum PositionStatus {
     Modifying,
     Closing,
     Closed,
+    Liquidated,
+    /// Open, but only top-ups and size reductions until the margin call clears
+    MarginCall,
 }
\ No newline at end of file
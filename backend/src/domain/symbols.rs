@@ -0,0 +1,104 @@
+use anyhow::{anyhow, Result};
+
+/// Quote every canonical symbol is expressed in
+pub const QUOTE_CURRENCY: &str = "USD";
+
+/// Same limit the program enforces on the position symbol
+pub const MAX_SYMBOL_LENGTH: usize = 32;
+
+const MAX_BASE_LENGTH: usize = 10;
+
+/// Quotes treated as USD, longest first so "BTCUSDT" is not read as "BTCU" + "SDT"
+const USD_QUOTES: &[&str] = &["USDT", "USDC", "USD", "DAI"];
+
+/// Alternate tickers for the same base asset
+const BASE_ALIASES: &[(&str, &str)] = &[
+    ("XBT", "BTC"),
+    ("WBTC", "BTC"),
+    ("WETH", "ETH"),
+    ("WSOL", "SOL"),
+];
+
+const SEPARATORS: &[char] = &['-', '/', '_', ':'];
+
+/// Resolve any accepted spelling ("btcusd", "BTC/USDT", "xbt-usd") to the
+/// canonical "BASE-USD" form used by the oracle, the monitor and on-chain
+pub fn canonicalize(symbol: &str) -> Result<String> {
+    let upper = symbol.trim().to_ascii_uppercase();
+    let (base, quote) = split(&upper)?;
+
+    if !USD_QUOTES.contains(&quote) {
+        return Err(anyhow!("Unsupported quote currency in {}: {}", symbol, quote));
+    }
+
+    if base.is_empty()
+        || base.len() > MAX_BASE_LENGTH
+        || !base.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return Err(anyhow!("Invalid base asset in symbol: {}", symbol));
+    }
+
+    let base = BASE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == base)
+        .map(|(_, canonical)| *canonical)
+        .unwrap_or(base);
+
+    Ok(format!("{}-{}", base, QUOTE_CURRENCY))
+}
+
+/// Canonical form when the symbol is recognised, otherwise the trimmed
+/// uppercase input, for lookups and data we cannot reject (on-chain accounts)
+pub fn normalize(symbol: &str) -> String {
+    canonicalize(symbol).unwrap_or_else(|_| symbol.trim().to_ascii_uppercase())
+}
+
+pub fn is_valid(symbol: &str) -> bool {
+    canonicalize(symbol).is_ok()
+}
+
+/// Quote currency as written in the symbol, before mapping to USD
+pub fn quote_currency(symbol: &str) -> Option<String> {
+    let upper = symbol.trim().to_ascii_uppercase();
+    split(&upper).ok().map(|(_, quote)| quote.to_string())
+}
+
+fn split(symbol: &str) -> Result<(&str, &str)> {
+    if symbol.len() > MAX_SYMBOL_LENGTH {
+        return Err(anyhow!("Symbol longer than {} characters", MAX_SYMBOL_LENGTH));
+    }
+
+    let parts: Vec<&str> = symbol.split(SEPARATORS).collect();
+    match parts.as_slice() {
+        [base, quote] => Ok((base, quote)),
+        [joined] => USD_QUOTES
+            .iter()
+            .find_map(|quote| joined.strip_suffix(quote).map(|base| (base, *quote)))
+            .ok_or_else(|| anyhow!("No quote currency in symbol: {}", symbol)),
+        _ => Err(anyhow!("Malformed symbol: {}", symbol)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize_spellings() {
+        for input in ["BTC-USD", "btcusd", "BTC/USDT", "btc_usdc", " xbt-usd ", "WBTC:DAI"] {
+            assert_eq!(canonicalize(input).unwrap(), "BTC-USD", "{}", input);
+        }
+        assert_eq!(canonicalize("SOLUSDT").unwrap(), "SOL-USD");
+        assert_eq!(quote_currency("eth/usdc").as_deref(), Some("USDC"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_symbols() {
+        assert!(!is_valid("BTC-EUR"));
+        assert!(!is_valid("BTC"));
+        assert!(!is_valid("-USD"));
+        assert!(!is_valid("BTC-ETH-USD"));
+        assert!(!is_valid("B$C-USD"));
+        assert_eq!(normalize("odd-pair"), "ODD-PAIR");
+    }
+}
@@ -0,0 +1,123 @@
+/// Backend Error Taxonomy
+/// Services keep returning anyhow::Result with one of these as the root cause,
+/// so `.context()` still reads well in logs while the API layer can recover
+/// the kind of failure with `Error::from_anyhow`
+use serde::Serialize;
+use solana_client::client_error::ClientError;
+use std::fmt;
+
+use crate::infrastructure::ProgramError;
+
+/// One rejected request field
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// Price feed missing, stale or unreachable
+    Oracle(String),
+    /// RPC node failure, or a transaction that failed without a decodable program error
+    Rpc {
+        message: String,
+        signature: Option<String>,
+        logs: Vec<String>,
+    },
+    /// Transaction rejected by the position program
+    Program {
+        error: ProgramError,
+        signature: Option<String>,
+        logs: Vec<String>,
+    },
+    NotFound(String),
+    Validation(Vec<FieldError>),
+    Internal(String),
+}
+
+impl Error {
+    pub fn rpc(message: impl Into<String>) -> Self {
+        Error::Rpc {
+            message: message.into(),
+            signature: None,
+            logs: Vec::new(),
+        }
+    }
+
+    /// Classify an anyhow chain by its root cause, keeping the context
+    /// messages for anything that is not already typed
+    pub fn from_anyhow(err: anyhow::Error) -> Self {
+        if let Some(error) = err.downcast_ref::<Error>() {
+            return error.clone();
+        }
+
+        if let Some(error) = err.downcast_ref::<ProgramError>() {
+            return Error::Program {
+                error: error.clone(),
+                signature: None,
+                logs: Vec::new(),
+            };
+        }
+
+        if err.downcast_ref::<ClientError>().is_some() {
+            return Error::rpc(format!("{:#}", err));
+        }
+
+        Error::Internal(format!("{:#}", err))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Oracle(message) => write!(f, "Oracle error: {}", message),
+            Error::Rpc { message, signature, .. } => match signature {
+                Some(signature) => write!(f, "RPC error: {} (signature {})", message, signature),
+                None => write!(f, "RPC error: {}", message),
+            },
+            Error::Program { error, signature, .. } => match signature {
+                Some(signature) => write!(f, "Program error: {} (signature {})", error, signature),
+                None => write!(f, "Program error: {}", error),
+            },
+            Error::NotFound(message) => write!(f, "Not found: {}", message),
+            Error::Validation(errors) => {
+                let fields: Vec<_> = errors.iter().map(|e| e.field).collect();
+                write!(f, "Validation failed: {}", fields.join(", "))
+            }
+            Error::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_from_anyhow_sees_through_context() {
+        let err = Err::<(), _>(Error::Oracle("SOL-USD feed stale".to_string()))
+            .context("Failed to refresh mark price")
+            .unwrap_err();
+        assert!(matches!(Error::from_anyhow(err), Error::Oracle(_)));
+
+        let program_error = ProgramError::from_number(6002).unwrap();
+        let err = anyhow::Error::new(program_error).context("Failed to open position");
+        match Error::from_anyhow(err) {
+            Error::Program { error, .. } => assert_eq!(error.code, "InsufficientCollateral"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_anyhow_keeps_untyped_chain() {
+        let err = anyhow::anyhow!("disk full").context("Failed to persist snapshot");
+        match Error::from_anyhow(err) {
+            Error::Internal(message) => assert_eq!(message, "Failed to persist snapshot: disk full"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,288 @@
+/// Account Stream
+/// programSubscribe over the RPC WebSocket, for deployments without Geyser
+/// Sits between gPA polling and a Geyser feed: pushes account updates to the
+/// monitor, reconnects with backoff, and announces every (re)subscribe so the
+/// consumer can gap-fill with one gPA. `ProgramLogStream` does the same with
+/// logsSubscribe, for transactions that mention the program
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature,
+};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+use tracing::{info, warn};
+
+#[derive(Debug)]
+pub enum AccountStreamEvent {
+    /// Subscription (re)established, updates since the last one may be missing
+    Subscribed,
+    Update {
+        pubkey: Pubkey,
+        account: Account,
+        slot: u64,
+    },
+    /// Connection lost, a reconnect follows after backoff
+    Disconnected,
+}
+
+#[derive(Debug)]
+pub enum LogStreamEvent {
+    /// Subscription (re)established, transactions since the last one may be missing
+    Subscribed,
+    /// A successful transaction that mentions the program
+    Transaction {
+        signature: Signature,
+        logs: Vec<String>,
+        slot: u64,
+    },
+    /// Connection lost, a reconnect follows after backoff
+    Disconnected,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccountStreamConfig {
+    pub ws_url: String,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl AccountStreamConfig {
+    /// Stream config for the WebSocket endpoint paired with `rpc_url`
+    pub fn for_rpc_url(rpc_url: &str) -> Self {
+        Self {
+            ws_url: ws_url_from_rpc(rpc_url),
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+/// http(s)://host -> ws(s)://host, the usual pairing for RPC providers
+pub fn ws_url_from_rpc(rpc_url: &str) -> String {
+    if let Some(rest) = rpc_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        rpc_url.to_string()
+    }
+}
+
+/// programSubscribe for one program, filtered like the gPA scan
+pub struct ProgramAccountStream {
+    config: AccountStreamConfig,
+    program_id: Pubkey,
+    filters: Vec<RpcFilterType>,
+}
+
+impl ProgramAccountStream {
+    pub fn new(config: AccountStreamConfig, program_id: Pubkey, filters: Vec<RpcFilterType>) -> Self {
+        Self {
+            config,
+            program_id,
+            filters,
+        }
+    }
+
+    /// Stream in a background task that runs until the receiver is dropped
+    pub fn spawn(self) -> mpsc::Receiver<AccountStreamEvent> {
+        let (tx, rx) = mpsc::channel(1024);
+        tokio::spawn(self.run(tx));
+        rx
+    }
+
+    async fn run(self, tx: mpsc::Sender<AccountStreamEvent>) {
+        let initial_backoff = Duration::from_millis(self.config.initial_backoff_ms);
+        let max_backoff = Duration::from_millis(self.config.max_backoff_ms);
+        let mut backoff = initial_backoff;
+
+        while !tx.is_closed() {
+            match self.stream_once(&tx).await {
+                // Was subscribed at some point, start the backoff over
+                Ok(true) => backoff = initial_backoff,
+                Ok(false) => {}
+                Err(e) => warn!("Program subscription to {} failed: {:#}", self.config.ws_url, e),
+            }
+
+            if tx.send(AccountStreamEvent::Disconnected).await.is_err() {
+                break;
+            }
+
+            info!("Resubscribing to program accounts in {:?}", backoff);
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(max_backoff);
+        }
+
+        info!("Program account stream stopped");
+    }
+
+    /// One connection, until it drops. Returns whether it got subscribed
+    async fn stream_once(&self, tx: &mpsc::Sender<AccountStreamEvent>) -> Result<bool> {
+        let client = PubsubClient::new(&self.config.ws_url)
+            .await
+            .context("Failed to connect to RPC WebSocket")?;
+
+        let config = RpcProgramAccountsConfig {
+            filters: Some(self.filters.clone()),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..Default::default()
+            },
+            with_context: Some(true),
+        };
+
+        let (mut updates, unsubscribe) = client
+            .program_subscribe(&self.program_id, Some(config))
+            .await
+            .context("programSubscribe failed")?;
+
+        info!("Subscribed to program accounts of {}", self.program_id);
+
+        if tx.send(AccountStreamEvent::Subscribed).await.is_err() {
+            return Ok(true);
+        }
+
+        while let Some(response) = updates.next().await {
+            let Ok(pubkey) = response.value.pubkey.parse::<Pubkey>() else {
+                warn!("Bad pubkey in program notification: {}", response.value.pubkey);
+                continue;
+            };
+
+            let Some(account) = response.value.account.decode::<Account>() else {
+                warn!("Failed to decode program notification for {}", pubkey);
+                continue;
+            };
+
+            let event = AccountStreamEvent::Update {
+                pubkey,
+                account,
+                slot: response.context.slot,
+            };
+
+            if tx.send(event).await.is_err() {
+                break;
+            }
+        }
+
+        drop(updates);
+        unsubscribe().await;
+
+        Ok(true)
+    }
+}
+
+/// logsSubscribe for transactions mentioning one program, failed ones skipped
+pub struct ProgramLogStream {
+    config: AccountStreamConfig,
+    program_id: Pubkey,
+}
+
+impl ProgramLogStream {
+    pub fn new(config: AccountStreamConfig, program_id: Pubkey) -> Self {
+        Self { config, program_id }
+    }
+
+    /// Stream in a background task that runs until the receiver is dropped
+    pub fn spawn(self) -> mpsc::Receiver<LogStreamEvent> {
+        let (tx, rx) = mpsc::channel(1024);
+        tokio::spawn(self.run(tx));
+        rx
+    }
+
+    async fn run(self, tx: mpsc::Sender<LogStreamEvent>) {
+        let initial_backoff = Duration::from_millis(self.config.initial_backoff_ms);
+        let max_backoff = Duration::from_millis(self.config.max_backoff_ms);
+        let mut backoff = initial_backoff;
+
+        while !tx.is_closed() {
+            match self.stream_once(&tx).await {
+                Ok(true) => backoff = initial_backoff,
+                Ok(false) => {}
+                Err(e) => warn!("Log subscription to {} failed: {:#}", self.config.ws_url, e),
+            }
+
+            if tx.send(LogStreamEvent::Disconnected).await.is_err() {
+                break;
+            }
+
+            info!("Resubscribing to program logs in {:?}", backoff);
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(max_backoff);
+        }
+
+        info!("Program log stream stopped");
+    }
+
+    /// One connection, until it drops. Returns whether it got subscribed
+    async fn stream_once(&self, tx: &mpsc::Sender<LogStreamEvent>) -> Result<bool> {
+        let client = PubsubClient::new(&self.config.ws_url)
+            .await
+            .context("Failed to connect to RPC WebSocket")?;
+
+        let (mut notifications, unsubscribe) = client
+            .logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![self.program_id.to_string()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await
+            .context("logsSubscribe failed")?;
+
+        info!("Subscribed to transactions mentioning {}", self.program_id);
+
+        if tx.send(LogStreamEvent::Subscribed).await.is_err() {
+            return Ok(true);
+        }
+
+        while let Some(response) = notifications.next().await {
+            // Failed transactions leave accounts untouched
+            if response.value.err.is_some() {
+                continue;
+            }
+
+            let Ok(signature) = response.value.signature.parse::<Signature>() else {
+                warn!("Bad signature in logs notification: {}", response.value.signature);
+                continue;
+            };
+
+            let event = LogStreamEvent::Transaction {
+                signature,
+                logs: response.value.logs,
+                slot: response.context.slot,
+            };
+
+            if tx.send(event).await.is_err() {
+                break;
+            }
+        }
+
+        drop(notifications);
+        unsubscribe().await;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ws_url_from_rpc() {
+        assert_eq!(
+            ws_url_from_rpc("https://api.devnet.solana.com"),
+            "wss://api.devnet.solana.com"
+        );
+        assert_eq!(ws_url_from_rpc("http://127.0.0.1:8899"), "ws://127.0.0.1:8899");
+        assert_eq!(ws_url_from_rpc("wss://rpc.example.com"), "wss://rpc.example.com");
+    }
+}
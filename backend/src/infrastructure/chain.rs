@@ -0,0 +1,198 @@
+/// Chain
+/// What the monitoring, alerting and analytics stack needs from the chain the
+/// program is deployed on: send a transaction, read every position, and
+/// derive the address of a program account. SolanaClient implements it
+/// today; an EVM deployment implements the same trait against its contracts
+/// instead of rewriting the services that read through it
+/// Account ids are still `Pubkey`s across the domain, an EVM implementation
+/// maps its addresses into them
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future::BoxFuture;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
+use solana_sdk::{commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::domain::Position;
+use crate::infrastructure::SolanaClient;
+use crate::services::on_chain_types::{deserialize_position_account, OnChainPosition};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainKind {
+    Solana,
+    Evm,
+}
+
+impl FromStr for ChainKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "solana" => Ok(ChainKind::Solana),
+            "evm" => Ok(ChainKind::Evm),
+            other => Err(anyhow!("Unknown chain {}, expected solana or evm", other)),
+        }
+    }
+}
+
+impl fmt::Display for ChainKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainKind::Solana => write!(f, "solana"),
+            ChainKind::Evm => write!(f, "evm"),
+        }
+    }
+}
+
+/// A program account, by what identifies it rather than how its address is derived
+#[derive(Debug, Clone, Copy)]
+pub enum AccountRef<'a> {
+    Config,
+    User { owner: &'a Pubkey },
+    UserSettings { owner: &'a Pubkey },
+    Position { owner: &'a Pubkey, position_index: u32 },
+    Market { symbol: &'a str },
+    OpenThrottle { owner: &'a Pubkey, symbol: &'a str },
+    BackstopVault,
+    LpAccount { owner: &'a Pubkey },
+}
+
+/// A transaction in the chain's own encoding
+#[derive(Debug, Clone)]
+pub enum ChainTx {
+    Solana(Vec<Instruction>),
+}
+
+pub trait Chain: Send + Sync {
+    fn kind(&self) -> ChainKind;
+
+    /// Sign and send `tx`, returning its id once accepted
+    fn submit_tx(&self, tx: ChainTx) -> BoxFuture<'_, Result<String>>;
+
+    /// Every position the program holds, open or not. Accounts that don't
+    /// decode are skipped
+    fn fetch_positions(&self) -> BoxFuture<'_, Result<Vec<Position>>>;
+
+    /// Address of a program account
+    fn derive_account(&self, account: AccountRef<'_>) -> Pubkey;
+}
+
+impl Chain for SolanaClient {
+    fn kind(&self) -> ChainKind {
+        ChainKind::Solana
+    }
+
+    fn submit_tx(&self, tx: ChainTx) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move {
+            let ChainTx::Solana(instructions) = tx;
+            let signature = self.send_priority_transaction(&instructions).await?;
+            Ok(signature.to_string())
+        })
+    }
+
+    fn fetch_positions(&self) -> BoxFuture<'_, Result<Vec<Position>>> {
+        Box::pin(async move {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new(
+                    0,
+                    MemcmpEncodedBytes::Bytes(OnChainPosition::DISCRIMINATOR.to_vec()),
+                ))]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    ..Default::default()
+                },
+                with_context: Some(false),
+            };
+
+            let accounts = self
+                .rpc_pool()
+                .read(|rpc_client| rpc_client.get_program_accounts_with_config(&self.program_id, config.clone()))
+                .context("Failed to fetch program accounts")?;
+
+            Ok(accounts
+                .into_iter()
+                .filter_map(|(pubkey, account)| {
+                    match deserialize_position_account(pubkey, &account).and_then(
+                        |(position_index, on_chain)| on_chain.to_domain_position(pubkey, position_index),
+                    ) {
+                        Ok(position) => Some(position),
+                        Err(e) => {
+                            warn!("Skipping undecodable position {}: {}", pubkey, e);
+                            None
+                        }
+                    }
+                })
+                .collect())
+        })
+    }
+
+    fn derive_account(&self, account: AccountRef<'_>) -> Pubkey {
+        match account {
+            AccountRef::Config => self.derive_config_pda().0,
+            AccountRef::User { owner } => self.derive_user_account_pda(owner).0,
+            AccountRef::UserSettings { owner } => self.derive_user_settings_pda(owner).0,
+            AccountRef::Position {
+                owner,
+                position_index,
+            } => self.derive_position_pda(owner, position_index).0,
+            AccountRef::Market { symbol } => self.derive_market_pda(symbol).0,
+            AccountRef::OpenThrottle { owner, symbol } => self.derive_open_throttle_pda(owner, symbol).0,
+            AccountRef::BackstopVault => self.derive_backstop_vault_pda().0,
+            AccountRef::LpAccount { owner } => self.derive_lp_account_pda(owner).0,
+        }
+    }
+}
+
+/// The chain `kind` selects for this environment, Solana through `solana_client`
+pub fn connect_chain(kind: ChainKind, solana_client: &Arc<SolanaClient>) -> Result<Arc<dyn Chain>> {
+    match kind {
+        ChainKind::Solana => Ok(Arc::clone(solana_client) as Arc<dyn Chain>),
+        ChainKind::Evm => bail!("No EVM deployment is supported yet, set CHAIN=solana"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+
+    #[test]
+    fn test_chain_kind_parse() {
+        assert_eq!("solana".parse::<ChainKind>().unwrap(), ChainKind::Solana);
+        assert_eq!("EVM".parse::<ChainKind>().unwrap(), ChainKind::Evm);
+        assert!("cosmos".parse::<ChainKind>().is_err());
+        assert_eq!(ChainKind::Solana.to_string(), "solana");
+    }
+
+    #[test]
+    fn test_connect_chain() {
+        let client = Arc::new(SolanaClient::new(
+            Pubkey::new_unique(),
+            Keypair::new(),
+            "http://127.0.0.1:8899".to_string(),
+        ));
+
+        let chain = connect_chain(ChainKind::Solana, &client).unwrap();
+        assert_eq!(chain.kind(), ChainKind::Solana);
+
+        let owner = Pubkey::new_unique();
+        assert_eq!(
+            chain.derive_account(AccountRef::Position {
+                owner: &owner,
+                position_index: 3
+            }),
+            client.derive_position_pda(&owner, 3).0
+        );
+        assert_eq!(
+            chain.derive_account(AccountRef::Market { symbol: "SOL-USD" }),
+            client.derive_market_pda("SOL-USD").0
+        );
+
+        assert!(connect_chain(ChainKind::Evm, &client).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+/// Circuit Breaker
+/// Per-key breakers for calls that run on a fixed tick, such as a symbol's
+/// Hermes quote or the chain scan. After enough failures in a row a breaker
+/// opens and the call is skipped until a backoff passes, then one half-open
+/// probe decides whether it closes or reopens with the backoff doubled
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreakerConfig {
+    /// Failures in a row that open a breaker
+    pub failure_threshold: u32,
+    /// First open interval, doubled by each failed probe
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            base_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    /// A probe call is in flight
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerStats {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    /// Times the breaker opened since startup
+    pub trips: u64,
+    /// Current open interval, zero while closed
+    pub backoff: Duration,
+    /// Time left until the next probe while open
+    pub retry_in: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: BreakerState,
+    consecutive_failures: u32,
+    trips: u64,
+    backoff: Duration,
+    open_until: Option<Instant>,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            trips: 0,
+            backoff: Duration::ZERO,
+            open_until: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CircuitBreakers {
+    config: CircuitBreakerConfig,
+    breakers: Mutex<BTreeMap<String, Breaker>>,
+}
+
+impl CircuitBreakers {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            breakers: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Whether the call for `key` should go ahead. An open breaker whose
+    /// backoff has passed lets this one call through as the probe
+    pub fn allow(&self, key: &str, now: Instant) -> bool {
+        let mut breakers = self.breakers.lock().unwrap();
+        let Some(breaker) = breakers.get_mut(key) else {
+            return true;
+        };
+
+        match breaker.state {
+            BreakerState::Closed => true,
+            BreakerState::HalfOpen => false,
+            BreakerState::Open => {
+                if breaker.open_until.is_some_and(|until| now < until) {
+                    return false;
+                }
+                breaker.state = BreakerState::HalfOpen;
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self, key: &str) {
+        let mut breakers = self.breakers.lock().unwrap();
+        if let Some(breaker) = breakers.get_mut(key) {
+            breaker.state = BreakerState::Closed;
+            breaker.consecutive_failures = 0;
+            breaker.backoff = Duration::ZERO;
+            breaker.open_until = None;
+        }
+    }
+
+    /// Returns true when this failure opened the breaker, so the caller can log it once
+    pub fn record_failure(&self, key: &str, now: Instant) -> bool {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(key.to_string()).or_default();
+        breaker.consecutive_failures += 1;
+
+        let backoff = match breaker.state {
+            BreakerState::HalfOpen => (breaker.backoff * 2).min(self.config.max_backoff),
+            BreakerState::Closed if breaker.consecutive_failures >= self.config.failure_threshold => {
+                self.config.base_backoff
+            }
+            _ => return false,
+        };
+
+        breaker.state = BreakerState::Open;
+        breaker.backoff = backoff;
+        breaker.open_until = Some(now + backoff);
+        breaker.trips += 1;
+        true
+    }
+
+    /// Open or probing, the last call for `key` failed
+    pub fn is_open(&self, key: &str) -> bool {
+        self.breakers
+            .lock()
+            .unwrap()
+            .get(key)
+            .is_some_and(|breaker| breaker.state != BreakerState::Closed)
+    }
+
+    /// Every breaker that has seen a failure, by key
+    pub fn snapshot(&self, now: Instant) -> Vec<(String, BreakerStats)> {
+        self.breakers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, breaker)| {
+                let stats = BreakerStats {
+                    state: breaker.state,
+                    consecutive_failures: breaker.consecutive_failures,
+                    trips: breaker.trips,
+                    backoff: breaker.backoff,
+                    retry_in: breaker
+                        .open_until
+                        .filter(|_| breaker.state == BreakerState::Open)
+                        .map(|until| until.saturating_duration_since(now)),
+                };
+                (key.clone(), stats)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breakers() -> CircuitBreakers {
+        CircuitBreakers::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            base_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(5),
+        })
+    }
+
+    #[test]
+    fn test_opens_after_threshold() {
+        let breakers = breakers();
+        let now = Instant::now();
+
+        assert!(!breakers.record_failure("hermes:SOL-USD", now));
+        assert!(!breakers.record_failure("hermes:SOL-USD", now));
+        assert!(breakers.allow("hermes:SOL-USD", now));
+        assert!(breakers.record_failure("hermes:SOL-USD", now));
+
+        assert!(breakers.is_open("hermes:SOL-USD"));
+        assert!(!breakers.allow("hermes:SOL-USD", now + Duration::from_secs(1)));
+        // Other keys are unaffected
+        assert!(breakers.allow("hermes:BTC-USD", now));
+    }
+
+    #[test]
+    fn test_half_open_probe_backs_off_exponentially() {
+        let breakers = breakers();
+        let now = Instant::now();
+        for _ in 0..3 {
+            breakers.record_failure("rpc:scan", now);
+        }
+
+        // One probe once the backoff passes, no second caller meanwhile
+        let probe_at = now + Duration::from_secs(2);
+        assert!(breakers.allow("rpc:scan", probe_at));
+        assert!(!breakers.allow("rpc:scan", probe_at));
+
+        // A failed probe doubles the backoff, capped at the max
+        assert!(breakers.record_failure("rpc:scan", probe_at));
+        let (_, stats) = breakers.snapshot(probe_at).remove(0);
+        assert_eq!(stats.backoff, Duration::from_secs(4));
+        assert_eq!(stats.trips, 2);
+
+        let probe_at = probe_at + Duration::from_secs(4);
+        assert!(breakers.allow("rpc:scan", probe_at));
+        breakers.record_failure("rpc:scan", probe_at);
+        assert_eq!(breakers.snapshot(probe_at)[0].1.backoff, Duration::from_secs(5));
+
+        // A successful probe closes it
+        let probe_at = probe_at + Duration::from_secs(5);
+        assert!(breakers.allow("rpc:scan", probe_at));
+        breakers.record_success("rpc:scan");
+        assert!(!breakers.is_open("rpc:scan"));
+        assert_eq!(breakers.snapshot(probe_at)[0].1.state, BreakerState::Closed);
+    }
+}
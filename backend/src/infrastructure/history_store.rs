@@ -0,0 +1,616 @@
+/// History Store
+/// Persistence for what the monitor produces (PnL snapshots, realized PnL,
+/// position opens and events, price ticks) and closed positions evicted from
+/// its memory
+/// Postgres for larger deployments, SQLite for a single binary with an
+/// embedded database; `connect_history_store` picks one from the URL
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::domain::{PnLSnapshot, Position, PositionStatus, RealizedPnL, Side};
+use crate::infrastructure::{PostgresHistoryStore, SqliteHistoryStore};
+
+/// Filters for closed positions read back from the store
+#[derive(Debug, Clone, Default)]
+pub struct ClosedPositionFilter {
+    pub owner: Option<Pubkey>,
+    pub symbol: Option<String>,
+    /// Closed or Liquidated, both when none
+    pub status: Option<PositionStatus>,
+    /// Only positions before this (closed_at, position_account) key, for keyset pages
+    pub before: Option<(DateTime<Utc>, Pubkey)>,
+}
+
+/// A position open as recorded when it was sent, the first leg of a tax lot
+#[derive(Debug, Clone)]
+pub struct PositionOpenRecord {
+    pub position_account: Pubkey,
+    pub owner: Pubkey,
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub entry_price: Decimal,
+    pub margin: Decimal,
+    pub signature: String,
+    pub opened_at: DateTime<Utc>,
+}
+
+/// What happened to a position between its open and its close, besides PnL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionEventKind {
+    Modified,
+    MarginCall,
+    MarginCallCleared,
+    Alert,
+}
+
+/// One position event, a line of its timeline next to the open and realized PnL
+#[derive(Debug, Clone)]
+pub struct PositionEventRecord {
+    pub id: Uuid,
+    pub position_account: Pubkey,
+    pub kind: PositionEventKind,
+    /// Size and margin after the event, none where it doesn't touch them
+    pub size: Option<Decimal>,
+    pub margin: Option<Decimal>,
+    pub mark_price: Option<Decimal>,
+    /// What changed or fired, e.g. the alert condition
+    pub detail: Option<String>,
+    /// Transaction, none for off-chain events like alerts
+    pub signature: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// An oracle price as the monitor saw it, the input of a replay and the
+/// mark price archive
+#[derive(Debug, Clone)]
+pub struct PriceTick {
+    pub symbol: String,
+    pub price: Decimal,
+    /// Pyth confidence interval, none on ticks recorded before it was kept
+    pub conf: Option<Decimal>,
+    /// When the oracle published the price, none on ticks recorded before it was kept
+    pub publish_time: Option<DateTime<Utc>>,
+    /// When the monitor received it
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Backstop vault state at one point in time, the basis of its return history
+#[derive(Debug, Clone)]
+pub struct VaultSnapshot {
+    pub total_assets: Decimal,
+    pub total_shares: Decimal,
+    pub locked_margin: Decimal,
+    pub share_price: Decimal,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// One point of a bucketed PnL series, the last snapshot in its bucket
+#[derive(Debug, Clone)]
+pub struct PnlPoint {
+    pub bucket: DateTime<Utc>,
+    pub unrealized_pnl: Decimal,
+    pub realized_pnl: Decimal,
+    pub funding_accrued: Decimal,
+    pub mark_price: Decimal,
+}
+
+/// Which symbols' ticks a compaction covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TickScope {
+    Symbol(String),
+    /// Every symbol but these, the ones with a policy of their own
+    AllExcept(Vec<String>),
+}
+
+impl TickScope {
+    pub fn covers(&self, symbol: &str) -> bool {
+        match self {
+            TickScope::Symbol(only) => only == symbol,
+            TickScope::AllExcept(excluded) => !excluded.iter().any(|s| s == symbol),
+        }
+    }
+}
+
+/// Rows and on-disk size of one history table, bytes none where the store can't tell
+#[derive(Debug, Clone)]
+pub struct TableSize {
+    pub table: String,
+    pub rows: i64,
+    pub bytes: Option<i64>,
+}
+
+/// Tables both stores create, in the order their sizes are reported
+pub const HISTORY_TABLES: [&str; 7] = [
+    "pnl_snapshots",
+    "realized_pnl",
+    "position_opens",
+    "position_events",
+    "closed_positions",
+    "price_ticks",
+    "vault_snapshots",
+];
+
+pub trait HistoryStore: Send + Sync {
+    fn insert_pnl_snapshots<'a>(&'a self, snapshots: &'a [PnLSnapshot]) -> BoxFuture<'a, Result<()>>;
+
+    /// Last `limit` buckets of PnL for a position, oldest first
+    fn pnl_history<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+        interval: Duration,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<PnlPoint>>>;
+
+    /// Skipped when the transaction's record for the position is already stored
+    fn insert_realized_pnl<'a>(&'a self, record: &'a RealizedPnL) -> BoxFuture<'a, Result<()>>;
+
+    /// Up to `limit` realized PnL events for `owner` in [from, to) after the
+    /// (closed_at, id) key `after`, oldest first
+    fn realized_pnl_page<'a>(
+        &'a self,
+        owner: &'a Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>>;
+
+    /// Realized PnL events for `owner` in [from, to), oldest first
+    fn realized_pnl<'a>(
+        &'a self,
+        owner: &'a Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>>;
+
+    /// Record an open, a no-op if the position was already recorded
+    fn insert_position_open<'a>(
+        &'a self,
+        position: &'a Position,
+        signature: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Opens for `owner` before `before`, oldest first
+    fn position_opens<'a>(
+        &'a self,
+        owner: &'a Pubkey,
+        before: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<PositionOpenRecord>>>;
+
+    /// Opens across all owners in [from, to), oldest first
+    fn position_opens_between<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<PositionOpenRecord>>>;
+
+    /// Realized PnL events across all owners in [from, to), oldest first
+    fn realized_pnl_between<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>>;
+
+    /// The recorded open of one position
+    fn position_open<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<PositionOpenRecord>>>;
+
+    /// Realized PnL events for one position, oldest first
+    fn position_realized_pnl<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>>;
+
+    /// Skipped when an event of the kind from the same transaction is already stored
+    fn insert_position_event<'a>(&'a self, event: &'a PositionEventRecord) -> BoxFuture<'a, Result<()>>;
+
+    /// Events for one position, oldest first
+    fn position_events<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<PositionEventRecord>>>;
+
+    fn insert_price_ticks<'a>(&'a self, ticks: &'a [PriceTick]) -> BoxFuture<'a, Result<()>>;
+
+    /// Price ticks in [from, to), oldest first
+    fn price_ticks<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<PriceTick>>>;
+
+    /// One symbol's price ticks in [from, to), oldest first, at most `limit`
+    fn symbol_price_ticks<'a>(
+        &'a self,
+        symbol: &'a str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<PriceTick>>>;
+
+    /// Thin ticks in `scope` received before `before` to the last one of each
+    /// symbol in each `bucket`, returns how many were deleted
+    fn compact_price_ticks<'a>(
+        &'a self,
+        scope: &'a TickScope,
+        before: DateTime<Utc>,
+        bucket: Duration,
+    ) -> BoxFuture<'a, Result<u64>>;
+
+    /// Delete PnL snapshots recorded before `before`, returns how many
+    fn delete_pnl_snapshots_before<'a>(&'a self, before: DateTime<Utc>) -> BoxFuture<'a, Result<u64>>;
+
+    /// Size of each of `HISTORY_TABLES`
+    fn table_sizes(&self) -> BoxFuture<'_, Result<Vec<TableSize>>>;
+
+    fn insert_vault_snapshot<'a>(&'a self, snapshot: &'a VaultSnapshot) -> BoxFuture<'a, Result<()>>;
+
+    /// Vault snapshots in [from, to), oldest first
+    fn vault_snapshots<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<VaultSnapshot>>>;
+
+    /// Upsert positions evicted from the monitor, in one transaction
+    fn insert_closed_positions<'a>(&'a self, positions: &'a [Position]) -> BoxFuture<'a, Result<()>>;
+
+    /// Closed positions matching `filter`, most recently closed first, ties by
+    /// position account descending
+    fn closed_positions<'a>(
+        &'a self,
+        filter: &'a ClosedPositionFilter,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<Position>>>;
+
+    fn closed_position<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<Position>>>;
+}
+
+/// Connect to the store `database_url` names: postgres:// or sqlite:
+pub async fn connect_history_store(database_url: &str) -> Result<Arc<dyn HistoryStore>> {
+    if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        Ok(Arc::new(PostgresHistoryStore::connect(database_url).await?))
+    } else if database_url.starts_with("sqlite:") {
+        Ok(Arc::new(SqliteHistoryStore::connect(database_url).await?))
+    } else {
+        Err(anyhow!("Unsupported DATABASE_URL scheme, expected postgres:// or sqlite:"))
+    }
+}
+
+// Postgres date_bin origin, so both stores put snapshots in the same buckets
+const BUCKET_ORIGIN_SECS: i64 = 946_684_800;
+
+/// Start of the `interval` bucket `timestamp` falls in
+pub fn bucket_start(timestamp: DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+    let width = interval.num_seconds().max(1);
+    let offset = (timestamp.timestamp() - BUCKET_ORIGIN_SECS).rem_euclid(width);
+
+    DateTime::from_timestamp(timestamp.timestamp() - offset, 0).unwrap_or(timestamp)
+}
+
+/// Keep the last snapshot in each bucket, `snapshots` ordered oldest first
+pub(crate) fn last_per_bucket(
+    snapshots: impl IntoIterator<Item = (DateTime<Utc>, PnlPoint)>,
+    interval: Duration,
+) -> Vec<PnlPoint> {
+    let mut points: Vec<PnlPoint> = Vec::new();
+
+    for (recorded_at, mut point) in snapshots {
+        point.bucket = bucket_start(recorded_at, interval);
+
+        match points.last_mut() {
+            Some(last) if last.bucket == point.bucket => *last = point,
+            _ => points.push(point),
+        }
+    }
+
+    points
+}
+
+/// Keep the last tick received in each `interval` bucket, `ticks` ordered oldest first
+pub fn last_tick_per_bucket(ticks: Vec<PriceTick>, interval: Duration) -> Vec<PriceTick> {
+    let mut kept: Vec<(DateTime<Utc>, PriceTick)> = Vec::new();
+
+    for tick in ticks {
+        let bucket = bucket_start(tick.recorded_at, interval);
+
+        match kept.last_mut() {
+            Some((last_bucket, last)) if *last_bucket == bucket => *last = tick,
+            _ => kept.push((bucket, tick)),
+        }
+    }
+
+    kept.into_iter().map(|(_, tick)| tick).collect()
+}
+
+/// Indexes of the ticks a compaction to `interval` buckets deletes, all but the
+/// last of each symbol in each bucket, `ticks` as (symbol, recorded_at)
+/// ordered by symbol then oldest first
+pub(crate) fn superseded_ticks(ticks: &[(String, DateTime<Utc>)], interval: Duration) -> Vec<usize> {
+    ticks
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| {
+            pair[0].0 == pair[1].0
+                && bucket_start(pair[0].1, interval) == bucket_start(pair[1].1, interval)
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// OHLC bar of the ticks received in one bucket
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub start: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub ticks: u32,
+}
+
+/// One candle per `interval` bucket that received a tick, `ticks` ordered oldest first
+pub fn candles(ticks: &[PriceTick], interval: Duration) -> Vec<Candle> {
+    let mut candles: Vec<Candle> = Vec::new();
+
+    for tick in ticks {
+        let start = bucket_start(tick.recorded_at, interval);
+
+        match candles.last_mut() {
+            Some(candle) if candle.start == start => {
+                candle.high = candle.high.max(tick.price);
+                candle.low = candle.low.min(tick.price);
+                candle.close = tick.price;
+                candle.ticks += 1;
+            }
+            _ => candles.push(Candle {
+                start,
+                open: tick.price,
+                high: tick.price,
+                low: tick.price,
+                close: tick.price,
+                ticks: 1,
+            }),
+        }
+    }
+
+    candles
+}
+
+pub(crate) fn side_to_str(side: Side) -> &'static str {
+    match side {
+        Side::Long => "long",
+        Side::Short => "short",
+    }
+}
+
+pub(crate) fn status_to_str(status: PositionStatus) -> &'static str {
+    match status {
+        PositionStatus::Opening => "opening",
+        PositionStatus::Open => "open",
+        PositionStatus::Modifying => "modifying",
+        PositionStatus::Closing => "closing",
+        PositionStatus::Closed => "closed",
+        PositionStatus::Liquidated => "liquidated",
+        PositionStatus::MarginCall => "margin_call",
+    }
+}
+
+pub(crate) fn status_from_str(status: &str) -> Result<PositionStatus> {
+    match status {
+        "closed" => Ok(PositionStatus::Closed),
+        "liquidated" => Ok(PositionStatus::Liquidated),
+        _ => Err(anyhow!("Unexpected status in history store: {}", status)),
+    }
+}
+
+pub(crate) fn side_from_str(side: &str) -> Result<Side> {
+    match side {
+        "long" => Ok(Side::Long),
+        "short" => Ok(Side::Short),
+        _ => Err(anyhow!("Unknown side in history store: {}", side)),
+    }
+}
+
+pub(crate) fn event_kind_to_str(kind: PositionEventKind) -> &'static str {
+    match kind {
+        PositionEventKind::Modified => "modified",
+        PositionEventKind::MarginCall => "margin_call",
+        PositionEventKind::MarginCallCleared => "margin_call_cleared",
+        PositionEventKind::Alert => "alert",
+    }
+}
+
+pub(crate) fn event_kind_from_str(kind: &str) -> Result<PositionEventKind> {
+    match kind {
+        "modified" => Ok(PositionEventKind::Modified),
+        "margin_call" => Ok(PositionEventKind::MarginCall),
+        "margin_call_cleared" => Ok(PositionEventKind::MarginCallCleared),
+        "alert" => Ok(PositionEventKind::Alert),
+        _ => Err(anyhow!("Unknown position event kind in history store: {}", kind)),
+    }
+}
+
+/// Longest interval `parse_interval` accepts
+pub const MAX_INTERVAL_DAYS: i64 = 366;
+
+/// Parse an interval like "30s", "5m", "1h" or "1d", at most MAX_INTERVAL_DAYS
+pub fn parse_interval(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Interval needs a unit: {}", value))?;
+    let (amount, unit) = value.split_at(split);
+
+    let amount: i64 = amount
+        .parse()
+        .with_context(|| format!("Invalid interval: {}", value))?;
+
+    if amount <= 0 {
+        return Err(anyhow!("Interval must be positive: {}", value));
+    }
+
+    let interval = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        _ => return Err(anyhow!("Unknown interval unit: {}", unit)),
+    };
+
+    interval
+        .filter(|interval| *interval <= Duration::days(MAX_INTERVAL_DAYS))
+        .ok_or_else(|| anyhow!("Interval must be at most {}d: {}", MAX_INTERVAL_DAYS, value))
+}
+
+/// Start of the last `limit` buckets of `interval` before `now`
+pub fn buckets_since(now: DateTime<Utc>, interval: Duration, limit: i64) -> Result<DateTime<Utc>> {
+    i32::try_from(limit)
+        .ok()
+        .and_then(|limit| interval.checked_mul(limit))
+        .and_then(|span| now.checked_sub_signed(span))
+        .ok_or_else(|| anyhow!("{} buckets of {} reach too far back", limit, interval))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(value: i64) -> PnlPoint {
+        PnlPoint {
+            bucket: Utc::now(),
+            unrealized_pnl: Decimal::from(value),
+            realized_pnl: Decimal::ZERO,
+            funding_accrued: Decimal::ZERO,
+            mark_price: Decimal::ONE,
+        }
+    }
+
+    #[test]
+    fn test_last_per_bucket() {
+        let at = |secs: i64| DateTime::from_timestamp(BUCKET_ORIGIN_SECS + secs, 0).unwrap();
+
+        let points = last_per_bucket(
+            [(at(10), point(1)), (at(290), point(2)), (at(300), point(3)), (at(899), point(4))],
+            Duration::minutes(5),
+        );
+
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].bucket, at(0));
+        assert_eq!(points[0].unrealized_pnl, Decimal::from(2));
+        assert_eq!(points[1].bucket, at(300));
+        assert_eq!(points[2].bucket, at(600));
+        assert_eq!(points[2].unrealized_pnl, Decimal::from(4));
+    }
+
+    #[test]
+    fn test_last_tick_per_bucket() {
+        let tick = |millis: i64, price: i64| PriceTick {
+            symbol: "SOL-USD".to_string(),
+            price: Decimal::from(price),
+            conf: None,
+            publish_time: None,
+            recorded_at: DateTime::from_timestamp_millis(BUCKET_ORIGIN_SECS * 1000 + millis).unwrap(),
+        };
+
+        let ticks = last_tick_per_bucket(
+            vec![tick(100, 1), tick(900, 2), tick(1_000, 3), tick(3_500, 4)],
+            Duration::seconds(1),
+        );
+
+        let prices: Vec<Decimal> = ticks.iter().map(|t| t.price).collect();
+        assert_eq!(prices, vec![Decimal::from(2), Decimal::from(3), Decimal::from(4)]);
+    }
+
+    #[test]
+    fn test_superseded_ticks() {
+        let tick = |symbol: &str, secs: i64| {
+            (symbol.to_string(), DateTime::from_timestamp(BUCKET_ORIGIN_SECS + secs, 0).unwrap())
+        };
+
+        let superseded = superseded_ticks(
+            &[
+                tick("BTC-USD", 5),
+                tick("BTC-USD", 30),
+                tick("BTC-USD", 59),
+                tick("BTC-USD", 60),
+                tick("SOL-USD", 61),
+                tick("SOL-USD", 119),
+                tick("SOL-USD", 120),
+            ],
+            Duration::minutes(1),
+        );
+
+        assert_eq!(superseded, vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn test_tick_scope_covers() {
+        assert!(TickScope::Symbol("BTC-USD".to_string()).covers("BTC-USD"));
+        assert!(!TickScope::Symbol("BTC-USD".to_string()).covers("SOL-USD"));
+        assert!(TickScope::AllExcept(vec!["BTC-USD".to_string()]).covers("SOL-USD"));
+        assert!(!TickScope::AllExcept(vec!["BTC-USD".to_string()]).covers("BTC-USD"));
+    }
+
+    #[test]
+    fn test_candles() {
+        let tick = |secs: i64, price: i64| PriceTick {
+            symbol: "SOL-USD".to_string(),
+            price: Decimal::from(price),
+            conf: None,
+            publish_time: None,
+            recorded_at: DateTime::from_timestamp(BUCKET_ORIGIN_SECS + secs, 0).unwrap(),
+        };
+
+        let bars = candles(
+            &[tick(0, 10), tick(20, 14), tick(40, 9), tick(59, 12), tick(150, 13)],
+            Duration::minutes(1),
+        );
+
+        assert_eq!(bars.len(), 2);
+        assert_eq!(
+            (bars[0].open, bars[0].high, bars[0].low, bars[0].close, bars[0].ticks),
+            (Decimal::from(10), Decimal::from(14), Decimal::from(9), Decimal::from(12), 4)
+        );
+        assert_eq!(bars[1].start, DateTime::from_timestamp(BUCKET_ORIGIN_SECS + 120, 0).unwrap());
+        assert_eq!(bars[1].ticks, 1);
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("30s").unwrap(), Duration::seconds(30));
+        assert_eq!(parse_interval("5m").unwrap(), Duration::minutes(5));
+        assert_eq!(parse_interval("1h").unwrap(), Duration::hours(1));
+        assert_eq!(parse_interval("7d").unwrap(), Duration::days(7));
+    }
+
+    #[test]
+    fn test_parse_invalid_interval() {
+        assert!(parse_interval("5").is_err());
+        assert!(parse_interval("m").is_err());
+        assert!(parse_interval("0m").is_err());
+        assert!(parse_interval("5w").is_err());
+        // Past the cap, or past what a Duration holds
+        assert!(parse_interval("367d").is_err());
+        assert!(parse_interval("9223372036854775807d").is_err());
+    }
+
+    #[test]
+    fn test_buckets_since() {
+        let now = Utc::now();
+        assert_eq!(buckets_since(now, Duration::minutes(5), 12).unwrap(), now - Duration::hours(1));
+        assert!(buckets_since(now, Duration::days(MAX_INTERVAL_DAYS), i64::MAX).is_err());
+        assert!(buckets_since(now, Duration::days(MAX_INTERVAL_DAYS), i32::MAX as i64).is_err());
+    }
+}
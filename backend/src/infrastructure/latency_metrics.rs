@@ -0,0 +1,152 @@
+/// Latency Metrics
+/// Time from an oracle response reaching the monitor to the liquidation
+/// ZSET queries finishing and to the alert going out on the broadcast,
+/// kept over a rolling window of recent ticks for p50/p99
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Stages measured from the oracle response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LatencyStage {
+    /// Liquidation range queries done
+    ZsetQuery,
+    /// Alert sent on the broadcast channel
+    AlertBroadcast,
+}
+
+impl LatencyStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LatencyStage::ZsetQuery => "zset_query",
+            LatencyStage::AlertBroadcast => "alert_broadcast",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatencyStats {
+    /// Samples since startup, percentiles only cover the window
+    pub samples: u64,
+    pub over_budget: u64,
+    pub p50: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+#[derive(Debug, Default)]
+struct StageSamples {
+    recent: VecDeque<Duration>,
+    samples: u64,
+    over_budget: u64,
+}
+
+#[derive(Debug)]
+pub struct LatencyMetrics {
+    window: usize,
+    budget: Duration,
+    stages: Mutex<BTreeMap<LatencyStage, StageSamples>>,
+}
+
+impl LatencyMetrics {
+    pub fn new(window: usize, budget: Duration) -> Self {
+        Self {
+            window,
+            budget,
+            stages: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Returns true when the sample is over budget, so the caller can log it
+    pub fn record(&self, stage: LatencyStage, elapsed: Duration) -> bool {
+        let mut stages = self.stages.lock().unwrap();
+        let samples = stages.entry(stage).or_default();
+
+        if samples.recent.len() == self.window {
+            samples.recent.pop_front();
+        }
+        samples.recent.push_back(elapsed);
+        samples.samples += 1;
+
+        let over_budget = elapsed > self.budget;
+        if over_budget {
+            samples.over_budget += 1;
+        }
+
+        over_budget
+    }
+
+    pub fn snapshot(&self) -> Vec<(LatencyStage, LatencyStats)> {
+        self.stages
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(stage, samples)| {
+                let mut sorted: Vec<Duration> = samples.recent.iter().copied().collect();
+                sorted.sort_unstable();
+
+                let stats = LatencyStats {
+                    samples: samples.samples,
+                    over_budget: samples.over_budget,
+                    p50: percentile(&sorted, 50.0),
+                    p99: percentile(&sorted, 99.0),
+                    max: sorted.last().copied().unwrap_or_default(),
+                };
+
+                (*stage, stats)
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile of an ascending sample, `pct` in 0..=100
+pub fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sample: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+
+        assert_eq!(percentile(&sample, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&sample, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&sample, 0.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_rolling_window_and_budget() {
+        let metrics = LatencyMetrics::new(100, Duration::from_millis(150));
+
+        // The first 50 samples fall out of the window
+        for _ in 0..50 {
+            assert!(metrics.record(LatencyStage::ZsetQuery, Duration::from_millis(500)));
+        }
+        for ms in 1..=100 {
+            metrics.record(LatencyStage::ZsetQuery, Duration::from_millis(ms));
+        }
+
+        let stats = metrics.snapshot();
+        assert_eq!(stats.len(), 1);
+
+        let (stage, zset) = stats[0];
+        assert_eq!(stage, LatencyStage::ZsetQuery);
+        assert_eq!((zset.samples, zset.over_budget), (150, 50));
+        assert_eq!(zset.p50, Duration::from_millis(50));
+        assert_eq!(zset.p99, Duration::from_millis(99));
+        assert_eq!(zset.max, Duration::from_millis(100));
+    }
+}
@@ -1,5 +1,37 @@
 pub mod solana_client;
 pub mod oracle_client;
+pub mod price_source;
+pub mod signer;
+pub mod program_error;
+pub mod history_store;
+pub mod postgres_history_store;
+pub mod sqlite_history_store;
+pub mod request_context;
+pub mod telemetry;
+pub mod rpc_metrics;
+pub mod rpc_pool;
+pub mod circuit_breaker;
+pub mod price_feed_metrics;
+pub mod latency_metrics;
+pub mod account_stream;
+pub mod chain;
+pub mod program_idl;
 
 pub use solana_client::*;
 pub use oracle_client::*;
+pub use price_source::*;
+pub use signer::*;
+pub use program_error::*;
+pub use history_store::*;
+pub use postgres_history_store::*;
+pub use sqlite_history_store::*;
+pub use request_context::*;
+pub use telemetry::*;
+pub use rpc_metrics::*;
+pub use rpc_pool::*;
+pub use circuit_breaker::*;
+pub use price_feed_metrics::*;
+pub use latency_metrics::*;
+pub use account_stream::*;
+pub use chain::*;
+pub use program_idl::*;
@@ -1,9 +1,55 @@
 use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::domain::symbols;
+use crate::error::Error;
+
+/// Pyth push oracle, which keeps a PriceUpdateV2 account current for each
+/// sponsored feed. The program reads its prices from these accounts
+pub const PYTH_PUSH_ORACLE_ID: Pubkey = solana_sdk::pubkey!("pythWSnswVUd12oZpeFP8e9CVaEqJg25g1Vtc2biRsT");
+
+/// Shard of the push oracle the sponsored feeds are posted to
+const PYTH_PUSH_SHARD: u16 = 0;
+
+/// Max price age the program applies while its Config has none set, its
+/// DEFAULT_MAX_PRICE_AGE_SECS
+pub const DEFAULT_MAX_PRICE_AGE_SECS: u32 = 60;
+
+/// Pyth feed ids (hex without 0x) of the symbols the program trades, the
+/// same table as its PRICE_FEEDS
+pub const PYTH_FEEDS: [(&str, &str); 3] = [
+    ("BTC-USD", "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43"),
+    ("ETH-USD", "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace"),
+    ("SOL-USD", "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d"),
+];
+
+/// Pyth feed id of `symbol`
+pub fn pyth_feed_id(symbol: &str) -> Result<[u8; 32]> {
+    let symbol = symbols::normalize(symbol);
+    let (_, hex) = PYTH_FEEDS
+        .iter()
+        .find(|(feed_symbol, _)| *feed_symbol == symbol)
+        .ok_or_else(|| Error::NotFound(format!("No Pyth feed for {}", symbol)))?;
+
+    let mut feed_id = [0u8; 32];
+    for (byte, pair) in feed_id.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair)?, 16)?;
+    }
+    Ok(feed_id)
+}
+
+/// The push oracle's price update account for `symbol`, passed to every
+/// instruction that prices a position
+pub fn price_update_address(symbol: &str) -> Result<Pubkey> {
+    let feed_id = pyth_feed_id(symbol)?;
+    Ok(Pubkey::find_program_address(&[&PYTH_PUSH_SHARD.to_le_bytes(), &feed_id], &PYTH_PUSH_ORACLE_ID).0)
+}
+
 /// Pyth price feed IDs for different assets
 #[derive(Debug, Clone)]
 pub struct AssetConfig {
@@ -11,11 +57,54 @@ pub struct AssetConfig {
     pub pyth_price_id: String, // Hex string without 0x
 }
 
+/// A Pyth price with its confidence interval and the feed's publish time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OracleQuote {
+    pub price: Decimal,
+    pub conf: Decimal,
+    pub publish_time: DateTime<Utc>,
+}
+
+/// The last quote fetched for a symbol and when it was fetched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CachedQuote {
+    pub quote: OracleQuote,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CachedQuote {
+    /// Time since the oracle published the price
+    pub fn age(&self, now: DateTime<Utc>) -> chrono::Duration {
+        now - self.quote.publish_time
+    }
+
+    /// Publish time as unix seconds, the program's proof of the price's age
+    /// An Oracle error when the price is older than `max_age_secs`, the limit
+    /// past which the program rejects it as StaleOracle. 0 is the program's default
+    pub fn fresh_publish_time(&self, symbol: &str, max_age_secs: u32, now: DateTime<Utc>) -> Result<i64> {
+        let max_age_secs = match max_age_secs {
+            0 => DEFAULT_MAX_PRICE_AGE_SECS,
+            max_age_secs => max_age_secs,
+        };
+        let age = self.age(now);
+        if age > chrono::Duration::seconds(max_age_secs as i64) {
+            return Err(Error::Oracle(format!(
+                "{} price is {}s old, the program accepts at most {}s",
+                symbol,
+                age.num_seconds(),
+                max_age_secs
+            ))
+            .into());
+        }
+        Ok(self.quote.publish_time.timestamp())
+    }
+}
+
 pub struct OracleClient {
     http_client: reqwest::Client,
     base_url: String,
     asset_configs: HashMap<String, AssetConfig>,
-    latest_prices: Arc<RwLock<HashMap<String, Decimal>>>,
+    latest_quotes: Arc<RwLock<HashMap<String, CachedQuote>>>,
 }
 
 impl OracleClient {
@@ -24,7 +113,7 @@ impl OracleClient {
             http_client: reqwest::Client::new(),
             base_url,
             asset_configs: HashMap::new(),
-            latest_prices: Arc::new(RwLock::new(HashMap::new())),
+            latest_quotes: Arc::new(RwLock::new(HashMap::new())),
         }
     }
     
@@ -34,38 +123,42 @@ impl OracleClient {
     }
     
     /// Add an asset to monitor
-    pub fn add_asset(&mut self, config: AssetConfig) {
+    pub fn add_asset(&mut self, mut config: AssetConfig) {
+        config.symbol = symbols::normalize(&config.symbol);
         self.asset_configs.insert(config.symbol.clone(), config);
     }
     
     /// Configure with default Pyth price feeds
     pub fn with_mainnet_defaults(mut self) -> Self {
-        // BTC/USD
-        self.add_asset(AssetConfig {
-            symbol: "BTC-USD".to_string(),
-            pyth_price_id: "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43".to_string(),
-        });
-        
-        // ETH/USD
-        self.add_asset(AssetConfig {
-            symbol: "ETH-USD".to_string(),
-            pyth_price_id: "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace".to_string(),
-        });
-        
-        // SOL/USD
-        self.add_asset(AssetConfig {
-            symbol: "SOL-USD".to_string(),
-            pyth_price_id: "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d".to_string(),
-        });
-        
+        for (symbol, pyth_price_id) in PYTH_FEEDS {
+            self.add_asset(AssetConfig {
+                symbol: symbol.to_string(),
+                pyth_price_id: pyth_price_id.to_string(),
+            });
+        }
+
         self
     }
     
     /// Fetch current price for an asset via HTTP API
     pub async fn fetch_price(&self, symbol: &str) -> Result<Decimal> {
+        Ok(self.fetch_quote(symbol).await?.price)
+    }
+
+    /// Fetch current price, confidence and publish time via HTTP API
+    #[tracing::instrument(skip(self))]
+    pub async fn fetch_quote(&self, symbol: &str) -> Result<OracleQuote> {
+        let symbol = symbols::normalize(symbol);
         let config = self.asset_configs
-            .get(symbol)
-            .ok_or_else(|| anyhow::anyhow!("Asset not configured: {}", symbol))?;
+            .get(&symbol)
+            .ok_or_else(|| Error::NotFound(format!("Asset not configured: {}", symbol)))?;
+
+        self.request_quote(&symbol, config)
+            .await
+            .map_err(|e| Error::Oracle(format!("{}: {:#}", symbol, e)).into())
+    }
+
+    async fn request_quote(&self, symbol: &str, config: &AssetConfig) -> Result<OracleQuote> {
         
         // Construct Pyth Hermes API URL
         let url = format!(
@@ -109,6 +202,12 @@ impl OracleClient {
             .get("conf")
             .and_then(|c| c.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing conf field"))?;
+
+        let publish_time = parsed
+            .get("publish_time")
+            .and_then(|t| t.as_i64())
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .ok_or_else(|| anyhow::anyhow!("Missing publish_time field"))?;
         
         // Parse price and convert to decimal
         let price_i64: i64 = price_str.parse()
@@ -134,23 +233,47 @@ impl OracleClient {
             conf_value
         );
         
+        let quote = OracleQuote {
+            price: price_value,
+            conf: conf_value,
+            publish_time,
+        };
+
         // Update cache
-        let mut latest_prices = self.latest_prices.write().await;
-        latest_prices.insert(symbol.to_string(), price_value);
-        
-        Ok(price_value)
+        let mut latest_quotes = self.latest_quotes.write().await;
+        latest_quotes.insert(
+            symbol.to_string(),
+            CachedQuote {
+                quote,
+                fetched_at: Utc::now(),
+            },
+        );
+
+        Ok(quote)
     }
-    
+
     /// Get cached price (non-blocking)
     pub async fn get_cached_price(&self, symbol: &str) -> Option<Decimal> {
-        let latest_prices = self.latest_prices.read().await;
-        latest_prices.get(symbol).copied()
+        self.get_cached_quote(symbol).await.map(|cached| cached.quote.price)
+    }
+
+    /// Get cached quote with its fetch time (non-blocking)
+    pub async fn get_cached_quote(&self, symbol: &str) -> Option<CachedQuote> {
+        let latest_quotes = self.latest_quotes.read().await;
+        latest_quotes.get(&symbols::normalize(symbol)).copied()
     }
     
     /// Get all configured symbols
     pub fn get_symbols(&self) -> Vec<String> {
         self.asset_configs.keys().cloned().collect()
     }
+
+    /// Pyth price feed ID of a configured symbol, hex without 0x
+    pub fn feed_id(&self, symbol: &str) -> Option<String> {
+        self.asset_configs
+            .get(&symbols::normalize(symbol))
+            .map(|config| config.pyth_price_id.clone())
+    }
 }
 
 #[cfg(test)]
@@ -169,6 +292,38 @@ mod tests {
         assert!(symbols.contains(&"ETH-USD".to_string()));
     }
     
+    #[test]
+    fn test_price_update_address() {
+        assert_eq!(pyth_feed_id("solusd").unwrap()[..4], [0xef, 0x0d, 0x8b, 0x6f]);
+        assert!(pyth_feed_id("DOGE-USD").is_err());
+
+        // Pyth's published SOL/USD push feed account
+        assert_eq!(
+            price_update_address("SOL-USD").unwrap().to_string(),
+            "7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE"
+        );
+    }
+
+    #[test]
+    fn test_fresh_publish_time() {
+        let now = Utc::now();
+        let publish_time = now - chrono::Duration::seconds(45);
+        let cached = CachedQuote {
+            quote: OracleQuote {
+                price: Decimal::from(150),
+                conf: Decimal::ZERO,
+                publish_time,
+            },
+            fetched_at: now,
+        };
+
+        assert_eq!(cached.fresh_publish_time("SOL-USD", 0, now).unwrap(), publish_time.timestamp());
+        assert_eq!(cached.fresh_publish_time("SOL-USD", 60, now).unwrap(), publish_time.timestamp());
+
+        let err = cached.fresh_publish_time("SOL-USD", 30, now).unwrap_err();
+        assert!(matches!(err.downcast_ref::<Error>(), Some(Error::Oracle(_))));
+    }
+
     #[tokio::test]
     async fn test_fetch_price() {
         let oracle = OracleClient::new_hermes()
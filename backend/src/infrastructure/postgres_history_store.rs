@@ -0,0 +1,969 @@
+/// Postgres History Store
+/// Queries are built at runtime so the crate compiles without a database
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::domain::{PnLSnapshot, Position, RealizedPnL};
+use crate::infrastructure::history_store::{
+    buckets_since, event_kind_from_str, event_kind_to_str, side_from_str, side_to_str, status_from_str,
+    status_to_str,
+};
+use crate::infrastructure::{
+    ClosedPositionFilter, HistoryStore, PnlPoint, PositionEventRecord, PositionOpenRecord,
+    PriceTick, TableSize, TickScope, VaultSnapshot, HISTORY_TABLES,
+};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS pnl_snapshots (
+    id UUID PRIMARY KEY,
+    position_account TEXT NOT NULL,
+    unrealized_pnl NUMERIC NOT NULL,
+    realized_pnl NUMERIC NOT NULL,
+    funding_accrued NUMERIC NOT NULL,
+    mark_price NUMERIC NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS pnl_snapshots_position_time
+    ON pnl_snapshots (position_account, recorded_at);
+CREATE TABLE IF NOT EXISTS realized_pnl (
+    id UUID PRIMARY KEY,
+    position_account TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+    size_closed NUMERIC NOT NULL,
+    entry_price NUMERIC NOT NULL,
+    close_price NUMERIC NOT NULL,
+    fee NUMERIC NOT NULL,
+    funding NUMERIC NOT NULL,
+    amount NUMERIC NOT NULL,
+    liquidation BOOLEAN NOT NULL,
+    signature TEXT NOT NULL,
+    closed_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS realized_pnl_owner_time
+    ON realized_pnl (owner, closed_at);
+CREATE INDEX IF NOT EXISTS realized_pnl_position_time
+    ON realized_pnl (position_account, closed_at);
+CREATE UNIQUE INDEX IF NOT EXISTS realized_pnl_signature_position
+    ON realized_pnl (signature, position_account);
+CREATE TABLE IF NOT EXISTS position_opens (
+    position_account TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+    size NUMERIC NOT NULL,
+    entry_price NUMERIC NOT NULL,
+    margin NUMERIC NOT NULL,
+    signature TEXT NOT NULL,
+    opened_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS position_opens_owner_time
+    ON position_opens (owner, opened_at);
+CREATE TABLE IF NOT EXISTS position_events (
+    id UUID PRIMARY KEY,
+    position_account TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    size NUMERIC,
+    margin NUMERIC,
+    mark_price NUMERIC,
+    detail TEXT,
+    signature TEXT,
+    recorded_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS position_events_position_time
+    ON position_events (position_account, recorded_at);
+CREATE UNIQUE INDEX IF NOT EXISTS position_events_signature_kind
+    ON position_events (signature, position_account, kind);
+CREATE TABLE IF NOT EXISTS closed_positions (
+    position_account TEXT PRIMARY KEY,
+    position_index INTEGER NOT NULL,
+    owner TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+    status TEXT NOT NULL,
+    size NUMERIC NOT NULL,
+    entry_price NUMERIC NOT NULL,
+    mark_price NUMERIC NOT NULL,
+    margin NUMERIC NOT NULL,
+    leverage INTEGER NOT NULL,
+    realized_pnl NUMERIC NOT NULL,
+    funding_accrued NUMERIC NOT NULL,
+    liquidation_price NUMERIC NOT NULL,
+    opened_at TIMESTAMPTZ NOT NULL,
+    last_update TIMESTAMPTZ NOT NULL,
+    closed_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS closed_positions_owner_time
+    ON closed_positions (owner, closed_at);
+CREATE INDEX IF NOT EXISTS closed_positions_closed_at
+    ON closed_positions (closed_at);
+CREATE TABLE IF NOT EXISTS price_ticks (
+    symbol TEXT NOT NULL,
+    price NUMERIC NOT NULL,
+    conf NUMERIC,
+    publish_time TIMESTAMPTZ,
+    recorded_at TIMESTAMPTZ NOT NULL
+);
+ALTER TABLE price_ticks ADD COLUMN IF NOT EXISTS conf NUMERIC;
+ALTER TABLE price_ticks ADD COLUMN IF NOT EXISTS publish_time TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS price_ticks_time
+    ON price_ticks (recorded_at);
+CREATE INDEX IF NOT EXISTS price_ticks_symbol_time
+    ON price_ticks (symbol, recorded_at);
+CREATE TABLE IF NOT EXISTS vault_snapshots (
+    total_assets NUMERIC NOT NULL,
+    total_shares NUMERIC NOT NULL,
+    locked_margin NUMERIC NOT NULL,
+    share_price NUMERIC NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS vault_snapshots_time
+    ON vault_snapshots (recorded_at);
+"#;
+
+pub struct PostgresHistoryStore {
+    pool: PgPool,
+}
+
+impl PostgresHistoryStore {
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(10)
+            .connect(database_url)
+            .await
+            .context("Failed to connect to Postgres")?;
+
+        let store = Self { pool };
+        store.init_schema().await?;
+
+        Ok(store)
+    }
+
+    async fn init_schema(&self) -> Result<()> {
+        for statement in SCHEMA.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            sqlx::query(statement)
+                .execute(&self.pool)
+                .await
+                .context("Failed to initialize history schema")?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn insert_pnl_snapshots(&self, snapshots: &[PnLSnapshot]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for snapshot in snapshots {
+            sqlx::query(
+                "INSERT INTO pnl_snapshots \
+                 (id, position_account, unrealized_pnl, realized_pnl, funding_accrued, mark_price, recorded_at) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            )
+            .bind(snapshot.id)
+            .bind(snapshot.position_account.to_string())
+            .bind(snapshot.unrealized_pnl)
+            .bind(snapshot.realized_pnl)
+            .bind(snapshot.funding_accrued)
+            .bind(snapshot.mark_price)
+            .bind(snapshot.timestamp)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Last `limit` buckets of PnL for a position, oldest first
+    pub async fn pnl_history(
+        &self,
+        position_account: &Pubkey,
+        interval: Duration,
+        limit: i64,
+    ) -> Result<Vec<PnlPoint>> {
+        let since = buckets_since(Utc::now(), interval, limit)?;
+
+        let rows = sqlx::query(
+            "SELECT DISTINCT ON (bucket) \
+                 date_bin($2::float8 * INTERVAL '1 second', recorded_at, TIMESTAMPTZ '2000-01-01') AS bucket, \
+                 unrealized_pnl, realized_pnl, funding_accrued, mark_price \
+             FROM pnl_snapshots \
+             WHERE position_account = $1 AND recorded_at >= $3 \
+             ORDER BY bucket, recorded_at DESC",
+        )
+        .bind(position_account.to_string())
+        .bind(interval.num_seconds())
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query PnL history")?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(PnlPoint {
+                    bucket: row.try_get("bucket")?,
+                    unrealized_pnl: row.try_get("unrealized_pnl")?,
+                    realized_pnl: row.try_get("realized_pnl")?,
+                    funding_accrued: row.try_get("funding_accrued")?,
+                    mark_price: row.try_get("mark_price")?,
+                })
+            })
+            .collect()
+    }
+    pub async fn insert_realized_pnl(&self, record: &RealizedPnL) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO realized_pnl \
+             (id, position_account, owner, symbol, side, size_closed, entry_price, close_price, \
+              fee, funding, amount, liquidation, signature, closed_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14) \
+             ON CONFLICT (signature, position_account) DO NOTHING",
+        )
+        .bind(record.id)
+        .bind(record.position_account.to_string())
+        .bind(record.owner.to_string())
+        .bind(&record.symbol)
+        .bind(side_to_str(record.side))
+        .bind(record.size_closed)
+        .bind(record.entry_price)
+        .bind(record.close_price)
+        .bind(record.fee)
+        .bind(record.funding)
+        .bind(record.amount)
+        .bind(record.liquidation)
+        .bind(&record.signature)
+        .bind(record.closed_at)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert realized PnL")?;
+
+        Ok(())
+    }
+
+    /// Realized PnL events for `owner` in [from, to), oldest first
+    pub async fn realized_pnl(
+        &self,
+        owner: &Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<RealizedPnL>> {
+        self.realized_pnl_page(owner, from, to, None, i64::MAX).await
+    }
+
+    /// Up to `limit` realized PnL events for `owner` in [from, to) after the
+    /// (closed_at, id) key `after`, oldest first
+    pub async fn realized_pnl_page(
+        &self,
+        owner: &Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<RealizedPnL>> {
+        let rows = sqlx::query(
+            "SELECT * FROM realized_pnl \
+             WHERE owner = $1 \
+               AND ($2::timestamptz IS NULL OR closed_at >= $2) \
+               AND ($3::timestamptz IS NULL OR closed_at < $3) \
+               AND ($4::timestamptz IS NULL OR (closed_at, id) > ($4, $5::uuid)) \
+             ORDER BY closed_at, id \
+             LIMIT $6",
+        )
+        .bind(owner.to_string())
+        .bind(from)
+        .bind(to)
+        .bind(after.map(|(closed_at, _)| closed_at))
+        .bind(after.map(|(_, id)| id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query realized PnL")?;
+
+        rows.iter().map(realized_pnl_from_row).collect()
+    }
+
+    pub async fn insert_position_open(&self, position: &Position, signature: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO position_opens \
+             (position_account, owner, symbol, side, size, entry_price, margin, signature, opened_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) \
+             ON CONFLICT (position_account) DO NOTHING",
+        )
+        .bind(position.position_account.to_string())
+        .bind(position.owner.to_string())
+        .bind(&position.symbol)
+        .bind(side_to_str(position.side))
+        .bind(position.size)
+        .bind(position.entry_price)
+        .bind(position.margin)
+        .bind(signature)
+        .bind(position.opened_at)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert position open")?;
+
+        Ok(())
+    }
+
+    /// Opens for `owner` before `before`, oldest first
+    pub async fn position_opens(
+        &self,
+        owner: &Pubkey,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<PositionOpenRecord>> {
+        let rows = sqlx::query(
+            "SELECT * FROM position_opens WHERE owner = $1 AND opened_at < $2 ORDER BY opened_at",
+        )
+        .bind(owner.to_string())
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query position opens")?;
+
+        rows.iter().map(position_open_from_row).collect()
+    }
+
+    /// Opens across all owners in [from, to), oldest first
+    pub async fn position_opens_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<PositionOpenRecord>> {
+        let rows = sqlx::query(
+            "SELECT * FROM position_opens WHERE opened_at >= $1 AND opened_at < $2 ORDER BY opened_at",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query position opens")?;
+
+        rows.iter().map(position_open_from_row).collect()
+    }
+
+    /// Realized PnL events across all owners in [from, to), oldest first
+    pub async fn realized_pnl_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<RealizedPnL>> {
+        let rows = sqlx::query(
+            "SELECT * FROM realized_pnl WHERE closed_at >= $1 AND closed_at < $2 ORDER BY closed_at",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query realized PnL")?;
+
+        rows.iter().map(realized_pnl_from_row).collect()
+    }
+
+    pub async fn position_open(&self, position_account: &Pubkey) -> Result<Option<PositionOpenRecord>> {
+        let row = sqlx::query("SELECT * FROM position_opens WHERE position_account = $1")
+            .bind(position_account.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to query position open")?;
+
+        row.as_ref().map(position_open_from_row).transpose()
+    }
+
+    /// Realized PnL events for one position, oldest first
+    pub async fn position_realized_pnl(&self, position_account: &Pubkey) -> Result<Vec<RealizedPnL>> {
+        let rows = sqlx::query(
+            "SELECT * FROM realized_pnl WHERE position_account = $1 ORDER BY closed_at, id",
+        )
+        .bind(position_account.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query realized PnL")?;
+
+        rows.iter().map(realized_pnl_from_row).collect()
+    }
+
+    pub async fn insert_position_event(&self, event: &PositionEventRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO position_events \
+             (id, position_account, kind, size, margin, mark_price, detail, signature, recorded_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) \
+             ON CONFLICT (signature, position_account, kind) DO NOTHING",
+        )
+        .bind(event.id)
+        .bind(event.position_account.to_string())
+        .bind(event_kind_to_str(event.kind))
+        .bind(event.size)
+        .bind(event.margin)
+        .bind(event.mark_price)
+        .bind(event.detail.as_deref())
+        .bind(event.signature.as_deref())
+        .bind(event.recorded_at)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert position event")?;
+
+        Ok(())
+    }
+
+    /// Events for one position, oldest first
+    pub async fn position_events(&self, position_account: &Pubkey) -> Result<Vec<PositionEventRecord>> {
+        let rows = sqlx::query(
+            "SELECT * FROM position_events WHERE position_account = $1 ORDER BY recorded_at, id",
+        )
+        .bind(position_account.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query position events")?;
+
+        rows.iter().map(position_event_from_row).collect()
+    }
+
+    pub async fn insert_price_ticks(&self, ticks: &[PriceTick]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for tick in ticks {
+            sqlx::query(
+                "INSERT INTO price_ticks (symbol, price, conf, publish_time, recorded_at) \
+                 VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(&tick.symbol)
+            .bind(tick.price)
+            .bind(tick.conf)
+            .bind(tick.publish_time)
+            .bind(tick.recorded_at)
+            .execute(&mut tx)
+            .await
+            .context("Failed to insert price tick")?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Price ticks in [from, to), oldest first
+    pub async fn price_ticks(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<PriceTick>> {
+        let rows = sqlx::query(
+            "SELECT symbol, price, conf, publish_time, recorded_at FROM price_ticks \
+             WHERE recorded_at >= $1 AND recorded_at < $2 \
+             ORDER BY recorded_at, symbol",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query price ticks")?;
+
+        rows.iter().map(price_tick_from_row).collect()
+    }
+
+    /// One symbol's price ticks in [from, to), oldest first, at most `limit`
+    pub async fn symbol_price_ticks(
+        &self,
+        symbol: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<PriceTick>> {
+        let rows = sqlx::query(
+            "SELECT symbol, price, conf, publish_time, recorded_at FROM price_ticks \
+             WHERE symbol = $1 AND recorded_at >= $2 AND recorded_at < $3 \
+             ORDER BY recorded_at \
+             LIMIT $4",
+        )
+        .bind(symbol)
+        .bind(from)
+        .bind(to)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query price ticks")?;
+
+        rows.iter().map(price_tick_from_row).collect()
+    }
+
+    /// Thin ticks in `scope` received before `before` to the last one of each
+    /// symbol in each `bucket`, returns how many were deleted
+    pub async fn compact_price_ticks(
+        &self,
+        scope: &TickScope,
+        before: DateTime<Utc>,
+        bucket: Duration,
+    ) -> Result<u64> {
+        let (symbol, excluded) = match scope {
+            TickScope::Symbol(symbol) => (Some(symbol.clone()), Vec::new()),
+            TickScope::AllExcept(excluded) => (None, excluded.clone()),
+        };
+
+        // A tick goes when a later one of its symbol landed in the same bucket
+        // and before the cutoff, so the last tick of each bucket stays
+        let result = sqlx::query(
+            "DELETE FROM price_ticks t \
+             WHERE t.recorded_at < $1 \
+               AND ($3::text IS NULL OR t.symbol = $3) \
+               AND NOT (t.symbol = ANY($4)) \
+               AND EXISTS ( \
+                   SELECT 1 FROM price_ticks later \
+                   WHERE later.symbol = t.symbol \
+                     AND later.recorded_at > t.recorded_at \
+                     AND later.recorded_at < LEAST($1, \
+                         date_bin($2::float8 * INTERVAL '1 second', t.recorded_at, TIMESTAMPTZ '2000-01-01') \
+                         + $2::float8 * INTERVAL '1 second'))",
+        )
+        .bind(before)
+        .bind(bucket.num_seconds())
+        .bind(symbol)
+        .bind(excluded)
+        .execute(&self.pool)
+        .await
+        .context("Failed to compact price ticks")?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Delete PnL snapshots recorded before `before`, returns how many
+    pub async fn delete_pnl_snapshots_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM pnl_snapshots WHERE recorded_at < $1")
+            .bind(before)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete PnL snapshots")?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Size of each history table, rows from the planner's live tuple count
+    /// rather than a scan
+    pub async fn table_sizes(&self) -> Result<Vec<TableSize>> {
+        let rows = sqlx::query(
+            "SELECT relname::text AS table_name, n_live_tup AS row_count, \
+                 pg_total_relation_size(relid) AS bytes \
+             FROM pg_stat_user_tables WHERE relname = ANY($1)",
+        )
+        .bind(HISTORY_TABLES.iter().map(|t| t.to_string()).collect::<Vec<_>>())
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query history table sizes")?;
+
+        let mut sizes = Vec::with_capacity(HISTORY_TABLES.len());
+
+        for table in HISTORY_TABLES {
+            let row = rows.iter().find(|row| row.try_get::<String, _>("table_name").ok().as_deref() == Some(table));
+
+            sizes.push(TableSize {
+                table: table.to_string(),
+                rows: match row {
+                    Some(row) => row.try_get("row_count")?,
+                    None => 0,
+                },
+                bytes: row.map(|row| row.try_get("bytes")).transpose()?,
+            });
+        }
+
+        Ok(sizes)
+    }
+
+    pub async fn insert_vault_snapshot(&self, snapshot: &VaultSnapshot) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO vault_snapshots \
+             (total_assets, total_shares, locked_margin, share_price, recorded_at) \
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(snapshot.total_assets)
+        .bind(snapshot.total_shares)
+        .bind(snapshot.locked_margin)
+        .bind(snapshot.share_price)
+        .bind(snapshot.recorded_at)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert vault snapshot")?;
+
+        Ok(())
+    }
+
+    /// Vault snapshots in [from, to), oldest first
+    pub async fn vault_snapshots(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<VaultSnapshot>> {
+        let rows = sqlx::query(
+            "SELECT total_assets, total_shares, locked_margin, share_price, recorded_at \
+             FROM vault_snapshots WHERE recorded_at >= $1 AND recorded_at < $2 \
+             ORDER BY recorded_at",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query vault snapshots")?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(VaultSnapshot {
+                    total_assets: row.try_get("total_assets")?,
+                    total_shares: row.try_get("total_shares")?,
+                    locked_margin: row.try_get("locked_margin")?,
+                    share_price: row.try_get("share_price")?,
+                    recorded_at: row.try_get("recorded_at")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Upsert positions evicted from the monitor, in one transaction
+    pub async fn insert_closed_positions(&self, positions: &[Position]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+
+        for position in positions {
+            sqlx::query(
+                "INSERT INTO closed_positions \
+                 (position_account, position_index, owner, symbol, side, status, size, entry_price, \
+                  mark_price, margin, leverage, realized_pnl, funding_accrued, liquidation_price, \
+                  opened_at, last_update, closed_at) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17) \
+                 ON CONFLICT (position_account) DO UPDATE SET \
+                 status = EXCLUDED.status, size = EXCLUDED.size, mark_price = EXCLUDED.mark_price, \
+                 margin = EXCLUDED.margin, realized_pnl = EXCLUDED.realized_pnl, \
+                 funding_accrued = EXCLUDED.funding_accrued, last_update = EXCLUDED.last_update, \
+                 closed_at = EXCLUDED.closed_at",
+            )
+            .bind(position.position_account.to_string())
+            .bind(position.position_index as i32)
+            .bind(position.owner.to_string())
+            .bind(&position.symbol)
+            .bind(side_to_str(position.side))
+            .bind(status_to_str(position.status))
+            .bind(position.size)
+            .bind(position.entry_price)
+            .bind(position.mark_price)
+            .bind(position.margin)
+            .bind(position.leverage as i32)
+            .bind(position.realized_pnl)
+            .bind(position.funding_accrued)
+            .bind(position.liquidation_price)
+            .bind(position.opened_at)
+            .bind(position.last_update)
+            .bind(position.closed_at.unwrap_or(position.last_update))
+            .execute(&mut tx)
+            .await
+            .context("Failed to insert closed position")?;
+        }
+
+        tx.commit().await.context("Failed to commit closed positions")?;
+
+        Ok(())
+    }
+
+    /// Closed positions matching `filter`, most recently closed first
+    pub async fn closed_positions(
+        &self,
+        filter: &ClosedPositionFilter,
+        limit: i64,
+    ) -> Result<Vec<Position>> {
+        let rows = sqlx::query(
+            "SELECT * FROM closed_positions \
+             WHERE ($1::text IS NULL OR owner = $1) \
+               AND ($2::text IS NULL OR symbol = $2) \
+               AND ($3::text IS NULL OR status = $3) \
+               AND ($5::timestamptz IS NULL OR (closed_at, position_account) < ($5, $6::text)) \
+             ORDER BY closed_at DESC, position_account DESC \
+             LIMIT $4",
+        )
+        .bind(filter.owner.map(|owner| owner.to_string()))
+        .bind(filter.symbol.as_deref())
+        .bind(filter.status.map(status_to_str))
+        .bind(limit)
+        .bind(filter.before.map(|(closed_at, _)| closed_at))
+        .bind(filter.before.map(|(_, account)| account.to_string()))
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query closed positions")?;
+
+        rows.iter().map(closed_position_from_row).collect()
+    }
+
+    pub async fn closed_position(&self, position_account: &Pubkey) -> Result<Option<Position>> {
+        let row = sqlx::query("SELECT * FROM closed_positions WHERE position_account = $1")
+            .bind(position_account.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to query closed position")?;
+
+        row.as_ref().map(closed_position_from_row).transpose()
+    }
+}
+
+// Inherent methods take precedence, so these delegate rather than recurse
+impl HistoryStore for PostgresHistoryStore {
+    fn insert_pnl_snapshots<'a>(&'a self, snapshots: &'a [PnLSnapshot]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_pnl_snapshots(snapshots))
+    }
+
+    fn pnl_history<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+        interval: Duration,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<PnlPoint>>> {
+        Box::pin(self.pnl_history(position_account, interval, limit))
+    }
+
+    fn insert_realized_pnl<'a>(&'a self, record: &'a RealizedPnL) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_realized_pnl(record))
+    }
+
+    fn realized_pnl_page<'a>(
+        &'a self,
+        owner: &'a Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>> {
+        Box::pin(self.realized_pnl_page(owner, from, to, after, limit))
+    }
+
+    fn realized_pnl<'a>(
+        &'a self,
+        owner: &'a Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>> {
+        Box::pin(self.realized_pnl(owner, from, to))
+    }
+
+    fn insert_position_open<'a>(
+        &'a self,
+        position: &'a Position,
+        signature: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_position_open(position, signature))
+    }
+
+    fn position_opens<'a>(
+        &'a self,
+        owner: &'a Pubkey,
+        before: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<PositionOpenRecord>>> {
+        Box::pin(self.position_opens(owner, before))
+    }
+
+    fn position_opens_between<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<PositionOpenRecord>>> {
+        Box::pin(self.position_opens_between(from, to))
+    }
+
+    fn realized_pnl_between<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>> {
+        Box::pin(self.realized_pnl_between(from, to))
+    }
+
+    fn position_open<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<PositionOpenRecord>>> {
+        Box::pin(self.position_open(position_account))
+    }
+
+    fn position_realized_pnl<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>> {
+        Box::pin(self.position_realized_pnl(position_account))
+    }
+
+    fn insert_position_event<'a>(&'a self, event: &'a PositionEventRecord) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_position_event(event))
+    }
+
+    fn position_events<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<PositionEventRecord>>> {
+        Box::pin(self.position_events(position_account))
+    }
+
+    fn insert_price_ticks<'a>(&'a self, ticks: &'a [PriceTick]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_price_ticks(ticks))
+    }
+
+    fn price_ticks<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<PriceTick>>> {
+        Box::pin(self.price_ticks(from, to))
+    }
+
+    fn symbol_price_ticks<'a>(
+        &'a self,
+        symbol: &'a str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<PriceTick>>> {
+        Box::pin(self.symbol_price_ticks(symbol, from, to, limit))
+    }
+
+    fn compact_price_ticks<'a>(
+        &'a self,
+        scope: &'a TickScope,
+        before: DateTime<Utc>,
+        bucket: Duration,
+    ) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(self.compact_price_ticks(scope, before, bucket))
+    }
+
+    fn delete_pnl_snapshots_before<'a>(&'a self, before: DateTime<Utc>) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(self.delete_pnl_snapshots_before(before))
+    }
+
+    fn table_sizes(&self) -> BoxFuture<'_, Result<Vec<TableSize>>> {
+        Box::pin(self.table_sizes())
+    }
+
+    fn insert_vault_snapshot<'a>(&'a self, snapshot: &'a VaultSnapshot) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_vault_snapshot(snapshot))
+    }
+
+    fn vault_snapshots<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<VaultSnapshot>>> {
+        Box::pin(self.vault_snapshots(from, to))
+    }
+
+    fn insert_closed_positions<'a>(&'a self, positions: &'a [Position]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_closed_positions(positions))
+    }
+
+    fn closed_positions<'a>(
+        &'a self,
+        filter: &'a ClosedPositionFilter,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<Position>>> {
+        Box::pin(self.closed_positions(filter, limit))
+    }
+
+    fn closed_position<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<Position>>> {
+        Box::pin(self.closed_position(position_account))
+    }
+}
+
+fn price_tick_from_row(row: &sqlx::postgres::PgRow) -> Result<PriceTick> {
+    Ok(PriceTick {
+        symbol: row.try_get("symbol")?,
+        price: row.try_get("price")?,
+        conf: row.try_get("conf")?,
+        publish_time: row.try_get("publish_time")?,
+        recorded_at: row.try_get("recorded_at")?,
+    })
+}
+
+fn realized_pnl_from_row(row: &sqlx::postgres::PgRow) -> Result<RealizedPnL> {
+    let position_account: String = row.try_get("position_account")?;
+    let owner: String = row.try_get("owner")?;
+    let side: String = row.try_get("side")?;
+
+    Ok(RealizedPnL {
+        id: row.try_get("id")?,
+        position_account: position_account.parse()?,
+        owner: owner.parse()?,
+        symbol: row.try_get("symbol")?,
+        side: side_from_str(&side)?,
+        size_closed: row.try_get("size_closed")?,
+        entry_price: row.try_get("entry_price")?,
+        close_price: row.try_get("close_price")?,
+        fee: row.try_get("fee")?,
+        funding: row.try_get("funding")?,
+        amount: row.try_get("amount")?,
+        liquidation: row.try_get("liquidation")?,
+        signature: row.try_get("signature")?,
+        closed_at: row.try_get("closed_at")?,
+    })
+}
+
+fn position_open_from_row(row: &sqlx::postgres::PgRow) -> Result<PositionOpenRecord> {
+    let position_account: String = row.try_get("position_account")?;
+    let owner: String = row.try_get("owner")?;
+    let side: String = row.try_get("side")?;
+
+    Ok(PositionOpenRecord {
+        position_account: position_account.parse()?,
+        owner: owner.parse()?,
+        symbol: row.try_get("symbol")?,
+        side: side_from_str(&side)?,
+        size: row.try_get("size")?,
+        entry_price: row.try_get("entry_price")?,
+        margin: row.try_get("margin")?,
+        signature: row.try_get("signature")?,
+        opened_at: row.try_get("opened_at")?,
+    })
+}
+
+fn position_event_from_row(row: &sqlx::postgres::PgRow) -> Result<PositionEventRecord> {
+    let position_account: String = row.try_get("position_account")?;
+    let kind: String = row.try_get("kind")?;
+
+    Ok(PositionEventRecord {
+        id: row.try_get("id")?,
+        position_account: position_account.parse()?,
+        kind: event_kind_from_str(&kind)?,
+        size: row.try_get("size")?,
+        margin: row.try_get("margin")?,
+        mark_price: row.try_get("mark_price")?,
+        detail: row.try_get("detail")?,
+        signature: row.try_get("signature")?,
+        recorded_at: row.try_get("recorded_at")?,
+    })
+}
+
+fn closed_position_from_row(row: &sqlx::postgres::PgRow) -> Result<Position> {
+    let position_account: String = row.try_get("position_account")?;
+    let owner: String = row.try_get("owner")?;
+    let side: String = row.try_get("side")?;
+    let status: String = row.try_get("status")?;
+    let position_index: i32 = row.try_get("position_index")?;
+    let leverage: i32 = row.try_get("leverage")?;
+
+    Ok(Position {
+        position_index: position_index as u32,
+        owner: owner.parse()?,
+        position_account: position_account.parse()?,
+        symbol: row.try_get("symbol")?,
+        side: side_from_str(&side)?,
+        size: row.try_get("size")?,
+        entry_price: row.try_get("entry_price")?,
+        mark_price: row.try_get("mark_price")?,
+        margin: row.try_get("margin")?,
+        leverage: leverage as u16,
+        unrealized_pnl: Decimal::ZERO,
+        realized_pnl: row.try_get("realized_pnl")?,
+        funding_accrued: row.try_get("funding_accrued")?,
+        liquidation_price: row.try_get("liquidation_price")?,
+        effective_liquidation_price: None,
+        status: status_from_str(&status)?,
+        opened_at: row.try_get("opened_at")?,
+        last_update: row.try_get("last_update")?,
+        closed_at: Some(row.try_get("closed_at")?),
+        margin_call_at: None,
+    })
+}
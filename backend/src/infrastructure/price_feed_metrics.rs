@@ -0,0 +1,114 @@
+/// Price Feed Metrics
+/// Per-symbol counters over the oracle's publish times, so holes in the feed
+/// and ticks published out of order show up in the archive's audit trail
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PriceFeedStats {
+    pub ticks: u64,
+    /// Publish times further apart than the gap threshold
+    pub gaps: u64,
+    pub longest_gap_ms: i64,
+    /// Published before the previous tick
+    pub out_of_order: u64,
+    /// Same publish time as the previous tick, the feed hadn't updated
+    pub repeated: u64,
+    pub last_publish_time: Option<DateTime<Utc>>,
+}
+
+/// How a tick's publish time relates to the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickOrder {
+    InOrder,
+    Gap(Duration),
+    Repeated,
+    OutOfOrder,
+}
+
+#[derive(Debug)]
+pub struct PriceFeedMetrics {
+    gap_threshold: Duration,
+    symbols: Mutex<BTreeMap<String, PriceFeedStats>>,
+}
+
+impl PriceFeedMetrics {
+    pub fn new(gap_threshold: Duration) -> Self {
+        Self {
+            gap_threshold,
+            symbols: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn record(&self, symbol: &str, publish_time: DateTime<Utc>) -> TickOrder {
+        let mut symbols = self.symbols.lock().unwrap();
+        let stats = symbols.entry(symbol.to_string()).or_default();
+        stats.ticks += 1;
+
+        let order = match stats.last_publish_time {
+            None => TickOrder::InOrder,
+            Some(last) if publish_time < last => TickOrder::OutOfOrder,
+            Some(last) if publish_time == last => TickOrder::Repeated,
+            Some(last) if publish_time - last > self.gap_threshold => TickOrder::Gap(publish_time - last),
+            Some(_) => TickOrder::InOrder,
+        };
+
+        match order {
+            TickOrder::OutOfOrder => stats.out_of_order += 1,
+            TickOrder::Repeated => stats.repeated += 1,
+            TickOrder::Gap(gap) => {
+                stats.gaps += 1;
+                stats.longest_gap_ms = stats.longest_gap_ms.max(gap.num_milliseconds());
+            }
+            TickOrder::InOrder => {}
+        }
+
+        // A late tick doesn't move the feed back
+        if order != TickOrder::OutOfOrder {
+            stats.last_publish_time = Some(publish_time);
+        }
+
+        order
+    }
+
+    /// Counters since startup, by symbol
+    pub fn snapshot(&self) -> Vec<(String, PriceFeedStats)> {
+        self.symbols
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(symbol, stats)| (symbol.clone(), *stats))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_gaps_and_reordering() {
+        let metrics = PriceFeedMetrics::new(Duration::seconds(5));
+        let t0 = Utc::now();
+
+        assert_eq!(metrics.record("SOL-USD", t0), TickOrder::InOrder);
+        assert_eq!(metrics.record("SOL-USD", t0 + Duration::seconds(1)), TickOrder::InOrder);
+        assert_eq!(metrics.record("SOL-USD", t0 + Duration::seconds(1)), TickOrder::Repeated);
+        assert_eq!(metrics.record("SOL-USD", t0), TickOrder::OutOfOrder);
+        assert_eq!(
+            metrics.record("SOL-USD", t0 + Duration::seconds(11)),
+            TickOrder::Gap(Duration::seconds(10))
+        );
+
+        // Symbols are tracked apart
+        assert_eq!(metrics.record("BTC-USD", t0), TickOrder::InOrder);
+
+        let stats = metrics.snapshot();
+        let (_, sol) = stats.iter().find(|(symbol, _)| symbol == "SOL-USD").unwrap();
+        assert_eq!(sol.ticks, 5);
+        assert_eq!((sol.gaps, sol.out_of_order, sol.repeated), (1, 1, 1));
+        assert_eq!(sol.longest_gap_ms, 10_000);
+        assert_eq!(sol.last_publish_time, Some(t0 + Duration::seconds(11)));
+    }
+}
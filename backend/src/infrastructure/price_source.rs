@@ -0,0 +1,212 @@
+/// Price Source
+/// What the monitor needs from an oracle. Pyth goes through OracleClient,
+/// benchmarks and load runs feed prices through a replay source instead, and
+/// devnet QA injects prices over the oracle with SimulatedPriceSource
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::infrastructure::{CachedQuote, OracleClient, OracleQuote};
+
+pub trait PriceSource: Send + Sync {
+    /// Symbols to poll every price tick
+    fn get_symbols(&self) -> BoxFuture<'_, Vec<String>>;
+
+    /// Fetch a fresh quote, updating the cache
+    fn fetch_quote<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Result<OracleQuote>>;
+
+    /// Fetch a fresh price, updating the cache
+    fn fetch_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Result<Decimal>> {
+        Box::pin(async move { Ok(self.fetch_quote(symbol).await?.price) })
+    }
+
+    /// Last fetched price, without a network call
+    fn get_cached_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>>;
+
+    /// Last fetched quote and when it was fetched, without a network call
+    fn get_cached_quote<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<CachedQuote>>;
+
+    /// Oracle feed the symbol is priced from, none for sources that aren't an oracle
+    fn feed_id<'a>(&'a self, _symbol: &'a str) -> BoxFuture<'a, Option<String>> {
+        Box::pin(async { None })
+    }
+}
+
+impl PriceSource for RwLock<OracleClient> {
+    fn get_symbols(&self) -> BoxFuture<'_, Vec<String>> {
+        Box::pin(async move { self.read().await.get_symbols() })
+    }
+
+    fn fetch_quote<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Result<OracleQuote>> {
+        Box::pin(async move { self.read().await.fetch_quote(symbol).await })
+    }
+
+    fn get_cached_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>> {
+        Box::pin(async move { self.read().await.get_cached_price(symbol).await })
+    }
+
+    fn get_cached_quote<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<CachedQuote>> {
+        Box::pin(async move { self.read().await.get_cached_quote(symbol).await })
+    }
+
+    fn feed_id<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<String>> {
+        Box::pin(async move { self.read().await.feed_id(symbol) })
+    }
+}
+
+/// A price injected over the oracle's, until `expires_at`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulatedPrice {
+    pub price: Decimal,
+    pub injected_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SimulatedPrice {
+    /// Quote it stands in for, published now with no confidence interval so
+    /// staleness and confidence checks pass
+    fn quote(&self, now: DateTime<Utc>) -> OracleQuote {
+        OracleQuote {
+            price: self.price,
+            conf: Decimal::ZERO,
+            publish_time: now,
+        }
+    }
+}
+
+/// Wraps a price source so admins can inject prices on devnet. An injected
+/// price is what the price tick fetches, so it's broadcast and runs
+/// liquidation, margin and price alert checks like a real move would
+pub struct SimulatedPriceSource {
+    inner: Arc<dyn PriceSource>,
+    injected: RwLock<HashMap<String, SimulatedPrice>>,
+}
+
+impl SimulatedPriceSource {
+    pub fn new(inner: Arc<dyn PriceSource>) -> Self {
+        Self {
+            inner,
+            injected: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Price `symbol` at `price` for `ttl`, replacing any earlier injection
+    pub async fn inject(&self, symbol: &str, price: Decimal, ttl: chrono::Duration) -> SimulatedPrice {
+        let now = Utc::now();
+        let simulated = SimulatedPrice {
+            price,
+            injected_at: now,
+            expires_at: now + ttl,
+        };
+
+        let mut injected = self.injected.write().await;
+        injected.retain(|_, simulated| simulated.expires_at > now);
+        injected.insert(symbol.to_string(), simulated);
+        simulated
+    }
+
+    /// Back to the oracle for `symbol`. False if nothing was injected
+    pub async fn clear(&self, symbol: &str) -> bool {
+        let now = Utc::now();
+        self.injected
+            .write()
+            .await
+            .remove(symbol)
+            .is_some_and(|simulated| simulated.expires_at > now)
+    }
+
+    /// Injection in effect for `symbol`
+    pub async fn simulated(&self, symbol: &str) -> Option<SimulatedPrice> {
+        let now = Utc::now();
+        self.injected
+            .read()
+            .await
+            .get(symbol)
+            .filter(|simulated| simulated.expires_at > now)
+            .copied()
+    }
+
+    /// Last price from the wrapped source, ignoring injections
+    pub async fn oracle_price(&self, symbol: &str) -> Option<Decimal> {
+        self.inner.get_cached_price(symbol).await
+    }
+}
+
+impl PriceSource for SimulatedPriceSource {
+    fn get_symbols(&self) -> BoxFuture<'_, Vec<String>> {
+        self.inner.get_symbols()
+    }
+
+    fn fetch_quote<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Result<OracleQuote>> {
+        Box::pin(async move {
+            match self.simulated(symbol).await {
+                Some(simulated) => Ok(simulated.quote(Utc::now())),
+                None => self.inner.fetch_quote(symbol).await,
+            }
+        })
+    }
+
+    fn get_cached_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>> {
+        Box::pin(async move {
+            match self.simulated(symbol).await {
+                Some(simulated) => Some(simulated.price),
+                None => self.inner.get_cached_price(symbol).await,
+            }
+        })
+    }
+
+    fn get_cached_quote<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<CachedQuote>> {
+        Box::pin(async move {
+            match self.simulated(symbol).await {
+                Some(simulated) => {
+                    let now = Utc::now();
+                    Some(CachedQuote {
+                        quote: simulated.quote(now),
+                        fetched_at: now,
+                    })
+                }
+                None => self.inner.get_cached_quote(symbol).await,
+            }
+        })
+    }
+
+    fn feed_id<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<String>> {
+        self.inner.feed_id(symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ReplayPriceSource;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_simulated_price_overrides_until_cleared() {
+        let oracle = Arc::new(ReplayPriceSource::new([("SOL-USD".to_string(), dec!(150))]));
+        let source = SimulatedPriceSource::new(oracle);
+
+        source.inject("SOL-USD", dec!(90), chrono::Duration::minutes(5)).await;
+        assert_eq!(source.fetch_price("SOL-USD").await.unwrap(), dec!(90));
+        assert_eq!(source.get_cached_price("SOL-USD").await, Some(dec!(90)));
+        assert_eq!(source.oracle_price("SOL-USD").await, Some(dec!(150)));
+
+        assert!(source.clear("SOL-USD").await);
+        assert!(!source.clear("SOL-USD").await);
+        assert_eq!(source.fetch_price("SOL-USD").await.unwrap(), dec!(150));
+    }
+
+    #[tokio::test]
+    async fn test_simulated_price_expires() {
+        let oracle = Arc::new(ReplayPriceSource::new([("SOL-USD".to_string(), dec!(150))]));
+        let source = SimulatedPriceSource::new(oracle);
+
+        source.inject("SOL-USD", dec!(90), chrono::Duration::zero()).await;
+        assert_eq!(source.simulated("SOL-USD").await, None);
+        assert_eq!(source.fetch_price("SOL-USD").await.unwrap(), dec!(150));
+    }
+}
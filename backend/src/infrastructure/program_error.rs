@@ -0,0 +1,217 @@
+/// Program Error Decoding
+/// Maps failed simulations and RPC errors back to the program's PositionError
+/// names so API callers see `InsufficientCollateral` instead of `0x1773`
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+use std::fmt;
+
+// Anchor numbers #[error_code] variants from 6000
+const ANCHOR_ERROR_CODE_OFFSET: u32 = 6000;
+
+// Variant name and message, in declaration order of PositionError (errors.rs)
+const POSITION_ERRORS: [(&str, &str); 50] = [
+    ("LeverageExceeded", "Leverage exceeds maximum allowed for position size"),
+    ("PositionSizeTooLarge", "Position size exceeds tier limit"),
+    ("InsufficientCollateral", "Insufficient collateral for position"),
+    ("InvalidLeverage", "Leverage must be between 1 and 1000"),
+    ("InvalidPositionSize", "Position size must be greater than 0"),
+    ("MarginRatioTooLow", "Margin ratio too low, position at risk"),
+    ("CannotRemoveMargin", "Cannot remove margin, would cause liquidation"),
+    ("InvalidSymbol", "Invalid symbol"),
+    ("ArithmeticOverflow", "Arithmetic overflow"),
+    ("PositionNotOpen", "Position is not open"),
+    ("Unauthorized", "Unauthorized"),
+    ("PositionNotLiquidatable", "Position is above maintenance margin and cannot be liquidated"),
+    ("LiquidatorNotWhitelisted", "Liquidator is not whitelisted"),
+    ("LiquidatorAlreadyWhitelisted", "Liquidator is already whitelisted"),
+    ("LiquidatorNotFound", "Liquidator not found in whitelist"),
+    ("LiquidatorWhitelistFull", "Liquidator whitelist is full"),
+    ("PartialLiquidationDisabled", "Partial liquidation is disabled"),
+    ("InvalidCloseFraction", "Close fraction must be between 1 and 10000 basis points"),
+    ("InvalidLiquidationBuffer", "Liquidation buffer must be at most 10000 basis points"),
+    ("PartialLiquidationInsufficient", "Partial liquidation leaves the position below maintenance plus buffer"),
+    ("InvalidBackstopDiscount", "Backstop vault discount must be at most 10000 basis points"),
+    ("BackstopInsufficientLiquidity", "Backstop vault lacks free assets for this operation"),
+    ("InsufficientShares", "Not enough vault shares"),
+    ("NothingToClaim", "No vault earnings to claim"),
+    ("NotBackstopPosition", "Position is not held by the backstop vault"),
+    ("TradingPaused", "Trading is paused"),
+    ("PositionIndexMismatch", "Position index does not match the user's position count"),
+    ("MarginCallActive", "Position is under a margin call, only top-ups and size reductions are allowed"),
+    ("MarginCallGracePeriod", "Margin call grace period has not elapsed"),
+    ("MarginCallDisabled", "Margin calls are disabled"),
+    ("MarginCallUnchanged", "Margin call status already matches the margin ratio"),
+    ("InvalidMarginCallBuffer", "Margin call buffer must be at most 10000 basis points"),
+    ("InvalidSlippage", "Default slippage must be at most 2000 basis points"),
+    ("InvalidMarketBounds", "Maintenance margin bounds must be min <= max <= 5000 basis points"),
+    ("MaintenanceMarginOutOfBounds", "Maintenance margin is outside the market's bounds"),
+    ("OpenCooldownActive", "Opened on this market too recently, wait for the cooldown"),
+    ("MarketOpenLimitReached", "Too many opens on this market in this slot"),
+    ("PositionUpToDate", "Position account is already at the current size"),
+    ("UserHasOpenPositions", "User still has open positions"),
+    ("UserHasCollateral", "User still has collateral, withdraw it first"),
+    ("InvalidFeeSchedule", "Taker fees must be at most 100 basis points and not rise with the tier"),
+    ("InvalidFeeTier", "Fee tier is out of range"),
+    ("StaleOracle", "Oracle price is older than the configured maximum age"),
+    ("InvalidLiquidationBatch", "Liquidation batch must hold 1 to 10 position and user account pairs on the market"),
+    ("InvalidOracleAccount", "Oracle account is not a fully verified Pyth price update"),
+    ("OracleFeedMismatch", "Oracle price update is not for this market's feed"),
+    ("InvalidOraclePrice", "Oracle price is not positive or out of range"),
+    ("InvalidMaxPriceAge", "Max oracle price age must be greater than 0"),
+    ("InvalidPositionAccounts", "Position accounts must be every position of the owner, in index order"),
+    ("MaintenanceMarginStepTooLarge", "Maintenance margin moves at most 50 basis points per crank and 150 per hour"),
+];
+
+// Market open throttles, retrying later succeeds
+const RATE_LIMIT_ERRORS: [&str; 2] = ["OpenCooldownActive", "MarketOpenLimitReached"];
+
+/// A program error decoded from a failed transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramError {
+    /// Error name, e.g. "InsufficientCollateral"
+    pub code: String,
+    /// Raw error number, e.g. 6002
+    pub number: u32,
+    pub message: String,
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.code, self.number, self.message)
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+impl ProgramError {
+    /// Look up a PositionError by its variant name, for checks that mirror the program off chain
+    pub fn from_code(code: &str) -> Option<Self> {
+        let index = POSITION_ERRORS.iter().position(|(name, _)| *name == code)?;
+        Self::from_number(ANCHOR_ERROR_CODE_OFFSET + index as u32)
+    }
+
+    /// Look up a PositionError by its custom error number
+    pub fn from_number(number: u32) -> Option<Self> {
+        let index = number.checked_sub(ANCHOR_ERROR_CODE_OFFSET)? as usize;
+        let (code, message) = POSITION_ERRORS.get(index)?;
+
+        Some(Self {
+            code: code.to_string(),
+            number,
+            message: message.to_string(),
+        })
+    }
+
+    /// Whether the program refused the call for coming too soon, not for what it asked
+    pub fn is_rate_limit(&self) -> bool {
+        RATE_LIMIT_ERRORS.contains(&self.code.as_str())
+    }
+
+    /// Decode from simulation output, preferring Anchor's log line since it
+    /// also covers framework errors (constraint failures, missing accounts)
+    pub fn from_simulation(err: &TransactionError, logs: &[String]) -> Option<Self> {
+        logs.iter()
+            .find_map(|line| Self::from_anchor_log(line))
+            .or_else(|| match err {
+                TransactionError::InstructionError(_, InstructionError::Custom(number)) => {
+                    Self::from_number(*number)
+                }
+                _ => None,
+            })
+    }
+
+    /// Decode from an RPC error string containing "custom program error: 0x..."
+    pub fn from_rpc_message(message: &str) -> Option<Self> {
+        let (_, hex) = message.split_once("custom program error: 0x")?;
+        let hex: String = hex.chars().take_while(|c| c.is_ascii_hexdigit()).collect();
+        let number = u32::from_str_radix(&hex, 16).ok()?;
+
+        Self::from_number(number)
+    }
+
+    /// Parse "... Error Code: <name>. Error Number: <n>. Error Message: <msg>."
+    fn from_anchor_log(line: &str) -> Option<Self> {
+        let (_, rest) = line.split_once("Error Code: ")?;
+        let (code, rest) = rest.split_once(". Error Number: ")?;
+        let (number, rest) = rest.split_once(". Error Message: ")?;
+
+        Some(Self {
+            code: code.to_string(),
+            number: number.parse().ok()?,
+            message: rest.trim_end_matches('.').to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_custom_error_number() {
+        let err = TransactionError::InstructionError(0, InstructionError::Custom(6002));
+        let decoded = ProgramError::from_simulation(&err, &[]).unwrap();
+
+        assert_eq!(decoded.code, "InsufficientCollateral");
+        assert_eq!(decoded.number, 6002);
+
+        assert_eq!(ProgramError::from_number(6037).unwrap().code, "PositionUpToDate");
+        assert_eq!(ProgramError::from_number(6039).unwrap().code, "UserHasCollateral");
+        assert_eq!(ProgramError::from_number(6041).unwrap().code, "InvalidFeeTier");
+        assert_eq!(ProgramError::from_number(6042).unwrap().code, "StaleOracle");
+        assert_eq!(ProgramError::from_number(6043).unwrap().code, "InvalidLiquidationBatch");
+        assert_eq!(ProgramError::from_number(6045).unwrap().code, "OracleFeedMismatch");
+        assert_eq!(ProgramError::from_number(6047).unwrap().code, "InvalidMaxPriceAge");
+        assert_eq!(ProgramError::from_number(6048).unwrap().code, "InvalidPositionAccounts");
+        assert_eq!(ProgramError::from_number(6049).unwrap().code, "MaintenanceMarginStepTooLarge");
+        assert!(ProgramError::from_number(6050).is_none());
+        assert!(ProgramError::from_number(42).is_none());
+
+        assert_eq!(ProgramError::from_code("PositionNotOpen").unwrap().number, 6009);
+        assert!(ProgramError::from_code("Nope").is_none());
+
+        assert!(ProgramError::from_number(6035).unwrap().is_rate_limit());
+        assert_eq!(ProgramError::from_number(6036).unwrap().code, "MarketOpenLimitReached");
+        assert!(!ProgramError::from_number(6002).unwrap().is_rate_limit());
+    }
+
+    #[test]
+    fn test_errors_match_program() {
+        // Every #[msg] of errors.rs followed by its variant, in declaration order
+        let source = include_str!("../../../position-management-system/programs/position-management-system/src/errors.rs");
+        let mut declared = Vec::new();
+        let mut message = None;
+        for line in source.lines().map(str::trim) {
+            if let Some(msg) = line.strip_prefix("#[msg(\"").and_then(|rest| rest.strip_suffix("\")]")) {
+                message = Some(msg);
+            } else if let (Some(name), Some(msg)) = (line.strip_suffix(','), message.take()) {
+                declared.push((name, msg));
+            }
+        }
+
+        assert_eq!(declared.len(), POSITION_ERRORS.len());
+        assert_eq!(declared, POSITION_ERRORS.to_vec());
+    }
+
+    #[test]
+    fn test_decode_anchor_log() {
+        let logs = vec![
+            "Program 9bca4kbDn7uyQWQaqfKpe8hCdbBh6KqJFNbkzwHhieC3 invoke [1]".to_string(),
+            "Program log: AnchorError caused by account: position. Error Code: ConstraintSeeds. Error Number: 2006. Error Message: A seeds constraint was violated.".to_string(),
+        ];
+        let err = TransactionError::InstructionError(0, InstructionError::Custom(2006));
+        let decoded = ProgramError::from_simulation(&err, &logs).unwrap();
+
+        assert_eq!(decoded.code, "ConstraintSeeds");
+        assert_eq!(decoded.number, 2006);
+        assert_eq!(decoded.message, "A seeds constraint was violated");
+    }
+
+    #[test]
+    fn test_decode_rpc_message() {
+        let message = "RPC response error -32002: Transaction simulation failed: Error processing Instruction 0: custom program error: 0x1773";
+        let decoded = ProgramError::from_rpc_message(message).unwrap();
+
+        assert_eq!(decoded.code, "InvalidLeverage");
+        assert!(ProgramError::from_rpc_message("blockhash not found").is_none());
+    }
+}
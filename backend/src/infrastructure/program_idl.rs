@@ -0,0 +1,149 @@
+/// Program IDL
+/// Reads the IDL `anchor idl init/upgrade` publishes on chain: a zlib
+/// compressed JSON document behind an `IdlAccount` header, at an address
+/// derived from the program id. Integrators fetch it from here, and the
+/// backend checks its hand-written instruction discriminators against it
+use anchor_lang::idl::IdlAccount;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{anyhow, Context, Result};
+use flate2::read::ZlibDecoder;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::io::Read;
+
+// Discriminator, authority and compressed length
+const IDL_HEADER_LEN: usize = 8 + 32 + 4;
+
+/// An IDL as published on chain
+#[derive(Debug, Clone)]
+pub struct ProgramIdl {
+    pub address: Pubkey,
+    /// Signer allowed to upgrade it
+    pub authority: Pubkey,
+    pub idl: Value,
+}
+
+/// Where `anchor idl init` puts the IDL of `program_id`
+pub fn idl_address(program_id: &Pubkey) -> Pubkey {
+    IdlAccount::address(program_id)
+}
+
+/// Decode the data of the IDL account at `address`
+pub fn decode_idl_account(address: Pubkey, data: &[u8]) -> Result<ProgramIdl> {
+    if data.len() < IDL_HEADER_LEN {
+        return Err(anyhow!("IDL account data too small"));
+    }
+    if data[..8] != IdlAccount::DISCRIMINATOR {
+        return Err(anyhow!("Not an IDL account"));
+    }
+
+    let header = IdlAccount::deserialize(&mut &data[8..IDL_HEADER_LEN]).context("Invalid IDL account header")?;
+    let compressed = data
+        .get(IDL_HEADER_LEN..IDL_HEADER_LEN + header.data_len as usize)
+        .ok_or_else(|| anyhow!("IDL account shorter than its data length"))?;
+
+    let mut json = Vec::new();
+    ZlibDecoder::new(compressed)
+        .read_to_end(&mut json)
+        .context("Failed to decompress IDL")?;
+
+    Ok(ProgramIdl {
+        address,
+        authority: header.authority,
+        idl: serde_json::from_slice(&json).context("IDL is not valid JSON")?,
+    })
+}
+
+impl ProgramIdl {
+    /// Instruction name to discriminator, from the `discriminator` fields of
+    /// an Anchor 0.30+ IDL
+    pub fn instruction_discriminators(&self) -> HashMap<String, [u8; 8]> {
+        instruction_discriminators(&self.idl)
+    }
+
+    /// Instructions of `expected` the IDL lacks or encodes differently
+    pub fn mismatched_instructions(&self, expected: &[(&str, [u8; 8])]) -> Vec<String> {
+        mismatched_instructions(&self.idl, expected)
+    }
+}
+
+/// Instruction name to discriminator of an IDL document
+pub fn instruction_discriminators(idl: &Value) -> HashMap<String, [u8; 8]> {
+    idl.get("instructions")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|instruction| {
+            let name = instruction.get("name")?.as_str()?;
+            let bytes: Vec<u8> = instruction
+                .get("discriminator")?
+                .as_array()?
+                .iter()
+                .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect::<Option<_>>()?;
+
+            Some((name.to_string(), bytes.try_into().ok()?))
+        })
+        .collect()
+}
+
+/// Instructions of `expected` that `idl` lacks or encodes differently
+pub fn mismatched_instructions(idl: &Value, expected: &[(&str, [u8; 8])]) -> Vec<String> {
+    let published = instruction_discriminators(idl);
+
+    expected
+        .iter()
+        .filter(|(name, discriminator)| published.get(*name) != Some(discriminator))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AnchorSerialize;
+    use flate2::{write::ZlibEncoder, Compression};
+    use serde_json::json;
+    use std::io::Write;
+
+    fn idl_account_data(authority: Pubkey, idl: &Value) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(idl.to_string().as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut data = IdlAccount::DISCRIMINATOR.to_vec();
+        authority.serialize(&mut data).unwrap();
+        (compressed.len() as u32).serialize(&mut data).unwrap();
+        data.extend(compressed);
+        // Accounts are allocated with room to grow
+        data.extend([0; 64]);
+        data
+    }
+
+    #[test]
+    fn test_decode_idl_account() {
+        let authority = Pubkey::new_unique();
+        let idl = json!({
+            "address": "9bca4kbDn7uyQWQaqfKpe8hCdbBh6KqJFNbkzwHhieC3",
+            "instructions": [
+                { "name": "open_position", "discriminator": [135, 128, 47, 77, 15, 152, 240, 49] },
+                { "name": "close_position", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] }
+            ]
+        });
+
+        let address = Pubkey::new_unique();
+        let decoded = decode_idl_account(address, &idl_account_data(authority, &idl)).unwrap();
+        assert_eq!(decoded.authority, authority);
+        assert_eq!(decoded.idl, idl);
+
+        let expected = [
+            ("open_position", [135, 128, 47, 77, 15, 152, 240, 49]),
+            ("close_position", [123, 134, 81, 0, 49, 68, 98, 98]),
+            ("add_collateral", [127, 82, 121, 42, 161, 176, 249, 206]),
+        ];
+        assert_eq!(decoded.mismatched_instructions(&expected), vec!["close_position", "add_collateral"]);
+
+        assert!(decode_idl_account(address, &[0; 64]).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+/// Request Context
+/// Correlation id of the API request being served, set by the HTTP middleware
+/// for the lifetime of the handler future so lower layers can tag their work
+use std::future::Future;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Run `future` with `request_id` as the current correlation id
+pub async fn with_request_id<F: Future>(request_id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(request_id, future).await
+}
+
+/// Correlation id of the request being served, none in background tasks
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
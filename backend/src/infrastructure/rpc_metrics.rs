@@ -0,0 +1,47 @@
+/// RPC Metrics
+/// Per-method call counters for the monitor's RPC traffic, so operators can
+/// see what the refresh loop costs in calls and bytes
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RpcCallStats {
+    pub calls: u64,
+    pub errors: u64,
+    /// Accounts or signatures returned
+    pub items: u64,
+    /// Account data bytes returned, before base64
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct RpcMetrics {
+    methods: Mutex<BTreeMap<&'static str, RpcCallStats>>,
+}
+
+impl RpcMetrics {
+    pub fn record(&self, method: &'static str, items: usize, bytes: usize) {
+        let mut methods = self.methods.lock().unwrap();
+        let stats = methods.entry(method).or_default();
+        stats.calls += 1;
+        stats.items += items as u64;
+        stats.bytes += bytes as u64;
+    }
+
+    pub fn record_error(&self, method: &'static str) {
+        let mut methods = self.methods.lock().unwrap();
+        let stats = methods.entry(method).or_default();
+        stats.calls += 1;
+        stats.errors += 1;
+    }
+
+    /// Counters since startup, by method
+    pub fn snapshot(&self) -> Vec<(&'static str, RpcCallStats)> {
+        self.methods
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(method, stats)| (*method, *stats))
+            .collect()
+    }
+}
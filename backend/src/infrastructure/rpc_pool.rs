@@ -0,0 +1,288 @@
+/// RPC Pool
+/// Several RPC endpoints of the same cluster, each scored on recent latency
+/// and error rate. Reads go to the healthiest endpoint, writes stay on the
+/// first configured endpoint that is up, and both fail over to the next one
+/// when a node is unreachable or reports itself unhealthy
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY;
+use solana_client::rpc_request::RpcError;
+use anyhow::{anyhow, Result};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// Weight of the newest sample in the latency and error averages
+const EWMA_ALPHA: f64 = 0.2;
+// Transport failures in a row that take an endpoint out of rotation
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+// How long a failed endpoint is skipped before it is tried again
+const FAILURE_COOLDOWN: Duration = Duration::from_secs(30);
+// Latency multiplier per unit of error rate, so a fast but flaky node loses to a slower steady one
+const ERROR_RATE_PENALTY: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcRoute {
+    /// Healthiest endpoint first
+    Read,
+    /// Configured order, the primary unless it is down
+    Write,
+}
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    /// None until the first response
+    latency_ms: Option<f64>,
+    error_rate: f64,
+    consecutive_failures: u32,
+    down_until: Option<Instant>,
+    calls: u64,
+    errors: u64,
+}
+
+impl EndpointHealth {
+    fn record_success(&mut self, latency: Duration) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        self.latency_ms = Some(match self.latency_ms {
+            Some(average) => average + EWMA_ALPHA * (latency_ms - average),
+            None => latency_ms,
+        });
+        self.error_rate -= EWMA_ALPHA * self.error_rate;
+        self.consecutive_failures = 0;
+        self.down_until = None;
+        self.calls += 1;
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.error_rate += EWMA_ALPHA * (1.0 - self.error_rate);
+        self.consecutive_failures += 1;
+        self.calls += 1;
+        self.errors += 1;
+
+        if self.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+            self.down_until = Some(now + FAILURE_COOLDOWN);
+        }
+    }
+
+    fn is_up(&self, now: Instant) -> bool {
+        self.down_until.is_none_or(|until| now >= until)
+    }
+
+    /// Lower is better, unmeasured endpoints score best so they get probed
+    fn score(&self) -> f64 {
+        self.latency_ms.unwrap_or(0.0) * (1.0 + ERROR_RATE_PENALTY * self.error_rate)
+    }
+}
+
+struct RpcEndpoint {
+    url: String,
+    client: RpcClient,
+    health: Mutex<EndpointHealth>,
+}
+
+/// One endpoint's health, for metrics and the readiness probe
+#[derive(Debug, Clone)]
+pub struct RpcEndpointHealth {
+    pub url: String,
+    pub healthy: bool,
+    pub latency_ms: Option<f64>,
+    pub error_rate: f64,
+    pub consecutive_failures: u32,
+    pub calls: u64,
+    pub errors: u64,
+}
+
+pub struct RpcPool {
+    endpoints: Vec<RpcEndpoint>,
+}
+
+impl RpcPool {
+    /// The first URL is the primary, the one writes prefer
+    pub fn new(urls: Vec<String>) -> Result<Self> {
+        if urls.is_empty() {
+            return Err(anyhow!("RPC pool needs at least one endpoint"));
+        }
+
+        let endpoints = urls
+            .into_iter()
+            .map(|url| RpcEndpoint {
+                client: RpcClient::new(url.clone()),
+                url,
+                health: Mutex::new(EndpointHealth::default()),
+            })
+            .collect();
+
+        Ok(Self { endpoints })
+    }
+
+    /// Run a call against the endpoint `route` picks, failing over on
+    /// transport errors. Errors the node answered with are returned as is
+    /// A write that failed over may have reached the first node already, which
+    /// is harmless for signed transactions since the signature dedupes them
+    pub fn call<T>(
+        &self,
+        route: RpcRoute,
+        mut f: impl FnMut(&RpcClient) -> Result<T, ClientError>,
+    ) -> Result<T, ClientError> {
+        let order = self.order(route, Instant::now());
+        let mut last_error = None;
+
+        for index in order {
+            let endpoint = &self.endpoints[index];
+            let started = Instant::now();
+
+            match f(&endpoint.client) {
+                Err(e) if is_transport_error(&e) => {
+                    warn!("RPC endpoint {} failed: {}", endpoint.url, e);
+                    endpoint.health.lock().unwrap().record_failure(Instant::now());
+                    last_error = Some(e);
+                }
+                result => {
+                    endpoint.health.lock().unwrap().record_success(started.elapsed());
+                    return result;
+                }
+            }
+        }
+
+        Err(last_error.expect("pool has at least one endpoint"))
+    }
+
+    /// Shorthand for `call(RpcRoute::Read, f)`
+    pub fn read<T>(&self, f: impl FnMut(&RpcClient) -> Result<T, ClientError>) -> Result<T, ClientError> {
+        self.call(RpcRoute::Read, f)
+    }
+
+    /// Shorthand for `call(RpcRoute::Write, f)`
+    pub fn write<T>(&self, f: impl FnMut(&RpcClient) -> Result<T, ClientError>) -> Result<T, ClientError> {
+        self.call(RpcRoute::Write, f)
+    }
+
+    /// Endpoint indexes in the order a call tries them. Endpoints that are
+    /// down go last rather than being skipped, so an outage of every node
+    /// still retries them all
+    fn order(&self, route: RpcRoute, now: Instant) -> Vec<usize> {
+        let health: Vec<(bool, f64)> = self
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = endpoint.health.lock().unwrap();
+                (health.is_up(now), health.score())
+            })
+            .collect();
+
+        let mut order: Vec<usize> = (0..self.endpoints.len()).collect();
+        // Stable, so ties keep the configured order
+        match route {
+            RpcRoute::Read => order.sort_by(|a, b| {
+                health[*b]
+                    .0
+                    .cmp(&health[*a].0)
+                    .then(health[*a].1.total_cmp(&health[*b].1))
+            }),
+            RpcRoute::Write => order.sort_by(|a, b| health[*b].0.cmp(&health[*a].0)),
+        }
+
+        order
+    }
+
+    /// Whether any endpoint is in rotation
+    pub fn is_healthy(&self) -> bool {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .any(|endpoint| endpoint.health.lock().unwrap().is_up(now))
+    }
+
+    /// Health of every endpoint, in configured order
+    pub fn snapshot(&self) -> Vec<RpcEndpointHealth> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = endpoint.health.lock().unwrap();
+                RpcEndpointHealth {
+                    url: endpoint.url.clone(),
+                    healthy: health.is_up(now),
+                    latency_ms: health.latency_ms,
+                    error_rate: health.error_rate,
+                    consecutive_failures: health.consecutive_failures,
+                    calls: health.calls,
+                    errors: health.errors,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Failures that say nothing about the request, only about the node
+fn is_transport_error(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => true,
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> RpcPool {
+        RpcPool::new(vec![
+            "http://primary:8899".to_string(),
+            "http://fallback-a:8899".to_string(),
+            "http://fallback-b:8899".to_string(),
+        ])
+        .unwrap()
+    }
+
+    fn health(pool: &RpcPool, index: usize) -> std::sync::MutexGuard<'_, EndpointHealth> {
+        pool.endpoints[index].health.lock().unwrap()
+    }
+
+    #[test]
+    fn test_reads_prefer_healthiest_endpoint() {
+        let pool = pool();
+        health(&pool, 0).record_success(Duration::from_millis(200));
+        health(&pool, 1).record_success(Duration::from_millis(50));
+        health(&pool, 2).record_success(Duration::from_millis(80));
+
+        let now = Instant::now();
+        assert_eq!(pool.order(RpcRoute::Read, now), vec![1, 2, 0]);
+        // Writes keep the primary while it is up
+        assert_eq!(pool.order(RpcRoute::Write, now), vec![0, 1, 2]);
+
+        // A couple of errors outweigh the latency edge, without taking it out of rotation
+        health(&pool, 1).record_failure(now);
+        health(&pool, 1).record_failure(now);
+        assert_eq!(pool.order(RpcRoute::Read, now), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn test_failed_endpoint_rotates_out_until_cooldown() {
+        let pool = pool();
+        let now = Instant::now();
+
+        for _ in 0..MAX_CONSECUTIVE_FAILURES {
+            health(&pool, 0).record_failure(now);
+        }
+
+        assert_eq!(pool.order(RpcRoute::Write, now), vec![1, 2, 0]);
+        assert!(!pool.snapshot()[0].healthy);
+        assert!(pool.is_healthy());
+
+        // Back in rotation once the cooldown passes
+        assert_eq!(pool.order(RpcRoute::Write, now + FAILURE_COOLDOWN), vec![0, 1, 2]);
+
+        health(&pool, 0).record_success(Duration::from_millis(10));
+        assert!(pool.snapshot()[0].healthy);
+    }
+
+    #[test]
+    fn test_empty_pool_rejected() {
+        assert!(RpcPool::new(Vec::new()).is_err());
+    }
+}
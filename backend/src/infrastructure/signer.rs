@@ -0,0 +1,469 @@
+/// Transaction signer abstraction
+/// Lets the service sign with a plain private key (dev), an encrypted
+/// keystore file, or a remote signer (HTTP signing service / Vault transit)
+/// so production keys never have to live in .env
+use aes_gcm_siv::aead::{Aead, NewAead};
+use aes_gcm_siv::{Aes256GcmSiv, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::Hmac;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::{Signer, SignerError},
+};
+use std::str::FromStr;
+use std::sync::{mpsc as std_mpsc, Arc};
+use tokio::sync::mpsc;
+
+const KEYSTORE_VERSION: u8 = 1;
+const KEYSTORE_PBKDF2_ROUNDS: u32 = 600_000;
+
+/// Shared handle to whichever signer was configured
+#[derive(Clone)]
+pub struct SharedSigner(Arc<dyn Signer + Send + Sync>);
+
+impl SharedSigner {
+    pub fn new(signer: Arc<dyn Signer + Send + Sync>) -> Self {
+        Self(signer)
+    }
+}
+
+impl Signer for SharedSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        self.0.try_pubkey()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.0.try_sign_message(message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.0.is_interactive()
+    }
+}
+
+impl From<Arc<Keypair>> for SharedSigner {
+    fn from(keypair: Arc<Keypair>) -> Self {
+        Self(keypair)
+    }
+}
+
+impl From<Keypair> for SharedSigner {
+    fn from(keypair: Keypair) -> Self {
+        Self(Arc::new(keypair))
+    }
+}
+
+impl From<RemoteSigner> for SharedSigner {
+    fn from(signer: RemoteSigner) -> Self {
+        Self(Arc::new(signer))
+    }
+}
+
+/// Which signer backs the service wallet
+#[derive(Debug, Clone)]
+pub enum SignerConfig {
+    /// Base58 private key (development only)
+    PrivateKey { private_key: String },
+    /// Passphrase-encrypted keystore file
+    Keystore { path: String, passphrase: String },
+    /// Remote signing service, key never leaves the signer
+    Remote {
+        kind: RemoteSignerKind,
+        url: String,
+        pubkey: Pubkey,
+        auth_token: Option<String>,
+    },
+}
+
+impl SignerConfig {
+    /// Load signer configuration from environment variables
+    /// SIGNER_TYPE: "private_key" (default), "keystore" or "remote"
+    pub fn from_env() -> Result<Self> {
+        let signer_type = std::env::var("SIGNER_TYPE").unwrap_or_else(|_| "private_key".to_string());
+
+        match signer_type.as_str() {
+            "private_key" => Ok(SignerConfig::PrivateKey {
+                private_key: env_var("SOLANA_PRIVATE_KEY")?,
+            }),
+            "keystore" => Ok(SignerConfig::Keystore {
+                path: env_var("SIGNER_KEYSTORE_PATH")?,
+                passphrase: read_secret_file(&env_var("SIGNER_KEYSTORE_PASSPHRASE_FILE")?)?,
+            }),
+            "remote" => Ok(SignerConfig::Remote {
+                kind: std::env::var("SIGNER_REMOTE_KIND")
+                    .unwrap_or_else(|_| "http".to_string())
+                    .parse()?,
+                url: env_var("SIGNER_REMOTE_URL")?,
+                pubkey: env_var("SIGNER_REMOTE_PUBKEY")?
+                    .parse()
+                    .context("Invalid SIGNER_REMOTE_PUBKEY")?,
+                auth_token: std::env::var("SIGNER_REMOTE_TOKEN").ok(),
+            }),
+            other => Err(anyhow!("Unknown SIGNER_TYPE: {}", other)),
+        }
+    }
+
+    /// Build the configured signer
+    pub fn load(&self) -> Result<SharedSigner> {
+        match self {
+            SignerConfig::PrivateKey { private_key } => {
+                let keypair = Keypair::from_base58_string(private_key);
+                Ok(keypair.into())
+            }
+            SignerConfig::Keystore { path, passphrase } => {
+                let keystore = EncryptedKeystore::read(path)?;
+                Ok(keystore.decrypt(passphrase)?.into())
+            }
+            SignerConfig::Remote {
+                kind,
+                url,
+                pubkey,
+                auth_token,
+            } => Ok(RemoteSigner::new(*kind, url.clone(), *pubkey, auth_token.clone())?.into()),
+        }
+    }
+}
+
+fn env_var(name: &str) -> Result<String> {
+    std::env::var(name).map_err(|_| anyhow!("{} not set in .env", name))
+}
+
+/// A secret mounted as a file (Docker/Kubernetes secret), trailing newline dropped
+pub fn read_secret_file(path: &str) -> Result<String> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read secret file {}", path))?;
+    let secret = contents.trim_end_matches(['\r', '\n']);
+
+    if secret.is_empty() {
+        return Err(anyhow!("Secret file {} is empty", path));
+    }
+
+    Ok(secret.to_string())
+}
+
+/// Passphrase-encrypted keypair file
+/// Key derivation: PBKDF2-HMAC-SHA256, encryption: AES-256-GCM-SIV
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptedKeystore {
+    pub version: u8,
+    pub pubkey: String,
+    pub iterations: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl EncryptedKeystore {
+    /// Encrypt a keypair with a passphrase
+    pub fn encrypt(keypair: &Keypair, passphrase: &str) -> Result<Self> {
+        Self::encrypt_with_iterations(keypair, passphrase, KEYSTORE_PBKDF2_ROUNDS)
+    }
+
+    fn encrypt_with_iterations(keypair: &Keypair, passphrase: &str, iterations: u32) -> Result<Self> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let cipher = keystore_cipher(passphrase, &salt, iterations)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), keypair.to_bytes().as_ref())
+            .map_err(|_| anyhow!("Failed to encrypt keypair"))?;
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            pubkey: keypair.pubkey().to_string(),
+            iterations,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    /// Decrypt the keypair, failing on a wrong passphrase or tampered file
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(anyhow!("Unsupported keystore version: {}", self.version));
+        }
+
+        let salt = BASE64.decode(&self.salt).context("Invalid keystore salt")?;
+        let nonce = BASE64.decode(&self.nonce).context("Invalid keystore nonce")?;
+        let ciphertext = BASE64
+            .decode(&self.ciphertext)
+            .context("Invalid keystore ciphertext")?;
+
+        if nonce.len() != 12 {
+            return Err(anyhow!("Invalid keystore nonce length"));
+        }
+
+        let cipher = keystore_cipher(passphrase, &salt, self.iterations)?;
+        let key_bytes = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| anyhow!("Failed to decrypt keystore (wrong passphrase?)"))?;
+
+        let keypair = Keypair::from_bytes(&key_bytes)
+            .map_err(|e| anyhow!("Invalid keypair in keystore: {}", e))?;
+
+        if keypair.pubkey().to_string() != self.pubkey {
+            return Err(anyhow!("Keystore pubkey does not match decrypted keypair"));
+        }
+
+        Ok(keypair)
+    }
+
+    pub fn read(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read keystore {}", path))?;
+        serde_json::from_str(&contents).context("Failed to parse keystore")
+    }
+
+    pub fn write(&self, path: &str) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents).with_context(|| format!("Failed to write keystore {}", path))
+    }
+}
+
+fn keystore_cipher(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Aes256GcmSiv> {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, iterations, &mut key);
+
+    Aes256GcmSiv::new_from_slice(&key).map_err(|_| anyhow!("Invalid keystore key length"))
+}
+
+/// Remote signer protocol
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemoteSignerKind {
+    /// POST {url} {"pubkey", "message": base64} -> {"signature": base58}
+    Http,
+    /// Vault transit engine: POST {url} {"input": base64} -> {"data": {"signature": "vault:v1:<base64>"}}
+    VaultTransit,
+}
+
+impl FromStr for RemoteSignerKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "http" => Ok(RemoteSignerKind::Http),
+            "vault" => Ok(RemoteSignerKind::VaultTransit),
+            other => Err(anyhow!("Unknown remote signer kind: {}", other)),
+        }
+    }
+}
+
+/// HTTP side of the remote signer, runs on the signer thread's runtime
+struct RemoteSignerClient {
+    kind: RemoteSignerKind,
+    url: String,
+    pubkey: Pubkey,
+    auth_token: Option<String>,
+    http_client: reqwest::Client,
+}
+
+impl RemoteSignerClient {
+    async fn sign_remote(&self, message: &[u8]) -> Result<Signature> {
+        let encoded = BASE64.encode(message);
+
+        let request = match self.kind {
+            RemoteSignerKind::Http => {
+                let request = self.http_client.post(&self.url).json(&serde_json::json!({
+                    "pubkey": self.pubkey.to_string(),
+                    "message": encoded,
+                }));
+                match &self.auth_token {
+                    Some(token) => request.bearer_auth(token),
+                    None => request,
+                }
+            }
+            RemoteSignerKind::VaultTransit => {
+                let request = self
+                    .http_client
+                    .post(&self.url)
+                    .json(&serde_json::json!({ "input": encoded }));
+                match &self.auth_token {
+                    Some(token) => request.header("X-Vault-Token", token),
+                    None => request,
+                }
+            }
+        };
+
+        let response: serde_json::Value = request
+            .send()
+            .await
+            .context("Failed to reach remote signer")?
+            .error_for_status()
+            .context("Remote signer rejected request")?
+            .json()
+            .await
+            .context("Failed to parse remote signer response")?;
+
+        let signature = match self.kind {
+            RemoteSignerKind::Http => response
+                .get("signature")
+                .and_then(|s| s.as_str())
+                .ok_or_else(|| anyhow!("Missing signature field"))?
+                .parse::<Signature>()
+                .context("Invalid signature from remote signer")?,
+            RemoteSignerKind::VaultTransit => {
+                let vault_signature = response
+                    .pointer("/data/signature")
+                    .and_then(|s| s.as_str())
+                    .ok_or_else(|| anyhow!("Missing data.signature field"))?;
+                let encoded = vault_signature
+                    .rsplit(':')
+                    .next()
+                    .ok_or_else(|| anyhow!("Invalid Vault signature format"))?;
+                let bytes = BASE64.decode(encoded).context("Invalid Vault signature encoding")?;
+                Signature::try_from(bytes.as_slice())
+                    .map_err(|_| anyhow!("Invalid Vault signature length"))?
+            }
+        };
+
+        // Catch a misconfigured signer before the transaction hits the cluster
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(anyhow!("Remote signature does not verify for {}", self.pubkey));
+        }
+
+        Ok(signature)
+    }
+}
+
+struct SignRequest {
+    message: Vec<u8>,
+    reply: std_mpsc::SyncSender<Result<Signature>>,
+}
+
+/// `Signer` is a sync trait, so requests go to a dedicated thread with its own
+/// runtime. Works from any caller, including a current-thread runtime
+pub struct RemoteSigner {
+    pubkey: Pubkey,
+    requests: mpsc::UnboundedSender<SignRequest>,
+}
+
+impl RemoteSigner {
+    pub fn new(
+        kind: RemoteSignerKind,
+        url: String,
+        pubkey: Pubkey,
+        auth_token: Option<String>,
+    ) -> Result<Self> {
+        let client = Arc::new(RemoteSignerClient {
+            kind,
+            url,
+            pubkey,
+            auth_token,
+            http_client: reqwest::Client::new(),
+        });
+        let (requests, mut pending) = mpsc::unbounded_channel::<SignRequest>();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to build remote signer runtime")?;
+
+        // Stops once every handle to the signer is dropped
+        std::thread::Builder::new()
+            .name("remote-signer".to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    while let Some(request) = pending.recv().await {
+                        let client = client.clone();
+                        tokio::spawn(async move {
+                            let _ = request.reply.send(client.sign_remote(&request.message).await);
+                        });
+                    }
+                })
+            })
+            .context("Failed to start remote signer thread")?;
+
+        Ok(Self { pubkey, requests })
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let (reply, response) = std_mpsc::sync_channel(1);
+        self.requests
+            .send(SignRequest {
+                message: message.to_vec(),
+                reply,
+            })
+            .map_err(|_| SignerError::Custom("Remote signer thread stopped".to_string()))?;
+
+        response
+            .recv()
+            .map_err(|_| SignerError::Custom("Remote signer thread stopped".to_string()))?
+            .map_err(|e| SignerError::Custom(e.to_string()))
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let keypair = Keypair::new();
+        let keystore = EncryptedKeystore::encrypt_with_iterations(&keypair, "correct horse", 1_000).unwrap();
+
+        let decrypted = keystore.decrypt("correct horse").unwrap();
+        assert_eq!(decrypted.pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn test_keystore_wrong_passphrase() {
+        let keypair = Keypair::new();
+        let keystore = EncryptedKeystore::encrypt_with_iterations(&keypair, "correct horse", 1_000).unwrap();
+
+        assert!(keystore.decrypt("battery staple").is_err());
+    }
+
+    #[test]
+    fn test_shared_signer_signs_like_keypair() {
+        let keypair = Keypair::new();
+        let expected = keypair.sign_message(b"message");
+        let pubkey = keypair.pubkey();
+
+        let signer: SharedSigner = keypair.into();
+        assert_eq!(signer.pubkey(), pubkey);
+        assert_eq!(signer.sign_message(b"message"), expected);
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_on_current_thread_runtime() {
+        // Nothing listens on port 1, the error must come back instead of a panic
+        let signer = RemoteSigner::new(
+            RemoteSignerKind::Http,
+            "http://127.0.0.1:1/sign".to_string(),
+            Pubkey::new_unique(),
+            None,
+        )
+        .unwrap();
+
+        assert!(signer.try_sign_message(b"message").is_err());
+    }
+
+    #[test]
+    fn test_read_secret_file_trims_newline() {
+        let path = std::env::temp_dir().join(format!("signer-secret-{}", Pubkey::new_unique()));
+        std::fs::write(&path, "correct horse\n").unwrap();
+
+        let secret = read_secret_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(secret, "correct horse");
+    }
+}
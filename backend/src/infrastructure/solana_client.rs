@@ -1,31 +1,140 @@
-use solana_client::rpc_client::RpcClient;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+use solana_client::rpc_client::SerializableTransaction;
 use solana_sdk::{
-    instruction::Instruction, pubkey::Pubkey, signature::{Keypair, Signer}, transaction::Transaction
+    address_lookup_table::{self, state::AddressLookupTable, AddressLookupTableAccount},
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signer,
+    system_instruction,
+    transaction::{Transaction, VersionedTransaction},
 };
 use solana_sdk::signature::Signature;
-use anyhow::Result;
-use std::sync::Arc;
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::error::Error;
+use crate::infrastructure::{current_request_id, ProgramError, RpcPool, SharedSigner};
+
+// SPL token and associated token programs, for the backstop vault share mint
+const TOKEN_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+// Genesis hash of mainnet-beta, how devnet-only features tell they're on mainnet
+const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+
+// Max addresses per extend instruction that still fit in a legacy transaction
+const LOOKUP_TABLE_EXTEND_CHUNK: usize = 20;
+
+// Jito tip accounts, one is picked at random per bundle to spread write locks
+const JITO_TIP_ACCOUNTS: [&str; 8] = [
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+];
+
+const JITO_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+// How long an accepted bundle gets to land before its transaction goes over RPC
+const JITO_LANDING_TIMEOUT: Duration = Duration::from_secs(20);
+const JITO_STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+const MEMO_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// Jito block-engine settings for bundle submission
+#[derive(Debug, Clone)]
+pub struct JitoConfig {
+    /// e.g. https://mainnet.block-engine.jito.wtf
+    pub block_engine_url: String,
+    pub tip_lamports: u64,
+}
 
 pub struct SolanaClient {
     pub program_id: Pubkey,
-    pub payer: Arc<Keypair>,
+    pub payer: SharedSigner,
+    /// Primary endpoint, also the base of the account stream's WebSocket URL
     pub rpc_url: String,
+    rpc_pool: Arc<RpcPool>,
+    /// Address lookup tables fetched from chain, keyed by table address
+    lookup_tables: RwLock<HashMap<Pubkey, AddressLookupTableAccount>>,
+    jito: Option<JitoConfig>,
+    http_client: reqwest::Client,
+    /// Append a memo with the API request id to transactions sent while serving a request
+    request_id_memo: bool,
 }
 
 impl SolanaClient {
     pub fn new(
         program_id: Pubkey,
-        payer: Arc<Keypair>,
+        payer: impl Into<SharedSigner>,
         rpc_url: String,
     ) -> Self {
         Self {
             program_id,
-            payer,
+            payer: payer.into(),
+            rpc_pool: Arc::new(RpcPool::new(vec![rpc_url.clone()]).expect("one endpoint")),
             rpc_url,
+            lookup_tables: RwLock::new(HashMap::new()),
+            jito: None,
+            http_client: reqwest::Client::new(),
+            request_id_memo: false,
         }
     }
+
+    /// Submit latency-sensitive transactions as Jito bundles
+    pub fn with_jito(mut self, jito: JitoConfig) -> Self {
+        self.jito = Some(jito);
+        self
+    }
+
+    /// Spread reads over `urls` as well and fail writes over to them, in order
+    pub fn with_fallback_urls(mut self, urls: Vec<String>) -> Self {
+        let mut all = vec![self.rpc_url.clone()];
+        all.extend(urls);
+        self.rpc_pool = Arc::new(RpcPool::new(all).expect("one endpoint"));
+        self
+    }
+
+    /// Tag transactions with the request id in a memo instruction, so they can
+    /// be matched to API logs from an explorer
+    pub fn with_request_id_memo(mut self) -> Self {
+        self.request_id_memo = true;
+        self
+    }
     
-    pub fn new_devnet(program_id: Pubkey, payer: Arc<Keypair>) -> Self {
+    /// A client on the same endpoints and settings that signs and pays as `payer`
+    pub fn for_signer(&self, payer: impl Into<SharedSigner>) -> Self {
+        Self {
+            program_id: self.program_id,
+            payer: payer.into(),
+            rpc_url: self.rpc_url.clone(),
+            rpc_pool: Arc::clone(&self.rpc_pool),
+            lookup_tables: RwLock::new(HashMap::new()),
+            jito: self.jito.clone(),
+            http_client: self.http_client.clone(),
+            request_id_memo: self.request_id_memo,
+        }
+    }
+
+    /// Endpoints behind this client, shared with the monitor's scans
+    pub fn rpc_pool(&self) -> &Arc<RpcPool> {
+        &self.rpc_pool
+    }
+
+    pub fn new_devnet(program_id: Pubkey, payer: impl Into<SharedSigner>) -> Self {
         Self::new(
             program_id,
             payer,
@@ -33,7 +142,7 @@ impl SolanaClient {
         )
     }
     
-    pub fn new_mainnet(program_id: Pubkey, payer: Arc<Keypair>) -> Self {
+    pub fn new_mainnet(program_id: Pubkey, payer: impl Into<SharedSigner>) -> Self {
         Self::new(
             program_id,
             payer,
@@ -65,35 +174,491 @@ impl SolanaClient {
         )
     }
     
+    /// Derive global config PDA
+    pub fn derive_config_pda(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"config"], &self.program_id)
+    }
+
+    /// Derive liquidator whitelist PDA
+    pub fn derive_liquidator_whitelist_pda(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"liquidator_whitelist"], &self.program_id)
+    }
+
+    /// Derive backstop vault PDA
+    pub fn derive_backstop_vault_pda(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"backstop_vault"], &self.program_id)
+    }
+
+    /// Derive an LP's backstop vault account PDA
+    pub fn derive_lp_account_pda(&self, owner: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"lp", owner.as_ref()], &self.program_id)
+    }
+
+    /// Derive a user's settings PDA
+    pub fn derive_user_settings_pda(&self, owner: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"user_settings", owner.as_ref()], &self.program_id)
+    }
+
+    /// Derive a user's fee tier PDA
+    pub fn derive_fee_tier_pda(&self, owner: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"fee_tier", owner.as_ref()], &self.program_id)
+    }
+
+    /// Derive a symbol's Market PDA, keyed by the symbol as stored on chain
+    pub fn derive_market_pda(&self, symbol: &str) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"market", symbol.as_bytes()], &self.program_id)
+    }
+
+    /// Derive the PDA holding a user's last open on a symbol, for the market's open cooldown
+    pub fn derive_open_throttle_pda(&self, owner: &Pubkey, symbol: &str) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"open_throttle", owner.as_ref(), symbol.as_bytes()],
+            &self.program_id,
+        )
+    }
+
+    /// Derive the backstop vault share mint PDA
+    pub fn derive_share_mint_pda(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"vault_shares"], &self.program_id)
+    }
+
+    /// Associated token account of `owner` for `mint`
+    pub fn derive_associated_token_address(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+            &ASSOCIATED_TOKEN_PROGRAM_ID,
+        )
+        .0
+    }
+
+    /// SPL token account balance in base units, zero if the account doesn't exist
+    pub fn get_token_balance(&self, token_account: &Pubkey) -> Result<u64> {
+        let data = match self.get_account_data(token_account, "Token account") {
+            Ok(data) => data,
+            Err(e) if matches!(e.downcast_ref::<Error>(), Some(Error::NotFound(_))) => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        // mint (32) + owner (32) + amount (u64 LE)
+        let amount = data
+            .get(64..72)
+            .ok_or_else(|| anyhow!("Token account {} too short", token_account))?;
+        Ok(u64::from_le_bytes(amount.try_into()?))
+    }
+
     /// Get payer pubkey
     pub fn payer_pubkey(&self) -> Pubkey {
         self.payer.pubkey()
     }
+    /// Request `lamports` for `to` from the cluster's faucet and wait until they
+    /// land. Only devnet, testnet and local validators have one
+    pub fn request_airdrop(&self, to: &Pubkey, lamports: u64) -> Result<Signature> {
+        let signature = self
+            .rpc_pool
+            .write(|rpc_client| rpc_client.request_airdrop(to, lamports))
+            .context("Airdrop request failed")?;
+
+        self.rpc_pool
+            .read(|rpc_client| rpc_client.poll_for_signature(&signature))
+            .with_context(|| format!("Airdrop {} was not confirmed", signature))?;
+
+        Ok(signature)
+    }
+
+    /// Whether the RPC endpoint serves mainnet-beta, going by its genesis hash
+    pub fn is_mainnet(&self) -> Result<bool> {
+        let genesis_hash = self
+            .rpc_pool
+            .read(|rpc_client| rpc_client.get_genesis_hash())
+            .context("Failed to get genesis hash")?;
+
+        Ok(genesis_hash.to_string() == MAINNET_GENESIS_HASH)
+    }
+
     /// Send transaction to Solana
     pub fn send_transaction(&self, instructions: &[Instruction]) -> Result<Signature> {
-        let rpc_client = RpcClient::new(&self.rpc_url);
-        
+        self.send_transaction_with_fee_payer(instructions, &self.payer.pubkey())
+    }
+
+    /// Send transaction to Solana with an explicit fee payer
+    /// The fee payer must be a key this client can sign for, user-paid
+    /// transactions for other wallets have to be co-signed client side
+    #[tracing::instrument(skip_all, fields(%fee_payer))]
+    pub fn send_transaction_with_fee_payer(
+        &self,
+        instructions: &[Instruction],
+        fee_payer: &Pubkey,
+    ) -> Result<Signature> {
+        let instructions = &self.with_memo(instructions);
+
         // Get recent blockhash
-        let recent_blockhash = rpc_client.get_latest_blockhash()?;
-        
+        let recent_blockhash = self.rpc_pool.read(|rpc_client| rpc_client.get_latest_blockhash())?;
+
         // Create transaction
-        let transaction = Transaction::new_signed_with_payer(
-            instructions,
-            Some(&self.payer.pubkey()),
-            &[&*self.payer],
-            recent_blockhash,
-        );
-        
+        let mut transaction = Transaction::new_with_payer(instructions, Some(fee_payer));
+        transaction
+            .try_sign(&[&self.payer], recent_blockhash)
+            .with_context(|| format!("Cannot sign transaction with fee payer {}", fee_payer))?;
+
+        self.simulate(&transaction)?;
+
         // Send and confirm
-        let signature = rpc_client.send_and_confirm_transaction(&transaction)?;
-        
+        let signature = self
+            .rpc_pool
+            .write(|rpc_client| rpc_client.send_and_confirm_transaction(&transaction))
+            .map_err(|e| decode_client_error(e, transaction.get_signature()))?;
+
         Ok(signature)
     }
+
+    /// Send a v0 transaction resolving accounts through address lookup tables
+    #[tracing::instrument(skip_all, fields(%fee_payer))]
+    pub fn send_versioned_transaction(
+        &self,
+        instructions: &[Instruction],
+        fee_payer: &Pubkey,
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<Signature> {
+        let recent_blockhash = self.rpc_pool.read(|rpc_client| rpc_client.get_latest_blockhash())?;
+        let transaction =
+            self.build_versioned_transaction(instructions, fee_payer, lookup_tables, recent_blockhash)?;
+
+        self.simulate(&transaction)?;
+
+        let signature = self
+            .rpc_pool
+            .write(|rpc_client| rpc_client.send_and_confirm_transaction(&transaction))
+            .map_err(|e| decode_client_error(e, transaction.get_signature()))?;
+
+        Ok(signature)
+    }
+
+    /// Compile and sign a v0 transaction, accounts found in `lookup_tables` are
+    /// referenced by index instead of listed in the message
+    pub fn build_versioned_transaction(
+        &self,
+        instructions: &[Instruction],
+        fee_payer: &Pubkey,
+        lookup_tables: &[AddressLookupTableAccount],
+        recent_blockhash: Hash,
+    ) -> Result<VersionedTransaction> {
+        let instructions = &self.with_memo(instructions);
+
+        let message = v0::Message::try_compile(fee_payer, instructions, lookup_tables, recent_blockhash)
+            .context("Failed to compile v0 message")?;
+
+        VersionedTransaction::try_new(VersionedMessage::V0(message), &[&self.payer])
+            .with_context(|| format!("Cannot sign transaction with fee payer {}", fee_payer))
+    }
+
+    /// Create a new address lookup table owned by the service payer
+    #[tracing::instrument(skip_all)]
+    pub fn create_lookup_table(&self) -> Result<(Pubkey, Signature)> {
+        let recent_slot = self
+            .rpc_pool
+            .read(|rpc_client| rpc_client.get_slot_with_commitment(CommitmentConfig::finalized()))?;
+        let payer = self.payer.pubkey();
+
+        let (instruction, table_address) =
+            address_lookup_table::instruction::create_lookup_table(payer, payer, recent_slot);
+
+        let signature = self.send_transaction(&[instruction])?;
+
+        Ok((table_address, signature))
+    }
+
+    /// Add addresses to a lookup table, skipping the ones it already holds
+    #[tracing::instrument(skip_all, fields(%table_address, addresses = addresses.len()))]
+    pub fn extend_lookup_table(&self, table_address: &Pubkey, addresses: &[Pubkey]) -> Result<Vec<Signature>> {
+        let table = self.get_lookup_table(table_address)?;
+
+        let mut new_addresses: Vec<Pubkey> = Vec::new();
+        for address in addresses {
+            if !table.addresses.contains(address) && !new_addresses.contains(address) {
+                new_addresses.push(*address);
+            }
+        }
+
+        let payer = self.payer.pubkey();
+        let mut signatures = Vec::new();
+
+        for chunk in new_addresses.chunks(LOOKUP_TABLE_EXTEND_CHUNK) {
+            let instruction = address_lookup_table::instruction::extend_lookup_table(
+                *table_address,
+                payer,
+                Some(payer),
+                chunk.to_vec(),
+            );
+            signatures.push(self.send_transaction(&[instruction])?);
+        }
+
+        if !signatures.is_empty() {
+            // Force a refetch so the next batch sees the new entries
+            self.lookup_tables
+                .write()
+                .map_err(|_| anyhow!("Lookup table cache poisoned"))?
+                .remove(table_address);
+        }
+
+        Ok(signatures)
+    }
+
+    /// Fetch raw account data, a missing account is `Error::NotFound`
+    #[tracing::instrument(skip(self, account), fields(%account))]
+    pub fn get_account_data(&self, account: &Pubkey, what: &str) -> Result<Vec<u8>> {
+        let account = self
+            .rpc_pool
+            .read(|rpc_client| rpc_client.get_account_with_commitment(account, rpc_client.commitment()))
+            .map_err(|e| Error::rpc(format!("Failed to fetch {}: {}", what, e)))?
+            .value
+            .ok_or_else(|| Error::NotFound(format!("{} {}", what, account)))?;
+
+        Ok(account.data)
+    }
+
+    /// Get a lookup table, served from cache when possible
+    #[tracing::instrument(skip_all, fields(%table_address))]
+    pub fn get_lookup_table(&self, table_address: &Pubkey) -> Result<AddressLookupTableAccount> {
+        if let Some(table) = self
+            .lookup_tables
+            .read()
+            .map_err(|_| anyhow!("Lookup table cache poisoned"))?
+            .get(table_address)
+        {
+            return Ok(table.clone());
+        }
+
+        let data = self
+            .rpc_pool
+            .read(|rpc_client| rpc_client.get_account_data(table_address))
+            .context("Failed to fetch lookup table")?;
+
+        let table = AddressLookupTable::deserialize(&data)
+            .map_err(|e| anyhow!("Failed to deserialize lookup table: {}", e))?;
+
+        let account = AddressLookupTableAccount {
+            key: *table_address,
+            addresses: table.addresses.to_vec(),
+        };
+
+        self.lookup_tables
+            .write()
+            .map_err(|_| anyhow!("Lookup table cache poisoned"))?
+            .insert(*table_address, account.clone());
+
+        Ok(account)
+    }
+
+    /// Send a transaction through the Jito block engine when configured,
+    /// falling back to normal RPC when the relay is unavailable or the bundle
+    /// doesn't land in time
+    pub async fn send_priority_transaction(&self, instructions: &[Instruction]) -> Result<Signature> {
+        if let Some(jito) = &self.jito {
+            match self.send_bundle(jito, instructions).await {
+                Ok(transaction) => return self.confirm_bundle_transaction(&transaction).await,
+                Err(e) => warn!("Jito bundle submission failed, falling back to RPC: {}", e),
+            }
+        }
+
+        self.send_transaction(instructions)
+    }
+
+    /// Wait for an accepted bundle's transaction to confirm. On timeout the
+    /// same signed transaction goes over RPC, so it can't execute twice
+    #[tracing::instrument(skip_all, fields(signature = %transaction.signatures[0]))]
+    async fn confirm_bundle_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let signature = transaction.signatures[0];
+        let deadline = tokio::time::Instant::now() + JITO_LANDING_TIMEOUT;
+
+        while tokio::time::Instant::now() < deadline {
+            let status = self
+                .rpc_pool
+                .read(|rpc_client| rpc_client.get_signature_statuses(&[signature]))
+                .map(|response| response.value.into_iter().next().flatten());
+
+            match status {
+                Ok(Some(status)) if status.satisfies_commitment(CommitmentConfig::confirmed()) => {
+                    return match status.err {
+                        None => Ok(signature),
+                        Some(err) => Err(Error::Rpc {
+                            message: format!("Bundle transaction failed: {}", err),
+                            signature: Some(signature.to_string()),
+                            logs: Vec::new(),
+                        }
+                        .into()),
+                    };
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to fetch bundle transaction status: {}", e),
+            }
+
+            tokio::time::sleep(JITO_STATUS_POLL_INTERVAL).await;
+        }
+
+        warn!("Jito bundle did not land within {:?}, sending over RPC", JITO_LANDING_TIMEOUT);
+
+        self.rpc_pool
+            .write(|rpc_client| rpc_client.send_and_confirm_transaction(transaction))
+            .map_err(|e| decode_client_error(e, &signature))
+    }
+
+    /// Submit the instructions plus a tip as a single-transaction bundle
+    /// Returns the signed transaction once the bundle is accepted by the relay
+    #[tracing::instrument(skip_all)]
+    async fn send_bundle(&self, jito: &JitoConfig, instructions: &[Instruction]) -> Result<Transaction> {
+        let recent_blockhash = self.rpc_pool.read(|rpc_client| rpc_client.get_latest_blockhash())?;
+
+        let payer = self.payer.pubkey();
+
+        let mut bundle_instructions = self.with_memo(instructions);
+        bundle_instructions.push(jito_tip_instruction(&payer, jito.tip_lamports)?);
+
+        let mut transaction = Transaction::new_with_payer(&bundle_instructions, Some(&payer));
+        transaction.try_sign(&[&self.payer], recent_blockhash)?;
+
+        // The relay skips preflight, so a failing liquidation would only burn the tip
+        self.simulate(&transaction)?;
+
+        let encoded = BASE64.encode(bincode::serialize(&transaction)?);
+
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "sendBundle",
+            "params": [[encoded], { "encoding": "base64" }],
+        });
+
+        let response: serde_json::Value = self
+            .http_client
+            .post(format!("{}/api/v1/bundles", jito.block_engine_url.trim_end_matches('/')))
+            .timeout(JITO_REQUEST_TIMEOUT)
+            .json(&request)
+            .send()
+            .await
+            .context("Jito block engine unreachable")?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("Jito rejected bundle: {}", error));
+        }
+
+        let bundle_id = response
+            .get("result")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Malformed Jito response"))?;
+
+        info!("Jito bundle {} accepted (tip {} lamports)", bundle_id, jito.tip_lamports);
+
+        Ok(transaction)
+    }
+
+    /// Simulate a signed transaction, decoding program errors from the logs
+    #[tracing::instrument(skip_all)]
+    pub fn simulate(&self, transaction: &impl SerializableTransaction) -> Result<()> {
+        let result = self
+            .rpc_pool
+            .read(|rpc_client| rpc_client.simulate_transaction(transaction))
+            .context("Failed to simulate transaction")?
+            .value;
+
+        let Some(err) = result.err else {
+            return Ok(());
+        };
+
+        let logs = result.logs.unwrap_or_default();
+        let signature = Some(transaction.get_signature().to_string());
+
+        match ProgramError::from_simulation(&err, &logs) {
+            Some(program_error) => Err(Error::Program {
+                error: program_error,
+                signature,
+                logs,
+            }
+            .into()),
+            None => Err(Error::Rpc {
+                message: format!("Transaction simulation failed: {}", err),
+                signature,
+                logs,
+            }
+            .into()),
+        }
+    }
+
+    /// Append the request id memo when enabled and serving a request
+    fn with_memo(&self, instructions: &[Instruction]) -> Vec<Instruction> {
+        let mut instructions = instructions.to_vec();
+
+        if self.request_id_memo {
+            if let Some(request_id) = current_request_id() {
+                instructions.push(Instruction::new_with_bytes(
+                    MEMO_PROGRAM_ID,
+                    format!("req:{}", request_id).as_bytes(),
+                    Vec::new(),
+                ));
+            }
+        }
+
+        instructions
+    }
+
+    /// Estimate the fee in lamports for a set of instructions paid by `fee_payer`
+    pub fn estimate_fee(&self, instructions: &[Instruction], fee_payer: &Pubkey) -> Result<u64> {
+        let recent_blockhash = self.rpc_pool.read(|rpc_client| rpc_client.get_latest_blockhash())?;
+        let message = Message::new_with_blockhash(instructions, Some(fee_payer), &recent_blockhash);
+
+        let fee = self
+            .rpc_pool
+            .read(|rpc_client| rpc_client.get_fee_for_message(&message))
+            .context("Failed to estimate transaction fee")?;
+
+        Ok(fee)
+    }
+}
+
+/// Type a send failure, decoding "custom program error: 0x.." into a ProgramError
+/// and keeping the preflight logs when the node returned them
+fn decode_client_error(err: ClientError, signature: &Signature) -> anyhow::Error {
+    let logs = match err.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => result.logs.clone().unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let signature = Some(signature.to_string());
+
+    let error = match ProgramError::from_rpc_message(&err.to_string()) {
+        Some(program_error) => Error::Program {
+            error: program_error,
+            signature,
+            logs,
+        },
+        None => Error::Rpc {
+            message: err.to_string(),
+            signature,
+            logs,
+        },
+    };
+
+    error.into()
+}
+
+/// Transfer `tip_lamports` from `payer` to a random Jito tip account
+fn jito_tip_instruction(payer: &Pubkey, tip_lamports: u64) -> Result<Instruction> {
+    let tip_account: Pubkey = JITO_TIP_ACCOUNTS
+        .choose(&mut rand::thread_rng())
+        .expect("tip account list is not empty")
+        .parse()?;
+
+    Ok(system_instruction::transfer(payer, &tip_account, tip_lamports))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use solana_sdk::signature::Keypair;
     
     #[test]
     fn test_derive_user_pda() {
@@ -140,4 +705,56 @@ mod tests {
         
         assert_eq!(client.rpc_url, "https://api.mainnet-beta.solana.com");
     }
+
+    #[test]
+    fn test_jito_tip_instruction() {
+        let payer = Pubkey::new_unique();
+        let instruction = jito_tip_instruction(&payer, 10_000).unwrap();
+
+        assert_eq!(instruction.program_id, solana_sdk::system_program::ID);
+        assert_eq!(instruction.accounts[0].pubkey, payer);
+        assert!(JITO_TIP_ACCOUNTS
+            .iter()
+            .any(|account| account.parse::<Pubkey>().unwrap() == instruction.accounts[1].pubkey));
+    }
+
+    #[test]
+    fn test_versioned_transaction_uses_lookup_table() {
+        let program_id = Pubkey::new_unique();
+        let payer = Arc::new(Keypair::new());
+        let client = SolanaClient::new_devnet(program_id, payer.clone());
+
+        let tabled: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let instructions: Vec<Instruction> = tabled
+            .iter()
+            .map(|account| {
+                Instruction::new_with_bytes(
+                    program_id,
+                    &[],
+                    vec![
+                        solana_sdk::instruction::AccountMeta::new(*account, false),
+                        solana_sdk::instruction::AccountMeta::new_readonly(payer.pubkey(), true),
+                    ],
+                )
+            })
+            .collect();
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: tabled.clone(),
+        };
+
+        let transaction = client
+            .build_versioned_transaction(&instructions, &payer.pubkey(), std::slice::from_ref(&table), Hash::new_unique())
+            .unwrap();
+
+        let VersionedMessage::V0(message) = &transaction.message else {
+            panic!("expected a v0 message");
+        };
+        // Only the signer and the program stay in the static keys
+        assert_eq!(message.account_keys, vec![payer.pubkey(), program_id]);
+        assert_eq!(message.address_table_lookups.len(), 1);
+        assert_eq!(message.address_table_lookups[0].account_key, table.key);
+        assert_eq!(message.address_table_lookups[0].writable_indexes, vec![0, 1, 2, 3]);
+        assert!(transaction.verify_with_results().iter().all(|verified| *verified));
+    }
 }
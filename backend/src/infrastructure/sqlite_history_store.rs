@@ -0,0 +1,1224 @@
+/// SQLite History Store
+/// Embedded store for single-binary deployments. Decimals are kept as TEXT
+/// since SQLite has no exact numeric type, and PnL buckets are built in Rust
+/// instead of with date_bin
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::Row;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::domain::{PnLSnapshot, Position, RealizedPnL};
+use crate::infrastructure::history_store::{
+    buckets_since, event_kind_from_str, event_kind_to_str, last_per_bucket, side_from_str, side_to_str,
+    status_from_str, status_to_str, superseded_ticks,
+};
+use crate::infrastructure::{
+    ClosedPositionFilter, HistoryStore, PnlPoint, PositionEventRecord, PositionOpenRecord,
+    PriceTick, TableSize, TickScope, VaultSnapshot, HISTORY_TABLES,
+};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS pnl_snapshots (
+    id TEXT PRIMARY KEY,
+    position_account TEXT NOT NULL,
+    unrealized_pnl TEXT NOT NULL,
+    realized_pnl TEXT NOT NULL,
+    funding_accrued TEXT NOT NULL,
+    mark_price TEXT NOT NULL,
+    recorded_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS pnl_snapshots_position_time
+    ON pnl_snapshots (position_account, recorded_at);
+CREATE TABLE IF NOT EXISTS realized_pnl (
+    id TEXT PRIMARY KEY,
+    position_account TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+    size_closed TEXT NOT NULL,
+    entry_price TEXT NOT NULL,
+    close_price TEXT NOT NULL,
+    fee TEXT NOT NULL,
+    funding TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    liquidation INTEGER NOT NULL,
+    signature TEXT NOT NULL,
+    closed_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS realized_pnl_owner_time
+    ON realized_pnl (owner, closed_at);
+CREATE INDEX IF NOT EXISTS realized_pnl_position_time
+    ON realized_pnl (position_account, closed_at);
+CREATE UNIQUE INDEX IF NOT EXISTS realized_pnl_signature_position
+    ON realized_pnl (signature, position_account);
+CREATE TABLE IF NOT EXISTS position_opens (
+    position_account TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+    size TEXT NOT NULL,
+    entry_price TEXT NOT NULL,
+    margin TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    opened_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS position_opens_owner_time
+    ON position_opens (owner, opened_at);
+CREATE TABLE IF NOT EXISTS position_events (
+    id TEXT PRIMARY KEY,
+    position_account TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    size TEXT,
+    margin TEXT,
+    mark_price TEXT,
+    detail TEXT,
+    signature TEXT,
+    recorded_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS position_events_position_time
+    ON position_events (position_account, recorded_at);
+CREATE UNIQUE INDEX IF NOT EXISTS position_events_signature_kind
+    ON position_events (signature, position_account, kind);
+CREATE TABLE IF NOT EXISTS closed_positions (
+    position_account TEXT PRIMARY KEY,
+    position_index INTEGER NOT NULL,
+    owner TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+    status TEXT NOT NULL,
+    size TEXT NOT NULL,
+    entry_price TEXT NOT NULL,
+    mark_price TEXT NOT NULL,
+    margin TEXT NOT NULL,
+    leverage INTEGER NOT NULL,
+    realized_pnl TEXT NOT NULL,
+    funding_accrued TEXT NOT NULL,
+    liquidation_price TEXT NOT NULL,
+    opened_at TEXT NOT NULL,
+    last_update TEXT NOT NULL,
+    closed_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS closed_positions_owner_time
+    ON closed_positions (owner, closed_at);
+CREATE INDEX IF NOT EXISTS closed_positions_closed_at
+    ON closed_positions (closed_at);
+CREATE TABLE IF NOT EXISTS price_ticks (
+    symbol TEXT NOT NULL,
+    price TEXT NOT NULL,
+    conf TEXT,
+    publish_time TEXT,
+    recorded_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS price_ticks_time
+    ON price_ticks (recorded_at);
+CREATE INDEX IF NOT EXISTS price_ticks_symbol_time
+    ON price_ticks (symbol, recorded_at);
+CREATE TABLE IF NOT EXISTS vault_snapshots (
+    total_assets TEXT NOT NULL,
+    total_shares TEXT NOT NULL,
+    locked_margin TEXT NOT NULL,
+    share_price TEXT NOT NULL,
+    recorded_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS vault_snapshots_time
+    ON vault_snapshots (recorded_at);
+"#;
+
+// Columns added after a table was first created, SQLite has no ADD COLUMN IF NOT EXISTS
+const COLUMN_MIGRATIONS: [&str; 2] = [
+    "ALTER TABLE price_ticks ADD COLUMN conf TEXT",
+    "ALTER TABLE price_ticks ADD COLUMN publish_time TEXT",
+];
+
+pub struct SqliteHistoryStore {
+    pool: SqlitePool,
+}
+
+impl SqliteHistoryStore {
+    /// Open (creating if missing) the database at `database_url`, e.g.
+    /// sqlite://perps.db or sqlite::memory:
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(database_url)
+            .context("Invalid SQLite URL")?
+            .create_if_missing(true);
+
+        // Every connection to :memory: gets its own database
+        let max_connections = if database_url.contains(":memory:") { 1 } else { 4 };
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await
+            .context("Failed to open SQLite database")?;
+
+        let store = Self { pool };
+        store.init_schema().await?;
+
+        Ok(store)
+    }
+
+    async fn init_schema(&self) -> Result<()> {
+        for statement in SCHEMA.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            sqlx::query(statement)
+                .execute(&self.pool)
+                .await
+                .context("Failed to initialize history schema")?;
+        }
+
+        for statement in COLUMN_MIGRATIONS {
+            match sqlx::query(statement).execute(&self.pool).await {
+                Ok(_) => {}
+                Err(e) if e.to_string().contains("duplicate column name") => {}
+                Err(e) => return Err(e).context("Failed to migrate history schema"),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn insert_pnl_snapshots(&self, snapshots: &[PnLSnapshot]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for snapshot in snapshots {
+            sqlx::query(
+                "INSERT INTO pnl_snapshots \
+                 (id, position_account, unrealized_pnl, realized_pnl, funding_accrued, mark_price, recorded_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .bind(snapshot.id.to_string())
+            .bind(snapshot.position_account.to_string())
+            .bind(snapshot.unrealized_pnl.to_string())
+            .bind(snapshot.realized_pnl.to_string())
+            .bind(snapshot.funding_accrued.to_string())
+            .bind(snapshot.mark_price.to_string())
+            .bind(snapshot.timestamp)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Last `limit` buckets of PnL for a position, oldest first
+    pub async fn pnl_history(
+        &self,
+        position_account: &Pubkey,
+        interval: Duration,
+        limit: i64,
+    ) -> Result<Vec<PnlPoint>> {
+        let since = buckets_since(Utc::now(), interval, limit)?;
+
+        let rows = sqlx::query(
+            "SELECT * FROM pnl_snapshots \
+             WHERE position_account = ?1 AND recorded_at >= ?2 \
+             ORDER BY recorded_at",
+        )
+        .bind(position_account.to_string())
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query PnL history")?;
+
+        let snapshots = rows
+            .iter()
+            .map(|row| {
+                let recorded_at: DateTime<Utc> = row.try_get("recorded_at")?;
+                let point = PnlPoint {
+                    bucket: recorded_at,
+                    unrealized_pnl: decimal(row, "unrealized_pnl")?,
+                    realized_pnl: decimal(row, "realized_pnl")?,
+                    funding_accrued: decimal(row, "funding_accrued")?,
+                    mark_price: decimal(row, "mark_price")?,
+                };
+                Ok((recorded_at, point))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(last_per_bucket(snapshots, interval))
+    }
+
+    pub async fn insert_realized_pnl(&self, record: &RealizedPnL) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO realized_pnl \
+             (id, position_account, owner, symbol, side, size_closed, entry_price, close_price, \
+              fee, funding, amount, liquidation, signature, closed_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14) \
+             ON CONFLICT (signature, position_account) DO NOTHING",
+        )
+        .bind(record.id.to_string())
+        .bind(record.position_account.to_string())
+        .bind(record.owner.to_string())
+        .bind(&record.symbol)
+        .bind(side_to_str(record.side))
+        .bind(record.size_closed.to_string())
+        .bind(record.entry_price.to_string())
+        .bind(record.close_price.to_string())
+        .bind(record.fee.to_string())
+        .bind(record.funding.to_string())
+        .bind(record.amount.to_string())
+        .bind(record.liquidation)
+        .bind(&record.signature)
+        .bind(record.closed_at)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert realized PnL")?;
+
+        Ok(())
+    }
+
+    /// Realized PnL events for `owner` in [from, to), oldest first
+    pub async fn realized_pnl(
+        &self,
+        owner: &Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<RealizedPnL>> {
+        self.realized_pnl_page(owner, from, to, None, i64::MAX).await
+    }
+
+    /// Up to `limit` realized PnL events for `owner` in [from, to) after the
+    /// (closed_at, id) key `after`, oldest first
+    pub async fn realized_pnl_page(
+        &self,
+        owner: &Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<RealizedPnL>> {
+        let rows = sqlx::query(
+            "SELECT * FROM realized_pnl \
+             WHERE owner = ?1 \
+               AND (?2 IS NULL OR closed_at >= ?2) \
+               AND (?3 IS NULL OR closed_at < ?3) \
+               AND (?4 IS NULL OR (closed_at, id) > (?4, ?5)) \
+             ORDER BY closed_at, id \
+             LIMIT ?6",
+        )
+        .bind(owner.to_string())
+        .bind(from)
+        .bind(to)
+        .bind(after.map(|(closed_at, _)| closed_at))
+        .bind(after.map(|(_, id)| id.to_string()))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query realized PnL")?;
+
+        rows.iter().map(realized_pnl_from_row).collect()
+    }
+
+    pub async fn insert_position_open(&self, position: &Position, signature: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO position_opens \
+             (position_account, owner, symbol, side, size, entry_price, margin, signature, opened_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9) \
+             ON CONFLICT (position_account) DO NOTHING",
+        )
+        .bind(position.position_account.to_string())
+        .bind(position.owner.to_string())
+        .bind(&position.symbol)
+        .bind(side_to_str(position.side))
+        .bind(position.size.to_string())
+        .bind(position.entry_price.to_string())
+        .bind(position.margin.to_string())
+        .bind(signature)
+        .bind(position.opened_at)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert position open")?;
+
+        Ok(())
+    }
+
+    /// Opens for `owner` before `before`, oldest first
+    pub async fn position_opens(
+        &self,
+        owner: &Pubkey,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<PositionOpenRecord>> {
+        let rows = sqlx::query(
+            "SELECT * FROM position_opens WHERE owner = ?1 AND opened_at < ?2 ORDER BY opened_at",
+        )
+        .bind(owner.to_string())
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query position opens")?;
+
+        rows.iter().map(position_open_from_row).collect()
+    }
+
+    /// Opens across all owners in [from, to), oldest first
+    pub async fn position_opens_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<PositionOpenRecord>> {
+        let rows = sqlx::query(
+            "SELECT * FROM position_opens WHERE opened_at >= ?1 AND opened_at < ?2 ORDER BY opened_at",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query position opens")?;
+
+        rows.iter().map(position_open_from_row).collect()
+    }
+
+    /// Realized PnL events across all owners in [from, to), oldest first
+    pub async fn realized_pnl_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<RealizedPnL>> {
+        let rows = sqlx::query(
+            "SELECT * FROM realized_pnl WHERE closed_at >= ?1 AND closed_at < ?2 ORDER BY closed_at",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query realized PnL")?;
+
+        rows.iter().map(realized_pnl_from_row).collect()
+    }
+
+    pub async fn position_open(&self, position_account: &Pubkey) -> Result<Option<PositionOpenRecord>> {
+        let row = sqlx::query("SELECT * FROM position_opens WHERE position_account = ?1")
+            .bind(position_account.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to query position open")?;
+
+        row.as_ref().map(position_open_from_row).transpose()
+    }
+
+    /// Realized PnL events for one position, oldest first
+    pub async fn position_realized_pnl(&self, position_account: &Pubkey) -> Result<Vec<RealizedPnL>> {
+        let rows = sqlx::query(
+            "SELECT * FROM realized_pnl WHERE position_account = ?1 ORDER BY closed_at, id",
+        )
+        .bind(position_account.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query realized PnL")?;
+
+        rows.iter().map(realized_pnl_from_row).collect()
+    }
+
+    pub async fn insert_position_event(&self, event: &PositionEventRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO position_events \
+             (id, position_account, kind, size, margin, mark_price, detail, signature, recorded_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9) \
+             ON CONFLICT (signature, position_account, kind) DO NOTHING",
+        )
+        .bind(event.id.to_string())
+        .bind(event.position_account.to_string())
+        .bind(event_kind_to_str(event.kind))
+        .bind(event.size.map(|size| size.to_string()))
+        .bind(event.margin.map(|margin| margin.to_string()))
+        .bind(event.mark_price.map(|price| price.to_string()))
+        .bind(event.detail.as_deref())
+        .bind(event.signature.as_deref())
+        .bind(event.recorded_at)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert position event")?;
+
+        Ok(())
+    }
+
+    /// Events for one position, oldest first
+    pub async fn position_events(&self, position_account: &Pubkey) -> Result<Vec<PositionEventRecord>> {
+        let rows = sqlx::query(
+            "SELECT * FROM position_events WHERE position_account = ?1 ORDER BY recorded_at, id",
+        )
+        .bind(position_account.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query position events")?;
+
+        rows.iter().map(position_event_from_row).collect()
+    }
+
+    pub async fn insert_price_ticks(&self, ticks: &[PriceTick]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for tick in ticks {
+            sqlx::query(
+                "INSERT INTO price_ticks (symbol, price, conf, publish_time, recorded_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .bind(&tick.symbol)
+            .bind(tick.price.to_string())
+            .bind(tick.conf.map(|conf| conf.to_string()))
+            .bind(tick.publish_time)
+            .bind(tick.recorded_at)
+            .execute(&mut tx)
+            .await
+            .context("Failed to insert price tick")?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Price ticks in [from, to), oldest first
+    pub async fn price_ticks(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<PriceTick>> {
+        let rows = sqlx::query(
+            "SELECT symbol, price, conf, publish_time, recorded_at FROM price_ticks \
+             WHERE recorded_at >= ?1 AND recorded_at < ?2 \
+             ORDER BY recorded_at, symbol",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query price ticks")?;
+
+        rows.iter().map(price_tick_from_row).collect()
+    }
+
+    /// One symbol's price ticks in [from, to), oldest first, at most `limit`
+    pub async fn symbol_price_ticks(
+        &self,
+        symbol: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<PriceTick>> {
+        let rows = sqlx::query(
+            "SELECT symbol, price, conf, publish_time, recorded_at FROM price_ticks \
+             WHERE symbol = ?1 AND recorded_at >= ?2 AND recorded_at < ?3 \
+             ORDER BY recorded_at \
+             LIMIT ?4",
+        )
+        .bind(symbol)
+        .bind(from)
+        .bind(to)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query price ticks")?;
+
+        rows.iter().map(price_tick_from_row).collect()
+    }
+
+    /// Thin ticks in `scope` received before `before` to the last one of each
+    /// symbol in each `bucket`, returns how many were deleted
+    pub async fn compact_price_ticks(
+        &self,
+        scope: &TickScope,
+        before: DateTime<Utc>,
+        bucket: Duration,
+    ) -> Result<u64> {
+        // SQLite has no date_bin, so buckets are worked out here like pnl_history's
+        let rows = sqlx::query(
+            "SELECT rowid, symbol, recorded_at FROM price_ticks \
+             WHERE recorded_at < ?1 \
+             ORDER BY symbol, recorded_at",
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query price ticks to compact")?;
+
+        let mut row_ids = Vec::new();
+        let mut ticks = Vec::new();
+
+        for row in &rows {
+            let symbol: String = row.try_get("symbol")?;
+
+            if scope.covers(&symbol) {
+                row_ids.push(row.try_get::<i64, _>("rowid")?);
+                ticks.push((symbol, row.try_get::<DateTime<Utc>, _>("recorded_at")?));
+            }
+        }
+
+        let superseded = superseded_ticks(&ticks, bucket);
+        let mut tx = self.pool.begin().await?;
+
+        for index in &superseded {
+            sqlx::query("DELETE FROM price_ticks WHERE rowid = ?1")
+                .bind(row_ids[*index])
+                .execute(&mut tx)
+                .await
+                .context("Failed to compact price ticks")?;
+        }
+
+        tx.commit().await?;
+
+        Ok(superseded.len() as u64)
+    }
+
+    /// Delete PnL snapshots recorded before `before`, returns how many
+    pub async fn delete_pnl_snapshots_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM pnl_snapshots WHERE recorded_at < ?1")
+            .bind(before)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete PnL snapshots")?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Row count of each history table, SQLite doesn't report per-table bytes
+    pub async fn table_sizes(&self) -> Result<Vec<TableSize>> {
+        let mut sizes = Vec::with_capacity(HISTORY_TABLES.len());
+
+        for table in HISTORY_TABLES {
+            let row = sqlx::query(&format!("SELECT COUNT(*) AS row_count FROM {}", table))
+                .fetch_one(&self.pool)
+                .await
+                .context("Failed to count history table rows")?;
+
+            sizes.push(TableSize {
+                table: table.to_string(),
+                rows: row.try_get("row_count")?,
+                bytes: None,
+            });
+        }
+
+        Ok(sizes)
+    }
+
+    pub async fn insert_vault_snapshot(&self, snapshot: &VaultSnapshot) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO vault_snapshots \
+             (total_assets, total_shares, locked_margin, share_price, recorded_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(snapshot.total_assets.to_string())
+        .bind(snapshot.total_shares.to_string())
+        .bind(snapshot.locked_margin.to_string())
+        .bind(snapshot.share_price.to_string())
+        .bind(snapshot.recorded_at)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert vault snapshot")?;
+
+        Ok(())
+    }
+
+    /// Vault snapshots in [from, to), oldest first
+    pub async fn vault_snapshots(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<VaultSnapshot>> {
+        let rows = sqlx::query(
+            "SELECT total_assets, total_shares, locked_margin, share_price, recorded_at \
+             FROM vault_snapshots WHERE recorded_at >= ?1 AND recorded_at < ?2 \
+             ORDER BY recorded_at",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query vault snapshots")?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(VaultSnapshot {
+                    total_assets: decimal(row, "total_assets")?,
+                    total_shares: decimal(row, "total_shares")?,
+                    locked_margin: decimal(row, "locked_margin")?,
+                    share_price: decimal(row, "share_price")?,
+                    recorded_at: row.try_get("recorded_at")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Upsert positions evicted from the monitor, in one transaction
+    pub async fn insert_closed_positions(&self, positions: &[Position]) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+
+        for position in positions {
+            sqlx::query(
+                "INSERT INTO closed_positions \
+                 (position_account, position_index, owner, symbol, side, status, size, entry_price, \
+                  mark_price, margin, leverage, realized_pnl, funding_accrued, liquidation_price, \
+                  opened_at, last_update, closed_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17) \
+                 ON CONFLICT (position_account) DO UPDATE SET \
+                 status = excluded.status, size = excluded.size, mark_price = excluded.mark_price, \
+                 margin = excluded.margin, realized_pnl = excluded.realized_pnl, \
+                 funding_accrued = excluded.funding_accrued, last_update = excluded.last_update, \
+                 closed_at = excluded.closed_at",
+            )
+            .bind(position.position_account.to_string())
+            .bind(position.position_index as i64)
+            .bind(position.owner.to_string())
+            .bind(&position.symbol)
+            .bind(side_to_str(position.side))
+            .bind(status_to_str(position.status))
+            .bind(position.size.to_string())
+            .bind(position.entry_price.to_string())
+            .bind(position.mark_price.to_string())
+            .bind(position.margin.to_string())
+            .bind(position.leverage as i64)
+            .bind(position.realized_pnl.to_string())
+            .bind(position.funding_accrued.to_string())
+            .bind(position.liquidation_price.to_string())
+            .bind(position.opened_at)
+            .bind(position.last_update)
+            .bind(position.closed_at.unwrap_or(position.last_update))
+            .execute(&mut tx)
+            .await
+            .context("Failed to insert closed position")?;
+        }
+
+        tx.commit().await.context("Failed to commit closed positions")?;
+
+        Ok(())
+    }
+
+    /// Closed positions matching `filter`, most recently closed first
+    pub async fn closed_positions(
+        &self,
+        filter: &ClosedPositionFilter,
+        limit: i64,
+    ) -> Result<Vec<Position>> {
+        let rows = sqlx::query(
+            "SELECT * FROM closed_positions \
+             WHERE (?1 IS NULL OR owner = ?1) \
+               AND (?2 IS NULL OR symbol = ?2) \
+               AND (?3 IS NULL OR status = ?3) \
+               AND (?5 IS NULL OR (closed_at, position_account) < (?5, ?6)) \
+             ORDER BY closed_at DESC, position_account DESC \
+             LIMIT ?4",
+        )
+        .bind(filter.owner.map(|owner| owner.to_string()))
+        .bind(filter.symbol.as_deref())
+        .bind(filter.status.map(status_to_str))
+        .bind(limit)
+        .bind(filter.before.map(|(closed_at, _)| closed_at))
+        .bind(filter.before.map(|(_, account)| account.to_string()))
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to query closed positions")?;
+
+        rows.iter().map(closed_position_from_row).collect()
+    }
+
+    pub async fn closed_position(&self, position_account: &Pubkey) -> Result<Option<Position>> {
+        let row = sqlx::query("SELECT * FROM closed_positions WHERE position_account = ?1")
+            .bind(position_account.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to query closed position")?;
+
+        row.as_ref().map(closed_position_from_row).transpose()
+    }
+}
+
+// Inherent methods take precedence, so these delegate rather than recurse
+impl HistoryStore for SqliteHistoryStore {
+    fn insert_pnl_snapshots<'a>(&'a self, snapshots: &'a [PnLSnapshot]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_pnl_snapshots(snapshots))
+    }
+
+    fn pnl_history<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+        interval: Duration,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<PnlPoint>>> {
+        Box::pin(self.pnl_history(position_account, interval, limit))
+    }
+
+    fn insert_realized_pnl<'a>(&'a self, record: &'a RealizedPnL) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_realized_pnl(record))
+    }
+
+    fn realized_pnl_page<'a>(
+        &'a self,
+        owner: &'a Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>> {
+        Box::pin(self.realized_pnl_page(owner, from, to, after, limit))
+    }
+
+    fn realized_pnl<'a>(
+        &'a self,
+        owner: &'a Pubkey,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>> {
+        Box::pin(self.realized_pnl(owner, from, to))
+    }
+
+    fn insert_position_open<'a>(
+        &'a self,
+        position: &'a Position,
+        signature: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_position_open(position, signature))
+    }
+
+    fn position_opens<'a>(
+        &'a self,
+        owner: &'a Pubkey,
+        before: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<PositionOpenRecord>>> {
+        Box::pin(self.position_opens(owner, before))
+    }
+
+    fn position_opens_between<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<PositionOpenRecord>>> {
+        Box::pin(self.position_opens_between(from, to))
+    }
+
+    fn realized_pnl_between<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>> {
+        Box::pin(self.realized_pnl_between(from, to))
+    }
+
+    fn position_open<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<PositionOpenRecord>>> {
+        Box::pin(self.position_open(position_account))
+    }
+
+    fn position_realized_pnl<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RealizedPnL>>> {
+        Box::pin(self.position_realized_pnl(position_account))
+    }
+
+    fn insert_position_event<'a>(&'a self, event: &'a PositionEventRecord) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_position_event(event))
+    }
+
+    fn position_events<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<PositionEventRecord>>> {
+        Box::pin(self.position_events(position_account))
+    }
+
+    fn insert_price_ticks<'a>(&'a self, ticks: &'a [PriceTick]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_price_ticks(ticks))
+    }
+
+    fn price_ticks<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<PriceTick>>> {
+        Box::pin(self.price_ticks(from, to))
+    }
+
+    fn symbol_price_ticks<'a>(
+        &'a self,
+        symbol: &'a str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<PriceTick>>> {
+        Box::pin(self.symbol_price_ticks(symbol, from, to, limit))
+    }
+
+    fn compact_price_ticks<'a>(
+        &'a self,
+        scope: &'a TickScope,
+        before: DateTime<Utc>,
+        bucket: Duration,
+    ) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(self.compact_price_ticks(scope, before, bucket))
+    }
+
+    fn delete_pnl_snapshots_before<'a>(&'a self, before: DateTime<Utc>) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(self.delete_pnl_snapshots_before(before))
+    }
+
+    fn table_sizes(&self) -> BoxFuture<'_, Result<Vec<TableSize>>> {
+        Box::pin(self.table_sizes())
+    }
+
+    fn insert_vault_snapshot<'a>(&'a self, snapshot: &'a VaultSnapshot) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_vault_snapshot(snapshot))
+    }
+
+    fn vault_snapshots<'a>(
+        &'a self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<VaultSnapshot>>> {
+        Box::pin(self.vault_snapshots(from, to))
+    }
+
+    fn insert_closed_positions<'a>(&'a self, positions: &'a [Position]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.insert_closed_positions(positions))
+    }
+
+    fn closed_positions<'a>(
+        &'a self,
+        filter: &'a ClosedPositionFilter,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<Position>>> {
+        Box::pin(self.closed_positions(filter, limit))
+    }
+
+    fn closed_position<'a>(
+        &'a self,
+        position_account: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<Position>>> {
+        Box::pin(self.closed_position(position_account))
+    }
+}
+
+fn decimal(row: &SqliteRow, column: &str) -> Result<Decimal> {
+    let value: String = row.try_get(column)?;
+    Decimal::from_str(&value).with_context(|| format!("Invalid decimal in {}: {}", column, value))
+}
+
+fn optional_decimal(row: &SqliteRow, column: &str) -> Result<Option<Decimal>> {
+    let value: Option<String> = row.try_get(column)?;
+    value
+        .map(|value| Decimal::from_str(&value).with_context(|| format!("Invalid decimal in {}: {}", column, value)))
+        .transpose()
+}
+
+fn price_tick_from_row(row: &SqliteRow) -> Result<PriceTick> {
+    Ok(PriceTick {
+        symbol: row.try_get("symbol")?,
+        price: decimal(row, "price")?,
+        conf: optional_decimal(row, "conf")?,
+        publish_time: row.try_get("publish_time")?,
+        recorded_at: row.try_get("recorded_at")?,
+    })
+}
+
+fn realized_pnl_from_row(row: &SqliteRow) -> Result<RealizedPnL> {
+    let id: String = row.try_get("id")?;
+    let position_account: String = row.try_get("position_account")?;
+    let owner: String = row.try_get("owner")?;
+    let side: String = row.try_get("side")?;
+
+    Ok(RealizedPnL {
+        id: id.parse()?,
+        position_account: position_account.parse()?,
+        owner: owner.parse()?,
+        symbol: row.try_get("symbol")?,
+        side: side_from_str(&side)?,
+        size_closed: decimal(row, "size_closed")?,
+        entry_price: decimal(row, "entry_price")?,
+        close_price: decimal(row, "close_price")?,
+        fee: decimal(row, "fee")?,
+        funding: decimal(row, "funding")?,
+        amount: decimal(row, "amount")?,
+        liquidation: row.try_get("liquidation")?,
+        signature: row.try_get("signature")?,
+        closed_at: row.try_get("closed_at")?,
+    })
+}
+
+fn position_open_from_row(row: &SqliteRow) -> Result<PositionOpenRecord> {
+    let position_account: String = row.try_get("position_account")?;
+    let owner: String = row.try_get("owner")?;
+    let side: String = row.try_get("side")?;
+
+    Ok(PositionOpenRecord {
+        position_account: position_account.parse()?,
+        owner: owner.parse()?,
+        symbol: row.try_get("symbol")?,
+        side: side_from_str(&side)?,
+        size: decimal(row, "size")?,
+        entry_price: decimal(row, "entry_price")?,
+        margin: decimal(row, "margin")?,
+        signature: row.try_get("signature")?,
+        opened_at: row.try_get("opened_at")?,
+    })
+}
+
+fn position_event_from_row(row: &SqliteRow) -> Result<PositionEventRecord> {
+    let id: String = row.try_get("id")?;
+    let position_account: String = row.try_get("position_account")?;
+    let kind: String = row.try_get("kind")?;
+
+    Ok(PositionEventRecord {
+        id: id.parse()?,
+        position_account: position_account.parse()?,
+        kind: event_kind_from_str(&kind)?,
+        size: optional_decimal(row, "size")?,
+        margin: optional_decimal(row, "margin")?,
+        mark_price: optional_decimal(row, "mark_price")?,
+        detail: row.try_get("detail")?,
+        signature: row.try_get("signature")?,
+        recorded_at: row.try_get("recorded_at")?,
+    })
+}
+
+fn closed_position_from_row(row: &SqliteRow) -> Result<Position> {
+    let position_account: String = row.try_get("position_account")?;
+    let owner: String = row.try_get("owner")?;
+    let side: String = row.try_get("side")?;
+    let status: String = row.try_get("status")?;
+    let position_index: i64 = row.try_get("position_index")?;
+    let leverage: i64 = row.try_get("leverage")?;
+
+    Ok(Position {
+        position_index: position_index as u32,
+        owner: owner.parse()?,
+        position_account: position_account.parse()?,
+        symbol: row.try_get("symbol")?,
+        side: side_from_str(&side)?,
+        size: decimal(row, "size")?,
+        entry_price: decimal(row, "entry_price")?,
+        mark_price: decimal(row, "mark_price")?,
+        margin: decimal(row, "margin")?,
+        leverage: leverage as u16,
+        unrealized_pnl: Decimal::ZERO,
+        realized_pnl: decimal(row, "realized_pnl")?,
+        funding_accrued: decimal(row, "funding_accrued")?,
+        liquidation_price: decimal(row, "liquidation_price")?,
+        effective_liquidation_price: None,
+        status: status_from_str(&status)?,
+        opened_at: row.try_get("opened_at")?,
+        last_update: row.try_get("last_update")?,
+        closed_at: Some(row.try_get("closed_at")?),
+        margin_call_at: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{PositionStatus, Side};
+    use crate::infrastructure::PositionEventKind;
+
+    fn closed_position(owner: Pubkey, closed_at: DateTime<Utc>) -> Position {
+        Position {
+            position_index: 3,
+            owner,
+            side: Side::Short,
+            size: Decimal::new(12345, 3),
+            entry_price: Decimal::new(150_250_000, 6),
+            mark_price: Decimal::new(149_000_000, 6),
+            margin: Decimal::new(925, 1),
+            leverage: 20,
+            realized_pnl: Decimal::new(-1525, 2),
+            liquidation_price: Decimal::new(157_000_000, 6),
+            status: PositionStatus::Closed,
+            opened_at: closed_at - Duration::hours(2),
+            last_update: closed_at,
+            closed_at: Some(closed_at),
+            ..Position::for_test()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_closed_positions_round_trip() {
+        let store = SqliteHistoryStore::connect("sqlite::memory:").await.unwrap();
+        let owner = Pubkey::new_unique();
+        let now = Utc::now();
+
+        let older = closed_position(owner, now - Duration::hours(3));
+        let newer = closed_position(owner, now - Duration::minutes(5));
+        store.insert_closed_positions(&[older.clone(), newer.clone()]).await.unwrap();
+        // Upserts, a second eviction of the same position is harmless
+        store.insert_closed_positions(std::slice::from_ref(&newer)).await.unwrap();
+
+        let filter = ClosedPositionFilter {
+            owner: Some(owner),
+            ..Default::default()
+        };
+        let positions = store.closed_positions(&filter, 10).await.unwrap();
+
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].position_account, newer.position_account);
+        assert_eq!(positions[0].entry_price, newer.entry_price);
+        assert_eq!(positions[0].realized_pnl, newer.realized_pnl);
+        assert_eq!(positions[1].position_account, older.position_account);
+
+        let fetched = store.closed_position(&older.position_account).await.unwrap().unwrap();
+        assert_eq!(fetched.size, older.size);
+        assert_eq!(fetched.side, Side::Short);
+
+        let other = ClosedPositionFilter {
+            owner: Some(Pubkey::new_unique()),
+            ..Default::default()
+        };
+        assert!(store.closed_positions(&other, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_closed_positions_keyset_pages() {
+        let store = SqliteHistoryStore::connect("sqlite::memory:").await.unwrap();
+        let owner = Pubkey::new_unique();
+        let closed_at = Utc::now();
+
+        // Same close time, so pages have to break ties by account
+        let positions: Vec<Position> = (0..5).map(|_| closed_position(owner, closed_at)).collect();
+        store.insert_closed_positions(&positions).await.unwrap();
+
+        let mut filter = ClosedPositionFilter::default();
+        let mut seen = Vec::new();
+        loop {
+            let page = store.closed_positions(&filter, 2).await.unwrap();
+            let Some(last) = page.last() else {
+                break;
+            };
+            filter.before = Some((last.closed_at.unwrap(), last.position_account));
+            seen.extend(page.iter().map(|position| position.position_account));
+        }
+
+        let mut expected: Vec<Pubkey> = positions.iter().map(|p| p.position_account).collect();
+        expected.sort_by_key(|account| std::cmp::Reverse(account.to_string()));
+        assert_eq!(seen, expected);
+    }
+
+    #[tokio::test]
+    async fn test_symbol_price_ticks() {
+        let store = SqliteHistoryStore::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+
+        let tick = |symbol: &str, secs: i64, conf: Option<Decimal>| PriceTick {
+            symbol: symbol.to_string(),
+            price: Decimal::new(150_123_456, 6),
+            conf,
+            publish_time: conf.map(|_| now + Duration::seconds(secs - 1)),
+            recorded_at: now + Duration::seconds(secs),
+        };
+
+        store
+            .insert_price_ticks(&[
+                // Recorded before confidence and publish times were kept
+                tick("SOL-USD", 0, None),
+                tick("BTC-USD", 1, Some(Decimal::new(25, 2))),
+                tick("SOL-USD", 2, Some(Decimal::new(7, 3))),
+                tick("SOL-USD", 3, Some(Decimal::new(8, 3))),
+            ])
+            .await
+            .unwrap();
+
+        let ticks = store
+            .symbol_price_ticks("SOL-USD", now, now + Duration::seconds(10), 2)
+            .await
+            .unwrap();
+
+        assert_eq!(ticks.len(), 2);
+        assert_eq!(ticks[0].conf, None);
+        assert_eq!(ticks[0].publish_time, None);
+        assert_eq!(ticks[1].price, Decimal::new(150_123_456, 6));
+        assert_eq!(ticks[1].conf, Some(Decimal::new(7, 3)));
+        assert_eq!(ticks[1].publish_time, Some(now + Duration::seconds(1)));
+    }
+
+    #[tokio::test]
+    async fn test_compact_price_ticks() {
+        let store = SqliteHistoryStore::connect("sqlite::memory:").await.unwrap();
+        let start = DateTime::from_timestamp(1_700_000_040, 0).unwrap();
+
+        let tick = |symbol: &str, secs: i64, price: i64| PriceTick {
+            symbol: symbol.to_string(),
+            price: Decimal::from(price),
+            conf: None,
+            publish_time: None,
+            recorded_at: start + Duration::seconds(secs),
+        };
+
+        store
+            .insert_price_ticks(&[
+                tick("SOL-USD", 0, 1),
+                tick("SOL-USD", 10, 2),
+                tick("SOL-USD", 70, 3),
+                tick("SOL-USD", 80, 4),
+                tick("BTC-USD", 0, 5),
+                tick("BTC-USD", 10, 6),
+            ])
+            .await
+            .unwrap();
+
+        // The cutoff splits SOL-USD's second minute, its tick after it stays raw
+        let deleted = store
+            .compact_price_ticks(
+                &TickScope::AllExcept(vec!["BTC-USD".to_string()]),
+                start + Duration::seconds(75),
+                Duration::minutes(1),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(deleted, 1);
+
+        let prices: Vec<Decimal> = store
+            .price_ticks(start, start + Duration::minutes(5))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tick| tick.price)
+            .collect();
+        assert_eq!(prices, vec![Decimal::from(5), Decimal::from(6), Decimal::from(2), Decimal::from(3), Decimal::from(4)]);
+
+        let sizes = store.table_sizes().await.unwrap();
+        let ticks = sizes.iter().find(|size| size.table == "price_ticks").unwrap();
+        assert_eq!(sizes.len(), HISTORY_TABLES.len());
+        assert_eq!(ticks.rows, 5);
+        assert_eq!(ticks.bytes, None);
+    }
+
+    #[tokio::test]
+    async fn test_position_events_round_trip() {
+        let store = SqliteHistoryStore::connect("sqlite::memory:").await.unwrap();
+        let position_account = Pubkey::new_unique();
+        let now = Utc::now();
+
+        let event = |kind, secs: i64, size: Option<Decimal>| PositionEventRecord {
+            id: Uuid::new_v4(),
+            position_account,
+            kind,
+            size,
+            margin: size.map(|_| Decimal::new(1205, 1)),
+            mark_price: Some(Decimal::new(149_500_000, 6)),
+            detail: None,
+            signature: size.map(|_| "sig".to_string()),
+            recorded_at: now + Duration::seconds(secs),
+        };
+
+        store
+            .insert_position_event(&event(PositionEventKind::Alert, 5, None))
+            .await
+            .unwrap();
+        store
+            .insert_position_event(&event(PositionEventKind::Modified, 1, Some(Decimal::new(8, 0))))
+            .await
+            .unwrap();
+
+        let events = store.position_events(&position_account).await.unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, PositionEventKind::Modified);
+        assert_eq!(events[0].size, Some(Decimal::new(8, 0)));
+        assert_eq!(events[0].margin, Some(Decimal::new(1205, 1)));
+        assert_eq!(events[0].signature.as_deref(), Some("sig"));
+        assert_eq!(events[1].kind, PositionEventKind::Alert);
+        assert_eq!(events[1].size, None);
+
+        assert!(store.position_events(&Pubkey::new_unique()).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+/// Telemetry
+/// Log output plus optional OTLP span export. Spans cover the HTTP request
+/// span, oracle fetches, RPC calls, Redis ops and monitor ticks
+use anyhow::{Context, Result};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector base URL, e.g. http://tempo:4318; logs only when unset
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: "perpetual-backend".to_string(),
+        }
+    }
+}
+
+/// Install the global subscriber, call once at startup
+pub fn init_tracing(config: &TelemetryConfig) -> Result<()> {
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_target(false)
+        .with_thread_ids(true);
+
+    let otel_layer = match &config.otlp_endpoint {
+        Some(endpoint) => {
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(
+                    opentelemetry_otlp::new_exporter()
+                        .http()
+                        .with_endpoint(endpoint.clone()),
+                )
+                .with_trace_config(trace::config().with_resource(Resource::new(vec![
+                    KeyValue::new("service.name", config.service_name.clone()),
+                ])))
+                .install_batch(runtime::Tokio)
+                .context("Failed to install OTLP exporter")?;
+
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(fmt_layer)
+        .with(otel_layer)
+        .init();
+
+    Ok(())
+}
+
+/// Flush spans still queued in the batch exporter
+pub fn shutdown_tracing() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
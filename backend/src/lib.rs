@@ -1,5 +1,10 @@
+// RpcPool calls take closures returning solana_client's ClientResult, whose
+// error is large but not ours to shrink
+#![allow(clippy::result_large_err)]
+
 pub mod api;
 pub mod domain;
+pub mod error;
 pub mod infrastructure;
 pub mod services;
 
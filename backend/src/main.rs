@@ -1,11 +1,27 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use perpetual_backend::{create_router};
+use perpetual_backend::api::admin::AdminToken;
+use perpetual_backend::api::cursor::CursorSigner;
+use perpetual_backend::api::fix_gateway::{self, FixGatewayConfig};
 use perpetual_backend::api::handlers::AppState;
-use perpetual_backend::infrastructure::{OracleClient, SolanaClient};
-use perpetual_backend::services::{MonitorConfig, PositionMonitor, PositionManager};
-use solana_sdk::signature::Keypair;
-use solana_sdk::signer::Signer;
+use perpetual_backend::api::ws_rooms::WsRooms;
+use perpetual_backend::infrastructure::{
+    connect_chain, connect_history_store, init_tracing, parse_interval, shutdown_tracing,
+    AccountStreamConfig, ChainKind, JitoConfig, OracleClient, PriceSource, SignerConfig, SimulatedPriceSource,
+    SolanaClient, TelemetryConfig,
+};
+use perpetual_backend::services::{
+    deliver_webhooks, ApiTokenService, BackfillService, BackstopVaultConfig, BackstopVaultService, DemoBootstrap, DynamicMarginConfig, DynamicMarginService, HistoryRetentionConfig, HistoryRetentionService, TickRetention, parse_tick_retention_overrides,
+    EventBus, FeePayerService, FeeTierKeeper, FeeTierKeeperConfig, RedisEventBus, RedisStreamNotifier, FeePayerStrategy,
+    LeaderElection, LeaderElectionConfig, LiquidationExecutor, MarginCallKeeper,
+    MarginCallKeeperConfig, MonitorConfig, Outbox, OutboxConfig, PaperLedger, PaperTradingConfig, PositionMonitor,
+    PositionManager, RiskLimitKeeper, RiskLimitKeeperConfig, RiskLimitService, SubmissionLimitConfig, Tenants, TradingHalt, TradingHaltConfig, WebhookConfig,
+    WebhookDispatcher, WebhookEndpoint, WebhookEventType, WebhookNotifier, INSTRUCTION_DISCRIMINATORS, WEBHOOK_GROUP,
+};
+use rust_decimal::Decimal;
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
@@ -16,12 +32,15 @@ async fn main() -> Result<()> {
     // Load .env file
     dotenvy::dotenv().ok();
     
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .with_target(false)
-        .with_thread_ids(true)
-        .init();
+    // Initialize tracing, exporting spans when an OTLP collector is configured
+    let mut telemetry_config = TelemetryConfig {
+        otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok(),
+        ..Default::default()
+    };
+    if let Ok(service_name) = std::env::var("OTEL_SERVICE_NAME") {
+        telemetry_config.service_name = service_name;
+    }
+    init_tracing(&telemetry_config)?;
 
     info!("Starting Perpetual Futures Backend");
 
@@ -31,79 +50,771 @@ async fn main() -> Result<()> {
         .parse()
         .expect("Invalid PROGRAM_ID format");
     
-    let private_key = std::env::var("SOLANA_PRIVATE_KEY")
-        .expect("SOLANA_PRIVATE_KEY not set in .env");
+    let signer_config = SignerConfig::from_env()?;
     
     let rpc_url = std::env::var("RPC_URL")
         .unwrap_or_else(|_| "https://api.devnet.solana.com".to_string());
     
+    // Same-cluster endpoints pooled behind RPC_URL
+    let rpc_fallback_urls: Vec<String> = std::env::var("RPC_FALLBACK_URLS")
+        .map(|urls| {
+            urls.split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    // Chain the program is deployed on, "solana" (default) or "evm"
+    let chain_kind: ChainKind = std::env::var("CHAIN")
+        .unwrap_or_else(|_| "solana".to_string())
+        .parse()
+        .expect("Invalid CHAIN");
+
+    // Pyth Hermes endpoint prices are polled from, a local mock in end-to-end tests
+    let hermes_url = std::env::var("PYTH_HERMES_URL")
+        .unwrap_or_else(|_| "https://hermes.pyth.network".to_string());
+
     let redis_url = std::env::var("REDIS_URL")
         .unwrap_or_else(|_| "redis://localhost:6379".to_string());
     
+    // "service", "user" or "sponsored:<daily_cap_lamports>"
+    let fee_payer_strategy: FeePayerStrategy = std::env::var("FEE_PAYER_STRATEGY")
+        .unwrap_or_else(|_| "service".to_string())
+        .parse()
+        .expect("Invalid FEE_PAYER_STRATEGY");
+
+    let lookup_table: Option<Pubkey> = std::env::var("LOOKUP_TABLE_ADDRESS")
+        .ok()
+        .map(|address| address.parse().expect("Invalid LOOKUP_TABLE_ADDRESS"));
+
+    let tx_memo_request_id = std::env::var("TX_MEMO_REQUEST_ID")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    let liquidation_executor_enabled = std::env::var("LIQUIDATION_EXECUTOR_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // Full liquidations go to the backstop vault first, vault snapshots need DATABASE_URL
+    let backstop_vault_enabled = std::env::var("BACKSTOP_VAULT_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // POST /admin/bootstrap-demo, refused at startup on mainnet-beta
+    let demo_bootstrap_enabled = std::env::var("DEMO_BOOTSTRAP_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // POST /admin/simulate-price, refused at startup on mainnet-beta
+    let price_simulation_enabled = std::env::var("PRICE_SIMULATION_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // Optional Postgres for PnL history
+    let database_url = std::env::var("DATABASE_URL").ok();
+
+    // Thins price ticks to the last of each minute, then of each hour, and
+    // drops old PnL snapshots, on the leader every hour. Needs DATABASE_URL,
+    // POST /admin/history/compact runs a pass whether or not this is on
+    let mut history_retention_config = HistoryRetentionConfig {
+        scheduled: std::env::var("HISTORY_RETENTION_ENABLED")
+            .map(|v| v == "true")
+            .unwrap_or(false),
+        ..Default::default()
+    };
+    if let Ok(retention) = std::env::var("PRICE_TICK_RETENTION") {
+        history_retention_config.ticks =
+            TickRetention::parse(&retention).expect("Invalid PRICE_TICK_RETENTION");
+    }
+    // Per-symbol tick windows, e.g. BTC-USD=30d/365d,SOL-USD=3d/30d
+    if let Ok(overrides) = std::env::var("PRICE_TICK_RETENTION_OVERRIDES") {
+        history_retention_config.symbol_ticks =
+            parse_tick_retention_overrides(&overrides).expect("Invalid PRICE_TICK_RETENTION_OVERRIDES");
+    }
+    // "forever" keeps PnL snapshots for good
+    if let Ok(retention) = std::env::var("PNL_SNAPSHOT_RETENTION") {
+        history_retention_config.pnl_snapshots = match retention.as_str() {
+            "forever" => None,
+            retention => Some(parse_interval(retention).expect("Invalid PNL_SNAPSHOT_RETENTION")),
+        };
+    }
+
+    // Cranks each symbol's Market maintenance rate from realized volatility,
+    // needs DATABASE_URL for the price archive and the Config admin as signer
+    let dynamic_margin_enabled = std::env::var("DYNAMIC_MARGIN_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // Assigns fee tiers on chain from 30-day taker volume, needs DATABASE_URL
+    // for the fill history and the Config admin as signer
+    let fee_tier_keeper_enabled = std::env::var("FEE_TIER_KEEPER_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    // Minimum 30-day taker volume of each fee tier, from tier 0
+    let mut fee_tier_config = FeeTierKeeperConfig::default();
+    if let Ok(volumes) = std::env::var("FEE_TIER_VOLUMES") {
+        let volumes: Vec<Decimal> = volumes
+            .split(',')
+            .map(|volume| volume.trim().parse().expect("Invalid FEE_TIER_VOLUMES"))
+            .collect();
+        fee_tier_config.tier_volumes = volumes
+            .try_into()
+            .expect("FEE_TIER_VOLUMES needs one volume per fee tier");
+    }
+
+    // White-label operators, each with its own key, fee tier thresholds and
+    // webhooks, a JSON array. None serves every request as the default tenant
+    let tenant_configs = match std::env::var("TENANTS_FILE") {
+        Ok(path) => Tenants::load(&path).expect("Invalid TENANTS_FILE"),
+        Err(_) => Vec::new(),
+    };
+    let tenants = Arc::new(
+        Tenants::new(
+            redis::Client::open(redis_url.clone()).expect("Invalid REDIS_URL"),
+            tenant_configs,
+            fee_tier_config.tier_volumes,
+        )
+        .expect("Invalid TENANTS_FILE"),
+    );
+
+    // One owner's transactions are always sent one at a time, this caps how
+    // many each chain-submitting route has in flight across owners
+    let submission_limits = SubmissionLimitConfig::from_env()?;
+
+    // Closed positions stay in memory this long, then move to Postgres
+    // (or are dropped without DATABASE_URL)
+    let mut monitor_config = MonitorConfig::default();
+    if let Ok(retention) = std::env::var("CLOSED_POSITION_RETENTION") {
+        monitor_config.retention.closed_retention =
+            parse_interval(&retention).expect("Invalid CLOSED_POSITION_RETENTION");
+    }
+    if let Ok(max_closed) = std::env::var("MAX_CLOSED_POSITIONS") {
+        monitor_config.retention.max_closed_positions =
+            max_closed.parse().expect("Invalid MAX_CLOSED_POSITIONS");
+    }
+    // Refresh ticks between full getProgramAccounts scans, the rest only pull
+    // the mutable bytes of each position and fetch what changed
+    if let Ok(full_scan_every) = std::env::var("POSITION_FULL_SCAN_EVERY") {
+        monitor_config.scan.full_scan_every =
+            full_scan_every.parse().expect("Invalid POSITION_FULL_SCAN_EVERY");
+    }
+    // Full scans list keys only and fetch accounts in batches, applying each
+    // as it lands, for programs with 100k+ positions
+    monitor_config.scan.chunked = std::env::var("POSITION_SCAN_CHUNKED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    if let Ok(concurrency) = std::env::var("POSITION_SCAN_CONCURRENCY") {
+        monitor_config.scan.fetch_concurrency =
+            concurrency.parse().expect("Invalid POSITION_SCAN_CONCURRENCY");
+    }
+    // A cache not synced from chain or a snapshot for this long reports not ready
+    if let Ok(max_lag) = std::env::var("READINESS_MAX_SYNC_LAG_SECS") {
+        monitor_config.max_sync_lag_secs = max_lag.parse().expect("Invalid READINESS_MAX_SYNC_LAG_SECS");
+    }
+    // Multiples of maintenance whose crossing raises a margin ratio alert,
+    // comma separated, empty turns them off
+    if let Ok(tiers) = std::env::var("MARGIN_RATIO_ALERT_TIERS") {
+        monitor_config.margin_ratio_alert_tiers = tiers
+            .split(',')
+            .map(str::trim)
+            .filter(|tier| !tier.is_empty())
+            .map(|tier| tier.parse().expect("Invalid MARGIN_RATIO_ALERT_TIERS"))
+            .collect();
+    }
+
+    // Replicas that only serve the API set MONITOR_ENABLED=false and read
+    // streams from the Redis event bus
+    let monitor_enabled = std::env::var("MONITOR_ENABLED")
+        .map(|v| v != "false")
+        .unwrap_or(true);
+
+    let event_bus_enabled = std::env::var("EVENT_BUS_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // Required when more than one replica runs the monitor
+    let leader_election_enabled = std::env::var("LEADER_ELECTION_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // programSubscribe for position changes, gPA polling becomes a backstop
+    let account_stream = std::env::var("ACCOUNT_SUBSCRIPTION_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false)
+        .then(|| {
+            let mut config = AccountStreamConfig::for_rpc_url(&rpc_url);
+            if let Ok(ws_url) = std::env::var("SOLANA_WS_URL") {
+                config.ws_url = ws_url;
+            }
+            config
+        });
+
+    // logsSubscribe for program transactions, refetches what they touched
+    let transaction_watch = std::env::var("TRANSACTION_WATCH_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false)
+        .then(|| {
+            let mut config = AccountStreamConfig::for_rpc_url(&rpc_url);
+            if let Ok(ws_url) = std::env::var("SOLANA_WS_URL") {
+                config.ws_url = ws_url;
+            }
+            config
+        });
+
+    // Optional Jito relay for liquidations, plain RPC otherwise
+    let jito_config = std::env::var("JITO_BLOCK_ENGINE_URL").ok().map(|block_engine_url| JitoConfig {
+        block_engine_url,
+        tip_lamports: std::env::var("JITO_TIP_LAMPORTS")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse()
+            .expect("Invalid JITO_TIP_LAMPORTS"),
+    });
+
+    // Sandbox requests with x-paper-session run against an in-memory ledger
+    let paper_trading_enabled = std::env::var("PAPER_TRADING_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // Shared by every replica so pagination cursors verify on any of them
+    let cursor_signer = match std::env::var("CURSOR_SECRET") {
+        Ok(secret) => CursorSigner::new(secret.into_bytes()),
+        Err(_) => {
+            tracing::warn!("CURSOR_SECRET not set, pagination cursors only verify on this process");
+            CursorSigner::ephemeral()
+        }
+    };
+
+    // Bearer token for /admin routes, which stay disabled without it
+    let admin_token = std::env::var("ADMIN_API_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .map(|token| Arc::new(AdminToken::new(&token)));
+
+    // Post-trade reports of closes and liquidations, signed with WEBHOOK_SECRET
+    let trade_report_webhook_urls: Vec<String> = std::env::var("TRADE_REPORT_WEBHOOK_URLS")
+        .map(|urls| {
+            urls.split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    // Liquidation alerts, POSTed in batches gathered for LIQUIDATION_ALERT_WEBHOOK_BATCH_MS
+    let liquidation_alert_webhook_urls: Vec<String> = std::env::var("LIQUIDATION_ALERT_WEBHOOK_URLS")
+        .map(|urls| {
+            urls.split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let liquidation_alert_webhook_batch = Duration::from_millis(
+        std::env::var("LIQUIDATION_ALERT_WEBHOOK_BATCH_MS")
+            .map(|ms| ms.parse().expect("Invalid LIQUIDATION_ALERT_WEBHOOK_BATCH_MS"))
+            .unwrap_or(1000),
+    );
+    // Liquidation alerts appended to this Redis stream, off unless named
+    let liquidation_alert_stream = std::env::var("LIQUIDATION_ALERT_STREAM")
+        .ok()
+        .filter(|stream| !stream.is_empty());
+    let tenant_webhooks = tenants.iter().any(|tenant| !tenant.webhooks.is_empty());
+    let global_webhooks = !trade_report_webhook_urls.is_empty() || !liquidation_alert_webhook_urls.is_empty();
+    let webhooks = (global_webhooks || tenant_webhooks).then(|| {
+        let secret = match std::env::var("WEBHOOK_SECRET") {
+            Ok(secret) => secret,
+            Err(_) if !global_webhooks => String::new(),
+            Err(_) => panic!("Webhook URLs need WEBHOOK_SECRET"),
+        };
+        let endpoints = trade_report_webhook_urls
+            .into_iter()
+            .map(|url| WebhookEndpoint {
+                url,
+                events: vec![WebhookEventType::TradeReport],
+            })
+            .chain(liquidation_alert_webhook_urls.into_iter().map(|url| WebhookEndpoint {
+                url,
+                events: vec![WebhookEventType::LiquidationAlerts],
+            }))
+            .collect();
+        Arc::new(WebhookDispatcher::new(endpoints, secret, WebhookConfig::default()).with_tenants(Arc::clone(&tenants)))
+    });
+    let report_trades = webhooks
+        .as_ref()
+        .is_some_and(|webhooks| webhooks.subscribed(WebhookEventType::TradeReport));
+
+    // Domain events kept in a Redis Stream for webhooks, indexers and analytics
+    let outbox_config = std::env::var("OUTBOX_ENABLED")
+        .map(|v| v == "true")
+        .unwrap_or(false)
+        .then(|| {
+            let mut config = OutboxConfig::default();
+            if let Ok(max_len) = std::env::var("OUTBOX_MAX_LEN") {
+                config.max_len = max_len.parse().expect("Invalid OUTBOX_MAX_LEN");
+            }
+            if let Ok(groups) = std::env::var("OUTBOX_CONSUMER_GROUPS") {
+                config.groups = groups
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|group| !group.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            let redis_client = redis::Client::open(redis_url.clone()).expect("Invalid REDIS_URL");
+            (redis_client, config)
+        });
+
+    // Reads under /users/:id/ refused without a read-only API token
+    let require_api_token = std::env::var("REQUIRE_API_TOKEN")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    let api_tokens = Arc::new(ApiTokenService::new(
+        redis::Client::open(redis_url.clone()).expect("Invalid REDIS_URL"),
+    ));
+
     let port = std::env::var("PORT")
         .unwrap_or_else(|_| "3000".to_string())
         .parse::<u16>()
         .expect("Invalid PORT");
 
     info!("Configuration:");
+    info!("  Chain: {}", chain_kind);
     info!("  Program ID: {}", program_id);
     info!("  RPC URL: {}", rpc_url);
+    if !rpc_fallback_urls.is_empty() {
+        info!("  RPC fallbacks: {}", rpc_fallback_urls.join(", "));
+    }
     info!("  Redis URL: {}", redis_url);
     info!("  Port: {}", port);
+    info!("  Fee payer strategy: {:?}", fee_payer_strategy);
 
     // Initialize Solana payer keypair from private key
     // Using a single account from ENV for all signing/accounts creation
     // Can use ephermal wallets per user after having an auth layer
     // Or use self custodial wallets and let users sign on client side
     // For the sake of demonstration have used a single wallet added to env for all things
-    let payer: Arc<Keypair> = Arc::new(Keypair::from_base58_string(&private_key));
+    // Production deployments should use SIGNER_TYPE=keystore or SIGNER_TYPE=remote
+    let payer = signer_config.load()?;
     info!("  Payer: {}", payer.pubkey());
     
-    let solana_client = Arc::new(SolanaClient::new(
+    let mut solana_client = SolanaClient::new(
         program_id,
         payer,
         rpc_url,
-    ));
+    );
+
+    if !rpc_fallback_urls.is_empty() {
+        solana_client = solana_client.with_fallback_urls(rpc_fallback_urls);
+    }
+
+    if let Some(jito_config) = jito_config {
+        info!("  Jito block engine: {}", jito_config.block_engine_url);
+        solana_client = solana_client.with_jito(jito_config);
+    }
+
+    if tx_memo_request_id {
+        solana_client = solana_client.with_request_id_memo();
+    }
+
+    let solana_client = Arc::new(solana_client);
     info!("solana client initialized");
 
+    let chain = connect_chain(chain_kind, &solana_client)?;
+
     // Initialize Oracle client
     let oracle_client = Arc::new(RwLock::new(
-        OracleClient::new_hermes().with_mainnet_defaults(),
+        OracleClient::new(hermes_url).with_mainnet_defaults(),
     ));
     info!("Oracle client initialized");
 
+    // Injected prices only reach the monitor, trades still price off the oracle
+    let price_simulator = if price_simulation_enabled {
+        if solana_client.is_mainnet()? {
+            bail!("PRICE_SIMULATION_ENABLED is for devnet only, RPC_URL serves mainnet-beta");
+        }
+        info!("Price simulation enabled");
+        Some(Arc::new(SimulatedPriceSource::new(oracle_client.clone())))
+    } else {
+        None
+    };
+    let monitor_price_source: Arc<dyn PriceSource> = match &price_simulator {
+        Some(price_simulator) => price_simulator.clone(),
+        None => oracle_client.clone(),
+    };
+
+    let history_store = match database_url {
+        Some(database_url) => {
+            let history_store = connect_history_store(&database_url).await?;
+            info!("History store connected");
+            Some(history_store)
+        }
+        None => None,
+    };
+
     // Initialize Position Monitor
-    let monitor: Arc<PositionMonitor> = Arc::new(
-        PositionMonitor::new(
-            Arc::clone(&solana_client),
-            oracle_client,
-            MonitorConfig::default(),
-            redis_url,
-        )?
-    );
+    let mut monitor = PositionMonitor::new(
+        Arc::clone(&solana_client),
+        monitor_price_source,
+        monitor_config,
+        redis_url.clone(),
+    )?;
+
+    if leader_election_enabled {
+        let leader_election = Arc::new(LeaderElection::new(
+            redis_url.clone(),
+            LeaderElectionConfig::default(),
+        )?);
+        leader_election.spawn();
+        info!("Leader election enabled (node {})", leader_election.node_id());
+        monitor = monitor.with_leader_election(leader_election);
+    }
+
+    if let Some(history_store) = &history_store {
+        monitor = monitor.with_history_store(Arc::clone(history_store));
+    }
+
+    if let Some(account_stream) = account_stream {
+        info!("Account subscription enabled ({})", account_stream.ws_url);
+        monitor = monitor.with_account_stream(account_stream);
+    }
+
+    if let Some(transaction_watch) = transaction_watch {
+        info!("Transaction watch enabled ({})", transaction_watch.ws_url);
+        monitor = monitor.with_transaction_watch(transaction_watch);
+    }
+
+    if let Some(webhooks) = webhooks
+        .as_ref()
+        .filter(|webhooks| webhooks.subscribed(WebhookEventType::LiquidationAlerts))
+    {
+        info!("Liquidation alert webhooks enabled");
+        monitor.alert_notifiers().register(Arc::new(WebhookNotifier::new(
+            Arc::clone(webhooks),
+            liquidation_alert_webhook_batch,
+        )));
+    }
+
+    if let Some(stream) = liquidation_alert_stream {
+        info!("Liquidation alerts appended to Redis stream {}", stream);
+        monitor.alert_notifiers().register(Arc::new(RedisStreamNotifier::new(
+            redis::Client::open(redis_url.clone()).expect("Invalid REDIS_URL"),
+            stream,
+            OutboxConfig::default().max_len,
+        )));
+    }
+
+    let monitor = Arc::new(monitor);
+
+    // Serve reads from the last snapshot until the first chain scan completes
+    match monitor.restore_snapshot().await {
+        Ok(true) => info!("Monitor state restored from snapshot"),
+        Ok(false) => info!("No monitor snapshot found, waiting for chain sync"),
+        Err(e) => tracing::warn!("Failed to restore monitor snapshot: {}", e),
+    }
     info!("Position monitor created");
 
-    // Start monitoring in background
-    let monitor_clone = Arc::clone(&monitor);
-    tokio::spawn(async move {
-        if let Err(e) = monitor_clone.start().await {
-            tracing::error!("Failed to start monitor: {}", e);
+    // Without the Redis bus, subscribers read the local monitor's events
+    let event_bus: Arc<dyn EventBus> = if event_bus_enabled {
+        let event_bus = Arc::new(RedisEventBus::new(redis_url.clone())?);
+        if monitor_enabled {
+            event_bus.spawn_publisher(&monitor);
         }
-    });
-    info!("Position monitor started (background tasks)");
+        event_bus.spawn_bridge();
+        info!("Redis event bus enabled");
+        event_bus
+    } else {
+        Arc::clone(monitor.events())
+    };
+
+    // Breach warnings go out on the bus so every replica's WebSockets see them
+    let risk_limits = Arc::new(RiskLimitService::new(
+        redis::Client::open(redis_url.clone()).expect("Invalid REDIS_URL"),
+        Arc::clone(&event_bus),
+    ));
+
+    // Start monitoring in background
+    if monitor_enabled {
+        let monitor_clone = Arc::clone(&monitor);
+        tokio::spawn(async move {
+            if let Err(e) = monitor_clone.start().await {
+                tracing::error!("Failed to start monitor: {}", e);
+            }
+        });
+        info!("Position monitor started (background tasks)");
+    } else {
+        // Reads still come from the cache, kept current from the leader's snapshot
+        monitor.spawn_snapshot_reloader();
+        info!("Position monitor disabled on this node");
+    }
+
+    let fee_payer = Arc::new(FeePayerService::new(fee_payer_strategy, redis_url)?);
 
     // Initialize Position Manager with monitor reference
-    let position_manager = Arc::new(PositionManager::new(
+    let mut position_manager = PositionManager::new(
         Arc::clone(&solana_client),
         Arc::clone(&monitor),  // Shared state
+    )
+    .with_fee_payer(fee_payer)
+    .with_submission_limits(submission_limits);
+
+    if let Some(lookup_table) = lookup_table {
+        info!("Using address lookup table {}", lookup_table);
+        position_manager = position_manager.with_lookup_table(lookup_table);
+    }
+
+    if let Some(history_store) = &history_store {
+        position_manager = position_manager.with_history_store(Arc::clone(history_store));
+    }
+
+    let outbox = match outbox_config {
+        Some((redis_client, mut config)) => {
+            // The webhooks group only exists while something consumes it, an
+            // unread group holds back trimming
+            if report_trades && !config.groups.iter().any(|group| group == WEBHOOK_GROUP) {
+                config.groups.push(WEBHOOK_GROUP.to_string());
+            }
+            let outbox = Arc::new(Outbox::new(redis_client, config));
+            outbox
+                .ensure_groups()
+                .await
+                .expect("Failed to create outbox consumer groups");
+            if let Some(webhooks) = webhooks.as_ref().filter(|_| report_trades) {
+                let webhooks = Arc::clone(webhooks);
+                outbox.spawn_consumer(WEBHOOK_GROUP, move |entry| {
+                    deliver_webhooks(Arc::clone(&webhooks), entry)
+                });
+            }
+            outbox.spawn_trimmer();
+            info!("Outbox enabled");
+            position_manager = position_manager.with_outbox(Arc::clone(&outbox));
+            Some(outbox)
+        }
+        None => None,
+    };
+
+    if let Some(webhooks) = webhooks.filter(|_| report_trades) {
+        info!("Trade report webhooks enabled");
+        position_manager = position_manager.with_webhooks(webhooks);
+    }
+
+    let position_manager = Arc::new(position_manager);
+
+    // The backend encodes instructions itself, check it still matches the deployed program
+    {
+        let position_manager = Arc::clone(&position_manager);
+        tokio::spawn(async move {
+            match position_manager.get_program_idl().await {
+                Ok(Some(idl)) => {
+                    let mismatched = idl.mismatched_instructions(&INSTRUCTION_DISCRIMINATORS);
+                    if mismatched.is_empty() {
+                        info!("Program IDL matches the backend's instruction encoding");
+                    } else {
+                        tracing::error!(
+                            "Program IDL encodes {} differently from the backend, redeploy one of them",
+                            mismatched.join(", ")
+                        );
+                    }
+                }
+                Ok(None) => tracing::warn!("No IDL published for the program, run the idl-publish binary"),
+                Err(e) => tracing::warn!("Failed to fetch the program IDL: {}", e),
+            }
+        });
+    }
+
+    let trading_halt = Arc::new(TradingHalt::new(
+        Arc::clone(&position_manager),
+        TradingHaltConfig::default(),
     ));
+    trading_halt.spawn();
+
+    if liquidation_executor_enabled {
+        let mut executor =
+            LiquidationExecutor::new(Arc::clone(&position_manager), Arc::clone(&monitor))
+                .with_trading_halt(Arc::clone(&trading_halt));
+        if backstop_vault_enabled {
+            executor = executor.with_backstop();
+        }
+        tokio::spawn(async move {
+            executor.run().await;
+        });
+        info!("Liquidation executor started");
+
+        // Idle until the program's Config sets a margin call grace period
+        let keeper = MarginCallKeeper::new(
+            Arc::clone(&position_manager),
+            Arc::clone(&monitor),
+            MarginCallKeeperConfig::default(),
+        )
+        .with_trading_halt(Arc::clone(&trading_halt));
+        tokio::spawn(async move {
+            keeper.run().await;
+        });
+    }
+
+    if monitor_enabled {
+        let keeper = RiskLimitKeeper::new(
+            Arc::clone(&position_manager),
+            Arc::clone(&monitor),
+            Arc::clone(&risk_limits),
+            RiskLimitKeeperConfig::default(),
+        );
+        tokio::spawn(async move {
+            keeper.run().await;
+        });
+    }
+
+    match (&history_store, monitor_enabled && fee_tier_keeper_enabled) {
+        (Some(history_store), true) => {
+            let keeper = FeeTierKeeper::new(
+                Arc::clone(&position_manager),
+                Arc::clone(&monitor),
+                Arc::clone(history_store),
+                fee_tier_config.clone(),
+            )
+            .with_tenants(Arc::clone(&tenants));
+            tokio::spawn(async move {
+                keeper.run().await;
+            });
+        }
+        (None, true) => tracing::warn!("FEE_TIER_KEEPER_ENABLED needs DATABASE_URL, fee tiers are not assigned"),
+        _ => {}
+    }
+
+    let backstop_vault = backstop_vault_enabled.then(|| {
+        let mut service = BackstopVaultService::new(
+            Arc::clone(&position_manager),
+            Arc::clone(&monitor),
+            BackstopVaultConfig::default(),
+        );
+        if let Some(history_store) = &history_store {
+            service = service.with_history_store(Arc::clone(history_store));
+        }
+        let service = Arc::new(service);
+        Arc::clone(&service).spawn();
+        info!("Backstop vault enabled");
+        service
+    });
+
+    let dynamic_margin = match (&history_store, dynamic_margin_enabled) {
+        (Some(history_store), true) => {
+            let service = Arc::new(DynamicMarginService::new(
+                Arc::clone(&position_manager),
+                Arc::clone(&monitor),
+                Arc::clone(history_store),
+                DynamicMarginConfig::default(),
+            ));
+            Arc::clone(&service).spawn();
+            info!("Dynamic margin enabled");
+            Some(service)
+        }
+        (None, true) => {
+            tracing::warn!("DYNAMIC_MARGIN_ENABLED needs DATABASE_URL, dynamic margin is off");
+            None
+        }
+        _ => None,
+    };
+
+    let backfill = history_store
+        .as_ref()
+        .map(|history_store| Arc::new(BackfillService::new(Arc::clone(&solana_client), Arc::clone(history_store))));
+
+    let history_retention = match &history_store {
+        Some(history_store) => {
+            let service = Arc::new(HistoryRetentionService::new(
+                Arc::clone(history_store),
+                Arc::clone(&monitor),
+                history_retention_config,
+            ));
+            if service.config().scheduled {
+                Arc::clone(&service).spawn();
+                info!("History retention enabled");
+            }
+            Some(service)
+        }
+        None => {
+            if history_retention_config.scheduled {
+                tracing::warn!("HISTORY_RETENTION_ENABLED needs DATABASE_URL, history is not pruned");
+            }
+            None
+        }
+    };
+
+    if demo_bootstrap_enabled && solana_client.is_mainnet()? {
+        bail!("DEMO_BOOTSTRAP_ENABLED is for devnet only, RPC_URL serves mainnet-beta");
+    }
+    let demo_bootstrap = demo_bootstrap_enabled.then(|| {
+        let mut demo_bootstrap = DemoBootstrap::new(Arc::clone(&solana_client), Arc::clone(&monitor));
+        if let Some(history_store) = &history_store {
+            demo_bootstrap = demo_bootstrap.with_history_store(Arc::clone(history_store));
+        }
+        info!("Demo bootstrap enabled");
+        Arc::new(demo_bootstrap)
+    });
+
+    // Paper sessions live on this node and price off its oracle cache
+    let paper_ledger = paper_trading_enabled.then(|| {
+        let ledger = Arc::new(PaperLedger::new(
+            oracle_client.clone(),
+            PaperTradingConfig::default(),
+        ));
+        ledger.spawn();
+        info!("Paper trading enabled");
+        ledger
+    });
+
+    // One fan-out per node routes updates to the WebSocket rooms
+    let ws_rooms = Arc::new(WsRooms::new());
+    ws_rooms.spawn(&event_bus);
 
     // Create app state
     let state = AppState {
         monitor: Arc::clone(&monitor),
         position_manager,
+        chain,
+        event_bus,
+        ws_rooms,
+        history_store,
+        paper_ledger,
+        backstop_vault,
+        dynamic_margin,
+        backfill,
+        history_retention,
+        demo_bootstrap,
+        price_simulator,
+        outbox,
+        trading_halt,
+        risk_limits,
+        tenants,
+        cursor_signer: Arc::new(cursor_signer),
+        admin_token,
+        api_tokens,
+        require_api_token,
     };
 
+    // FIX 4.4 order entry next to the HTTP API, off unless FIX_GATEWAY_ADDR is set
+    if let Ok(addr) = std::env::var("FIX_GATEWAY_ADDR") {
+        let config = FixGatewayConfig {
+            addr,
+            comp_id: std::env::var("FIX_COMP_ID").unwrap_or_else(|_| "PERPS".to_string()),
+            password: std::env::var("FIX_GATEWAY_PASSWORD")
+                .ok()
+                .filter(|password| !password.is_empty())
+                .map(|password| AdminToken::new(&password)),
+        };
+        if config.password.is_none() {
+            tracing::warn!("FIX_GATEWAY_PASSWORD not set, any FIX counterparty can log on");
+        }
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = fix_gateway::serve(config, state).await {
+                tracing::error!("FIX gateway stopped: {}", e);
+            }
+        });
+    }
+
     // Create router with middleware
     let app = create_router(state)
         .layer(
@@ -124,5 +835,7 @@ async fn main() -> Result<()> {
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     axum::serve(listener, app).await?;
 
+    shutdown_tracing();
+
     Ok(())
 }
@@ -0,0 +1,274 @@
+/// Read-Only API Tokens
+/// Owner-scoped bearer tokens for portfolio trackers. A token is minted or
+/// revoked with a wallet signature over a timestamped message, then read
+/// requests for that owner carry it instead of a signature each. Only the
+/// SHA-256 digest is stored, under a Redis key that expires with the token
+/// Tokens are kept per tenant, one minted through a tenant's frontend only
+/// authenticates requests made with that tenant's key
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::HashMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::services::tenant_key;
+
+/// Marks a bearer value as an API token rather than the admin token
+pub const API_TOKEN_PREFIX: &str = "pt_";
+
+pub const DEFAULT_API_TOKEN_TTL_SECS: u64 = 90 * 24 * 3600;
+pub const MAX_API_TOKEN_TTL_SECS: u64 = 365 * 24 * 3600;
+pub const MAX_API_TOKENS_PER_OWNER: usize = 20;
+
+/// Wallet signature of a request whose body can't carry one, e.g. a CSV upload
+pub const WALLET_SIGNATURE_HEADER: &str = "x-wallet-signature";
+/// Unix seconds the `WALLET_SIGNATURE_HEADER` message was signed at
+pub const WALLET_TIMESTAMP_HEADER: &str = "x-wallet-timestamp";
+
+/// How far a signed timestamp may be from our clock
+const SIGNATURE_WINDOW_SECS: u64 = 300;
+
+fn token_key(tenant: &str, digest: &str) -> String {
+    tenant_key(tenant, &format!("api_tokens:{}", digest))
+}
+
+fn owner_key(tenant: &str, owner: &Pubkey) -> String {
+    tenant_key(tenant, &format!("api_tokens:owner:{}", owner))
+}
+
+/// Seen wallet signatures, so a signed message can't be replayed inside its window
+fn signature_key(signature: &Signature) -> String {
+    format!("api_tokens:sig:{}", signature)
+}
+
+/// Message the wallet signs to mint a token
+pub fn create_token_message(owner: &Pubkey, timestamp: i64) -> String {
+    format!("Create read-only API token for {} at {}", owner, timestamp)
+}
+
+/// Message the wallet signs to revoke `token_id`
+pub fn revoke_token_message(owner: &Pubkey, token_id: Uuid, timestamp: i64) -> String {
+    format!("Revoke API token {} for {} at {}", token_id, owner, timestamp)
+}
+
+/// Check a base58 ed25519 `signature` by `owner` over `message`, signed at
+/// `timestamp` (unix seconds) within the window around `now`
+pub fn verify_wallet_signature(
+    owner: &Pubkey,
+    message: &str,
+    signature: &str,
+    timestamp: i64,
+    now: DateTime<Utc>,
+) -> Result<Signature> {
+    if now.timestamp().abs_diff(timestamp) > SIGNATURE_WINDOW_SECS {
+        return Err(anyhow!(
+            "Signed timestamp must be within {}s of the server clock",
+            SIGNATURE_WINDOW_SECS
+        ));
+    }
+
+    let signature = Signature::from_str(signature).map_err(|_| anyhow!("Invalid signature encoding"))?;
+    if !signature.verify(owner.as_ref(), message.as_bytes()) {
+        return Err(anyhow!("Signature does not match owner {}", owner));
+    }
+
+    Ok(signature)
+}
+
+fn digest(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub owner: Pubkey,
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct ApiTokenService {
+    redis_client: redis::Client,
+}
+
+impl ApiTokenService {
+    pub fn new(redis_client: redis::Client) -> Self {
+        Self { redis_client }
+    }
+
+    /// Record a verified wallet signature, false when it was already used
+    pub async fn consume_signature(&self, signature: &Signature) -> Result<bool> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let fresh: bool = redis::cmd("SET")
+            .arg(signature_key(signature))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(2 * SIGNATURE_WINDOW_SECS)
+            .query_async::<_, Option<String>>(&mut conn)
+            .await?
+            .is_some();
+
+        Ok(fresh)
+    }
+
+    /// Unexpired tokens of an owner, oldest first
+    pub async fn list(&self, tenant: &str, owner: &Pubkey) -> Result<Vec<ApiToken>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let digests: HashMap<String, String> = conn.hgetall(owner_key(tenant, owner)).await?;
+        if digests.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut pipe = redis::pipe();
+        for digest in digests.values() {
+            pipe.get(token_key(tenant, digest));
+        }
+        let stored: Vec<Option<String>> = pipe.query_async(&mut conn).await?;
+
+        // Entries whose token key expired are dropped from the owner index
+        let expired: Vec<&String> = digests
+            .keys()
+            .zip(&stored)
+            .filter(|(_, raw)| raw.is_none())
+            .map(|(id, _)| id)
+            .collect();
+        if !expired.is_empty() {
+            conn.hdel::<_, _, ()>(owner_key(tenant, owner), expired).await?;
+        }
+
+        let mut tokens: Vec<ApiToken> = stored
+            .into_iter()
+            .flatten()
+            .map(|raw| serde_json::from_str(&raw))
+            .collect::<Result<_, _>>()
+            .context("Failed to decode API token")?;
+        tokens.sort_by_key(|token| token.created_at);
+        Ok(tokens)
+    }
+
+    /// Mint a token, returns its record and the secret, which is not stored
+    pub async fn create(
+        &self,
+        tenant: &str,
+        owner: Pubkey,
+        label: Option<String>,
+        ttl_secs: u64,
+    ) -> Result<(ApiToken, String)> {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let token = format!("{}{}", API_TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(secret));
+        let digest = digest(&token);
+
+        let created_at = Utc::now();
+        let record = ApiToken {
+            id: Uuid::new_v4(),
+            owner,
+            label,
+            created_at,
+            expires_at: created_at + Duration::seconds(ttl_secs as i64),
+        };
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        redis::pipe()
+            .atomic()
+            .set_ex(token_key(tenant, &digest), serde_json::to_string(&record)?, ttl_secs)
+            .hset(owner_key(tenant, &owner), record.id.to_string(), &digest)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .context("Failed to store API token")?;
+
+        Ok((record, token))
+    }
+
+    /// Delete every token of an owner, returns how many there were
+    pub async fn revoke_all(&self, tenant: &str, owner: &Pubkey) -> Result<usize> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let digests: Vec<String> = conn.hvals(owner_key(tenant, owner)).await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for digest in &digests {
+            pipe.del(token_key(tenant, digest)).ignore();
+        }
+        pipe.del(owner_key(tenant, owner))
+            .ignore()
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+
+        Ok(digests.len())
+    }
+
+    /// Delete a token, returns it when it existed
+    pub async fn revoke(&self, tenant: &str, owner: &Pubkey, token_id: Uuid) -> Result<Option<ApiToken>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let digest: Option<String> = conn.hget(owner_key(tenant, owner), token_id.to_string()).await?;
+        let Some(digest) = digest else {
+            return Ok(None);
+        };
+
+        let stored: Option<String> = conn.get(token_key(tenant, &digest)).await?;
+        redis::pipe()
+            .atomic()
+            .del(token_key(tenant, &digest))
+            .hdel(owner_key(tenant, owner), token_id.to_string())
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+
+        stored
+            .map(|raw| serde_json::from_str(&raw).context("Failed to decode API token"))
+            .transpose()
+    }
+
+    /// The token a bearer value stands for, none when unknown, revoked or expired
+    pub async fn authenticate(&self, tenant: &str, token: &str) -> Result<Option<ApiToken>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let stored: Option<String> = conn.get(token_key(tenant, &digest(token))).await?;
+
+        let Some(raw) = stored else {
+            return Ok(None);
+        };
+        let record: ApiToken = serde_json::from_str(&raw).context("Failed to decode API token")?;
+
+        Ok((record.expires_at > Utc::now()).then_some(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn test_verify_wallet_signature() {
+        let wallet = Keypair::new();
+        let owner = wallet.pubkey();
+        let now = Utc::now();
+        let message = create_token_message(&owner, now.timestamp());
+        let signature = wallet.sign_message(message.as_bytes()).to_string();
+
+        assert!(verify_wallet_signature(&owner, &message, &signature, now.timestamp(), now).is_ok());
+
+        // Someone else's wallet, a different message, a stale timestamp
+        let other = Pubkey::new_unique();
+        assert!(verify_wallet_signature(&other, &message, &signature, now.timestamp(), now).is_err());
+        let revoke = revoke_token_message(&owner, Uuid::new_v4(), now.timestamp());
+        assert!(verify_wallet_signature(&owner, &revoke, &signature, now.timestamp(), now).is_err());
+        let later = now + Duration::seconds(SIGNATURE_WINDOW_SECS as i64 + 1);
+        assert!(verify_wallet_signature(&owner, &message, &signature, now.timestamp(), later).is_err());
+        // Timestamps far enough out to overflow a subtraction
+        for timestamp in [i64::MIN, i64::MAX] {
+            assert!(verify_wallet_signature(&owner, &message, &signature, timestamp, now).is_err());
+        }
+    }
+}
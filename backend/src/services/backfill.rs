@@ -0,0 +1,686 @@
+/// History Backfill
+/// Rebuilds position opens, realized PnL and position events from the
+/// program's transaction logs, for when the history store missed writes or
+/// lost data. Transactions are replayed oldest first from a slot; writes are
+/// keyed on the transaction signature, so rerunning over the same range adds
+/// nothing twice
+/// Program events don't carry fees or funding, so backfilled closes record
+/// both as zero, and a close's price is derived from its realized PnL
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+
+use crate::domain::{symbols, Position, PositionStatus, RealizedPnL, Side};
+use crate::infrastructure::{HistoryStore, PositionEventKind, PositionEventRecord, PositionOpenRecord, SolanaClient};
+use crate::services::{OnChainEvent, OnChainSide};
+
+// getSignaturesForAddress page size, the RPC maximum
+const SIGNATURE_PAGE_LIMIT: usize = 1000;
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackfillState {
+    /// Listing signatures from the newest back to the start slot
+    Scanning,
+    /// Fetching and replaying transactions
+    Replaying,
+    Completed,
+    Failed,
+}
+
+impl BackfillState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackfillState::Scanning => "scanning",
+            BackfillState::Replaying => "replaying",
+            BackfillState::Completed => "completed",
+            BackfillState::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackfillProgress {
+    pub from_slot: u64,
+    pub state: BackfillState,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Successful program transactions at or after `from_slot`
+    pub transactions_found: u64,
+    pub transactions_replayed: u64,
+    /// Slot of the last transaction replayed
+    pub current_slot: Option<u64>,
+    pub opens_written: u64,
+    pub realized_pnl_written: u64,
+    pub events_written: u64,
+    /// Closes of positions with no recorded or replayed open
+    pub skipped_closes: u64,
+    pub error: Option<String>,
+}
+
+impl BackfillProgress {
+    fn new(from_slot: u64) -> Self {
+        Self {
+            from_slot,
+            state: BackfillState::Scanning,
+            started_at: Utc::now(),
+            finished_at: None,
+            transactions_found: 0,
+            transactions_replayed: 0,
+            current_slot: None,
+            opens_written: 0,
+            realized_pnl_written: 0,
+            events_written: 0,
+            skipped_closes: 0,
+            error: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, BackfillState::Scanning | BackfillState::Replaying)
+    }
+}
+
+/// A history store write rebuilt from one event
+#[derive(Debug, Clone)]
+pub enum BackfillRecord {
+    Open(Box<Position>),
+    Realized(RealizedPnL),
+    Event(PositionEventRecord),
+}
+
+#[derive(Debug, Clone)]
+struct TrackedPosition {
+    owner: Pubkey,
+    symbol: String,
+    side: Side,
+    size: Decimal,
+    entry_price: Decimal,
+}
+
+/// Position state carried across replayed events, enough to price closes
+#[derive(Debug, Default)]
+pub struct BackfillLedger {
+    positions: HashMap<Pubkey, TrackedPosition>,
+}
+
+impl BackfillLedger {
+    pub fn knows(&self, position_account: &Pubkey) -> bool {
+        self.positions.contains_key(position_account)
+    }
+
+    /// Start from a stored open, for positions opened before the replayed range
+    /// Modifies before the range aren't seen, so the size is the opening one
+    pub fn seed(&mut self, open: &PositionOpenRecord) {
+        self.positions.insert(
+            open.position_account,
+            TrackedPosition {
+                owner: open.owner,
+                symbol: open.symbol.clone(),
+                side: open.side,
+                size: open.size,
+                entry_price: open.entry_price,
+            },
+        );
+    }
+
+    /// The record `event` rebuilds, none for a close of an unknown position
+    pub fn apply(&mut self, event: OnChainEvent, signature: &str) -> Option<BackfillRecord> {
+        match event {
+            OnChainEvent::PositionOpened(opened) => {
+                let side = side_from_chain(opened.side);
+                let size = from_fixed(opened.size);
+                let entry_price = from_fixed(opened.entry_price);
+                let symbol = symbols::normalize(&opened.symbol);
+                let at = timestamp(opened.timestamp);
+
+                self.positions.insert(
+                    opened.position,
+                    TrackedPosition {
+                        owner: opened.owner,
+                        symbol: symbol.clone(),
+                        side,
+                        size,
+                        entry_price,
+                    },
+                );
+
+                Some(BackfillRecord::Open(Box::new(Position {
+                    position_index: 0,
+                    owner: opened.owner,
+                    position_account: opened.position,
+                    symbol,
+                    side,
+                    size,
+                    entry_price,
+                    mark_price: entry_price,
+                    margin: from_fixed(opened.margin),
+                    leverage: opened.leverage,
+                    unrealized_pnl: Decimal::ZERO,
+                    realized_pnl: Decimal::ZERO,
+                    funding_accrued: Decimal::ZERO,
+                    liquidation_price: Decimal::ZERO,
+                    effective_liquidation_price: None,
+                    status: PositionStatus::Open,
+                    opened_at: at,
+                    last_update: at,
+                    closed_at: None,
+                    margin_call_at: None,
+                })))
+            }
+            OnChainEvent::PositionModified(modified) => {
+                let size = from_fixed(modified.new_size);
+                if let Some(tracked) = self.positions.get_mut(&modified.position) {
+                    tracked.size = size;
+                }
+
+                Some(BackfillRecord::Event(PositionEventRecord {
+                    id: uuid::Uuid::new_v4(),
+                    position_account: modified.position,
+                    kind: PositionEventKind::Modified,
+                    size: Some(size),
+                    margin: Some(from_fixed(modified.new_margin)),
+                    mark_price: None,
+                    detail: None,
+                    signature: Some(signature.to_string()),
+                    recorded_at: timestamp(modified.timestamp),
+                }))
+            }
+            OnChainEvent::PositionClosed(closed) => {
+                let tracked = self.positions.remove(&closed.position)?;
+                let amount = from_fixed_signed(closed.realized_pnl);
+                let close_price = implied_close_price(&tracked, amount);
+
+                Some(BackfillRecord::Realized(realized(
+                    closed.position,
+                    &tracked,
+                    tracked.size,
+                    close_price,
+                    amount,
+                    false,
+                    signature,
+                    closed.timestamp,
+                )))
+            }
+            OnChainEvent::PositionLiquidated(liquidated) => {
+                let tracked = if liquidated.remaining_size == 0 {
+                    self.positions.remove(&liquidated.position)?
+                } else {
+                    let tracked = self.positions.get_mut(&liquidated.position)?;
+                    tracked.size = from_fixed(liquidated.remaining_size);
+                    tracked.clone()
+                };
+
+                Some(BackfillRecord::Realized(realized(
+                    liquidated.position,
+                    &tracked,
+                    from_fixed(liquidated.closed_size),
+                    from_fixed(liquidated.mark_price),
+                    from_fixed_signed(liquidated.realized_pnl),
+                    true,
+                    signature,
+                    liquidated.timestamp,
+                )))
+            }
+            OnChainEvent::MarginCallIssued(issued) => Some(BackfillRecord::Event(PositionEventRecord {
+                id: uuid::Uuid::new_v4(),
+                position_account: issued.position,
+                kind: PositionEventKind::MarginCall,
+                size: None,
+                margin: None,
+                mark_price: None,
+                detail: None,
+                signature: Some(signature.to_string()),
+                recorded_at: timestamp(issued.timestamp),
+            })),
+            OnChainEvent::MarginCallCleared(cleared) => Some(BackfillRecord::Event(PositionEventRecord {
+                id: uuid::Uuid::new_v4(),
+                position_account: cleared.position,
+                kind: PositionEventKind::MarginCallCleared,
+                size: None,
+                margin: None,
+                mark_price: None,
+                detail: None,
+                signature: Some(signature.to_string()),
+                recorded_at: timestamp(cleared.timestamp),
+            })),
+        }
+    }
+}
+
+/// The position an event is about
+pub fn event_position(event: &OnChainEvent) -> Pubkey {
+    match event {
+        OnChainEvent::PositionOpened(e) => e.position,
+        OnChainEvent::PositionModified(e) => e.position,
+        OnChainEvent::PositionClosed(e) => e.position,
+        OnChainEvent::PositionLiquidated(e) => e.position,
+        OnChainEvent::MarginCallIssued(e) => e.position,
+        OnChainEvent::MarginCallCleared(e) => e.position,
+    }
+}
+
+/// Program events in a transaction's logs, in emit order
+pub fn events_from_logs(logs: &[String]) -> Vec<OnChainEvent> {
+    logs.iter()
+        .filter_map(|line| line.strip_prefix(PROGRAM_DATA_PREFIX))
+        .filter_map(|data| BASE64.decode(data).ok())
+        .filter_map(|data| match OnChainEvent::decode(&data)? {
+            Ok(event) => Some(event),
+            Err(e) => {
+                warn!("Skipping undecodable program event: {:#}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Position accounts named by a transaction's events, each once
+pub fn positions_in_logs(logs: &[String]) -> Vec<Pubkey> {
+    let mut positions = Vec::new();
+    for position in events_from_logs(logs).iter().map(OnChainEvent::position) {
+        if !positions.contains(&position) {
+            positions.push(position);
+        }
+    }
+    positions
+}
+
+#[allow(clippy::too_many_arguments)]
+fn realized(
+    position_account: Pubkey,
+    tracked: &TrackedPosition,
+    size_closed: Decimal,
+    close_price: Decimal,
+    amount: Decimal,
+    liquidation: bool,
+    signature: &str,
+    at: i64,
+) -> RealizedPnL {
+    RealizedPnL {
+        id: uuid::Uuid::new_v4(),
+        position_account,
+        owner: tracked.owner,
+        symbol: tracked.symbol.clone(),
+        side: tracked.side,
+        size_closed,
+        entry_price: tracked.entry_price,
+        close_price,
+        fee: Decimal::ZERO,
+        funding: Decimal::ZERO,
+        amount,
+        liquidation,
+        signature: signature.to_string(),
+        closed_at: timestamp(at),
+    }
+}
+
+/// Price at which closing the whole position realizes `amount`
+fn implied_close_price(tracked: &TrackedPosition, amount: Decimal) -> Decimal {
+    if tracked.size.is_zero() {
+        return tracked.entry_price;
+    }
+
+    let per_unit = amount / tracked.size;
+    match tracked.side {
+        Side::Long => tracked.entry_price + per_unit,
+        Side::Short => tracked.entry_price - per_unit,
+    }
+}
+
+fn side_from_chain(side: OnChainSide) -> Side {
+    match side {
+        OnChainSide::Long => Side::Long,
+        OnChainSide::Short => Side::Short,
+    }
+}
+
+// Program amounts carry 6 decimals
+fn from_fixed(value: u64) -> Decimal {
+    Decimal::new(value as i64, 6)
+}
+
+fn from_fixed_signed(value: i64) -> Decimal {
+    Decimal::new(value, 6)
+}
+
+fn timestamp(secs: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(secs, 0).unwrap_or_else(Utc::now)
+}
+
+pub struct BackfillService {
+    solana_client: Arc<SolanaClient>,
+    history_store: Arc<dyn HistoryStore>,
+    progress: RwLock<Option<BackfillProgress>>,
+}
+
+impl BackfillService {
+    pub fn new(solana_client: Arc<SolanaClient>, history_store: Arc<dyn HistoryStore>) -> Self {
+        Self {
+            solana_client,
+            history_store,
+            progress: RwLock::new(None),
+        }
+    }
+
+    /// The running or last backfill
+    pub async fn progress(&self) -> Option<BackfillProgress> {
+        self.progress.read().await.clone()
+    }
+
+    /// Start a backfill from `from_slot` in the background, none while one is running
+    pub async fn start(self: &Arc<Self>, from_slot: u64) -> Option<BackfillProgress> {
+        let progress = {
+            let mut current = self.progress.write().await;
+            if current.as_ref().is_some_and(|progress| progress.is_running()) {
+                return None;
+            }
+            let progress = BackfillProgress::new(from_slot);
+            *current = Some(progress.clone());
+            progress
+        };
+
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            info!("History backfill from slot {} started", from_slot);
+
+            let result = service.run(from_slot).await;
+
+            let mut current = service.progress.write().await;
+            if let Some(progress) = current.as_mut() {
+                progress.finished_at = Some(Utc::now());
+                match result {
+                    Ok(()) => {
+                        progress.state = BackfillState::Completed;
+                        info!(
+                            "History backfill done: {} transactions, {} opens, {} closes, {} events",
+                            progress.transactions_replayed,
+                            progress.opens_written,
+                            progress.realized_pnl_written,
+                            progress.events_written
+                        );
+                    }
+                    Err(e) => {
+                        error!("History backfill failed: {:#}", e);
+                        progress.state = BackfillState::Failed;
+                        progress.error = Some(format!("{:#}", e));
+                    }
+                }
+            }
+        });
+
+        Some(progress)
+    }
+
+    async fn update(&self, f: impl FnOnce(&mut BackfillProgress)) {
+        if let Some(progress) = self.progress.write().await.as_mut() {
+            f(progress);
+        }
+    }
+
+    async fn run(&self, from_slot: u64) -> Result<()> {
+        let signatures = self.signatures_since(from_slot)?;
+        let found = signatures.len() as u64;
+        self.update(|progress| {
+            progress.state = BackfillState::Replaying;
+            progress.transactions_found = found;
+        })
+        .await;
+
+        let mut ledger = BackfillLedger::default();
+
+        for (signature, slot) in signatures {
+            let logs = self.transaction_logs(&signature)?;
+
+            let mut written = BackfillProgress::new(from_slot);
+            for event in events_from_logs(&logs) {
+                let position_account = event_position(&event);
+                if !ledger.knows(&position_account)
+                    && matches!(event, OnChainEvent::PositionClosed(_) | OnChainEvent::PositionLiquidated(_))
+                {
+                    if let Some(open) = self.history_store.position_open(&position_account).await? {
+                        ledger.seed(&open);
+                    }
+                }
+
+                let signature = signature.to_string();
+                match ledger.apply(event, &signature) {
+                    Some(BackfillRecord::Open(position)) => {
+                        self.history_store.insert_position_open(&position, &signature).await?;
+                        written.opens_written += 1;
+                    }
+                    Some(BackfillRecord::Realized(record)) => {
+                        self.history_store.insert_realized_pnl(&record).await?;
+                        written.realized_pnl_written += 1;
+                    }
+                    Some(BackfillRecord::Event(record)) => {
+                        self.history_store.insert_position_event(&record).await?;
+                        written.events_written += 1;
+                    }
+                    None => {
+                        warn!("No open known for {}, skipping its close in {}", position_account, signature);
+                        written.skipped_closes += 1;
+                    }
+                }
+            }
+
+            self.update(|progress| {
+                progress.transactions_replayed += 1;
+                progress.current_slot = Some(slot);
+                progress.opens_written += written.opens_written;
+                progress.realized_pnl_written += written.realized_pnl_written;
+                progress.events_written += written.events_written;
+                progress.skipped_closes += written.skipped_closes;
+            })
+            .await;
+        }
+
+        Ok(())
+    }
+
+    /// Successful program transactions at or after `from_slot`, oldest first
+    fn signatures_since(&self, from_slot: u64) -> Result<Vec<(Signature, u64)>> {
+        let program_id = self.solana_client.program_id;
+        let mut signatures = Vec::new();
+        let mut before = None;
+
+        loop {
+            let page = self
+                .solana_client
+                .rpc_pool()
+                .read(|rpc_client| {
+                    let config = GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: Some(SIGNATURE_PAGE_LIMIT),
+                        commitment: Some(CommitmentConfig::finalized()),
+                    };
+                    rpc_client.get_signatures_for_address_with_config(&program_id, config)
+                })
+                .context("Failed to list program signatures")?;
+
+            let Some(last) = page.last() else {
+                break;
+            };
+            before = Some(Signature::from_str(&last.signature)?);
+            let reached_start = last.slot < from_slot;
+            let full_page = page.len() == SIGNATURE_PAGE_LIMIT;
+
+            for entry in page {
+                if entry.slot >= from_slot && entry.err.is_none() {
+                    signatures.push((Signature::from_str(&entry.signature)?, entry.slot));
+                }
+            }
+
+            if reached_start || !full_page {
+                break;
+            }
+        }
+
+        signatures.reverse();
+        Ok(signatures)
+    }
+
+    fn transaction_logs(&self, signature: &Signature) -> Result<Vec<String>> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: Some(CommitmentConfig::finalized()),
+            max_supported_transaction_version: Some(0),
+        };
+
+        let transaction = self
+            .solana_client
+            .rpc_pool()
+            .read(|rpc_client| rpc_client.get_transaction_with_config(signature, config))
+            .with_context(|| format!("Failed to fetch transaction {}", signature))?;
+
+        let meta = transaction
+            .transaction
+            .meta
+            .ok_or_else(|| anyhow!("Transaction {} has no status meta", signature))?;
+
+        Ok(Option::<Vec<String>>::from(meta.log_messages).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{OnChainPositionClosed, OnChainPositionLiquidated, OnChainPositionOpened};
+    use anchor_lang::AnchorSerialize;
+    use rust_decimal_macros::dec;
+
+    fn opened(position: Pubkey, side: OnChainSide) -> OnChainPositionOpened {
+        OnChainPositionOpened {
+            position,
+            owner: Pubkey::new_unique(),
+            symbol: "SOL/USD".to_string(),
+            side,
+            size: 10_000_000,
+            entry_price: 100_000_000,
+            leverage: 5,
+            margin: 200_000_000,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_events_from_logs() {
+        let position = Pubkey::new_unique();
+        // sha256("event:PositionClosed")[..8]
+        let mut data = vec![0x9d, 0xa3, 0xe3, 0xe4, 0x0d, 0x61, 0x8a, 0x79];
+        OnChainPositionClosed {
+            position,
+            owner: Pubkey::new_unique(),
+            realized_pnl: -5_000_000,
+            timestamp: 1_700_000_100,
+        }
+        .serialize(&mut data)
+        .unwrap();
+
+        let logs = vec![
+            "Program 9bca4kbDn7uyQWQaqfKpe8hCdbBh6KqJFNbkzwHhieC3 invoke [1]".to_string(),
+            format!("Program data: {}", BASE64.encode(&data)),
+            // Some other event
+            format!("Program data: {}", BASE64.encode([0u8; 16])),
+        ];
+
+        let events = events_from_logs(&logs);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], OnChainEvent::PositionClosed(e) if e.position == position));
+    }
+
+    #[test]
+    fn test_positions_in_logs() {
+        let encode = |event: &OnChainPositionOpened| {
+            // sha256("event:PositionOpened")[..8]
+            let mut data = vec![0xed, 0xaf, 0xf3, 0xe6, 0x93, 0x75, 0x65, 0x79];
+            event.serialize(&mut data).unwrap();
+            format!("Program data: {}", BASE64.encode(&data))
+        };
+
+        let first = opened(Pubkey::new_unique(), OnChainSide::Long);
+        let second = opened(Pubkey::new_unique(), OnChainSide::Short);
+        let logs = vec![encode(&first), encode(&second), encode(&first)];
+
+        assert_eq!(positions_in_logs(&logs), vec![first.position, second.position]);
+        assert!(positions_in_logs(&["Program log: Instruction: Deposit".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_ledger_prices_closes_from_replayed_open() {
+        let position = Pubkey::new_unique();
+        let mut ledger = BackfillLedger::default();
+
+        let Some(BackfillRecord::Open(open)) =
+            ledger.apply(OnChainEvent::PositionOpened(opened(position, OnChainSide::Short)), "open")
+        else {
+            panic!("expected an open");
+        };
+        assert_eq!(open.symbol, "SOL-USD");
+        assert_eq!(open.size, dec!(10));
+
+        // Partial liquidation of 4 at 105
+        let Some(BackfillRecord::Realized(partial)) = ledger.apply(
+            OnChainEvent::PositionLiquidated(OnChainPositionLiquidated {
+                position,
+                owner: open.owner,
+                liquidator: Pubkey::new_unique(),
+                mark_price: 105_000_000,
+                realized_pnl: -20_000_000,
+                closed_size: 4_000_000,
+                remaining_size: 6_000_000,
+                timestamp: 1_700_000_050,
+            }),
+            "partial",
+        ) else {
+            panic!("expected a realized record");
+        };
+        assert!(partial.liquidation);
+        assert_eq!(partial.size_closed, dec!(4));
+        assert_eq!(partial.close_price, dec!(105));
+
+        // Short closing the remaining 6 for +12 implies a 98 close
+        let Some(BackfillRecord::Realized(close)) = ledger.apply(
+            OnChainEvent::PositionClosed(OnChainPositionClosed {
+                position,
+                owner: open.owner,
+                realized_pnl: 12_000_000,
+                timestamp: 1_700_000_100,
+            }),
+            "close",
+        ) else {
+            panic!("expected a realized record");
+        };
+        assert_eq!(close.size_closed, dec!(6));
+        assert_eq!(close.close_price, dec!(98));
+        assert_eq!(close.signature, "close");
+        assert!(!ledger.knows(&position));
+    }
+
+    #[test]
+    fn test_ledger_skips_close_of_unknown_position() {
+        let mut ledger = BackfillLedger::default();
+        let record = ledger.apply(
+            OnChainEvent::PositionClosed(OnChainPositionClosed {
+                position: Pubkey::new_unique(),
+                owner: Pubkey::new_unique(),
+                realized_pnl: 0,
+                timestamp: 0,
+            }),
+            "close",
+        );
+        assert!(record.is_none());
+    }
+}
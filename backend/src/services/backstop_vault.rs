@@ -0,0 +1,312 @@
+/// Backstop Vault
+/// LPs deposit collateral into the program's backstop vault, which takes over
+/// liquidated positions at a discount to mark and keeps the spread
+/// This service marks the vault to market on an interval, valuing shares at
+/// NAV (holdings plus unrealized PnL of absorbed positions) rather than the
+/// program's book value, and snapshots it so LPs' returns can be charted
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use tracing::{info, warn};
+
+use crate::domain::Position;
+use crate::infrastructure::{HistoryStore, VaultSnapshot};
+use crate::services::{BackstopVaultData, PositionManager, PositionMonitor};
+
+#[derive(Debug, Clone)]
+pub struct BackstopVaultConfig {
+    pub nav_interval_ms: u64,
+    pub snapshot_interval_ms: u64,
+}
+
+impl Default for BackstopVaultConfig {
+    fn default() -> Self {
+        Self {
+            nav_interval_ms: 5_000,
+            snapshot_interval_ms: 60_000,
+        }
+    }
+}
+
+/// Vault marked to market, amounts in collateral units
+#[derive(Debug, Clone, PartialEq)]
+pub struct VaultNav {
+    /// Book value: LP deposits plus realized PnL
+    pub total_assets: Decimal,
+    /// Open PnL of absorbed positions, each floored at minus its margin
+    pub unrealized_pnl: Decimal,
+    pub nav: Decimal,
+    pub total_shares: Decimal,
+    pub share_price: Decimal,
+    pub open_positions: usize,
+    pub computed_at: DateTime<Utc>,
+}
+
+/// NAV from the vault's holdings and the open positions it owns
+pub fn vault_nav(vault: &BackstopVaultData, positions: &[Position], computed_at: DateTime<Utc>) -> VaultNav {
+    let open: Vec<&Position> = positions.iter().filter(|p| p.is_open()).collect();
+
+    // A position can't lose the vault more than the margin it locked
+    let unrealized_pnl: Decimal = open
+        .iter()
+        .map(|p| p.unrealized_pnl.max(-p.margin))
+        .sum();
+
+    let nav = (vault.total_assets + unrealized_pnl).max(Decimal::ZERO);
+    let share_price = if vault.total_shares.is_zero() {
+        Decimal::ONE
+    } else {
+        nav / vault.total_shares
+    };
+
+    VaultNav {
+        total_assets: vault.total_assets,
+        unrealized_pnl,
+        nav,
+        total_shares: vault.total_shares,
+        share_price,
+        open_positions: open.len(),
+        computed_at,
+    }
+}
+
+/// Share price change over a window
+#[derive(Debug, Clone, PartialEq)]
+pub struct VaultReturn {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub start_share_price: Decimal,
+    pub end_share_price: Decimal,
+    /// 0.012 = 1.2% over the window
+    pub period_return: Decimal,
+    /// Simple (not compounded) rate scaled to 365 days
+    pub annualized_return: Decimal,
+}
+
+/// Return between the first and last snapshot, none with fewer than two
+pub fn vault_return(snapshots: &[VaultSnapshot]) -> Option<VaultReturn> {
+    let (first, last) = (snapshots.first()?, snapshots.last()?);
+
+    let elapsed = last.recorded_at - first.recorded_at;
+    if elapsed <= Duration::zero() || first.share_price.is_zero() {
+        return None;
+    }
+
+    let period_return = last.share_price / first.share_price - Decimal::ONE;
+    let year = Decimal::from(Duration::days(365).num_seconds());
+    let annualized_return = period_return * year / Decimal::from(elapsed.num_seconds().max(1));
+
+    Some(VaultReturn {
+        from: first.recorded_at,
+        to: last.recorded_at,
+        start_share_price: first.share_price,
+        end_share_price: last.share_price,
+        period_return,
+        annualized_return,
+    })
+}
+
+/// Snapshot at NAV share price so returns include open exposure
+pub fn vault_snapshot(vault: &BackstopVaultData, nav: &VaultNav) -> VaultSnapshot {
+    VaultSnapshot {
+        total_assets: vault.total_assets,
+        total_shares: vault.total_shares,
+        locked_margin: vault.locked_margin,
+        share_price: nav.share_price,
+        recorded_at: nav.computed_at,
+    }
+}
+
+pub struct BackstopVaultService {
+    position_manager: Arc<PositionManager>,
+    monitor: Arc<PositionMonitor>,
+    history_store: Option<Arc<dyn HistoryStore>>,
+    latest_nav: RwLock<Option<VaultNav>>,
+    nav_tx: broadcast::Sender<VaultNav>,
+    config: BackstopVaultConfig,
+}
+
+impl BackstopVaultService {
+    pub fn new(
+        position_manager: Arc<PositionManager>,
+        monitor: Arc<PositionMonitor>,
+        config: BackstopVaultConfig,
+    ) -> Self {
+        let (nav_tx, _) = broadcast::channel(16);
+
+        Self {
+            position_manager,
+            monitor,
+            history_store: None,
+            latest_nav: RwLock::new(None),
+            nav_tx,
+            config,
+        }
+    }
+
+    /// Persist snapshots for `/vault/returns`
+    pub fn with_history_store(mut self, history_store: Arc<dyn HistoryStore>) -> Self {
+        self.history_store = Some(history_store);
+        self
+    }
+
+    /// Last computed NAV, none until the first interval completes
+    pub async fn latest_nav(&self) -> Option<VaultNav> {
+        self.latest_nav.read().await.clone()
+    }
+
+    pub fn subscribe_nav(&self) -> broadcast::Receiver<VaultNav> {
+        self.nav_tx.subscribe()
+    }
+
+    /// Compute NAV every interval on every node, snapshot it on the leader
+    pub fn spawn(self: Arc<Self>) {
+        let service = Arc::clone(&self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_millis(
+                service.config.nav_interval_ms,
+            ));
+
+            info!("Backstop vault NAV started");
+
+            loop {
+                ticker.tick().await;
+
+                if let Err(e) = service.refresh_nav().await {
+                    warn!("Failed to compute backstop vault NAV: {}", e);
+                }
+            }
+        });
+
+        if self.history_store.is_none() {
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_millis(
+                self.config.snapshot_interval_ms,
+            ));
+
+            info!("Backstop vault snapshots started");
+
+            loop {
+                ticker.tick().await;
+
+                if !self.monitor.is_leader() {
+                    continue;
+                }
+
+                if let Err(e) = self.snapshot().await {
+                    warn!("Failed to snapshot backstop vault: {}", e);
+                }
+            }
+        });
+    }
+
+    async fn compute_nav(&self) -> Result<(BackstopVaultData, VaultNav)> {
+        let vault = self.position_manager.get_backstop_vault().await?;
+        let positions = self
+            .monitor
+            .get_user_positions(&self.position_manager.backstop_vault_address())
+            .await?;
+
+        let nav = vault_nav(&vault, &positions, Utc::now());
+        Ok((vault, nav))
+    }
+
+    async fn refresh_nav(&self) -> Result<()> {
+        let (_, nav) = self.compute_nav().await?;
+
+        *self.latest_nav.write().await = Some(nav.clone());
+        // No receivers is fine, nobody is streaming
+        let _ = self.nav_tx.send(nav);
+
+        Ok(())
+    }
+
+    async fn snapshot(&self) -> Result<()> {
+        let Some(history_store) = &self.history_store else {
+            return Ok(());
+        };
+
+        let (vault, nav) = self.compute_nav().await?;
+        history_store.insert_vault_snapshot(&vault_snapshot(&vault, &nav)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::PositionStatus;
+    use rust_decimal_macros::dec;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn snapshot(days: i64, share_price: Decimal) -> VaultSnapshot {
+        VaultSnapshot {
+            total_assets: dec!(1000) * share_price,
+            total_shares: dec!(1000),
+            locked_margin: Decimal::ZERO,
+            share_price,
+            recorded_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap() + Duration::days(days),
+        }
+    }
+
+    #[test]
+    fn test_vault_return() {
+        let snapshots = [
+            snapshot(0, dec!(1.00)),
+            snapshot(3, dec!(0.99)),
+            snapshot(5, dec!(1.01)),
+        ];
+
+        let vault_return = vault_return(&snapshots).unwrap();
+        assert_eq!(vault_return.period_return, dec!(0.01));
+        assert_eq!(vault_return.annualized_return, dec!(0.73));
+
+        assert!(super::vault_return(&snapshots[..1]).is_none());
+        assert!(super::vault_return(&[]).is_none());
+    }
+
+    fn vault_position(unrealized_pnl: Decimal, margin: Decimal, status: PositionStatus) -> Position {
+        Position {
+            margin,
+            leverage: 5,
+            unrealized_pnl,
+            liquidation_price: dec!(80),
+            status,
+            ..Position::for_test()
+        }
+    }
+
+    #[test]
+    fn test_vault_nav() {
+        let vault = BackstopVaultData {
+            address: Pubkey::new_unique(),
+            total_assets: dec!(1000),
+            total_shares: dec!(800),
+            locked_margin: dec!(300),
+            realized_pnl: dec!(200),
+            discount_bps: 250,
+            open_positions: 2,
+            position_count_total: 2,
+            share_mint: Pubkey::new_unique(),
+        };
+        let positions = [
+            vault_position(dec!(60), dec!(200), PositionStatus::Open),
+            // Floored at its margin
+            vault_position(dec!(-150), dec!(100), PositionStatus::Open),
+            vault_position(dec!(500), dec!(100), PositionStatus::Closed),
+        ];
+
+        let nav = vault_nav(&vault, &positions, Utc::now());
+        assert_eq!(nav.unrealized_pnl, dec!(-40));
+        assert_eq!(nav.nav, dec!(960));
+        assert_eq!(nav.share_price, dec!(1.2));
+        assert_eq!(nav.open_positions, 2);
+
+        let empty = BackstopVaultData { total_shares: Decimal::ZERO, ..vault };
+        assert_eq!(vault_nav(&empty, &[], Utc::now()).share_price, Decimal::ONE);
+    }
+}
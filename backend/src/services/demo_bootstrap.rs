@@ -0,0 +1,271 @@
+/// Demo Bootstrap
+/// Populates a devnet deployment for people evaluating it in one call: fresh
+/// demo wallets funded from the faucet, each with a user account, collateral
+/// and a few positions across symbols opened at the oracle price
+/// Every wallet signs its own transactions through a PositionManager of its
+/// own. The service doesn't keep their keys, it hands each one back to the
+/// caller, who can then modify and close the demo positions
+use anyhow::Result;
+use rust_decimal::Decimal;
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::domain::{Position, Side};
+use crate::error::Error;
+use crate::infrastructure::{HistoryStore, SolanaClient};
+use crate::services::{PositionManager, PositionMonitor};
+
+// Most users and positions per user one bootstrap creates
+pub const MAX_DEMO_USERS: usize = 10;
+pub const MAX_DEMO_POSITIONS_PER_USER: usize = 5;
+
+// Leverage the demo positions cycle through
+const DEMO_LEVERAGES: [u16; 3] = [2, 5, 10];
+
+// Decimal places of demo position sizes
+const DEMO_SIZE_DP: u32 = 4;
+
+#[derive(Debug, Clone)]
+pub struct DemoBootstrapConfig {
+    pub users: usize,
+    /// Faucet SOL per wallet, for fees and account rent
+    pub airdrop_lamports: u64,
+    /// Collateral deposited per user, 6 decimals
+    pub collateral: u64,
+    /// Open positions per user, each on a different symbol
+    pub positions_per_user: usize,
+    /// USD notional of each position
+    pub notional: Decimal,
+}
+
+impl Default for DemoBootstrapConfig {
+    fn default() -> Self {
+        Self {
+            users: 3,
+            airdrop_lamports: LAMPORTS_PER_SOL,
+            collateral: 10_000_000_000,
+            positions_per_user: 2,
+            notional: Decimal::from(1000),
+        }
+    }
+}
+
+/// A position the bootstrap opens for one demo user
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemoOrder {
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub leverage: u16,
+    pub entry_price: Decimal,
+}
+
+/// `positions` orders for the user at `user_index`, over `prices` by symbol.
+/// Users start one symbol further along and alternate the first side, so the
+/// book comes out spread over symbols, sides and leverage. One order per
+/// symbol, the program throttles repeated opens on the same market
+pub fn demo_orders(
+    user_index: usize,
+    prices: &[(String, Decimal)],
+    positions: usize,
+    notional: Decimal,
+) -> Vec<DemoOrder> {
+    let prices: Vec<&(String, Decimal)> = prices.iter().filter(|(_, price)| *price > Decimal::ZERO).collect();
+    if prices.is_empty() {
+        return Vec::new();
+    }
+
+    (0..positions.min(prices.len()))
+        .filter_map(|i| {
+            let (symbol, price) = prices[(user_index + i) % prices.len()];
+            let size = (notional / price).round_dp(DEMO_SIZE_DP);
+            if size.is_zero() {
+                return None;
+            }
+
+            Some(DemoOrder {
+                symbol: symbol.clone(),
+                side: if (user_index + i).is_multiple_of(2) { Side::Long } else { Side::Short },
+                size,
+                leverage: DEMO_LEVERAGES[(user_index + i) % DEMO_LEVERAGES.len()],
+                entry_price: *price,
+            })
+        })
+        .collect()
+}
+
+/// What the bootstrap did for one demo wallet. A failed step ends that user's
+/// setup, a failed open skips only that position
+#[derive(Debug, Clone)]
+pub struct DemoUser {
+    pub owner: Pubkey,
+    /// The wallet's 64-byte keypair, the only copy of it
+    pub keypair: Vec<u8>,
+    pub airdrop_signature: Option<Signature>,
+    pub collateral: u64,
+    pub positions: Vec<Position>,
+    pub errors: Vec<String>,
+}
+
+pub struct DemoBootstrap {
+    solana_client: Arc<SolanaClient>,
+    monitor: Arc<PositionMonitor>,
+    history_store: Option<Arc<dyn HistoryStore>>,
+}
+
+impl DemoBootstrap {
+    pub fn new(solana_client: Arc<SolanaClient>, monitor: Arc<PositionMonitor>) -> Self {
+        Self {
+            solana_client,
+            monitor,
+            history_store: None,
+        }
+    }
+
+    /// Record the demo opens like any other
+    pub fn with_history_store(mut self, history_store: Arc<dyn HistoryStore>) -> Self {
+        self.history_store = Some(history_store);
+        self
+    }
+
+    /// Create and populate `config.users` demo users, one after the other
+    pub async fn run(&self, config: &DemoBootstrapConfig) -> Result<Vec<DemoUser>> {
+        let mut prices = Vec::new();
+        for symbol in self.monitor.get_monitored_symbols().await {
+            if let Some(price) = self.monitor.get_cached_price(&symbol).await {
+                prices.push((symbol, price));
+            }
+        }
+        prices.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        if prices.is_empty() {
+            return Err(Error::Oracle("No oracle prices yet to open demo positions at".to_string()).into());
+        }
+
+        let mut users = Vec::with_capacity(config.users);
+        for user_index in 0..config.users {
+            let orders = demo_orders(user_index, &prices, config.positions_per_user, config.notional);
+            users.push(self.bootstrap_user(config, orders).await);
+        }
+
+        info!(
+            "Demo bootstrap created {} users with {} positions",
+            users.len(),
+            users.iter().map(|user| user.positions.len()).sum::<usize>()
+        );
+
+        Ok(users)
+    }
+
+    async fn bootstrap_user(&self, config: &DemoBootstrapConfig, orders: Vec<DemoOrder>) -> DemoUser {
+        let keypair = Keypair::new();
+        let owner = keypair.pubkey();
+
+        let mut user = DemoUser {
+            owner,
+            keypair: keypair.to_bytes().to_vec(),
+            airdrop_signature: None,
+            collateral: 0,
+            positions: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        let solana_client = Arc::new(self.solana_client.for_signer(keypair));
+        let mut position_manager = PositionManager::new(Arc::clone(&solana_client), Arc::clone(&self.monitor));
+        if let Some(history_store) = &self.history_store {
+            position_manager = position_manager.with_history_store(Arc::clone(history_store));
+        }
+
+        match solana_client.request_airdrop(&owner, config.airdrop_lamports) {
+            Ok(signature) => user.airdrop_signature = Some(signature),
+            Err(e) => {
+                warn!("Demo user {} airdrop failed: {:#}", owner, e);
+                user.errors.push(format!("Airdrop failed: {:#}", e));
+                return user;
+            }
+        }
+
+        if let Err(e) = position_manager.initialize_user(&owner).await {
+            user.errors.push(format!("Initializing the user account failed: {:#}", e));
+            return user;
+        }
+
+        if let Err(e) = position_manager.add_collateral(&owner, config.collateral).await {
+            user.errors.push(format!("Depositing collateral failed: {:#}", e));
+            return user;
+        }
+        user.collateral = config.collateral;
+
+        for order in orders {
+            let maintenance_margin_ratio = self.monitor.maintenance_margin_ratio(&order.symbol);
+            match position_manager
+                .open_position(
+                    owner,
+                    order.symbol.clone(),
+                    order.side,
+                    order.size,
+                    order.leverage,
+                    order.entry_price,
+                    maintenance_margin_ratio,
+                )
+                .await
+            {
+                Ok((position, _)) => user.positions.push(position),
+                Err(e) => user.errors.push(format!("Opening {} failed: {:#}", order.symbol, e)),
+            }
+        }
+
+        user
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn prices() -> Vec<(String, Decimal)> {
+        vec![
+            ("BTC-USD".to_string(), dec!(50000)),
+            ("ETH-USD".to_string(), dec!(2500)),
+            ("SOL-USD".to_string(), dec!(100)),
+        ]
+    }
+
+    #[test]
+    fn test_demo_orders_spread_users() {
+        let first = demo_orders(0, &prices(), 2, dec!(1000));
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].symbol, "BTC-USD");
+        assert_eq!(first[0].side, Side::Long);
+        assert_eq!(first[0].size, dec!(0.02));
+        assert_eq!(first[0].leverage, 2);
+        assert_eq!(first[1].symbol, "ETH-USD");
+        assert_eq!(first[1].side, Side::Short);
+        assert_eq!(first[1].size, dec!(0.4));
+
+        let second = demo_orders(1, &prices(), 2, dec!(1000));
+        assert_eq!(second[0].symbol, "ETH-USD");
+        assert_eq!(second[0].side, Side::Short);
+        assert_eq!(second[1].symbol, "SOL-USD");
+        assert_eq!(second[1].entry_price, dec!(100));
+    }
+
+    #[test]
+    fn test_demo_orders_one_per_symbol() {
+        let orders = demo_orders(0, &prices(), 5, dec!(1000));
+        assert_eq!(orders.len(), 3);
+
+        // Too small a notional for a 4 dp BTC size
+        let orders = demo_orders(0, &prices(), 1, dec!(1));
+        assert!(orders.is_empty());
+
+        assert!(demo_orders(0, &[], 2, dec!(1000)).is_empty());
+    }
+}
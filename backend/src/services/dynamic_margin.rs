@@ -0,0 +1,301 @@
+/// Dynamic Margin
+/// Realized volatility per symbol from the archived oracle ticks, bucketed
+/// into candles, mapped to a maintenance rate and cranked into the symbol's
+/// on-chain Market within its bounds, so the rate follows the market's regime
+/// The lower bound holds at the baseline volatility and the rate scales with
+/// volatility above it. The leader cranks, and only once the target moves a
+/// full step from the current rate. The program caps each crank's move and
+/// the move per window, so a far target is reached over several cranks
+/// Every node floors the monitor's maintenance ratio at the Market rate, so
+/// liquidation alerts match what the program accepts
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, MathematicalOps};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use crate::infrastructure::{last_tick_per_bucket, HistoryStore, PriceTick};
+use crate::services::{MarginCalculator, MarketData, PositionManager, PositionMonitor};
+
+const BPS_DENOMINATOR: u32 = 10_000;
+// The program's caps on the maintenance crank (constants.rs)
+const MAX_MAINTENANCE_MARGIN_STEP_BPS: u16 = 50;
+const MAINTENANCE_MARGIN_WINDOW_SECS: i64 = 3_600;
+const MAX_MAINTENANCE_MARGIN_WINDOW_BPS: u16 = 150;
+// Ticks one volatility read takes, a day of one-second ticks fits
+const MAX_VOLATILITY_TICKS: i64 = 100_000;
+
+#[derive(Debug, Clone)]
+pub struct DynamicMarginConfig {
+    pub crank_interval_secs: u64,
+    /// Window the volatility is measured over
+    pub lookback: Duration,
+    pub candle: Duration,
+    /// Daily volatility at which a market sits at its lower bound
+    pub baseline_volatility: Decimal,
+    /// Smallest move of the rate worth a transaction
+    pub min_step_bps: u16,
+}
+
+impl Default for DynamicMarginConfig {
+    fn default() -> Self {
+        Self {
+            crank_interval_secs: 300,
+            lookback: Duration::hours(24),
+            candle: Duration::minutes(5),
+            baseline_volatility: Decimal::new(3, 2),
+            min_step_bps: 10,
+        }
+    }
+}
+
+/// Daily volatility of `ticks` (oldest first) from the returns of `candle`
+/// closes, none with too few candles to tell
+pub fn realized_volatility(ticks: Vec<PriceTick>, candle: Duration) -> Option<Decimal> {
+    let closes: Vec<Decimal> = last_tick_per_bucket(ticks, candle)
+        .into_iter()
+        .map(|tick| tick.price)
+        .collect();
+    if closes.len() < 3 {
+        return None;
+    }
+
+    let candles_per_day = Decimal::from(Duration::days(1).num_seconds() / candle.num_seconds().max(1));
+    let per_candle = MarginCalculator::candle_volatility(&closes);
+
+    Some(per_candle * candles_per_day.sqrt()?)
+}
+
+/// Rate for a market at `volatility`: its lower bound scaled by how far
+/// volatility is above the baseline, within the bounds
+pub fn target_maintenance_bps(
+    volatility: Decimal,
+    baseline_volatility: Decimal,
+    min_bps: u16,
+    max_bps: u16,
+) -> u16 {
+    if baseline_volatility <= Decimal::ZERO {
+        return min_bps;
+    }
+
+    let scale = (volatility / baseline_volatility).max(Decimal::ONE);
+    (Decimal::from(min_bps) * scale)
+        .ceil()
+        .to_u16()
+        .unwrap_or(max_bps)
+        .clamp(min_bps, max_bps)
+}
+
+/// `target` moved no further from the market's rate than one crank may take
+/// it at `now`, the current rate when the window's move is used up
+pub fn capped_maintenance_bps(market: &MarketData, target: u16, now: DateTime<Utc>) -> u16 {
+    let current = market.maintenance_margin_bps;
+    let window_start_bps = if now.timestamp()
+        >= market.rate_window_start.timestamp().saturating_add(MAINTENANCE_MARGIN_WINDOW_SECS)
+    {
+        current
+    } else {
+        market.rate_window_start_bps
+    };
+
+    let low = current
+        .saturating_sub(MAX_MAINTENANCE_MARGIN_STEP_BPS)
+        .max(window_start_bps.saturating_sub(MAX_MAINTENANCE_MARGIN_WINDOW_BPS))
+        .max(market.min_maintenance_margin_bps);
+    let high = current
+        .saturating_add(MAX_MAINTENANCE_MARGIN_STEP_BPS)
+        .min(window_start_bps.saturating_add(MAX_MAINTENANCE_MARGIN_WINDOW_BPS))
+        .min(market.max_maintenance_margin_bps);
+    if low > high {
+        return current;
+    }
+    target.clamp(low, high)
+}
+
+pub struct DynamicMarginService {
+    position_manager: Arc<PositionManager>,
+    monitor: Arc<PositionMonitor>,
+    history_store: Arc<dyn HistoryStore>,
+    config: DynamicMarginConfig,
+}
+
+impl DynamicMarginService {
+    pub fn new(
+        position_manager: Arc<PositionManager>,
+        monitor: Arc<PositionMonitor>,
+        history_store: Arc<dyn HistoryStore>,
+        config: DynamicMarginConfig,
+    ) -> Self {
+        Self {
+            position_manager,
+            monitor,
+            history_store,
+            config,
+        }
+    }
+
+    /// Crank on an interval in the background
+    pub fn spawn(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(std::time::Duration::from_secs(self.config.crank_interval_secs));
+
+            info!("Dynamic margin started");
+
+            loop {
+                ticker.tick().await;
+
+                for symbol in self.monitor.get_monitored_symbols().await {
+                    if let Err(e) = self.update_symbol(&symbol).await {
+                        warn!("Dynamic margin update for {} failed: {}", symbol, e);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Daily volatility of `symbol` over the lookback, none without enough history
+    pub async fn volatility(&self, symbol: &str) -> Result<Option<Decimal>> {
+        let to = Utc::now();
+        let ticks = self
+            .history_store
+            .symbol_price_ticks(symbol, to - self.config.lookback, to, MAX_VOLATILITY_TICKS)
+            .await?;
+
+        Ok(realized_volatility(ticks, self.config.candle))
+    }
+
+    /// Rate the crank would set for `market` at `volatility`
+    pub fn target_for(&self, market: &MarketData, volatility: Decimal) -> u16 {
+        target_maintenance_bps(
+            volatility,
+            self.config.baseline_volatility,
+            market.min_maintenance_margin_bps,
+            market.max_maintenance_margin_bps,
+        )
+    }
+
+    async fn update_symbol(&self, symbol: &str) -> Result<()> {
+        let Some(market) = self.position_manager.get_market(symbol).await? else {
+            debug!("No Market for {}, its tiers apply", symbol);
+            return Ok(());
+        };
+
+        self.monitor
+            .symbol_configs()
+            .set_market_floor(symbol, bps_to_ratio(market.maintenance_margin_bps));
+
+        if !self.monitor.is_leader() {
+            return Ok(());
+        }
+
+        let Some(volatility) = self.volatility(symbol).await? else {
+            debug!("Not enough price history for {} volatility", symbol);
+            return Ok(());
+        };
+
+        let target = self.target_for(&market, volatility);
+        if target.abs_diff(market.maintenance_margin_bps) < self.config.min_step_bps {
+            return Ok(());
+        }
+        let target = capped_maintenance_bps(&market, target, Utc::now());
+        if target == market.maintenance_margin_bps {
+            debug!("{} maintenance margin moved as far as this window allows", symbol);
+            return Ok(());
+        }
+
+        info!(
+            "{} daily volatility {}, maintenance margin {} -> {} bps",
+            symbol,
+            volatility.round_dp(4),
+            market.maintenance_margin_bps,
+            target
+        );
+
+        self.position_manager.set_maintenance_margin(symbol, target).await?;
+        self.monitor
+            .symbol_configs()
+            .set_market_floor(symbol, bps_to_ratio(target));
+
+        Ok(())
+    }
+}
+
+fn bps_to_ratio(bps: u16) -> Decimal {
+    Decimal::from(bps) / Decimal::from(BPS_DENOMINATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn tick(secs: i64, price: Decimal) -> PriceTick {
+        PriceTick {
+            symbol: "SOL-USD".to_string(),
+            price,
+            conf: None,
+            publish_time: None,
+            recorded_at: DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_realized_volatility() {
+        let candle = Duration::minutes(5);
+        assert_eq!(realized_volatility(vec![tick(0, dec!(100)), tick(300, dec!(101))], candle), None);
+
+        // Flat market
+        let flat = (0..10).map(|i| tick(i * 300, dec!(100))).collect();
+        assert_eq!(realized_volatility(flat, candle), Some(Decimal::ZERO));
+
+        // Alternating +1% / -1% five-minute candles, 288 a day
+        let prices = [dec!(100), dec!(101), dec!(99.99), dec!(100.9899), dec!(99.980001)];
+        let ticks = prices.iter().enumerate().map(|(i, p)| tick(i as i64 * 300, *p)).collect();
+        let daily = realized_volatility(ticks, candle).unwrap();
+        assert_eq!(daily.round_dp(3), dec!(0.196));
+    }
+
+    #[test]
+    fn test_target_maintenance_bps() {
+        // At or under the baseline, the lower bound
+        assert_eq!(target_maintenance_bps(dec!(0.01), dec!(0.03), 250, 1_000), 250);
+        assert_eq!(target_maintenance_bps(dec!(0.03), dec!(0.03), 250, 1_000), 250);
+
+        // Twice the baseline, twice the rate
+        assert_eq!(target_maintenance_bps(dec!(0.06), dec!(0.03), 250, 1_000), 500);
+
+        // Capped at the upper bound
+        assert_eq!(target_maintenance_bps(dec!(0.6), dec!(0.03), 250, 1_000), 1_000);
+    }
+
+    #[test]
+    fn test_capped_maintenance_bps() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut market = MarketData {
+            address: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            maintenance_margin_bps: 600,
+            min_maintenance_margin_bps: 500,
+            max_maintenance_margin_bps: 1_000,
+            updated_at: now,
+            open_cooldown_slots: 0,
+            max_opens_per_slot: 0,
+            rate_window_start: now - Duration::minutes(10),
+            rate_window_start_bps: 500,
+        };
+
+        // One step at a time, then what is left of the window
+        assert_eq!(capped_maintenance_bps(&market, 1_000, now), 650);
+        assert_eq!(capped_maintenance_bps(&market, 520, now), 550);
+        market.maintenance_margin_bps = 630;
+        assert_eq!(capped_maintenance_bps(&market, 1_000, now), 650);
+        market.maintenance_margin_bps = 650;
+        assert_eq!(capped_maintenance_bps(&market, 1_000, now), 650);
+
+        // A new window starts from the current rate
+        assert_eq!(capped_maintenance_bps(&market, 1_000, now + Duration::hours(1)), 700);
+    }
+}
@@ -0,0 +1,437 @@
+/// Event Bus
+/// Every event the backend broadcasts is a `DomainEvent` and goes through an
+/// `EventBus`, so a new event type is a new variant rather than another
+/// channel threaded through every constructor. `LocalEventBus` delivers in
+/// process; `RedisEventBus` fans events out across API replicas through Redis
+/// pub/sub so any replica behind the load balancer can serve any client
+use anyhow::{Context, Result};
+use futures::future::select_all;
+use futures::StreamExt;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+
+use crate::services::{
+    LiquidationAlert, PortfolioUpdate, PositionAlert, PositionMonitor, PositionUpdate,
+    PriceAlertTriggered, PriceUpdate, RiskLimitBreach,
+};
+
+const EVENT_CHANNEL: &str = "perps:events";
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Events waiting for the Redis writer before new ones are dropped
+const OUTGOING_CAPACITY: usize = 4096;
+
+/// Event types, each with its own buffer so a flood of one can't push
+/// another out from under a slow subscriber
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Price,
+    Position,
+    Liquidation,
+    PositionAlert,
+    PriceAlert,
+    RiskLimit,
+    Portfolio,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 7] = [
+        EventKind::Price,
+        EventKind::Position,
+        EventKind::Liquidation,
+        EventKind::PositionAlert,
+        EventKind::PriceAlert,
+        EventKind::RiskLimit,
+        EventKind::Portfolio,
+    ];
+
+    /// Events a subscriber can fall behind by before it skips ahead
+    fn capacity(self) -> usize {
+        match self {
+            EventKind::Price => 100,
+            EventKind::Position
+            | EventKind::Liquidation
+            | EventKind::PositionAlert
+            | EventKind::PriceAlert
+            | EventKind::RiskLimit
+            | EventKind::Portfolio => 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum DomainEvent {
+    Price(PriceUpdate),
+    Position(PositionUpdate),
+    Liquidation(LiquidationAlert),
+    PositionAlert(PositionAlert),
+    PriceAlert(PriceAlertTriggered),
+    RiskLimitBreached(RiskLimitBreach),
+    Portfolio(PortfolioUpdate),
+}
+
+impl DomainEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            DomainEvent::Price(_) => EventKind::Price,
+            DomainEvent::Position(_) => EventKind::Position,
+            DomainEvent::Liquidation(_) => EventKind::Liquidation,
+            DomainEvent::PositionAlert(_) => EventKind::PositionAlert,
+            DomainEvent::PriceAlert(_) => EventKind::PriceAlert,
+            DomainEvent::RiskLimitBreached(_) => EventKind::RiskLimit,
+            DomainEvent::Portfolio(_) => EventKind::Portfolio,
+        }
+    }
+
+    /// Market the event belongs to, none for account-wide events
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            DomainEvent::Price(update) => Some(&update.symbol),
+            DomainEvent::Position(update) => Some(&update.symbol),
+            DomainEvent::Liquidation(alert) => Some(&alert.symbol),
+            DomainEvent::PositionAlert(alert) => Some(&alert.symbol),
+            DomainEvent::PriceAlert(alert) => Some(&alert.alert.spec.symbol),
+            DomainEvent::RiskLimitBreached(breach) => breach.symbol.as_deref(),
+            DomainEvent::Portfolio(_) => None,
+        }
+    }
+}
+
+pub trait EventBus: Send + Sync {
+    /// Deliver `event` to the current subscribers of its kind
+    fn publish(&self, event: DomainEvent);
+
+    /// Receiver for one kind of event
+    fn receiver(&self, kind: EventKind) -> broadcast::Receiver<DomainEvent>;
+
+    /// Receiver merging several kinds of event
+    fn subscribe(&self, kinds: &[EventKind]) -> EventSubscription {
+        EventSubscription {
+            receivers: kinds.iter().map(|kind| self.receiver(*kind)).collect(),
+        }
+    }
+}
+
+/// Events of several kinds, each kind lagging independently
+pub struct EventSubscription {
+    receivers: Vec<broadcast::Receiver<DomainEvent>>,
+}
+
+impl EventSubscription {
+    /// Next event of any subscribed kind, `Closed` once the bus is gone
+    pub async fn recv(&mut self) -> Result<DomainEvent, RecvError> {
+        if self.receivers.is_empty() {
+            return Err(RecvError::Closed);
+        }
+
+        let (event, _, _) =
+            select_all(self.receivers.iter_mut().map(|receiver| Box::pin(receiver.recv()))).await;
+        event
+    }
+}
+
+/// In-process bus, one broadcast channel per event kind
+pub struct LocalEventBus {
+    senders: Vec<(EventKind, broadcast::Sender<DomainEvent>)>,
+}
+
+impl Default for LocalEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalEventBus {
+    pub fn new() -> Self {
+        Self {
+            senders: EventKind::ALL
+                .iter()
+                .map(|kind| (*kind, broadcast::channel(kind.capacity()).0))
+                .collect(),
+        }
+    }
+
+    fn sender(&self, kind: EventKind) -> &broadcast::Sender<DomainEvent> {
+        self.senders
+            .iter()
+            .find(|(sender_kind, _)| *sender_kind == kind)
+            .map(|(_, sender)| sender)
+            .expect("every event kind has a channel")
+    }
+
+    /// Handle sharing this bus's channels, for tasks that outlive a borrow
+    fn clone_senders(&self) -> LocalEventBus {
+        LocalEventBus {
+            senders: self.senders.clone(),
+        }
+    }
+}
+
+impl EventBus for LocalEventBus {
+    fn publish(&self, event: DomainEvent) {
+        let _ = self.sender(event.kind()).send(event);
+    }
+
+    fn receiver(&self, kind: EventKind) -> broadcast::Receiver<DomainEvent> {
+        self.sender(kind).subscribe()
+    }
+}
+
+/// Bus shared by every replica through a Redis channel. Published events go
+/// to Redis and come back through the bridge, on this node as on the others
+pub struct RedisEventBus {
+    redis_client: redis::Client,
+    local: LocalEventBus,
+    outgoing_tx: mpsc::Sender<DomainEvent>,
+    outgoing_rx: Mutex<Option<mpsc::Receiver<DomainEvent>>>,
+}
+
+impl RedisEventBus {
+    pub fn new(redis_url: String) -> Result<Self> {
+        let redis_client =
+            redis::Client::open(redis_url).context("Failed to create Redis client")?;
+        let (outgoing_tx, outgoing_rx) = mpsc::channel(OUTGOING_CAPACITY);
+
+        Ok(Self {
+            redis_client,
+            local: LocalEventBus::new(),
+            outgoing_tx,
+            outgoing_rx: Mutex::new(Some(outgoing_rx)),
+        })
+    }
+
+    /// Forward events produced by the local monitor to Redis
+    /// Only the leader publishes so replicas don't fan out duplicates
+    pub fn spawn_publisher(&self, monitor: &Arc<PositionMonitor>) {
+        let outgoing_tx = self.outgoing_tx.clone();
+        let monitor = Arc::clone(monitor);
+        let mut events = monitor.events().subscribe(&EventKind::ALL);
+
+        tokio::spawn(async move {
+            info!("Event bus publisher started");
+
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Event bus publisher lagged, skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                if monitor.is_leader() {
+                    queue_outgoing(&outgoing_tx, event);
+                }
+            }
+        });
+    }
+
+    /// Bridge the Redis channel both ways: published events out to Redis, and
+    /// Redis back into the local channels, reconnecting on failure
+    pub fn spawn_bridge(&self) {
+        if let Some(outgoing_rx) = self.outgoing_rx.lock().unwrap().take() {
+            self.spawn_writer(outgoing_rx);
+        }
+
+        let redis_client = self.redis_client.clone();
+        let local = self.local.clone_senders();
+
+        tokio::spawn(async move {
+            loop {
+                let mut pubsub = match redis_client.get_async_connection().await {
+                    Ok(conn) => conn.into_pubsub(),
+                    Err(e) => {
+                        error!("Event bus bridge failed to connect to Redis: {}", e);
+                        sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                };
+
+                if let Err(e) = pubsub.subscribe(EVENT_CHANNEL).await {
+                    error!("Event bus bridge failed to subscribe: {}", e);
+                    sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+
+                info!("Event bus bridge subscribed to {}", EVENT_CHANNEL);
+
+                let mut messages = pubsub.on_message();
+                while let Some(msg) = messages.next().await {
+                    let payload: String = match msg.get_payload() {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            warn!("Invalid bus payload: {}", e);
+                            continue;
+                        }
+                    };
+
+                    match serde_json::from_str::<DomainEvent>(&payload) {
+                        Ok(event) => local.publish(event),
+                        Err(e) => warn!("Failed to decode bus event: {}", e),
+                    }
+                }
+
+                warn!("Event bus bridge disconnected, reconnecting");
+                sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+
+    /// Publish queued events to Redis, reconnecting after a failed connect or
+    /// publish. Events queued while disconnected wait, up to the channel's capacity
+    fn spawn_writer(&self, mut outgoing_rx: mpsc::Receiver<DomainEvent>) {
+        let redis_client = self.redis_client.clone();
+
+        tokio::spawn(async move {
+            'connect: loop {
+                let mut conn = match redis_client.get_multiplexed_async_connection().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        error!("Event bus writer failed to connect to Redis: {}", e);
+                        sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                };
+
+                info!("Event bus writer connected to Redis");
+
+                while let Some(event) = outgoing_rx.recv().await {
+                    let payload = match serde_json::to_string(&event) {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            error!("Failed to serialize bus event: {}", e);
+                            continue;
+                        }
+                    };
+
+                    if let Err(e) = conn.publish::<_, _, ()>(EVENT_CHANNEL, payload).await {
+                        warn!("Failed to publish bus event, reconnecting: {}", e);
+                        sleep(RECONNECT_DELAY).await;
+                        continue 'connect;
+                    }
+                }
+
+                // Every sender is gone, nothing more will be published
+                break;
+            }
+        });
+    }
+}
+
+/// Hand an event to the Redis writer, dropping it while the writer is too far
+/// behind rather than buffering without bound
+fn queue_outgoing(outgoing_tx: &mpsc::Sender<DomainEvent>, event: DomainEvent) {
+    match outgoing_tx.try_send(event) {
+        Ok(()) => {}
+        Err(TrySendError::Full(event)) => {
+            warn!("Event bus writer backed up, dropping {:?} event", event.kind());
+        }
+        Err(TrySendError::Closed(event)) => {
+            error!("Event bus writer stopped, dropping {:?} event", event.kind());
+        }
+    }
+}
+
+impl EventBus for RedisEventBus {
+    fn publish(&self, event: DomainEvent) {
+        queue_outgoing(&self.outgoing_tx, event);
+    }
+
+    fn receiver(&self, kind: EventKind) -> broadcast::Receiver<DomainEvent> {
+        self.local.receiver(kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Risk, Side};
+    use chrono::Utc;
+    use rust_decimal::Decimal;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn liquidation_alert() -> LiquidationAlert {
+        LiquidationAlert {
+            position_account: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Short,
+            liquidation_price: Decimal::new(15025, 2),
+            current_price: Decimal::new(15100, 2),
+            risk_type: Risk::Liquidated,
+            margin_call_deadline: None,
+            owner: Some(Pubkey::new_unique()),
+            estimated_loss: Some(Decimal::new(95, 1)),
+            margin_ratio: None,
+            margin_ratio_tier: None,
+        }
+    }
+
+    fn price(symbol: &str) -> DomainEvent {
+        DomainEvent::Price(PriceUpdate {
+            symbol: symbol.to_string(),
+            price: Decimal::new(150, 0),
+            timestamp: Utc::now(),
+            publish_time: None,
+        })
+    }
+
+    #[test]
+    fn test_full_writer_queue_drops_events() {
+        let (outgoing_tx, mut outgoing_rx) = mpsc::channel(1);
+
+        queue_outgoing(&outgoing_tx, price("SOL-USD"));
+        queue_outgoing(&outgoing_tx, price("BTC-USD"));
+
+        assert_eq!(outgoing_rx.try_recv().unwrap().symbol(), Some("SOL-USD"));
+        assert!(outgoing_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_bus_event_roundtrip() {
+        let alert = liquidation_alert();
+
+        let payload = serde_json::to_string(&DomainEvent::Liquidation(alert.clone())).unwrap();
+
+        match serde_json::from_str::<DomainEvent>(&payload).unwrap() {
+            DomainEvent::Liquidation(decoded) => {
+                assert_eq!(decoded.position_account, alert.position_account);
+                assert_eq!(decoded.liquidation_price, alert.liquidation_price);
+                assert_eq!(decoded.side, Side::Short);
+            }
+            _ => panic!("expected a liquidation event"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_local_bus_buffers_kinds_separately() {
+        let bus = LocalEventBus::new();
+        let mut liquidations = bus.receiver(EventKind::Liquidation);
+        let mut events = bus.subscribe(&[EventKind::Price, EventKind::Liquidation]);
+
+        bus.publish(DomainEvent::Liquidation(liquidation_alert()));
+        // Overrun the price buffer, the alert must survive it
+        for _ in 0..=EventKind::Price.capacity() {
+            bus.publish(price("SOL-USD"));
+        }
+
+        assert_eq!(liquidations.try_recv().unwrap().kind(), EventKind::Liquidation);
+        assert!(liquidations.try_recv().is_err());
+
+        let mut kinds = Vec::new();
+        loop {
+            match tokio::time::timeout(Duration::from_millis(10), events.recv()).await {
+                Ok(Ok(event)) => kinds.push(event.kind()),
+                Ok(Err(RecvError::Lagged(_))) => continue,
+                _ => break,
+            }
+        }
+        assert!(kinds.contains(&EventKind::Liquidation));
+        assert!(kinds.contains(&EventKind::Price));
+    }
+}
@@ -0,0 +1,340 @@
+/// External Positions
+/// Positions a user holds off-platform (a CEX, another venue), imported so a
+/// risk desk sees them next to the on-chain book. They are marked with the
+/// monitor's oracle prices and never touch the chain. An import replaces the
+/// owner's whole external book, kept in one Redis hash per owner. Imports and
+/// clears are signed by the owner's wallet
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::domain::{Position, Side};
+use crate::error::{Error, FieldError};
+use crate::services::MarginCalculator;
+
+/// Enforced by the API before `replace`
+pub const MAX_EXTERNAL_POSITIONS_PER_USER: usize = 200;
+
+/// Message the wallet signs to replace its external book
+pub fn import_external_positions_message(owner: &Pubkey, timestamp: i64) -> String {
+    format!("Import external positions for {} at {}", owner, timestamp)
+}
+
+/// Message the wallet signs to drop its external book
+pub fn clear_external_positions_message(owner: &Pubkey, timestamp: i64) -> String {
+    format!("Clear external positions for {} at {}", owner, timestamp)
+}
+
+fn external_positions_key(owner: &Pubkey) -> String {
+    format!("external_positions:user:{}", owner)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalPosition {
+    pub id: Uuid,
+    pub owner: Pubkey,
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub entry_price: Decimal,
+    /// Where the position is held, free text
+    pub venue: Option<String>,
+    pub imported_at: DateTime<Utc>,
+}
+
+impl ExternalPosition {
+    pub fn unrealized_pnl(&self, mark_price: Decimal) -> Result<Decimal> {
+        MarginCalculator::calculate_unrealized_pnl(self.side, self.size, mark_price, self.entry_price)
+    }
+}
+
+/// One row of an import, the symbol is canonicalized by the API
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExternalPositionInput {
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub entry_price: Decimal,
+    pub venue: Option<String>,
+}
+
+/// Rows of a CSV import. The header names the columns `symbol`, `side`,
+/// `size`, `entry_price` and optionally `venue`, in any order
+pub fn parse_external_positions_csv(text: &str) -> Result<Vec<ExternalPositionInput>> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let (_, header) = lines.next().ok_or_else(|| invalid("header row is missing".to_string()))?;
+    let columns: Vec<String> = header
+        .split(',')
+        .map(|column| column.trim().to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| columns.iter().position(|column| column == name);
+
+    let (Some(symbol), Some(side), Some(size), Some(entry_price)) =
+        (column("symbol"), column("side"), column("size"), column("entry_price"))
+    else {
+        return Err(invalid(
+            "header must name symbol, side, size and entry_price".to_string(),
+        ));
+    };
+    let venue = column("venue");
+
+    lines
+        .map(|(line_number, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+            let decimal = |index: usize, name: &str| {
+                Decimal::from_str(field(index))
+                    .map_err(|_| invalid(format!("line {}: {} is not a number", line_number, name)))
+            };
+
+            let side = match field(side).to_ascii_lowercase().as_str() {
+                "long" | "buy" => Side::Long,
+                "short" | "sell" => Side::Short,
+                other => {
+                    return Err(invalid(format!(
+                        "line {}: side must be long or short, got {:?}",
+                        line_number, other
+                    )))
+                }
+            };
+
+            Ok(ExternalPositionInput {
+                symbol: field(symbol).to_string(),
+                side,
+                size: decimal(size, "size")?,
+                entry_price: decimal(entry_price, "entry_price")?,
+                venue: venue.map(field).filter(|venue| !venue.is_empty()).map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+fn invalid(message: String) -> anyhow::Error {
+    Error::Validation(vec![FieldError { field: "csv", message }]).into()
+}
+
+/// Exposure on one symbol across the on-chain and external books
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolExposure {
+    pub symbol: String,
+    /// Oracle price the external positions were marked at
+    pub mark_price: Option<Decimal>,
+    /// Long notional minus short notional
+    pub on_chain_net: Decimal,
+    pub external_net: Decimal,
+    /// Sum of notional on both books regardless of side
+    pub gross: Decimal,
+    pub on_chain_unrealized_pnl: Decimal,
+    pub external_unrealized_pnl: Decimal,
+    pub external_positions: usize,
+}
+
+impl SymbolExposure {
+    pub fn net(&self) -> Decimal {
+        self.on_chain_net + self.external_net
+    }
+}
+
+/// Exposure by symbol. Open on-chain positions use the monitor's mark; external
+/// ones are marked at `marks`, or held at entry with no PnL when the symbol
+/// has no price
+pub fn consolidate_exposure(
+    on_chain: &[Position],
+    external: &[ExternalPosition],
+    marks: &HashMap<String, Decimal>,
+) -> Vec<SymbolExposure> {
+    let mut by_symbol: BTreeMap<String, SymbolExposure> = BTreeMap::new();
+    let signed = |side: Side, notional: Decimal| match side {
+        Side::Long => notional,
+        Side::Short => -notional,
+    };
+
+    for position in on_chain.iter().filter(|position| position.is_open()) {
+        let exposure = by_symbol.entry(position.symbol.clone()).or_default();
+        let notional = position.size * position.mark_price;
+        exposure.on_chain_net += signed(position.side, notional);
+        exposure.gross += notional;
+        exposure.on_chain_unrealized_pnl += position.unrealized_pnl;
+    }
+
+    for position in external {
+        let exposure = by_symbol.entry(position.symbol.clone()).or_default();
+        let mark_price = marks.get(&position.symbol).copied();
+        let notional = position.size * mark_price.unwrap_or(position.entry_price);
+        exposure.mark_price = mark_price;
+        exposure.external_net += signed(position.side, notional);
+        exposure.gross += notional;
+        exposure.external_unrealized_pnl += mark_price
+            .and_then(|mark_price| position.unrealized_pnl(mark_price).ok())
+            .unwrap_or_default();
+        exposure.external_positions += 1;
+    }
+
+    by_symbol
+        .into_iter()
+        .map(|(symbol, exposure)| SymbolExposure { symbol, ..exposure })
+        .collect()
+}
+
+pub struct ExternalPositionService {
+    redis_client: redis::Client,
+}
+
+impl ExternalPositionService {
+    pub fn new(redis_client: redis::Client) -> Self {
+        Self { redis_client }
+    }
+
+    /// An owner's external positions, in import order
+    pub async fn list(&self, owner: &Pubkey) -> Result<Vec<ExternalPosition>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let stored: HashMap<String, String> = conn.hgetall(external_positions_key(owner)).await?;
+
+        let mut positions: Vec<(usize, ExternalPosition)> = stored
+            .into_iter()
+            .filter_map(|(field, raw)| {
+                // Fields are "<row>:<id>" so the import order survives the hash
+                let row = field.split(':').next()?.parse().ok()?;
+                match serde_json::from_str(&raw) {
+                    Ok(position) => Some((row, position)),
+                    Err(e) => {
+                        warn!("Skipping undecodable external position: {}", e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        positions.sort_by_key(|(row, _)| *row);
+
+        Ok(positions.into_iter().map(|(_, position)| position).collect())
+    }
+
+    /// Replace the owner's external book with `inputs`
+    pub async fn replace(&self, owner: Pubkey, inputs: Vec<ExternalPositionInput>) -> Result<Vec<ExternalPosition>> {
+        let imported_at = Utc::now();
+        let positions: Vec<ExternalPosition> = inputs
+            .into_iter()
+            .map(|input| ExternalPosition {
+                id: Uuid::new_v4(),
+                owner,
+                symbol: input.symbol,
+                side: input.side,
+                size: input.size,
+                entry_price: input.entry_price,
+                venue: input.venue,
+                imported_at,
+            })
+            .collect();
+
+        let key = external_positions_key(&owner);
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(&key);
+        for (row, position) in positions.iter().enumerate() {
+            pipe.hset(&key, format!("{}:{}", row, position.id), serde_json::to_string(position)?);
+        }
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        pipe.query_async::<_, ()>(&mut conn)
+            .await
+            .context("Failed to store external positions")?;
+
+        Ok(positions)
+    }
+
+    /// Drop the owner's external book, returns how many positions it had
+    pub async fn clear(&self, owner: &Pubkey) -> Result<usize> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let key = external_positions_key(owner);
+        let (count, _): (usize, ()) = redis::pipe()
+            .atomic()
+            .hlen(&key)
+            .del(&key)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "Side,Symbol,Size,Entry_Price,Venue\n\
+                   long,BTC-USD,0.5,60000,binance\n\
+                   \n\
+                   SELL,ETH-USD,2,3000,\n";
+
+        let rows = parse_external_positions_csv(csv).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].side, Side::Long);
+        assert_eq!(rows[0].venue.as_deref(), Some("binance"));
+        assert_eq!(rows[1].side, Side::Short);
+        assert_eq!(rows[1].entry_price, dec!(3000));
+        assert_eq!(rows[1].venue, None);
+
+        assert!(parse_external_positions_csv("symbol,side,size\nBTC-USD,long,1").is_err());
+        assert!(parse_external_positions_csv("symbol,side,size,entry_price\nBTC-USD,flat,1,1").is_err());
+        assert!(parse_external_positions_csv("symbol,side,size,entry_price\nBTC-USD,long,x,1").is_err());
+    }
+
+    #[test]
+    fn test_consolidate_exposure() {
+        let on_chain = Position {
+            symbol: "BTC-USD".to_string(),
+            size: dec!(1),
+            mark_price: dec!(110),
+            margin: dec!(10),
+            unrealized_pnl: dec!(10),
+            liquidation_price: dec!(92.5),
+            ..Position::for_test()
+        };
+        let external = |symbol: &str, side: Side, size: Decimal| ExternalPosition {
+            id: Uuid::new_v4(),
+            owner: on_chain.owner,
+            symbol: symbol.to_string(),
+            side,
+            size,
+            entry_price: dec!(100),
+            venue: None,
+            imported_at: Utc::now(),
+        };
+        let marks = HashMap::from([("BTC-USD".to_string(), dec!(110))]);
+
+        let exposure = consolidate_exposure(
+            std::slice::from_ref(&on_chain),
+            &[external("BTC-USD", Side::Short, dec!(0.5)), external("XYZ-USD", Side::Long, dec!(2))],
+            &marks,
+        );
+
+        // The on-chain long is half hedged off-platform
+        assert_eq!(exposure[0].symbol, "BTC-USD");
+        assert_eq!(exposure[0].on_chain_net, dec!(110));
+        assert_eq!(exposure[0].external_net, dec!(-55));
+        assert_eq!(exposure[0].net(), dec!(55));
+        assert_eq!(exposure[0].gross, dec!(165));
+        assert_eq!(exposure[0].external_unrealized_pnl, dec!(-5));
+
+        // No price: held at entry, no PnL
+        assert_eq!(exposure[1].symbol, "XYZ-USD");
+        assert_eq!(exposure[1].mark_price, None);
+        assert_eq!(exposure[1].external_net, dec!(200));
+        assert_eq!(exposure[1].external_unrealized_pnl, Decimal::ZERO);
+    }
+}
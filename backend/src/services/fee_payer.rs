@@ -0,0 +1,198 @@
+/// Fee Payer Service
+/// The service wallet pays the fees of every transaction the backend sends,
+/// it can't sign as the owner. Sponsored spend is tracked per user per day in
+/// Redis so the operator can subsidize onboarding with a bounded SOL budget
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use redis::AsyncCommands;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::error::{Error, FieldError};
+use crate::infrastructure::SolanaClient;
+
+// Keep daily counters around a bit longer than a day to cover clock skew
+const SPONSORSHIP_KEY_TTL_SECS: i64 = 2 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeePayerStrategy {
+    /// Service wallet pays every fee
+    ServicePaid,
+    /// Service pays until the user's daily cap is used up, then the user's
+    /// transactions are refused until the next day
+    Sponsored { daily_cap_lamports: u64 },
+}
+
+impl FromStr for FeePayerStrategy {
+    type Err = anyhow::Error;
+
+    /// Parse "service" or "sponsored:<daily_cap_lamports>"
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "service" => Ok(FeePayerStrategy::ServicePaid),
+            "user" => Err(anyhow!(
+                "User-paid fees need the owner's signature, which transactions the backend sends don't carry"
+            )),
+            other => {
+                let cap = other
+                    .strip_prefix("sponsored:")
+                    .ok_or_else(|| anyhow!("Unknown fee payer strategy: {}", s))?;
+                let daily_cap_lamports = cap
+                    .parse()
+                    .with_context(|| format!("Invalid sponsorship cap: {}", cap))?;
+                Ok(FeePayerStrategy::Sponsored { daily_cap_lamports })
+            }
+        }
+    }
+}
+
+pub struct FeePayerService {
+    strategy: FeePayerStrategy,
+    redis_client: Option<redis::Client>,
+}
+
+impl Default for FeePayerService {
+    fn default() -> Self {
+        Self {
+            strategy: FeePayerStrategy::ServicePaid,
+            redis_client: None,
+        }
+    }
+}
+
+impl FeePayerService {
+    pub fn new(strategy: FeePayerStrategy, redis_url: String) -> Result<Self> {
+        let redis_client =
+            redis::Client::open(redis_url).context("Failed to create Redis client")?;
+
+        Ok(Self {
+            strategy,
+            redis_client: Some(redis_client),
+        })
+    }
+
+    pub fn strategy(&self) -> FeePayerStrategy {
+        self.strategy
+    }
+
+    /// Reserve the service wallet's fee for a transaction sent on behalf of
+    /// `owner`, the lamports reserved when the strategy caps sponsorship. A
+    /// Validation error once the owner's daily cap is used up
+    pub async fn reserve_fee(
+        &self,
+        owner: &Pubkey,
+        solana_client: &Arc<SolanaClient>,
+        instructions: &[Instruction],
+    ) -> Result<Option<u64>> {
+        let FeePayerStrategy::Sponsored { daily_cap_lamports } = self.strategy else {
+            return Ok(None);
+        };
+
+        let fee = solana_client.estimate_fee(instructions, &solana_client.payer_pubkey())?;
+        if !self.reserve_sponsorship(owner, fee, daily_cap_lamports).await? {
+            return Err(Error::Validation(vec![FieldError {
+                field: "owner",
+                message: format!(
+                    "Daily fee sponsorship cap of {} lamports reached for {}",
+                    daily_cap_lamports, owner
+                ),
+            }])
+            .into());
+        }
+
+        info!("Sponsoring {} lamports of fees for {}", fee, owner);
+        Ok(Some(fee))
+    }
+
+    /// Give back a reservation whose transaction failed to send
+    pub async fn release_fee(&self, owner: &Pubkey, fee: u64) -> Result<()> {
+        let redis_client = self
+            .redis_client
+            .as_ref()
+            .ok_or_else(|| anyhow!("Sponsored fees require Redis"))?;
+
+        let mut conn = redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")?;
+
+        conn.decr::<_, _, ()>(sponsorship_key(owner), fee).await?;
+        Ok(())
+    }
+
+    /// Lamports sponsored for `owner` today
+    pub async fn sponsored_today(&self, owner: &Pubkey) -> Result<u64> {
+        let Some(redis_client) = &self.redis_client else {
+            return Ok(0);
+        };
+
+        let mut conn = redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")?;
+
+        let spent: Option<u64> = conn.get(sponsorship_key(owner)).await?;
+
+        Ok(spent.unwrap_or(0))
+    }
+
+    /// Atomically reserve `fee` lamports against the user's daily cap
+    /// Returns false (and releases the reservation) when the cap would be exceeded
+    async fn reserve_sponsorship(&self, owner: &Pubkey, fee: u64, daily_cap: u64) -> Result<bool> {
+        let redis_client = self
+            .redis_client
+            .as_ref()
+            .ok_or_else(|| anyhow!("Sponsored fees require Redis"))?;
+
+        let mut conn = redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")?;
+
+        let key = sponsorship_key(owner);
+
+        let spent: u64 = conn.incr(&key, fee).await?;
+        conn.expire::<_, ()>(&key, SPONSORSHIP_KEY_TTL_SECS).await?;
+
+        if spent > daily_cap {
+            conn.decr::<_, _, ()>(&key, fee).await?;
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
+
+fn sponsorship_key(owner: &Pubkey) -> String {
+    format!("fee_sponsorship:{}:{}", owner, Utc::now().format("%Y%m%d"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_strategies() {
+        assert_eq!(
+            "service".parse::<FeePayerStrategy>().unwrap(),
+            FeePayerStrategy::ServicePaid
+        );
+        assert_eq!(
+            "sponsored:50000".parse::<FeePayerStrategy>().unwrap(),
+            FeePayerStrategy::Sponsored {
+                daily_cap_lamports: 50_000
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_strategy() {
+        assert!("sponsored".parse::<FeePayerStrategy>().is_err());
+        assert!("sponsored:abc".parse::<FeePayerStrategy>().is_err());
+        assert!("free".parse::<FeePayerStrategy>().is_err());
+        // The backend can't sign as the owner
+        assert!("user".parse::<FeePayerStrategy>().is_err());
+    }
+}
@@ -0,0 +1,300 @@
+/// Fee Tiers
+/// Opens and closes pay a taker fee at the rate the program Config's schedule
+/// sets for the owner's tier, tier 0 until they have a fee tier account. The
+/// tier follows the owner's taker volume over the last 30 days, the notional
+/// of the opens and closes they sent per the fill history, liquidations not
+/// counted. The keeper recomputes it and writes the tiers that changed
+/// Users of a tenant climb at that tenant's thresholds, the rates are the
+/// program's for everyone
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::domain::RealizedPnL;
+use crate::infrastructure::{HistoryStore, PositionOpenRecord};
+use crate::services::{PositionManager, PositionMonitor, Tenants, FEE_TIER_COUNT};
+
+/// Highest taker fee the program accepts, matching its MAX_TAKER_FEE_BPS
+pub const MAX_TAKER_FEE_BPS: u16 = 100;
+
+/// Days of fills a tier is assigned on
+pub const FEE_TIER_WINDOW_DAYS: i64 = 30;
+
+const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Default minimum 30-day volume of each tier, in collateral units
+pub fn default_fee_tier_volumes() -> [Decimal; FEE_TIER_COUNT] {
+    [
+        Decimal::ZERO,
+        Decimal::from(1_000_000),
+        Decimal::from(10_000_000),
+        Decimal::from(50_000_000),
+    ]
+}
+
+/// Taker fee on a fill of `size` at `price`
+pub fn taker_fee(size: Decimal, price: Decimal, taker_fee_bps: u16) -> Decimal {
+    size * price * Decimal::from(taker_fee_bps) / Decimal::from(BPS_DENOMINATOR)
+}
+
+/// The schedule's rate for `tier`, tier 0 without one, clamped like the program does
+pub fn taker_fee_bps(schedule: &[u16; FEE_TIER_COUNT], tier: Option<u8>) -> u16 {
+    schedule[(tier.unwrap_or(0) as usize).min(FEE_TIER_COUNT - 1)]
+}
+
+/// Highest tier whose minimum volume `volume` reaches
+pub fn fee_tier_for_volume(volume: Decimal, tier_volumes: &[Decimal; FEE_TIER_COUNT]) -> u8 {
+    tier_volumes
+        .iter()
+        .rposition(|minimum| volume >= *minimum)
+        .unwrap_or(0) as u8
+}
+
+/// Taker volume per owner of the opens and non-liquidation closes since `from`
+pub fn taker_volumes(
+    opens: &[PositionOpenRecord],
+    closes: &[RealizedPnL],
+    from: DateTime<Utc>,
+) -> HashMap<Pubkey, Decimal> {
+    let mut volumes: HashMap<Pubkey, Decimal> = HashMap::new();
+
+    for open in opens.iter().filter(|open| open.opened_at >= from) {
+        *volumes.entry(open.owner).or_default() += open.size * open.entry_price;
+    }
+    for close in closes
+        .iter()
+        .filter(|close| !close.liquidation && close.closed_at >= from)
+    {
+        *volumes.entry(close.owner).or_default() += close.size_closed * close.close_price;
+    }
+
+    volumes
+}
+
+/// Where an owner stands against the tiers
+#[derive(Debug, Clone)]
+pub struct FeeTierProgress {
+    pub volume_30d: Decimal,
+    /// Tier their volume earns
+    pub tier: u8,
+    pub taker_fee_bps: u16,
+    pub next_tier: Option<u8>,
+    pub next_tier_taker_fee_bps: Option<u16>,
+    /// More volume the next tier needs
+    pub volume_to_next_tier: Option<Decimal>,
+}
+
+impl FeeTierProgress {
+    pub fn new(volume_30d: Decimal, tier_volumes: &[Decimal; FEE_TIER_COUNT], schedule: &[u16; FEE_TIER_COUNT]) -> Self {
+        let tier = fee_tier_for_volume(volume_30d, tier_volumes);
+        let next_tier = ((tier as usize) + 1 < FEE_TIER_COUNT).then_some(tier + 1);
+
+        Self {
+            volume_30d,
+            tier,
+            taker_fee_bps: taker_fee_bps(schedule, Some(tier)),
+            next_tier,
+            next_tier_taker_fee_bps: next_tier.map(|next| taker_fee_bps(schedule, Some(next))),
+            volume_to_next_tier: next_tier.map(|next| tier_volumes[next as usize] - volume_30d),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeeTierKeeperConfig {
+    pub tier_volumes: [Decimal; FEE_TIER_COUNT],
+    pub sweep_interval_ms: u64,
+}
+
+impl Default for FeeTierKeeperConfig {
+    fn default() -> Self {
+        Self {
+            tier_volumes: default_fee_tier_volumes(),
+            sweep_interval_ms: 600_000,
+        }
+    }
+}
+
+pub struct FeeTierKeeper {
+    position_manager: Arc<PositionManager>,
+    monitor: Arc<PositionMonitor>,
+    history_store: Arc<dyn HistoryStore>,
+    tenants: Option<Arc<Tenants>>,
+    config: FeeTierKeeperConfig,
+}
+
+impl FeeTierKeeper {
+    pub fn new(
+        position_manager: Arc<PositionManager>,
+        monitor: Arc<PositionMonitor>,
+        history_store: Arc<dyn HistoryStore>,
+        config: FeeTierKeeperConfig,
+    ) -> Self {
+        Self {
+            position_manager,
+            monitor,
+            history_store,
+            tenants: None,
+            config,
+        }
+    }
+
+    /// Tier tenants' users at their tenant's thresholds
+    pub fn with_tenants(mut self, tenants: Arc<Tenants>) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    /// Sweep on an interval, only the leader writes tiers
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(Duration::from_millis(self.config.sweep_interval_ms));
+
+        info!("Fee tier keeper started");
+
+        loop {
+            ticker.tick().await;
+
+            if !self.monitor.is_leader() {
+                continue;
+            }
+
+            if let Err(e) = self.sweep().await {
+                warn!("Fee tier sweep failed: {}", e);
+            }
+        }
+    }
+
+    /// Owners with volume, plus those holding a tier whose volume lapsed
+    async fn sweep(&self) -> Result<()> {
+        let now = Utc::now();
+        let from = now - ChronoDuration::days(FEE_TIER_WINDOW_DAYS);
+
+        let opens = self.history_store.position_opens_between(from, now).await?;
+        let closes = self.history_store.realized_pnl_between(from, now).await?;
+        let volumes = taker_volumes(&opens, &closes, from);
+
+        let assigned: HashMap<Pubkey, u8> = self
+            .position_manager
+            .get_user_fee_tiers()
+            .await?
+            .into_iter()
+            .map(|fee_tier| (fee_tier.owner, fee_tier.tier))
+            .collect();
+        let owner_tenants = match &self.tenants {
+            Some(tenants) => tenants.owner_tenants().await?,
+            None => HashMap::new(),
+        };
+
+        let mut owners: Vec<Pubkey> = volumes.keys().chain(assigned.keys()).copied().collect();
+        owners.sort();
+        owners.dedup();
+
+        for owner in owners {
+            let volume = volumes.get(&owner).copied().unwrap_or_default();
+            let tier_volumes = match (&self.tenants, owner_tenants.get(&owner)) {
+                (Some(tenants), Some(tenant)) => tenants.fee_tier_volumes(tenant),
+                _ => self.config.tier_volumes,
+            };
+            let tier = fee_tier_for_volume(volume, &tier_volumes);
+            // Tier 0 is the rate without an account, no need to create one
+            if assigned.get(&owner).copied().unwrap_or(0) == tier {
+                continue;
+            }
+
+            match self.position_manager.set_user_fee_tier(&owner, tier, volume).await {
+                Ok(_) => info!("Fee tier of {} now {} on {} volume", owner, tier, volume),
+                Err(e) => warn!("Failed to set fee tier of {}: {}", owner, e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Side;
+    use rust_decimal_macros::dec;
+
+    fn open(owner: Pubkey, size: Decimal, opened_at: DateTime<Utc>) -> PositionOpenRecord {
+        PositionOpenRecord {
+            position_account: Pubkey::new_unique(),
+            owner,
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size,
+            entry_price: dec!(100),
+            margin: dec!(10),
+            signature: String::new(),
+            opened_at,
+        }
+    }
+
+    fn close(owner: Pubkey, size_closed: Decimal, liquidation: bool, closed_at: DateTime<Utc>) -> RealizedPnL {
+        RealizedPnL {
+            id: uuid::Uuid::new_v4(),
+            position_account: Pubkey::new_unique(),
+            owner,
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size_closed,
+            entry_price: dec!(100),
+            close_price: dec!(110),
+            fee: Decimal::ZERO,
+            funding: Decimal::ZERO,
+            amount: Decimal::ZERO,
+            liquidation,
+            signature: String::new(),
+            closed_at,
+        }
+    }
+
+    #[test]
+    fn test_taker_volumes() {
+        let now = Utc::now();
+        let from = now - ChronoDuration::days(FEE_TIER_WINDOW_DAYS);
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+
+        let opens = vec![
+            open(a, dec!(2), now),
+            open(a, dec!(5), from - ChronoDuration::seconds(1)),
+            open(b, dec!(1), now),
+        ];
+        let closes = vec![close(a, dec!(1), false, now), close(b, dec!(1), true, now)];
+
+        let volumes = taker_volumes(&opens, &closes, from);
+        assert_eq!(volumes[&a], dec!(310));
+        assert_eq!(volumes[&b], dec!(100));
+    }
+
+    #[test]
+    fn test_fee_tier_progress() {
+        let tier_volumes = default_fee_tier_volumes();
+        let schedule = [5, 4, 3, 2];
+
+        assert_eq!(fee_tier_for_volume(Decimal::ZERO, &tier_volumes), 0);
+        assert_eq!(fee_tier_for_volume(dec!(1_000_000), &tier_volumes), 1);
+        assert_eq!(fee_tier_for_volume(dec!(75_000_000), &tier_volumes), 3);
+
+        let progress = FeeTierProgress::new(dec!(4_000_000), &tier_volumes, &schedule);
+        assert_eq!(progress.tier, 1);
+        assert_eq!(progress.taker_fee_bps, 4);
+        assert_eq!(progress.next_tier, Some(2));
+        assert_eq!(progress.next_tier_taker_fee_bps, Some(3));
+        assert_eq!(progress.volume_to_next_tier, Some(dec!(6_000_000)));
+
+        let top = FeeTierProgress::new(dec!(75_000_000), &tier_volumes, &schedule);
+        assert_eq!(top.next_tier, None);
+        assert_eq!(top.volume_to_next_tier, None);
+
+        assert_eq!(taker_fee(dec!(10), dec!(100), 5), dec!(0.5));
+        assert_eq!(taker_fee_bps(&schedule, Some(9)), 2);
+    }
+}
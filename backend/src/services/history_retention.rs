@@ -0,0 +1,412 @@
+/// History Retention
+/// Keeps the history store from growing without bound. Price ticks are kept
+/// raw for a while, then thinned to the last tick of each minute, then to the
+/// last tick of each hour, which is kept for good. Candles over a thinned
+/// range keep their close but lose the high and low inside each bucket
+/// PnL snapshots, each position's PnL and funding history, are deleted past
+/// their own window. Passes run on the leader on a timer and on demand from
+/// the admin API
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info};
+
+use crate::domain::symbols;
+use crate::infrastructure::{parse_interval, HistoryStore, TableSize, TickScope};
+use crate::services::PositionMonitor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickRetention {
+    /// Every tick is kept this long
+    pub raw: Duration,
+    /// Then the last tick of each minute until this age, the last of each hour after
+    pub minute: Duration,
+}
+
+impl TickRetention {
+    /// Parse "raw/minute", e.g. "7d/90d"
+    pub fn parse(value: &str) -> Result<Self> {
+        let (raw, minute) = value
+            .split_once('/')
+            .ok_or_else(|| anyhow!("Expected raw/minute retention, e.g. 7d/90d: {}", value))?;
+
+        let retention = Self {
+            raw: parse_interval(raw.trim())?,
+            minute: parse_interval(minute.trim())?,
+        };
+        retention.validate()?;
+
+        Ok(retention)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.minute < self.raw {
+            return Err(anyhow!("Minute tick retention is shorter than raw tick retention"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryRetentionConfig {
+    pub ticks: TickRetention,
+    /// Symbols with windows of their own in place of `ticks`
+    pub symbol_ticks: HashMap<String, TickRetention>,
+    /// None keeps PnL snapshots forever
+    pub pnl_snapshots: Option<Duration>,
+    /// Prune on the leader on a timer, otherwise only when asked to
+    pub scheduled: bool,
+    pub prune_interval_secs: u64,
+}
+
+impl Default for HistoryRetentionConfig {
+    fn default() -> Self {
+        Self {
+            ticks: TickRetention {
+                raw: Duration::days(7),
+                minute: Duration::days(90),
+            },
+            symbol_ticks: HashMap::new(),
+            pnl_snapshots: Some(Duration::days(90)),
+            scheduled: false,
+            prune_interval_secs: 3600,
+        }
+    }
+}
+
+/// Parse per-symbol tick windows, e.g. "BTC-USD=30d/365d,SOL-USD=3d/30d"
+pub fn parse_tick_retention_overrides(value: &str) -> Result<HashMap<String, TickRetention>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (symbol, retention) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected SYMBOL=raw/minute: {}", entry))?;
+            let symbol = symbols::canonicalize(symbol)?;
+            let retention = TickRetention::parse(retention).with_context(|| format!("Invalid retention for {}", symbol))?;
+            Ok((symbol, retention))
+        })
+        .collect()
+}
+
+/// One thinning step of a pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickCompaction {
+    pub scope: TickScope,
+    pub before: DateTime<Utc>,
+    pub bucket: Duration,
+}
+
+/// Steps of a pass at `now`, the default windows first, then each override
+/// Each scope is thinned hourly before minutely so the minute step doesn't
+/// work through ticks the hourly one is about to delete
+pub fn tick_compactions(config: &HistoryRetentionConfig, now: DateTime<Utc>) -> Vec<TickCompaction> {
+    let mut overridden: Vec<&String> = config.symbol_ticks.keys().collect();
+    overridden.sort();
+
+    let mut scopes = vec![(
+        TickScope::AllExcept(overridden.iter().map(|symbol| symbol.to_string()).collect()),
+        config.ticks,
+    )];
+    scopes.extend(
+        overridden
+            .into_iter()
+            .map(|symbol| (TickScope::Symbol(symbol.clone()), config.symbol_ticks[symbol])),
+    );
+
+    scopes
+        .into_iter()
+        .flat_map(|(scope, retention)| {
+            [
+                TickCompaction {
+                    scope: scope.clone(),
+                    before: now - retention.minute,
+                    bucket: Duration::hours(1),
+                },
+                TickCompaction {
+                    scope,
+                    before: now - retention.raw,
+                    bucket: Duration::minutes(1),
+                },
+            ]
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruneTrigger {
+    Scheduled,
+    /// POST /admin/history/compact
+    Manual,
+}
+
+impl PruneTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PruneTrigger::Scheduled => "scheduled",
+            PruneTrigger::Manual => "manual",
+        }
+    }
+}
+
+/// The running or last prune pass
+#[derive(Debug, Clone)]
+pub struct PruneRun {
+    pub trigger: PruneTrigger,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub ticks_deleted: u64,
+    pub pnl_snapshots_deleted: u64,
+    pub error: Option<String>,
+}
+
+impl PruneRun {
+    fn new(trigger: PruneTrigger) -> Self {
+        Self {
+            trigger,
+            started_at: Utc::now(),
+            finished_at: None,
+            ticks_deleted: 0,
+            pnl_snapshots_deleted: 0,
+            error: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.finished_at.is_none()
+    }
+}
+
+/// Prune counters since startup
+#[derive(Debug, Default)]
+pub struct HistoryRetentionMetrics {
+    passes: AtomicU64,
+    failed_passes: AtomicU64,
+    ticks_deleted: AtomicU64,
+    pnl_snapshots_deleted: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HistoryRetentionStats {
+    pub passes: u64,
+    pub failed_passes: u64,
+    pub ticks_deleted: u64,
+    pub pnl_snapshots_deleted: u64,
+}
+
+impl HistoryRetentionMetrics {
+    fn record(&self, run: &PruneRun) {
+        self.passes.fetch_add(1, Ordering::Relaxed);
+        if run.error.is_some() {
+            self.failed_passes.fetch_add(1, Ordering::Relaxed);
+        }
+        self.ticks_deleted.fetch_add(run.ticks_deleted, Ordering::Relaxed);
+        self.pnl_snapshots_deleted.fetch_add(run.pnl_snapshots_deleted, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> HistoryRetentionStats {
+        HistoryRetentionStats {
+            passes: self.passes.load(Ordering::Relaxed),
+            failed_passes: self.failed_passes.load(Ordering::Relaxed),
+            ticks_deleted: self.ticks_deleted.load(Ordering::Relaxed),
+            pnl_snapshots_deleted: self.pnl_snapshots_deleted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub struct HistoryRetentionService {
+    history_store: Arc<dyn HistoryStore>,
+    monitor: Arc<PositionMonitor>,
+    config: HistoryRetentionConfig,
+    last_run: RwLock<Option<PruneRun>>,
+    metrics: HistoryRetentionMetrics,
+}
+
+impl HistoryRetentionService {
+    pub fn new(
+        history_store: Arc<dyn HistoryStore>,
+        monitor: Arc<PositionMonitor>,
+        config: HistoryRetentionConfig,
+    ) -> Self {
+        Self {
+            history_store,
+            monitor,
+            config,
+            last_run: RwLock::new(None),
+            metrics: HistoryRetentionMetrics::default(),
+        }
+    }
+
+    pub fn config(&self) -> &HistoryRetentionConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> HistoryRetentionStats {
+        self.metrics.stats()
+    }
+
+    /// The running or last pass
+    pub async fn last_run(&self) -> Option<PruneRun> {
+        self.last_run.read().await.clone()
+    }
+
+    pub async fn table_sizes(&self) -> Result<Vec<TableSize>> {
+        self.history_store.table_sizes().await
+    }
+
+    /// Prune on the leader every `prune_interval_secs`
+    pub fn spawn(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(std::time::Duration::from_secs(self.config.prune_interval_secs));
+
+            info!("History retention started");
+
+            loop {
+                ticker.tick().await;
+
+                if !self.monitor.is_leader() {
+                    continue;
+                }
+
+                if self.begin(PruneTrigger::Scheduled).await.is_some() {
+                    self.run().await;
+                }
+            }
+        });
+    }
+
+    /// Start a pass in the background, none while one is running
+    pub async fn start(self: &Arc<Self>) -> Option<PruneRun> {
+        let run = self.begin(PruneTrigger::Manual).await?;
+
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            service.run().await;
+        });
+
+        Some(run)
+    }
+
+    async fn begin(&self, trigger: PruneTrigger) -> Option<PruneRun> {
+        let mut current = self.last_run.write().await;
+        if current.as_ref().is_some_and(|run| run.is_running()) {
+            return None;
+        }
+
+        let run = PruneRun::new(trigger);
+        *current = Some(run.clone());
+        Some(run)
+    }
+
+    async fn update(&self, f: impl FnOnce(&mut PruneRun)) {
+        if let Some(run) = self.last_run.write().await.as_mut() {
+            f(run);
+        }
+    }
+
+    async fn run(&self) {
+        let result = self.prune().await;
+
+        let mut current = self.last_run.write().await;
+        if let Some(run) = current.as_mut() {
+            run.finished_at = Some(Utc::now());
+            match result {
+                Ok(()) => info!(
+                    "History prune done: {} ticks, {} PnL snapshots deleted",
+                    run.ticks_deleted, run.pnl_snapshots_deleted
+                ),
+                Err(e) => {
+                    error!("History prune failed: {:#}", e);
+                    run.error = Some(format!("{:#}", e));
+                }
+            }
+            self.metrics.record(run);
+        }
+    }
+
+    async fn prune(&self) -> Result<()> {
+        let now = Utc::now();
+
+        for step in tick_compactions(&self.config, now) {
+            let deleted = self
+                .history_store
+                .compact_price_ticks(&step.scope, step.before, step.bucket)
+                .await?;
+            self.update(|run| run.ticks_deleted += deleted).await;
+        }
+
+        if let Some(retention) = self.config.pnl_snapshots {
+            let deleted = self.history_store.delete_pnl_snapshots_before(now - retention).await?;
+            self.update(|run| run.pnl_snapshots_deleted += deleted).await;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tick_retention_overrides() {
+        let overrides = parse_tick_retention_overrides("btcusd=30d/365d, SOL-USD=1d/1d").unwrap();
+
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(
+            overrides["BTC-USD"],
+            TickRetention {
+                raw: Duration::days(30),
+                minute: Duration::days(365),
+            }
+        );
+        assert_eq!(overrides["SOL-USD"].minute, Duration::days(1));
+
+        assert!(parse_tick_retention_overrides("").unwrap().is_empty());
+        assert!(parse_tick_retention_overrides("BTC-USD=30d").is_err());
+        assert!(parse_tick_retention_overrides("BTC-USD=30d/7d").is_err());
+        assert!(parse_tick_retention_overrides("30d/365d").is_err());
+    }
+
+    #[test]
+    fn test_tick_compactions() {
+        let now = Utc::now();
+        let config = HistoryRetentionConfig {
+            symbol_ticks: parse_tick_retention_overrides("SOL-USD=1d/30d,BTC-USD=30d/365d").unwrap(),
+            ..Default::default()
+        };
+
+        let steps = tick_compactions(&config, now);
+
+        let all_but = TickScope::AllExcept(vec!["BTC-USD".to_string(), "SOL-USD".to_string()]);
+        let btc = TickScope::Symbol("BTC-USD".to_string());
+        assert_eq!(steps.len(), 6);
+        assert_eq!(
+            steps[0],
+            TickCompaction {
+                scope: all_but.clone(),
+                before: now - Duration::days(90),
+                bucket: Duration::hours(1),
+            }
+        );
+        assert_eq!(
+            steps[1],
+            TickCompaction {
+                scope: all_but,
+                before: now - Duration::days(7),
+                bucket: Duration::minutes(1),
+            }
+        );
+        assert_eq!(steps[2].scope, btc);
+        assert_eq!(steps[2].before, now - Duration::days(365));
+        assert_eq!(steps[3].before, now - Duration::days(30));
+        assert_eq!(steps[5].scope, TickScope::Symbol("SOL-USD".to_string()));
+        assert_eq!(steps[5].before, now - Duration::days(1));
+    }
+}
@@ -0,0 +1,115 @@
+/// Leader Election
+/// Redis lease (SET NX PX + renewal) so only one replica runs keeper tasks
+/// A node that can't renew its lease steps down immediately, another replica
+/// takes over once the TTL runs out
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+use tracing::{info, warn};
+
+// Renew our own lease, or take it if nobody holds it
+const ACQUIRE_OR_RENEW_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('PEXPIRE', KEYS[1], ARGV[2])
+    return 1
+end
+if redis.call('SET', KEYS[1], ARGV[1], 'NX', 'PX', ARGV[2]) then
+    return 1
+end
+return 0
+"#;
+
+#[derive(Debug, Clone)]
+pub struct LeaderElectionConfig {
+    pub key: String,
+    pub lease_ttl_ms: u64,
+    pub renew_interval_ms: u64,
+}
+
+impl Default for LeaderElectionConfig {
+    fn default() -> Self {
+        Self {
+            key: "perps:leader".to_string(),
+            lease_ttl_ms: 10_000,
+            renew_interval_ms: 3_000,
+        }
+    }
+}
+
+pub struct LeaderElection {
+    redis_client: redis::Client,
+    config: LeaderElectionConfig,
+    node_id: String,
+    is_leader: AtomicBool,
+}
+
+impl LeaderElection {
+    pub fn new(redis_url: String, config: LeaderElectionConfig) -> Result<Self> {
+        let redis_client =
+            redis::Client::open(redis_url).context("Failed to create Redis client")?;
+
+        Ok(Self {
+            redis_client,
+            config,
+            node_id: uuid::Uuid::new_v4().to_string(),
+            is_leader: AtomicBool::new(false),
+        })
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::SeqCst)
+    }
+
+    /// Keep trying to acquire or renew the lease in the background
+    pub fn spawn(self: &Arc<Self>) {
+        let election = Arc::clone(self);
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(election.config.renew_interval_ms));
+
+            info!("Leader election started (node {})", election.node_id);
+
+            loop {
+                ticker.tick().await;
+
+                let leader = match election.acquire_or_renew().await {
+                    Ok(leader) => leader,
+                    Err(e) => {
+                        warn!("Leader lease renewal failed: {}", e);
+                        false
+                    }
+                };
+
+                let was_leader = election.is_leader.swap(leader, Ordering::SeqCst);
+
+                if leader && !was_leader {
+                    info!("Node {} became leader", election.node_id);
+                } else if !leader && was_leader {
+                    warn!("Node {} lost leadership", election.node_id);
+                }
+            }
+        });
+    }
+
+    async fn acquire_or_renew(&self) -> Result<bool> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")?;
+
+        let acquired: i32 = redis::Script::new(ACQUIRE_OR_RENEW_SCRIPT)
+            .key(&self.config.key)
+            .arg(&self.node_id)
+            .arg(self.config.lease_ttl_ms)
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok(acquired == 1)
+    }
+}
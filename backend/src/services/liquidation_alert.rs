@@ -1,16 +1,27 @@
 /// Liquidation Alert Service
 /// Uses Redis sorted sets to track positions nearing liquidation prices 
 /// Optimal range queries for quick and efficient checks
+/// Alerts go out through the registered `Notifier` sinks, the event bus and
+/// the log by default
 use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
 use redis::AsyncCommands;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
-use tokio::sync::broadcast;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::RwLock;
 use tracing::{info, warn};
 
-use crate::domain::{ Side, Risk };
+use crate::domain::{ Position, Side, Risk };
+use crate::infrastructure::{LatencyMetrics, LatencyStage};
+use crate::services::{
+    AlertNotifiers, EventBus, EventBusNotifier, LogNotifier, MarginCalculator, MarginRatioAlerts, SymbolConfigs,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LiquidationAlert {
     pub position_account: Pubkey,
     pub symbol: String,
@@ -18,6 +29,49 @@ pub struct LiquidationAlert {
     pub liquidation_price: Decimal,
     pub current_price: Decimal,
     pub risk_type: Risk,
+    /// Liquidation is held off until then, set on MarginCall alerts
+    #[serde(default)]
+    pub margin_call_deadline: Option<DateTime<Utc>>,
+    /// None when the monitor no longer holds the position
+    #[serde(default)]
+    pub owner: Option<Pubkey>,
+    /// What liquidation at `liquidation_price` costs the owner
+    #[serde(default)]
+    pub estimated_loss: Option<Decimal>,
+    /// Set on MarginWarning alerts, with the multiple of maintenance crossed
+    #[serde(default)]
+    pub margin_ratio: Option<Decimal>,
+    #[serde(default)]
+    pub margin_ratio_tier: Option<Decimal>,
+}
+
+impl LiquidationAlert {
+    /// Fill in the owner and the estimated loss from the alerted position
+    pub fn with_position(mut self, position: &Position) -> Self {
+        self.owner = Some(position.owner);
+        self.estimated_loss = MarginCalculator::estimated_loss_at_liquidation(
+            position.side,
+            position.size,
+            position.entry_price,
+            self.liquidation_price,
+            position.margin,
+            position.funding_accrued,
+        )
+        .ok();
+        self
+    }
+
+    /// Percent the price can still move against the position before
+    /// `liquidation_price`, negative once past it
+    pub fn distance_to_liquidation_pct(&self) -> Option<Decimal> {
+        if self.liquidation_price <= Decimal::ZERO {
+            return None;
+        }
+
+        MarginCalculator::distance_to_liquidation(self.current_price, self.liquidation_price, self.side)
+            .ok()
+            .map(|distance| distance * Decimal::ONE_HUNDRED)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -36,37 +90,85 @@ impl Default for LiquidationAlertConfig {
 pub struct LiquidationAlertService {
     redis_client: redis::Client,
     config: LiquidationAlertConfig,
-    alert_tx: broadcast::Sender<LiquidationAlert>,
+    /// Sinks every alert is published to
+    notifiers: Arc<AlertNotifiers>,
+    latency_metrics: Option<Arc<LatencyMetrics>>,
+    /// Per-symbol alert thresholds, none uses `alert_threshold_pct` everywhere
+    symbol_configs: Option<Arc<SymbolConfigs>>,
+    /// The monitor's positions, alerts carry their owner and estimated loss
+    positions: Option<Arc<RwLock<HashMap<Pubkey, Position>>>>,
+    /// Told about every alert here so the margin ratio path doesn't repeat it
+    margin_ratio_alerts: Option<Arc<MarginRatioAlerts>>,
 }
 
 impl LiquidationAlertService {
     pub fn new(
         redis_url: String,
         config: LiquidationAlertConfig,
-    ) -> Result<(Self, broadcast::Receiver<LiquidationAlert>)> {
+        events: Arc<dyn EventBus>,
+    ) -> Result<Self> {
         let redis_client = redis::Client::open(redis_url)?;
-        let (alert_tx, alert_rx) = broadcast::channel(1000);
-        
-        Ok((
-            Self {
-                redis_client,
-                config,
-                alert_tx,
-            },
-            alert_rx,
-        ))
+        let notifiers = Arc::new(AlertNotifiers::new());
+        notifiers.register(Arc::new(EventBusNotifier::new(events)));
+        notifiers.register(Arc::new(LogNotifier));
+
+        Ok(Self {
+            redis_client,
+            config,
+            notifiers,
+            latency_metrics: None,
+            symbol_configs: None,
+            positions: None,
+            margin_ratio_alerts: None,
+        })
     }
     
+    /// Time range queries and alerts against the price's arrival
+    pub fn with_latency_metrics(mut self, latency_metrics: Arc<LatencyMetrics>) -> Self {
+        self.latency_metrics = Some(latency_metrics);
+        self
+    }
+
+    pub fn with_symbol_configs(mut self, symbol_configs: Arc<SymbolConfigs>) -> Self {
+        self.symbol_configs = Some(symbol_configs);
+        self
+    }
+
+    pub fn with_positions(mut self, positions: Arc<RwLock<HashMap<Pubkey, Position>>>) -> Self {
+        self.positions = Some(positions);
+        self
+    }
+
+    pub fn with_margin_ratio_alerts(mut self, margin_ratio_alerts: Arc<MarginRatioAlerts>) -> Self {
+        self.margin_ratio_alerts = Some(margin_ratio_alerts);
+        self
+    }
+
+    /// Register further sinks here, at startup
+    pub fn notifiers(&self) -> &Arc<AlertNotifiers> {
+        &self.notifiers
+    }
+
+    /// Send an alert raised elsewhere, e.g. by the margin ratio path, to the sinks
+    pub async fn publish(&self, alert: LiquidationAlert) {
+        self.notifiers.notify(alert).await;
+    }
+
     /// Check liquidations using Redis range queries
+    #[tracing::instrument(skip(self))]
     pub async fn check_liquidations_for_price_update(
         &self,
         symbol: &str,
         current_price: Decimal,
+        received_at: Instant,
     ) -> Result<Vec<Pubkey>> {
-        let threshold = self.config.alert_threshold_pct;
+        let threshold = match &self.symbol_configs {
+            Some(symbol_configs) => symbol_configs.settings(symbol).alert_threshold_pct,
+            None => self.config.alert_threshold_pct,
+        };
         let mut at_risk_position_accounts = Vec::new();
         
-        // Check LONG positions
+        // Range queries for both sides first, the alerts below only need their results
         let long_lower_bound = current_price * (Decimal::ONE - threshold);
         let long_to_liquidate = self.get_positions_in_range(
             &format!("liquidations:{}:long", symbol),
@@ -79,7 +181,31 @@ impl LiquidationAlertService {
             long_lower_bound,
             current_price,
         ).await?;
-        
+
+        let short_upper_bound = current_price * (Decimal::ONE + threshold);
+        let short_at_liquidation = self.get_positions_in_range(
+            &format!("liquidations:{}:short", symbol),
+            0.into(),
+            current_price,
+        ).await?;
+
+        let short_at_risk = self.get_positions_in_range(
+            &format!("liquidations:{}:short", symbol),
+            current_price,
+            short_upper_bound,
+        ).await?;
+
+        if let Some(latency) = &self.latency_metrics {
+            let elapsed = received_at.elapsed();
+            if latency.record(LatencyStage::ZsetQuery, elapsed) {
+                warn!(
+                    "Liquidation range queries for {} took {}ms after the price arrived",
+                    symbol,
+                    elapsed.as_millis()
+                );
+            }
+        }
+
         info!("asset: {}, long_at_risk: {:?} current_price: {:?}, count {}", symbol, long_lower_bound, current_price, long_at_risk.len()); 
         info!("asset: {}, current_price: {:?}, count {}", symbol, current_price, long_to_liquidate.len()); 
         
@@ -97,7 +223,8 @@ impl LiquidationAlertService {
                         Side::Long,
                         liq_price,
                         current_price,
-                        Risk::Liquidated
+                        Risk::Liquidated,
+                        received_at,
                     ).await;
                     // Simulate by removing the liquidated position from Redis
                     self.remove_from_redis_sorted_set(symbol, Side::Long, position_account).await?;
@@ -119,28 +246,15 @@ impl LiquidationAlertService {
                         Side::Long,
                         liq_price,
                         current_price,
-                        Risk::Liquidating
+                        Risk::Liquidating,
+                        received_at,
                     ).await;
                     // Simulate by removing the liquidated position from Redis
                     self.remove_from_redis_sorted_set(symbol, Side::Long, position_account).await?;
                 }
             }
         }
-        
-        // Check SHORT positions
-        let short_upper_bound = current_price * (Decimal::ONE + threshold);
-        let short_at_liquidation = self.get_positions_in_range(
-            &format!("liquidations:{}:short", symbol),
-            0.into(),
-            current_price,
-        ).await?;
 
-        let short_at_risk = self.get_positions_in_range(
-            &format!("liquidations:{}:short", symbol),
-            current_price,
-            short_upper_bound,
-        ).await?;
-        
         for position_account_str in short_at_liquidation {
             if let Ok(position_account) = position_account_str.parse::<Pubkey>() {
                 at_risk_position_accounts.push(position_account);
@@ -155,7 +269,8 @@ impl LiquidationAlertService {
                         Side::Short,
                         liq_price,
                         current_price,
-                        Risk::Liquidated
+                        Risk::Liquidated,
+                        received_at,
                     ).await;
                     // Simulate by removing the liquidated position from Redis
                     self.remove_from_redis_sorted_set(symbol, Side::Short, position_account).await?;
@@ -177,7 +292,8 @@ impl LiquidationAlertService {
                         Side::Short,
                         liq_price,
                         current_price,
-                        Risk::Liquidating
+                        Risk::Liquidating,
+                        received_at,
                     ).await;
                     // Simulate by removing the liquidated position from Redis
                     self.remove_from_redis_sorted_set(symbol, Side::Short, position_account).await?;
@@ -246,12 +362,13 @@ impl LiquidationAlertService {
         };
 
         let member = position_account.to_string();
-        conn.zrem(&key, &member).await?;
+        conn.zrem::<_, _, ()>(&key, &member).await?;
 
         Ok(())
     }
 
     /// Emit liquidation alert
+    #[allow(clippy::too_many_arguments)]
     async fn emit_alert(
         &self,
         position_account: Pubkey,
@@ -259,26 +376,42 @@ impl LiquidationAlertService {
         side: Side,
         liquidation_price: Decimal,
         current_price: Decimal,
-        risk_type: Risk
+        risk_type: Risk,
+        received_at: Instant,
     ) {
-        let alert: LiquidationAlert = LiquidationAlert {
+        let mut alert: LiquidationAlert = LiquidationAlert {
             position_account,  
             symbol: symbol.to_string(),
             side,
             liquidation_price,
             current_price,
-            risk_type: risk_type
+            risk_type,
+            margin_call_deadline: None,
+            owner: None,
+            estimated_loss: None,
+            margin_ratio: None,
+            margin_ratio_tier: None,
         };
-        
-        warn!(
-            "{:?} ALERT: {:?} {:?} position {} - Current: ${:.2}, Liquidation: ${:.2}",
-            risk_type, symbol, side, position_account, current_price, liquidation_price
-        );
-        
-        let _ = self.alert_tx.send(alert);
-    }
-    
-    pub fn subscribe(&self) -> broadcast::Receiver<LiquidationAlert> {
-        self.alert_tx.subscribe()
+        if let Some(margin_ratio_alerts) = &self.margin_ratio_alerts {
+            margin_ratio_alerts.mark_alerted(&position_account);
+        }
+        if let Some(positions) = &self.positions {
+            if let Some(position) = positions.read().await.get(&position_account) {
+                alert = alert.with_position(position);
+            }
+        }
+
+        self.publish(alert).await;
+
+        if let Some(latency) = &self.latency_metrics {
+            let elapsed = received_at.elapsed();
+            if latency.record(LatencyStage::AlertBroadcast, elapsed) {
+                warn!(
+                    "Alert for {} went out {}ms after the price arrived",
+                    position_account,
+                    elapsed.as_millis()
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,334 @@
+/// Liquidation Executor
+/// Listens for liquidation alerts from the monitor and liquidates the
+/// positions on-chain with the service wallet
+/// With partial liquidation enabled in the program Config, only enough size
+/// is closed to bring the rest back to maintenance plus the Config buffer
+/// With the backstop enabled, full liquidations go to the backstop vault
+/// first, and positions the vault holds are closed when they hit maintenance
+/// While trading is halted alerts are dropped and in-flight liquidations are
+/// abandoned, the next alert after resuming picks the position up again
+/// With margin calls enabled, a position is only liquidated once its call's
+/// grace period has run out; the margin call keeper re-raises it then
+/// Alerts that queue up while a liquidation is in flight are taken together,
+/// and full liquidations on the same market and price are sent as
+/// liquidate_many batches sized to the transaction's compute budget
+use chrono::Utc;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tracing::{error, info, warn};
+
+use crate::domain::Risk;
+use crate::services::{
+    margin_call_deadline, pack_liquidation_batches, DomainEvent, EventKind, LiquidationAlert, MarginCalculator,
+    PositionManager, PositionMonitor, TradingHalt, MAX_TRANSACTION_COMPUTE_UNITS,
+};
+
+pub struct LiquidationExecutor {
+    position_manager: Arc<PositionManager>,
+    monitor: Arc<PositionMonitor>,
+    backstop: bool,
+    trading_halt: Option<Arc<TradingHalt>>,
+}
+
+impl LiquidationExecutor {
+    pub fn new(position_manager: Arc<PositionManager>, monitor: Arc<PositionMonitor>) -> Self {
+        Self {
+            position_manager,
+            monitor,
+            backstop: false,
+            trading_halt: None,
+        }
+    }
+
+    /// Hand full liquidations to the backstop vault, falling back to a plain
+    /// liquidation when it can't take them
+    pub fn with_backstop(mut self) -> Self {
+        self.backstop = true;
+        self
+    }
+
+    /// Stop liquidating while trading is halted
+    pub fn with_trading_halt(mut self, trading_halt: Arc<TradingHalt>) -> Self {
+        self.trading_halt = Some(trading_halt);
+        self
+    }
+
+    /// Run until the alert channel closes
+    pub async fn run(&self) {
+        let mut alert_rx = self.monitor.events().receiver(EventKind::Liquidation);
+
+        info!("Liquidation executor started");
+
+        loop {
+            match alert_rx.recv().await {
+                Ok(DomainEvent::Liquidation(alert)) => {
+                    if !self.monitor.is_leader() {
+                        continue;
+                    }
+
+                    // Take whatever queued up behind it, a cascade arrives as a burst
+                    let mut alerts = vec![alert];
+                    loop {
+                        match alert_rx.try_recv() {
+                            Ok(DomainEvent::Liquidation(alert)) => alerts.push(alert),
+                            Ok(_) => {}
+                            Err(TryRecvError::Lagged(skipped)) => {
+                                warn!("Liquidation executor lagged, skipped {} alerts", skipped);
+                            }
+                            Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+                        }
+                    }
+
+                    alerts.retain(|alert| matches!(alert.risk_type, Risk::Liquidated));
+                    if !alerts.is_empty() {
+                        self.execute_unless_halted(alerts).await;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Liquidation executor lagged, skipped {} alerts", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+
+        info!("Liquidation executor stopped");
+    }
+
+    async fn execute_unless_halted(&self, alerts: Vec<LiquidationAlert>) {
+        let Some(trading_halt) = &self.trading_halt else {
+            return self.execute_all(alerts).await;
+        };
+
+        if trading_halt.is_halted() {
+            warn!("Trading halted, skipping {} liquidations", alerts.len());
+            return;
+        }
+
+        let count = alerts.len();
+        tokio::select! {
+            _ = self.execute_all(alerts) => {}
+            _ = trading_halt.halted() => {
+                warn!("Trading halted, cancelled {} liquidations", count);
+            }
+        }
+    }
+
+    /// Liquidate each alerted position once, batching the full liquidations
+    /// left over on each market and price
+    async fn execute_all(&self, alerts: Vec<LiquidationAlert>) {
+        let mut latest: HashMap<_, LiquidationAlert> = HashMap::new();
+        for alert in alerts {
+            latest.insert(alert.position_account, alert);
+        }
+
+        let mut batches: HashMap<(String, Decimal), Vec<LiquidationAlert>> = HashMap::new();
+        for alert in latest.into_values() {
+            if let Some(alert) = self.execute(alert).await {
+                batches
+                    .entry((alert.symbol.clone(), alert.current_price))
+                    .or_default()
+                    .push(alert);
+            }
+        }
+
+        for ((symbol, mark_price), alerts) in batches {
+            self.liquidate_batch(&symbol, mark_price, alerts).await;
+        }
+    }
+
+    /// Full liquidations of `alerts` in liquidate_many batches, each batch
+    /// that fails is retried one position at a time
+    async fn liquidate_batch(&self, symbol: &str, mark_price: Decimal, alerts: Vec<LiquidationAlert>) {
+        if alerts.len() == 1 {
+            for alert in alerts {
+                self.liquidate(alert, Decimal::ONE).await;
+            }
+            return;
+        }
+
+        for batch in pack_liquidation_batches(alerts, MAX_TRANSACTION_COMPUTE_UNITS) {
+            let position_accounts: Vec<_> = batch.iter().map(|alert| alert.position_account).collect();
+
+            match self
+                .position_manager
+                .liquidate_many(symbol, &position_accounts, mark_price)
+                .await
+            {
+                Ok((signature, liquidated)) => info!(
+                    "Liquidated {} of {} {} positions at ${}: {}",
+                    liquidated.len(),
+                    batch.len(),
+                    symbol,
+                    mark_price,
+                    signature
+                ),
+                Err(e) => {
+                    warn!(
+                        "Batch liquidation of {} {} positions failed, liquidating one at a time: {}",
+                        batch.len(),
+                        symbol,
+                        e
+                    );
+                    for alert in batch {
+                        self.liquidate(alert, Decimal::ONE).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle one alert, returning it when what's left is a plain full
+    /// liquidation that can go in a batch
+    async fn execute(&self, alert: LiquidationAlert) -> Option<LiquidationAlert> {
+        let backstop_held = self
+            .position_manager
+            .get_position(alert.position_account)
+            .await
+            .is_ok_and(|position| position.owner == self.position_manager.backstop_vault_address());
+
+        if backstop_held {
+            match self
+                .position_manager
+                .close_backstop_position(alert.position_account, alert.current_price)
+                .await
+            {
+                Ok(signature) => info!(
+                    "Closed backstop position {} {} at ${}: {}",
+                    alert.symbol, alert.position_account, alert.current_price, signature
+                ),
+                Err(e) => error!("Failed to close backstop position {}: {}", alert.position_account, e),
+            }
+            return None;
+        }
+
+        if self.in_margin_call_grace(&alert).await {
+            info!(
+                "Holding off liquidating {} until its margin call grace period ends",
+                alert.position_account
+            );
+            return None;
+        }
+
+        let close_fraction = self.close_fraction(&alert).await;
+
+        if self.backstop && close_fraction == Decimal::ONE {
+            match self
+                .position_manager
+                .backstop_liquidation(alert.position_account, alert.current_price)
+                .await
+            {
+                Ok(signature) => {
+                    info!(
+                        "Liquidated {} {} into the backstop vault at ${}: {}",
+                        alert.symbol, alert.position_account, alert.current_price, signature
+                    );
+                    return None;
+                }
+                Err(e) => warn!(
+                    "Backstop vault could not take {}, liquidating: {}",
+                    alert.position_account, e
+                ),
+            }
+        }
+
+        if close_fraction == Decimal::ONE {
+            return Some(alert);
+        }
+
+        self.liquidate(alert, close_fraction).await;
+        None
+    }
+
+    /// Liquidate `close_fraction` of one position, fully when a partial fails
+    async fn liquidate(&self, alert: LiquidationAlert, close_fraction: Decimal) {
+        let mut result = self
+            .position_manager
+            .liquidate_position(alert.position_account, alert.current_price, close_fraction)
+            .await;
+
+        // The price may have moved past what a partial close can fix
+        if let Err(e) = &result {
+            if close_fraction < Decimal::ONE {
+                warn!(
+                    "Partial liquidation of {} failed, liquidating fully: {}",
+                    alert.position_account, e
+                );
+                result = self
+                    .position_manager
+                    .liquidate_position(alert.position_account, alert.current_price, Decimal::ONE)
+                    .await;
+            }
+        }
+
+        match result {
+            Ok(signature) => info!(
+                "Liquidated {} of {} {} at ${}: {}",
+                close_fraction.round_dp(4),
+                alert.symbol,
+                alert.position_account,
+                alert.current_price,
+                signature
+            ),
+            Err(e) => error!("Failed to liquidate {}: {}", alert.position_account, e),
+        }
+    }
+
+    /// Whether the program would refuse the liquidation for a margin call, either
+    /// not issued yet or still inside its grace period
+    async fn in_margin_call_grace(&self, alert: &LiquidationAlert) -> bool {
+        let config = match self.position_manager.get_liquidation_config().await {
+            Ok(config) if config.margin_calls_enabled() => config,
+            Ok(_) => return false,
+            // The program has the final say
+            Err(e) => {
+                warn!("Failed to read margin call config: {}", e);
+                return false;
+            }
+        };
+
+        let Ok(position) = self.position_manager.get_position(alert.position_account).await else {
+            return false;
+        };
+
+        margin_call_deadline(&position, config.margin_call_grace)
+            .is_none_or(|deadline| deadline > Utc::now())
+    }
+
+    /// Share of the position to close, 1 when partial liquidation is off
+    /// Targets the monitor's maintenance ratio, never below the program's
+    /// tier rate, so the remaining position passes the on-chain check
+    async fn close_fraction(&self, alert: &LiquidationAlert) -> Decimal {
+        let config = match self.position_manager.get_liquidation_config().await {
+            Ok(config) if config.partial_liquidation_enabled => config,
+            Ok(_) => return Decimal::ONE,
+            Err(e) => {
+                warn!("Failed to read liquidation config, liquidating fully: {}", e);
+                return Decimal::ONE;
+            }
+        };
+
+        let Ok(position) = self.position_manager.get_position(alert.position_account).await else {
+            return Decimal::ONE;
+        };
+
+        MarginCalculator::calculate_unrealized_pnl(
+            position.side,
+            position.size,
+            alert.current_price,
+            position.entry_price,
+        )
+        .and_then(|pnl| {
+            MarginCalculator::calculate_liquidation_close_fraction(
+                position.margin,
+                pnl,
+                position.size,
+                alert.current_price,
+                self.monitor.maintenance_margin_ratio(&position.symbol) + config.buffer_ratio,
+            )
+        })
+        .unwrap_or(Decimal::ONE)
+    }
+}
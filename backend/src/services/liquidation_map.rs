@@ -0,0 +1,188 @@
+/// Liquidation Map
+/// Open positions on a symbol bucketed by liquidation price, the data behind
+/// a liquidation heatmap. Each side gets its own ladder running outward from
+/// the mark price: longs liquidate below it, shorts above it. Buckets carry
+/// the notional liquidated inside them and the running total from the mark,
+/// i.e. what a move that far would liquidate
+use rust_decimal::Decimal;
+
+use crate::domain::{Position, Side};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiquidationBucket {
+    pub price_low: Decimal,
+    pub price_high: Decimal,
+    /// Size times liquidation price of the positions in the bucket
+    pub notional: Decimal,
+    pub positions: usize,
+    /// Notional of this bucket and every bucket nearer the mark
+    pub cumulative_notional: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiquidationLadder {
+    pub side: Side,
+    pub bucket_width: Decimal,
+    pub total_notional: Decimal,
+    /// Nearest the mark first, empty without open positions on the side
+    pub buckets: Vec<LiquidationBucket>,
+}
+
+/// Ladder of `side`'s open positions in `bucket_count` equal buckets from
+/// `mark_price` to the furthest liquidation price. Positions already past the
+/// mark, waiting to be liquidated, fall in the first bucket
+pub fn build_ladder(
+    positions: &[Position],
+    side: Side,
+    mark_price: Decimal,
+    bucket_count: usize,
+) -> LiquidationLadder {
+    // Distance from the mark each position's liquidation price sits at
+    let entries: Vec<(Decimal, Decimal)> = positions
+        .iter()
+        .filter(|position| position.side == side && position.is_open())
+        .filter(|position| position.liquidation_price > Decimal::ZERO)
+        .map(|position| {
+            let distance = match side {
+                Side::Long => mark_price - position.liquidation_price,
+                Side::Short => position.liquidation_price - mark_price,
+            };
+            (
+                distance.max(Decimal::ZERO),
+                position.size * position.liquidation_price,
+            )
+        })
+        .collect();
+
+    let Some(furthest) = entries.iter().map(|(distance, _)| *distance).max() else {
+        return LiquidationLadder {
+            side,
+            bucket_width: Decimal::ZERO,
+            total_notional: Decimal::ZERO,
+            buckets: Vec::new(),
+        };
+    };
+
+    // Everything at the mark needs only one bucket
+    let bucket_count = if furthest.is_zero() { 1 } else { bucket_count.max(1) };
+    let bucket_width = furthest / Decimal::from(bucket_count);
+
+    let mut notionals = vec![(Decimal::ZERO, 0usize); bucket_count];
+    for (distance, notional) in entries {
+        let index = if bucket_width.is_zero() {
+            0
+        } else {
+            (distance / bucket_width)
+                .floor()
+                .try_into()
+                .unwrap_or(usize::MAX)
+                .min(bucket_count - 1)
+        };
+        notionals[index].0 += notional;
+        notionals[index].1 += 1;
+    }
+
+    let mut cumulative_notional = Decimal::ZERO;
+    let buckets = notionals
+        .into_iter()
+        .enumerate()
+        .map(|(index, (notional, positions))| {
+            cumulative_notional += notional;
+            let near = bucket_width * Decimal::from(index);
+            let far = near + bucket_width;
+            let (price_low, price_high) = match side {
+                Side::Long => (mark_price - far, mark_price - near),
+                Side::Short => (mark_price + near, mark_price + far),
+            };
+            LiquidationBucket {
+                price_low,
+                price_high,
+                notional,
+                positions,
+                cumulative_notional,
+            }
+        })
+        .collect();
+
+    LiquidationLadder {
+        side,
+        bucket_width,
+        total_notional: cumulative_notional,
+        buckets,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn position(side: Side, size: Decimal, liquidation_price: Decimal) -> Position {
+        Position {
+            side,
+            size,
+            margin: dec!(10),
+            liquidation_price,
+            ..Position::for_test()
+        }
+    }
+
+    #[test]
+    fn test_long_ladder_runs_down_from_mark() {
+        let positions = vec![
+            position(Side::Long, dec!(1), dec!(95)),
+            position(Side::Long, dec!(2), dec!(90)),
+            position(Side::Long, dec!(1), dec!(80)),
+            // Past its liquidation price, not yet liquidated
+            position(Side::Long, dec!(1), dec!(101)),
+            position(Side::Short, dec!(5), dec!(110)),
+        ];
+
+        let ladder = build_ladder(&positions, Side::Long, dec!(100), 4);
+        assert_eq!(ladder.bucket_width, dec!(5));
+        assert_eq!(ladder.buckets.len(), 4);
+
+        let first = &ladder.buckets[0];
+        assert_eq!((first.price_low, first.price_high), (dec!(95), dec!(100)));
+        assert_eq!(first.positions, 1);
+        assert_eq!(first.notional, dec!(101));
+
+        // 95 sits on a boundary and falls in the farther bucket
+        let second = &ladder.buckets[1];
+        assert_eq!((second.price_low, second.price_high), (dec!(90), dec!(95)));
+        assert_eq!(second.positions, 1);
+        assert_eq!(ladder.buckets[2].positions, 1);
+        assert_eq!(ladder.buckets[3].notional, dec!(80));
+
+        assert_eq!(ladder.total_notional, dec!(101) + dec!(95) + dec!(180) + dec!(80));
+        assert_eq!(ladder.buckets[3].cumulative_notional, ladder.total_notional);
+    }
+
+    #[test]
+    fn test_short_ladder_runs_up_from_mark() {
+        let positions = vec![
+            position(Side::Short, dec!(2), dec!(110)),
+            position(Side::Short, dec!(1), dec!(120)),
+        ];
+
+        let ladder = build_ladder(&positions, Side::Short, dec!(100), 2);
+        assert_eq!(ladder.buckets[0].price_low, dec!(100));
+        assert_eq!(ladder.buckets[0].price_high, dec!(110));
+        assert_eq!(ladder.buckets[0].notional, Decimal::ZERO);
+        assert_eq!(ladder.buckets[1].notional, dec!(340));
+        assert_eq!(ladder.buckets[1].positions, 2);
+    }
+
+    #[test]
+    fn test_empty_and_degenerate_ladders() {
+        let ladder = build_ladder(&[], Side::Long, dec!(100), 50);
+        assert!(ladder.buckets.is_empty());
+        assert_eq!(ladder.total_notional, Decimal::ZERO);
+
+        // All at the mark collapses to one bucket
+        let positions = vec![position(Side::Short, dec!(1), dec!(100))];
+        let ladder = build_ladder(&positions, Side::Short, dec!(100), 50);
+        assert_eq!(ladder.buckets.len(), 1);
+        assert_eq!(ladder.buckets[0].notional, dec!(100));
+    }
+}
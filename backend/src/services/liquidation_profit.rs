@@ -0,0 +1,154 @@
+/// Liquidation Profitability
+/// What a third-party liquidator earns from a liquidation against what the
+/// transaction costs. The program pays liquidators nothing itself, so the
+/// reward is whatever share of the closed notional the keeper is paid off
+/// chain, and the cost is the signature fee plus the priority fee in SOL
+/// Also how many liquidations a liquidate_many transaction can carry
+use rust_decimal::Decimal;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+// Estimated compute of a liquidate_many, with headroom over what one
+// liquidation's deserialization, settlement and event take
+const LIQUIDATE_MANY_BASE_COMPUTE_UNITS: u32 = 30_000;
+const LIQUIDATE_MANY_COMPUTE_UNITS_PER_POSITION: u32 = 45_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Most compute a transaction may request
+pub const MAX_TRANSACTION_COMPUTE_UNITS: u32 = 1_400_000;
+/// Positions the program takes per liquidate_many
+pub const MAX_BATCH_LIQUIDATIONS: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct LiquidationCostModel {
+    /// Keeper reward as a share of the notional closed
+    pub reward_bps: u32,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price_micro_lamports: u64,
+    /// Fee per signature, one signature per liquidation
+    pub base_fee_lamports: u64,
+}
+
+impl Default for LiquidationCostModel {
+    fn default() -> Self {
+        Self {
+            reward_bps: 50,
+            compute_unit_limit: 200_000,
+            compute_unit_price_micro_lamports: 10_000,
+            base_fee_lamports: 5_000,
+        }
+    }
+}
+
+/// One liquidation's economics, in USD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidationEstimate {
+    pub reward: Decimal,
+    pub fee: Decimal,
+    pub profit: Decimal,
+}
+
+impl LiquidationCostModel {
+    /// Priority fee at the configured price for the whole compute budget, rounded up
+    pub fn priority_fee_lamports(&self) -> u64 {
+        let micro_lamports = self.compute_unit_limit as u64 * self.compute_unit_price_micro_lamports;
+        micro_lamports.div_ceil(MICRO_LAMPORTS_PER_LAMPORT)
+    }
+
+    pub fn fee_lamports(&self) -> u64 {
+        self.base_fee_lamports + self.priority_fee_lamports()
+    }
+
+    /// Reward for closing `notional` against the fee at `sol_price`
+    pub fn estimate(&self, notional: Decimal, sol_price: Decimal) -> LiquidationEstimate {
+        let reward = notional * Decimal::from(self.reward_bps) / Decimal::from(BPS_DENOMINATOR);
+        let fee = Decimal::from(self.fee_lamports()) * sol_price / Decimal::from(LAMPORTS_PER_SOL);
+
+        LiquidationEstimate {
+            reward,
+            fee,
+            profit: reward - fee,
+        }
+    }
+
+    /// Compute budget instructions to put ahead of the liquidation
+    pub fn compute_budget_instructions(&self) -> Vec<Instruction> {
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(self.compute_unit_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(self.compute_unit_price_micro_lamports),
+        ]
+    }
+}
+
+/// Compute unit limit to request for a liquidate_many of `count` positions
+pub fn liquidate_many_compute_units(count: usize) -> u32 {
+    let per_position = LIQUIDATE_MANY_COMPUTE_UNITS_PER_POSITION.saturating_mul(count as u32);
+    LIQUIDATE_MANY_BASE_COMPUTE_UNITS
+        .saturating_add(per_position)
+        .min(MAX_TRANSACTION_COMPUTE_UNITS)
+}
+
+/// Split liquidation candidates into liquidate_many batches, each no larger
+/// than the program takes and estimated to fit in `compute_unit_limit`
+pub fn pack_liquidation_batches<T>(candidates: Vec<T>, compute_unit_limit: u32) -> Vec<Vec<T>> {
+    let fits = compute_unit_limit.saturating_sub(LIQUIDATE_MANY_BASE_COMPUTE_UNITS)
+        / LIQUIDATE_MANY_COMPUTE_UNITS_PER_POSITION;
+    let batch_size = (fits as usize).clamp(1, MAX_BATCH_LIQUIDATIONS);
+
+    let mut candidates = candidates.into_iter().peekable();
+    let mut batches = Vec::new();
+    while candidates.peek().is_some() {
+        batches.push(candidates.by_ref().take(batch_size).collect());
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_priority_fee_rounds_up() {
+        let model = LiquidationCostModel {
+            compute_unit_limit: 150_001,
+            compute_unit_price_micro_lamports: 10,
+            ..LiquidationCostModel::default()
+        };
+
+        // 1_500_010 micro-lamports
+        assert_eq!(model.priority_fee_lamports(), 2);
+        assert_eq!(model.fee_lamports(), 5_002);
+    }
+
+    #[test]
+    fn test_estimate() {
+        let model = LiquidationCostModel::default();
+
+        // 200k CU at 10k micro-lamports is 2_000 lamports, plus 5_000 base
+        let estimate = model.estimate(dec!(10_000), dec!(200));
+        assert_eq!(estimate.reward, dec!(50));
+        assert_eq!(estimate.fee, dec!(0.0014));
+        assert_eq!(estimate.profit, dec!(49.9986));
+
+        let dust = model.estimate(dec!(0.2), dec!(200));
+        assert!(dust.profit < Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_pack_liquidation_batches() {
+        let sizes = |batches: Vec<Vec<u32>>| batches.iter().map(Vec::len).collect::<Vec<_>>();
+
+        assert_eq!(sizes(pack_liquidation_batches((0..23).collect(), MAX_TRANSACTION_COMPUTE_UNITS)), vec![10, 10, 3]);
+        // 200k fits the base and 3 positions
+        assert_eq!(sizes(pack_liquidation_batches((0..7).collect(), 200_000)), vec![3, 3, 1]);
+        // Always at least one per transaction
+        assert_eq!(sizes(pack_liquidation_batches((0..2).collect(), 10_000)), vec![1, 1]);
+        assert!(pack_liquidation_batches(Vec::<u32>::new(), 200_000).is_empty());
+
+        assert_eq!(liquidate_many_compute_units(3), 165_000);
+        assert_eq!(liquidate_many_compute_units(100), MAX_TRANSACTION_COMPUTE_UNITS);
+    }
+}
@@ -1,9 +1,46 @@
-use crate::domain::{Side};
+use crate::domain::{max_notional_for_leverage, Side};
 use anyhow::{anyhow, Result};
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, MathematicalOps, RoundingStrategy};
+
+// Suggested sizes keep the instruction's size precision
+const SIZE_DECIMALS: u32 = 8;
 
 pub struct MarginCalculator;
 
+/// How far price may move against a new position before its owner reacts,
+/// from the oracle confidence and recent candle volatility
+#[derive(Debug, Clone)]
+pub struct SizingModel {
+    /// Standard deviations of candle moves a suggested position survives
+    pub z_score: Decimal,
+    /// Candles between opening and the owner reacting
+    pub horizon_candles: u32,
+    /// Notional suggested when the move is negligible and no tier caps it
+    pub base_notional: Decimal,
+    pub leverage_buckets: Vec<u16>,
+}
+
+impl Default for SizingModel {
+    fn default() -> Self {
+        Self {
+            z_score: Decimal::TWO,
+            horizon_candles: 60,
+            base_notional: Decimal::from(1_000_000),
+            leverage_buckets: vec![1, 2, 5, 10, 20, 50, 100],
+        }
+    }
+}
+
+/// Suggested ceiling for one leverage
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizingBucket {
+    pub leverage: u16,
+    /// Price move to liquidation at this leverage, as a ratio
+    pub liquidation_buffer: Decimal,
+    pub max_notional: Decimal,
+    pub max_size: Decimal,
+}
+
 impl MarginCalculator {
     /// Calculate initial margin required for a position
     /// Formula: Initial Margin = (Position Size × Entry Price) / Leverage
@@ -89,7 +126,8 @@ impl MarginCalculator {
 
     /// Calculate margin ratio
     /// Formula: (collateral + unrealized_pnl) / position_value
-    /// Returns ratio as decimal (e.g., 0.15 = 15%)
+    /// Returns ratio as decimal (e.g., 0.15 = 15%), negative once losses
+    /// exceed the collateral. Clients are shown it floored at 0
     pub fn calculate_margin_ratio(
         collateral: Decimal,
         unrealized_pnl: Decimal,
@@ -115,6 +153,12 @@ impl MarginCalculator {
         Ok(margin_ratio)
     }
 
+    /// Losses beyond the collateral, left to the backstop if the position
+    /// closed now. Zero while the margin holds
+    pub fn calculate_margin_deficit(collateral: Decimal, unrealized_pnl: Decimal) -> Decimal {
+        (-(collateral + unrealized_pnl)).max(Decimal::ZERO)
+    }
+
     /// Calculate liquidation price for long positions
     /// Formula: entry_price × (1 - 1/leverage + maintenance_margin_ratio)
     pub fn calculate_liquidation_price_long(
@@ -204,6 +248,51 @@ impl MarginCalculator {
         }
     }
 
+    /// Price at which equity net of accrued funding and the fee to close
+    /// falls to maintenance, from the position's actual margin rather than
+    /// entry / leverage
+    /// Long:  (size × entry - margin - funding) / (size × (1 - mmr - fee_rate))
+    /// Short: (size × entry + margin + funding) / (size × (1 + mmr + fee_rate))
+    /// Longs that can't be liquidated at any positive price return zero
+    pub fn calculate_effective_liquidation_price(
+        side: Side,
+        size: Decimal,
+        entry_price: Decimal,
+        margin: Decimal,
+        funding_accrued: Decimal,
+        maintenance_margin_ratio: Decimal,
+        close_fee_rate: Decimal,
+    ) -> Result<Decimal> {
+        if size <= Decimal::ZERO {
+            return Err(anyhow!("Position size must be positive"));
+        }
+
+        let entry_value = size
+            .checked_mul(entry_price)
+            .ok_or_else(|| anyhow!("Position value overflow"))?;
+        let equity = margin
+            .checked_add(funding_accrued)
+            .ok_or_else(|| anyhow!("Equity overflow"))?;
+        let rate = maintenance_margin_ratio
+            .checked_add(close_fee_rate)
+            .ok_or_else(|| anyhow!("Rate overflow"))?;
+
+        let (numerator, factor) = match side {
+            Side::Long => (entry_value - equity, Decimal::ONE - rate),
+            Side::Short => (entry_value + equity, Decimal::ONE + rate),
+        };
+
+        if factor <= Decimal::ZERO {
+            return Err(anyhow!("Maintenance ratio plus fee must be below 1"));
+        }
+
+        let liquidation_price = numerator
+            .checked_div(size * factor)
+            .ok_or_else(|| anyhow!("Effective liquidation price calculation failed"))?;
+
+        Ok(liquidation_price.max(Decimal::ZERO))
+    }
+
     /// Calculate maintenance margin
     /// Formula: Initial Margin × Maintenance Margin Ratio
     pub fn calculate_maintenance_margin(
@@ -232,6 +321,37 @@ impl MarginCalculator {
         Ok(margin_ratio < maintenance_margin_ratio)
     }
 
+    /// Fraction of a position a partial liquidation closes so the rest sits
+    /// at `target_ratio`, with the closed share's PnL realized against margin
+    /// Formula: 1 - (collateral + unrealized_pnl) / (size × mark_price × target_ratio)
+    /// Returns 1 (full liquidation) when no partial close gets there
+    pub fn calculate_liquidation_close_fraction(
+        collateral: Decimal,
+        unrealized_pnl: Decimal,
+        size: Decimal,
+        mark_price: Decimal,
+        target_ratio: Decimal,
+    ) -> Result<Decimal> {
+        let equity = collateral
+            .checked_add(unrealized_pnl)
+            .ok_or_else(|| anyhow!("Effective margin overflow"))?;
+
+        let target_value = size
+            .checked_mul(mark_price)
+            .and_then(|value| value.checked_mul(target_ratio))
+            .ok_or_else(|| anyhow!("Position value overflow"))?;
+
+        if equity <= Decimal::ZERO || target_value <= Decimal::ZERO {
+            return Ok(Decimal::ONE);
+        }
+
+        let kept = equity
+            .checked_div(target_value)
+            .ok_or_else(|| anyhow!("Close fraction calculation failed"))?;
+
+        Ok((Decimal::ONE - kept).clamp(Decimal::ZERO, Decimal::ONE))
+    }
+
     /// Calculate distance to liquidation as a percentage
     pub fn distance_to_liquidation(
         current_price: Decimal,
@@ -264,6 +384,23 @@ impl MarginCalculator {
         Ok(distance)
     }
 
+    /// Loss the owner realizes if liquidated at `liquidation_price`: the price
+    /// loss less funding owed to the position, at most the margin
+    pub fn estimated_loss_at_liquidation(
+        side: Side,
+        size: Decimal,
+        entry_price: Decimal,
+        liquidation_price: Decimal,
+        margin: Decimal,
+        funding_accrued: Decimal,
+    ) -> Result<Decimal> {
+        let pnl = Self::calculate_unrealized_pnl(side, size, liquidation_price, entry_price)?
+            .checked_add(funding_accrued)
+            .ok_or_else(|| anyhow!("PnL calculation overflow"))?;
+
+        Ok((-pnl).clamp(Decimal::ZERO, margin.max(Decimal::ZERO)))
+    }
+
     /// Calculate maximum position size for given margin and leverage
     /// Formula: (margin * leverage) / entry_price
     pub fn calculate_max_position_size(
@@ -292,6 +429,93 @@ impl MarginCalculator {
         Ok(max_size)
     }
 
+    /// Standard deviation of close-to-close returns, zero with under three closes
+    pub fn candle_volatility(closes: &[Decimal]) -> Decimal {
+        let returns: Vec<Decimal> = closes
+            .windows(2)
+            .filter(|pair| pair[0] > Decimal::ZERO)
+            .map(|pair| (pair[1] - pair[0]) / pair[0])
+            .collect();
+        if returns.len() < 2 {
+            return Decimal::ZERO;
+        }
+
+        let count = Decimal::from(returns.len());
+        let mean = returns.iter().sum::<Decimal>() / count;
+        let variance = returns
+            .iter()
+            .map(|r| (*r - mean) * (*r - mean))
+            .sum::<Decimal>()
+            / (count - Decimal::ONE);
+
+        variance.sqrt().unwrap_or(Decimal::ZERO)
+    }
+
+    /// Adverse move to plan for, as a ratio of price: the oracle's confidence
+    /// band plus `z_score` standard deviations over the horizon
+    pub fn expected_adverse_move(
+        model: &SizingModel,
+        price: Decimal,
+        confidence: Decimal,
+        volatility: Decimal,
+    ) -> Result<Decimal> {
+        if price <= Decimal::ZERO {
+            return Err(anyhow!("Price must be positive"));
+        }
+
+        let horizon = Decimal::from(model.horizon_candles)
+            .sqrt()
+            .unwrap_or(Decimal::ZERO);
+
+        Ok(confidence / price + model.z_score * volatility * horizon)
+    }
+
+    /// Suggested max size per leverage bucket. Each bucket's notional, the
+    /// tier cap or `base_notional`, is scaled by the share of its buffer to
+    /// liquidation left after the adverse move; none is left, none suggested
+    pub fn suggest_position_sizes(
+        model: &SizingModel,
+        price: Decimal,
+        adverse_move: Decimal,
+        maintenance_margin_ratio: Decimal,
+    ) -> Result<Vec<SizingBucket>> {
+        if price <= Decimal::ZERO {
+            return Err(anyhow!("Price must be positive"));
+        }
+
+        let buckets = model
+            .leverage_buckets
+            .iter()
+            .filter(|leverage| **leverage > 0)
+            .map(|&leverage| {
+                let liquidation_buffer =
+                    (Decimal::ONE / Decimal::from(leverage) - maintenance_margin_ratio)
+                        .max(Decimal::ZERO);
+
+                let headroom = if liquidation_buffer.is_zero() {
+                    Decimal::ZERO
+                } else {
+                    ((liquidation_buffer - adverse_move) / liquidation_buffer)
+                        .clamp(Decimal::ZERO, Decimal::ONE)
+                };
+
+                let cap = max_notional_for_leverage(leverage)
+                    .map_or(model.base_notional, |cap| cap.min(model.base_notional));
+                let max_notional = cap * headroom;
+
+                SizingBucket {
+                    leverage,
+                    liquidation_buffer,
+                    max_notional,
+                    max_size: (max_notional / price)
+                        .round_dp_with_strategy(SIZE_DECIMALS, RoundingStrategy::ToZero),
+                }
+            })
+            .collect();
+
+        Ok(buckets)
+    }
+
     /// Calculate ROI (Return on Investment)
     /// Formula: (unrealized_pnl / margin) * 100
     pub fn calculate_roi(unrealized_pnl: Decimal, initial_margin: Decimal) -> Result<Decimal> {
@@ -449,6 +673,18 @@ mod tests {
         assert!(ratio > dec!(0.127) && ratio < dec!(0.128));
     }
 
+    #[test]
+    fn test_underwater_margin_ratio() {
+        // 5,000 collateral down 7,000 on 55,000 notional: 2,000 under
+        let ratio =
+            MarginCalculator::calculate_margin_ratio(dec!(5000), dec!(-7000), dec!(1), dec!(55000))
+                .unwrap();
+
+        assert!(ratio < dec!(-0.036) && ratio > dec!(-0.037));
+        assert_eq!(MarginCalculator::calculate_margin_deficit(dec!(5000), dec!(-7000)), dec!(2000));
+        assert_eq!(MarginCalculator::calculate_margin_deficit(dec!(5000), dec!(-3000)), Decimal::ZERO);
+    }
+
     #[test]
     fn test_liquidation_price_long() {
         // Long position at 50,000 with 10x leverage
@@ -473,6 +709,65 @@ mod tests {
         assert_eq!(liq_price, dec!(53750));
     }
 
+    #[test]
+    fn test_effective_liquidation_price() {
+        // 1 BTC at 50,000, 5,000 margin, 2% maintenance, 0.5% close fee
+        // Long with 1,800 funding paid: 46,800 / 0.975 = 48,000
+        let long = MarginCalculator::calculate_effective_liquidation_price(
+            Side::Long,
+            dec!(1),
+            dec!(50000),
+            dec!(5000),
+            dec!(-1800),
+            dec!(0.02),
+            dec!(0.005),
+        )
+        .unwrap();
+        assert_eq!(long, dec!(48000));
+
+        // At 48,000 equity is 5,000 - 2,000 - 1,800 - 240 fee = 960, 2% of notional
+        assert_eq!(dec!(5000) - dec!(2000) - dec!(1800) - dec!(240), dec!(0.02) * long);
+
+        // Short with 1,700 funding paid: 53,300 / 1.025 = 52,000
+        let short = MarginCalculator::calculate_effective_liquidation_price(
+            Side::Short,
+            dec!(1),
+            dec!(50000),
+            dec!(5000),
+            dec!(-1700),
+            dec!(0.02),
+            dec!(0.005),
+        )
+        .unwrap();
+        assert_eq!(short, dec!(52000));
+
+        // Funding received moves it away from the mark
+        let funded = MarginCalculator::calculate_effective_liquidation_price(
+            Side::Long,
+            dec!(1),
+            dec!(50000),
+            dec!(5000),
+            dec!(500),
+            dec!(0.02),
+            dec!(0.005),
+        )
+        .unwrap();
+        assert!(funded < long);
+
+        // Fully collateralized longs never liquidate
+        let unlevered = MarginCalculator::calculate_effective_liquidation_price(
+            Side::Long,
+            dec!(1),
+            dec!(50000),
+            dec!(60000),
+            dec!(0),
+            dec!(0.02),
+            dec!(0),
+        )
+        .unwrap();
+        assert_eq!(unlevered, Decimal::ZERO);
+    }
+
     #[test]
     fn test_should_liquidate_safe() {
         // Margin ratio well above maintenance
@@ -503,6 +798,32 @@ mod tests {
         assert!(should_liq); // Margin ratio is 2%, below 2.5%
     }
 
+    #[test]
+    fn test_liquidation_close_fraction() {
+        // Equity 1,000 on 50,000 notional (2%), restore 3.5%
+        let fraction = MarginCalculator::calculate_liquidation_close_fraction(
+            dec!(5000),
+            dec!(-4000),
+            dec!(1),
+            dec!(50000),
+            dec!(0.035),
+        )
+        .unwrap();
+
+        // 1,000 / 0.035 = 28,571 notional may stay open
+        assert_eq!(fraction.round_dp(4), dec!(0.4286));
+
+        let wiped_out = MarginCalculator::calculate_liquidation_close_fraction(
+            dec!(5000),
+            dec!(-5000),
+            dec!(1),
+            dec!(50000),
+            dec!(0.035),
+        )
+        .unwrap();
+        assert_eq!(wiped_out, Decimal::ONE);
+    }
+
     #[test]
     fn test_distance_to_liquidation_long() {
         // Long at current 50,000, liquidation at 46,250
@@ -513,4 +834,64 @@ mod tests {
 
         assert_eq!(distance, dec!(0.075));
     }
+
+    #[test]
+    fn test_estimated_loss_at_liquidation() {
+        // Long 1 @ 100 with 10 margin, liquidated at 90.5
+        let loss = MarginCalculator::estimated_loss_at_liquidation(
+            Side::Long, dec!(1), dec!(100), dec!(90.5), dec!(10), dec!(0),
+        )
+        .unwrap();
+        assert_eq!(loss, dec!(9.5));
+
+        // Owed funding adds to it, capped at the margin
+        let loss = MarginCalculator::estimated_loss_at_liquidation(
+            Side::Long, dec!(1), dec!(100), dec!(90.5), dec!(10), dec!(-1),
+        )
+        .unwrap();
+        assert_eq!(loss, dec!(10));
+
+        // Short 2 @ 100 liquidated at 104.75
+        let loss = MarginCalculator::estimated_loss_at_liquidation(
+            Side::Short, dec!(2), dec!(100), dec!(104.75), dec!(20), dec!(0.5),
+        )
+        .unwrap();
+        assert_eq!(loss, dec!(9));
+    }
+
+    #[test]
+    fn test_candle_volatility() {
+        assert_eq!(MarginCalculator::candle_volatility(&[dec!(100), dec!(101)]), Decimal::ZERO);
+
+        // Returns +1%, -1%, +1%, -1%: mean 0, sample variance 0.0004/3
+        let closes = [dec!(100), dec!(101), dec!(99.99), dec!(100.9899), dec!(99.980001)];
+        let volatility = MarginCalculator::candle_volatility(&closes);
+        assert_eq!(volatility.round_dp(6), dec!(0.011547));
+    }
+
+    #[test]
+    fn test_suggest_position_sizes() {
+        let model = SizingModel {
+            leverage_buckets: vec![10, 20, 50],
+            ..SizingModel::default()
+        };
+
+        // 0.1% confidence, 0.05% per candle over 60 candles: ~0.87% adverse move
+        let adverse = MarginCalculator::expected_adverse_move(&model, dec!(100), dec!(0.1), dec!(0.0005))
+            .unwrap();
+        assert_eq!(adverse.round_dp(4), dec!(0.0087));
+
+        let buckets =
+            MarginCalculator::suggest_position_sizes(&model, dec!(100), dec!(0.02), dec!(0.025))
+                .unwrap();
+
+        // 10x: 7.5% buffer, 2% move leaves 11/15 of 1M
+        assert_eq!(buckets[0].liquidation_buffer, dec!(0.075));
+        assert_eq!(buckets[0].max_size, dec!(7333.33333333));
+        // 20x: 2.5% buffer, 2% move leaves a fifth of 1M
+        assert_eq!(buckets[1].max_notional, dec!(200000));
+        // 50x: past maintenance, nothing suggested
+        assert_eq!(buckets[2].liquidation_buffer, Decimal::ZERO);
+        assert_eq!(buckets[2].max_size, Decimal::ZERO);
+    }
 }
@@ -0,0 +1,278 @@
+/// Margin Call Keeper
+/// Sweeps open positions and issues an on-chain margin call once one drops
+/// below maintenance plus the Config margin call buffer, and clears it once
+/// the owner tops up past it
+/// While a call runs the program blocks margin withdrawals and size
+/// increases and refuses liquidation until the grace period ends; the keeper
+/// then raises a Liquidated alert for the executor if the position is still
+/// below maintenance
+/// Calls and clears go out as `MarginCall` / `MarginCallCleared` alerts
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::domain::{Position, PositionStatus, Risk};
+use crate::error::Error;
+use crate::services::{
+    LiquidationAlert, LiquidationConfigData, MarginCalculator, PositionManager, PositionMonitor,
+    TradingHalt,
+};
+
+#[derive(Debug, Clone)]
+pub struct MarginCallKeeperConfig {
+    pub sweep_interval_ms: u64,
+}
+
+impl Default for MarginCallKeeperConfig {
+    fn default() -> Self {
+        Self {
+            sweep_interval_ms: 2_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarginCallAction {
+    Issue,
+    Clear,
+    /// Grace period over and still below maintenance
+    Liquidate,
+}
+
+/// When liquidation of a margin-called position becomes allowed
+pub fn margin_call_deadline(position: &Position, grace: chrono::Duration) -> Option<DateTime<Utc>> {
+    match position.status {
+        PositionStatus::MarginCall => position.margin_call_at.map(|at| at + grace),
+        _ => None,
+    }
+}
+
+/// What the keeper should do with a position at `mark_price`
+pub fn margin_call_action(
+    position: &Position,
+    mark_price: Decimal,
+    maintenance_ratio: Decimal,
+    config: &LiquidationConfigData,
+    now: DateTime<Utc>,
+) -> Option<MarginCallAction> {
+    let margin_ratio = MarginCalculator::calculate_unrealized_pnl(
+        position.side,
+        position.size,
+        mark_price,
+        position.entry_price,
+    )
+    .and_then(|pnl| {
+        MarginCalculator::calculate_margin_ratio(position.margin, pnl, position.size, mark_price)
+    })
+    .ok()?;
+
+    let warning_ratio = maintenance_ratio + config.margin_call_buffer_ratio;
+
+    match position.status {
+        PositionStatus::Open if config.margin_calls_enabled() && margin_ratio < warning_ratio => {
+            Some(MarginCallAction::Issue)
+        }
+        // Calls still clear after they are turned off
+        PositionStatus::MarginCall if margin_ratio >= warning_ratio => Some(MarginCallAction::Clear),
+        PositionStatus::MarginCall if margin_ratio < maintenance_ratio => {
+            margin_call_deadline(position, config.margin_call_grace)
+                .filter(|deadline| *deadline <= now)
+                .map(|_| MarginCallAction::Liquidate)
+        }
+        _ => None,
+    }
+}
+
+pub struct MarginCallKeeper {
+    position_manager: Arc<PositionManager>,
+    monitor: Arc<PositionMonitor>,
+    config: MarginCallKeeperConfig,
+    trading_halt: Option<Arc<TradingHalt>>,
+}
+
+impl MarginCallKeeper {
+    pub fn new(
+        position_manager: Arc<PositionManager>,
+        monitor: Arc<PositionMonitor>,
+        config: MarginCallKeeperConfig,
+    ) -> Self {
+        Self {
+            position_manager,
+            monitor,
+            config,
+            trading_halt: None,
+        }
+    }
+
+    /// Pause sweeps while trading is halted
+    pub fn with_trading_halt(mut self, trading_halt: Arc<TradingHalt>) -> Self {
+        self.trading_halt = Some(trading_halt);
+        self
+    }
+
+    /// Sweep on an interval, only the leader sends transactions
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(Duration::from_millis(self.config.sweep_interval_ms));
+
+        info!("Margin call keeper started");
+
+        loop {
+            ticker.tick().await;
+
+            if !self.monitor.is_leader()
+                || self.trading_halt.as_ref().is_some_and(|halt| halt.is_halted())
+            {
+                continue;
+            }
+
+            if let Err(e) = self.sweep().await {
+                warn!("Margin call sweep failed: {}", e);
+            }
+        }
+    }
+
+    async fn sweep(&self) -> anyhow::Result<()> {
+        let config = self.position_manager.get_liquidation_config().await?;
+        let vault = self.position_manager.backstop_vault_address();
+        let now = Utc::now();
+
+        for position in self.monitor.get_all_positions().await {
+            // The vault's positions are closed at maintenance without a call
+            if !position.is_open() || position.owner == vault {
+                continue;
+            }
+
+            let Some(mark_price) = self.monitor.get_cached_price(&position.symbol).await else {
+                continue;
+            };
+
+            let maintenance_ratio = self.monitor.maintenance_margin_ratio(&position.symbol);
+            let Some(action) = margin_call_action(&position, mark_price, maintenance_ratio, &config, now)
+            else {
+                continue;
+            };
+
+            self.apply(action, &position, mark_price, &config).await;
+        }
+
+        Ok(())
+    }
+
+    async fn apply(
+        &self,
+        action: MarginCallAction,
+        position: &Position,
+        mark_price: Decimal,
+        config: &LiquidationConfigData,
+    ) {
+        let risk_type = match action {
+            MarginCallAction::Liquidate => Risk::Liquidated,
+            MarginCallAction::Issue | MarginCallAction::Clear => {
+                if let Err(e) = self
+                    .position_manager
+                    .update_margin_call(position.position_account, mark_price)
+                    .await
+                {
+                    // The program's tier rate can differ from the monitor's maintenance ratio
+                    if is_margin_call_unchanged(&e) {
+                        debug!("Margin call on {} unchanged on-chain", position.position_account);
+                    } else {
+                        warn!("Failed to update margin call on {}: {}", position.position_account, e);
+                    }
+                    return;
+                }
+
+                if action == MarginCallAction::Issue {
+                    Risk::MarginCall
+                } else {
+                    Risk::MarginCallCleared
+                }
+            }
+        };
+
+        let margin_call_deadline = (risk_type == Risk::MarginCall)
+            .then(|| Utc::now() + config.margin_call_grace);
+
+        let alert = LiquidationAlert {
+            position_account: position.position_account,
+            symbol: position.symbol.clone(),
+            side: position.side,
+            liquidation_price: position.liquidation_price,
+            current_price: mark_price,
+            risk_type,
+            margin_call_deadline,
+            owner: None,
+            estimated_loss: None,
+            margin_ratio: None,
+            margin_ratio_tier: None,
+        }
+        .with_position(position);
+        self.monitor.alert_notifiers().notify(alert).await;
+    }
+}
+
+fn is_margin_call_unchanged(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::Program { error, .. }) if error.code == "MarginCallUnchanged"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn long(margin: Decimal, status: PositionStatus, margin_call_at: Option<DateTime<Utc>>) -> Position {
+        Position {
+            margin,
+            status,
+            margin_call_at,
+            ..Position::for_test()
+        }
+    }
+
+    #[test]
+    fn test_margin_call_action() {
+        let config = LiquidationConfigData {
+            partial_liquidation_enabled: false,
+            buffer_ratio: Decimal::ZERO,
+            margin_call_buffer_ratio: dec!(0.01),
+            margin_call_grace: chrono::Duration::minutes(5),
+        };
+        let maintenance = dec!(0.025);
+        let now = Utc::now();
+
+        // 100 margin on 10 @ 100: 10% at 100, 3.2% at 93, 2.2% at 92
+        let open = long(dec!(100), PositionStatus::Open, None);
+        assert_eq!(margin_call_action(&open, dec!(100), maintenance, &config, now), None);
+        assert_eq!(
+            margin_call_action(&open, dec!(93), maintenance, &config, now),
+            Some(MarginCallAction::Issue)
+        );
+
+        // Disabled calls leave liquidation to the executor
+        let disabled = LiquidationConfigData {
+            margin_call_grace: chrono::Duration::zero(),
+            ..config.clone()
+        };
+        assert_eq!(margin_call_action(&open, dec!(92), maintenance, &disabled, now), None);
+
+        // Below maintenance, liquidation waits out the grace period
+        let called = long(dec!(100), PositionStatus::MarginCall, Some(now));
+        assert_eq!(margin_call_action(&called, dec!(92), maintenance, &config, now), None);
+        assert_eq!(
+            margin_call_action(&called, dec!(92), maintenance, &config, now + chrono::Duration::minutes(5)),
+            Some(MarginCallAction::Liquidate)
+        );
+
+        // Topped up past the buffer
+        let topped_up = long(dec!(200), PositionStatus::MarginCall, Some(now));
+        assert_eq!(
+            margin_call_action(&topped_up, dec!(93), maintenance, &disabled, now),
+            Some(MarginCallAction::Clear)
+        );
+    }
+}
@@ -0,0 +1,146 @@
+/// Margin Ratio Alerts
+/// A second liquidation alerting path, driven by the margin ratio the PnL
+/// updater computes instead of the distance to the liquidation price, so a
+/// position whose owner added margin reads as safer than its price suggests
+/// Tiers are multiples of the symbol's maintenance ratio, and each one alerts
+/// once as the ratio falls through it. Positions the price-distance path has
+/// already alerted on are marked, so they don't get a second alert here
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Default tiers, 2x and 1.5x the maintenance ratio
+pub fn default_margin_ratio_tiers() -> Vec<Decimal> {
+    vec![Decimal::TWO, Decimal::new(15, 1)]
+}
+
+/// How many of `tiers` (descending multiples of `maintenance_margin_ratio`)
+/// the margin ratio is below, 0 when above all of them
+pub fn margin_ratio_tier(margin_ratio: Decimal, maintenance_margin_ratio: Decimal, tiers: &[Decimal]) -> usize {
+    tiers
+        .iter()
+        .take_while(|multiple| margin_ratio < maintenance_margin_ratio * **multiple)
+        .count()
+}
+
+/// Where a position stands with the alerts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Alerted {
+    /// Deepest tier alerted since it last climbed above
+    Tier(usize),
+    /// The price-distance path alerted, silent until every tier clears
+    ByPrice,
+}
+
+pub struct MarginRatioAlerts {
+    /// Descending
+    tiers: Vec<Decimal>,
+    alerted: Mutex<HashMap<Pubkey, Alerted>>,
+}
+
+impl MarginRatioAlerts {
+    /// Tiers at or below 1x maintenance are dropped, the price path covers them
+    pub fn new(mut tiers: Vec<Decimal>) -> Self {
+        tiers.retain(|multiple| *multiple > Decimal::ONE);
+        tiers.sort_by(|a, b| b.cmp(a));
+        tiers.dedup();
+
+        Self {
+            tiers,
+            alerted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.tiers.is_empty()
+    }
+
+    /// The tier multiple to alert on when the position's margin ratio fell
+    /// into a tier deeper than any alerted so far. Climbing back above a tier
+    /// re-arms it, and back above all of them the position is forgotten
+    pub fn check(
+        &self,
+        position_account: &Pubkey,
+        margin_ratio: Decimal,
+        maintenance_margin_ratio: Decimal,
+    ) -> Option<Decimal> {
+        let tier = margin_ratio_tier(margin_ratio, maintenance_margin_ratio, &self.tiers);
+        let mut alerted = self.alerted.lock().unwrap();
+
+        if tier == 0 {
+            alerted.remove(position_account);
+            return None;
+        }
+
+        let last = match alerted.get(position_account) {
+            Some(Alerted::ByPrice) => return None,
+            Some(Alerted::Tier(last)) => *last,
+            None => 0,
+        };
+        alerted.insert(*position_account, Alerted::Tier(tier));
+        (tier > last).then(|| self.tiers[tier - 1])
+    }
+
+    /// The price-distance path alerted on the position, nothing more until
+    /// its margin ratio recovers
+    pub fn mark_alerted(&self, position_account: &Pubkey) {
+        if self.is_enabled() {
+            self.alerted.lock().unwrap().insert(*position_account, Alerted::ByPrice);
+        }
+    }
+
+    pub fn forget(&self, position_account: &Pubkey) {
+        self.alerted.lock().unwrap().remove(position_account);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_margin_ratio_tier() {
+        let tiers = default_margin_ratio_tiers();
+        // 2.5% maintenance: tiers at 5% and 3.75%
+        assert_eq!(margin_ratio_tier(dec!(0.06), dec!(0.025), &tiers), 0);
+        assert_eq!(margin_ratio_tier(dec!(0.05), dec!(0.025), &tiers), 0);
+        assert_eq!(margin_ratio_tier(dec!(0.045), dec!(0.025), &tiers), 1);
+        assert_eq!(margin_ratio_tier(dec!(0.03), dec!(0.025), &tiers), 2);
+        assert_eq!(margin_ratio_tier(dec!(-0.01), dec!(0.025), &tiers), 2);
+    }
+
+    #[test]
+    fn test_each_tier_alerts_once() {
+        let alerts = MarginRatioAlerts::new(vec![dec!(1.5), dec!(2), dec!(1)]);
+        let account = Pubkey::new_unique();
+
+        assert_eq!(alerts.check(&account, dec!(0.06), dec!(0.025)), None);
+        assert_eq!(alerts.check(&account, dec!(0.045), dec!(0.025)), Some(dec!(2)));
+        assert_eq!(alerts.check(&account, dec!(0.044), dec!(0.025)), None);
+        assert_eq!(alerts.check(&account, dec!(0.03), dec!(0.025)), Some(dec!(1.5)));
+
+        // Topped up above the first tier, falling again alerts again
+        assert_eq!(alerts.check(&account, dec!(0.045), dec!(0.025)), None);
+        assert_eq!(alerts.check(&account, dec!(0.03), dec!(0.025)), Some(dec!(1.5)));
+        assert_eq!(alerts.check(&account, dec!(0.06), dec!(0.025)), None);
+        assert_eq!(alerts.check(&account, dec!(0.045), dec!(0.025)), Some(dec!(2)));
+    }
+
+    #[test]
+    fn test_price_alert_suppresses_ratio_alert() {
+        let alerts = MarginRatioAlerts::new(default_margin_ratio_tiers());
+        let account = Pubkey::new_unique();
+
+        alerts.mark_alerted(&account);
+        assert_eq!(alerts.check(&account, dec!(0.045), dec!(0.025)), None);
+        assert_eq!(alerts.check(&account, dec!(0.03), dec!(0.025)), None);
+
+        // Recovered, the next fall is the ratio path's to report
+        assert_eq!(alerts.check(&account, dec!(0.06), dec!(0.025)), None);
+        assert_eq!(alerts.check(&account, dec!(0.03), dec!(0.025)), Some(dec!(1.5)));
+
+        assert!(!MarginRatioAlerts::new(Vec::new()).is_enabled());
+    }
+}
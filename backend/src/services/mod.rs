@@ -3,10 +3,87 @@ pub mod position_manager;
 pub mod position_monitor;
 pub mod on_chain_types;
 pub mod liquidation_alert;
+pub mod fee_payer;
+pub mod liquidation_executor;
+pub mod event_bus;
+pub mod leader_election;
+pub mod mutation_tracker;
+pub mod tax_report;
+pub mod synthetic_load;
+pub mod position_retention;
+pub mod position_scan;
+pub mod position_timeline;
+pub mod position_alerts;
+pub mod price_alerts;
+pub mod paper_trading;
+pub mod replay;
+pub mod backstop_vault;
+pub mod trading_halt;
+pub mod submission_limiter;
+pub mod margin_call;
+pub mod symbol_config;
+pub mod strategies;
+pub mod netting;
+pub mod api_tokens;
+pub mod external_positions;
+pub mod liquidation_profit;
+pub mod dynamic_margin;
+pub mod backfill;
+pub mod webhooks;
+pub mod liquidation_map;
+pub mod outbox;
+pub mod risk_limits;
+pub mod demo_bootstrap;
+pub mod margin_ratio_alerts;
+pub mod notifier;
+pub mod user_offboarding;
+pub mod fee_tiers;
+pub mod portfolio;
+pub mod tenants;
+pub mod history_retention;
+pub mod trade_simulation;
 
 
 pub use margin_calculator::*;
 pub use position_manager::*;
 pub use position_monitor::*;
 pub use on_chain_types::*;
-pub use liquidation_alert::*;
\ No newline at end of file
+pub use liquidation_alert::*;
+pub use fee_payer::*;
+pub use liquidation_executor::*;
+pub use event_bus::*;
+pub use leader_election::*;
+pub use mutation_tracker::*;
+pub use tax_report::*;
+pub use synthetic_load::*;
+pub use position_retention::*;
+pub use position_scan::*;
+pub use position_timeline::*;
+pub use position_alerts::*;
+pub use price_alerts::*;
+pub use paper_trading::*;
+pub use replay::*;
+pub use backstop_vault::*;
+pub use trading_halt::*;
+pub use submission_limiter::*;
+pub use margin_call::*;
+pub use symbol_config::*;
+pub use strategies::*;
+pub use netting::*;
+pub use api_tokens::*;
+pub use external_positions::*;
+pub use liquidation_profit::*;pub use dynamic_margin::*;
+pub use backfill::*;
+pub use webhooks::*;
+pub use liquidation_map::*;
+pub use outbox::*;
+pub use risk_limits::*;
+pub use demo_bootstrap::*;
+pub use margin_ratio_alerts::*;
+pub use notifier::*;
+pub use user_offboarding::*;
+pub use fee_tiers::*;
+pub use portfolio::*;
+pub use tenants::*;
+pub use history_retention::*;
+pub use trade_simulation::*;
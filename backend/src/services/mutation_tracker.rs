@@ -0,0 +1,228 @@
+/// Mutation Tracker
+/// Cache changes made right after a transaction confirms can be rolled back
+/// if the fork is abandoned. Every such change is recorded with the cached
+/// position it replaced, re-checked until finalized, and reverted when the
+/// transaction fails or disappears
+/// Opens are cached as Opening before they're sent, so reads right after an
+/// open find the position. Until a chain scan has seen the account, scans
+/// from a lagging RPC must not drop it as closed
+use chrono::{DateTime, Duration, Utc};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+use crate::domain::Position;
+
+#[derive(Debug, Clone)]
+pub struct MutationTrackerConfig {
+    pub verify_interval_ms: u64,
+    /// Unknown signatures older than this are treated as rolled back
+    pub drop_timeout: Duration,
+}
+
+impl Default for MutationTrackerConfig {
+    fn default() -> Self {
+        Self {
+            verify_interval_ms: 5000,
+            // Blockhash expiry plus finalization lag
+            drop_timeout: Duration::seconds(90),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackedMutation {
+    pub signature: Signature,
+    pub position_account: Pubkey,
+    /// Cached position before the change, None if it was newly added
+    pub previous: Option<Position>,
+    /// Slot the transaction was last seen in
+    pub slot: Option<u64>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// What the cluster currently reports for a signature
+#[derive(Debug, Clone, Copy)]
+pub struct SignatureState {
+    pub slot: u64,
+    pub failed: bool,
+    pub finalized: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MutationVerdict {
+    Finalized,
+    Pending,
+    RolledBack,
+}
+
+pub struct MutationTracker {
+    config: MutationTrackerConfig,
+    pending: RwLock<Vec<TrackedMutation>>,
+    /// Opened position accounts not yet seen by a chain scan, and when they
+    /// were registered or confirmed
+    unseen_opens: RwLock<HashMap<Pubkey, DateTime<Utc>>>,
+}
+
+impl MutationTracker {
+    pub fn new(config: MutationTrackerConfig) -> Self {
+        Self {
+            config,
+            pending: RwLock::new(Vec::new()),
+            unseen_opens: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &MutationTrackerConfig {
+        &self.config
+    }
+
+    pub async fn track(&self, signature: Signature, position_account: Pubkey, previous: Option<Position>) {
+        self.pending.write().await.push(TrackedMutation {
+            signature,
+            position_account,
+            previous,
+            slot: None,
+            recorded_at: Utc::now(),
+        });
+    }
+
+    /// Shield an opened account from stale scans, from `at` for `drop_timeout`
+    pub async fn register_open(&self, position_account: Pubkey, at: DateTime<Utc>) {
+        self.unseen_opens.write().await.insert(position_account, at);
+    }
+
+    /// The account is on chain, or its open was abandoned
+    pub async fn forget_open(&self, position_account: &Pubkey) {
+        self.unseen_opens.write().await.remove(position_account);
+    }
+
+    /// Whether a scan missing the account may be older than its open
+    pub async fn is_unseen_open(&self, position_account: &Pubkey, now: DateTime<Utc>) -> bool {
+        let mut unseen_opens = self.unseen_opens.write().await;
+        unseen_opens.retain(|_, at| now - *at <= self.config.drop_timeout);
+        unseen_opens.contains_key(position_account)
+    }
+
+    pub async fn pending(&self) -> Vec<TrackedMutation> {
+        self.pending.read().await.clone()
+    }
+
+    /// Apply the latest statuses, returning mutations that have to be reverted
+    /// Finalized and rolled back mutations stop being tracked
+    pub async fn resolve(
+        &self,
+        statuses: &[(Signature, Option<SignatureState>)],
+        now: DateTime<Utc>,
+    ) -> Vec<TrackedMutation> {
+        let mut pending = self.pending.write().await;
+        let mut rolled_back = Vec::new();
+
+        pending.retain_mut(|mutation| {
+            let Some((_, state)) = statuses.iter().find(|(sig, _)| *sig == mutation.signature) else {
+                return true;
+            };
+
+            if let Some(state) = state {
+                mutation.slot = Some(state.slot);
+            }
+
+            match verdict(*state, now - mutation.recorded_at, self.config.drop_timeout) {
+                MutationVerdict::Finalized => false,
+                MutationVerdict::Pending => true,
+                MutationVerdict::RolledBack => {
+                    rolled_back.push(mutation.clone());
+                    false
+                }
+            }
+        });
+
+        // Undo newest first so older snapshots end up in the cache
+        rolled_back.reverse();
+        rolled_back
+    }
+}
+
+pub fn verdict(state: Option<SignatureState>, age: Duration, drop_timeout: Duration) -> MutationVerdict {
+    match state {
+        Some(state) if state.failed => MutationVerdict::RolledBack,
+        Some(state) if state.finalized => MutationVerdict::Finalized,
+        Some(_) => MutationVerdict::Pending,
+        None if age > drop_timeout => MutationVerdict::RolledBack,
+        None => MutationVerdict::Pending,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(failed: bool, finalized: bool) -> Option<SignatureState> {
+        Some(SignatureState {
+            slot: 100,
+            failed,
+            finalized,
+        })
+    }
+
+    #[test]
+    fn test_verdict() {
+        let timeout = Duration::seconds(90);
+
+        assert_eq!(verdict(state(false, true), Duration::seconds(1), timeout), MutationVerdict::Finalized);
+        assert_eq!(verdict(state(false, false), Duration::seconds(200), timeout), MutationVerdict::Pending);
+        assert_eq!(verdict(state(true, false), Duration::seconds(1), timeout), MutationVerdict::RolledBack);
+        assert_eq!(verdict(None, Duration::seconds(10), timeout), MutationVerdict::Pending);
+        assert_eq!(verdict(None, Duration::seconds(91), timeout), MutationVerdict::RolledBack);
+    }
+
+    #[tokio::test]
+    async fn test_unseen_opens() {
+        let tracker = MutationTracker::new(MutationTrackerConfig::default());
+        let now = Utc::now();
+        let seen = Pubkey::new_unique();
+        let stale = Pubkey::new_unique();
+
+        tracker.register_open(seen, now).await;
+        tracker.register_open(stale, now - Duration::seconds(91)).await;
+
+        assert!(tracker.is_unseen_open(&seen, now).await);
+        assert!(!tracker.is_unseen_open(&stale, now).await);
+        assert!(!tracker.is_unseen_open(&Pubkey::new_unique(), now).await);
+
+        tracker.forget_open(&seen).await;
+        assert!(!tracker.is_unseen_open(&seen, now).await);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_drops_finalized_and_returns_rolled_back() {
+        let tracker = MutationTracker::new(MutationTrackerConfig::default());
+        let finalized = Signature::new_unique();
+        let dropped = Signature::new_unique();
+        let pending = Signature::new_unique();
+
+        tracker.track(finalized, Pubkey::new_unique(), None).await;
+        tracker.track(dropped, Pubkey::new_unique(), None).await;
+        tracker.track(pending, Pubkey::new_unique(), None).await;
+
+        let later = Utc::now() + Duration::seconds(120);
+        let rolled_back = tracker
+            .resolve(
+                &[
+                    (finalized, state(false, true)),
+                    (dropped, None),
+                    (pending, state(false, false)),
+                ],
+                later,
+            )
+            .await;
+
+        assert_eq!(rolled_back.len(), 1);
+        assert_eq!(rolled_back[0].signature, dropped);
+
+        let remaining = tracker.pending().await;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].signature, pending);
+        assert_eq!(remaining[0].slot, Some(100));
+    }
+}
@@ -0,0 +1,312 @@
+/// One-Way Netting
+/// In one-way mode an owner holds a single direction per symbol. An order
+/// against that direction first closes existing lots, oldest first, and only
+/// the remainder opens on the other side: selling 0.5 while long 0.3 closes
+/// the 0.3 long and opens a 0.2 short. The program has no netting, so the plan
+/// is carried out as ordinary close and open instructions. `modify_position`
+/// can shrink a lot but settles no PnL, so a lot the order only partly covers
+/// is closed and what is left of it reopened at the order price
+use anyhow::Result;
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::fmt;
+
+use crate::domain::{Position, Side};
+use crate::error::{Error, FieldError};
+use crate::services::MarginCalculator;
+
+/// One instruction of a netted order
+#[derive(Debug, Clone, PartialEq)]
+pub enum NettingStep {
+    /// Close a whole lot at the order price
+    Close { position_account: Pubkey, size: Decimal },
+    /// Close a lot the order covers `size` of, then reopen `new_size` on its
+    /// side at the order price and its leverage
+    Reduce {
+        position_account: Pubkey,
+        size: Decimal,
+        new_size: Decimal,
+        side: Side,
+        leverage: u16,
+    },
+    /// Open what is left of the order
+    Open { side: Side, size: Decimal },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NettingPlan {
+    pub steps: Vec<NettingStep>,
+    /// Part of the order matched against existing lots
+    pub netted_size: Decimal,
+    /// Part of the order opened as a new lot
+    pub opened_size: Decimal,
+    /// PnL settled at the order price, with the accrued funding
+    /// `close_position` settles. A reduced lot is closed whole, so all of its
+    /// PnL is realized, not just the netted part's
+    pub realized_pnl: Decimal,
+    /// Direction and size held once the plan has run, none when flat
+    pub resulting_side: Option<Side>,
+    pub resulting_size: Decimal,
+}
+
+/// Plan an order against the owner's open `lots` on one symbol, in the order
+/// they should be consumed (oldest first). Lots must all share one side
+pub fn plan_one_way_order(
+    lots: &[Position],
+    side: Side,
+    size: Decimal,
+    price: Decimal,
+) -> Result<NettingPlan> {
+    if size <= Decimal::ZERO {
+        return Err(invalid("size", "must be positive".to_string()));
+    }
+
+    let held_side = match lots.first() {
+        Some(lot) if lots.iter().any(|other| other.side != lot.side) => {
+            return Err(invalid(
+                "side",
+                format!(
+                    "{} has both long and short positions open, one-way netting needs a single side",
+                    lot.symbol
+                ),
+            ));
+        }
+        Some(lot) => Some(lot.side),
+        None => None,
+    };
+    let held_size: Decimal = lots.iter().map(|lot| lot.size).sum();
+
+    if held_side.is_none_or(|held| held == side) {
+        return Ok(NettingPlan {
+            steps: vec![NettingStep::Open { side, size }],
+            netted_size: Decimal::ZERO,
+            opened_size: size,
+            realized_pnl: Decimal::ZERO,
+            resulting_side: Some(side),
+            resulting_size: held_size + size,
+        });
+    }
+
+    let mut steps = Vec::new();
+    let mut remaining = size;
+    let mut realized_pnl = Decimal::ZERO;
+
+    for lot in lots {
+        if remaining.is_zero() {
+            break;
+        }
+
+        let matched = remaining.min(lot.size);
+        let pnl = MarginCalculator::calculate_unrealized_pnl(lot.side, lot.size, price, lot.entry_price)?;
+        realized_pnl += pnl + lot.funding_accrued;
+        remaining -= matched;
+
+        if matched == lot.size {
+            steps.push(NettingStep::Close {
+                position_account: lot.position_account,
+                size: matched,
+            });
+        } else {
+            steps.push(NettingStep::Reduce {
+                position_account: lot.position_account,
+                size: matched,
+                new_size: lot.size - matched,
+                side: lot.side,
+                leverage: lot.leverage,
+            });
+        }
+    }
+
+    if remaining > Decimal::ZERO {
+        steps.push(NettingStep::Open { side, size: remaining });
+    }
+
+    let netted_size = size - remaining;
+    let (resulting_side, resulting_size) = if remaining > Decimal::ZERO {
+        (Some(side), remaining)
+    } else if netted_size < held_size {
+        (held_side, held_size - netted_size)
+    } else {
+        (None, Decimal::ZERO)
+    };
+
+    Ok(NettingPlan {
+        steps,
+        netted_size,
+        opened_size: remaining,
+        realized_pnl,
+        resulting_side,
+        resulting_size,
+    })
+}
+
+/// A netted order that failed part way, `completed` are the signatures of
+/// the transactions that landed before `source`
+#[derive(Debug)]
+pub struct PartialExecution {
+    pub completed: Vec<Signature>,
+    pub total_steps: usize,
+    pub source: anyhow::Error,
+}
+
+impl fmt::Display for PartialExecution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Netted order stopped after {} of {} transactions: {:#}",
+            self.completed.len(),
+            self.total_steps,
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for PartialExecution {}
+
+fn invalid(field: &'static str, message: String) -> anyhow::Error {
+    Error::Validation(vec![FieldError { field, message }]).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn lot(side: Side, size: Decimal, entry_price: Decimal) -> Position {
+        Position {
+            symbol: "BTC-USD".to_string(),
+            side,
+            size,
+            entry_price,
+            mark_price: entry_price,
+            ..Position::for_test()
+        }
+    }
+
+    #[test]
+    fn test_flat_opens_whole_order() {
+        let plan = plan_one_way_order(&[], Side::Short, dec!(0.5), dec!(100)).unwrap();
+
+        assert_eq!(plan.steps, vec![NettingStep::Open { side: Side::Short, size: dec!(0.5) }]);
+        assert_eq!(plan.netted_size, Decimal::ZERO);
+        assert_eq!(plan.resulting_side, Some(Side::Short));
+        assert_eq!(plan.resulting_size, dec!(0.5));
+    }
+
+    #[test]
+    fn test_same_side_adds_a_lot() {
+        let lots = [lot(Side::Long, dec!(0.3), dec!(100))];
+        let plan = plan_one_way_order(&lots, Side::Long, dec!(0.2), dec!(110)).unwrap();
+
+        assert_eq!(plan.steps, vec![NettingStep::Open { side: Side::Long, size: dec!(0.2) }]);
+        assert_eq!(plan.realized_pnl, Decimal::ZERO);
+        assert_eq!(plan.resulting_size, dec!(0.5));
+    }
+
+    #[test]
+    fn test_smaller_opposite_order_reduces() {
+        let lots = [lot(Side::Long, dec!(0.3), dec!(100))];
+        let plan = plan_one_way_order(&lots, Side::Short, dec!(0.1), dec!(120)).unwrap();
+
+        assert_eq!(
+            plan.steps,
+            vec![NettingStep::Reduce {
+                position_account: lots[0].position_account,
+                size: dec!(0.1),
+                new_size: dec!(0.2),
+                side: Side::Long,
+                leverage: lots[0].leverage,
+            }]
+        );
+        // The whole lot is closed at 120 and 0.2 reopened there
+        assert_eq!(plan.realized_pnl, dec!(6));
+        assert_eq!(plan.opened_size, Decimal::ZERO);
+        assert_eq!(plan.resulting_side, Some(Side::Long));
+        assert_eq!(plan.resulting_size, dec!(0.2));
+    }
+
+    #[test]
+    fn test_exact_opposite_order_goes_flat() {
+        let mut short = lot(Side::Short, dec!(0.3), dec!(100));
+        short.funding_accrued = dec!(-0.5);
+        let plan = plan_one_way_order(&[short.clone()], Side::Long, dec!(0.3), dec!(110)).unwrap();
+
+        assert_eq!(
+            plan.steps,
+            vec![NettingStep::Close { position_account: short.position_account, size: dec!(0.3) }]
+        );
+        assert_eq!(plan.realized_pnl, dec!(-3.5));
+        assert_eq!(plan.resulting_side, None);
+        assert_eq!(plan.resulting_size, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_larger_opposite_order_flips() {
+        let lots = [lot(Side::Long, dec!(0.3), dec!(100))];
+        let plan = plan_one_way_order(&lots, Side::Short, dec!(0.5), dec!(90)).unwrap();
+
+        assert_eq!(
+            plan.steps,
+            vec![
+                NettingStep::Close { position_account: lots[0].position_account, size: dec!(0.3) },
+                NettingStep::Open { side: Side::Short, size: dec!(0.2) },
+            ]
+        );
+        assert_eq!(plan.netted_size, dec!(0.3));
+        assert_eq!(plan.opened_size, dec!(0.2));
+        assert_eq!(plan.realized_pnl, dec!(-3));
+        assert_eq!(plan.resulting_side, Some(Side::Short));
+        assert_eq!(plan.resulting_size, dec!(0.2));
+    }
+
+    #[test]
+    fn test_consumes_lots_oldest_first() {
+        let lots = [
+            lot(Side::Long, dec!(0.2), dec!(100)),
+            lot(Side::Long, dec!(0.2), dec!(105)),
+            lot(Side::Long, dec!(0.2), dec!(110)),
+        ];
+        let plan = plan_one_way_order(&lots, Side::Short, dec!(0.3), dec!(110)).unwrap();
+
+        assert_eq!(
+            plan.steps,
+            vec![
+                NettingStep::Close { position_account: lots[0].position_account, size: dec!(0.2) },
+                NettingStep::Reduce {
+                    position_account: lots[1].position_account,
+                    size: dec!(0.1),
+                    new_size: dec!(0.1),
+                    side: Side::Long,
+                    leverage: lots[1].leverage,
+                },
+            ]
+        );
+        // 0.2 × 10 + 0.2 × 5, the second lot is closed whole
+        assert_eq!(plan.realized_pnl, dec!(3));
+        assert_eq!(plan.resulting_size, dec!(0.3));
+    }
+
+    #[test]
+    fn test_rejects_mixed_sides_and_empty_orders() {
+        let lots = [lot(Side::Long, dec!(0.2), dec!(100)), lot(Side::Short, dec!(0.2), dec!(100))];
+
+        assert!(plan_one_way_order(&lots, Side::Short, dec!(0.1), dec!(100)).is_err());
+        assert!(plan_one_way_order(&[], Side::Long, Decimal::ZERO, dec!(100)).is_err());
+    }
+
+    #[test]
+    fn test_partial_execution_keeps_landed_signatures() {
+        let landed = vec![Signature::new_unique()];
+        let err: anyhow::Error = PartialExecution {
+            completed: landed.clone(),
+            total_steps: 3,
+            source: anyhow::anyhow!("Insufficient collateral"),
+        }
+        .into();
+
+        assert!(err.to_string().starts_with("Netted order stopped after 1 of 3 transactions"));
+        let partial = err.downcast::<PartialExecution>().unwrap();
+        assert_eq!(partial.completed, landed);
+    }
+}
@@ -0,0 +1,377 @@
+/// Alert Notifiers
+/// Liquidation alerts go out through sinks implementing `Notifier`, registered
+/// at startup: the event bus feeding the WebSockets and keepers, the log, a
+/// Redis stream and webhooks. A sink with a batch window gets alerts in
+/// batches, gathered for that long and coalesced to the latest alert per
+/// position, so a market-wide crash is one webhook POST per window instead
+/// of one per position
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use redis::streams::StreamMaxlen;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::services::{DomainEvent, EventBus, LiquidationAlert, WebhookDispatcher, WebhookEventType};
+
+/// Alerts delivered in one batch at most, a longer backlog goes out in several
+pub const MAX_ALERT_BATCH: usize = 500;
+
+/// Gathering window of the Redis stream sink, keeps appends off the alert path
+const REDIS_STREAM_BATCH_WINDOW: Duration = Duration::from_millis(100);
+
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// How long to gather alerts before a delivery, zero delivers each alert
+    /// as it's raised
+    fn batch_window(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn notify<'a>(&'a self, alerts: &'a [LiquidationAlert]) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Latest alert per position, in the order positions first appeared
+pub fn coalesce_alerts(alerts: Vec<LiquidationAlert>) -> Vec<LiquidationAlert> {
+    let mut index: HashMap<_, usize> = HashMap::new();
+    let mut coalesced: Vec<LiquidationAlert> = Vec::with_capacity(alerts.len());
+
+    for alert in alerts {
+        match index.get(&alert.position_account) {
+            Some(i) => coalesced[*i] = alert,
+            None => {
+                index.insert(alert.position_account, coalesced.len());
+                coalesced.push(alert);
+            }
+        }
+    }
+
+    coalesced
+}
+
+/// The sinks alerts are published to
+#[derive(Default)]
+pub struct AlertNotifiers {
+    /// Sinks without a batch window, called as each alert is raised
+    immediate: RwLock<Vec<Arc<dyn Notifier>>>,
+    /// Queues of the batched sinks' workers
+    batched: RwLock<Vec<mpsc::UnboundedSender<LiquidationAlert>>>,
+}
+
+impl AlertNotifiers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sink, spawning its batching worker if it has a batch window
+    pub fn register(&self, notifier: Arc<dyn Notifier>) {
+        if notifier.batch_window().is_zero() {
+            self.immediate.write().unwrap().push(notifier);
+            return;
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run_batched(notifier, rx));
+        self.batched.write().unwrap().push(tx);
+    }
+
+    /// Hand `alert` to every sink
+    pub async fn notify(&self, alert: LiquidationAlert) {
+        for tx in self.batched.read().unwrap().iter() {
+            let _ = tx.send(alert.clone());
+        }
+
+        let immediate = self.immediate.read().unwrap().clone();
+        let alerts = [alert];
+        for notifier in immediate {
+            if let Err(e) = notifier.notify(&alerts).await {
+                warn!("{} notifier failed on alert for {}: {}", notifier.name(), alerts[0].position_account, e);
+            }
+        }
+    }
+}
+
+/// Deliver whatever arrives within a window of the first queued alert, until
+/// the registry is dropped
+async fn run_batched(notifier: Arc<dyn Notifier>, mut rx: mpsc::UnboundedReceiver<LiquidationAlert>) {
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        let window = tokio::time::sleep(notifier.batch_window());
+        tokio::pin!(window);
+
+        while batch.len() < MAX_ALERT_BATCH {
+            tokio::select! {
+                _ = &mut window => break,
+                alert = rx.recv() => match alert {
+                    Some(alert) => batch.push(alert),
+                    None => break,
+                },
+            }
+        }
+
+        let batch = coalesce_alerts(batch);
+        if let Err(e) = notifier.notify(&batch).await {
+            warn!("{} notifier failed on a batch of {} alerts: {}", notifier.name(), batch.len(), e);
+        }
+    }
+}
+
+/// Publishes on the event bus, for the WebSocket rooms, GraphQL and keepers
+pub struct EventBusNotifier {
+    events: Arc<dyn EventBus>,
+}
+
+impl EventBusNotifier {
+    pub fn new(events: Arc<dyn EventBus>) -> Self {
+        Self { events }
+    }
+}
+
+impl Notifier for EventBusNotifier {
+    fn name(&self) -> &str {
+        "event_bus"
+    }
+
+    fn notify<'a>(&'a self, alerts: &'a [LiquidationAlert]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            for alert in alerts {
+                self.events.publish(DomainEvent::Liquidation(alert.clone()));
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Logs each alert as a warning
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    fn notify<'a>(&'a self, alerts: &'a [LiquidationAlert]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            for alert in alerts {
+                match alert.margin_ratio {
+                    Some(margin_ratio) => warn!(
+                        "{:?} ALERT: {} {:?} position {} - Margin ratio {} below {}x maintenance",
+                        alert.risk_type,
+                        alert.symbol,
+                        alert.side,
+                        alert.position_account,
+                        margin_ratio,
+                        alert.margin_ratio_tier.unwrap_or_default()
+                    ),
+                    None => warn!(
+                        "{:?} ALERT: {} {:?} position {} - Current: ${:.2}, Liquidation: ${:.2}",
+                        alert.risk_type,
+                        alert.symbol,
+                        alert.side,
+                        alert.position_account,
+                        alert.current_price,
+                        alert.liquidation_price
+                    ),
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Appends alerts to a capped Redis stream, one entry each with the alert
+/// JSON under `alert`
+pub struct RedisStreamNotifier {
+    redis_client: redis::Client,
+    stream: String,
+    max_len: usize,
+}
+
+impl RedisStreamNotifier {
+    pub fn new(redis_client: redis::Client, stream: String, max_len: usize) -> Self {
+        Self {
+            redis_client,
+            stream,
+            max_len,
+        }
+    }
+}
+
+impl Notifier for RedisStreamNotifier {
+    fn name(&self) -> &str {
+        "redis_stream"
+    }
+
+    fn batch_window(&self) -> Duration {
+        REDIS_STREAM_BATCH_WINDOW
+    }
+
+    fn notify<'a>(&'a self, alerts: &'a [LiquidationAlert]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut conn = self
+                .redis_client
+                .get_multiplexed_async_connection()
+                .await
+                .context("Failed to get Redis connection")?;
+
+            let mut pipe = redis::pipe();
+            for alert in alerts {
+                pipe.cmd("XADD")
+                    .arg(&self.stream)
+                    .arg(StreamMaxlen::Approx(self.max_len))
+                    .arg("*")
+                    .arg("alert")
+                    .arg(serde_json::to_string(alert)?)
+                    .ignore();
+            }
+            pipe.query_async::<_, ()>(&mut conn)
+                .await
+                .context("Failed to append alerts to the stream")?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Posts each batch to the webhooks subscribed to `liquidation_alerts`, the
+/// alerts as a JSON array under `data`. A tenant's endpoints get the part of
+/// the batch on its users' positions
+pub struct WebhookNotifier {
+    webhooks: Arc<WebhookDispatcher>,
+    batch_window: Duration,
+}
+
+impl WebhookNotifier {
+    pub fn new(webhooks: Arc<WebhookDispatcher>, batch_window: Duration) -> Self {
+        Self {
+            webhooks,
+            batch_window,
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn batch_window(&self) -> Duration {
+        self.batch_window
+    }
+
+    fn notify<'a>(&'a self, alerts: &'a [LiquidationAlert]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.webhooks.dispatch(WebhookEventType::LiquidationAlerts, &alerts);
+
+            // Tenants get the alerts of their own users
+            let Some(tenants) = self.webhooks.tenants().filter(|tenants| !tenants.is_empty()) else {
+                return Ok(());
+            };
+            let owner_tenants = tenants.owner_tenants().await?;
+            let mut by_tenant: HashMap<&str, Vec<&LiquidationAlert>> = HashMap::new();
+            for alert in alerts {
+                if let Some(tenant) = alert.owner.as_ref().and_then(|owner| owner_tenants.get(owner)) {
+                    by_tenant.entry(tenant).or_default().push(alert);
+                }
+            }
+            for (tenant, alerts) in by_tenant {
+                self.webhooks
+                    .dispatch_to_tenant(tenant, WebhookEventType::LiquidationAlerts, &alerts);
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Risk, Side};
+    use rust_decimal::Decimal;
+    use solana_sdk::pubkey::Pubkey;
+    use std::sync::Mutex;
+
+    fn alert(position_account: Pubkey, risk_type: Risk) -> LiquidationAlert {
+        LiquidationAlert {
+            position_account,
+            symbol: "SOL".to_string(),
+            side: Side::Long,
+            liquidation_price: Decimal::from(90),
+            current_price: Decimal::from(95),
+            risk_type,
+            margin_call_deadline: None,
+            owner: None,
+            estimated_loss: None,
+            margin_ratio: None,
+            margin_ratio_tier: None,
+        }
+    }
+
+    /// Keeps every batch it's handed
+    struct Recorder {
+        batch_window: Duration,
+        batches: Mutex<Vec<Vec<LiquidationAlert>>>,
+    }
+
+    impl Notifier for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn batch_window(&self) -> Duration {
+            self.batch_window
+        }
+
+        fn notify<'a>(&'a self, alerts: &'a [LiquidationAlert]) -> BoxFuture<'a, Result<()>> {
+            self.batches.lock().unwrap().push(alerts.to_vec());
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[test]
+    fn test_coalesce_keeps_latest_per_position() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+
+        let coalesced = coalesce_alerts(vec![
+            alert(a, Risk::Liquidating),
+            alert(b, Risk::Liquidating),
+            alert(a, Risk::Liquidated),
+        ]);
+
+        assert_eq!(coalesced.len(), 2);
+        assert_eq!(coalesced[0].position_account, a);
+        assert_eq!(coalesced[0].risk_type, Risk::Liquidated);
+        assert_eq!(coalesced[1].position_account, b);
+    }
+
+    #[tokio::test]
+    async fn test_batched_sink_gets_one_delivery_per_window() {
+        let notifiers = AlertNotifiers::new();
+        let immediate = Arc::new(Recorder {
+            batch_window: Duration::ZERO,
+            batches: Mutex::new(Vec::new()),
+        });
+        let batched = Arc::new(Recorder {
+            batch_window: Duration::from_millis(50),
+            batches: Mutex::new(Vec::new()),
+        });
+        notifiers.register(immediate.clone());
+        notifiers.register(batched.clone());
+
+        let a = Pubkey::new_unique();
+        for _ in 0..3 {
+            notifiers.notify(alert(a, Risk::Liquidating)).await;
+        }
+        notifiers.notify(alert(Pubkey::new_unique(), Risk::Liquidating)).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(immediate.batches.lock().unwrap().len(), 4);
+        let batches = batched.batches.lock().unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].len(), 2);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::domain::{Position, PositionStatus, Side};
+use crate::domain::{symbols, Position, PositionStatus, Side};
 use anyhow::{Result, anyhow, Context};
 use rust_decimal::Decimal;
 use solana_sdk::{
@@ -25,6 +25,28 @@ pub enum OnChainPositionStatus {
     Modifying,
     Closing,
     Closed,
+    Liquidated,
+    MarginCall,
+}
+
+/// Changes a Position account keeps, matching the program's POSITION_HISTORY_LEN
+pub const POSITION_HISTORY_LEN: usize = 8;
+
+/// Taker fee tiers in the Config schedule, matching the program's FEE_TIER_COUNT
+pub const FEE_TIER_COUNT: usize = 4;
+
+/// On-chain PositionChange, one entry of a Position's history
+#[derive(AnchorDeserialize, AnchorSerialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct OnChainPositionChange {
+    pub size: u64,
+    pub margin: u64,
+    pub leverage: u16,
+    pub price: u64,
+    pub slot: u64,
+}
+
+impl OnChainPositionChange {
+    pub const LEN: usize = 8 + 8 + 2 + 8 + 8;
 }
 
 /// On-chain Position account structure
@@ -44,12 +66,32 @@ pub struct OnChainPosition {
     pub last_update: i64,
     pub status: OnChainPositionStatus,
     pub bump: u8,
+    pub history: [OnChainPositionChange; POSITION_HISTORY_LEN],
+    pub history_count: u32,
+    pub funding_settled: i64,
 }
 
 impl OnChainPosition {
     pub const DISCRIMINATOR: [u8; 8] = [
         0xaa, 0xbc, 0x8f, 0xe4, 0x7a, 0x40, 0xf7, 0xd0,
     ];
+
+    /// Account size the program allocates, see Position::MAX_SIZE
+    pub const MAX_SIZE: usize = 8 + 32 + 4 + 32 + 1 + 8 + 8 + 8 + 2 + 8 + 8 + 8 + 8 + 8 + 1 + 1
+        + POSITION_HISTORY_LEN * OnChainPositionChange::LEN
+        + 4
+        + 8;
+
+    /// Recorded changes, oldest first. The program overwrites the oldest
+    /// once the history is full
+    pub fn changes(&self) -> Vec<OnChainPositionChange> {
+        let count = (self.history_count as usize).min(POSITION_HISTORY_LEN);
+        let start = self.history_count as usize - count;
+        (start..start + count)
+            .map(|i| self.history[i % POSITION_HISTORY_LEN])
+            .collect()
+    }
+
     /// Convert to domain Position model
     pub fn to_domain_position(&self, position_account: Pubkey, position_index: u32) -> Result<Position> {
         // Convert side
@@ -65,6 +107,8 @@ impl OnChainPosition {
             OnChainPositionStatus::Modifying => PositionStatus::Modifying,
             OnChainPositionStatus::Closing => PositionStatus::Closing,
             OnChainPositionStatus::Closed => PositionStatus::Closed,
+            OnChainPositionStatus::Liquidated => PositionStatus::Liquidated,
+            OnChainPositionStatus::MarginCall => PositionStatus::MarginCall,
         };
         
         // Convert fixed-point numbers to Decimal (considering 6 decimals)
@@ -76,19 +120,7 @@ impl OnChainPosition {
         let funding_accrued_decimal = Decimal::new(self.funding_accrued, 6);
         let liquidation_price_decimal = Decimal::new(self.liquidation_price as i64, 6);
         
-        // Map symbol
-        // let oracle_symbol = self.symbol.replace("-USDT", "-USD");
-
-        fn normalize_symbol(symbol: &str) -> String {
-            for stable in ["USDT", "USDC", "DAI"].iter() {
-                if let Some(base) = symbol.strip_suffix(&format!("-{}", stable)) {
-                        return format!("{}-USD", base);
-                    }
-                }
-                symbol.to_string()
-        }
-
-        let oracle_symbol = normalize_symbol(&self.symbol);
+        let oracle_symbol = symbols::normalize(&self.symbol);
         
         Ok(Position {
             position_index,
@@ -105,14 +137,21 @@ impl OnChainPosition {
             realized_pnl: realized_pnl_decimal,
             funding_accrued: funding_accrued_decimal,
             liquidation_price: liquidation_price_decimal,
+            effective_liquidation_price: None,
             status,
             opened_at: chrono::DateTime::from_timestamp(self.last_update, 0)
-                .unwrap_or_else(|| Utc::now()),
+                .unwrap_or_else(Utc::now),
             last_update: chrono::DateTime::from_timestamp(self.last_update, 0)
-                .unwrap_or_else(|| Utc::now()),
-            closed_at: if status == PositionStatus::Closed {
+                .unwrap_or_else(Utc::now),
+            closed_at: if matches!(status, PositionStatus::Closed | PositionStatus::Liquidated) {
                 Some(chrono::DateTime::from_timestamp(self.last_update, 0)
-                    .unwrap_or_else(|| Utc::now()))
+                    .unwrap_or_else(Utc::now))
+            } else {
+                None
+            },
+            // The program keeps the call's start in last_update while it runs
+            margin_call_at: if status == PositionStatus::MarginCall {
+                chrono::DateTime::from_timestamp(self.last_update, 0)
             } else {
                 None
             },
@@ -132,20 +171,366 @@ pub struct OnChainUserAccount {
     pub bump: u8,
 }
 
+/// On-chain Config structure
+#[derive(AnchorDeserialize, AnchorSerialize, Debug)]
+pub struct OnChainConfig {
+    pub admin: Pubkey,
+    pub liquidator_whitelist_enabled: bool,
+    pub bump: u8,
+    pub partial_liquidation_enabled: bool,
+    pub liquidation_buffer_bps: u16,
+    pub paused: bool,
+    pub margin_call_buffer_bps: u16,
+    pub margin_call_grace_secs: u32,
+    pub taker_fee_bps: [u16; FEE_TIER_COUNT],
+    pub max_price_age_secs: u32,
+}
+
+impl OnChainConfig {
+    pub const DISCRIMINATOR: [u8; 8] = [
+        0x9b, 0x0c, 0xaa, 0xe0, 0x1e, 0xfa, 0xcc, 0x82,
+    ];
+}
+
+/// On-chain LiquidatorWhitelist structure
+#[derive(AnchorDeserialize, AnchorSerialize, Debug)]
+pub struct OnChainLiquidatorWhitelist {
+    pub liquidators: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl OnChainLiquidatorWhitelist {
+    pub const DISCRIMINATOR: [u8; 8] = [
+        0x3d, 0xf7, 0x82, 0x0c, 0xbe, 0x98, 0x76, 0x6b,
+    ];
+}
+
+/// On-chain BackstopVault structure
+#[derive(AnchorDeserialize, AnchorSerialize, Debug)]
+pub struct OnChainBackstopVault {
+    pub total_assets: u64,
+    pub total_shares: u64,
+    pub locked_margin: u64,
+    pub realized_pnl: i64,
+    pub discount_bps: u16,
+    pub position_count: u32,
+    pub position_count_total: u32,
+    pub share_mint: Pubkey,
+    pub bump: u8,
+}
+
+impl OnChainBackstopVault {
+    pub const DISCRIMINATOR: [u8; 8] = [
+        0x66, 0xb1, 0xdc, 0x1a, 0xaa, 0xa8, 0xc6, 0x8b,
+    ];
+}
+
+/// On-chain LpAccount structure
+#[derive(AnchorDeserialize, AnchorSerialize, Debug)]
+pub struct OnChainLpAccount {
+    pub owner: Pubkey,
+    pub cost_basis: u64,
+    pub bump: u8,
+}
+
+impl OnChainLpAccount {
+    pub const DISCRIMINATOR: [u8; 8] = [
+        0x17, 0xa6, 0xfb, 0xde, 0x17, 0x1b, 0xd7, 0x30,
+    ];
+}
+
+/// On-chain MarginMode enum
+#[derive(AnchorDeserialize, AnchorSerialize, Debug, Clone, Copy, PartialEq)]
+pub enum OnChainMarginMode {
+    Isolated,
+    Cross,
+}
+
+/// On-chain UserSettings structure
+#[derive(AnchorDeserialize, AnchorSerialize, Debug)]
+pub struct OnChainUserSettings {
+    pub owner: Pubkey,
+    pub default_slippage_bps: u16,
+    pub default_leverage: u16,
+    pub margin_mode: OnChainMarginMode,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl OnChainUserSettings {
+    pub const DISCRIMINATOR: [u8; 8] = [
+        0x93, 0xe5, 0x78, 0x38, 0x9e, 0x56, 0x4d, 0xd1,
+    ];
+}
+
+/// On-chain UserFeeTier structure
+#[derive(AnchorDeserialize, AnchorSerialize, Debug)]
+pub struct OnChainUserFeeTier {
+    pub owner: Pubkey,
+    pub tier: u8,
+    pub volume_30d: u64,
+    pub fees_paid: u64,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl OnChainUserFeeTier {
+    pub const DISCRIMINATOR: [u8; 8] = [
+        0x0a, 0x41, 0x94, 0x48, 0x7f, 0x1b, 0x8e, 0xb3,
+    ];
+}
+
+/// On-chain Market structure
+#[derive(AnchorDeserialize, AnchorSerialize, Debug)]
+pub struct OnChainMarket {
+    pub symbol: String,
+    pub maintenance_margin_bps: u16,
+    pub min_maintenance_margin_bps: u16,
+    pub max_maintenance_margin_bps: u16,
+    pub updated_at: i64,
+    pub bump: u8,
+    pub open_cooldown_slots: u64,
+    pub max_opens_per_slot: u16,
+    pub open_counter_slot: u64,
+    pub opens_in_slot: u16,
+    pub rate_window_start: i64,
+    pub rate_window_start_bps: u16,
+}
+
+impl OnChainMarket {
+    pub const DISCRIMINATOR: [u8; 8] = [
+        0xdb, 0xbe, 0xd5, 0x37, 0x00, 0xe3, 0xc6, 0x9a,
+    ];
+}
+
+/// On-chain PositionOpened event
+#[derive(AnchorDeserialize, AnchorSerialize, Debug, Clone)]
+pub struct OnChainPositionOpened {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub symbol: String,
+    pub side: OnChainSide,
+    pub size: u64,
+    pub entry_price: u64,
+    pub leverage: u16,
+    pub margin: u64,
+    pub timestamp: i64,
+}
+
+/// On-chain PositionModified event
+#[derive(AnchorDeserialize, AnchorSerialize, Debug, Clone)]
+pub struct OnChainPositionModified {
+    pub position: Pubkey,
+    pub old_size: u64,
+    pub new_size: u64,
+    pub old_margin: u64,
+    pub new_margin: u64,
+    pub timestamp: i64,
+}
+
+/// On-chain PositionClosed event
+#[derive(AnchorDeserialize, AnchorSerialize, Debug, Clone)]
+pub struct OnChainPositionClosed {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub realized_pnl: i64,
+    pub timestamp: i64,
+}
+
+/// On-chain PositionLiquidated event
+#[derive(AnchorDeserialize, AnchorSerialize, Debug, Clone)]
+pub struct OnChainPositionLiquidated {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub liquidator: Pubkey,
+    pub mark_price: u64,
+    pub realized_pnl: i64,
+    pub closed_size: u64,
+    pub remaining_size: u64,
+    pub timestamp: i64,
+}
+
+/// On-chain MarginCallIssued event
+#[derive(AnchorDeserialize, AnchorSerialize, Debug, Clone)]
+pub struct OnChainMarginCallIssued {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub margin_ratio_bps: u64,
+    pub deadline: i64,
+    pub timestamp: i64,
+}
+
+/// On-chain MarginCallCleared event
+#[derive(AnchorDeserialize, AnchorSerialize, Debug, Clone)]
+pub struct OnChainMarginCallCleared {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub margin_ratio_bps: u64,
+    pub timestamp: i64,
+}
+
+/// Program events the backend reads back from transaction logs
+#[derive(Debug, Clone)]
+pub enum OnChainEvent {
+    PositionOpened(OnChainPositionOpened),
+    PositionModified(OnChainPositionModified),
+    PositionClosed(OnChainPositionClosed),
+    PositionLiquidated(OnChainPositionLiquidated),
+    MarginCallIssued(OnChainMarginCallIssued),
+    MarginCallCleared(OnChainMarginCallCleared),
+}
+
+impl OnChainEvent {
+    const POSITION_OPENED: [u8; 8] = [0xed, 0xaf, 0xf3, 0xe6, 0x93, 0x75, 0x65, 0x79];
+    const POSITION_MODIFIED: [u8; 8] = [0x02, 0xfb, 0x8c, 0x41, 0xb0, 0x4e, 0xfa, 0x7e];
+    const POSITION_CLOSED: [u8; 8] = [0x9d, 0xa3, 0xe3, 0xe4, 0x0d, 0x61, 0x8a, 0x79];
+    const POSITION_LIQUIDATED: [u8; 8] = [0x28, 0x6b, 0x5a, 0xd6, 0x60, 0x1e, 0x3d, 0x80];
+    const MARGIN_CALL_ISSUED: [u8; 8] = [0xfb, 0x74, 0x84, 0x2a, 0xe3, 0x7d, 0x20, 0x83];
+    const MARGIN_CALL_CLEARED: [u8; 8] = [0x80, 0x8f, 0xd2, 0x4e, 0xc5, 0xfb, 0x3e, 0xb2];
+
+    /// Decode one `emit!` payload, none for events the backend doesn't read
+    pub fn decode(data: &[u8]) -> Option<Result<Self>> {
+        let discriminator: [u8; 8] = data.get(..8)?.try_into().ok()?;
+        let body = &mut &data[8..];
+
+        let event = match discriminator {
+            Self::POSITION_OPENED => OnChainPositionOpened::deserialize(body).map(Self::PositionOpened),
+            Self::POSITION_MODIFIED => OnChainPositionModified::deserialize(body).map(Self::PositionModified),
+            Self::POSITION_CLOSED => OnChainPositionClosed::deserialize(body).map(Self::PositionClosed),
+            Self::POSITION_LIQUIDATED => {
+                OnChainPositionLiquidated::deserialize(body).map(Self::PositionLiquidated)
+            }
+            Self::MARGIN_CALL_ISSUED => OnChainMarginCallIssued::deserialize(body).map(Self::MarginCallIssued),
+            Self::MARGIN_CALL_CLEARED => {
+                OnChainMarginCallCleared::deserialize(body).map(Self::MarginCallCleared)
+            }
+            _ => return None,
+        };
+
+        Some(event.context("Failed to deserialize program event"))
+    }
+
+    /// Position account the event is about
+    pub fn position(&self) -> Pubkey {
+        match self {
+            Self::PositionOpened(e) => e.position,
+            Self::PositionModified(e) => e.position,
+            Self::PositionClosed(e) => e.position,
+            Self::PositionLiquidated(e) => e.position,
+            Self::MarginCallIssued(e) => e.position,
+            Self::MarginCallCleared(e) => e.position,
+        }
+    }
+}
+
+/// Deserialize an Anchor account after checking its discriminator
+pub fn deserialize_anchor_account<T: AnchorDeserialize>(
+    data: &[u8],
+    discriminator: &[u8; 8],
+) -> Result<T> {
+    if data.len() < 8 {
+        return Err(anyhow!("Account data too small"));
+    }
+
+    if &data[..8] != discriminator {
+        return Err(anyhow!("Account discriminator mismatch"));
+    }
+
+    T::deserialize(&mut &data[8..]).context("Failed to deserialize account")
+}
+
 /// Deserialize Position account from Solana account data
 pub fn deserialize_position_account(
-    pubkey: Pubkey,
+    _pubkey: Pubkey,
     account: &Account,
 ) -> Result<(u32, OnChainPosition)> {
-    let data = &account.data;
+    let position = deserialize_position_data(&account.data)?;
     
+    Ok((0, position))
+}
+
+/// Deserialize a Position from account data, discriminator included
+pub fn deserialize_position_data(data: &[u8]) -> Result<OnChainPosition> {
     if data.len() < 8 {
         return Err(anyhow!("Account data too small"));
     }
-    
+
+    // Accounts opened before the history was added end at bump, read the
+    // missing bytes as an empty history
+    let padded;
+    let data = if data.len() < OnChainPosition::MAX_SIZE {
+        padded = [data, &vec![0; OnChainPosition::MAX_SIZE - data.len()]].concat();
+        padded.as_slice()
+    } else {
+        data
+    };
+
     // Skip 8-byte discriminator and deserialize
-    let position = OnChainPosition::deserialize(&mut &data[8..])
-        .context("Failed to deserialize Position")?;
-    
-    Ok((0, position))
+    OnChainPosition::deserialize(&mut &data[8..]).context("Failed to deserialize Position")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(history_count: u32) -> OnChainPosition {
+        let mut history = [OnChainPositionChange::default(); POSITION_HISTORY_LEN];
+        for i in 0..history_count {
+            history[i as usize % POSITION_HISTORY_LEN].slot = i as u64 + 1;
+        }
+
+        OnChainPosition {
+            owner: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            side: OnChainSide::Long,
+            size: 100_000_000,
+            entry_price: 100_000_000,
+            margin: 10_000_000,
+            leverage: 10,
+            unrealized_pnl: 0,
+            realized_pnl: 0,
+            funding_accrued: 0,
+            liquidation_price: 90_000_000,
+            last_update: 0,
+            status: OnChainPositionStatus::Open,
+            bump: 255,
+            history,
+            history_count,
+            funding_settled: 0,
+        }
+    }
+
+    fn account(data: Vec<u8>) -> Account {
+        Account { lamports: 1, data, owner: Pubkey::new_unique(), executable: false, rent_epoch: 0 }
+    }
+
+    #[test]
+    fn test_position_history_oldest_first() {
+        let slots = |position: &OnChainPosition| -> Vec<u64> {
+            position.changes().iter().map(|change| change.slot).collect()
+        };
+        assert_eq!(slots(&position(3)), vec![1, 2, 3]);
+
+        // Wrapped: the first two were overwritten
+        let wrapped = position(POSITION_HISTORY_LEN as u32 + 2);
+        assert_eq!(slots(&wrapped), (3..=POSITION_HISTORY_LEN as u64 + 2).collect::<Vec<_>>());
+
+        let mut data = OnChainPosition::DISCRIMINATOR.to_vec();
+        wrapped.serialize(&mut data).unwrap();
+        let (_, decoded) = deserialize_position_account(Pubkey::new_unique(), &account(data)).unwrap();
+        assert_eq!(decoded.changes(), wrapped.changes());
+    }
+
+    #[test]
+    fn test_legacy_position_reads_empty_history() {
+        let mut data = OnChainPosition::DISCRIMINATOR.to_vec();
+        position(2).serialize(&mut data).unwrap();
+        // Cut at bump, the layout before the history was appended
+        data.truncate(data.len() - POSITION_HISTORY_LEN * OnChainPositionChange::LEN - 4 - 8);
+
+        let (_, decoded) = deserialize_position_account(Pubkey::new_unique(), &account(data)).unwrap();
+        assert_eq!(decoded.bump, 255);
+        assert_eq!(decoded.history_count, 0);
+        assert!(decoded.changes().is_empty());
+    }
 }
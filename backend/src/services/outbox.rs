@@ -0,0 +1,535 @@
+/// Outbox
+/// Domain events appended to a Redis Stream, so consumers that were down
+/// when an event happened still get it. Broadcast channels only reach
+/// whoever is listening at the time; the stream keeps every event, up to a
+/// capped length, and each consumer group tracks what it has acknowledged.
+/// The webhook dispatcher consumes the `webhooks` group in process. Other
+/// consumers, such as an indexer, list their groups in OUTBOX_CONSUMER_GROUPS
+/// and read them with XREADGROUP and XACK. Only entries every group has
+/// acknowledged are trimmed.
+/// Delivery is at least once: entries left unacknowledged by a consumer that
+/// died are claimed by another after a timeout, so consumers dedupe on `id`
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use redis::streams::{
+    StreamClaimReply, StreamId, StreamInfoGroupsReply, StreamMaxlen, StreamPendingCountReply,
+    StreamPendingReply, StreamReadOptions, StreamReadReply,
+};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::domain::{Position, Side};
+use crate::infrastructure::{event_kind_to_str, PositionEventRecord};
+use crate::services::{TradeReport, WebhookDispatcher, WebhookEventType};
+
+pub const WEBHOOK_GROUP: &str = "webhooks";
+
+// Wait after a failed stream read before trying again
+const READ_RETRY_DELAY: Duration = Duration::from_secs(1);
+// How often the stream length is checked against `max_len`
+const TRIM_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct OutboxConfig {
+    pub stream: String,
+    /// Length above which entries every group has acknowledged are trimmed.
+    /// Unacknowledged entries are kept however long the stream grows
+    pub max_len: usize,
+    /// Groups created on startup, reading from the start of the stream. Each
+    /// needs a consumer, an unread group holds back trimming
+    pub groups: Vec<String>,
+    /// Entries read per XREADGROUP
+    pub batch_size: usize,
+    /// How long a read blocks for new entries
+    pub block: Duration,
+    /// Entries pending this long on any consumer are claimed and retried
+    pub claim_idle: Duration,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            stream: "perps:outbox".to_string(),
+            max_len: 100_000,
+            groups: Vec::new(),
+            batch_size: 100,
+            block: Duration::from_secs(5),
+            claim_idle: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A position opened by this service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionOpenedEvent {
+    pub position_account: String,
+    pub owner: String,
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub entry_price: Decimal,
+    pub leverage: u16,
+    pub margin: Decimal,
+    pub signature: String,
+    pub opened_at: DateTime<Utc>,
+}
+
+impl PositionOpenedEvent {
+    pub fn new(position: &Position, signature: &str) -> Self {
+        Self {
+            position_account: position.position_account.to_string(),
+            owner: position.owner.to_string(),
+            symbol: position.symbol.clone(),
+            side: position.side,
+            size: position.size,
+            entry_price: position.entry_price,
+            leverage: position.leverage,
+            margin: position.margin,
+            signature: signature.to_string(),
+            opened_at: position.opened_at,
+        }
+    }
+}
+
+/// A line of a position's timeline: a modify or a margin call set or cleared
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionChangedEvent {
+    pub position_account: String,
+    /// `modified`, `margin_call`, `margin_call_cleared` or `alert`
+    pub kind: String,
+    pub size: Option<Decimal>,
+    pub margin: Option<Decimal>,
+    pub mark_price: Option<Decimal>,
+    pub detail: Option<String>,
+    pub signature: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl From<&PositionEventRecord> for PositionChangedEvent {
+    fn from(event: &PositionEventRecord) -> Self {
+        Self {
+            position_account: event.position_account.to_string(),
+            kind: event_kind_to_str(event.kind).to_string(),
+            size: event.size,
+            margin: event.margin,
+            mark_price: event.mark_price,
+            detail: event.detail.clone(),
+            signature: event.signature.clone(),
+            recorded_at: event.recorded_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum OutboxEvent {
+    PositionOpened(PositionOpenedEvent),
+    PositionChanged(PositionChangedEvent),
+    /// A close or liquidation
+    TradeReport(TradeReport),
+}
+
+/// One stream entry, fields `id`, `type`, `created_at` and `data` (JSON)
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    /// Stream entry id, what XACK takes
+    pub stream_id: String,
+    /// Event id, the same on every redelivery
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub event: OutboxEvent,
+}
+
+impl OutboxEvent {
+    /// Entry fields of a new event
+    fn to_fields(&self, id: Uuid, created_at: DateTime<Utc>) -> Result<Vec<(&'static str, String)>> {
+        let mut tagged = serde_json::to_value(self)?;
+        let event_type = tagged["type"].as_str().unwrap_or_default().to_string();
+        let data = tagged["data"].take().to_string();
+
+        Ok(vec![
+            ("id", id.to_string()),
+            ("type", event_type),
+            ("created_at", created_at.to_rfc3339()),
+            ("data", data),
+        ])
+    }
+}
+
+impl OutboxEntry {
+    fn from_stream_id(entry: &StreamId) -> Result<Self> {
+        let field = |name: &str| {
+            entry
+                .get::<String>(name)
+                .ok_or_else(|| anyhow!("Outbox entry {} has no {}", entry.id, name))
+        };
+        let data: serde_json::Value = serde_json::from_str(&field("data")?)?;
+        let event = serde_json::from_value(serde_json::json!({ "type": field("type")?, "data": data }))?;
+
+        Ok(Self {
+            stream_id: entry.id.clone(),
+            id: field("id")?.parse()?,
+            created_at: DateTime::parse_from_rfc3339(&field("created_at")?)?.with_timezone(&Utc),
+            event,
+        })
+    }
+}
+
+/// A consumer group's progress, from XINFO GROUPS
+#[derive(Debug, Clone)]
+pub struct OutboxGroupStats {
+    pub name: String,
+    pub consumers: usize,
+    /// Delivered and not yet acknowledged
+    pub pending: usize,
+    pub last_delivered_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutboxStats {
+    pub stream: String,
+    pub length: usize,
+    pub groups: Vec<OutboxGroupStats>,
+}
+
+pub struct Outbox {
+    redis_client: redis::Client,
+    config: OutboxConfig,
+    /// Consumer name of this node within each group
+    consumer: String,
+}
+
+impl Outbox {
+    pub fn new(redis_client: redis::Client, config: OutboxConfig) -> Self {
+        Self {
+            redis_client,
+            config,
+            consumer: Uuid::new_v4().to_string(),
+        }
+    }
+
+    async fn connection(&self) -> Result<redis::aio::MultiplexedConnection> {
+        self.redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")
+    }
+
+    /// Create the configured consumer groups and the stream, groups that
+    /// already exist keep their position
+    pub async fn ensure_groups(&self) -> Result<()> {
+        let mut conn = self.connection().await?;
+        for group in &self.config.groups {
+            let created: redis::RedisResult<()> = conn
+                .xgroup_create_mkstream(&self.config.stream, group, "0")
+                .await;
+            match created {
+                Ok(()) => info!("Created outbox consumer group {}", group),
+                Err(e) if e.code() == Some("BUSYGROUP") => {}
+                Err(e) => return Err(e).context(format!("Failed to create outbox group {}", group)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Append an event, returning its stream entry id
+    pub async fn append(&self, event: &OutboxEvent) -> Result<String> {
+        let fields = event.to_fields(Uuid::new_v4(), Utc::now())?;
+        let mut conn = self.connection().await?;
+
+        let stream_id: String = conn
+            .xadd(&self.config.stream, "*", &fields)
+            .await
+            .context("Failed to append to outbox")?;
+        Ok(stream_id)
+    }
+
+    /// Trim the stream every TRIM_INTERVAL while it is longer than `max_len`
+    pub fn spawn_trimmer(self: &Arc<Self>) {
+        let outbox = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TRIM_INTERVAL);
+            loop {
+                interval.tick().await;
+                match outbox.trim().await {
+                    Ok(0) => {}
+                    Ok(trimmed) => info!("Trimmed {} acknowledged outbox entries", trimmed),
+                    Err(e) => warn!("Outbox trim failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Drop the entries older than the oldest one some group has not yet
+    /// acknowledged, returning how many were removed. Without groups the
+    /// stream is capped at `max_len`
+    async fn trim(&self) -> Result<usize> {
+        let mut conn = self.connection().await?;
+        let length: usize = conn.xlen(&self.config.stream).await?;
+        if length <= self.config.max_len {
+            return Ok(0);
+        }
+
+        if self.config.groups.is_empty() {
+            let trimmed: usize = conn
+                .xtrim(&self.config.stream, StreamMaxlen::Approx(self.config.max_len))
+                .await?;
+            return Ok(trimmed);
+        }
+
+        let groups: StreamInfoGroupsReply = conn.xinfo_groups(&self.config.stream).await?;
+        let mut cutoffs = Vec::with_capacity(groups.groups.len());
+        for group in groups.groups {
+            let pending: StreamPendingReply = conn.xpending(&self.config.stream, &group.name).await?;
+            cutoffs.push(match pending {
+                StreamPendingReply::Data(pending) => pending.start_id,
+                StreamPendingReply::Empty => group.last_delivered_id,
+            });
+        }
+
+        let Some(cutoff) = trim_cutoff(&cutoffs)? else {
+            return Ok(0);
+        };
+        let trimmed: usize = redis::cmd("XTRIM")
+            .arg(&self.config.stream)
+            .arg("MINID")
+            .arg(cutoff)
+            .query_async(&mut conn)
+            .await?;
+        Ok(trimmed)
+    }
+
+    pub async fn stats(&self) -> Result<OutboxStats> {
+        let mut conn = self.connection().await?;
+        let length: usize = conn.xlen(&self.config.stream).await?;
+        let groups: StreamInfoGroupsReply = match conn.xinfo_groups(&self.config.stream).await {
+            Ok(groups) => groups,
+            // No stream yet
+            Err(e) if e.kind() == redis::ErrorKind::ResponseError => StreamInfoGroupsReply::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(OutboxStats {
+            stream: self.config.stream.clone(),
+            length,
+            groups: groups
+                .groups
+                .into_iter()
+                .map(|group| OutboxGroupStats {
+                    name: group.name,
+                    consumers: group.consumers,
+                    pending: group.pending,
+                    last_delivered_id: group.last_delivered_id,
+                })
+                .collect(),
+        })
+    }
+
+    /// Consume `group` until the process exits. `handle` returning an error
+    /// leaves the entry pending, to be claimed again after `claim_idle`.
+    /// Entries that don't decode are acknowledged and dropped
+    pub fn spawn_consumer<F, Fut>(self: &Arc<Self>, group: &str, handle: F)
+    where
+        F: Fn(OutboxEntry) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send,
+    {
+        let outbox = Arc::clone(self);
+        let group = group.to_string();
+
+        tokio::spawn(async move {
+            info!("Outbox consumer {} started in group {}", outbox.consumer, group);
+            let mut last_claim = tokio::time::Instant::now();
+            loop {
+                let entries = if last_claim.elapsed() >= outbox.config.claim_idle / 2 {
+                    last_claim = tokio::time::Instant::now();
+                    outbox.claim_stale(&group).await
+                } else {
+                    outbox.read_new(&group).await
+                };
+
+                let entries = match entries {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        warn!("Outbox read for group {} failed: {}", group, e);
+                        tokio::time::sleep(READ_RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+
+                for entry in entries {
+                    let handled = match OutboxEntry::from_stream_id(&entry) {
+                        Ok(entry) => handle(entry).await,
+                        Err(e) => {
+                            warn!("Dropping undecodable outbox entry {}: {}", entry.id, e);
+                            Ok(())
+                        }
+                    };
+
+                    match handled {
+                        Ok(()) => {
+                            if let Err(e) = outbox.ack(&group, &entry.id).await {
+                                warn!("Failed to ack outbox entry {} in {}: {}", entry.id, group, e);
+                            }
+                        }
+                        Err(e) => warn!(
+                            "Outbox entry {} failed in group {}, retrying after {:?}: {}",
+                            entry.id, group, outbox.config.claim_idle, e
+                        ),
+                    }
+                }
+            }
+        });
+    }
+
+    async fn read_new(&self, group: &str) -> Result<Vec<StreamId>> {
+        let mut conn = self.connection().await?;
+        let options = StreamReadOptions::default()
+            .group(group, &self.consumer)
+            .count(self.config.batch_size)
+            .block(self.config.block.as_millis() as usize);
+
+        let reply: Option<StreamReadReply> = conn
+            .xread_options(&[&self.config.stream], &[">"], &options)
+            .await?;
+        Ok(reply
+            .map(|reply| reply.keys.into_iter().flat_map(|key| key.ids).collect())
+            .unwrap_or_default())
+    }
+
+    /// Take over entries pending past `claim_idle`, ours from before a
+    /// failed handle and those of consumers that went away
+    async fn claim_stale(&self, group: &str) -> Result<Vec<StreamId>> {
+        let mut conn = self.connection().await?;
+        let min_idle = self.config.claim_idle.as_millis() as usize;
+
+        let pending: StreamPendingCountReply = conn
+            .xpending_count(&self.config.stream, group, "-", "+", self.config.batch_size)
+            .await?;
+        let stale: Vec<String> = pending
+            .ids
+            .into_iter()
+            .filter(|pending| pending.last_delivered_ms >= min_idle)
+            .map(|pending| pending.id)
+            .collect();
+        if stale.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let claimed: StreamClaimReply = conn
+            .xclaim(&self.config.stream, group, &self.consumer, min_idle, &stale)
+            .await?;
+        if !claimed.ids.is_empty() {
+            info!("Claimed {} stale outbox entries in group {}", claimed.ids.len(), group);
+        }
+        Ok(claimed.ids)
+    }
+
+    async fn ack(&self, group: &str, stream_id: &str) -> Result<()> {
+        let mut conn = self.connection().await?;
+        conn.xack::<_, _, _, ()>(&self.config.stream, group, &[stream_id])
+            .await?;
+        Ok(())
+    }
+}
+
+/// The lowest of the groups' trim cutoffs: each group's oldest pending entry,
+/// or its last delivered entry when nothing is pending. Everything below it
+/// has been acknowledged by every group
+fn trim_cutoff(cutoffs: &[String]) -> Result<Option<&str>> {
+    let mut lowest: Option<((u64, u64), &str)> = None;
+    for cutoff in cutoffs {
+        let (ms, seq) = cutoff
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Invalid stream id {}", cutoff))?;
+        let id = (ms.parse()?, seq.parse()?);
+        if lowest.is_none_or(|(lowest, _)| id < lowest) {
+            lowest = Some((id, cutoff));
+        }
+    }
+    Ok(lowest.map(|(_, cutoff)| cutoff))
+}
+
+/// Handler of the `webhooks` group: deliver trade reports to subscribed
+/// endpoints under the event's id, done once every delivery has finished
+pub async fn deliver_webhooks(webhooks: Arc<WebhookDispatcher>, entry: OutboxEntry) -> Result<()> {
+    if let OutboxEvent::TradeReport(report) = &entry.event {
+        let owner = Pubkey::from_str(&report.account).ok();
+        webhooks
+            .deliver(owner.as_ref(), WebhookEventType::TradeReport, entry.id, entry.created_at, report)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redis::Value;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_entry_round_trips() {
+        let event = OutboxEvent::PositionChanged(PositionChangedEvent {
+            position_account: "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU".to_string(),
+            kind: "margin_call".to_string(),
+            size: Some(Decimal::new(15, 1)),
+            margin: None,
+            mark_price: None,
+            detail: None,
+            signature: Some("5wHu1qwD".to_string()),
+            recorded_at: Utc::now(),
+        });
+        let id = Uuid::new_v4();
+        let fields = event.to_fields(id, Utc::now()).unwrap();
+        assert_eq!(fields[1], ("type", "position_changed".to_string()));
+        assert!(fields[3].1.starts_with('{'));
+
+        let entry = StreamId {
+            id: "1700000000000-0".to_string(),
+            map: fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), Value::Data(value.into_bytes())))
+                .collect::<HashMap<_, _>>(),
+        };
+        let decoded = OutboxEntry::from_stream_id(&entry).unwrap();
+        assert_eq!(decoded.stream_id, "1700000000000-0");
+        assert_eq!(decoded.id, id);
+        match decoded.event {
+            OutboxEvent::PositionChanged(changed) => {
+                assert_eq!(changed.kind, "margin_call");
+                assert_eq!(changed.size, Some(Decimal::new(15, 1)));
+            }
+            other => panic!("Decoded {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_trim_cutoff_is_lowest_id() {
+        let cutoffs = vec![
+            "1700000000123-4".to_string(),
+            "1700000000123-10".to_string(),
+            "999999999999-0".to_string(),
+        ];
+        assert_eq!(trim_cutoff(&cutoffs).unwrap(), Some("999999999999-0"));
+        assert_eq!(trim_cutoff(&cutoffs[..2]).unwrap(), Some("1700000000123-4"));
+        assert_eq!(trim_cutoff(&[]).unwrap(), None);
+        assert!(trim_cutoff(&["bogus".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_entry_without_data_is_rejected() {
+        let entry = StreamId {
+            id: "1-0".to_string(),
+            map: HashMap::from([("type".to_string(), Value::Data(b"trade_report".to_vec()))]),
+        };
+        assert!(OutboxEntry::from_stream_id(&entry).is_err());
+    }
+}
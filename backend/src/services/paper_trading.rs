@@ -0,0 +1,497 @@
+/// Paper Trading
+/// Sandbox sessions where open/modify/close run against an in-memory ledger
+/// instead of the program. Fills use the request prices exactly like the live
+/// path, PnL and liquidations follow live oracle prices with the monitor's
+/// MarginCalculator math, and updates stream to the session's WebSockets.
+/// Nothing here touches Solana, Redis or the real monitor state
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use tokio::time::{interval, Duration};
+use tracing::{debug, info};
+
+use crate::domain::{symbols, Position, PositionStatus, Side};
+use crate::error::Error;
+use crate::infrastructure::{PriceSource, ProgramError};
+use crate::services::{MarginCalculator, PositionUpdate};
+
+#[derive(Debug, Clone)]
+pub struct PaperTradingConfig {
+    pub pnl_update_interval_ms: u64,
+    /// Sessions idle this long are dropped with their positions
+    pub session_ttl: chrono::Duration,
+    pub max_sessions: usize,
+    pub max_positions_per_session: usize,
+}
+
+impl Default for PaperTradingConfig {
+    fn default() -> Self {
+        Self {
+            pnl_update_interval_ms: 2000,
+            session_ttl: chrono::Duration::hours(24),
+            max_sessions: 10_000,
+            max_positions_per_session: 100,
+        }
+    }
+}
+
+/// A simulated position's update, tagged with its session
+#[derive(Debug, Clone)]
+pub struct PaperPositionUpdate {
+    pub session: String,
+    pub update: PositionUpdate,
+}
+
+#[derive(Debug, Clone)]
+struct PaperPosition {
+    position: Position,
+    maintenance_margin_ratio: Decimal,
+}
+
+#[derive(Debug, Default)]
+struct PaperSession {
+    positions: HashMap<Pubkey, PaperPosition>,
+    next_index: u32,
+    last_active: Option<DateTime<Utc>>,
+}
+
+fn program_error(code: &str) -> anyhow::Error {
+    match ProgramError::from_code(code) {
+        Some(error) => error.into(),
+        None => anyhow!("{}", code),
+    }
+}
+
+/// Apply a modify the way the program does: a new size re-derives margin at
+/// the entry price, added margin lowers leverage, removed margin must leave
+/// at least the initial margin
+fn apply_modify(
+    position: &mut Position,
+    maintenance_margin_ratio: Decimal,
+    new_size: Option<Decimal>,
+    margin_delta: Option<i64>,
+) -> Result<()> {
+    if !position.is_open() {
+        return Err(program_error("PositionNotOpen"));
+    }
+
+    if let Some(size) = new_size {
+        position.margin =
+            MarginCalculator::calculate_initial_margin(size, position.entry_price, position.leverage)?;
+        position.size = size;
+    }
+
+    if let Some(delta) = margin_delta {
+        // Margin is in the program's 6-decimal base units
+        let delta = Decimal::new(delta, 6);
+
+        if delta > Decimal::ZERO {
+            position.margin += delta;
+            let leverage = (position.size * position.entry_price / position.margin)
+                .floor()
+                .min(Decimal::from(1000));
+            position.leverage = leverage.to_u16().unwrap_or(1).max(1);
+        } else {
+            let new_margin = position.margin + delta;
+            let min_margin = MarginCalculator::calculate_initial_margin(
+                position.size,
+                position.entry_price,
+                position.leverage,
+            )?;
+
+            if new_margin <= Decimal::ZERO || new_margin < min_margin {
+                return Err(program_error("CannotRemoveMargin"));
+            }
+            position.margin = new_margin;
+        }
+    }
+
+    position.liquidation_price = MarginCalculator::calculate_liquidation_price(
+        position.side,
+        position.entry_price,
+        position.leverage,
+        maintenance_margin_ratio,
+    )?;
+    position.last_update = Utc::now();
+
+    Ok(())
+}
+
+/// Mark a position to `mark_price`, liquidating it below maintenance
+/// Returns the update to stream, none when the math fails
+fn mark_position(
+    paper: &mut PaperPosition,
+    mark_price: Decimal,
+    now: DateTime<Utc>,
+) -> Option<PositionUpdate> {
+    let position = &mut paper.position;
+
+    let pnl = MarginCalculator::calculate_unrealized_pnl(
+        position.side,
+        position.size,
+        mark_price,
+        position.entry_price,
+    )
+    .ok()?;
+
+    position.mark_price = mark_price;
+    position.unrealized_pnl = pnl;
+    position.last_update = now;
+    // Paper positions pay no funding or fees
+    position.effective_liquidation_price = MarginCalculator::calculate_effective_liquidation_price(
+        position.side,
+        position.size,
+        position.entry_price,
+        position.margin,
+        position.funding_accrued,
+        paper.maintenance_margin_ratio,
+        Decimal::ZERO,
+    )
+    .ok();
+
+    let margin_ratio =
+        MarginCalculator::calculate_margin_ratio(position.margin, pnl, position.size, mark_price)
+            .unwrap_or(Decimal::ZERO);
+
+    if margin_ratio < paper.maintenance_margin_ratio {
+        // The whole margin is lost, as in a full on-chain liquidation
+        position.status = PositionStatus::Liquidated;
+        position.realized_pnl = -position.margin;
+        position.unrealized_pnl = Decimal::ZERO;
+        position.closed_at = Some(now);
+    }
+
+    Some(PositionUpdate {
+        position_account: position.position_account,
+        symbol: position.symbol.clone(),
+        side: position.side,
+        size: position.size,
+        entry_price: position.entry_price,
+        mark_price,
+        unrealized_pnl: position.unrealized_pnl,
+        margin: position.margin,
+        margin_ratio,
+        liquidation_price: position.liquidation_price,
+        effective_liquidation_price: position.effective_liquidation_price,
+        timestamp: now,
+    })
+}
+
+pub struct PaperLedger {
+    price_source: Arc<dyn PriceSource>,
+    config: PaperTradingConfig,
+    sessions: Arc<RwLock<HashMap<String, PaperSession>>>,
+    update_tx: broadcast::Sender<PaperPositionUpdate>,
+}
+
+impl PaperLedger {
+    pub fn new(price_source: Arc<dyn PriceSource>, config: PaperTradingConfig) -> Self {
+        let (update_tx, _) = broadcast::channel(1000);
+
+        Self {
+            price_source,
+            config,
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            update_tx,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PaperPositionUpdate> {
+        self.update_tx.subscribe()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn open_position(
+        &self,
+        session: &str,
+        owner: Pubkey,
+        symbol: String,
+        side: Side,
+        size: Decimal,
+        leverage: u16,
+        entry_price: Decimal,
+        maintenance_margin_ratio: Decimal,
+    ) -> Result<Position> {
+        let symbol = symbols::canonicalize(&symbol)?;
+        let margin = MarginCalculator::calculate_initial_margin(size, entry_price, leverage)?;
+        let liquidation_price = MarginCalculator::calculate_liquidation_price(
+            side,
+            entry_price,
+            leverage,
+            maintenance_margin_ratio,
+        )?;
+
+        let mut sessions = self.sessions.write().await;
+        if !sessions.contains_key(session) && sessions.len() >= self.config.max_sessions {
+            return Err(anyhow!("Too many paper trading sessions, try again later"));
+        }
+
+        let paper_session = sessions.entry(session.to_string()).or_default();
+        let open = paper_session.positions.values().filter(|p| p.position.is_open()).count();
+        if open >= self.config.max_positions_per_session {
+            return Err(anyhow!(
+                "Paper session already has {} open positions",
+                self.config.max_positions_per_session
+            ));
+        }
+
+        let now = Utc::now();
+        let position = Position {
+            position_index: paper_session.next_index,
+            owner,
+            // Random, so it can never collide with a real position PDA
+            position_account: Pubkey::new_unique(),
+            symbol,
+            side,
+            size,
+            entry_price,
+            mark_price: entry_price,
+            margin,
+            leverage,
+            unrealized_pnl: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
+            funding_accrued: Decimal::ZERO,
+            liquidation_price,
+            effective_liquidation_price: None,
+            status: PositionStatus::Open,
+            opened_at: now,
+            last_update: now,
+            closed_at: None,
+            margin_call_at: None,
+        };
+
+        paper_session.next_index += 1;
+        paper_session.last_active = Some(now);
+        paper_session.positions.insert(
+            position.position_account,
+            PaperPosition {
+                position: position.clone(),
+                maintenance_margin_ratio,
+            },
+        );
+
+        debug!("Paper session {} opened {}", session, position.position_account);
+
+        Ok(position)
+    }
+
+    pub async fn modify_position(
+        &self,
+        session: &str,
+        position_account: Pubkey,
+        new_size: Option<Decimal>,
+        margin_delta: Option<i64>,
+    ) -> Result<Position> {
+        let mut sessions = self.sessions.write().await;
+        let paper = Self::find(&mut sessions, session, position_account)?;
+
+        // Apply to a copy so a rejected modify leaves the position untouched
+        let mut position = paper.position.clone();
+        apply_modify(&mut position, paper.maintenance_margin_ratio, new_size, margin_delta)?;
+        paper.position = position.clone();
+
+        Ok(position)
+    }
+
+    /// Close at `final_price`, returns the realized PnL
+    pub async fn close_position(
+        &self,
+        session: &str,
+        position_account: Pubkey,
+        final_price: Decimal,
+    ) -> Result<Decimal> {
+        let mut sessions = self.sessions.write().await;
+        let paper = Self::find(&mut sessions, session, position_account)?;
+        let position = &mut paper.position;
+
+        if !position.is_open() {
+            return Err(program_error("PositionNotOpen"));
+        }
+
+        let pnl = MarginCalculator::calculate_unrealized_pnl(
+            position.side,
+            position.size,
+            final_price,
+            position.entry_price,
+        )?
+        .checked_add(position.funding_accrued)
+        .ok_or_else(|| anyhow!("PnL overflow"))?;
+
+        let now = Utc::now();
+        position.mark_price = final_price;
+        position.realized_pnl = pnl;
+        position.unrealized_pnl = Decimal::ZERO;
+        position.status = PositionStatus::Closed;
+        position.closed_at = Some(now);
+        position.last_update = now;
+
+        Ok(pnl)
+    }
+
+    pub async fn get_position(&self, session: &str, position_account: Pubkey) -> Option<Position> {
+        self.sessions
+            .read()
+            .await
+            .get(session)?
+            .positions
+            .get(&position_account)
+            .map(|paper| paper.position.clone())
+    }
+
+    /// Every position of the session, open and closed
+    pub async fn get_positions(&self, session: &str) -> Vec<Position> {
+        self.sessions
+            .read()
+            .await
+            .get(session)
+            .map(|paper_session| {
+                paper_session
+                    .positions
+                    .values()
+                    .map(|paper| paper.position.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Mark every open paper position to the cached oracle price and stream it
+    pub async fn update_all_pnl(&self) -> usize {
+        let mut prices: HashMap<String, Option<Decimal>> = HashMap::new();
+        let mut sessions = self.sessions.write().await;
+        let now = Utc::now();
+        let mut updated = 0usize;
+
+        for (session, paper_session) in sessions.iter_mut() {
+            for paper in paper_session.positions.values_mut() {
+                if !paper.position.is_open() {
+                    continue;
+                }
+
+                let symbol = &paper.position.symbol;
+                let mark_price = match prices.get(symbol) {
+                    Some(price) => *price,
+                    None => {
+                        let price = self.price_source.get_cached_price(symbol).await;
+                        prices.insert(symbol.clone(), price);
+                        price
+                    }
+                };
+
+                let Some(update) = mark_price.and_then(|price| mark_position(paper, price, now)) else {
+                    continue;
+                };
+
+                let _ = self.update_tx.send(PaperPositionUpdate {
+                    session: session.clone(),
+                    update,
+                });
+                updated += 1;
+            }
+        }
+
+        updated
+    }
+
+    /// Drop sessions idle past the TTL, returns how many
+    pub async fn evict_idle_sessions(&self) -> usize {
+        let cutoff = Utc::now() - self.config.session_ttl;
+        let mut sessions = self.sessions.write().await;
+        let before = sessions.len();
+
+        sessions.retain(|_, session| session.last_active.is_some_and(|at| at > cutoff));
+
+        before - sessions.len()
+    }
+
+    /// PnL updates and session expiry, runs on every node since sessions are node-local
+    pub fn spawn(self: &Arc<Self>) {
+        let ledger = Arc::clone(self);
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(ledger.config.pnl_update_interval_ms));
+
+            loop {
+                ticker.tick().await;
+
+                ledger.update_all_pnl().await;
+
+                let evicted = ledger.evict_idle_sessions().await;
+                if evicted > 0 {
+                    info!("Dropped {} idle paper trading sessions", evicted);
+                }
+            }
+        });
+    }
+
+    fn find<'a>(
+        sessions: &'a mut HashMap<String, PaperSession>,
+        session: &str,
+        position_account: Pubkey,
+    ) -> Result<&'a mut PaperPosition> {
+        let paper_session = sessions
+            .get_mut(session)
+            .ok_or_else(|| Error::NotFound(format!("Position {}", position_account)))?;
+
+        paper_session.last_active = Some(Utc::now());
+
+        paper_session
+            .positions
+            .get_mut(&position_account)
+            .ok_or_else(|| Error::NotFound(format!("Position {}", position_account)).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn paper(side: Side) -> PaperPosition {
+        PaperPosition {
+            position: Position {
+                side,
+                liquidation_price: dec!(92.5),
+                ..Position::for_test()
+            },
+            maintenance_margin_ratio: dec!(0.025),
+        }
+    }
+
+    #[test]
+    fn test_modify_follows_program_rules() {
+        let mut long = paper(Side::Long);
+        let mmr = long.maintenance_margin_ratio;
+
+        apply_modify(&mut long.position, mmr, Some(dec!(20)), None).unwrap();
+        assert_eq!(long.position.margin, dec!(200));
+
+        // 200 more margin on 2000 notional halves the leverage
+        apply_modify(&mut long.position, mmr, None, Some(200_000_000)).unwrap();
+        assert_eq!(long.position.margin, dec!(400));
+        assert_eq!(long.position.leverage, 5);
+
+        let err = apply_modify(&mut long.position, mmr, None, Some(-300_000_000)).unwrap_err();
+        assert_eq!(err.downcast_ref::<ProgramError>().unwrap().code, "CannotRemoveMargin");
+    }
+
+    #[test]
+    fn test_mark_liquidates_below_maintenance() {
+        let mut short = paper(Side::Short);
+
+        let update = mark_position(&mut short, dec!(105), Utc::now()).unwrap();
+        assert_eq!(update.unrealized_pnl, dec!(-50));
+        assert!(short.position.is_open());
+
+        mark_position(&mut short, dec!(109.9), Utc::now()).unwrap();
+        assert_eq!(short.position.status, PositionStatus::Liquidated);
+        assert_eq!(short.position.realized_pnl, dec!(-100));
+
+        let err = apply_modify(&mut short.position, dec!(0.025), Some(dec!(1)), None).unwrap_err();
+        assert_eq!(err.downcast_ref::<ProgramError>().unwrap().code, "PositionNotOpen");
+    }
+}
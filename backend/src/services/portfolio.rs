@@ -0,0 +1,243 @@
+/// Portfolio
+/// Per-owner totals over their open positions, kept by the PnL pass so
+/// portfolio headers don't sum every position update client-side. Each pass
+/// swaps a position's old contribution for its new one, and owners whose
+/// totals moved are published once at the end of the pass
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::domain::Position;
+
+/// An owner's totals across their open positions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioUpdate {
+    pub owner: Pubkey,
+    pub total_margin: Decimal,
+    pub total_unrealized_pnl: Decimal,
+    pub total_notional: Decimal,
+    /// Margin plus unrealized PnL, collateral outside positions not counted
+    pub equity: Decimal,
+    /// Maintenance requirement over equity, liquidations start at 1. None
+    /// once losses have used up the equity
+    pub margin_usage: Option<Decimal>,
+    pub open_positions: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// What one position adds to its owner's totals
+#[derive(Debug, Clone, Copy, Default)]
+struct Contribution {
+    margin: Decimal,
+    unrealized_pnl: Decimal,
+    notional: Decimal,
+    maintenance: Decimal,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Totals {
+    margin: Decimal,
+    unrealized_pnl: Decimal,
+    notional: Decimal,
+    maintenance: Decimal,
+    open_positions: usize,
+}
+
+impl Totals {
+    fn add(&mut self, contribution: &Contribution) {
+        self.margin += contribution.margin;
+        self.unrealized_pnl += contribution.unrealized_pnl;
+        self.notional += contribution.notional;
+        self.maintenance += contribution.maintenance;
+        self.open_positions += 1;
+    }
+
+    fn subtract(&mut self, contribution: &Contribution) {
+        self.margin -= contribution.margin;
+        self.unrealized_pnl -= contribution.unrealized_pnl;
+        self.notional -= contribution.notional;
+        self.maintenance -= contribution.maintenance;
+        self.open_positions = self.open_positions.saturating_sub(1);
+    }
+
+    fn update(&self, owner: Pubkey, timestamp: DateTime<Utc>) -> PortfolioUpdate {
+        let equity = self.margin + self.unrealized_pnl;
+        let margin_usage = if equity > Decimal::ZERO {
+            Some(self.maintenance / equity)
+        } else if self.maintenance.is_zero() {
+            Some(Decimal::ZERO)
+        } else {
+            None
+        };
+
+        PortfolioUpdate {
+            owner,
+            total_margin: self.margin,
+            total_unrealized_pnl: self.unrealized_pnl,
+            total_notional: self.notional,
+            equity,
+            margin_usage,
+            open_positions: self.open_positions,
+            timestamp,
+        }
+    }
+}
+
+#[derive(Default)]
+struct PortfolioState {
+    /// position_account -> (owner, contribution)
+    positions: HashMap<Pubkey, (Pubkey, Contribution)>,
+    totals: HashMap<Pubkey, Totals>,
+    /// Owners whose totals changed since the last drain
+    changed: HashSet<Pubkey>,
+}
+
+impl PortfolioState {
+    fn take(&mut self, position_account: &Pubkey) {
+        let Some((owner, old)) = self.positions.remove(position_account) else {
+            return;
+        };
+
+        if let Some(totals) = self.totals.get_mut(&owner) {
+            totals.subtract(&old);
+            if totals.open_positions == 0 {
+                self.totals.remove(&owner);
+            }
+        }
+        self.changed.insert(owner);
+    }
+}
+
+#[derive(Default)]
+pub struct PortfolioTracker {
+    state: Mutex<PortfolioState>,
+}
+
+impl PortfolioTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the contribution of a freshly marked open position
+    pub fn record(&self, position: &Position, maintenance_margin_ratio: Decimal) {
+        let notional = position.size * position.mark_price;
+        let contribution = Contribution {
+            margin: position.margin,
+            unrealized_pnl: position.unrealized_pnl,
+            notional,
+            maintenance: notional * maintenance_margin_ratio,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.take(&position.position_account);
+        state
+            .totals
+            .entry(position.owner)
+            .or_default()
+            .add(&contribution);
+        state
+            .positions
+            .insert(position.position_account, (position.owner, contribution));
+        state.changed.insert(position.owner);
+    }
+
+    /// Drop a position that closed or left the cache
+    pub fn remove(&self, position_account: &Pubkey) {
+        self.state.lock().unwrap().take(position_account);
+    }
+
+    /// Totals of the owners that changed since the last call, owners whose
+    /// last position closed come back empty once
+    pub fn drain_changed(&self) -> Vec<PortfolioUpdate> {
+        let mut state = self.state.lock().unwrap();
+        let timestamp = Utc::now();
+        let changed: Vec<Pubkey> = state.changed.drain().collect();
+
+        changed
+            .into_iter()
+            .map(|owner| {
+                state
+                    .totals
+                    .get(&owner)
+                    .cloned()
+                    .unwrap_or_default()
+                    .update(owner, timestamp)
+            })
+            .collect()
+    }
+
+    /// Current totals of `owner`, empty without open positions
+    pub fn portfolio(&self, owner: &Pubkey) -> PortfolioUpdate {
+        self.state
+            .lock()
+            .unwrap()
+            .totals
+            .get(owner)
+            .cloned()
+            .unwrap_or_default()
+            .update(*owner, Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn position(owner: Pubkey, margin: Decimal, unrealized_pnl: Decimal) -> Position {
+        Position {
+            owner,
+            margin,
+            unrealized_pnl,
+            liquidation_price: dec!(90),
+            ..Position::for_test()
+        }
+    }
+
+    #[test]
+    fn test_record_replaces_contribution() {
+        let tracker = PortfolioTracker::new();
+        let owner = Pubkey::new_unique();
+        let mut a = position(owner, dec!(100), dec!(20));
+        let b = position(owner, dec!(50), dec!(-10));
+
+        tracker.record(&a, dec!(0.05));
+        tracker.record(&b, dec!(0.05));
+        a.unrealized_pnl = dec!(50);
+        tracker.record(&a, dec!(0.05));
+
+        let portfolio = tracker.portfolio(&owner);
+        assert_eq!(portfolio.open_positions, 2);
+        assert_eq!(portfolio.total_margin, dec!(150));
+        assert_eq!(portfolio.total_unrealized_pnl, dec!(40));
+        assert_eq!(portfolio.total_notional, dec!(2000));
+        assert_eq!(portfolio.equity, dec!(190));
+        assert_eq!(portfolio.margin_usage, Some(dec!(100) / dec!(190)));
+
+        let changed = tracker.drain_changed();
+        assert_eq!(changed.len(), 1);
+        assert!(tracker.drain_changed().is_empty());
+    }
+
+    #[test]
+    fn test_remove_empties_owner_once() {
+        let tracker = PortfolioTracker::new();
+        let owner = Pubkey::new_unique();
+        let a = position(owner, dec!(100), dec!(20));
+
+        tracker.record(&a, dec!(0.05));
+        tracker.drain_changed();
+
+        tracker.remove(&a.position_account);
+        tracker.remove(&a.position_account);
+
+        let changed = tracker.drain_changed();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].open_positions, 0);
+        assert_eq!(changed[0].equity, Decimal::ZERO);
+        assert!(tracker.drain_changed().is_empty());
+    }
+}
@@ -0,0 +1,357 @@
+/// Position Alerts
+/// User-defined alert rules on a single position: PnL floor, margin ratio
+/// floor and price crossings. Rules live in Redis so any replica can manage
+/// them, the leader evaluates them on every PnL pass
+/// Rules are edge triggered: a threshold rule fires when its condition starts
+/// to hold and re-arms once it stops, a crossing rule fires on every cross
+/// The position's owner signs adding and removing rules with their wallet
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::services::{DomainEvent, EventBus, PositionUpdate};
+
+/// Accounts that have at least one rule
+const RULE_INDEX_KEY: &str = "alerts:positions";
+
+/// Enforced by the API before `add_rule`
+pub const MAX_RULES_PER_POSITION: usize = 20;
+
+fn rules_key(position_account: &Pubkey) -> String {
+    format!("alerts:position:{}", position_account)
+}
+
+/// Message the position's owner signs to add a rule
+pub fn create_position_alert_message(position_account: &Pubkey, timestamp: i64) -> String {
+    format!("Create alert on position {} at {}", position_account, timestamp)
+}
+
+/// Message the position's owner signs to remove rule `rule_id`
+pub fn delete_position_alert_message(position_account: &Pubkey, rule_id: Uuid, timestamp: i64) -> String {
+    format!("Delete alert {} on position {} at {}", rule_id, position_account, timestamp)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Unrealized PnL drops below `threshold` (quote currency)
+    PnlBelow { threshold: Decimal },
+    /// Margin ratio drops below `threshold`, 0.05 = 5%
+    MarginRatioBelow { threshold: Decimal },
+    /// Mark price crosses `price` in either direction
+    PriceCrosses { price: Decimal },
+}
+
+impl AlertCondition {
+    /// Whether the condition holds, for crossings whether price is at or above the level
+    fn observe(&self, update: &PositionUpdate) -> bool {
+        match self {
+            AlertCondition::PnlBelow { threshold } => update.unrealized_pnl < *threshold,
+            AlertCondition::MarginRatioBelow { threshold } => update.margin_ratio < *threshold,
+            AlertCondition::PriceCrosses { price } => update.mark_price >= *price,
+        }
+    }
+
+    /// Compare with the previous observation, returns whether to fire and the new state
+    /// A crossing needs a previous observation, so a new rule never fires on its first pass
+    pub fn evaluate(&self, update: &PositionUpdate, previous: Option<bool>) -> (bool, bool) {
+        let state = self.observe(update);
+        let fired = match self {
+            AlertCondition::PriceCrosses { .. } => previous.is_some_and(|above| above != state),
+            _ => state && previous != Some(true),
+        };
+        (fired, state)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionAlertRule {
+    pub id: Uuid,
+    pub position_account: Pubkey,
+    pub condition: AlertCondition,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A rule that fired
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionAlert {
+    pub rule_id: Uuid,
+    pub position_account: Pubkey,
+    pub symbol: String,
+    pub condition: AlertCondition,
+    pub mark_price: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub margin_ratio: Decimal,
+    pub triggered_at: DateTime<Utc>,
+}
+
+pub struct PositionAlertService {
+    redis_client: redis::Client,
+    /// Last observation per rule, keyed with its position for pruning
+    states: Mutex<HashMap<Uuid, (Pubkey, bool)>>,
+    events: Arc<dyn EventBus>,
+}
+
+impl PositionAlertService {
+    pub fn new(redis_client: redis::Client, events: Arc<dyn EventBus>) -> Self {
+        Self {
+            redis_client,
+            states: Mutex::new(HashMap::new()),
+            events,
+        }
+    }
+
+    pub async fn add_rule(
+        &self,
+        position_account: Pubkey,
+        condition: AlertCondition,
+    ) -> Result<PositionAlertRule> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let key = rules_key(&position_account);
+
+        let rule = PositionAlertRule {
+            id: Uuid::new_v4(),
+            position_account,
+            condition,
+            created_at: Utc::now(),
+        };
+
+        redis::pipe()
+            .atomic()
+            .hset(&key, rule.id.to_string(), serde_json::to_string(&rule)?)
+            .sadd(RULE_INDEX_KEY, position_account.to_string())
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .context("Failed to store alert rule")?;
+
+        Ok(rule)
+    }
+
+    /// Rules for a position, oldest first
+    pub async fn rules_for(&self, position_account: &Pubkey) -> Result<Vec<PositionAlertRule>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let stored: HashMap<String, String> = conn.hgetall(rules_key(position_account)).await?;
+
+        let mut rules = decode_rules(stored.values());
+        rules.sort_by_key(|rule| rule.created_at);
+        Ok(rules)
+    }
+
+    /// Delete one rule, returns it when it existed
+    pub async fn remove_rule(
+        &self,
+        position_account: &Pubkey,
+        rule_id: Uuid,
+    ) -> Result<Option<PositionAlertRule>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let key = rules_key(position_account);
+
+        let stored: Option<String> = conn.hget(&key, rule_id.to_string()).await?;
+        let Some(stored) = stored else {
+            return Ok(None);
+        };
+
+        conn.hdel::<_, _, ()>(&key, rule_id.to_string()).await?;
+
+        let remaining: usize = conn.hlen(&key).await?;
+        if remaining == 0 {
+            conn.srem::<_, _, ()>(RULE_INDEX_KEY, position_account.to_string()).await?;
+        }
+
+        self.states.lock().unwrap().remove(&rule_id);
+
+        Ok(Some(serde_json::from_str(&stored)?))
+    }
+
+    /// Delete every rule on the given positions, used once they leave the monitor
+    pub async fn remove_rules_for(&self, position_accounts: &[Pubkey]) -> Result<()> {
+        if position_accounts.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let mut pipe = redis::pipe();
+        for account in position_accounts {
+            pipe.del(rules_key(account))
+                .srem(RULE_INDEX_KEY, account.to_string());
+        }
+        pipe.query_async::<_, ()>(&mut conn).await?;
+
+        let removed: HashSet<&Pubkey> = position_accounts.iter().collect();
+        self.states
+            .lock()
+            .unwrap()
+            .retain(|_, (account, _)| !removed.contains(account));
+
+        Ok(())
+    }
+
+    /// Run every rule against this PnL pass and broadcast the ones that fire
+    /// Returns the ones that fired
+    pub async fn evaluate(&self, updates: &[PositionUpdate]) -> Result<Vec<PositionAlert>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+
+        let indexed: HashSet<String> = conn.smembers(RULE_INDEX_KEY).await?;
+        let watched: Vec<&PositionUpdate> = updates
+            .iter()
+            .filter(|update| indexed.contains(&update.position_account.to_string()))
+            .collect();
+
+        // Forget rules whose position no longer has any
+        self.states
+            .lock()
+            .unwrap()
+            .retain(|_, (account, _)| indexed.contains(&account.to_string()));
+
+        if watched.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut pipe = redis::pipe();
+        for update in &watched {
+            pipe.hgetall(rules_key(&update.position_account));
+        }
+        let stored: Vec<HashMap<String, String>> = pipe.query_async(&mut conn).await?;
+
+        let mut fired = Vec::new();
+        {
+            let mut states = self.states.lock().unwrap();
+
+            for (update, rules) in watched.into_iter().zip(stored) {
+                for rule in decode_rules(rules.values()) {
+                    let previous = states.get(&rule.id).map(|(_, state)| *state);
+                    let (fire, state) = rule.condition.evaluate(update, previous);
+                    states.insert(rule.id, (rule.position_account, state));
+
+                    if fire {
+                        fired.push(PositionAlert {
+                            rule_id: rule.id,
+                            position_account: rule.position_account,
+                            symbol: update.symbol.clone(),
+                            condition: rule.condition,
+                            mark_price: update.mark_price,
+                            unrealized_pnl: update.unrealized_pnl,
+                            margin_ratio: update.margin_ratio,
+                            triggered_at: update.timestamp,
+                        });
+                    }
+                }
+            }
+        }
+
+        for alert in &fired {
+            self.events.publish(DomainEvent::PositionAlert(alert.clone()));
+        }
+
+        Ok(fired)
+    }
+}
+
+fn decode_rules<'a>(stored: impl Iterator<Item = &'a String>) -> Vec<PositionAlertRule> {
+    stored
+        .filter_map(|json| match serde_json::from_str(json) {
+            Ok(rule) => Some(rule),
+            Err(e) => {
+                warn!("Skipping undecodable alert rule: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Side;
+    use crate::services::verify_wallet_signature;
+    use rust_decimal_macros::dec;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::signer::Signer;
+
+    #[test]
+    fn test_position_alert_signatures() {
+        let owner = Keypair::new();
+        let position_account = Pubkey::new_unique();
+        let now = Utc::now();
+        let message = create_position_alert_message(&position_account, now.timestamp());
+        let signature = owner.sign_message(message.as_bytes()).to_string();
+        assert!(verify_wallet_signature(&owner.pubkey(), &message, &signature, now.timestamp(), now).is_ok());
+
+        // Unsigned, or signed by someone other than the position's owner
+        assert!(verify_wallet_signature(&owner.pubkey(), &message, "", now.timestamp(), now).is_err());
+        let stranger = Keypair::new().sign_message(message.as_bytes()).to_string();
+        assert!(verify_wallet_signature(&owner.pubkey(), &message, &stranger, now.timestamp(), now).is_err());
+
+        // A create signature doesn't delete, nor does a delete of another rule
+        let rule_id = Uuid::new_v4();
+        let delete = delete_position_alert_message(&position_account, rule_id, now.timestamp());
+        assert!(verify_wallet_signature(&owner.pubkey(), &delete, &signature, now.timestamp(), now).is_err());
+        let delete_signature = owner.sign_message(delete.as_bytes()).to_string();
+        let other_rule = delete_position_alert_message(&position_account, Uuid::new_v4(), now.timestamp());
+        assert!(
+            verify_wallet_signature(&owner.pubkey(), &other_rule, &delete_signature, now.timestamp(), now).is_err()
+        );
+    }
+
+    fn update(mark_price: Decimal, unrealized_pnl: Decimal, margin_ratio: Decimal) -> PositionUpdate {
+        PositionUpdate {
+            position_account: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size: dec!(10),
+            entry_price: dec!(150),
+            mark_price,
+            unrealized_pnl,
+            margin: dec!(150),
+            margin_ratio,
+            liquidation_price: dec!(136),
+            effective_liquidation_price: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_threshold_rule_fires_once_and_rearms() {
+        let rule = AlertCondition::PnlBelow { threshold: dec!(-500) };
+
+        let (fired, state) = rule.evaluate(&update(dec!(90), dec!(-600), dec!(0.1)), None);
+        assert!(fired);
+
+        let (fired, state) = rule.evaluate(&update(dec!(89), dec!(-610), dec!(0.1)), Some(state));
+        assert!(!fired, "still below, already alerted");
+
+        let (fired, state) = rule.evaluate(&update(dec!(110), dec!(-400), dec!(0.1)), Some(state));
+        assert!(!fired);
+
+        let (fired, _) = rule.evaluate(&update(dec!(90), dec!(-600), dec!(0.1)), Some(state));
+        assert!(fired, "re-armed after recovering");
+
+        let margin = AlertCondition::MarginRatioBelow { threshold: dec!(0.05) };
+        assert!(!margin.evaluate(&update(dec!(90), dec!(0), dec!(0.05)), None).0);
+        assert!(margin.evaluate(&update(dec!(90), dec!(0), dec!(0.049)), None).0);
+    }
+
+    #[test]
+    fn test_price_rule_fires_on_each_cross() {
+        let rule = AlertCondition::PriceCrosses { price: dec!(100) };
+
+        let (fired, state) = rule.evaluate(&update(dec!(99), dec!(0), dec!(0.1)), None);
+        assert!(!fired, "no cross without a previous price");
+
+        let (fired, state) = rule.evaluate(&update(dec!(100), dec!(0), dec!(0.1)), Some(state));
+        assert!(fired, "upward cross");
+
+        let (fired, state) = rule.evaluate(&update(dec!(105), dec!(0), dec!(0.1)), Some(state));
+        assert!(!fired);
+
+        let (fired, _) = rule.evaluate(&update(dec!(98), dec!(0), dec!(0.1)), Some(state));
+        assert!(fired, "downward cross");
+    }
+}
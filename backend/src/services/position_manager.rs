@@ -1,18 +1,34 @@
-use crate::domain::{Position, PositionStatus, Side};
-use crate::infrastructure::SolanaClient;
-use crate::services::{MarginCalculator, PositionMonitor};
+use crate::domain::{symbols, MarginMode, Position, PositionStatus, RealizedPnL, Side};
+use crate::error::Error;
+use crate::infrastructure::{
+    decode_idl_account, idl_address, price_update_address, HistoryStore, PositionEventKind, PositionEventRecord, ProgramIdl, SolanaClient,
+};
+use crate::services::on_chain_types::{
+    deserialize_anchor_account, deserialize_position_account, deserialize_position_data, OnChainBackstopVault,
+    OnChainConfig, OnChainLiquidatorWhitelist, OnChainLpAccount, OnChainMarginMode, OnChainMarket, OnChainPosition,
+    OnChainPositionStatus, OnChainUserFeeTier, OnChainUserSettings, FEE_TIER_COUNT,
+};
+use crate::services::{
+    liquidate_many_compute_units, plan_one_way_order, taker_fee, PartialExecution, taker_fee_bps, FeePayerService, MarginCalculator, NettingStep, Outbox, OutboxEvent,
+    PositionChangedEvent, PositionMonitor, PositionOpenedEvent, SubmissionLimitConfig, SubmissionLimiter, SubmissionRoute, TradeReport, WebhookDispatcher,
+    WebhookEventType,
+};
 use anyhow::{Context, Result, anyhow};
 use chrono::Utc;
 use rust_decimal::{prelude::ToPrimitive, Decimal};
-use solana_client::rpc_client::RpcClient;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
 use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
     signature::Signature,
     system_program,
 };
 use std::sync::Arc;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, instrument, warn};
 
 // Program ID
 const PROGRAM_ID: &str = "9bca4kbDn7uyQWQaqfKpe8hCdbBh6KqJFNbkzwHhieC3";
@@ -22,10 +38,88 @@ const DISCRIMINATOR_CLOSE_POSITION: [u8; 8] = [123, 134, 81, 0, 49, 68, 98, 98];
 const DISCRIMINATOR_MODIFY_POSITION: [u8; 8] = [48, 249, 6, 139, 14, 95, 106, 88];
 const DISCRIMINATOR_INITIALIZE_USER: [u8; 8] = [111, 17, 185, 250, 60, 122, 38, 254];
 const DISCRIMINATOR_ADD_COLLATERAL: [u8; 8] = [127, 82, 121, 42, 161, 176, 249, 206];
+const DISCRIMINATOR_LIQUIDATE_POSITION: [u8; 8] = [187, 74, 229, 149, 102, 81, 221, 68];
+const DISCRIMINATOR_LIQUIDATE_MANY: [u8; 8] = [201, 148, 149, 219, 234, 229, 167, 108];
+const DISCRIMINATOR_BACKSTOP_LIQUIDATION: [u8; 8] = [198, 53, 48, 254, 37, 14, 200, 108];
+const DISCRIMINATOR_CLOSE_BACKSTOP_POSITION: [u8; 8] = [239, 243, 158, 23, 40, 83, 164, 15];
+const DISCRIMINATOR_UPDATE_MARGIN_CALL: [u8; 8] = [189, 17, 69, 131, 242, 131, 63, 85];
+const DISCRIMINATOR_SET_USER_SETTINGS: [u8; 8] = [26, 175, 170, 93, 31, 136, 123, 56];
+const DISCRIMINATOR_INITIALIZE_MARKET: [u8; 8] = [35, 35, 189, 193, 155, 48, 170, 203];
+const DISCRIMINATOR_SET_MAINTENANCE_MARGIN: [u8; 8] = [149, 216, 211, 104, 143, 111, 45, 125];
+const DISCRIMINATOR_SET_MARKET_THROTTLE: [u8; 8] = [255, 144, 135, 40, 83, 177, 191, 96];
+const DISCRIMINATOR_MIGRATE_POSITION: [u8; 8] = [15, 132, 59, 50, 199, 6, 251, 46];
+const DISCRIMINATOR_WITHDRAW_COLLATERAL: [u8; 8] = [115, 135, 168, 106, 139, 214, 138, 150];
+const DISCRIMINATOR_CLOSE_USER_ACCOUNT: [u8; 8] = [236, 181, 3, 71, 194, 18, 151, 191];
+const DISCRIMINATOR_SET_FEE_SCHEDULE: [u8; 8] = [239, 37, 205, 178, 164, 47, 23, 13];
+const DISCRIMINATOR_SET_USER_FEE_TIER: [u8; 8] = [225, 72, 62, 117, 255, 107, 205, 163];
+
+/// Instructions the backend encodes itself, by their IDL names, checked
+/// against the IDL published on chain
+pub const INSTRUCTION_DISCRIMINATORS: [(&str, [u8; 8]); 19] = [
+    ("open_position", DISCRIMINATOR_OPEN_POSITION),
+    ("close_position", DISCRIMINATOR_CLOSE_POSITION),
+    ("modify_position", DISCRIMINATOR_MODIFY_POSITION),
+    ("initialize_user", DISCRIMINATOR_INITIALIZE_USER),
+    ("add_collateral", DISCRIMINATOR_ADD_COLLATERAL),
+    ("liquidate_position", DISCRIMINATOR_LIQUIDATE_POSITION),
+    ("liquidate_many", DISCRIMINATOR_LIQUIDATE_MANY),
+    ("backstop_liquidation", DISCRIMINATOR_BACKSTOP_LIQUIDATION),
+    ("close_backstop_position", DISCRIMINATOR_CLOSE_BACKSTOP_POSITION),
+    ("update_margin_call", DISCRIMINATOR_UPDATE_MARGIN_CALL),
+    ("set_user_settings", DISCRIMINATOR_SET_USER_SETTINGS),
+    ("initialize_market", DISCRIMINATOR_INITIALIZE_MARKET),
+    ("set_maintenance_margin", DISCRIMINATOR_SET_MAINTENANCE_MARGIN),
+    ("set_market_throttle", DISCRIMINATOR_SET_MARKET_THROTTLE),
+    ("migrate_position", DISCRIMINATOR_MIGRATE_POSITION),
+    ("withdraw_collateral", DISCRIMINATOR_WITHDRAW_COLLATERAL),
+    ("close_user_account", DISCRIMINATOR_CLOSE_USER_ACCOUNT),
+    ("set_fee_schedule", DISCRIMINATOR_SET_FEE_SCHEDULE),
+    ("set_user_fee_tier", DISCRIMINATOR_SET_USER_FEE_TIER),
+];
+
+// Opens retried after an index race, with a growing pause for the RPC to catch up
+const MAX_OPEN_ATTEMPTS: u32 = 3;
+const INDEX_RETRY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(400);
+
+// Program close fractions are in basis points
+const BPS_DENOMINATOR: u16 = 10_000;
+
+/// A one-way order as the client sees it, one fill whatever it took on chain
+#[derive(Debug, Clone)]
+pub struct NettedFill {
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub price: Decimal,
+    pub netted_size: Decimal,
+    pub opened_size: Decimal,
+    /// See `NettingPlan::realized_pnl`
+    pub realized_pnl: Decimal,
+    pub resulting_side: Option<Side>,
+    pub resulting_size: Decimal,
+    /// The lot opened for the remainder, if any
+    pub position: Option<Position>,
+    pub signatures: Vec<Signature>,
+}
+
+/// A lookup table filled with the accounts most transactions share
+#[derive(Debug, Clone)]
+pub struct LookupTableSetup {
+    pub address: Pubkey,
+    pub created: bool,
+    pub addresses: usize,
+    pub signatures: Vec<Signature>,
+}
 
 pub struct PositionManager {
     solana_client: Arc<SolanaClient>,
     monitor: Arc<PositionMonitor>,  // Shared state with monitor
+    fee_payer: Arc<FeePayerService>,
+    lookup_table: Option<Pubkey>,
+    history_store: Option<Arc<dyn HistoryStore>>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+    outbox: Option<Arc<Outbox>>,
+    submissions: SubmissionLimiter,
 }
 
 impl PositionManager {
@@ -33,10 +127,147 @@ impl PositionManager {
         Self {
             solana_client,
             monitor,
+            fee_payer: Arc::new(FeePayerService::default()),
+            lookup_table: None,
+            history_store: None,
+            webhooks: None,
+            outbox: None,
+            submissions: SubmissionLimiter::default(),
+        }
+    }
+
+    /// Use a custom fee payer strategy (defaults to service-paid)
+    pub fn with_fee_payer(mut self, fee_payer: Arc<FeePayerService>) -> Self {
+        self.fee_payer = fee_payer;
+        self
+    }
+
+    /// Send multi-instruction batches as v0 transactions through this lookup table
+    pub fn with_lookup_table(mut self, lookup_table: Pubkey) -> Self {
+        self.lookup_table = Some(lookup_table);
+        self
+    }
+
+    /// Record realized PnL for closes and liquidations
+    pub fn with_history_store(mut self, history_store: Arc<dyn HistoryStore>) -> Self {
+        self.history_store = Some(history_store);
+        self
+    }
+
+    /// Send a trade report of each close and liquidation to subscribed webhooks
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Append opens, position events and trade reports to the outbox. Trade
+    /// report webhooks are then sent by the outbox's `webhooks` consumer
+    pub fn with_outbox(mut self, outbox: Arc<Outbox>) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    /// Cap transactions in flight per chain-submitting route
+    pub fn with_submission_limits(mut self, config: SubmissionLimitConfig) -> Self {
+        self.submissions = SubmissionLimiter::new(config);
+        self
+    }
+
+    /// Send a transaction on behalf of `owner`, the service wallet paying its
+    /// fee within the owner's sponsorship
+    async fn send_for_owner(&self, owner: &Pubkey, instructions: &[Instruction]) -> Result<Signature> {
+        let reserved = self
+            .fee_payer
+            .reserve_fee(owner, &self.solana_client, instructions)
+            .await?;
+        let fee_payer = self.solana_client.payer_pubkey();
+
+        let sent = match self.lookup_table {
+            Some(table_address) if instructions.len() > 1 => {
+                self.send_batch_with_lookup_table(&table_address, instructions, &fee_payer)
+            }
+            _ => self
+                .solana_client
+                .send_transaction_with_fee_payer(instructions, &fee_payer),
+        };
+
+        if let (Err(_), Some(fee)) = (&sent, reserved) {
+            if let Err(e) = self.fee_payer.release_fee(owner, fee).await {
+                warn!("Failed to release {} sponsored lamports of {}: {}", fee, owner, e);
+            }
+        }
+        sent
+    }
+
+    /// Send a batch as a v0 transaction. The table is filled ahead of time by
+    /// `prepare_lookup_table`, never from a request
+    fn send_batch_with_lookup_table(
+        &self,
+        table_address: &Pubkey,
+        instructions: &[Instruction],
+        fee_payer: &Pubkey,
+    ) -> Result<Signature> {
+        let table = self.solana_client.get_lookup_table(table_address)?;
+
+        self.solana_client
+            .send_versioned_transaction(instructions, fee_payer, std::slice::from_ref(&table))
+    }
+
+    /// Accounts shared across transactions: the program, its singletons and
+    /// each market with its price feed. Signers never go in a table
+    pub fn lookup_table_addresses(&self, symbols: &[String]) -> Vec<Pubkey> {
+        let program_id = self.solana_client.program_id;
+        let mut addresses = vec![
+            program_id,
+            system_program::ID,
+            self.solana_client.derive_config_pda().0,
+            Pubkey::find_program_address(&[b"liquidator_whitelist"], &program_id).0,
+        ];
+
+        for symbol in symbols {
+            addresses.push(self.solana_client.derive_market_pda(symbol).0);
+            match price_update_address(symbol) {
+                Ok(price_update) => addresses.push(price_update),
+                Err(e) => warn!("No price feed to table for {}: {}", symbol, e),
+            }
         }
+
+        addresses
+    }
+
+    /// Create a lookup table unless one is given or configured, then add the
+    /// shared accounts it's missing. Run from the admin API, not per request
+    #[instrument(skip_all)]
+    pub async fn prepare_lookup_table(
+        &self,
+        table_address: Option<Pubkey>,
+        symbols: &[String],
+    ) -> Result<LookupTableSetup> {
+        let mut signatures = Vec::new();
+
+        let (address, created) = match table_address.or(self.lookup_table) {
+            Some(address) => (address, false),
+            None => {
+                let (address, signature) = self.solana_client.create_lookup_table()?;
+                info!("Created address lookup table {}: {}", address, signature);
+                signatures.push(signature);
+                (address, true)
+            }
+        };
+
+        let addresses = self.lookup_table_addresses(symbols);
+        signatures.extend(self.solana_client.extend_lookup_table(&address, &addresses)?);
+
+        Ok(LookupTableSetup {
+            address,
+            created,
+            addresses: addresses.len(),
+            signatures,
+        })
     }
 
     /// Initialize user account on-chain
+    #[instrument(skip_all, fields(%owner))]
     pub async fn initialize_user(&self, owner: &Pubkey) -> Result<Signature> {
         info!("Initializing user account for {}", owner);
 
@@ -60,13 +291,18 @@ impl PositionManager {
             data,
         };
 
-        let signature = self.solana_client.send_transaction(&[instruction])?;
+        let _permit = self
+            .submissions
+            .acquire(SubmissionRoute::InitializeUser, owner)
+            .await;
+        let signature = self.send_for_owner(owner, &[instruction]).await?;
 
         info!("User initialized: {}", signature);
         Ok(signature)
     }
 
     /// Add collateral to user account
+    #[instrument(skip_all, fields(%owner))]
     pub async fn add_collateral(&self, owner: &Pubkey, amount: u64) -> Result<Signature> {
         info!("Adding collateral: {} for {}", amount, owner);
 
@@ -90,13 +326,139 @@ impl PositionManager {
             data,
         };
 
-        let signature = self.solana_client.send_transaction(&[instruction])?;
+        let _permit = self
+            .submissions
+            .acquire(SubmissionRoute::AddCollateral, owner)
+            .await;
+        let signature = self.send_for_owner(owner, &[instruction]).await?;
 
         info!("Collateral added: {}", signature);
         Ok(signature)
     }
 
+    /// Close the owner's user account, settings and settled positions,
+    /// withdrawing `withdraw` collateral first in the same transaction. Rent
+    /// goes back to the owner
+    #[instrument(skip_all, fields(%owner))]
+    pub async fn close_user_account(&self, owner: &Pubkey, withdraw: u64) -> Result<Signature> {
+        info!("Closing user account of {}, withdrawing {}", owner, withdraw);
+
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+
+        let (user_account, _) =
+            Pubkey::find_program_address(&[b"user", owner.as_ref()], &program_id);
+        let position_count_total = self.get_user_account(owner).await?.position_count_total;
+
+        // Anchor reads the program id as an absent optional account
+        let user_settings = match self.get_user_settings(owner).await? {
+            Some(_) => self.solana_client.derive_user_settings_pda(owner).0,
+            None => program_id,
+        };
+
+        let mut instructions = Vec::new();
+        if withdraw > 0 {
+            let mut data = Vec::new();
+            data.extend_from_slice(&DISCRIMINATOR_WITHDRAW_COLLATERAL);
+            data.extend_from_slice(&withdraw.to_le_bytes());
+
+            instructions.push(Instruction {
+                program_id,
+                accounts: vec![
+                    AccountMeta::new(user_account, false),
+                    AccountMeta::new_readonly(*owner, true),
+                ],
+                data,
+            });
+        }
+
+        let mut accounts = vec![
+            AccountMeta::new(user_account, false),
+            if user_settings == program_id {
+                AccountMeta::new_readonly(program_id, false)
+            } else {
+                AccountMeta::new(user_settings, false)
+            },
+            AccountMeta::new(*owner, true),
+        ];
+        // Remaining accounts, every position the owner opened in index order
+        for position_index in 0..position_count_total {
+            let (position_account, _) = Pubkey::find_program_address(
+                &[b"position", owner.as_ref(), &position_index.to_le_bytes()],
+                &program_id,
+            );
+            accounts.push(AccountMeta::new(position_account, false));
+        }
+
+        instructions.push(Instruction {
+            program_id,
+            accounts,
+            data: DISCRIMINATOR_CLOSE_USER_ACCOUNT.to_vec(),
+        });
+
+        let mut permit = self
+            .submissions
+            .acquire(SubmissionRoute::CloseUser, owner)
+            .await;
+        let signature = self.send_for_owner(owner, &instructions).await?;
+        // A new user account starts its position indexes over
+        permit.forget_position_index();
+
+        info!("User account closed: {}", signature);
+        Ok(signature)
+    }
+
+    /// Write the owner's defaults for new positions, the program creates the
+    /// settings account on the first write
+    #[instrument(skip_all, fields(%owner))]
+    pub async fn set_user_settings(
+        &self,
+        owner: &Pubkey,
+        default_slippage_bps: u16,
+        default_leverage: u16,
+        margin_mode: MarginMode,
+    ) -> Result<Signature> {
+        info!(
+            "Setting defaults for {}: {} bps slippage, {}x, {:?}",
+            owner, default_slippage_bps, default_leverage, margin_mode
+        );
+
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        let (user_settings, _) = self.solana_client.derive_user_settings_pda(owner);
+
+        let margin_mode = match margin_mode {
+            MarginMode::Isolated => OnChainMarginMode::Isolated,
+            MarginMode::Cross => OnChainMarginMode::Cross,
+        };
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&DISCRIMINATOR_SET_USER_SETTINGS);
+        data.extend_from_slice(&default_slippage_bps.to_le_bytes());
+        data.extend_from_slice(&default_leverage.to_le_bytes());
+        data.push(margin_mode as u8);
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(user_settings, false),
+                AccountMeta::new(*owner, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data,
+        };
+
+        let _permit = self
+            .submissions
+            .acquire(SubmissionRoute::UserSettings, owner)
+            .await;
+        let signature = self.send_for_owner(owner, &[instruction]).await?;
+
+        info!("User settings updated: {}", signature);
+        Ok(signature)
+    }
+
     /// Open a new position on-chain
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip_all, fields(%owner, %symbol))]
     pub async fn open_position(
         &self,
         owner: Pubkey,
@@ -107,6 +469,8 @@ impl PositionManager {
         entry_price: Decimal,
         maintenance_margin_ratio: Decimal,
     ) -> Result<(Position, Signature)> {
+        let symbol = symbols::canonicalize(&symbol)?;
+
         info!(
             "Opening position: {} {:?} {} {}x @ ${}",
             symbol, side, size, leverage, entry_price
@@ -124,57 +488,25 @@ impl PositionManager {
             maintenance_margin_ratio,
         )?;
 
+        self.fresh_price_publish_time(&symbol).await?;
+        let price_update = price_update_address(&symbol)?;
+
         let program_id: Pubkey = PROGRAM_ID.parse()?;
 
         let (user_account, _) =
             Pubkey::find_program_address(&[b"user", owner.as_ref()], &program_id);
-
-        let position_index = self.get_next_position_index(&owner).await?;
-
-        let (position_account, bump) = Pubkey::find_program_address(
-            &[b"position", owner.as_ref(), &position_index.to_le_bytes()],
-            &program_id,
-        );
-
-        info!(
-            "Position PDA: {} (index: {}, bump: {})",
-            position_account, position_index, bump
-        );
-
-        let mut data = Vec::new();
-        data.extend_from_slice(&DISCRIMINATOR_OPEN_POSITION);
-
-        let symbol_bytes = symbol.as_bytes();
-        data.extend_from_slice(&(symbol_bytes.len() as u32).to_le_bytes());
-        data.extend_from_slice(symbol_bytes);
-
-        let side_u8 = match side {
-            Side::Long => 0u8,
-            Side::Short => 1u8,
-        };
-        data.push(side_u8);
-
-        data.extend_from_slice(&size_u64.to_le_bytes());
-        data.extend_from_slice(&leverage.to_le_bytes());
-        data.extend_from_slice(&entry_price_u64.to_le_bytes());
-
-        let instruction = Instruction {
-            program_id,
-            accounts: vec![
-                AccountMeta::new(position_account, false),
-                AccountMeta::new(user_account, false),
-                AccountMeta::new(owner, true),
-                AccountMeta::new_readonly(system_program::ID, false),
-            ],
-            data,
-        };
-
-        let signature = self.solana_client.send_transaction(&[instruction])?;
-
-        info!("Position opened on-chain: {}", signature);
-
-        // Create position object
-        let position = Position {
+        let fee_tier = self.fee_tier_account(&owner, self.get_user_fee_tier(&owner).await?.is_some());
+
+        // Held until the open is sent so queued opens get the following indexes
+        let mut permit = self
+            .submissions
+            .acquire(SubmissionRoute::OpenPosition, &owner)
+            .await;
+        let mut chain_next = self.get_next_position_index(&owner).await?;
+        let mut attempt = 1;
+
+        let opened_at = Utc::now();
+        let position_at = |position_index: u32, position_account: Pubkey| Position {
             position_index,
             owner,
             position_account,
@@ -189,19 +521,224 @@ impl PositionManager {
             realized_pnl: Decimal::ZERO,
             funding_accrued: Decimal::ZERO,
             liquidation_price,
+            effective_liquidation_price: None,
             status: PositionStatus::Open,
-            opened_at: Utc::now(),
-            last_update: Utc::now(),
+            opened_at,
+            last_update: opened_at,
             closed_at: None,
+            margin_call_at: None,
+        };
+
+        let (position_index, position_account, signature) = loop {
+            let position_index = permit.reserve_position_index(chain_next);
+
+            let (position_account, bump) = Pubkey::find_program_address(
+                &[b"position", owner.as_ref(), &position_index.to_le_bytes()],
+                &program_id,
+            );
+
+            info!(
+                "Position PDA: {} (index: {}, bump: {})",
+                position_account, position_index, bump
+            );
+
+            let mut data = Vec::new();
+            data.extend_from_slice(&DISCRIMINATOR_OPEN_POSITION);
+            data.extend_from_slice(&position_index.to_le_bytes());
+
+            let symbol_bytes = symbol.as_bytes();
+            data.extend_from_slice(&(symbol_bytes.len() as u32).to_le_bytes());
+            data.extend_from_slice(symbol_bytes);
+
+            let side_u8 = match side {
+                Side::Long => 0u8,
+                Side::Short => 1u8,
+            };
+            data.push(side_u8);
+
+            data.extend_from_slice(&size_u64.to_le_bytes());
+            data.extend_from_slice(&leverage.to_le_bytes());
+            data.extend_from_slice(&entry_price_u64.to_le_bytes());
+
+            let instruction = Instruction {
+                program_id,
+                accounts: vec![
+                    AccountMeta::new(user_account, false),
+                    AccountMeta::new(position_account, false),
+                    AccountMeta::new_readonly(self.solana_client.derive_config_pda().0, false),
+                    AccountMeta::new(owner, true),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                    AccountMeta::new(self.solana_client.derive_market_pda(&symbol).0, false),
+                    AccountMeta::new(self.solana_client.derive_open_throttle_pda(&owner, &symbol).0, false),
+                    fee_tier.clone(),
+                    AccountMeta::new_readonly(price_update, false),
+                ],
+                data,
+            };
+
+            // Reads between here and the confirmation see the position as Opening
+            self.monitor
+                .add_pending_position(position_at(position_index, position_account))
+                .await?;
+
+            let sent = self.send_for_owner(&owner, &[instruction]).await;
+            if sent.is_err() {
+                self.monitor.discard_pending_position(position_account).await?;
+            }
+
+            match sent {
+                Ok(signature) => break (position_index, position_account, signature),
+                // Another client opened for this owner, or the RPC served a stale count
+                Err(e) if is_position_index_mismatch(&e) && attempt < MAX_OPEN_ATTEMPTS => {
+                    warn!(
+                        "Position index {} taken for {}, retrying (attempt {})",
+                        position_index, owner, attempt
+                    );
+                    permit.forget_position_index();
+                    tokio::time::sleep(INDEX_RETRY_BACKOFF * attempt).await;
+                    chain_next = self.get_next_position_index(&owner).await?;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
         };
+        permit.commit_position_index(position_index);
+        drop(permit);
+
+        info!("Position opened on-chain: {}", signature);
 
-        // Register with monitor
-        self.monitor.add_position(position.clone()).await?;
+        let position = position_at(position_index, position_account);
+        self.monitor.confirm_pending_position(position.clone()).await?;
+        self.monitor
+            .track_mutation(signature, position.position_account, None)
+            .await;
+        self.record_open(&position, signature).await;
 
         Ok((position, signature))
     }
 
+    /// Place an order in one-way mode: net it against the owner's open lots
+    /// on the symbol, then open whatever is left. Steps run in order; when one
+    /// fails the earlier ones have already landed, and the error is a
+    /// `PartialExecution` carrying their signatures
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip_all, fields(%owner, %symbol))]
+    pub async fn place_one_way_order(
+        &self,
+        owner: Pubkey,
+        symbol: String,
+        side: Side,
+        size: Decimal,
+        leverage: u16,
+        price: Decimal,
+        maintenance_margin_ratio: Decimal,
+    ) -> Result<NettedFill> {
+        let symbol = symbols::canonicalize(&symbol)?;
+
+        let mut lots: Vec<Position> = self
+            .get_open_positions(&owner)
+            .await?
+            .into_iter()
+            .filter(|position| position.symbol == symbol)
+            .collect();
+        lots.sort_by_key(|position| (position.opened_at, position.position_index));
+
+        let plan = plan_one_way_order(&lots, side, size, price)?;
+        info!(
+            "One-way order {:?} {} {}: {} netted, {} opened",
+            side, size, symbol, plan.netted_size, plan.opened_size
+        );
+
+        // A reduce is a close and a reopen
+        let total_steps = plan
+            .steps
+            .iter()
+            .map(|step| if matches!(step, NettingStep::Reduce { .. }) { 2 } else { 1 })
+            .sum();
+        let mut signatures = Vec::with_capacity(total_steps);
+        let mut position = None;
+
+        let partial = |signatures: &Vec<Signature>, source: anyhow::Error| -> anyhow::Error {
+            PartialExecution {
+                completed: signatures.clone(),
+                total_steps,
+                source,
+            }
+            .into()
+        };
+
+        for step in &plan.steps {
+            match step {
+                NettingStep::Close { position_account, .. } => {
+                    let (_, signature) = self
+                        .close_position(*position_account, price)
+                        .await
+                        .map_err(|e| partial(&signatures, e))?;
+                    signatures.push(signature);
+                }
+                NettingStep::Reduce {
+                    position_account,
+                    new_size,
+                    side,
+                    leverage,
+                    ..
+                } => {
+                    let (_, signature) = self
+                        .close_position(*position_account, price)
+                        .await
+                        .map_err(|e| partial(&signatures, e))?;
+                    signatures.push(signature);
+
+                    let (_, signature) = self
+                        .open_position(
+                            owner,
+                            symbol.clone(),
+                            *side,
+                            *new_size,
+                            *leverage,
+                            price,
+                            maintenance_margin_ratio,
+                        )
+                        .await
+                        .map_err(|e| partial(&signatures, e))?;
+                    signatures.push(signature);
+                }
+                NettingStep::Open { side, size } => {
+                    let (opened, signature) = self
+                        .open_position(
+                            owner,
+                            symbol.clone(),
+                            *side,
+                            *size,
+                            leverage,
+                            price,
+                            maintenance_margin_ratio,
+                        )
+                        .await
+                        .map_err(|e| partial(&signatures, e))?;
+                    position = Some(opened);
+                    signatures.push(signature);
+                }
+            }
+        }
+
+        Ok(NettedFill {
+            symbol,
+            side,
+            size,
+            price,
+            netted_size: plan.netted_size,
+            opened_size: plan.opened_size,
+            realized_pnl: plan.realized_pnl,
+            resulting_side: plan.resulting_side,
+            resulting_size: plan.resulting_size,
+            position,
+            signatures,
+        })
+    }
+
     /// Modify position on-chain
+    #[instrument(skip_all, fields(%position_account))]
     pub async fn modify_position(
         &self,
         position_account: Pubkey,
@@ -247,19 +784,47 @@ impl PositionManager {
             accounts: vec![
                 AccountMeta::new(position.position_account, false),
                 AccountMeta::new(user_account, false),
+                AccountMeta::new_readonly(self.solana_client.derive_config_pda().0, false),
                 AccountMeta::new_readonly(position.owner, true),
+                AccountMeta::new_readonly(self.market_address(&position.symbol), false),
             ],
             data,
         };
 
-        let signature = self.solana_client.send_transaction(&[instruction])?;
+        let _permit = self
+            .submissions
+            .acquire(SubmissionRoute::ModifyPosition, &position.owner)
+            .await;
+        let mut instructions = self.migrations_for(&[position.position_account])?;
+        instructions.push(instruction);
+        let signature = self.send_for_owner(&position.owner, &instructions).await?;
 
         info!("Position modified on-chain: {}", signature);
 
+        let margin = position.margin + margin_delta.map_or(Decimal::ZERO, |delta| Decimal::new(delta, 6));
+        let detail = match (new_size, margin_delta) {
+            (Some(size), Some(_)) => format!("size {} -> {}, margin {} -> {}", position.size, size, position.margin, margin),
+            (Some(size), None) => format!("size {} -> {}", position.size, size),
+            _ => format!("margin {} -> {}", position.margin, margin),
+        };
+        self.record_event(PositionEventRecord {
+            id: uuid::Uuid::new_v4(),
+            position_account,
+            kind: PositionEventKind::Modified,
+            size: Some(new_size.unwrap_or(position.size)),
+            margin: Some(margin),
+            mark_price: Some(position.mark_price),
+            detail: Some(detail),
+            signature: Some(signature.to_string()),
+            recorded_at: Utc::now(),
+        })
+        .await;
+
         Ok(signature)
     }
 
     /// Close a position on-chain
+    #[instrument(skip_all, fields(%position_account))]
     pub async fn close_position(
         &self,
         position_account: Pubkey,
@@ -273,6 +838,8 @@ impl PositionManager {
 
         info!("Closing position {} at price ${}", position_account, final_price);
 
+        self.fresh_price_publish_time(&position.symbol).await?;
+
         let realized_pnl = MarginCalculator::calculate_unrealized_pnl(
             position.side,
             position.size,
@@ -280,11 +847,19 @@ impl PositionManager {
             position.entry_price,
         )?;
 
+        let fee_tier = self.get_user_fee_tier(&position.owner).await?;
+        let fee = taker_fee(
+            position.size,
+            final_price,
+            taker_fee_bps(&self.get_fee_schedule().await?, fee_tier.as_ref().map(|fee_tier| fee_tier.tier)),
+        );
+
         let total_pnl = realized_pnl
             .checked_add(position.funding_accrued)
+            .and_then(|pnl| pnl.checked_sub(fee))
             .ok_or_else(|| anyhow!("PnL overflow"))?;
 
-        info!("Closing PnL: {}", total_pnl);
+        info!("Closing PnL: {} after {} taker fee", total_pnl, fee);
 
         let program_id: Pubkey = PROGRAM_ID.parse()?;
 
@@ -303,107 +878,1303 @@ impl PositionManager {
                 AccountMeta::new(position.position_account, false),
                 AccountMeta::new(user_account, false),
                 AccountMeta::new(position.owner, true),
+                AccountMeta::new_readonly(self.solana_client.derive_config_pda().0, false),
+                self.fee_tier_account(&position.owner, fee_tier.is_some()),
+                AccountMeta::new_readonly(price_update_address(&position.symbol)?, false),
             ],
             data,
         };
 
-        let signature = self.solana_client.send_transaction(&[instruction])?;
+        let _permit = self
+            .submissions
+            .acquire(SubmissionRoute::ClosePosition, &position.owner)
+            .await;
+        let mut instructions = self.migrations_for(&[position.position_account])?;
+        instructions.push(instruction);
+        let signature = self.send_for_owner(&position.owner, &instructions).await?;
 
         info!("Position closed on-chain: {}", signature);
 
+        self.record_realized_pnl(&position, position.size, final_price, fee, total_pnl, false, signature)
+            .await;
+        self.apply_settled_status(position, PositionStatus::Closed, signature).await?;
+
         Ok((total_pnl, signature))
     }
 
-    /// Get position from monitor's shared state
-    pub async fn get_position(&self, position_account: Pubkey) -> Result<Position> {
-        self.monitor
-            .get_position(position_account)
-            .await
-            .ok_or_else(|| anyhow!("Position not found"))
-    }
+    /// Liquidate `close_fraction` of an underwater position with the service
+    /// wallet as liquidator, 1 closes it entirely
+    /// Goes through the Jito relay when the client has one configured
+    #[instrument(skip_all, fields(%position_account))]
+    pub async fn liquidate_position(
+        &self,
+        position_account: Pubkey,
+        mark_price: Decimal,
+        close_fraction: Decimal,
+    ) -> Result<Signature> {
+        let position = self.get_position(position_account).await?;
 
-    /// Get user positions from monitor
-    pub async fn get_user_positions(&self, owner: &Pubkey) -> Result<Vec<Position>> {
-        self.monitor.get_user_positions(owner).await
-    }
+        if !position.is_open() {
+            return Err(anyhow!("Position is not open"));
+        }
 
-    /// Get open positions from monitor
-    pub async fn get_open_positions(&self, owner: &Pubkey) -> Result<Vec<Position>> {
-        let positions = self.get_user_positions(owner).await?;
-        Ok(positions.into_iter().filter(|p| p.is_open()).collect())
-    }
+        // Rounded up, the program checks the remaining ratio after closing
+        let close_bps = (close_fraction * Decimal::from(BPS_DENOMINATOR))
+            .ceil()
+            .clamp(Decimal::ONE, Decimal::from(BPS_DENOMINATOR))
+            .to_u16()
+            .ok_or_else(|| anyhow!("Invalid close fraction {}", close_fraction))?;
+        // The program reads the price itself, a stale one would only fail there
+        self.fresh_price_publish_time(&position.symbol).await?;
 
-    /// Get user account from chain
-    pub async fn get_user_account(&self, owner: &Pubkey) -> Result<UserAccountData> {
-        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        info!(
+            "Liquidating {} bps of position {} at mark price ${}",
+            close_bps, position_account, mark_price
+        );
 
-        let (user_account, _) =
-            Pubkey::find_program_address(&[b"user", owner.as_ref()], &program_id);
+        let mut instructions = self.migrations_for(&[position.position_account])?;
+        instructions.push(liquidate_position_instruction(
+            &PROGRAM_ID.parse()?,
+            &position,
+            &self.solana_client.payer_pubkey(),
+            close_bps,
+        )?);
 
-        let rpc_client = RpcClient::new(&self.solana_client.rpc_url);
-        let account_data = rpc_client
-            .get_account_data(&user_account)
-            .context("Failed to fetch user account")?;
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&instructions)
+            .await?;
 
-        let data = &account_data[8..];
+        info!("Position liquidated on-chain: {}", signature);
 
-        let owner_bytes: [u8; 32] = data[0..32].try_into()?;
-        let owner_pubkey = Pubkey::new_from_array(owner_bytes);
+        let size_closed = (position.size * Decimal::from(close_bps) / Decimal::from(BPS_DENOMINATOR))
+            .min(position.size);
+        let share = size_closed / position.size;
 
-        let total_collateral = u64::from_le_bytes(data[32..40].try_into()?);
-        let locked_collateral = u64::from_le_bytes(data[40..48].try_into()?);
-        let total_pnl = i64::from_le_bytes(data[48..56].try_into()?);
-        let position_count = u32::from_le_bytes(data[56..60].try_into()?);
-        let position_count_total = u32::from_le_bytes(data[60..64].try_into()?);
-        let bump = data[64];
+        // The program caps a liquidation loss at the position's margin
+        let price_pnl = MarginCalculator::calculate_unrealized_pnl(
+            position.side,
+            size_closed,
+            mark_price,
+            position.entry_price,
+        )?;
+        let funding = position.funding_accrued * share;
+        let realized = (price_pnl + funding).max(-position.margin);
 
-        Ok(UserAccountData {
-            owner: owner_pubkey,
-            total_collateral,
-            locked_collateral,
-            total_pnl,
-            position_count,
-            position_count_total,
-            bump,
-        })
+        self.record_realized_pnl(&position, size_closed, mark_price, Decimal::ZERO, realized, true, signature)
+            .await;
+
+        if size_closed < position.size {
+            self.apply_partial_liquidation(position, size_closed, funding, realized, signature)
+                .await?;
+        } else {
+            self.apply_settled_status(position, PositionStatus::Liquidated, signature)
+                .await?;
+        }
+
+        Ok(signature)
     }
 
-    /// Get next position index from on-chain user account
-    async fn get_next_position_index(&self, owner: &Pubkey) -> Result<u32> {
-        match self.get_user_account(owner).await {
-            Ok(user_account) => {
-                info!(
-                    "Fetched position_count_total from chain: {}",
-                    user_account.position_count_total
-                );
-                Ok(user_account.position_count_total)
+    /// Fully liquidate several positions on `symbol` at `mark_price` in one
+    /// liquidate_many transaction, with the service wallet as liquidator
+    /// The program skips positions that are no longer liquidatable, so the
+    /// ones liquidated are read back from chain and returned with the signature.
+    /// Any the read misses, e.g. a bundle that hasn't landed yet, are left to
+    /// the monitor's next sync
+    #[instrument(skip_all, fields(%symbol, count = position_accounts.len()))]
+    pub async fn liquidate_many(
+        &self,
+        symbol: &str,
+        position_accounts: &[Pubkey],
+        mark_price: Decimal,
+    ) -> Result<(Signature, Vec<Pubkey>)> {
+        let mut positions = Vec::with_capacity(position_accounts.len());
+        for position_account in position_accounts {
+            let position = self.get_position(*position_account).await?;
+            if position.is_open() && position.symbol == symbol {
+                positions.push(position);
             }
-            Err(e) => {
-                warn!(
-                    "Could not fetch user account (might not be initialized): {}",
-                    e
-                );
-                Ok(0)
+        }
+        if positions.is_empty() {
+            return Err(anyhow!("No open {} positions to liquidate", symbol));
+        }
+
+        // The program reads the price itself, a stale one would only fail there
+        self.fresh_price_publish_time(symbol).await?;
+
+        info!(
+            "Liquidating {} {} positions at mark price ${}",
+            positions.len(),
+            symbol,
+            mark_price
+        );
+
+        let keys: Vec<Pubkey> = positions.iter().map(|position| position.position_account).collect();
+        let migrations = self.migrations_for(&keys)?;
+
+        // A migration costs less than a position liquidated, budgeted as one
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            liquidate_many_compute_units(positions.len() + migrations.len()),
+        )];
+        instructions.extend(migrations);
+        instructions.push(liquidate_many_instruction(
+            &PROGRAM_ID.parse()?,
+            symbol,
+            &positions,
+            &self.solana_client.payer_pubkey(),
+        )?);
+
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&instructions)
+            .await?;
+
+        info!("Positions liquidated on-chain: {}", signature);
+
+        let accounts = self
+            .solana_client
+            .rpc_pool()
+            .read(|rpc_client| rpc_client.get_multiple_accounts_with_commitment(&keys, CommitmentConfig::confirmed()))
+            .context("Failed to read back liquidated positions")?
+            .value;
+
+        let mut liquidated = Vec::new();
+        for (position, account) in positions.into_iter().zip(accounts) {
+            let settled = account
+                .map(|account| deserialize_position_account(position.position_account, &account))
+                .transpose()?
+                .is_some_and(|(_, on_chain)| on_chain.status == OnChainPositionStatus::Liquidated);
+            if !settled {
+                debug!("Position {} was not liquidated by {}", position.position_account, signature);
+                continue;
             }
+
+            // The program caps a liquidation loss at the position's margin
+            let price_pnl = MarginCalculator::calculate_unrealized_pnl(
+                position.side,
+                position.size,
+                mark_price,
+                position.entry_price,
+            )?;
+            let realized = (price_pnl + position.funding_accrued).max(-position.margin);
+
+            liquidated.push(position.position_account);
+            self.record_realized_pnl(&position, position.size, mark_price, Decimal::ZERO, realized, true, signature)
+                .await;
+            self.apply_settled_status(position, PositionStatus::Liquidated, signature)
+                .await?;
         }
+
+        Ok((signature, liquidated))
     }
-    
-    /// Get statistics from monitor
-    pub async fn get_statistics(&self) -> Result<PositionStats> {
-        let monitor_stats = self.monitor.get_statistics().await;
-        
-        Ok(PositionStats {
-            total_positions: monitor_stats.total_positions,
-            open_positions: monitor_stats.open_positions,
-            closed_positions: monitor_stats.total_positions - monitor_stats.open_positions,
-            total_unrealized_pnl: monitor_stats.total_unrealized_pnl,
-            total_realized_pnl: Decimal::ZERO, // Monitor doesn't track realized PnL
-        })
-    }
-}
 
-/// Helper: Convert Decimal to u64 with precision
-fn decimal_to_u64(decimal: Decimal, precision: u32) -> Result<u64> {
+    /// Issue or clear a margin call with the service wallet, the program
+    /// decides which from the margin ratio at the oracle price. `mark_price`
+    /// is the backend's view of it, recorded with the event
+    #[instrument(skip_all, fields(%position_account))]
+    pub async fn update_margin_call(
+        &self,
+        position_account: Pubkey,
+        mark_price: Decimal,
+    ) -> Result<Signature> {
+        let position = self.get_position(position_account).await?;
+
+        if !position.is_open() {
+            return Err(anyhow!("Position is not open"));
+        }
+
+        // The program reads the price itself, a stale one would only fail there
+        self.fresh_price_publish_time(&position.symbol).await?;
+
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        let (config, _) = self.solana_client.derive_config_pda();
+        let (liquidator_whitelist, _) = self.solana_client.derive_liquidator_whitelist_pda();
+        let (market, _) = self.solana_client.derive_market_pda(&position.symbol);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&DISCRIMINATOR_UPDATE_MARGIN_CALL);
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(position.position_account, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new_readonly(liquidator_whitelist, false),
+                AccountMeta::new_readonly(self.solana_client.payer_pubkey(), true),
+                AccountMeta::new_readonly(market, false),
+                AccountMeta::new_readonly(price_update_address(&position.symbol)?, false),
+            ],
+            data,
+        };
+
+        let mut instructions = self.migrations_for(&[position.position_account])?;
+        instructions.push(instruction);
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&instructions)
+            .await?;
+
+        // The instruction toggles the status, Open becomes MarginCall and back
+        let mut updated = position.clone();
+        if position.status == PositionStatus::MarginCall {
+            updated.status = PositionStatus::Open;
+            updated.margin_call_at = None;
+        } else {
+            updated.status = PositionStatus::MarginCall;
+            updated.margin_call_at = Some(Utc::now());
+        }
+        updated.last_update = Utc::now();
+
+        info!("Margin call on {} now {:?}: {}", position_account, updated.status, signature);
+
+        let kind = if updated.status == PositionStatus::MarginCall {
+            PositionEventKind::MarginCall
+        } else {
+            PositionEventKind::MarginCallCleared
+        };
+        self.record_event(PositionEventRecord {
+            id: uuid::Uuid::new_v4(),
+            position_account,
+            kind,
+            size: Some(position.size),
+            margin: Some(position.margin),
+            mark_price: Some(mark_price),
+            detail: None,
+            signature: Some(signature.to_string()),
+            recorded_at: Utc::now(),
+        })
+        .await;
+
+        self.monitor.replace_position(updated).await?;
+        self.monitor
+            .track_mutation(signature, position_account, Some(position))
+            .await;
+
+        Ok(signature)
+    }
+
+    /// Liquidate a whole position into the backstop vault, which takes it over
+    /// at its discount to `mark_price`
+    #[instrument(skip_all, fields(%position_account))]
+    pub async fn backstop_liquidation(
+        &self,
+        position_account: Pubkey,
+        mark_price: Decimal,
+    ) -> Result<Signature> {
+        let position = self.get_position(position_account).await?;
+
+        if !position.is_open() {
+            return Err(anyhow!("Position is not open"));
+        }
+
+        let vault = self.get_backstop_vault().await?;
+        // The program reads the price itself, a stale one would only fail there
+        self.fresh_price_publish_time(&position.symbol).await?;
+
+        info!(
+            "Liquidating position {} into the backstop vault at mark price ${}",
+            position_account, mark_price
+        );
+
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+
+        let (user_account, _) =
+            Pubkey::find_program_address(&[b"user", position.owner.as_ref()], &program_id);
+        let (config, _) = self.solana_client.derive_config_pda();
+        let (liquidator_whitelist, _) = self.solana_client.derive_liquidator_whitelist_pda();
+        let (market, _) = self.solana_client.derive_market_pda(&position.symbol);
+        let (vault_position, _) = Pubkey::find_program_address(
+            &[
+                b"position",
+                vault.address.as_ref(),
+                &vault.position_count_total.to_le_bytes(),
+            ],
+            &program_id,
+        );
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&DISCRIMINATOR_BACKSTOP_LIQUIDATION);
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(position.position_account, false),
+                AccountMeta::new(user_account, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new_readonly(liquidator_whitelist, false),
+                AccountMeta::new(vault.address, false),
+                AccountMeta::new(vault_position, false),
+                AccountMeta::new(self.solana_client.payer_pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(market, false),
+                AccountMeta::new_readonly(price_update_address(&position.symbol)?, false),
+            ],
+            data,
+        };
+
+        let mut instructions = self.migrations_for(&[position.position_account])?;
+        instructions.push(instruction);
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&instructions)
+            .await?;
+
+        info!("Position absorbed by backstop vault as {}: {}", vault_position, signature);
+
+        // The owner is settled at the takeover price, loss capped at margin
+        let takeover_price = vault.takeover_price(mark_price, position.side);
+        let price_pnl = MarginCalculator::calculate_unrealized_pnl(
+            position.side,
+            position.size,
+            takeover_price,
+            position.entry_price,
+        )?;
+        let realized = (price_pnl + position.funding_accrued).max(-position.margin);
+
+        self.record_realized_pnl(&position, position.size, takeover_price, Decimal::ZERO, realized, true, signature)
+            .await;
+        self.apply_settled_status(position, PositionStatus::Liquidated, signature)
+            .await?;
+
+        Ok(signature)
+    }
+
+    /// Unwind a position held by the backstop vault. The program settles at
+    /// the oracle price, `mark_price` is what the close is recorded at
+    #[instrument(skip_all, fields(%position_account))]
+    pub async fn close_backstop_position(
+        &self,
+        position_account: Pubkey,
+        mark_price: Decimal,
+    ) -> Result<Signature> {
+        let position = self.get_position(position_account).await?;
+
+        if !position.is_open() {
+            return Err(anyhow!("Position is not open"));
+        }
+
+        let backstop_vault = self.backstop_vault_address();
+        if position.owner != backstop_vault {
+            return Err(anyhow!("Position is not held by the backstop vault"));
+        }
+
+        // The program reads the price itself, a stale one would only fail there
+        self.fresh_price_publish_time(&position.symbol).await?;
+
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        let (config, _) = self.solana_client.derive_config_pda();
+        let (liquidator_whitelist, _) = self.solana_client.derive_liquidator_whitelist_pda();
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&DISCRIMINATOR_CLOSE_BACKSTOP_POSITION);
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(position.position_account, false),
+                AccountMeta::new(backstop_vault, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new_readonly(liquidator_whitelist, false),
+                AccountMeta::new_readonly(self.solana_client.payer_pubkey(), true),
+                AccountMeta::new_readonly(price_update_address(&position.symbol)?, false),
+            ],
+            data,
+        };
+
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&[instruction])
+            .await?;
+
+        info!("Backstop position closed on-chain: {}", signature);
+
+        let price_pnl = MarginCalculator::calculate_unrealized_pnl(
+            position.side,
+            position.size,
+            mark_price,
+            position.entry_price,
+        )?;
+        let realized = (price_pnl + position.funding_accrued).max(-position.margin);
+
+        self.record_realized_pnl(&position, position.size, mark_price, Decimal::ZERO, realized, false, signature)
+            .await;
+        self.apply_settled_status(position, PositionStatus::Closed, signature)
+            .await?;
+
+        Ok(signature)
+    }
+
+    /// Owner of positions the backstop vault took over
+    pub fn backstop_vault_address(&self) -> Pubkey {
+        self.solana_client.derive_backstop_vault_pda().0
+    }
+
+    /// Get the backstop vault from chain
+    pub async fn get_backstop_vault(&self) -> Result<BackstopVaultData> {
+        let (address, _) = self.solana_client.derive_backstop_vault_pda();
+
+        let data = self
+            .solana_client
+            .get_account_data(&address, "Backstop vault account")?;
+        let vault: OnChainBackstopVault =
+            deserialize_anchor_account(&data, &OnChainBackstopVault::DISCRIMINATOR)?;
+
+        Ok(BackstopVaultData {
+            address,
+            total_assets: Decimal::new(vault.total_assets as i64, 6),
+            total_shares: Decimal::new(vault.total_shares as i64, 6),
+            locked_margin: Decimal::new(vault.locked_margin as i64, 6),
+            realized_pnl: Decimal::new(vault.realized_pnl, 6),
+            discount_bps: vault.discount_bps,
+            open_positions: vault.position_count,
+            position_count_total: vault.position_count_total,
+            share_mint: vault.share_mint,
+        })
+    }
+
+    /// Get an LP's backstop vault account from chain, shares are the balance
+    /// of their share token account
+    pub async fn get_lp_account(&self, owner: &Pubkey) -> Result<LpAccountData> {
+        let (address, _) = self.solana_client.derive_lp_account_pda(owner);
+
+        let data = self.solana_client.get_account_data(&address, "LP account")?;
+        let lp_account: OnChainLpAccount =
+            deserialize_anchor_account(&data, &OnChainLpAccount::DISCRIMINATOR)?;
+
+        let (share_mint, _) = self.solana_client.derive_share_mint_pda();
+        let token_account = self
+            .solana_client
+            .derive_associated_token_address(owner, &share_mint);
+        let shares = self.solana_client.get_token_balance(&token_account)?;
+
+        Ok(LpAccountData {
+            owner: lp_account.owner,
+            shares: Decimal::new(shares as i64, 6),
+            cost_basis: Decimal::new(lp_account.cost_basis as i64, 6),
+        })
+    }
+
+    /// A user's defaults from chain, none until they first set them
+    pub async fn get_user_settings(&self, owner: &Pubkey) -> Result<Option<UserSettingsData>> {
+        let (address, _) = self.solana_client.derive_user_settings_pda(owner);
+
+        let data = match self.solana_client.get_account_data(&address, "User settings") {
+            Ok(data) => data,
+            Err(e) if matches!(e.downcast_ref::<Error>(), Some(Error::NotFound(_))) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let settings: OnChainUserSettings =
+            deserialize_anchor_account(&data, &OnChainUserSettings::DISCRIMINATOR)?;
+
+        Ok(Some(UserSettingsData {
+            owner: settings.owner,
+            default_slippage_bps: settings.default_slippage_bps,
+            default_leverage: settings.default_leverage,
+            margin_mode: match settings.margin_mode {
+                OnChainMarginMode::Isolated => MarginMode::Isolated,
+                OnChainMarginMode::Cross => MarginMode::Cross,
+            },
+            updated_at: chrono::DateTime::from_timestamp(settings.updated_at, 0)
+                .unwrap_or_default(),
+        }))
+    }
+
+    /// Address of a symbol's Market PDA
+    pub fn market_address(&self, symbol: &str) -> Pubkey {
+        self.solana_client.derive_market_pda(symbol).0
+    }
+
+    /// A symbol's Market from chain, none until the admin initializes it
+    pub async fn get_market(&self, symbol: &str) -> Result<Option<MarketData>> {
+        let (address, _) = self.solana_client.derive_market_pda(symbol);
+
+        let data = match self.solana_client.get_account_data(&address, "Market") {
+            Ok(data) => data,
+            Err(e) if matches!(e.downcast_ref::<Error>(), Some(Error::NotFound(_))) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let market: OnChainMarket = deserialize_anchor_account(&data, &OnChainMarket::DISCRIMINATOR)?;
+
+        Ok(Some(MarketData {
+            address,
+            symbol: market.symbol,
+            maintenance_margin_bps: market.maintenance_margin_bps,
+            min_maintenance_margin_bps: market.min_maintenance_margin_bps,
+            max_maintenance_margin_bps: market.max_maintenance_margin_bps,
+            updated_at: chrono::DateTime::from_timestamp(market.updated_at, 0).unwrap_or_default(),
+            open_cooldown_slots: market.open_cooldown_slots,
+            max_opens_per_slot: market.max_opens_per_slot,
+            rate_window_start: chrono::DateTime::from_timestamp(market.rate_window_start, 0).unwrap_or_default(),
+            rate_window_start_bps: market.rate_window_start_bps,
+        }))
+    }
+
+    /// The IDL published for the program, None until `anchor idl init` has run
+    pub async fn get_program_idl(&self) -> Result<Option<ProgramIdl>> {
+        let address = idl_address(&self.solana_client.program_id);
+
+        let data = match self.solana_client.get_account_data(&address, "IDL") {
+            Ok(data) => data,
+            Err(e) if matches!(e.downcast_ref::<Error>(), Some(Error::NotFound(_))) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        decode_idl_account(address, &data).map(Some)
+    }
+
+    /// The size and margin changes a Position account keeps on chain, oldest
+    /// first. None when the account doesn't exist or isn't a Position
+    pub async fn get_position_history(&self, position_account: &Pubkey) -> Result<Option<PositionHistoryData>> {
+        let data = match self.solana_client.get_account_data(position_account, "Position") {
+            Ok(data) => data,
+            Err(e) if matches!(e.downcast_ref::<Error>(), Some(Error::NotFound(_))) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if !data.starts_with(&OnChainPosition::DISCRIMINATOR) {
+            return Ok(None);
+        }
+        let position = deserialize_position_data(&data)?;
+
+        Ok(Some(PositionHistoryData {
+            position_account: *position_account,
+            total_changes: position.history_count,
+            changes: position
+                .changes()
+                .into_iter()
+                .map(|change| PositionChangeData {
+                    slot: change.slot,
+                    size: Decimal::new(change.size as i64, 8),
+                    margin: Decimal::new(change.margin as i64, 6),
+                    leverage: change.leverage,
+                    price: Decimal::new(change.price as i64, 6),
+                })
+                .collect(),
+        }))
+    }
+
+    /// Create a symbol's Market with the service wallet, which must be the Config admin
+    #[instrument(skip_all, fields(%symbol))]
+    pub async fn initialize_market(
+        &self,
+        symbol: &str,
+        min_maintenance_margin_bps: u16,
+        max_maintenance_margin_bps: u16,
+    ) -> Result<Signature> {
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        let (config, _) = self.solana_client.derive_config_pda();
+        let (market, _) = self.solana_client.derive_market_pda(symbol);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&DISCRIMINATOR_INITIALIZE_MARKET);
+        data.extend_from_slice(&(symbol.len() as u32).to_le_bytes());
+        data.extend_from_slice(symbol.as_bytes());
+        data.extend_from_slice(&min_maintenance_margin_bps.to_le_bytes());
+        data.extend_from_slice(&max_maintenance_margin_bps.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new(self.solana_client.payer_pubkey(), true),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data,
+        };
+
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&[instruction])
+            .await?;
+
+        info!(
+            "Market {} initialized at {}-{} bps: {}",
+            symbol, min_maintenance_margin_bps, max_maintenance_margin_bps, signature
+        );
+        Ok(signature)
+    }
+
+    /// Crank a symbol's maintenance rate with the service wallet, which must
+    /// be the Config admin. The program rejects rates outside the market's bounds
+    #[instrument(skip_all, fields(%symbol))]
+    pub async fn set_maintenance_margin(
+        &self,
+        symbol: &str,
+        maintenance_margin_bps: u16,
+    ) -> Result<Signature> {
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        let (config, _) = self.solana_client.derive_config_pda();
+        let (market, _) = self.solana_client.derive_market_pda(symbol);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&DISCRIMINATOR_SET_MAINTENANCE_MARGIN);
+        data.extend_from_slice(&maintenance_margin_bps.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new_readonly(self.solana_client.payer_pubkey(), true),
+            ],
+            data,
+        };
+
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&[instruction])
+            .await?;
+
+        info!("Market {} maintenance margin set to {} bps: {}", symbol, maintenance_margin_bps, signature);
+        Ok(signature)
+    }
+
+    /// Set a symbol's open throttle with the service wallet, which must be the
+    /// Config admin. 0 disables the cooldown or the per-slot limit
+    #[instrument(skip_all, fields(%symbol))]
+    pub async fn set_market_throttle(
+        &self,
+        symbol: &str,
+        open_cooldown_slots: u64,
+        max_opens_per_slot: u16,
+    ) -> Result<Signature> {
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        let (config, _) = self.solana_client.derive_config_pda();
+        let (market, _) = self.solana_client.derive_market_pda(symbol);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&DISCRIMINATOR_SET_MARKET_THROTTLE);
+        data.extend_from_slice(&open_cooldown_slots.to_le_bytes());
+        data.extend_from_slice(&max_opens_per_slot.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new_readonly(self.solana_client.payer_pubkey(), true),
+            ],
+            data,
+        };
+
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&[instruction])
+            .await?;
+
+        info!(
+            "Market {} throttle set to {} slot cooldown, {} opens per slot: {}",
+            symbol, open_cooldown_slots, max_opens_per_slot, signature
+        );
+        Ok(signature)
+    }
+
+    /// Grow a Position account created before the program's latest Position
+    /// fields to the current size, the service wallet paying the extra rent
+    pub async fn migrate_position(&self, position_account: &Pubkey) -> Result<Signature> {
+        let instruction = migrate_position_instruction(
+            &PROGRAM_ID.parse()?,
+            position_account,
+            &self.solana_client.payer_pubkey(),
+        );
+
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&[instruction])
+            .await?;
+
+        info!("Position {} migrated: {}", position_account, signature);
+        Ok(signature)
+    }
+
+    /// migrate_position instructions for those of `position_accounts` still
+    /// in a layout from before the program's latest Position fields, to go
+    /// ahead of an instruction that would otherwise fail to load them
+    fn migrations_for(&self, position_accounts: &[Pubkey]) -> Result<Vec<Instruction>> {
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        let accounts = self
+            .solana_client
+            .rpc_pool()
+            .read(|rpc_client| rpc_client.get_multiple_accounts_with_commitment(position_accounts, rpc_client.commitment()))
+            .map_err(|e| Error::rpc(format!("Failed to fetch positions: {}", e)))?
+            .value;
+
+        Ok(position_accounts
+            .iter()
+            .zip(accounts)
+            .filter(|(_, account)| account.as_ref().is_some_and(|account| account.data.len() < OnChainPosition::MAX_SIZE))
+            .map(|(position_account, _)| {
+                info!("Migrating position {} to the current layout first", position_account);
+                migrate_position_instruction(&program_id, position_account, &self.solana_client.payer_pubkey())
+            })
+            .collect())
+    }
+
+    /// Partial liquidation parameters from the program Config
+    pub async fn get_liquidation_config(&self) -> Result<LiquidationConfigData> {
+        let (config_account, _) = self.solana_client.derive_config_pda();
+
+        let config_data = self
+            .solana_client
+            .get_account_data(&config_account, "Config account")?;
+        let config: OnChainConfig =
+            deserialize_anchor_account(&config_data, &OnChainConfig::DISCRIMINATOR)?;
+
+        Ok(LiquidationConfigData {
+            partial_liquidation_enabled: config.partial_liquidation_enabled,
+            buffer_ratio: Decimal::from(config.liquidation_buffer_bps)
+                / Decimal::from(BPS_DENOMINATOR),
+            margin_call_buffer_ratio: Decimal::from(config.margin_call_buffer_bps)
+                / Decimal::from(BPS_DENOMINATOR),
+            margin_call_grace: chrono::Duration::seconds(config.margin_call_grace_secs as i64),
+        })
+    }
+
+    /// Whether the program's kill switch is set
+    pub async fn is_trading_paused(&self) -> Result<bool> {
+        let (config_account, _) = self.solana_client.derive_config_pda();
+
+        let config_data = self
+            .solana_client
+            .get_account_data(&config_account, "Config account")?;
+        let config: OnChainConfig =
+            deserialize_anchor_account(&config_data, &OnChainConfig::DISCRIMINATOR)?;
+
+        Ok(config.paused)
+    }
+
+    /// Taker fee per volume tier from the program Config, in basis points
+    pub async fn get_fee_schedule(&self) -> Result<[u16; FEE_TIER_COUNT]> {
+        let (config_account, _) = self.solana_client.derive_config_pda();
+
+        let config_data = self
+            .solana_client
+            .get_account_data(&config_account, "Config account")?;
+        let config: OnChainConfig =
+            deserialize_anchor_account(&config_data, &OnChainConfig::DISCRIMINATOR)?;
+
+        Ok(config.taker_fee_bps)
+    }
+
+    /// Oldest oracle price the program accepts, in seconds, 0 when unlimited
+    pub async fn get_max_price_age_secs(&self) -> Result<u32> {
+        let (config_account, _) = self.solana_client.derive_config_pda();
+
+        let config_data = self
+            .solana_client
+            .get_account_data(&config_account, "Config account")?;
+        let config: OnChainConfig =
+            deserialize_anchor_account(&config_data, &OnChainConfig::DISCRIMINATOR)?;
+
+        Ok(config.max_price_age_secs)
+    }
+
+    /// Publish time of the cached oracle price for `symbol`, checked against
+    /// the Config's max age so a trade the program would reject as
+    /// StaleOracle is never sent
+    async fn fresh_price_publish_time(&self, symbol: &str) -> Result<i64> {
+        let cached = self
+            .monitor
+            .get_cached_quote(symbol)
+            .await
+            .ok_or_else(|| Error::Oracle(format!("No price for {} yet", symbol)))?;
+
+        cached.fresh_publish_time(symbol, self.get_max_price_age_secs().await?, Utc::now())
+    }
+
+    /// An owner's fee tier from chain, none until the admin first assigns one
+    pub async fn get_user_fee_tier(&self, owner: &Pubkey) -> Result<Option<UserFeeTierData>> {
+        let (address, _) = self.solana_client.derive_fee_tier_pda(owner);
+
+        let data = match self.solana_client.get_account_data(&address, "Fee tier") {
+            Ok(data) => data,
+            Err(e) if matches!(e.downcast_ref::<Error>(), Some(Error::NotFound(_))) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let fee_tier: OnChainUserFeeTier =
+            deserialize_anchor_account(&data, &OnChainUserFeeTier::DISCRIMINATOR)?;
+
+        Ok(Some(UserFeeTierData::from(fee_tier)))
+    }
+
+    /// Every assigned fee tier, including owners who since stopped trading
+    pub async fn get_user_fee_tiers(&self) -> Result<Vec<UserFeeTierData>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new(
+                0,
+                MemcmpEncodedBytes::Bytes(OnChainUserFeeTier::DISCRIMINATOR.to_vec()),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..Default::default()
+            },
+            with_context: Some(false),
+        };
+        let program_id = self.solana_client.program_id;
+
+        let accounts = self
+            .solana_client
+            .rpc_pool()
+            .read(|rpc_client| rpc_client.get_program_accounts_with_config(&program_id, config.clone()))
+            .context("Failed to fetch fee tier accounts")?;
+
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(address, account)| {
+                match deserialize_anchor_account::<OnChainUserFeeTier>(&account.data, &OnChainUserFeeTier::DISCRIMINATOR) {
+                    Ok(fee_tier) => Some(UserFeeTierData::from(fee_tier)),
+                    Err(e) => {
+                        warn!("Skipping undecodable fee tier {}: {}", address, e);
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// The owner's fee tier PDA when they have one, or the program id Anchor
+    /// reads as the absent optional account
+    fn fee_tier_account(&self, owner: &Pubkey, exists: bool) -> AccountMeta {
+        if exists {
+            AccountMeta::new(self.solana_client.derive_fee_tier_pda(owner).0, false)
+        } else {
+            AccountMeta::new_readonly(self.solana_client.program_id, false)
+        }
+    }
+
+    /// Set the Config's taker fee per volume tier, with the service wallet as
+    /// Config admin. Rates must not rise with the tier
+    pub async fn set_fee_schedule(&self, taker_fee_bps: [u16; FEE_TIER_COUNT]) -> Result<Signature> {
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        let (config, _) = self.solana_client.derive_config_pda();
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&DISCRIMINATOR_SET_FEE_SCHEDULE);
+        for bps in taker_fee_bps {
+            data.extend_from_slice(&bps.to_le_bytes());
+        }
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(self.solana_client.payer_pubkey(), true),
+            ],
+            data,
+        };
+
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&[instruction])
+            .await?;
+
+        info!("Taker fee schedule set to {:?} bps: {}", taker_fee_bps, signature);
+        Ok(signature)
+    }
+
+    /// Assign an owner's fee tier with the service wallet as Config admin,
+    /// which pays the tier account's rent on first assignment
+    #[instrument(skip_all, fields(%owner))]
+    pub async fn set_user_fee_tier(&self, owner: &Pubkey, tier: u8, volume_30d: Decimal) -> Result<Signature> {
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+        let (config, _) = self.solana_client.derive_config_pda();
+        let (fee_tier, _) = self.solana_client.derive_fee_tier_pda(owner);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&DISCRIMINATOR_SET_USER_FEE_TIER);
+        data.push(tier);
+        data.extend_from_slice(&decimal_to_u64(volume_30d, 6)?.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(fee_tier, false),
+                AccountMeta::new_readonly(*owner, false),
+                AccountMeta::new(self.solana_client.payer_pubkey(), true),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data,
+        };
+
+        let signature = self
+            .solana_client
+            .send_priority_transaction(&[instruction])
+            .await?;
+
+        info!("Fee tier of {} set to {}: {}", owner, tier, signature);
+        Ok(signature)
+    }
+
+    /// Persist a realized PnL event, failures are logged and never fail the close
+    async fn record_open(&self, position: &Position, signature: Signature) {
+        self.append_outbox(OutboxEvent::PositionOpened(PositionOpenedEvent::new(
+            position,
+            &signature.to_string(),
+        )))
+        .await;
+
+        let Some(history_store) = &self.history_store else {
+            return;
+        };
+
+        if let Err(e) = history_store
+            .insert_position_open(position, &signature.to_string())
+            .await
+        {
+            warn!("Failed to record open for {}: {}", position.position_account, e);
+        }
+    }
+
+    /// Persist a position event for its timeline, failures are logged and never fail the caller
+    async fn record_event(&self, event: PositionEventRecord) {
+        self.append_outbox(OutboxEvent::PositionChanged(PositionChangedEvent::from(&event)))
+            .await;
+
+        let Some(history_store) = &self.history_store else {
+            return;
+        };
+
+        if let Err(e) = history_store.insert_position_event(&event).await {
+            warn!("Failed to record {:?} for {}: {}", event.kind, event.position_account, e);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn record_realized_pnl(
+        &self,
+        position: &Position,
+        size_closed: Decimal,
+        close_price: Decimal,
+        fee: Decimal,
+        amount: Decimal,
+        liquidation: bool,
+        signature: Signature,
+    ) {
+        let report_trades = self
+            .webhooks
+            .as_ref()
+            .is_some_and(|webhooks| webhooks.subscribed(WebhookEventType::TradeReport));
+        if self.history_store.is_none() && self.outbox.is_none() && !report_trades {
+            return;
+        }
+
+        let record = RealizedPnL {
+            id: uuid::Uuid::new_v4(),
+            position_account: position.position_account,
+            owner: position.owner,
+            symbol: position.symbol.clone(),
+            side: position.side,
+            size_closed,
+            entry_price: position.entry_price,
+            close_price,
+            fee,
+            funding: position.funding_accrued,
+            amount,
+            liquidation,
+            signature: signature.to_string(),
+            closed_at: Utc::now(),
+        };
+
+        if let Some(history_store) = &self.history_store {
+            if let Err(e) = history_store.insert_realized_pnl(&record).await {
+                warn!("Failed to record realized PnL for {}: {}", position.position_account, e);
+            }
+        }
+
+        let report = TradeReport::new(&record, position.opened_at);
+        let appended = self.append_outbox(OutboxEvent::TradeReport(report.clone())).await;
+
+        // Without the outbox, or when Redis refused it, send straight away
+        if let Some(webhooks) = self.webhooks.as_ref().filter(|_| report_trades && !appended) {
+            if let Err(e) = webhooks
+                .dispatch_for_owner(&position.owner, WebhookEventType::TradeReport, &report)
+                .await
+            {
+                warn!("Failed to send trade report for {}: {}", position.position_account, e);
+            }
+        }
+    }
+
+    /// Append to the outbox if there is one, failures are logged and never
+    /// fail the caller. Whether the event made it in
+    async fn append_outbox(&self, event: OutboxEvent) -> bool {
+        let Some(outbox) = &self.outbox else {
+            return false;
+        };
+
+        match outbox.append(&event).await {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("Failed to append event to outbox: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Reflect a close/liquidation in the monitor cache right away, tracked so it
+    /// is reverted if the transaction gets rolled back before finalizing
+    async fn apply_settled_status(
+        &self,
+        position: Position,
+        status: PositionStatus,
+        signature: Signature,
+    ) -> Result<()> {
+        let mut settled = position.clone();
+        settled.status = status;
+        settled.closed_at = Some(Utc::now());
+        settled.last_update = Utc::now();
+
+        self.monitor.replace_position(settled).await?;
+        self.monitor
+            .track_mutation(signature, position.position_account, Some(position))
+            .await;
+
+        Ok(())
+    }
+
+    /// Shrink the cached position the way the program did, tracked like a settle
+    async fn apply_partial_liquidation(
+        &self,
+        position: Position,
+        size_closed: Decimal,
+        funding: Decimal,
+        realized: Decimal,
+        signature: Signature,
+    ) -> Result<()> {
+        let mut remaining = position.clone();
+        remaining.size -= size_closed;
+        remaining.margin += realized.min(Decimal::ZERO);
+        remaining.funding_accrued -= funding;
+        remaining.realized_pnl += realized;
+        // The program lifts a margin call with a partial liquidation
+        remaining.status = PositionStatus::Open;
+        remaining.margin_call_at = None;
+        remaining.last_update = Utc::now();
+
+        self.monitor.replace_position(remaining).await?;
+        self.monitor
+            .track_mutation(signature, position.position_account, Some(position))
+            .await;
+
+        Ok(())
+    }
+
+    /// Get position from monitor's shared state
+    pub async fn get_position(&self, position_account: Pubkey) -> Result<Position> {
+        self.monitor
+            .get_position(position_account)
+            .await
+            .ok_or_else(|| Error::NotFound(format!("Position {}", position_account)).into())
+    }
+
+    /// Get user positions from monitor
+    pub async fn get_user_positions(&self, owner: &Pubkey) -> Result<Vec<Position>> {
+        self.monitor.get_user_positions(owner).await
+    }
+
+    /// Get open positions from monitor
+    pub async fn get_open_positions(&self, owner: &Pubkey) -> Result<Vec<Position>> {
+        let positions = self.get_user_positions(owner).await?;
+        Ok(positions.into_iter().filter(|p| p.is_open()).collect())
+    }
+
+    /// Get user account from chain
+    pub async fn get_user_account(&self, owner: &Pubkey) -> Result<UserAccountData> {
+        let program_id: Pubkey = PROGRAM_ID.parse()?;
+
+        let (user_account, _) =
+            Pubkey::find_program_address(&[b"user", owner.as_ref()], &program_id);
+
+        let account_data = self
+            .solana_client
+            .get_account_data(&user_account, "User account")?;
+
+        let data = &account_data[8..];
+
+        let owner_bytes: [u8; 32] = data[0..32].try_into()?;
+        let owner_pubkey = Pubkey::new_from_array(owner_bytes);
+
+        let total_collateral = u64::from_le_bytes(data[32..40].try_into()?);
+        let locked_collateral = u64::from_le_bytes(data[40..48].try_into()?);
+        let total_pnl = i64::from_le_bytes(data[48..56].try_into()?);
+        let position_count = u32::from_le_bytes(data[56..60].try_into()?);
+        let position_count_total = u32::from_le_bytes(data[60..64].try_into()?);
+        let bump = data[64];
+
+        Ok(UserAccountData {
+            owner: owner_pubkey,
+            total_collateral,
+            locked_collateral,
+            total_pnl,
+            position_count,
+            position_count_total,
+            bump,
+        })
+    }
+
+    /// Get the liquidator whitelist and its enforcement flag from chain
+    pub async fn get_liquidator_set(&self) -> Result<LiquidatorSetData> {
+        let (config_account, _) = self.solana_client.derive_config_pda();
+        let (whitelist_account, _) = self.solana_client.derive_liquidator_whitelist_pda();
+
+        let config_data = self
+            .solana_client
+            .get_account_data(&config_account, "Config account")?;
+        let config: OnChainConfig =
+            deserialize_anchor_account(&config_data, &OnChainConfig::DISCRIMINATOR)?;
+
+        let whitelist_data = self
+            .solana_client
+            .get_account_data(&whitelist_account, "Liquidator whitelist account")?;
+        let whitelist: OnChainLiquidatorWhitelist = deserialize_anchor_account(
+            &whitelist_data,
+            &OnChainLiquidatorWhitelist::DISCRIMINATOR,
+        )?;
+
+        Ok(LiquidatorSetData {
+            admin: config.admin,
+            whitelist_enabled: config.liquidator_whitelist_enabled,
+            liquidators: whitelist.liquidators,
+            partial_liquidation_enabled: config.partial_liquidation_enabled,
+            liquidation_buffer_bps: config.liquidation_buffer_bps,
+        })
+    }
+
+    /// Get next position index from on-chain user account
+    async fn get_next_position_index(&self, owner: &Pubkey) -> Result<u32> {
+        match self.get_user_account(owner).await {
+            Ok(user_account) => {
+                info!(
+                    "Fetched position_count_total from chain: {}",
+                    user_account.position_count_total
+                );
+                Ok(user_account.position_count_total)
+            }
+            Err(e) => {
+                warn!(
+                    "Could not fetch user account (might not be initialized): {}",
+                    e
+                );
+                Ok(0)
+            }
+        }
+    }
+    
+    /// Get statistics from monitor
+    pub async fn get_statistics(&self) -> Result<PositionStats> {
+        let monitor_stats = self.monitor.get_statistics().await;
+        
+        Ok(PositionStats {
+            total_positions: monitor_stats.total_positions,
+            open_positions: monitor_stats.open_positions,
+            closed_positions: monitor_stats.total_positions - monitor_stats.open_positions,
+            total_unrealized_pnl: monitor_stats.total_unrealized_pnl,
+            total_realized_pnl: Decimal::ZERO, // Monitor doesn't track realized PnL
+        })
+    }
+}
+
+/// Open rejected because the position index wasn't the user's next one
+fn is_position_index_mismatch(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::Program { error, .. }) if error.code == "PositionIndexMismatch"
+    )
+}
+
+/// liquidate_many instruction signed by `liquidator`, fully closing each of
+/// `positions` on `symbol` at the price of the symbol's Pyth price update account
+pub fn liquidate_many_instruction(
+    program_id: &Pubkey,
+    symbol: &str,
+    positions: &[Position],
+    liquidator: &Pubkey,
+) -> Result<Instruction> {
+    let (config, _) = Pubkey::find_program_address(&[b"config"], program_id);
+    let (liquidator_whitelist, _) =
+        Pubkey::find_program_address(&[b"liquidator_whitelist"], program_id);
+    let (market, _) = Pubkey::find_program_address(&[b"market", symbol.as_bytes()], program_id);
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&DISCRIMINATOR_LIQUIDATE_MANY);
+    data.extend_from_slice(&(symbol.len() as u32).to_le_bytes());
+    data.extend_from_slice(symbol.as_bytes());
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(config, false),
+        AccountMeta::new_readonly(liquidator_whitelist, false),
+        AccountMeta::new_readonly(*liquidator, true),
+        AccountMeta::new_readonly(market, false),
+        AccountMeta::new_readonly(price_update_address(symbol)?, false),
+    ];
+    // Remaining accounts, a (position, user_account) pair per position
+    for position in positions {
+        let (user_account, _) =
+            Pubkey::find_program_address(&[b"user", position.owner.as_ref()], program_id);
+        accounts.push(AccountMeta::new(position.position_account, false));
+        accounts.push(AccountMeta::new(user_account, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// migrate_position instruction growing `position_account` to the program's
+/// current Position layout, `payer` covering the extra rent
+pub fn migrate_position_instruction(program_id: &Pubkey, position_account: &Pubkey, payer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*position_account, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: DISCRIMINATOR_MIGRATE_POSITION.to_vec(),
+    }
+}
+
+/// liquidate_position instruction signed by `liquidator`, closing `close_bps`
+/// of the position at the price of the symbol's Pyth price update account
+pub fn liquidate_position_instruction(
+    program_id: &Pubkey,
+    position: &Position,
+    liquidator: &Pubkey,
+    close_bps: u16,
+) -> Result<Instruction> {
+    let (user_account, _) =
+        Pubkey::find_program_address(&[b"user", position.owner.as_ref()], program_id);
+    let (config, _) = Pubkey::find_program_address(&[b"config"], program_id);
+    let (liquidator_whitelist, _) =
+        Pubkey::find_program_address(&[b"liquidator_whitelist"], program_id);
+    let (market, _) =
+        Pubkey::find_program_address(&[b"market", position.symbol.as_bytes()], program_id);
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&DISCRIMINATOR_LIQUIDATE_POSITION);
+    data.extend_from_slice(&close_bps.to_le_bytes());
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(position.position_account, false),
+            AccountMeta::new(user_account, false),
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new_readonly(liquidator_whitelist, false),
+            AccountMeta::new_readonly(*liquidator, true),
+            AccountMeta::new_readonly(market, false),
+            AccountMeta::new_readonly(price_update_address(&position.symbol)?, false),
+        ],
+        data,
+    })
+}
+
+/// Helper: Convert Decimal to u64 with precision
+fn decimal_to_u64(decimal: Decimal, precision: u32) -> Result<u64> {
     let multiplier = 10u64.pow(precision);
     let scaled = decimal
         .checked_mul(Decimal::from(multiplier))
@@ -428,6 +2199,155 @@ pub struct UserAccountData {
     pub bump: u8,
 }
 
+// A user's defaults for new positions
+#[derive(Debug, Clone)]
+pub struct UserSettingsData {
+    pub owner: Pubkey,
+    pub default_slippage_bps: u16,
+    pub default_leverage: u16,
+    pub margin_mode: MarginMode,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+// An owner's assigned fee tier, amounts in collateral units
+#[derive(Debug, Clone)]
+pub struct UserFeeTierData {
+    pub owner: Pubkey,
+    pub tier: u8,
+    /// Taker volume the tier was assigned on
+    pub volume_30d: Decimal,
+    /// Taker fees paid since the account was created
+    pub fees_paid: Decimal,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+impl From<OnChainUserFeeTier> for UserFeeTierData {
+    fn from(fee_tier: OnChainUserFeeTier) -> Self {
+        Self {
+            owner: fee_tier.owner,
+            tier: fee_tier.tier,
+            volume_30d: Decimal::new(fee_tier.volume_30d as i64, 6),
+            fees_paid: Decimal::new(fee_tier.fees_paid as i64, 6),
+            updated_at: chrono::DateTime::from_timestamp(fee_tier.updated_at, 0).unwrap_or_default(),
+        }
+    }
+}
+
+// A symbol's on-chain risk parameters
+#[derive(Debug, Clone)]
+pub struct MarketData {
+    pub address: Pubkey,
+    pub symbol: String,
+    pub maintenance_margin_bps: u16,
+    pub min_maintenance_margin_bps: u16,
+    pub max_maintenance_margin_bps: u16,
+    pub updated_at: chrono::DateTime<Utc>,
+    pub open_cooldown_slots: u64,
+    pub max_opens_per_slot: u16,
+    /// Start of the window the maintenance crank's move is capped over
+    pub rate_window_start: chrono::DateTime<Utc>,
+    pub rate_window_start_bps: u16,
+}
+
+// A Position's on-chain change history
+#[derive(Debug, Clone)]
+pub struct PositionHistoryData {
+    pub position_account: Pubkey,
+    /// Changes ever recorded, more than `changes` once the oldest were overwritten
+    pub total_changes: u32,
+    pub changes: Vec<PositionChangeData>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PositionChangeData {
+    pub slot: u64,
+    pub size: Decimal,
+    pub margin: Decimal,
+    pub leverage: u16,
+    pub price: Decimal,
+}
+
+// Liquidator whitelist state
+#[derive(Debug, Clone)]
+pub struct LiquidatorSetData {
+    pub admin: Pubkey,
+    pub whitelist_enabled: bool,
+    pub liquidators: Vec<Pubkey>,
+    pub partial_liquidation_enabled: bool,
+    pub liquidation_buffer_bps: u16,
+}
+
+// Backstop vault state, amounts in collateral units
+#[derive(Debug, Clone)]
+pub struct BackstopVaultData {
+    pub address: Pubkey,
+    pub total_assets: Decimal,
+    pub total_shares: Decimal,
+    pub locked_margin: Decimal,
+    pub realized_pnl: Decimal,
+    pub discount_bps: u16,
+    pub open_positions: u32,
+    pub position_count_total: u32,
+    pub share_mint: Pubkey,
+}
+
+impl BackstopVaultData {
+    /// Collateral per share, 1 for an empty vault like the program's first deposit
+    pub fn share_price(&self) -> Decimal {
+        if self.total_shares.is_zero() {
+            return Decimal::ONE;
+        }
+        self.total_assets / self.total_shares
+    }
+
+    /// Share of assets backing absorbed positions
+    pub fn utilization(&self) -> Decimal {
+        if self.total_assets.is_zero() {
+            return Decimal::ZERO;
+        }
+        (self.locked_margin / self.total_assets).min(Decimal::ONE)
+    }
+
+    pub fn available_assets(&self) -> Decimal {
+        (self.total_assets - self.locked_margin).max(Decimal::ZERO)
+    }
+
+    /// Price the vault takes a liquidated position over at, as the program computes it
+    pub fn takeover_price(&self, mark_price: Decimal, side: Side) -> Decimal {
+        let discount = Decimal::from(self.discount_bps) / Decimal::from(BPS_DENOMINATOR);
+        match side {
+            Side::Long => mark_price * (Decimal::ONE - discount),
+            Side::Short => mark_price * (Decimal::ONE + discount),
+        }
+    }
+}
+
+// LP holding in the backstop vault
+#[derive(Debug, Clone)]
+pub struct LpAccountData {
+    pub owner: Pubkey,
+    pub shares: Decimal,
+    pub cost_basis: Decimal,
+}
+
+// Partial liquidation parameters
+#[derive(Debug, Clone)]
+pub struct LiquidationConfigData {
+    pub partial_liquidation_enabled: bool,
+    /// Margin ratio a partial liquidation must restore above maintenance
+    pub buffer_ratio: Decimal,
+    /// Margin ratio above maintenance below which a margin call is issued
+    pub margin_call_buffer_ratio: Decimal,
+    /// Wait after a margin call before liquidation, zero disables calls
+    pub margin_call_grace: chrono::Duration,
+}
+
+impl LiquidationConfigData {
+    pub fn margin_calls_enabled(&self) -> bool {
+        self.margin_call_grace > chrono::Duration::zero()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PositionStats {
     pub total_positions: usize,
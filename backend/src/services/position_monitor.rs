@@ -1,36 +1,56 @@
-use crate::domain::{Position, Side};
-use crate::infrastructure::{OracleClient, SolanaClient};
+use crate::domain::{rank_by_severity, symbols, PnLSnapshot, Position, PositionStatus, Risk, Side, UnderwaterPosition};
+use crate::infrastructure::{
+    AccountStreamConfig, AccountStreamEvent, CachedQuote, HistoryStore, LatencyMetrics, LatencyStage,
+    LatencyStats, LogStreamEvent, PositionEventKind, PositionEventRecord, PriceFeedMetrics, PriceFeedStats,
+    PriceSource, PriceTick, ProgramAccountStream, ProgramLogStream,
+    BreakerStats, CircuitBreakerConfig, CircuitBreakers, RpcCallStats, RpcEndpointHealth, RpcMetrics, RpcPool, SolanaClient, TickOrder,
+};
 use crate::services::on_chain_types::{deserialize_position_account, OnChainPosition};
+use crate::services::position_scan::{
+    fingerprint, ScanConfig, ScanProgress, ScanState, POSITION_MUTABLE_LEN, POSITION_MUTABLE_OFFSET,
+};
 use crate::services::{
-    LiquidationAlert, LiquidationAlertConfig, LiquidationAlertService, MarginCalculator,
+    default_margin_ratio_tiers, positions_in_logs, select_evictions, AlertNotifiers, DomainEvent, EventBus, ExternalPositionService, LeaderElection,
+    LiquidationAlert, LiquidationAlertConfig, LiquidationAlertService, LocalEventBus, MarginCalculator, MarginRatioAlerts, MutationTracker, MutationTrackerConfig,
+    PortfolioTracker, PortfolioUpdate, PositionAlert, PositionAlertService, PriceAlertService, RetentionConfig, RetentionMetrics, RetentionStats, SignatureState,
+    StrategyService, SymbolConfigs, SymbolOverrides, SymbolSettings,
 };
 use anyhow::{anyhow, Context, Result};
-use chrono::Utc;
+use futures::StreamExt;
+use chrono::{DateTime, Utc};
 use redis::AsyncCommands;
 use rust_decimal::Decimal;
-use solana_account_decoder::UiAccountEncoding;
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
 use solana_client::{
-    rpc_client::RpcClient,
-    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionConfig},
     rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
 };
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
-use std::collections::HashMap;
+use solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature,
+};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tokio::time::{interval, Duration};
-use tracing::{debug, error, info};
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::{interval, interval_at, Duration};
+use tracing::{debug, error, field, info, info_span, instrument, warn, Instrument};
 
 /// Price update event
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PriceUpdate {
     pub symbol: String,
     pub price: Decimal,
+    /// When the price was fetched
     pub timestamp: chrono::DateTime<Utc>,
+    /// Oracle publish time, none from replicas that predate it
+    #[serde(default)]
+    pub publish_time: Option<chrono::DateTime<Utc>>,
 }
 
 /// Position update event
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PositionUpdate {
     pub position_account: Pubkey,
     pub symbol: String,
@@ -39,16 +59,62 @@ pub struct PositionUpdate {
     pub entry_price: Decimal,
     pub mark_price: Decimal,
     pub unrealized_pnl: Decimal,
+    #[serde(default)]
+    pub margin: Decimal,
+    /// Signed, negative once losses exceed the margin
     pub margin_ratio: Decimal,
+    #[serde(default)]
+    pub liquidation_price: Decimal,
+    /// Liquidation price net of accrued funding and the close fee
+    #[serde(default)]
+    pub effective_liquidation_price: Option<Decimal>,
     pub timestamp: chrono::DateTime<Utc>,
 }
 
+const SNAPSHOT_KEY: &str = "monitor:snapshot";
+
+// Recent ticks kept per latency stage for percentiles
+const LATENCY_WINDOW: usize = 4096;
+
+// How often admin edits made on other replicas are picked up
+const SYMBOL_CONFIG_RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+// Circuit breaker of the refresher's getProgramAccounts scans
+const SCAN_BREAKER_KEY: &str = "rpc:scan";
+
+/// Circuit breaker of a symbol's oracle quotes
+fn oracle_breaker_key(symbol: &str) -> String {
+    format!("hermes:{}", symbol)
+}
+
 /// Monitoring configuration
 #[derive(Debug, Clone)]
 pub struct MonitorConfig {
     pub pnl_update_interval_ms: u64,
     pub position_refresh_interval_ms: u64,
+    pub snapshot_interval_ms: u64,
+    pub pnl_snapshot_interval_ms: u64,
     pub maintenance_margin_ratio: Decimal,
+    pub retention: RetentionConfig,
+    pub scan: ScanConfig,
+    /// Backstop polling interval while the account stream is connected
+    pub streaming_refresh_interval_ms: u64,
+    /// Oracle publish times further apart than this count as a feed gap
+    pub price_gap_threshold_ms: i64,
+    /// Oracle response to alert broadcast, slower ticks are logged
+    pub alert_latency_budget_ms: u64,
+    /// Per-symbol cadence and thresholds, the admin API can change them at runtime
+    pub symbols: HashMap<String, SymbolOverrides>,
+    /// Fee on closing notional counted in the effective liquidation price,
+    /// zero while the program charges none
+    pub close_fee_rate: Decimal,
+    /// Backoff of Hermes quotes per symbol and of chain scans after repeated failures
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Multiples of the maintenance ratio whose crossing raises a margin
+    /// ratio alert, empty turns them off
+    pub margin_ratio_alert_tiers: Vec<Decimal>,
+    /// Cache older than this, by its last chain sync or snapshot, is not ready
+    pub max_sync_lag_secs: i64,
 }
 
 impl Default for MonitorConfig {
@@ -56,16 +122,61 @@ impl Default for MonitorConfig {
         Self {
             pnl_update_interval_ms: 2000,
             position_refresh_interval_ms: 2000,
+            snapshot_interval_ms: 30_000,
+            pnl_snapshot_interval_ms: 300_000,
             maintenance_margin_ratio: Decimal::from_str_exact("0.025").unwrap(),
+            retention: RetentionConfig::default(),
+            scan: ScanConfig::default(),
+            streaming_refresh_interval_ms: 60_000,
+            price_gap_threshold_ms: 5_000,
+            alert_latency_budget_ms: 250,
+            symbols: HashMap::new(),
+            close_fee_rate: Decimal::ZERO,
+            circuit_breaker: CircuitBreakerConfig::default(),
+            margin_ratio_alert_tiers: default_margin_ratio_tiers(),
+            max_sync_lag_secs: 120,
         }
     }
 }
 
+/// Serialized monitor state, lets a cold node serve reads before its first chain scan
+#[derive(Debug, Serialize, Deserialize)]
+struct MonitorSnapshot {
+    taken_at: DateTime<Utc>,
+    positions: Vec<Position>,
+    positions_by_asset: HashMap<String, Vec<Pubkey>>,
+    positions_by_user: Vec<(Pubkey, Vec<Pubkey>)>,
+}
+
+/// Where the in-memory state came from, used by the readiness probe
+#[derive(Debug, Clone, Default)]
+pub struct SyncStatus {
+    /// Time of the snapshot last restored or saved by this node
+    pub snapshot_taken_at: Option<DateTime<Utc>>,
+    pub last_chain_sync: Option<DateTime<Utc>>,
+    /// Chunked full scan in progress, none between scans
+    pub scan_progress: Option<ScanProgress>,
+}
+
+impl SyncStatus {
+    /// Age of the cache, by the newer of its last chain sync and snapshot.
+    /// None until either has happened
+    pub fn lag(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        self.snapshot_taken_at
+            .max(self.last_chain_sync)
+            .map(|synced_at| now - synced_at)
+    }
+
+    pub fn is_ready(&self, now: DateTime<Utc>, max_lag: chrono::Duration) -> bool {
+        self.lag(now).is_some_and(|lag| lag <= max_lag)
+    }
+}
+
 /// Position Monitor
 pub struct PositionMonitor {
     solana_client: Arc<SolanaClient>,
-    oracle_client: Arc<RwLock<OracleClient>>,
-    rpc_client: Arc<RpcClient>,
+    price_source: Arc<dyn PriceSource>,
+    rpc_pool: Arc<RpcPool>,
     config: MonitorConfig,
     redis_client: redis::Client,
 
@@ -78,178 +189,1160 @@ pub struct PositionMonitor {
     /// User-based lookup: owner -> Vec<position_account>
     positions_by_user: Arc<RwLock<HashMap<Pubkey, Vec<Pubkey>>>>,
 
-    position_update_tx: broadcast::Sender<PositionUpdate>,
-    price_update_tx: broadcast::Sender<PriceUpdate>,
+    /// Prices, PnL updates and alerts, for the WebSocket rooms, GraphQL and keepers
+    events: Arc<dyn EventBus>,
     liquidation_service: Arc<LiquidationAlertService>,
+    /// Margin ratio tiers alerted per position, shared with the price-distance path
+    margin_ratio_alerts: Arc<MarginRatioAlerts>,
+    /// Per-owner totals the PnL pass keeps for portfolio updates
+    portfolios: Arc<PortfolioTracker>,
+    /// User alert rules, evaluated by the leader after each PnL pass
+    position_alerts: Arc<PositionAlertService>,
+    /// Standalone price levels, checked by the leader on each price update
+    price_alerts: Arc<PriceAlertService>,
+    /// Users' named position groups
+    strategies: Arc<StrategyService>,
+    /// Users' off-platform positions
+    external_positions: Arc<ExternalPositionService>,
     running: Arc<RwLock<bool>>,
+
+    /// When set, write-side tasks only run while this node holds the lease
+    leader_election: Option<Arc<LeaderElection>>,
+
+    sync_status: Arc<RwLock<SyncStatus>>,
+
+    /// Cache changes waiting for their transactions to finalize
+    mutations: Arc<MutationTracker>,
+
+    /// Where PnL snapshots and evicted closed positions are persisted, none
+    /// disables snapshots and drops evicted positions
+    history_store: Option<Arc<dyn HistoryStore>>,
+
+    retention_metrics: Arc<RetentionMetrics>,
+
+    /// Fingerprints from the last chain scan, owned by the refresher
+    scan_state: Arc<Mutex<ScanState>>,
+    rpc_metrics: Arc<RpcMetrics>,
+    price_feed_metrics: Arc<PriceFeedMetrics>,
+    latency_metrics: Arc<LatencyMetrics>,
+    symbol_configs: Arc<SymbolConfigs>,
+    /// Keyed by `oracle_breaker_key` and `SCAN_BREAKER_KEY`
+    breakers: Arc<CircuitBreakers>,
+
+    /// programSubscribe feed, none polls only
+    account_stream: Option<AccountStreamConfig>,
+    stream_connected: Arc<AtomicBool>,
+    /// logsSubscribe feed for targeted refetches, none waits for the scan
+    transaction_watch: Option<AccountStreamConfig>,
 }
 
 impl PositionMonitor {
     pub fn new(
         solana_client: Arc<SolanaClient>,
-        oracle_client: Arc<RwLock<OracleClient>>,
+        price_source: Arc<dyn PriceSource>,
         config: MonitorConfig,
         redis_url: String,
     ) -> Result<Self> {
-        let (position_update_tx, _) = broadcast::channel(1000);
-        let (price_update_tx, _) = broadcast::channel(100);
+        let events: Arc<dyn EventBus> = Arc::new(LocalEventBus::new());
 
         let redis_client =
             redis::Client::open(redis_url.clone()).context("Failed to create Redis client")?;
 
-        let (liquidation_service, _alert_rx) =
-            LiquidationAlertService::new(redis_url, LiquidationAlertConfig::default())?;
+        let latency_metrics = Arc::new(LatencyMetrics::new(
+            LATENCY_WINDOW,
+            Duration::from_millis(config.alert_latency_budget_ms),
+        ));
+
+        let alert_config = LiquidationAlertConfig::default();
+        let symbol_configs = Arc::new(SymbolConfigs::new(
+            redis_client.clone(),
+            SymbolSettings {
+                pnl_update_interval_ms: config.pnl_update_interval_ms,
+                maintenance_margin_ratio: config.maintenance_margin_ratio,
+                alert_threshold_pct: alert_config.alert_threshold_pct,
+                max_open_interest: None,
+            },
+            &config.symbols,
+        ));
+
+        let positions = Arc::new(RwLock::new(HashMap::new()));
+        let margin_ratio_alerts = Arc::new(MarginRatioAlerts::new(config.margin_ratio_alert_tiers.clone()));
+        let liquidation_service =
+            LiquidationAlertService::new(redis_url, alert_config, Arc::clone(&events))?
+            .with_latency_metrics(Arc::clone(&latency_metrics))
+            .with_symbol_configs(Arc::clone(&symbol_configs))
+            .with_positions(Arc::clone(&positions))
+            .with_margin_ratio_alerts(Arc::clone(&margin_ratio_alerts));
+
+        let price_feed_metrics = Arc::new(PriceFeedMetrics::new(chrono::Duration::milliseconds(
+            config.price_gap_threshold_ms,
+        )));
 
         Ok(Self {
-            rpc_client: Arc::new(RpcClient::new(solana_client.rpc_url.clone())),
+            breakers: Arc::new(CircuitBreakers::new(config.circuit_breaker)),
+            rpc_pool: Arc::clone(solana_client.rpc_pool()),
             solana_client,
-            oracle_client,
+            price_source,
             config,
+            position_alerts: Arc::new(PositionAlertService::new(
+                redis_client.clone(),
+                Arc::clone(&events),
+            )),
+            price_alerts: Arc::new(PriceAlertService::new(redis_client.clone(), Arc::clone(&events))),
+            strategies: Arc::new(StrategyService::new(redis_client.clone())),
+            external_positions: Arc::new(ExternalPositionService::new(redis_client.clone())),
             redis_client,
-            positions: Arc::new(RwLock::new(HashMap::new())),
+            positions,
             positions_by_asset: Arc::new(RwLock::new(HashMap::new())),
             positions_by_user: Arc::new(RwLock::new(HashMap::new())),
-            position_update_tx,
-            price_update_tx,
+            events,
             liquidation_service: Arc::new(liquidation_service),
+            margin_ratio_alerts,
+            portfolios: Arc::new(PortfolioTracker::new()),
             running: Arc::new(RwLock::new(false)),
+            leader_election: None,
+            sync_status: Arc::new(RwLock::new(SyncStatus::default())),
+            mutations: Arc::new(MutationTracker::new(MutationTrackerConfig::default())),
+            history_store: None,
+            retention_metrics: Arc::new(RetentionMetrics::default()),
+            scan_state: Arc::new(Mutex::new(ScanState::default())),
+            rpc_metrics: Arc::new(RpcMetrics::default()),
+            price_feed_metrics,
+            latency_metrics,
+            symbol_configs,
+            account_stream: None,
+            stream_connected: Arc::new(AtomicBool::new(false)),
+            transaction_watch: None,
         })
     }
 
-    pub fn subscribe_positions(&self) -> broadcast::Receiver<PositionUpdate> {
-        self.position_update_tx.subscribe()
+    /// Gate write-side tasks on leadership when running multiple replicas
+    pub fn with_leader_election(mut self, leader_election: Arc<LeaderElection>) -> Self {
+        self.leader_election = Some(leader_election);
+        self
+    }
+
+    /// Persist PnL snapshots every `pnl_snapshot_interval_ms`
+    pub fn with_history_store(mut self, history_store: Arc<dyn HistoryStore>) -> Self {
+        self.history_store = Some(history_store);
+        self
+    }
+
+    /// Take account changes from programSubscribe, polling drops to
+    /// `streaming_refresh_interval_ms` while the subscription is up
+    pub fn with_account_stream(mut self, config: AccountStreamConfig) -> Self {
+        self.account_stream = Some(config);
+        self
+    }
+
+    /// Refetch the positions a program transaction touched as soon as
+    /// logsSubscribe reports it, so changes made without the backend show
+    /// up before the next scan
+    pub fn with_transaction_watch(mut self, config: AccountStreamConfig) -> Self {
+        self.transaction_watch = Some(config);
+        self
+    }
+
+    /// Ratio below which the monitor raises liquidation alerts for `symbol`
+    pub fn maintenance_margin_ratio(&self, symbol: &str) -> Decimal {
+        self.symbol_configs.settings(symbol).maintenance_margin_ratio
+    }
+
+    /// Per-symbol overrides of the monitor config
+    pub fn symbol_configs(&self) -> &Arc<SymbolConfigs> {
+        &self.symbol_configs
+    }
+
+    /// Whether this node should run keeper tasks, always true for a single node
+    pub fn is_leader(&self) -> bool {
+        self.leader_election
+            .as_ref()
+            .is_none_or(|election| election.is_leader())
+    }
+
+    /// Sinks liquidation alerts are published to, register more at startup
+    pub fn alert_notifiers(&self) -> &Arc<AlertNotifiers> {
+        self.liquidation_service.notifiers()
+    }
+
+    /// Bus this node's monitor publishes prices, PnL updates and alerts on
+    pub fn events(&self) -> &Arc<dyn EventBus> {
+        &self.events
+    }
+
+    /// Rule store behind the position alert endpoints
+    pub fn position_alerts(&self) -> &Arc<PositionAlertService> {
+        &self.position_alerts
+    }
+
+    /// Group store behind the strategy endpoints
+    pub fn strategies(&self) -> &Arc<StrategyService> {
+        &self.strategies
+    }
+
+    /// Off-platform positions users import for the consolidated risk view
+    pub fn external_positions(&self) -> &Arc<ExternalPositionService> {
+        &self.external_positions
+    }
+
+    /// Store behind the /alerts/price endpoints
+    pub fn price_alerts(&self) -> &Arc<PriceAlertService> {
+        &self.price_alerts
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        let mut running = self.running.write().await;
+        if *running {
+            return Err(anyhow!("Monitor already running"));
+        }
+        *running = true;
+        drop(running);
+
+        info!("Starting position monitor");
+
+        self.spawn_price_monitor();
+        self.spawn_position_refresher();
+        self.spawn_symbol_config_reloader();
+        self.spawn_pnl_updater();
+        self.spawn_snapshotter();
+        self.spawn_mutation_verifier();
+        self.spawn_closed_position_evictor();
+
+        if let Some(config) = self.account_stream.clone() {
+            self.spawn_account_subscriber(config);
+        }
+
+        if let Some(config) = self.transaction_watch.clone() {
+            self.spawn_transaction_watcher(config);
+        }
+
+        Ok(())
+    }
+
+    pub async fn stop(&self) {
+        let mut running = self.running.write().await;
+        *running = false;
+        info!("Position monitor stopped");
+    }
+
+    fn spawn_price_monitor(&self) {
+        let monitor = self.clone_for_task();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(1000));
+
+            loop {
+                if !*monitor.running.read().await {
+                    break;
+                }
+
+                ticker.tick().await;
+
+                let symbols = monitor.price_source.get_symbols().await;
+                let tick_span = info_span!("price_tick", symbols = symbols.len());
+
+                async {
+                    let mut ticks = Vec::with_capacity(symbols.len());
+
+                    for symbol in symbols {
+                        let breaker_key = oracle_breaker_key(&symbol);
+                        if !monitor.breakers.allow(&breaker_key, std::time::Instant::now()) {
+                            debug!("Skipping {} quote, circuit breaker open", symbol);
+                            continue;
+                        }
+
+                        match monitor.price_source.fetch_quote(&symbol).await {
+                            Ok(quote) => {
+                                monitor.breakers.record_success(&breaker_key);
+                                let received_at = std::time::Instant::now();
+                                let price = quote.price;
+
+                                match monitor.price_feed_metrics.record(&symbol, quote.publish_time) {
+                                    TickOrder::Gap(gap) => warn!(
+                                        "Price feed gap for {}: {}ms between publish times",
+                                        symbol,
+                                        gap.num_milliseconds()
+                                    ),
+                                    TickOrder::OutOfOrder => warn!(
+                                        "Out-of-order price for {} published at {}",
+                                        symbol, quote.publish_time
+                                    ),
+                                    TickOrder::InOrder | TickOrder::Repeated => {}
+                                }
+
+                                let update = PriceUpdate {
+                                    symbol: symbol.clone(),
+                                    price,
+                                    timestamp: Utc::now(),
+                                    publish_time: Some(quote.publish_time),
+                                };
+
+                                info!("Price update: {} = {}", symbol, price);
+
+                                ticks.push(PriceTick {
+                                    symbol: symbol.clone(),
+                                    price,
+                                    conf: Some(quote.conf),
+                                    publish_time: Some(quote.publish_time),
+                                    recorded_at: update.timestamp,
+                                });
+
+                                monitor.events.publish(DomainEvent::Price(update));
+
+                                if !monitor.is_leader() {
+                                    continue;
+                                }
+
+                                if let Err(e) = monitor
+                                    .liquidation_service
+                                    .check_liquidations_for_price_update(&symbol, price, received_at)
+                                    .await
+                                {
+                                    error!("Failed to check liquidations for {}: {}", symbol, e);
+                                }
+
+                                if let Err(e) = monitor
+                                    .price_alerts
+                                    .check_price_update(&symbol, price)
+                                    .await
+                                {
+                                    error!("Failed to check price alerts for {}: {}", symbol, e);
+                                }
+                            }
+                            Err(e) => {
+                                error!("Failed to fetch price for {}: {}", symbol, e);
+                                if monitor.breakers.record_failure(&breaker_key, std::time::Instant::now()) {
+                                    warn!("Circuit breaker opened for {} quotes", symbol);
+                                }
+                            }
+                        }
+                    }
+
+                    // Archived for audits of liquidation prices and offline replays
+                    if let Some(history_store) = &monitor.history_store {
+                        if monitor.is_leader() && !ticks.is_empty() {
+                            if let Err(e) = history_store.insert_price_ticks(&ticks).await {
+                                error!("Failed to persist price ticks: {}", e);
+                            }
+                        }
+                    }
+                }
+                .instrument(tick_span)
+                .await;
+            }
+
+            info!("Price monitor stopped");
+        });
+    }
+
+    fn spawn_position_refresher(&self) {
+        let monitor = self.clone_for_task();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(
+                monitor.config.position_refresh_interval_ms,
+            ));
+            let streaming_interval =
+                Duration::from_millis(monitor.config.streaming_refresh_interval_ms);
+            let mut last_refresh: Option<tokio::time::Instant> = None;
+
+            loop {
+                if !*monitor.running.read().await {
+                    break;
+                }
+
+                ticker.tick().await;
+
+                if !monitor.is_leader() {
+                    continue;
+                }
+
+                // The account stream carries changes, polling is only a backstop
+                if monitor.stream_connected.load(Ordering::Relaxed)
+                    && last_refresh.is_some_and(|at| at.elapsed() < streaming_interval)
+                {
+                    continue;
+                }
+                if !monitor.breakers.allow(SCAN_BREAKER_KEY, std::time::Instant::now()) {
+                    continue;
+                }
+                last_refresh = Some(tokio::time::Instant::now());
+
+                match monitor.refresh_positions_from_chain(false).await {
+                    Ok(()) => monitor.breakers.record_success(SCAN_BREAKER_KEY),
+                    Err(e) => {
+                        error!("Failed to refresh positions: {}", e);
+                        if monitor.breakers.record_failure(SCAN_BREAKER_KEY, std::time::Instant::now()) {
+                            warn!("Circuit breaker opened for chain scans");
+                        }
+                    }
+                }
+            }
+
+            info!("Position refresher stopped");
+        });
     }
 
-    pub fn subscribe_prices(&self) -> broadcast::Receiver<PriceUpdate> {
-        self.price_update_tx.subscribe()
+    fn spawn_pnl_updater(&self) {
+        let monitor = self.clone_for_task();
+
+        tokio::spawn(async move {
+            // Ticks at the fastest symbol's cadence, slower symbols skip until due
+            let mut period = monitor.symbol_configs.min_pnl_update_interval();
+            let mut ticker = interval(period);
+            let mut last_pass: HashMap<String, tokio::time::Instant> = HashMap::new();
+            let snapshot_interval = Duration::from_millis(monitor.config.pnl_snapshot_interval_ms);
+            let mut last_snapshot: Option<tokio::time::Instant> = None;
+
+            loop {
+                if !*monitor.running.read().await {
+                    break;
+                }
+
+                ticker.tick().await;
+
+                let now = tokio::time::Instant::now();
+                let due: HashSet<String> = monitor
+                    .positions_by_asset
+                    .read()
+                    .await
+                    .keys()
+                    .filter(|symbol| {
+                        let cadence = Duration::from_millis(
+                            monitor.symbol_configs.settings(symbol).pnl_update_interval_ms,
+                        );
+                        last_pass
+                            .get(*symbol)
+                            .is_none_or(|at| now.duration_since(*at) >= cadence)
+                    })
+                    .cloned()
+                    .collect();
+
+                for symbol in &due {
+                    last_pass.insert(symbol.clone(), now);
+                }
+
+                if !due.is_empty() {
+                    if let Err(e) = monitor.update_pnl(Some(&due)).await {
+                        error!("Failed to update PnL: {}", e);
+                    }
+                }
+
+                let snapshot_due = last_snapshot.is_none_or(|at| at.elapsed() >= snapshot_interval);
+
+                if snapshot_due && monitor.history_store.is_some() && monitor.is_leader() {
+                    last_snapshot = Some(tokio::time::Instant::now());
+
+                    if let Err(e) = monitor.persist_pnl_snapshots().await {
+                        error!("Failed to persist PnL snapshots: {}", e);
+                    }
+                }
+
+                // Overrides changed the fastest cadence
+                let min_period = monitor.symbol_configs.min_pnl_update_interval();
+                if min_period != period {
+                    period = min_period;
+                    ticker = interval_at(tokio::time::Instant::now() + period, period);
+                }
+            }
+
+            info!("PnL updater stopped");
+        });
+    }
+
+    fn spawn_symbol_config_reloader(&self) {
+        let monitor = self.clone_for_task();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(SYMBOL_CONFIG_RELOAD_INTERVAL);
+
+            loop {
+                if !*monitor.running.read().await {
+                    break;
+                }
+
+                ticker.tick().await;
+
+                if let Err(e) = monitor.symbol_configs.reload().await {
+                    warn!("Failed to reload symbol configs: {}", e);
+                }
+            }
+        });
+    }
+
+    fn spawn_snapshotter(&self) {
+        let monitor = self.clone_for_task();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(monitor.config.snapshot_interval_ms));
+
+            loop {
+                if !*monitor.running.read().await {
+                    break;
+                }
+
+                ticker.tick().await;
+
+                // Followers don't sync from chain, they catch up from the leader's snapshot
+                if !monitor.is_leader() {
+                    if let Err(e) = monitor.restore_snapshot().await {
+                        warn!("Failed to reload monitor snapshot: {}", e);
+                    }
+                    continue;
+                }
+
+                // Only a chain-synced leader writes, so a restored snapshot is never re-saved as fresh
+                if monitor.sync_status.read().await.last_chain_sync.is_none() {
+                    continue;
+                }
+
+                if let Err(e) = monitor.save_snapshot().await {
+                    error!("Failed to save monitor snapshot: {}", e);
+                }
+            }
+
+            info!("Snapshotter stopped");
+        });
+    }
+
+    /// Reload the snapshot on the snapshot interval, for API-only replicas
+    /// that never start the monitor
+    pub fn spawn_snapshot_reloader(&self) {
+        let monitor = self.clone_for_task();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(monitor.config.snapshot_interval_ms));
+
+            loop {
+                ticker.tick().await;
+
+                if let Err(e) = monitor.restore_snapshot().await {
+                    warn!("Failed to reload monitor snapshot: {}", e);
+                }
+            }
+        });
+    }
+
+    fn spawn_account_subscriber(&self, config: AccountStreamConfig) {
+        let monitor = self.clone_for_task();
+
+        // Unfiltered: a closed account is zeroed, so a discriminator memcmp
+        // would hide exactly the updates that remove positions
+        let mut events =
+            ProgramAccountStream::new(config, self.solana_client.program_id, Vec::new()).spawn();
+
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if !*monitor.running.read().await {
+                    break;
+                }
+
+                match event {
+                    AccountStreamEvent::Subscribed => {
+                        monitor.stream_connected.store(true, Ordering::Relaxed);
+
+                        if !monitor.is_leader() {
+                            continue;
+                        }
+
+                        // Gap-fill whatever changed while disconnected
+                        if let Err(e) = monitor.refresh_positions_from_chain(true).await {
+                            error!("Failed to gap-fill positions after subscribe: {}", e);
+                        }
+                    }
+                    AccountStreamEvent::Update { pubkey, account, slot } => {
+                        monitor
+                            .rpc_metrics
+                            .record("programNotification", 1, account.data.len());
+
+                        // Followers don't write, but drop closed positions from their own cache
+                        if !monitor.is_leader() && !account.data.is_empty() {
+                            continue;
+                        }
+
+                        debug!("Account update for {} at slot {}", pubkey, slot);
+
+                        if let Err(e) = monitor.apply_streamed_account(pubkey, &account).await {
+                            error!("Failed to apply account update for {}: {}", pubkey, e);
+                        }
+                    }
+                    AccountStreamEvent::Disconnected => {
+                        monitor.stream_connected.store(false, Ordering::Relaxed);
+                    }
+                }
+            }
+
+            monitor.stream_connected.store(false, Ordering::Relaxed);
+            info!("Account subscriber stopped");
+        });
+    }
+
+    // Transactions missed while disconnected are left to the regular scan
+    fn spawn_transaction_watcher(&self, config: AccountStreamConfig) {
+        let monitor = self.clone_for_task();
+        let mut events = ProgramLogStream::new(config, self.solana_client.program_id).spawn();
+
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if !*monitor.running.read().await {
+                    break;
+                }
+
+                // Drain whatever queued up behind this one into a single fetch
+                let mut touched = Vec::new();
+                let mut next = Some(event);
+                while let Some(event) = next.take() {
+                    if let LogStreamEvent::Transaction { signature, logs, slot } = event {
+                        monitor.rpc_metrics.record("logsNotification", 1, 0);
+
+                        if monitor.is_leader() {
+                            debug!("Program transaction {} at slot {}", signature, slot);
+                            for position in monitor.touched_positions(&signature, &logs).await {
+                                if !touched.contains(&position) {
+                                    touched.push(position);
+                                }
+                            }
+                        }
+                    }
+                    next = events.try_recv().ok();
+                }
+
+                if touched.is_empty() {
+                    continue;
+                }
+
+                if let Err(e) = monitor.refetch_position_accounts(&touched).await {
+                    error!("Failed to refetch {} touched positions: {}", touched.len(), e);
+                }
+            }
+
+            info!("Transaction watcher stopped");
+        });
+    }
+
+    /// Positions a program transaction wrote. Its events name them; without
+    /// any, the transaction's accounts are matched against the cache
+    async fn touched_positions(&self, signature: &Signature, logs: &[String]) -> Vec<Pubkey> {
+        let positions = positions_in_logs(logs);
+        if !positions.is_empty() {
+            return positions;
+        }
+
+        let keys = match self.transaction_account_keys(signature) {
+            Ok(keys) => keys,
+            Err(e) => {
+                warn!("Failed to read accounts of {}: {:#}", signature, e);
+                return Vec::new();
+            }
+        };
+
+        let cached = self.positions.read().await;
+        keys.into_iter().filter(|key| cached.contains_key(key)).collect()
+    }
+
+    fn transaction_account_keys(&self, signature: &Signature) -> Result<Vec<Pubkey>> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+
+        let transaction = self
+            .rpc_pool
+            .read(|rpc_client| rpc_client.get_transaction_with_config(signature, config))
+            .inspect_err(|_| self.rpc_metrics.record_error("getTransaction"))
+            .context("getTransaction failed")?;
+        self.rpc_metrics.record("getTransaction", 1, 0);
+
+        let decoded = transaction
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| anyhow!("Undecodable transaction"))?;
+        let mut keys = decoded.message.static_account_keys().to_vec();
+
+        // Accounts pulled in through lookup tables
+        if let Some(meta) = transaction.transaction.meta {
+            if let OptionSerializer::Some(loaded) = meta.loaded_addresses {
+                keys.extend(loaded.writable.iter().filter_map(|key| key.parse::<Pubkey>().ok()));
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Read `accounts` back from chain and merge them. Missing accounts were
+    /// closed, except opens a lagging RPC node hasn't seen yet
+    async fn refetch_position_accounts(&self, accounts: &[Pubkey]) -> Result<()> {
+        for batch in accounts.chunks(self.config.scan.fetch_batch_size.max(1)) {
+            let response = self.rpc_pool.read(|rpc_client| {
+                rpc_client.get_multiple_accounts_with_commitment(batch, CommitmentConfig::confirmed())
+            });
+
+            let response = match response {
+                Ok(response) => response.value,
+                Err(e) => {
+                    self.rpc_metrics.record_error("getMultipleAccounts");
+                    error!("Failed to refetch {} position accounts: {}", batch.len(), e);
+                    continue;
+                }
+            };
+
+            let bytes = response.iter().flatten().map(|account| account.data.len()).sum();
+            self.rpc_metrics.record("getMultipleAccounts", batch.len(), bytes);
+
+            let now = Utc::now();
+            for (pubkey, account) in batch.iter().zip(response) {
+                match account {
+                    Some(account) => self.apply_streamed_account(*pubkey, &account).await?,
+                    None if self.mutations.is_unseen_open(pubkey, now).await => {}
+                    None => self.apply_streamed_account(*pubkey, &Account::default()).await?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn apply_streamed_account(&self, pubkey: Pubkey, account: &Account) -> Result<()> {
+        // Closed accounts arrive with no data, same as missing from a scan
+        if account.data.is_empty() {
+            if self.get_position(pubkey).await.is_some_and(|p| p.is_open()) {
+                info!("Removing closed position {}", pubkey);
+                self.remove_position(pubkey).await?;
+            }
+            self.scan_state.lock().await.forget(&pubkey);
+            return Ok(());
+        }
+
+        // The subscription is unfiltered, so other program accounts come through too
+        if !account.data.starts_with(&OnChainPosition::DISCRIMINATOR) {
+            return Ok(());
+        }
+
+        self.scan_state
+            .lock()
+            .await
+            .record(pubkey, fingerprint(account.lamports, &account.data, false));
+
+        self.apply_position_account(pubkey, account).await
+    }
+
+    // Runs on every node, each node only reverts changes to its own cache
+    fn spawn_mutation_verifier(&self) {
+        let monitor = self.clone_for_task();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(
+                monitor.mutations.config().verify_interval_ms,
+            ));
+
+            loop {
+                if !*monitor.running.read().await {
+                    break;
+                }
+
+                ticker.tick().await;
+
+                if let Err(e) = monitor.verify_mutations().await {
+                    error!("Failed to verify tracked mutations: {}", e);
+                }
+            }
+
+            info!("Mutation verifier stopped");
+        });
+    }
+
+    // Runs on every node, each node bounds its own cache; store writes are upserts
+    fn spawn_closed_position_evictor(&self) {
+        let monitor = self.clone_for_task();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(
+                monitor.config.retention.eviction_interval_ms,
+            ));
+
+            loop {
+                if !*monitor.running.read().await {
+                    break;
+                }
+
+                ticker.tick().await;
+
+                if let Err(e) = monitor.evict_closed_positions().await {
+                    error!("Failed to evict closed positions: {}", e);
+                }
+            }
+
+            info!("Closed position evictor stopped");
+        });
+    }
+
+    fn is_past_retention(&self, position: &Position) -> bool {
+        position.is_closed()
+            && position.closed_at.unwrap_or(position.last_update)
+                <= Utc::now() - self.config.retention.closed_retention
+    }
+
+    /// Move closed positions past the retention window or over the size cap
+    /// to the history store and drop them from memory
+    /// Returns how many were evicted
+    #[instrument(skip_all, fields(evicted = field::Empty))]
+    pub async fn evict_closed_positions(&self) -> Result<usize> {
+        let closed: Vec<(Pubkey, DateTime<Utc>)> = self
+            .positions
+            .read()
+            .await
+            .values()
+            .filter(|position| position.is_closed())
+            .map(|position| {
+                (
+                    position.position_account,
+                    position.closed_at.unwrap_or(position.last_update),
+                )
+            })
+            .collect();
+
+        let evictions = select_evictions(&self.config.retention, closed, Utc::now());
+        if evictions.is_empty() {
+            return Ok(0);
+        }
+
+        if let Some(history_store) = &self.history_store {
+            let positions = self.positions.read().await;
+            let evicted: Vec<Position> = evictions
+                .iter()
+                .filter_map(|(account, _)| positions.get(account).cloned())
+                .collect();
+            drop(positions);
+
+            // Keep them in memory and retry next pass rather than lose them
+            if let Err(e) = history_store.insert_closed_positions(&evicted).await {
+                self.retention_metrics.record_persist_failure();
+                return Err(e);
+            }
+        }
+
+        let mut positions = self.positions.write().await;
+        let mut positions_by_asset = self.positions_by_asset.write().await;
+        let mut positions_by_user = self.positions_by_user.write().await;
+
+        let mut evicted = Vec::new();
+        for (account, reason) in &evictions {
+            // Skip anything reopened or reverted since it was selected
+            if !positions.get(account).is_some_and(|position| position.is_closed()) {
+                continue;
+            }
+
+            let Some(position) = positions.remove(account) else {
+                continue;
+            };
+
+            if let Some(accounts) = positions_by_asset.get_mut(&position.symbol) {
+                accounts.retain(|acc| acc != account);
+                if accounts.is_empty() {
+                    positions_by_asset.remove(&position.symbol);
+                }
+            }
+            if let Some(accounts) = positions_by_user.get_mut(&position.owner) {
+                accounts.retain(|acc| acc != account);
+                if accounts.is_empty() {
+                    positions_by_user.remove(&position.owner);
+                }
+            }
+
+            self.retention_metrics.record_eviction(*reason);
+            self.margin_ratio_alerts.forget(account);
+            self.portfolios.remove(account);
+            evicted.push(*account);
+        }
+
+        drop(positions);
+        drop(positions_by_asset);
+        drop(positions_by_user);
+
+        // Alert rules can't fire on a position that is gone
+        if let Err(e) = self.position_alerts.remove_rules_for(&evicted).await {
+            warn!("Failed to drop alert rules of evicted positions: {}", e);
+        }
+
+        tracing::Span::current().record("evicted", evicted.len());
+        debug!("Evicted {} closed positions", evicted.len());
+
+        Ok(evicted.len())
     }
 
-    pub fn subscribe_liquidation_alerts(&self) -> broadcast::Receiver<LiquidationAlert> {
-        self.liquidation_service.subscribe()
+    /// Record a cache change made for `signature` so it can be reverted on rollback
+    pub async fn track_mutation(
+        &self,
+        signature: Signature,
+        position_account: Pubkey,
+        previous: Option<Position>,
+    ) {
+        self.mutations.track(signature, position_account, previous).await;
     }
 
-    pub async fn start(&self) -> Result<()> {
-        let mut running = self.running.write().await;
-        if *running {
-            return Err(anyhow!("Monitor already running"));
+    #[instrument(skip_all)]
+    async fn verify_mutations(&self) -> Result<()> {
+        let pending = self.mutations.pending().await;
+        if pending.is_empty() {
+            return Ok(());
         }
-        *running = true;
-        drop(running);
 
-        info!("Starting position monitor");
+        let signatures: Vec<Signature> = pending.iter().map(|m| m.signature).collect();
+        let mut statuses = Vec::with_capacity(signatures.len());
+
+        // RPC accepts at most 256 signatures per call. Searching the ledger
+        // history keeps signatures past the status cache from reading as
+        // dropped, and a chunk that fails stays pending for the next pass
+        for chunk in signatures.chunks(256) {
+            let response = match self
+                .rpc_pool
+                .read(|rpc_client| rpc_client.get_signature_statuses_with_history(chunk))
+            {
+                Ok(response) => response,
+                Err(e) => {
+                    self.rpc_metrics.record_error("getSignatureStatuses");
+                    warn!("Failed to fetch {} signature statuses: {}", chunk.len(), e);
+                    continue;
+                }
+            };
+            self.rpc_metrics.record("getSignatureStatuses", chunk.len(), 0);
+
+            for (signature, status) in chunk.iter().zip(response.value) {
+                let state = status.map(|status| SignatureState {
+                    slot: status.slot,
+                    failed: status.err.is_some(),
+                    finalized: status.satisfies_commitment(CommitmentConfig::finalized()),
+                });
+                statuses.push((*signature, state));
+            }
+        }
 
-        self.spawn_price_monitor();
-        self.spawn_position_refresher();
-        self.spawn_pnl_updater();
+        let rolled_back = self.mutations.resolve(&statuses, Utc::now()).await;
+        // Later transactions may have landed on top, so the chain decides what
+        // the cache holds. The recorded position is the fallback
+        for batch in rolled_back.chunks(100) {
+            let accounts: Vec<Pubkey> = batch.iter().map(|mutation| mutation.position_account).collect();
+            let fetched = self
+                .rpc_pool
+                .read(|rpc_client| rpc_client.get_multiple_accounts_with_commitment(&accounts, CommitmentConfig::confirmed()))
+                .map(|response| response.value)
+                .inspect_err(|e| {
+                    self.rpc_metrics.record_error("getMultipleAccounts");
+                    warn!("Failed to refetch {} rolled back positions: {}", accounts.len(), e);
+                })
+                .ok();
+
+            for (i, mutation) in batch.iter().enumerate() {
+                warn!(
+                    "Transaction {} for {} was rolled back (last seen in slot {:?}), reverting cache",
+                    mutation.signature, mutation.position_account, mutation.slot
+                );
+
+                let reverted = match fetched.as_ref().map(|accounts| &accounts[i]) {
+                    Some(Some(account)) => self.apply_position_account(mutation.position_account, account).await,
+                    Some(None) => self.remove_position(mutation.position_account).await,
+                    None => match mutation.previous.clone() {
+                        Some(previous) => self.replace_position(previous).await,
+                        None => self.remove_position(mutation.position_account).await,
+                    },
+                };
+
+                if let Err(e) = reverted {
+                    error!("Failed to revert {}: {}", mutation.position_account, e);
+                }
+            }
+        }
 
         Ok(())
     }
 
-    pub async fn stop(&self) {
-        let mut running = self.running.write().await;
-        *running = false;
-        info!("Position monitor stopped");
-    }
+    /// Serialize positions and indexes to Redis
+    #[instrument(skip_all)]
+    pub async fn save_snapshot(&self) -> Result<()> {
+        let snapshot = MonitorSnapshot {
+            taken_at: Utc::now(),
+            positions: self.positions.read().await.values().cloned().collect(),
+            positions_by_asset: self.positions_by_asset.read().await.clone(),
+            positions_by_user: self
+                .positions_by_user
+                .read()
+                .await
+                .iter()
+                .map(|(owner, accounts)| (*owner, accounts.clone()))
+                .collect(),
+        };
 
-    fn spawn_price_monitor(&self) {
-        let monitor = self.clone_for_task();
+        let payload = serde_json::to_string(&snapshot)?;
 
-        tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_millis(1000));
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")?;
 
-            loop {
-                if !*monitor.running.read().await {
-                    break;
-                }
+        conn.set::<_, _, ()>(SNAPSHOT_KEY, payload).await?;
 
-                ticker.tick().await;
+        self.sync_status.write().await.snapshot_taken_at = Some(snapshot.taken_at);
 
-                let oracle = monitor.oracle_client.read().await;
-                let symbols = oracle.get_symbols();
+        debug!("Saved monitor snapshot with {} positions", snapshot.positions.len());
 
-                for symbol in symbols {
-                    match oracle.fetch_price(&symbol).await {
-                        Ok(price) => {
-                            let update = PriceUpdate {
-                                symbol: symbol.clone(),
-                                price,
-                                timestamp: Utc::now(),
-                            };
+        Ok(())
+    }
 
-                            info!("Price update: {} = {}", symbol, price);
+    /// Load the last snapshot from Redis, call before `start` so reads are
+    /// served while the first chain reconciliation runs
+    /// Returns false when there is no snapshot
+    #[instrument(skip_all)]
+    pub async fn restore_snapshot(&self) -> Result<bool> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")?;
 
-                            let _ = monitor.price_update_tx.send(update);
+        let payload: Option<String> = conn.get(SNAPSHOT_KEY).await?;
 
-                            if let Err(e) = monitor
-                                .liquidation_service
-                                .check_liquidations_for_price_update(&symbol, price)
-                                .await
-                            {
-                                error!("Failed to check liquidations for {}: {}", symbol, e);
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed to fetch price for {}: {}", symbol, e);
-                        }
-                    }
-                }
-            }
+        let Some(payload) = payload else {
+            return Ok(false);
+        };
 
-            info!("Price monitor stopped");
-        });
-    }
+        let snapshot: MonitorSnapshot =
+            serde_json::from_str(&payload).context("Failed to decode monitor snapshot")?;
 
-    fn spawn_position_refresher(&self) {
-        let monitor = self.clone_for_task();
+        let count = snapshot.positions.len();
 
-        tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_millis(
-                monitor.config.position_refresh_interval_ms,
-            ));
+        *self.positions.write().await = snapshot
+            .positions
+            .into_iter()
+            .map(|position| (position.position_account, position))
+            .collect();
+        *self.positions_by_asset.write().await = snapshot.positions_by_asset;
+        *self.positions_by_user.write().await = snapshot.positions_by_user.into_iter().collect();
 
-            loop {
-                if !*monitor.running.read().await {
-                    break;
-                }
+        self.sync_status.write().await.snapshot_taken_at = Some(snapshot.taken_at);
 
-                ticker.tick().await;
+        info!("Restored {} positions from snapshot taken at {}", count, snapshot.taken_at);
 
-                if let Err(e) = monitor.refresh_positions_from_chain().await {
-                    error!("Failed to refresh positions: {}", e);
-                }
-            }
+        Ok(true)
+    }
 
-            info!("Position refresher stopped");
-        });
+    pub async fn sync_status(&self) -> SyncStatus {
+        self.sync_status.read().await.clone()
     }
 
-    fn spawn_pnl_updater(&self) {
-        let monitor = self.clone_for_task();
+    /// Longest the cache may go without a chain sync or snapshot and still be ready
+    pub fn max_sync_lag(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.config.max_sync_lag_secs)
+    }
 
-        tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_millis(monitor.config.pnl_update_interval_ms));
+    #[instrument(
+        skip_all,
+        fields(full = field::Empty, accounts = field::Empty, fetched = field::Empty)
+    )]
+    async fn refresh_positions_from_chain(&self, force_full: bool) -> Result<()> {
+        info!("Refreshing positions from chain...");
 
-            loop {
-                if !*monitor.running.read().await {
-                    break;
-                }
+        // Runs at a small interval, or a long one while the account stream is connected
+        let mut scan = self.scan_state.lock().await;
+        let full = scan.next_tick_is_full(&self.config.scan) || force_full;
+
+        let (live, accounts): (HashSet<Pubkey>, Vec<(Pubkey, Account)>) = if full && self.config.scan.chunked {
+            // Applies accounts itself, batch by batch
+            drop(scan);
+            (self.scan_positions_chunked().await?, Vec::new())
+        } else if full {
+            let accounts = self.scan_position_accounts(None)?;
+            scan.reset(accounts.iter().map(|(pubkey, account)| {
+                (*pubkey, fingerprint(account.lamports, &account.data, false))
+            }));
+            drop(scan);
+            (accounts.iter().map(|(pubkey, _)| *pubkey).collect(), accounts)
+        } else {
+            // Only the mutable window, full data is fetched for what changed
+            let sliced = self.scan_position_accounts(Some(UiDataSliceConfig {
+                offset: POSITION_MUTABLE_OFFSET,
+                length: POSITION_MUTABLE_LEN,
+            }))?;
+            let diff = scan.diff(sliced.iter().map(|(pubkey, account)| {
+                (*pubkey, fingerprint(account.lamports, &account.data, true))
+            }));
+            let accounts = self.fetch_position_accounts(&diff.changed, &mut scan);
+            drop(scan);
+            (diff.live, accounts)
+        };
 
-                ticker.tick().await;
+        info!("Found {} position accounts on chain", live.len());
+        let span = tracing::Span::current();
+        span.record("full", full);
+        span.record("accounts", live.len());
+        span.record("fetched", accounts.len());
 
-                if let Err(e) = monitor.update_all_pnl().await {
-                    error!("Failed to update PnL: {}", e);
-                }
+        for (pubkey, account) in accounts {
+            self.apply_position_account(pubkey, &account).await?;
+        }
+
+        // Remove stale positions, except opens the scan may predate
+        let all_positions = self.get_all_positions().await;
+        let now = Utc::now();
+        for position in all_positions {
+            if !live.contains(&position.position_account)
+                && position.is_open()
+                && !self.mutations.is_unseen_open(&position.position_account, now).await
+            {
+                info!("Removing closed position {}", position.position_account);
+                let _ = self.remove_position(position.position_account).await;
             }
+        }
 
-            info!("PnL updater stopped");
-        });
+        self.sync_status.write().await.last_chain_sync = Some(Utc::now());
+
+        info!("Position refresh completed");
+
+        Ok(())
     }
 
-    async fn refresh_positions_from_chain(&self) -> Result<()> {
-        info!("Refreshing positions from chain...");
+    /// Decode a Position account and merge it into the cache, shared by the
+    /// gPA scan and the account stream
+    async fn apply_position_account(&self, pubkey: Pubkey, account: &Account) -> Result<()> {
+        let position = match deserialize_position_account(pubkey, account)
+            .and_then(|(position_index, on_chain)| on_chain.to_domain_position(pubkey, position_index))
+        {
+            Ok(position) => position,
+            Err(e) => {
+                error!("Failed to decode position at {}: {}", pubkey, e);
+                return Ok(());
+            }
+        };
+
+        let position_account = position.position_account;
+        self.mutations.forget_open(&position_account).await;
+
+        // Check if already exists
+        if self.get_position(position_account).await.is_some() {
+            self.update_position(position).await?;
+        } else if !self.is_past_retention(&position) {
+            // Closed accounts stay on chain, don't re-add what was evicted
+            self.add_position(position).await?;
+            info!("Added new position {}", position_account);
+        }
+
+        Ok(())
+    }
 
-        let program_id = self.solana_client.program_id;
+    /// getProgramAccounts over every Position account, optionally sliced
+    fn scan_position_accounts(
+        &self,
+        data_slice: Option<UiDataSliceConfig>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let method = match data_slice {
+            None => "getProgramAccounts",
+            Some(slice) if slice.length == 0 => "getProgramAccounts(keys)",
+            Some(_) => "getProgramAccounts(sliced)",
+        };
 
+        // The memcmp filter runs on full account data, before the slice
         let config = RpcProgramAccountsConfig {
             filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new(
                 0,
@@ -257,78 +1350,205 @@ impl PositionMonitor {
             ))]),
             account_config: RpcAccountInfoConfig {
                 encoding: Some(UiAccountEncoding::Base64),
+                data_slice,
                 commitment: Some(CommitmentConfig::confirmed()),
                 ..Default::default()
             },
             with_context: Some(false),
         };
 
-        // Fetch all position accounts
-        // Runs at a small interval
-        // Supplement to this would be listening to grpc updates and updating positions accordingly, and keeping this interval bigger
         let accounts = self
-            .rpc_client
-            .get_program_accounts_with_config(&program_id, config)
+            .rpc_pool
+            .read(|rpc_client| {
+                rpc_client.get_program_accounts_with_config(&self.solana_client.program_id, config.clone())
+            })
+            .inspect_err(|_| self.rpc_metrics.record_error(method))
             .context("Failed to fetch program accounts")?;
 
-        info!("Found {} position accounts on chain", accounts.len());
+        let bytes = accounts.iter().map(|(_, account)| account.data.len()).sum();
+        self.rpc_metrics.record(method, accounts.len(), bytes);
 
-        let mut seen_positions = HashMap::new();
+        Ok(accounts)
+    }
 
-        for (pubkey, account) in accounts {
-            match deserialize_position_account(pubkey, &account) {
-                Ok((position_index, on_chain_position)) => {
-                    match on_chain_position.to_domain_position(pubkey, position_index) {
-                        Ok(position) => {
-                            let position_account = position.position_account;
-                            seen_positions.insert(position_account, true);
-
-                            
-                            // Check if already exists
-                            if self.get_position(position_account).await.is_some() {
-                                self.update_position(position).await?;
-                            } else {
-                                self.add_position(position).await?;
-                                info!("Added new position {}", position_account);
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed to convert position at {}: {}", pubkey, e);
+    /// Full scan that never holds every account at once: list keys with an
+    /// empty slice, then fetch `fetch_concurrency` batches at a time and merge
+    /// each into the cache as it lands. Returns the live accounts
+    async fn scan_positions_chunked(&self) -> Result<HashSet<Pubkey>> {
+        let keys: Vec<Pubkey> = self
+            .scan_position_accounts(Some(UiDataSliceConfig { offset: 0, length: 0 }))?
+            .into_iter()
+            .map(|(pubkey, _)| pubkey)
+            .collect();
+
+        let mut progress = ScanProgress {
+            total: keys.len(),
+            fetched: 0,
+        };
+        self.sync_status.write().await.scan_progress = Some(progress);
+
+        let mut live: HashSet<Pubkey> = keys.iter().copied().collect();
+        let mut fingerprints = Vec::with_capacity(keys.len());
+
+        let result = async {
+            let rpc_pool = Arc::clone(&self.rpc_pool);
+            let batches: Vec<Vec<Pubkey>> = keys
+                .chunks(self.config.scan.fetch_batch_size.max(1))
+                .map(<[Pubkey]>::to_vec)
+                .collect();
+
+            let mut batches = futures::stream::iter(batches)
+                .map(move |batch| {
+                    let rpc_pool = Arc::clone(&rpc_pool);
+                    tokio::task::spawn_blocking(move || {
+                        let response = rpc_pool.read(|rpc_client| {
+                            rpc_client.get_multiple_accounts_with_commitment(&batch, CommitmentConfig::confirmed())
+                        });
+                        (batch, response)
+                    })
+                })
+                .buffer_unordered(self.config.scan.fetch_concurrency.max(1));
+
+            while let Some(fetched) = batches.next().await {
+                let (batch, response) = fetched.context("Position fetch task failed")?;
+
+                match response {
+                    Ok(response) => {
+                        let bytes = response.value.iter().flatten().map(|account| account.data.len()).sum();
+                        self.rpc_metrics.record("getMultipleAccounts", batch.len(), bytes);
+
+                        for (pubkey, account) in batch.iter().zip(response.value) {
+                            // Closed since it was listed
+                            let Some(account) = account else {
+                                live.remove(pubkey);
+                                continue;
+                            };
+                            fingerprints.push((*pubkey, fingerprint(account.lamports, &account.data, false)));
+                            self.apply_position_account(*pubkey, &account).await?;
                         }
                     }
+                    // Left without a fingerprint, so the next sliced scan fetches them
+                    Err(e) => {
+                        self.rpc_metrics.record_error("getMultipleAccounts");
+                        error!("Failed to fetch {} position accounts: {}", batch.len(), e);
+                    }
                 }
-                Err(e) => {
-                    error!("Failed to deserialize position at {}: {}", pubkey, e);
-                }
+
+                progress.fetched += batch.len();
+                self.sync_status.write().await.scan_progress = Some(progress);
             }
+
+            Ok::<_, anyhow::Error>(())
         }
+        .await;
 
-        // Remove stale positions
-        let all_positions = self.get_all_positions().await;
-        for position in all_positions {
-            if !seen_positions.contains_key(&position.position_account) && position.is_open() {
-                info!("Removing closed position {}", position.position_account);
-                let _ = self.remove_position(position.position_account).await;
+        self.sync_status.write().await.scan_progress = None;
+        result?;
+
+        self.scan_state.lock().await.reset(fingerprints);
+
+        Ok(live)
+    }
+
+    /// Full data for `accounts` in getMultipleAccounts batches. Accounts whose
+    /// batch failed or that vanished are forgotten so the next scan retries them
+    fn fetch_position_accounts(
+        &self,
+        accounts: &[Pubkey],
+        scan: &mut ScanState,
+    ) -> Vec<(Pubkey, Account)> {
+        let mut fetched = Vec::with_capacity(accounts.len());
+
+        for batch in accounts.chunks(self.config.scan.fetch_batch_size.max(1)) {
+            let response = self.rpc_pool.read(|rpc_client| {
+                rpc_client.get_multiple_accounts_with_commitment(batch, CommitmentConfig::confirmed())
+            });
+
+            let response = match response {
+                Ok(response) => response.value,
+                Err(e) => {
+                    self.rpc_metrics.record_error("getMultipleAccounts");
+                    error!("Failed to fetch {} position accounts: {}", batch.len(), e);
+                    batch.iter().for_each(|account| scan.forget(account));
+                    continue;
+                }
+            };
+
+            let mut bytes = 0;
+            for (pubkey, account) in batch.iter().zip(response) {
+                match account {
+                    Some(account) => {
+                        bytes += account.data.len();
+                        fetched.push((*pubkey, account));
+                    }
+                    None => scan.forget(pubkey),
+                }
             }
+
+            self.rpc_metrics.record("getMultipleAccounts", batch.len(), bytes);
         }
 
-        info!("Position refresh completed");
+        fetched
+    }
 
-        Ok(())
+    /// Mark `position` to `mark_price`: its unrealized PnL and effective
+    /// liquidation price. The margin ratio at the mark
+    fn mark_position(&self, position: &mut Position, mark_price: Decimal) -> Result<Decimal> {
+        position.mark_price = mark_price;
+        position.unrealized_pnl = MarginCalculator::calculate_unrealized_pnl(
+            position.side,
+            position.size,
+            mark_price,
+            position.entry_price,
+        )?;
+        position.last_update = Utc::now();
+
+        position.effective_liquidation_price = MarginCalculator::calculate_effective_liquidation_price(
+            position.side,
+            position.size,
+            position.entry_price,
+            position.margin,
+            position.funding_accrued,
+            self.maintenance_margin_ratio(&position.symbol),
+            self.config.close_fee_rate,
+        )
+        .ok();
+
+        Ok(MarginCalculator::calculate_margin_ratio(
+            position.margin,
+            position.unrealized_pnl,
+            position.size,
+            position.mark_price,
+        )
+        .unwrap_or(Decimal::ZERO))
     }
 
     // Update unrealized PnL for all open positions in real time
     // As per the requirement, though we could do updates for unrealized PnL only on demand(when asked for a certain position/user)
-    async fn update_all_pnl(&self) -> Result<()> {
+    // Driven by the PnL updater task, public so load runs can time single passes
+    pub async fn update_all_pnl(&self) -> Result<()> {
+        self.update_pnl(None).await
+    }
+
+    /// PnL pass over the open positions in `symbols`, or all of them
+    #[instrument(skip_all, fields(positions = field::Empty))]
+    async fn update_pnl(&self, symbols: Option<&HashSet<String>>) -> Result<()> {
         let mut positions = self.positions.write().await;
-        let oracle = self.oracle_client.read().await;
+        let mut updates = Vec::new();
+        let mut ratio_alerts = Vec::new();
+        // Ratio alerts follow the same rules as the user alerts below
+        let leader = self.is_leader();
 
         for position in positions.values_mut() {
             if !position.is_open() {
+                self.portfolios.remove(&position.position_account);
+                continue;
+            }
+            if symbols.is_some_and(|symbols| !symbols.contains(&position.symbol)) {
                 continue;
             }
 
-            let mark_price = match oracle.get_cached_price(&position.symbol).await {
+            let mark_price = match self.price_source.get_cached_price(&position.symbol).await {
                 Some(price) => price,
                 None => {
                     debug!("No price available for {}", position.symbol);
@@ -336,25 +1556,10 @@ impl PositionMonitor {
                 }
             };
 
-            position.mark_price = mark_price;
-
-            match MarginCalculator::calculate_unrealized_pnl(
-                position.side,
-                position.size,
-                mark_price,
-                position.entry_price,
-            ) {
-                Ok(pnl) => {
-                    position.unrealized_pnl = pnl;
-                    position.last_update = Utc::now();
-
-                    let margin_ratio = MarginCalculator::calculate_margin_ratio(
-                        position.margin,
-                        position.unrealized_pnl,
-                        position.size,
-                        position.mark_price,
-                    )
-                    .unwrap_or(Decimal::ZERO);
+            match self.mark_position(position, mark_price) {
+                Ok(margin_ratio) => {
+                    self.portfolios
+                        .record(position, self.maintenance_margin_ratio(&position.symbol));
 
                     let update = PositionUpdate {
                         position_account: position.position_account,
@@ -364,11 +1569,26 @@ impl PositionMonitor {
                         entry_price: position.entry_price,
                         mark_price: position.mark_price,
                         unrealized_pnl: position.unrealized_pnl,
+                        margin: position.margin,
                         margin_ratio,
+                        liquidation_price: position.liquidation_price,
+                        effective_liquidation_price: position.effective_liquidation_price,
                         timestamp: Utc::now(),
                     };
 
-                    let _ = self.position_update_tx.send(update);
+                    if leader && !self.breakers.is_open(&oracle_breaker_key(&position.symbol)) {
+                        let maintenance_margin_ratio = self.maintenance_margin_ratio(&position.symbol);
+                        if let Some(tier) = self.margin_ratio_alerts.check(
+                            &position.position_account,
+                            margin_ratio,
+                            maintenance_margin_ratio,
+                        ) {
+                            ratio_alerts.push(margin_ratio_alert(position, margin_ratio, tier));
+                        }
+                    }
+
+                    self.events.publish(DomainEvent::Position(update.clone()));
+                    updates.push(update);
                 }
                 Err(e) => {
                     error!(
@@ -379,11 +1599,117 @@ impl PositionMonitor {
             }
         }
 
+        drop(positions);
+        tracing::Span::current().record("positions", updates.len());
+
+        for portfolio in self.portfolios.drain_changed() {
+            self.events.publish(DomainEvent::Portfolio(portfolio));
+        }
+
+        for alert in ratio_alerts {
+            self.liquidation_service.publish(alert).await;
+        }
+
+        // Only the leader alerts so replicas don't send duplicates. A symbol
+        // whose quotes are failing marks at a stale price, so isn't evaluated
+        if self.is_leader() {
+            updates.retain(|update| !self.breakers.is_open(&oracle_breaker_key(&update.symbol)));
+
+            match self.position_alerts.evaluate(&updates).await {
+                Ok(fired) => self.record_alerts(&fired).await,
+                Err(e) => warn!("Failed to evaluate position alert rules: {}", e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keep fired alerts for their positions' timelines
+    async fn record_alerts(&self, fired: &[PositionAlert]) {
+        let Some(history_store) = &self.history_store else {
+            return;
+        };
+
+        for alert in fired {
+            let event = PositionEventRecord {
+                id: uuid::Uuid::new_v4(),
+                position_account: alert.position_account,
+                kind: PositionEventKind::Alert,
+                size: None,
+                margin: None,
+                mark_price: Some(alert.mark_price),
+                detail: serde_json::to_string(&alert.condition).ok(),
+                signature: None,
+                recorded_at: alert.triggered_at,
+            };
+
+            if let Err(e) = history_store.insert_position_event(&event).await {
+                warn!("Failed to record alert for {}: {}", alert.position_account, e);
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn persist_pnl_snapshots(&self) -> Result<()> {
+        let Some(history_store) = &self.history_store else {
+            return Ok(());
+        };
+
+        let timestamp = Utc::now();
+
+        let snapshots: Vec<PnLSnapshot> = self
+            .positions
+            .read()
+            .await
+            .values()
+            .filter(|position| position.is_open())
+            .map(|position| PnLSnapshot {
+                id: uuid::Uuid::new_v4(),
+                position_account: position.position_account,
+                unrealized_pnl: position.unrealized_pnl,
+                realized_pnl: position.realized_pnl,
+                funding_accrued: position.funding_accrued,
+                mark_price: position.mark_price,
+                timestamp,
+            })
+            .collect();
+
+        if snapshots.is_empty() {
+            return Ok(());
+        }
+
+        history_store.insert_pnl_snapshots(&snapshots).await?;
+
+        debug!("Persisted {} PnL snapshots", snapshots.len());
+
         Ok(())
     }
 
+    /// Insert positions into the in-memory state and indexes only, leaving the
+    /// Redis liquidation sets alone; for state replayed from elsewhere (load runs)
+    pub async fn seed_positions(&self, seeded: impl IntoIterator<Item = Position>) {
+        let mut positions = self.positions.write().await;
+        let mut positions_by_asset = self.positions_by_asset.write().await;
+        let mut positions_by_user = self.positions_by_user.write().await;
+
+        for mut position in seeded {
+            position.symbol = symbols::normalize(&position.symbol);
+
+            positions_by_asset
+                .entry(position.symbol.clone())
+                .or_default()
+                .push(position.position_account);
+            positions_by_user
+                .entry(position.owner)
+                .or_default()
+                .push(position.position_account);
+            positions.insert(position.position_account, position);
+        }
+    }
+
     /// Add position to global state
-    pub async fn add_position(&self, position: Position) -> Result<()> {
+    pub async fn add_position(&self, mut position: Position) -> Result<()> {
+        position.symbol = symbols::normalize(&position.symbol);
         let position_account = position.position_account;
         let asset_symbol = position.symbol.clone();
         let owner = position.owner;
@@ -409,8 +1735,8 @@ impl PositionMonitor {
             .push(position_account);
         drop(positions_by_user);
 
-        // Add to Redis only if its open
-        if position.is_open() {
+        // Add to Redis only if its open, opens join once confirmed
+        if position.is_open() && position.status != PositionStatus::Opening {
             self.add_to_redis_sorted_set(&position).await?;
         }
 
@@ -419,6 +1745,32 @@ impl PositionMonitor {
         Ok(())
     }
 
+    /// Cache an open about to be sent as Opening, so reads see it before it
+    /// confirms and the next scan finds it
+    pub async fn add_pending_position(&self, mut position: Position) -> Result<()> {
+        position.status = PositionStatus::Opening;
+        self.mutations.register_open(position.position_account, Utc::now()).await;
+        self.add_position(position).await
+    }
+
+    /// The open confirmed: cache the position as sent, shielded from scans
+    /// that predate it
+    pub async fn confirm_pending_position(&self, position: Position) -> Result<()> {
+        self.mutations.register_open(position.position_account, Utc::now()).await;
+        self.replace_position(position).await
+    }
+
+    /// The open failed, drop the position unless a scan found it on chain meanwhile
+    pub async fn discard_pending_position(&self, position_account: Pubkey) -> Result<()> {
+        self.mutations.forget_open(&position_account).await;
+        match self.get_position(position_account).await {
+            Some(position) if position.status == PositionStatus::Opening => {
+                self.remove_position(position_account).await
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Update existing position
     pub async fn update_position(&self, position: Position) -> Result<()> {
         let position_account = position.position_account;
@@ -429,6 +1781,21 @@ impl PositionMonitor {
         Ok(())
     }
 
+    /// Replace a cached position, keeping the Redis liquidation set in step with its status
+    pub async fn replace_position(&self, position: Position) -> Result<()> {
+        if self.get_position(position.position_account).await.is_none() {
+            return self.add_position(position).await;
+        }
+
+        if position.is_open() {
+            self.add_to_redis_sorted_set(&position).await?;
+        } else {
+            self.remove_from_redis_sorted_set(&position).await?;
+        }
+
+        self.update_position(position).await
+    }
+
     /// Remove position
     pub async fn remove_position(&self, position_account: Pubkey) -> Result<()> {
         let mut positions = self.positions.write().await;
@@ -436,6 +1803,7 @@ impl PositionMonitor {
             .remove(&position_account)
             .ok_or_else(|| anyhow!("Position not found"))?;
         drop(positions);
+        self.portfolios.remove(&position_account);
 
         // Remove from asset lookup
         let mut positions_by_asset = self.positions_by_asset.write().await;
@@ -459,6 +1827,7 @@ impl PositionMonitor {
         Ok(())
     }
 
+    #[instrument(level = "debug", skip_all, fields(position = %position.position_account))]
     async fn add_to_redis_sorted_set(&self, position: &Position) -> Result<()> {
         let mut conn = self
             .redis_client
@@ -474,7 +1843,7 @@ impl PositionMonitor {
         let member = position.position_account.to_string();
         let score = position.liquidation_price.to_string();
 
-        conn.zadd(&key, &member, &score).await?;
+        conn.zadd::<_, _, _, ()>(&key, &member, &score).await?;
 
         info!(
             "Added {} to Redis sorted set {} with score {}",
@@ -485,6 +1854,7 @@ impl PositionMonitor {
     }
 
     // After a position is closed or liquidated it will be removed from redis sorted set
+    #[instrument(level = "debug", skip_all, fields(position = %position.position_account))]
     async fn remove_from_redis_sorted_set(&self, position: &Position) -> Result<()> {
         let mut conn: redis::aio::MultiplexedConnection = self
             .redis_client
@@ -498,7 +1868,7 @@ impl PositionMonitor {
         };
 
         let member = position.position_account.to_string();
-        conn.zrem(&key, &member).await?;
+        conn.zrem::<_, _, ()>(&key, &member).await?;
 
         Ok(())
     }
@@ -509,6 +1879,27 @@ impl PositionMonitor {
         positions.get(&position_account).cloned()
     }
 
+    /// A cached position marked to the latest cached price now, rather than
+    /// as of the last PnL pass. The cache itself waits for the next pass
+    pub async fn get_position_marked(&self, position_account: Pubkey) -> Option<Position> {
+        let mut position = self.get_position(position_account).await?;
+        if !position.is_open() {
+            return Some(position);
+        }
+
+        if let Some(mark_price) = self.price_source.get_cached_price(&position.symbol).await {
+            if let Err(e) = self.mark_position(&mut position, mark_price) {
+                warn!("Failed to mark position {}: {}", position_account, e);
+            }
+        }
+        Some(position)
+    }
+
+    /// The owner's totals as of the last PnL pass
+    pub fn portfolio(&self, owner: &Pubkey) -> PortfolioUpdate {
+        self.portfolios.portfolio(owner)
+    }
+
     /// Get positions by user
     pub async fn get_user_positions(&self, owner: &Pubkey) -> Result<Vec<Position>> {
         let positions_by_user = self.positions_by_user.read().await;
@@ -528,7 +1919,7 @@ impl PositionMonitor {
     pub async fn get_positions_by_asset(&self, asset_symbol: &str) -> Vec<Position> {
         let positions_by_asset = self.positions_by_asset.read().await;
         let position_accounts = positions_by_asset
-            .get(asset_symbol)
+            .get(&symbols::normalize(asset_symbol))
             .cloned()
             .unwrap_or_default();
         drop(positions_by_asset);
@@ -540,6 +1931,16 @@ impl PositionMonitor {
             .collect()
     }
 
+    /// Summed size of the open positions on a symbol, both sides
+    pub async fn open_interest(&self, asset_symbol: &str) -> Decimal {
+        self.get_positions_by_asset(asset_symbol)
+            .await
+            .iter()
+            .filter(|position| position.is_open())
+            .map(|position| position.size)
+            .sum()
+    }
+
     /// Get all positions
     pub async fn get_all_positions(&self) -> Vec<Position> {
         let positions = self.positions.read().await;
@@ -548,57 +1949,191 @@ impl PositionMonitor {
 
     /// Get statistics
     pub async fn get_statistics(&self) -> MonitorStatistics {
+        self.get_statistics_for(None).await
+    }
+
+    /// Statistics over the positions of `owners`, or all of them. Retention
+    /// counters are the whole cache's either way
+    pub async fn get_statistics_for(&self, owners: Option<&HashSet<Pubkey>>) -> MonitorStatistics {
         let positions = self.positions.read().await;
+        let positions: Vec<&Position> = positions
+            .values()
+            .filter(|position| owners.is_none_or(|owners| owners.contains(&position.owner)))
+            .collect();
+
+        let mut stats = MonitorStatistics {
+            total_positions: positions.len(),
+            ..Default::default()
+        };
+
+        for position in &positions {
+            if position.is_closed() {
+                stats.closed_positions += 1;
+            }
 
-        let mut stats = MonitorStatistics::default();
-        stats.total_positions = positions.len();
+            if position.status == PositionStatus::MarginCall {
+                stats.margin_call_positions += 1;
+            }
 
-        for position in positions.values() {
             if position.is_open() {
                 stats.open_positions += 1;
                 stats.total_unrealized_pnl = stats
                     .total_unrealized_pnl
                     .checked_add(position.unrealized_pnl)
                     .unwrap_or(stats.total_unrealized_pnl);
+
+                let deficit =
+                    MarginCalculator::calculate_margin_deficit(position.margin, position.unrealized_pnl);
+                if deficit > Decimal::ZERO {
+                    stats.underwater_positions += 1;
+                    stats.total_margin_deficit += deficit;
+                }
             }
         }
 
-        let positions_by_asset = self.positions_by_asset.read().await;
-        stats.assets_monitored = positions_by_asset.len();
+        stats.assets_monitored = match owners {
+            Some(_) => positions
+                .iter()
+                .map(|position| position.symbol.as_str())
+                .collect::<HashSet<_>>()
+                .len(),
+            None => self.positions_by_asset.read().await.len(),
+        };
+        stats.retention = self.retention_metrics.stats();
 
         stats
     }
 
+    /// Open positions of `owners`, or everyone's, whose losses at their last
+    /// mark exceed their margin, most severe first
+    pub async fn underwater_positions(&self, owners: Option<&HashSet<Pubkey>>) -> Vec<UnderwaterPosition> {
+        let mut underwater: Vec<UnderwaterPosition> = self
+            .positions
+            .read()
+            .await
+            .values()
+            .filter(|position| position.is_open())
+            .filter(|position| owners.is_none_or(|owners| owners.contains(&position.owner)))
+            .filter_map(|position| {
+                let margin_deficit =
+                    MarginCalculator::calculate_margin_deficit(position.margin, position.unrealized_pnl);
+                if margin_deficit.is_zero() {
+                    return None;
+                }
+
+                let margin_ratio = MarginCalculator::calculate_margin_ratio(
+                    position.margin,
+                    position.unrealized_pnl,
+                    position.size,
+                    position.mark_price,
+                )
+                .ok()?;
+
+                Some(UnderwaterPosition {
+                    position_account: position.position_account,
+                    owner: position.owner,
+                    symbol: position.symbol.clone(),
+                    mark_price: position.mark_price,
+                    margin_ratio,
+                    margin_deficit,
+                })
+            })
+            .collect();
+
+        rank_by_severity(&mut underwater);
+        underwater
+    }
+
     fn clone_for_task(&self) -> Self {
         Self {
             solana_client: Arc::clone(&self.solana_client),
-            oracle_client: Arc::clone(&self.oracle_client),
-            rpc_client: Arc::clone(&self.rpc_client),
+            price_source: Arc::clone(&self.price_source),
+            rpc_pool: Arc::clone(&self.rpc_pool),
             config: self.config.clone(),
             redis_client: self.redis_client.clone(),
             positions: Arc::clone(&self.positions),
             positions_by_asset: Arc::clone(&self.positions_by_asset),
             positions_by_user: Arc::clone(&self.positions_by_user),
-            position_update_tx: self.position_update_tx.clone(),
-            price_update_tx: self.price_update_tx.clone(),
+            events: Arc::clone(&self.events),
             liquidation_service: Arc::clone(&self.liquidation_service),
+            margin_ratio_alerts: Arc::clone(&self.margin_ratio_alerts),
+            portfolios: Arc::clone(&self.portfolios),
+            position_alerts: Arc::clone(&self.position_alerts),
+            price_alerts: Arc::clone(&self.price_alerts),
+            strategies: Arc::clone(&self.strategies),
+            external_positions: Arc::clone(&self.external_positions),
             running: Arc::clone(&self.running),
+            leader_election: self.leader_election.clone(),
+            sync_status: Arc::clone(&self.sync_status),
+            mutations: Arc::clone(&self.mutations),
+            history_store: self.history_store.clone(),
+            retention_metrics: Arc::clone(&self.retention_metrics),
+            scan_state: Arc::clone(&self.scan_state),
+            rpc_metrics: Arc::clone(&self.rpc_metrics),
+            price_feed_metrics: Arc::clone(&self.price_feed_metrics),
+            latency_metrics: Arc::clone(&self.latency_metrics),
+            symbol_configs: Arc::clone(&self.symbol_configs),
+            breakers: Arc::clone(&self.breakers),
+            account_stream: self.account_stream.clone(),
+            stream_connected: Arc::clone(&self.stream_connected),
+            transaction_watch: self.transaction_watch.clone(),
         }
     }
 
+    /// RPC calls made by the refresher and mutation verifier since startup
+    pub fn rpc_stats(&self) -> Vec<(&'static str, RpcCallStats)> {
+        self.rpc_metrics.snapshot()
+    }
+
+    /// Circuit breakers that have seen a failure, by key
+    pub fn breaker_stats(&self) -> Vec<(String, BreakerStats)> {
+        self.breakers.snapshot(std::time::Instant::now())
+    }
+
+    /// Health of each RPC endpoint, in configured order
+    pub fn rpc_endpoints(&self) -> Vec<RpcEndpointHealth> {
+        self.rpc_pool.snapshot()
+    }
+
+    /// Whether any RPC endpoint is in rotation
+    pub fn rpc_healthy(&self) -> bool {
+        self.rpc_pool.is_healthy()
+    }
+
+    /// Oracle publish-time gaps and reordering seen since startup, by symbol
+    pub fn price_feed_stats(&self) -> Vec<(String, PriceFeedStats)> {
+        self.price_feed_metrics.snapshot()
+    }
+
+    /// Oracle response to liquidation check and alert, over recent ticks
+    pub fn latency_stats(&self) -> Vec<(LatencyStage, LatencyStats)> {
+        self.latency_metrics.snapshot()
+    }
+
+    pub fn alert_latency_budget(&self) -> Duration {
+        self.latency_metrics.budget()
+    }
+
     pub async fn get_cached_price(&self, symbol: &str) -> Option<Decimal> {
-        let oracle = self.oracle_client.read().await;
-        oracle.get_cached_price(symbol).await
+        self.price_source.get_cached_price(symbol).await
+    }
+
+    /// Cached price with its publish and fetch times
+    pub async fn get_cached_quote(&self, symbol: &str) -> Option<CachedQuote> {
+        self.price_source.get_cached_quote(symbol).await
     }
 
     pub async fn get_monitored_symbols(&self) -> Vec<String> {
-        let oracle = self.oracle_client.read().await;
-        oracle.get_symbols()
+        self.price_source.get_symbols().await
     }
 
     pub async fn fetch_price(&self, symbol: &str) -> Result<Decimal> {
-        let oracle = self.oracle_client.read().await;
-        oracle.fetch_price(symbol).await
+        self.price_source.fetch_price(symbol).await
+    }
+
+    /// Oracle feed ID `symbol` is priced from
+    pub async fn oracle_feed_id(&self, symbol: &str) -> Option<String> {
+        self.price_source.feed_id(symbol).await
     }
 }
 
@@ -606,6 +2141,33 @@ impl PositionMonitor {
 pub struct MonitorStatistics {
     pub total_positions: usize,
     pub open_positions: usize,
+    /// Open positions under a margin call
+    pub margin_call_positions: usize,
+    /// Closed or liquidated positions still held in memory
+    pub closed_positions: usize,
     pub assets_monitored: usize,
     pub total_unrealized_pnl: Decimal,
+    /// Open positions whose losses exceed their margin
+    pub underwater_positions: usize,
+    /// Losses beyond margin across them, the backstop's exposure at the marks
+    pub total_margin_deficit: Decimal,
+    pub retention: RetentionStats,
+}
+
+/// Alert on `position` falling below the `tier` multiple of maintenance
+fn margin_ratio_alert(position: &Position, margin_ratio: Decimal, tier: Decimal) -> LiquidationAlert {
+    LiquidationAlert {
+        position_account: position.position_account,
+        symbol: position.symbol.clone(),
+        side: position.side,
+        liquidation_price: position.liquidation_price,
+        current_price: position.mark_price,
+        risk_type: Risk::MarginWarning,
+        margin_call_deadline: None,
+        owner: None,
+        estimated_loss: None,
+        margin_ratio: Some(margin_ratio),
+        margin_ratio_tier: Some(tier),
+    }
+    .with_position(position)
 }
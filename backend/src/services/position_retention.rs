@@ -0,0 +1,152 @@
+/// Position Retention
+/// Closed and liquidated positions stay in the monitor for a while so recent
+/// history is served from memory, then move to the history store. A size cap
+/// bounds memory when closes come in faster than the retention window drains
+use chrono::{DateTime, Duration, Utc};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    /// How long a closed position is kept in memory after it closed
+    pub closed_retention: Duration,
+    /// Most closed positions kept in memory, oldest closes go first beyond this
+    pub max_closed_positions: usize,
+    pub eviction_interval_ms: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            closed_retention: Duration::hours(6),
+            max_closed_positions: 10_000,
+            eviction_interval_ms: 60_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EvictionReason {
+    /// Closed longer than `closed_retention` ago
+    Expired,
+    /// Pushed out by `max_closed_positions`
+    OverCap,
+}
+
+/// Closed positions to evict, given (position_account, closed_at) for every
+/// closed position in memory. Expired ones first, then the oldest closes
+/// until the rest fit under the cap
+pub fn select_evictions(
+    config: &RetentionConfig,
+    mut closed: Vec<(Pubkey, DateTime<Utc>)>,
+    now: DateTime<Utc>,
+) -> Vec<(Pubkey, EvictionReason)> {
+    closed.sort_by_key(|(_, closed_at)| *closed_at);
+
+    let cutoff = now - config.closed_retention;
+    let expired = closed.partition_point(|(_, closed_at)| *closed_at <= cutoff);
+    let over_cap = (closed.len() - expired).saturating_sub(config.max_closed_positions);
+
+    closed
+        .into_iter()
+        .take(expired + over_cap)
+        .enumerate()
+        .map(|(index, (account, _))| {
+            let reason = if index < expired {
+                EvictionReason::Expired
+            } else {
+                EvictionReason::OverCap
+            };
+            (account, reason)
+        })
+        .collect()
+}
+
+/// Eviction counters since startup
+#[derive(Debug, Default)]
+pub struct RetentionMetrics {
+    evicted_expired: AtomicU64,
+    evicted_over_cap: AtomicU64,
+    persist_failures: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetentionStats {
+    pub evicted_expired: u64,
+    pub evicted_over_cap: u64,
+    /// Eviction passes that could not write to the history store
+    pub persist_failures: u64,
+}
+
+impl RetentionMetrics {
+    pub fn record_eviction(&self, reason: EvictionReason) {
+        let counter = match reason {
+            EvictionReason::Expired => &self.evicted_expired,
+            EvictionReason::OverCap => &self.evicted_over_cap,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_persist_failure(&self) {
+        self.persist_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> RetentionStats {
+        RetentionStats {
+            evicted_expired: self.evicted_expired.load(Ordering::Relaxed),
+            evicted_over_cap: self.evicted_over_cap.load(Ordering::Relaxed),
+            persist_failures: self.persist_failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closed(now: DateTime<Utc>, minutes_ago: &[i64]) -> Vec<(Pubkey, DateTime<Utc>)> {
+        minutes_ago
+            .iter()
+            .map(|minutes| (Pubkey::new_unique(), now - Duration::minutes(*minutes)))
+            .collect()
+    }
+
+    #[test]
+    fn test_select_evictions_by_age() {
+        let now = Utc::now();
+        let config = RetentionConfig {
+            closed_retention: Duration::hours(1),
+            ..Default::default()
+        };
+        let positions = closed(now, &[5, 90, 30, 61]);
+
+        let evicted = select_evictions(&config, positions.clone(), now);
+
+        assert_eq!(evicted.len(), 2);
+        assert_eq!(evicted[0], (positions[1].0, EvictionReason::Expired));
+        assert_eq!(evicted[1], (positions[3].0, EvictionReason::Expired));
+    }
+
+    #[test]
+    fn test_select_evictions_over_cap_takes_oldest() {
+        let now = Utc::now();
+        let config = RetentionConfig {
+            closed_retention: Duration::hours(1),
+            max_closed_positions: 2,
+            ..Default::default()
+        };
+        let positions = closed(now, &[5, 120, 30, 10, 20]);
+
+        let evicted = select_evictions(&config, positions.clone(), now);
+
+        assert_eq!(
+            evicted,
+            vec![
+                (positions[1].0, EvictionReason::Expired),
+                (positions[2].0, EvictionReason::OverCap),
+                (positions[4].0, EvictionReason::OverCap),
+            ]
+        );
+        assert!(select_evictions(&config, closed(now, &[1, 2]), now).is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+/// Position Scan
+/// Most refresh ticks ask getProgramAccounts for only the bytes of each
+/// Position account that can change (dataSlice), fingerprint them, and fetch
+/// full data just for accounts that are new or whose fingerprint moved.
+/// A full scan every `full_scan_every` ticks keeps the cache honest. For
+/// programs with 100k+ positions a chunked full scan lists keys only and
+/// fetches data in batches, so the whole account set is never held at once
+use solana_sdk::pubkey::Pubkey;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// Position accounts are allocated at their max size with the symbol
+/// (String, max 32 chars) before every mutable field, so `size` starts at
+/// 45 for an empty symbol and `status` ends at 144 for a full one
+pub const POSITION_MUTABLE_OFFSET: usize = 8 + 32 + 4 + 1;
+pub const POSITION_MUTABLE_LEN: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    /// Every Nth refresh is a full scan, 1 turns sliced scans off
+    pub full_scan_every: u64,
+    /// getMultipleAccounts accepts at most 100 keys per call
+    pub fetch_batch_size: usize,
+    /// Full scans list keys, then fetch and apply accounts batch by batch
+    pub chunked: bool,
+    /// getMultipleAccounts batches in flight during a chunked full scan
+    pub fetch_concurrency: usize,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            full_scan_every: 30,
+            fetch_batch_size: 100,
+            chunked: false,
+            fetch_concurrency: 4,
+        }
+    }
+}
+
+/// Fingerprint of an account's mutable window plus its lamports. Takes full
+/// account data or the sliced bytes the RPC returns, both hash the same
+pub fn fingerprint(lamports: u64, data: &[u8], sliced: bool) -> u64 {
+    let window = if sliced {
+        data
+    } else {
+        let end = data.len().min(POSITION_MUTABLE_OFFSET + POSITION_MUTABLE_LEN);
+        data.get(POSITION_MUTABLE_OFFSET..end).unwrap_or_default()
+    };
+
+    let mut hasher = DefaultHasher::new();
+    lamports.hash(&mut hasher);
+    window.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// How far a chunked full scan has got
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanProgress {
+    /// Position accounts the scan listed
+    pub total: usize,
+    /// Of those, accounts whose batch has been fetched and applied
+    pub fetched: usize,
+}
+
+impl ScanProgress {
+    pub fn pct(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.fetched as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// What a sliced scan found compared with the last one
+#[derive(Debug, Default)]
+pub struct ScanDiff {
+    /// Every account the scan returned
+    pub live: HashSet<Pubkey>,
+    /// New or modified accounts that need a full fetch
+    pub changed: Vec<Pubkey>,
+}
+
+/// Fingerprints from the last scan and the refresh tick counter
+#[derive(Debug, Default)]
+pub struct ScanState {
+    fingerprints: HashMap<Pubkey, u64>,
+    ticks: u64,
+}
+
+impl ScanState {
+    /// Advance the tick counter, true when this tick should be a full scan
+    pub fn next_tick_is_full(&mut self, config: &ScanConfig) -> bool {
+        let full = self.ticks.is_multiple_of(config.full_scan_every.max(1));
+        self.ticks += 1;
+        full
+    }
+
+    /// Record fingerprints from a full scan, replacing everything known
+    pub fn reset(&mut self, fingerprints: impl IntoIterator<Item = (Pubkey, u64)>) {
+        self.fingerprints = fingerprints.into_iter().collect();
+    }
+
+    /// Compare a sliced scan with the stored fingerprints and store the new
+    /// ones. Accounts missing from the scan are forgotten
+    pub fn diff(&mut self, scanned: impl IntoIterator<Item = (Pubkey, u64)>) -> ScanDiff {
+        let mut diff = ScanDiff::default();
+        let mut fingerprints = HashMap::with_capacity(self.fingerprints.len());
+
+        for (account, fingerprint) in scanned {
+            if self.fingerprints.get(&account) != Some(&fingerprint) {
+                diff.changed.push(account);
+            }
+            diff.live.insert(account);
+            fingerprints.insert(account, fingerprint);
+        }
+
+        self.fingerprints = fingerprints;
+        diff
+    }
+
+    /// Record an account seen outside a scan, so the next sliced scan does
+    /// not fetch it again
+    pub fn record(&mut self, account: Pubkey, fingerprint: u64) {
+        self.fingerprints.insert(account, fingerprint);
+    }
+
+    /// Drop a fingerprint so the account is fetched again next scan, used
+    /// when its full fetch failed
+    pub fn forget(&mut self, account: &Pubkey) {
+        self.fingerprints.remove(account);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::on_chain_types::{
+        OnChainPosition, OnChainPositionChange, OnChainPositionStatus, OnChainSide, POSITION_HISTORY_LEN,
+    };
+    use anchor_lang::AnchorSerialize;
+
+    fn account_data(symbol: &str, size: u64, status: OnChainPositionStatus) -> Vec<u8> {
+        let position = OnChainPosition {
+            owner: Pubkey::new_unique(),
+            symbol: symbol.to_string(),
+            side: OnChainSide::Long,
+            size,
+            entry_price: 1,
+            margin: 1,
+            leverage: 1,
+            unrealized_pnl: 0,
+            realized_pnl: 0,
+            funding_accrued: 0,
+            liquidation_price: 1,
+            last_update: 0,
+            status,
+            bump: 255,
+            history: [OnChainPositionChange::default(); POSITION_HISTORY_LEN],
+            history_count: 0,
+            funding_settled: 0,
+        };
+
+        let mut data = OnChainPosition::DISCRIMINATOR.to_vec();
+        position.serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_scan_progress_pct() {
+        assert_eq!(ScanProgress { total: 0, fetched: 0 }.pct(), 100.0);
+        assert_eq!(ScanProgress { total: 400, fetched: 100 }.pct(), 25.0);
+        assert_eq!(ScanProgress { total: 400, fetched: 400 }.pct(), 100.0);
+    }
+
+    #[test]
+    fn test_mutable_window_covers_every_symbol_length() {
+        for symbol in ["", "SOL-USD", "ABCDEFGHIJKLMNOPQRSTUVWXYZ012345"] {
+            let open = account_data(symbol, 10, OnChainPositionStatus::Open);
+            let resized = account_data(symbol, 11, OnChainPositionStatus::Open);
+            let closed = account_data(symbol, 10, OnChainPositionStatus::Closed);
+
+            let window_end = POSITION_MUTABLE_OFFSET + POSITION_MUTABLE_LEN;
+            // The history and settled funding after bump are only written
+            // alongside size, margin or status
+            let bump_at = open.len() - POSITION_HISTORY_LEN * OnChainPositionChange::LEN - 4 - 8 - 1;
+            assert!(bump_at <= window_end, "status past window for {:?}", symbol);
+
+            assert_ne!(fingerprint(1, &open, false), fingerprint(1, &resized, false));
+            assert_ne!(fingerprint(1, &open, false), fingerprint(1, &closed, false));
+
+            let sliced = &open[POSITION_MUTABLE_OFFSET..open.len().min(window_end)];
+            assert_eq!(fingerprint(1, &open, false), fingerprint(1, sliced, true));
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_new_and_changed_accounts() {
+        let (kept, modified, dropped, added) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        let mut state = ScanState::default();
+        state.reset([(kept, 1), (modified, 2), (dropped, 3)]);
+
+        let diff = state.diff([(kept, 1), (modified, 20), (added, 4)]);
+
+        assert_eq!(diff.changed, vec![modified, added]);
+        assert_eq!(diff.live.len(), 3);
+        assert!(!diff.live.contains(&dropped));
+
+        state.forget(&added);
+        assert_eq!(state.diff([(kept, 1), (modified, 20), (added, 4)]).changed, vec![added]);
+    }
+}
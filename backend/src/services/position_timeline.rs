@@ -0,0 +1,313 @@
+/// Position Timeline
+/// One position's history in order: its open, the events recorded against it
+/// (modifications, margin calls, alerts) and each realized PnL event up to the
+/// final close or liquidation, with the PnL each line realized and the running
+/// total. Funding is settled on closes, so it shows on those lines
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::domain::RealizedPnL;
+use crate::infrastructure::{PositionEventKind, PositionEventRecord, PositionOpenRecord};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    Open,
+    Modified,
+    MarginCall,
+    MarginCallCleared,
+    Alert,
+    PartialClose,
+    PartialLiquidation,
+    Close,
+    Liquidation,
+}
+
+impl From<PositionEventKind> for TimelineEventKind {
+    fn from(kind: PositionEventKind) -> Self {
+        match kind {
+            PositionEventKind::Modified => TimelineEventKind::Modified,
+            PositionEventKind::MarginCall => TimelineEventKind::MarginCall,
+            PositionEventKind::MarginCallCleared => TimelineEventKind::MarginCallCleared,
+            PositionEventKind::Alert => TimelineEventKind::Alert,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimelineEntry {
+    pub kind: TimelineEventKind,
+    pub at: DateTime<Utc>,
+    /// Size after the event, none where it isn't known
+    pub size: Option<Decimal>,
+    pub margin: Option<Decimal>,
+    /// Entry price of the open, close price of a close, mark price otherwise
+    pub price: Option<Decimal>,
+    /// PnL this event realized
+    pub pnl_delta: Decimal,
+    /// PnL realized up to and including this event
+    pub cumulative_pnl: Decimal,
+    /// Funding settled by a close
+    pub funding: Option<Decimal>,
+    pub detail: Option<String>,
+    pub signature: Option<String>,
+}
+
+impl TimelineEntry {
+    fn new(kind: TimelineEventKind, at: DateTime<Utc>) -> Self {
+        Self {
+            kind,
+            at,
+            size: None,
+            margin: None,
+            price: None,
+            pnl_delta: Decimal::ZERO,
+            cumulative_pnl: Decimal::ZERO,
+            funding: None,
+            detail: None,
+            signature: None,
+        }
+    }
+}
+
+/// Merge a position's records into one timeline, oldest first
+/// Without the open record the size isn't tracked, and only the last realized
+/// PnL event counts as the final close
+pub fn build_position_timeline(
+    open: Option<PositionOpenRecord>,
+    realized: Vec<RealizedPnL>,
+    events: Vec<PositionEventRecord>,
+) -> Vec<TimelineEntry> {
+    let mut entries = Vec::with_capacity(1 + realized.len() + events.len());
+
+    if let Some(open) = &open {
+        let mut entry = TimelineEntry::new(TimelineEventKind::Open, open.opened_at);
+        entry.size = Some(open.size);
+        entry.margin = Some(open.margin);
+        entry.price = Some(open.entry_price);
+        entry.signature = Some(open.signature.clone());
+        entries.push(entry);
+    }
+
+    for event in events {
+        let mut entry = TimelineEntry::new(event.kind.into(), event.recorded_at);
+        entry.size = event.size;
+        entry.margin = event.margin;
+        entry.price = event.mark_price;
+        entry.detail = event.detail;
+        entry.signature = event.signature;
+        entries.push(entry);
+    }
+
+    let last_realized = realized.len().saturating_sub(1);
+    for (i, record) in realized.into_iter().enumerate() {
+        // Placeholder, settled below once the size before it is known
+        let kind = if i == last_realized {
+            final_kind(record.liquidation)
+        } else {
+            partial_kind(record.liquidation)
+        };
+
+        let mut entry = TimelineEntry::new(kind, record.closed_at);
+        entry.size = Some(record.size_closed);
+        entry.price = Some(record.close_price);
+        entry.pnl_delta = record.amount;
+        entry.funding = Some(record.funding);
+        entry.signature = Some(record.signature);
+        entries.push(entry);
+    }
+
+    // Stable, so an event keeps its place behind the open at the same instant
+    entries.sort_by_key(|entry| entry.at);
+
+    let mut size = open.map(|open| open.size);
+    let mut cumulative_pnl = Decimal::ZERO;
+
+    for entry in &mut entries {
+        match entry.kind {
+            TimelineEventKind::Open => {}
+            TimelineEventKind::PartialClose
+            | TimelineEventKind::PartialLiquidation
+            | TimelineEventKind::Close
+            | TimelineEventKind::Liquidation => {
+                // `size` holds the size closed until here
+                let size_closed = entry.size.unwrap_or(Decimal::ZERO);
+                let liquidation = matches!(
+                    entry.kind,
+                    TimelineEventKind::PartialLiquidation | TimelineEventKind::Liquidation
+                );
+
+                if let Some(current) = size {
+                    let remaining = (current - size_closed).max(Decimal::ZERO);
+                    entry.kind = if remaining.is_zero() {
+                        final_kind(liquidation)
+                    } else {
+                        partial_kind(liquidation)
+                    };
+                    size = Some(remaining);
+                }
+
+                entry.size = size;
+                cumulative_pnl += entry.pnl_delta;
+            }
+            _ => {
+                if entry.size.is_some() {
+                    size = entry.size;
+                }
+            }
+        }
+
+        entry.cumulative_pnl = cumulative_pnl;
+    }
+
+    entries
+}
+
+fn partial_kind(liquidation: bool) -> TimelineEventKind {
+    if liquidation {
+        TimelineEventKind::PartialLiquidation
+    } else {
+        TimelineEventKind::PartialClose
+    }
+}
+
+fn final_kind(liquidation: bool) -> TimelineEventKind {
+    if liquidation {
+        TimelineEventKind::Liquidation
+    } else {
+        TimelineEventKind::Close
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Side;
+    use chrono::Duration;
+    use rust_decimal_macros::dec;
+    use solana_sdk::pubkey::Pubkey;
+    use uuid::Uuid;
+
+    fn realized(
+        position_account: Pubkey,
+        at: DateTime<Utc>,
+        size_closed: Decimal,
+        amount: Decimal,
+        liquidation: bool,
+    ) -> RealizedPnL {
+        RealizedPnL {
+            id: Uuid::new_v4(),
+            position_account,
+            owner: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size_closed,
+            entry_price: dec!(100),
+            close_price: dec!(90),
+            fee: Decimal::ZERO,
+            funding: dec!(-1),
+            amount,
+            liquidation,
+            signature: "sig".to_string(),
+            closed_at: at,
+        }
+    }
+
+    fn event(position_account: Pubkey, at: DateTime<Utc>, kind: PositionEventKind) -> PositionEventRecord {
+        PositionEventRecord {
+            id: Uuid::new_v4(),
+            position_account,
+            kind,
+            size: None,
+            margin: None,
+            mark_price: Some(dec!(95)),
+            detail: None,
+            signature: None,
+            recorded_at: at,
+        }
+    }
+
+    #[test]
+    fn test_timeline_orders_and_accumulates() {
+        let account = Pubkey::new_unique();
+        let opened_at = Utc::now() - Duration::hours(5);
+        let at = |hours: i64| opened_at + Duration::hours(hours);
+
+        let open = PositionOpenRecord {
+            position_account: account,
+            owner: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size: dec!(10),
+            entry_price: dec!(100),
+            margin: dec!(100),
+            signature: "open".to_string(),
+            opened_at,
+        };
+
+        let mut modified = event(account, at(1), PositionEventKind::Modified);
+        modified.size = Some(dec!(8));
+        modified.margin = Some(dec!(120));
+
+        let timeline = build_position_timeline(
+            Some(open),
+            vec![
+                realized(account, at(3), dec!(3), dec!(-30), true),
+                realized(account, at(4), dec!(5), dec!(-50), true),
+            ],
+            vec![
+                event(account, at(2), PositionEventKind::MarginCall),
+                modified,
+            ],
+        );
+
+        let kinds: Vec<_> = timeline.iter().map(|entry| entry.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TimelineEventKind::Open,
+                TimelineEventKind::Modified,
+                TimelineEventKind::MarginCall,
+                TimelineEventKind::PartialLiquidation,
+                TimelineEventKind::Liquidation,
+            ]
+        );
+
+        // Sizes follow the modification, then each liquidation
+        assert_eq!(timeline[3].size, Some(dec!(5)));
+        assert_eq!(timeline[4].size, Some(Decimal::ZERO));
+
+        assert_eq!(timeline[2].pnl_delta, Decimal::ZERO);
+        assert_eq!(timeline[3].pnl_delta, dec!(-30));
+        assert_eq!(timeline[3].cumulative_pnl, dec!(-30));
+        assert_eq!(timeline[4].cumulative_pnl, dec!(-80));
+    }
+
+    #[test]
+    fn test_timeline_without_open_record() {
+        let account = Pubkey::new_unique();
+        let now = Utc::now();
+
+        let timeline = build_position_timeline(
+            None,
+            vec![
+                realized(account, now, dec!(2), dec!(5), false),
+                realized(account, now + Duration::minutes(1), dec!(2), dec!(7), false),
+            ],
+            vec![event(account, now - Duration::minutes(1), PositionEventKind::Alert)],
+        );
+
+        let kinds: Vec<_> = timeline.iter().map(|entry| entry.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TimelineEventKind::Alert,
+                TimelineEventKind::PartialClose,
+                TimelineEventKind::Close,
+            ]
+        );
+        assert_eq!(timeline[1].size, None);
+        assert_eq!(timeline[2].cumulative_pnl, dec!(12));
+    }
+}
@@ -0,0 +1,444 @@
+/// Price Alerts
+/// User-defined price levels per symbol, independent of positions
+/// Levels sit in a Redis sorted set per symbol and direction, so each price
+/// update finds every crossed alert with one range query, like liquidations
+/// Alerts are one-shot: a triggered alert is removed and delivered over the
+/// event stream and its webhook, if it has one
+/// Every alert belongs to an owner, who signs its changes, and each owner's
+/// ids sit in a set of their own so listing them doesn't scan every alert
+/// Webhooks only go to https URLs on public addresses, never following redirects
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::services::{DomainEvent, EventBus};
+
+/// Alert id -> alert JSON
+const ALERTS_KEY: &str = "price_alerts";
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const MAX_PRICE_ALERTS_PER_OWNER: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceDirection {
+    Above,
+    Below,
+}
+
+impl PriceDirection {
+    /// Whether `current_price` has reached `level` from this side
+    pub fn is_triggered(self, level: Decimal, current_price: Decimal) -> bool {
+        match self {
+            PriceDirection::Above => current_price >= level,
+            PriceDirection::Below => current_price <= level,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            PriceDirection::Above => "above",
+            PriceDirection::Below => "below",
+        }
+    }
+}
+
+fn levels_key(symbol: &str, direction: PriceDirection) -> String {
+    format!("price_alerts:{}:{}", symbol, direction.as_str())
+}
+
+/// Owner -> ids of their pending alerts
+fn owner_key(owner: &str) -> String {
+    format!("price_alerts:owner:{}", owner)
+}
+
+/// Message the wallet signs to create an alert
+pub fn create_price_alert_message(owner: &Pubkey, timestamp: i64) -> String {
+    format!("Create price alert for {} at {}", owner, timestamp)
+}
+
+/// Message the wallet signs to replace alert `id`'s fields
+pub fn update_price_alert_message(owner: &Pubkey, id: Uuid, timestamp: i64) -> String {
+    format!("Update price alert {} for {} at {}", id, owner, timestamp)
+}
+
+/// Message the wallet signs to delete alert `id`
+pub fn delete_price_alert_message(owner: &Pubkey, id: Uuid, timestamp: i64) -> String {
+    format!("Delete price alert {} for {} at {}", id, owner, timestamp)
+}
+
+/// Whether a webhook may be sent to `ip`: not loopback, private, link-local,
+/// shared, documentation, multicast or unspecified
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local fc00::/7 and link-local fe80::/10
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80
+                    // Documentation 2001:db8::/32
+                    || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+            }
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // 0.0.0.0/8, carrier-grade NAT 100.64.0.0/10 and reserved 240.0.0.0/4
+        || a == 0
+        || (a == 100 && (b & 0xc0) == 64)
+        || a >= 240)
+}
+
+/// Resolve a webhook URL's host to the public address to connect to, so the
+/// request can be pinned to an address that was checked
+async fn resolve_webhook(url: &reqwest::Url) -> Result<(String, SocketAddr)> {
+    if url.scheme() != "https" {
+        return Err(anyhow!("Webhook must be an https URL"));
+    }
+    let host = url.host_str().ok_or_else(|| anyhow!("Webhook URL has no host"))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .with_context(|| format!("Failed to resolve {}", host))?
+        .collect();
+
+    // Every address has to be public, else the host could pick a private one
+    if addrs.is_empty() || addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(anyhow!("Webhook host {} does not resolve to public addresses only", host));
+    }
+
+    Ok((host.to_string(), addrs[0]))
+}
+
+/// Fields a user sets on an alert
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceAlertSpec {
+    pub symbol: String,
+    pub direction: PriceDirection,
+    pub price: Decimal,
+    /// Base58 so webhook bodies match the API, none only on alerts stored
+    /// before owners were required
+    pub owner: Option<String>,
+    pub webhook_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceAlert {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub spec: PriceAlertSpec,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An alert whose level was reached, also the webhook body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceAlertTriggered {
+    pub alert: PriceAlert,
+    pub current_price: Decimal,
+    pub triggered_at: DateTime<Utc>,
+}
+
+pub struct PriceAlertService {
+    redis_client: redis::Client,
+    events: Arc<dyn EventBus>,
+}
+
+impl PriceAlertService {
+    pub fn new(redis_client: redis::Client, events: Arc<dyn EventBus>) -> Self {
+        Self {
+            redis_client,
+            events,
+        }
+    }
+
+    /// Pending alerts `owner` has
+    pub async fn count_for_owner(&self, owner: &str) -> Result<usize> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let count: usize = conn.scard(owner_key(owner)).await?;
+        Ok(count)
+    }
+
+    pub async fn create(&self, spec: PriceAlertSpec) -> Result<PriceAlert> {
+        let alert = PriceAlert {
+            id: Uuid::new_v4(),
+            spec,
+            created_at: Utc::now(),
+        };
+
+        self.store(&alert).await?;
+        Ok(alert)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<PriceAlert>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let stored: Option<String> = conn.hget(ALERTS_KEY, id.to_string()).await?;
+
+        stored
+            .map(|json| serde_json::from_str(&json).context("Failed to decode price alert"))
+            .transpose()
+    }
+
+    /// Pending alerts, optionally for one symbol or owner, newest first
+    /// An owner's come from their index, only the unfiltered listing reads them all
+    pub async fn list(&self, symbol: Option<&str>, owner: Option<&str>) -> Result<Vec<PriceAlert>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let stored: Vec<String> = match owner {
+            Some(owner) => {
+                let ids: Vec<String> = conn.smembers(owner_key(owner)).await?;
+                if ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let stored: Vec<Option<String>> = redis::cmd("HMGET")
+                    .arg(ALERTS_KEY)
+                    .arg(&ids)
+                    .query_async(&mut conn)
+                    .await?;
+                stored.into_iter().flatten().collect()
+            }
+            None => {
+                let stored: HashMap<String, String> = conn.hgetall(ALERTS_KEY).await?;
+                stored.into_values().collect()
+            }
+        };
+
+        let mut alerts: Vec<PriceAlert> = stored
+            .iter()
+            .filter_map(|json| match serde_json::from_str::<PriceAlert>(json) {
+                Ok(alert) => Some(alert),
+                Err(e) => {
+                    warn!("Skipping undecodable price alert: {}", e);
+                    None
+                }
+            })
+            .filter(|alert| symbol.is_none_or(|symbol| alert.spec.symbol == symbol))
+            .filter(|alert| owner.is_none_or(|owner| alert.spec.owner.as_deref() == Some(owner)))
+            .collect();
+
+        alerts.sort_by_key(|alert| std::cmp::Reverse(alert.created_at));
+        Ok(alerts)
+    }
+
+    /// Replace an alert's fields, keeping its id. None when it no longer exists
+    pub async fn update(&self, id: Uuid, spec: PriceAlertSpec) -> Result<Option<PriceAlert>> {
+        let Some(existing) = self.remove(id).await? else {
+            return Ok(None);
+        };
+
+        let alert = PriceAlert {
+            id,
+            spec,
+            created_at: existing.created_at,
+        };
+
+        self.store(&alert).await?;
+        Ok(Some(alert))
+    }
+
+    /// Delete an alert, returns it when it existed
+    pub async fn remove(&self, id: Uuid) -> Result<Option<PriceAlert>> {
+        let Some(alert) = self.get(id).await? else {
+            return Ok(None);
+        };
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .zrem(levels_key(&alert.spec.symbol, alert.spec.direction), id.to_string())
+            .hdel(ALERTS_KEY, id.to_string());
+        if let Some(owner) = &alert.spec.owner {
+            pipe.srem(owner_key(owner), id.to_string());
+        }
+        pipe.query_async::<_, ()>(&mut conn)
+            .await
+            .context("Failed to remove price alert")?;
+
+        Ok(Some(alert))
+    }
+
+    /// Fire every alert on `symbol` crossed by `current_price`
+    /// Returns how many fired
+    pub async fn check_price_update(&self, symbol: &str, current_price: Decimal) -> Result<usize> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let price = current_price.to_string();
+
+        let above: Vec<String> = conn
+            .zrangebyscore(levels_key(symbol, PriceDirection::Above), "-inf", &price)
+            .await
+            .context("Failed to query price alert levels")?;
+        let below: Vec<String> = conn
+            .zrangebyscore(levels_key(symbol, PriceDirection::Below), &price, "+inf")
+            .await
+            .context("Failed to query price alert levels")?;
+
+        let crossed = above
+            .into_iter()
+            .map(|id| (PriceDirection::Above, id))
+            .chain(below.into_iter().map(|id| (PriceDirection::Below, id)));
+
+        let mut fired = 0usize;
+        for (direction, id) in crossed {
+            let stored: Option<String> = conn.hget(ALERTS_KEY, &id).await?;
+            let Some(alert) = stored.and_then(|json| serde_json::from_str::<PriceAlert>(&json).ok()) else {
+                warn!("Price alert {} crossed but its record is missing", id);
+                conn.zrem::<_, _, ()>(levels_key(symbol, direction), &id).await?;
+                continue;
+            };
+
+            // Scores are doubles, confirm against the exact level
+            if !direction.is_triggered(alert.spec.price, current_price) {
+                continue;
+            }
+
+            // Whoever removes the level owns the alert, so it fires once
+            let removed: usize = conn.zrem(levels_key(symbol, direction), &id).await?;
+            if removed == 0 {
+                continue;
+            }
+            conn.hdel::<_, _, ()>(ALERTS_KEY, &id).await?;
+            if let Some(owner) = &alert.spec.owner {
+                conn.srem::<_, _, ()>(owner_key(owner), &id).await?;
+            }
+
+            info!(
+                "Price alert {} triggered: {} {} {} at {}",
+                alert.id,
+                symbol,
+                direction.as_str(),
+                alert.spec.price,
+                current_price
+            );
+
+            let triggered = PriceAlertTriggered {
+                alert,
+                current_price,
+                triggered_at: Utc::now(),
+            };
+
+            if let Some(url) = triggered.alert.spec.webhook_url.clone() {
+                self.spawn_webhook(url, triggered.clone());
+            }
+
+            self.events.publish(DomainEvent::PriceAlert(triggered));
+            fired += 1;
+        }
+
+        Ok(fired)
+    }
+
+    async fn store(&self, alert: &PriceAlert) -> Result<()> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hset(ALERTS_KEY, alert.id.to_string(), serde_json::to_string(alert)?)
+            .zadd(
+                levels_key(&alert.spec.symbol, alert.spec.direction),
+                alert.id.to_string(),
+                alert.spec.price.to_string(),
+            );
+        if let Some(owner) = &alert.spec.owner {
+            pipe.sadd(owner_key(owner), alert.id.to_string());
+        }
+        pipe.query_async::<_, ()>(&mut conn)
+            .await
+            .context("Failed to store price alert")
+    }
+
+    /// POST the triggered alert to its webhook, best effort. The host is
+    /// resolved and checked first and the request pinned to that address, so
+    /// a DNS change between the check and the connect can't point it inward
+    fn spawn_webhook(&self, url: String, triggered: PriceAlertTriggered) {
+        tokio::spawn(async move {
+            let result = async {
+                let parsed = reqwest::Url::parse(&url).context("Invalid webhook URL")?;
+                let (host, addr) = resolve_webhook(&parsed).await?;
+
+                let http_client = reqwest::Client::builder()
+                    .redirect(reqwest::redirect::Policy::none())
+                    .resolve(&host, addr)
+                    .timeout(WEBHOOK_TIMEOUT)
+                    .build()?;
+
+                http_client
+                    .post(parsed)
+                    .json(&triggered)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                anyhow::Ok(())
+            }
+            .await;
+
+            if let Err(e) = result {
+                warn!("Price alert {} webhook to {} failed: {}", triggered.alert.id, url, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_direction_triggers_at_level() {
+        assert!(PriceDirection::Above.is_triggered(dec!(100), dec!(100)));
+        assert!(PriceDirection::Above.is_triggered(dec!(100), dec!(101)));
+        assert!(!PriceDirection::Above.is_triggered(dec!(100), dec!(99.99)));
+
+        assert!(PriceDirection::Below.is_triggered(dec!(100), dec!(100)));
+        assert!(!PriceDirection::Below.is_triggered(dec!(100), dec!(100.01)));
+    }
+
+    #[test]
+    fn test_webhook_addresses_must_be_public() {
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+}
@@ -0,0 +1,387 @@
+/// Liquidation Replay
+/// Re-runs the monitor's liquidation check over recorded price ticks and
+/// position events, then compares the liquidations it would have made with
+/// the ones that actually happened
+/// Positions are rebuilt from recorded opens and closes; modifies are not
+/// recorded, so a modified position replays with its opening size and margin
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+
+use crate::domain::{RealizedPnL, Side};
+use crate::infrastructure::{PositionOpenRecord, PriceTick};
+use crate::services::{MarginCalculator, MonitorConfig};
+
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    pub maintenance_margin_ratio: Decimal,
+    /// Liquidations this close to the replayed one count as a match
+    pub tolerance: Duration,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            maintenance_margin_ratio: MonitorConfig::default().maintenance_margin_ratio,
+            tolerance: Duration::seconds(5),
+        }
+    }
+}
+
+/// Everything recorded for the replayed range
+/// Opens and closes should reach back far enough to cover positions still
+/// open at `from`, ticks outside [from, to) are ignored
+#[derive(Debug, Clone)]
+pub struct ReplayInput {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub opens: Vec<PositionOpenRecord>,
+    pub closes: Vec<RealizedPnL>,
+    pub ticks: Vec<PriceTick>,
+}
+
+/// A liquidation as (time, price)
+pub type LiquidationPoint = (DateTime<Utc>, Decimal);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// The replay liquidated, the live system never did
+    Missed,
+    /// The live system liquidated after the replay did
+    Late { lag: Duration },
+    /// The live system liquidated before the replay did
+    Early { lead: Duration },
+    /// The live system liquidated, the replay never did
+    Unexpected,
+}
+
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub position_account: Pubkey,
+    pub symbol: String,
+    pub side: Side,
+    pub kind: DivergenceKind,
+    pub replayed: Option<LiquidationPoint>,
+    pub actual: Option<LiquidationPoint>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    pub positions: usize,
+    pub ticks: usize,
+    pub replayed_liquidations: usize,
+    pub actual_liquidations: usize,
+    pub matched: usize,
+    /// Ordered by the earlier of the replayed and actual times
+    pub divergences: Vec<Divergence>,
+}
+
+#[derive(Debug, Clone)]
+struct ReplayPosition {
+    symbol: String,
+    side: Side,
+    size: Decimal,
+    entry_price: Decimal,
+    margin: Decimal,
+    /// Still marked by the replay, false once it liquidated or the owner closed
+    active: bool,
+    replayed: Option<LiquidationPoint>,
+    actual: Option<LiquidationPoint>,
+}
+
+impl ReplayPosition {
+    /// The monitor's rule: margin ratio below maintenance at this price
+    fn should_liquidate(&self, price: Decimal, maintenance_margin_ratio: Decimal) -> bool {
+        MarginCalculator::calculate_unrealized_pnl(self.side, self.size, price, self.entry_price)
+            .and_then(|pnl| {
+                MarginCalculator::should_liquidate(
+                    self.margin,
+                    pnl,
+                    self.size,
+                    price,
+                    maintenance_margin_ratio,
+                )
+            })
+            .unwrap_or(false)
+    }
+}
+
+// Same-instant order: opens, then ticks, then closes, so a close never hides
+// a crossing recorded at the same time
+enum Event<'a> {
+    Open(&'a PositionOpenRecord),
+    Tick(&'a PriceTick),
+    Close(&'a RealizedPnL),
+}
+
+impl Event<'_> {
+    fn key(&self) -> (DateTime<Utc>, u8) {
+        match self {
+            Event::Open(open) => (open.opened_at, 0),
+            Event::Tick(tick) => (tick.recorded_at, 1),
+            Event::Close(close) => (close.closed_at, 2),
+        }
+    }
+}
+
+/// Replay `input` deterministically, the same input always gives the same report
+pub fn replay(input: &ReplayInput, config: &ReplayConfig) -> ReplayReport {
+    let ticks: Vec<&PriceTick> = input
+        .ticks
+        .iter()
+        .filter(|tick| tick.recorded_at >= input.from && tick.recorded_at < input.to)
+        .collect();
+
+    let mut events: Vec<Event> = input
+        .opens
+        .iter()
+        .map(Event::Open)
+        .chain(ticks.iter().map(|tick| Event::Tick(tick)))
+        .chain(input.closes.iter().map(Event::Close))
+        .collect();
+    // Stable, so ties keep the store's order
+    events.sort_by_key(Event::key);
+
+    let mut positions: BTreeMap<Pubkey, ReplayPosition> = BTreeMap::new();
+
+    for event in events {
+        match event {
+            Event::Open(open) => {
+                positions.entry(open.position_account).or_insert(ReplayPosition {
+                    symbol: open.symbol.clone(),
+                    side: open.side,
+                    size: open.size,
+                    entry_price: open.entry_price,
+                    margin: open.margin,
+                    active: true,
+                    replayed: None,
+                    actual: None,
+                });
+            }
+            Event::Tick(tick) => {
+                for position in positions.values_mut() {
+                    if !position.active
+                        || position.symbol != tick.symbol
+                        || !position.should_liquidate(tick.price, config.maintenance_margin_ratio)
+                    {
+                        continue;
+                    }
+                    position.active = false;
+                    position.replayed = Some((tick.recorded_at, tick.price));
+                }
+            }
+            Event::Close(close) => {
+                let Some(position) = positions.get_mut(&close.position_account) else {
+                    continue;
+                };
+
+                if close.closed_at < input.from {
+                    // Settled before the range, nothing to compare
+                    if close.liquidation || close.size_closed >= position.size {
+                        positions.remove(&close.position_account);
+                    } else {
+                        reduce(position, close.size_closed);
+                    }
+                    continue;
+                }
+
+                if close.liquidation {
+                    // Keep marking it, the replay may still liquidate it later
+                    position.actual.get_or_insert((close.closed_at, close.close_price));
+                } else if position.active {
+                    if close.size_closed >= position.size {
+                        position.active = false;
+                    } else {
+                        reduce(position, close.size_closed);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut report = ReplayReport {
+        positions: positions.len(),
+        ticks: ticks.len(),
+        ..Default::default()
+    };
+
+    for (position_account, position) in positions {
+        report.replayed_liquidations += usize::from(position.replayed.is_some());
+        report.actual_liquidations += usize::from(position.actual.is_some());
+
+        let kind = match (position.replayed, position.actual) {
+            (None, None) => continue,
+            (Some(_), None) => DivergenceKind::Missed,
+            (None, Some(_)) => DivergenceKind::Unexpected,
+            (Some((replayed_at, _)), Some((actual_at, _))) => {
+                let lag = actual_at - replayed_at;
+                if lag > config.tolerance {
+                    DivergenceKind::Late { lag }
+                } else if -lag > config.tolerance {
+                    DivergenceKind::Early { lead: -lag }
+                } else {
+                    report.matched += 1;
+                    continue;
+                }
+            }
+        };
+
+        report.divergences.push(Divergence {
+            position_account,
+            symbol: position.symbol,
+            side: position.side,
+            kind,
+            replayed: position.replayed,
+            actual: position.actual,
+        });
+    }
+
+    report.divergences.sort_by_key(|divergence| {
+        let times = [divergence.replayed, divergence.actual];
+        times.into_iter().flatten().map(|(at, _)| at).min()
+    });
+
+    report
+}
+
+/// Partial close, margin is released in proportion to the size closed
+fn reduce(position: &mut ReplayPosition, size_closed: Decimal) {
+    if position.size.is_zero() {
+        return;
+    }
+
+    let remaining = position.size - size_closed;
+    position.margin = position.margin * remaining / position.size;
+    position.size = remaining;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    // 10x long at 100, liquidated once the price falls below ~92.3
+    fn open(opened_at: DateTime<Utc>) -> PositionOpenRecord {
+        PositionOpenRecord {
+            position_account: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size: dec!(10),
+            entry_price: dec!(100),
+            margin: dec!(100),
+            signature: "sig".to_string(),
+            opened_at,
+        }
+    }
+
+    fn close(open: &PositionOpenRecord, closed_at: DateTime<Utc>, liquidation: bool) -> RealizedPnL {
+        RealizedPnL {
+            id: uuid::Uuid::new_v4(),
+            position_account: open.position_account,
+            owner: open.owner,
+            symbol: open.symbol.clone(),
+            side: open.side,
+            size_closed: open.size,
+            entry_price: open.entry_price,
+            close_price: dec!(90),
+            fee: Decimal::ZERO,
+            funding: Decimal::ZERO,
+            amount: Decimal::ZERO,
+            liquidation,
+            signature: "sig".to_string(),
+            closed_at,
+        }
+    }
+
+    fn ticks(prices: &[(i64, Decimal)]) -> Vec<PriceTick> {
+        prices
+            .iter()
+            .map(|(secs, price)| PriceTick {
+                symbol: "SOL-USD".to_string(),
+                price: *price,
+                conf: None,
+                publish_time: None,
+                recorded_at: at(*secs),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_classifies_divergences() {
+        let on_time = open(at(-100));
+        let late = open(at(-100));
+        let missed = open(at(-100));
+        // Closed before the range, ignored
+        let settled = open(at(-100));
+
+        let input = ReplayInput {
+            from: at(0),
+            to: at(100),
+            closes: vec![
+                close(&settled, at(-50), true),
+                close(&on_time, at(32), true),
+                close(&late, at(60), true),
+                close(&missed, at(80), false),
+            ],
+            opens: vec![on_time, late.clone(), missed.clone(), settled],
+            ticks: ticks(&[(10, dec!(99)), (30, dec!(92)), (50, dec!(95))]),
+        };
+
+        let report = replay(&input, &ReplayConfig::default());
+
+        assert_eq!(report.positions, 3);
+        assert_eq!(report.ticks, 3);
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.replayed_liquidations, 3);
+        assert_eq!(report.actual_liquidations, 2);
+
+        let kinds: Vec<(Pubkey, DivergenceKind)> = report
+            .divergences
+            .iter()
+            .map(|divergence| (divergence.position_account, divergence.kind))
+            .collect();
+        let late_kind = DivergenceKind::Late {
+            lag: Duration::seconds(30),
+        };
+        assert_eq!(kinds.len(), 2);
+        assert!(kinds.contains(&(late.position_account, late_kind)));
+        assert!(kinds.contains(&(missed.position_account, DivergenceKind::Missed)));
+    }
+
+    #[test]
+    fn test_unexpected_and_early_liquidations() {
+        let healthy = open(at(0));
+        let early = open(at(0));
+
+        let input = ReplayInput {
+            from: at(0),
+            to: at(100),
+            closes: vec![close(&healthy, at(20), true), close(&early, at(20), true)],
+            opens: vec![healthy.clone(), early.clone()],
+            ticks: ticks(&[(10, dec!(99)), (50, dec!(97))]),
+        };
+
+        let report = replay(&input, &ReplayConfig::default());
+        assert_eq!(report.divergences.len(), 2);
+        assert!(report
+            .divergences
+            .iter()
+            .all(|divergence| divergence.kind == DivergenceKind::Unexpected));
+
+        // A later crossing makes the live liquidation early rather than unexpected
+        let input = ReplayInput {
+            ticks: ticks(&[(10, dec!(99)), (50, dec!(90))]),
+            ..input
+        };
+        let report = replay(&input, &ReplayConfig::default());
+        assert!(report.divergences.iter().all(|divergence| {
+            divergence.kind == DivergenceKind::Early { lead: Duration::seconds(30) }
+        }));
+    }
+}
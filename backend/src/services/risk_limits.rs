@@ -0,0 +1,575 @@
+/// Risk Limits
+/// Per-user caps on the share of collateral one position puts at risk and on
+/// portfolio leverage, set by the user and kept in Redis. The keeper measures
+/// every user with limits against their on-chain collateral and the
+/// monitor's marks, and publishes a `RiskLimitBreached` event when a limit is
+/// first crossed, with how much to cut to get back under it, so clients can
+/// deleverage before margin calls start
+/// Users who opt in also have opens refused by the API while over a limit
+/// Limits are set with the owner's wallet signature over the new limits
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::domain::Position;
+use crate::services::{DomainEvent, EventBus, PositionManager, PositionMonitor};
+
+/// Owner -> limits JSON
+const RISK_LIMITS_KEY: &str = "risk_limits";
+
+// On-chain collateral amounts carry 6 decimals
+const COLLATERAL_DECIMALS: u32 = 6;
+
+/// Identifies one breach across sweeps, the position is none for leverage
+type BreachKey = (RiskLimitKind, Option<Pubkey>);
+
+/// Message the wallet signs to set `limits`, each limit as sent or `none`
+pub fn set_risk_limits_message(owner: &Pubkey, limits: &RiskLimits, timestamp: i64) -> String {
+    let limit = |value: Option<Decimal>| value.map_or_else(|| "none".to_string(), |value| value.to_string());
+    format!(
+        "Set risk limits for {}: position risk {}, portfolio leverage {}, block opens {} at {}",
+        owner,
+        limit(limits.max_position_risk_pct),
+        limit(limits.max_portfolio_leverage),
+        limits.block_opens,
+        timestamp
+    )
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RiskLimits {
+    /// Most of the collateral one position's margin may put at risk, in percent
+    pub max_position_risk_pct: Option<Decimal>,
+    /// Most open notional per unit of equity across every position
+    pub max_portfolio_leverage: Option<Decimal>,
+    /// Refuse opens through the API while over a limit
+    #[serde(default)]
+    pub block_opens: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionRisk {
+    /// None for a position about to be opened
+    pub position_account: Option<Pubkey>,
+    pub symbol: String,
+    pub margin: Decimal,
+    pub notional: Decimal,
+    /// Margin as a percent of collateral, none without collateral
+    pub risk_pct: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskUtilization {
+    pub total_collateral: Decimal,
+    /// Collateral plus unrealized PnL
+    pub equity: Decimal,
+    pub notional: Decimal,
+    /// Notional over equity, none once equity is gone
+    pub portfolio_leverage: Option<Decimal>,
+    pub positions: Vec<PositionRisk>,
+}
+
+impl RiskUtilization {
+    /// Measure `positions` at their last marks against raw on-chain collateral
+    pub fn measure(positions: &[Position], total_collateral: u64) -> Self {
+        let total_collateral = Decimal::new(total_collateral as i64, COLLATERAL_DECIMALS);
+        let mut utilization = Self {
+            total_collateral,
+            equity: total_collateral,
+            notional: Decimal::ZERO,
+            portfolio_leverage: None,
+            positions: Vec::new(),
+        };
+
+        for position in positions.iter().filter(|position| position.is_open()) {
+            utilization.equity += position.unrealized_pnl;
+            utilization.push(
+                Some(position.position_account),
+                &position.symbol,
+                position.margin,
+                position.size * position.mark_price,
+            );
+        }
+
+        utilization.portfolio_leverage = utilization.leverage();
+        utilization
+    }
+
+    /// The utilization once a new position with `margin` and `notional` opens
+    pub fn with_open(mut self, symbol: &str, margin: Decimal, notional: Decimal) -> Self {
+        self.push(None, symbol, margin, notional);
+        self.portfolio_leverage = self.leverage();
+        self
+    }
+
+    /// The utilization once `position_account` is resized to `margin` and `notional`
+    pub fn with_modified(mut self, position_account: Pubkey, margin: Decimal, notional: Decimal) -> Self {
+        let total_collateral = self.total_collateral;
+        if let Some(position) = self
+            .positions
+            .iter_mut()
+            .find(|position| position.position_account == Some(position_account))
+        {
+            self.notional += notional - position.notional;
+            position.margin = margin;
+            position.notional = notional;
+            position.risk_pct = (total_collateral > Decimal::ZERO)
+                .then(|| margin / total_collateral * Decimal::ONE_HUNDRED);
+        }
+        self.portfolio_leverage = self.leverage();
+        self
+    }
+
+    fn push(&mut self, position_account: Option<Pubkey>, symbol: &str, margin: Decimal, notional: Decimal) {
+        let risk_pct = (self.total_collateral > Decimal::ZERO)
+            .then(|| margin / self.total_collateral * Decimal::ONE_HUNDRED);
+
+        self.notional += notional;
+        self.positions.push(PositionRisk {
+            position_account,
+            symbol: symbol.to_string(),
+            margin,
+            notional,
+            risk_pct,
+        });
+    }
+
+    fn leverage(&self) -> Option<Decimal> {
+        if self.notional.is_zero() {
+            return Some(Decimal::ZERO);
+        }
+        (self.equity > Decimal::ZERO).then(|| self.notional / self.equity)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLimitKind {
+    PositionRisk,
+    PortfolioLeverage,
+}
+
+/// A limit the user is over
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskLimitBreach {
+    pub owner: Pubkey,
+    pub kind: RiskLimitKind,
+    /// The position over the limit, none for portfolio leverage
+    pub position_account: Option<Pubkey>,
+    pub symbol: Option<String>,
+    pub limit: Decimal,
+    /// Measured value, none once collateral or equity is gone
+    pub value: Option<Decimal>,
+    /// Margin (position risk) or notional (leverage) to cut to get back under the limit
+    pub excess: Decimal,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl RiskLimitBreach {
+    /// What is over which limit, for API errors and warnings
+    pub fn message(&self) -> String {
+        match self.kind {
+            RiskLimitKind::PositionRisk => format!(
+                "{} margin puts {} of collateral at risk, over the {}% limit",
+                self.symbol.as_deref().unwrap_or("Position"),
+                self.value
+                    .map(|value| format!("{}%", value.round_dp(2)))
+                    .unwrap_or_else(|| "all".to_string()),
+                self.limit
+            ),
+            RiskLimitKind::PortfolioLeverage => format!(
+                "Portfolio leverage is {}, over the {}x limit",
+                self.value
+                    .map(|value| format!("{}x", value.round_dp(2)))
+                    .unwrap_or_else(|| "unbounded with no equity left".to_string()),
+                self.limit
+            ),
+        }
+    }
+}
+
+impl RiskLimits {
+    pub fn is_empty(&self) -> bool {
+        self.max_position_risk_pct.is_none() && self.max_portfolio_leverage.is_none()
+    }
+
+    /// Limits `utilization` is over
+    pub fn breaches(
+        &self,
+        owner: Pubkey,
+        utilization: &RiskUtilization,
+        now: DateTime<Utc>,
+    ) -> Vec<RiskLimitBreach> {
+        let mut breaches = Vec::new();
+
+        if let Some(limit) = self.max_position_risk_pct {
+            let allowed_margin = utilization.total_collateral * limit / Decimal::ONE_HUNDRED;
+            for position in &utilization.positions {
+                if position.risk_pct.is_none_or(|risk_pct| risk_pct > limit) {
+                    breaches.push(RiskLimitBreach {
+                        owner,
+                        kind: RiskLimitKind::PositionRisk,
+                        position_account: position.position_account,
+                        symbol: Some(position.symbol.clone()),
+                        limit,
+                        value: position.risk_pct,
+                        excess: position.margin - allowed_margin,
+                        detected_at: now,
+                    });
+                }
+            }
+        }
+
+        if let Some(limit) = self.max_portfolio_leverage {
+            if utilization.portfolio_leverage.is_none_or(|leverage| leverage > limit) {
+                breaches.push(RiskLimitBreach {
+                    owner,
+                    kind: RiskLimitKind::PortfolioLeverage,
+                    position_account: None,
+                    symbol: None,
+                    limit,
+                    value: utilization.portfolio_leverage,
+                    excess: utilization.notional - limit * utilization.equity.max(Decimal::ZERO),
+                    detected_at: now,
+                });
+            }
+        }
+
+        breaches
+    }
+}
+
+pub struct RiskLimitService {
+    redis_client: redis::Client,
+    events: Arc<dyn EventBus>,
+    /// Limits each owner was over on the last sweep
+    breached: Mutex<HashMap<Pubkey, HashSet<BreachKey>>>,
+}
+
+impl RiskLimitService {
+    pub fn new(redis_client: redis::Client, events: Arc<dyn EventBus>) -> Self {
+        Self {
+            redis_client,
+            events,
+            breached: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn get(&self, owner: &Pubkey) -> Result<Option<RiskLimits>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let stored: Option<String> = conn.hget(RISK_LIMITS_KEY, owner.to_string()).await?;
+
+        stored
+            .map(|stored| serde_json::from_str(&stored).context("Invalid stored risk limits"))
+            .transpose()
+    }
+
+    /// Replace the owner's limits, no limits at all removes them
+    pub async fn set(&self, owner: &Pubkey, limits: &RiskLimits) -> Result<()> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+
+        if limits.is_empty() {
+            conn.hdel::<_, _, ()>(RISK_LIMITS_KEY, owner.to_string()).await?;
+            self.breached.lock().unwrap().remove(owner);
+        } else {
+            conn.hset::<_, _, _, ()>(RISK_LIMITS_KEY, owner.to_string(), serde_json::to_string(limits)?)
+                .await
+                .context("Failed to store risk limits")?;
+        }
+
+        Ok(())
+    }
+
+    /// Every owner with limits set
+    pub async fn all(&self) -> Result<Vec<(Pubkey, RiskLimits)>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let stored: HashMap<String, String> = conn.hgetall(RISK_LIMITS_KEY).await?;
+
+        Ok(stored
+            .into_iter()
+            .filter_map(|(owner, limits)| {
+                let owner = Pubkey::from_str(&owner).ok()?;
+                match serde_json::from_str(&limits) {
+                    Ok(limits) => Some((owner, limits)),
+                    Err(e) => {
+                        warn!("Skipping invalid risk limits for {}: {}", owner, e);
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Keep the owner's current breaches and publish the ones that are new
+    /// Returns the ones published
+    pub fn record(&self, owner: Pubkey, breaches: Vec<RiskLimitBreach>) -> Vec<RiskLimitBreach> {
+        let current: HashSet<BreachKey> = breaches
+            .iter()
+            .map(|breach| (breach.kind, breach.position_account))
+            .collect();
+
+        let previous = {
+            let mut breached = self.breached.lock().unwrap();
+            if current.is_empty() {
+                breached.remove(&owner)
+            } else {
+                breached.insert(owner, current)
+            }
+        }
+        .unwrap_or_default();
+
+        let fired: Vec<_> = breaches
+            .into_iter()
+            .filter(|breach| !previous.contains(&(breach.kind, breach.position_account)))
+            .collect();
+
+        for breach in &fired {
+            self.events.publish(DomainEvent::RiskLimitBreached(breach.clone()));
+        }
+
+        fired
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RiskLimitKeeperConfig {
+    pub sweep_interval_ms: u64,
+}
+
+impl Default for RiskLimitKeeperConfig {
+    fn default() -> Self {
+        Self {
+            sweep_interval_ms: 5_000,
+        }
+    }
+}
+
+pub struct RiskLimitKeeper {
+    position_manager: Arc<PositionManager>,
+    monitor: Arc<PositionMonitor>,
+    risk_limits: Arc<RiskLimitService>,
+    config: RiskLimitKeeperConfig,
+}
+
+impl RiskLimitKeeper {
+    pub fn new(
+        position_manager: Arc<PositionManager>,
+        monitor: Arc<PositionMonitor>,
+        risk_limits: Arc<RiskLimitService>,
+        config: RiskLimitKeeperConfig,
+    ) -> Self {
+        Self {
+            position_manager,
+            monitor,
+            risk_limits,
+            config,
+        }
+    }
+
+    /// Sweep on an interval, only the leader warns
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(Duration::from_millis(self.config.sweep_interval_ms));
+
+        info!("Risk limit keeper started");
+
+        loop {
+            ticker.tick().await;
+
+            if !self.monitor.is_leader() {
+                continue;
+            }
+
+            if let Err(e) = self.sweep().await {
+                warn!("Risk limit sweep failed: {}", e);
+            }
+        }
+    }
+
+    async fn sweep(&self) -> Result<()> {
+        let now = Utc::now();
+
+        for (owner, limits) in self.risk_limits.all().await? {
+            let positions = self.monitor.get_user_positions(&owner).await?;
+            // Nothing open is never over a limit, and needs no RPC call
+            if !positions.iter().any(|position| position.is_open()) {
+                self.risk_limits.record(owner, Vec::new());
+                continue;
+            }
+
+            let user_account = match self.position_manager.get_user_account(&owner).await {
+                Ok(user_account) => user_account,
+                Err(e) => {
+                    warn!("Failed to read collateral for {}: {}", owner, e);
+                    continue;
+                }
+            };
+
+            let utilization = RiskUtilization::measure(&positions, user_account.total_collateral);
+            for breach in self
+                .risk_limits
+                .record(owner, limits.breaches(owner, &utilization, now))
+            {
+                warn!("Risk limit breached by {}: {}", owner, breach.message());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{verify_wallet_signature, EventKind, LocalEventBus};
+    use rust_decimal_macros::dec;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::signer::Signer;
+
+    fn position(margin: Decimal, size: Decimal, mark_price: Decimal, unrealized_pnl: Decimal) -> Position {
+        Position {
+            size,
+            entry_price: mark_price,
+            mark_price,
+            margin,
+            unrealized_pnl,
+            ..Position::for_test()
+        }
+    }
+
+    #[test]
+    fn test_set_risk_limits_signature() {
+        let wallet = Keypair::new();
+        let owner = wallet.pubkey();
+        let now = Utc::now();
+        let limits = RiskLimits {
+            max_position_risk_pct: Some(dec!(25)),
+            max_portfolio_leverage: None,
+            block_opens: true,
+        };
+        let message = set_risk_limits_message(&owner, &limits, now.timestamp());
+        assert_eq!(
+            message,
+            format!(
+                "Set risk limits for {}: position risk 25, portfolio leverage none, block opens true at {}",
+                owner,
+                now.timestamp()
+            )
+        );
+        let signature = wallet.sign_message(message.as_bytes()).to_string();
+        assert!(verify_wallet_signature(&owner, &message, &signature, now.timestamp(), now).is_ok());
+
+        // Unsigned, signed by another wallet, or signed for other limits
+        assert!(verify_wallet_signature(&owner, &message, "", now.timestamp(), now).is_err());
+        let other = Keypair::new().sign_message(message.as_bytes()).to_string();
+        assert!(verify_wallet_signature(&owner, &message, &other, now.timestamp(), now).is_err());
+        let unblocked = set_risk_limits_message(
+            &owner,
+            &RiskLimits {
+                block_opens: false,
+                ..limits
+            },
+            now.timestamp(),
+        );
+        assert!(verify_wallet_signature(&owner, &unblocked, &signature, now.timestamp(), now).is_err());
+    }
+
+    #[test]
+    fn test_measure_utilization() {
+        let positions = vec![
+            position(dec!(100), dec!(10), dec!(100), dec!(-50)),
+            position(dec!(300), dec!(20), dec!(100), dec!(50)),
+        ];
+
+        // 1000 collateral
+        let utilization = RiskUtilization::measure(&positions, 1_000_000_000);
+        assert_eq!(utilization.total_collateral, dec!(1000));
+        assert_eq!(utilization.equity, dec!(1000));
+        assert_eq!(utilization.notional, dec!(3000));
+        assert_eq!(utilization.portfolio_leverage, Some(dec!(3)));
+        assert_eq!(utilization.positions[0].risk_pct, Some(dec!(10)));
+        assert_eq!(utilization.positions[1].risk_pct, Some(dec!(30)));
+
+        let modified = utilization.clone().with_modified(positions[1].position_account, dec!(400), dec!(4000));
+        assert_eq!(modified.notional, dec!(5000));
+        assert_eq!(modified.portfolio_leverage, Some(dec!(5)));
+        assert_eq!(modified.positions[1].risk_pct, Some(dec!(40)));
+
+        let opened = utilization.with_open("BTC-USD", dec!(100), dec!(2000));
+        assert_eq!(opened.portfolio_leverage, Some(dec!(5)));
+        assert_eq!(opened.positions[2].position_account, None);
+    }
+
+    #[test]
+    fn test_breaches_and_excess() {
+        let owner = Pubkey::new_unique();
+        let positions = vec![
+            position(dec!(100), dec!(10), dec!(100), Decimal::ZERO),
+            position(dec!(300), dec!(20), dec!(100), Decimal::ZERO),
+        ];
+        let utilization = RiskUtilization::measure(&positions, 1_000_000_000);
+
+        let limits = RiskLimits {
+            max_position_risk_pct: Some(dec!(25)),
+            max_portfolio_leverage: Some(dec!(2)),
+            block_opens: false,
+        };
+        let breaches = limits.breaches(owner, &utilization, Utc::now());
+        assert_eq!(breaches.len(), 2);
+
+        assert_eq!(breaches[0].kind, RiskLimitKind::PositionRisk);
+        assert_eq!(breaches[0].position_account, Some(positions[1].position_account));
+        assert_eq!(breaches[0].excess, dec!(50));
+
+        assert_eq!(breaches[1].kind, RiskLimitKind::PortfolioLeverage);
+        assert_eq!(breaches[1].excess, dec!(1000));
+
+        // Equity wiped out is over any leverage limit
+        let underwater = vec![position(dec!(100), dec!(10), dec!(100), dec!(-2000))];
+        let utilization = RiskUtilization::measure(&underwater, 1_000_000_000);
+        assert_eq!(utilization.portfolio_leverage, None);
+        let limits = RiskLimits {
+            max_portfolio_leverage: Some(dec!(50)),
+            ..RiskLimits::default()
+        };
+        assert_eq!(limits.breaches(owner, &utilization, Utc::now()).len(), 1);
+    }
+
+    #[test]
+    fn test_record_publishes_new_breaches_once() {
+        let events = Arc::new(LocalEventBus::new());
+        let mut published = events.receiver(EventKind::RiskLimit);
+        let service = RiskLimitService::new(
+            redis::Client::open("redis://127.0.0.1/").unwrap(),
+            events,
+        );
+
+        let owner = Pubkey::new_unique();
+        let breach = RiskLimitBreach {
+            owner,
+            kind: RiskLimitKind::PortfolioLeverage,
+            position_account: None,
+            symbol: None,
+            limit: dec!(2),
+            value: Some(dec!(3)),
+            excess: dec!(1000),
+            detected_at: Utc::now(),
+        };
+
+        assert_eq!(service.record(owner, vec![breach.clone()]).len(), 1);
+        assert!(published.try_recv().is_ok());
+        assert!(service.record(owner, vec![breach.clone()]).is_empty());
+        assert!(published.try_recv().is_err());
+
+        // Back under the limit, a later breach warns again
+        assert!(service.record(owner, Vec::new()).is_empty());
+        assert_eq!(service.record(owner, vec![breach]).len(), 1);
+    }
+}
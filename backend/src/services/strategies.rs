@@ -0,0 +1,242 @@
+/// Strategy Groups
+/// Named groups of a user's positions, so multi-leg structures (hedges,
+/// spreads, basis trades) can be watched and closed as a unit. Groups live in
+/// one Redis hash per owner, keyed by name, so any replica can serve them
+/// The owner signs every change, and closing, with their wallet
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::domain::{Position, Side};
+
+/// Enforced by the API before `save`
+pub const MAX_STRATEGIES_PER_USER: usize = 50;
+pub const MAX_LEGS_PER_STRATEGY: usize = 20;
+
+fn strategies_key(owner: &Pubkey) -> String {
+    format!("strategies:user:{}", owner)
+}
+
+/// Message the wallet signs to create strategy `name` or replace its legs
+pub fn put_strategy_message(owner: &Pubkey, name: &str, timestamp: i64) -> String {
+    format!("Save strategy {} for {} at {}", name, owner, timestamp)
+}
+
+/// Message the wallet signs to ungroup strategy `name`
+pub fn delete_strategy_message(owner: &Pubkey, name: &str, timestamp: i64) -> String {
+    format!("Delete strategy {} for {} at {}", name, owner, timestamp)
+}
+
+/// Message the wallet signs to close every leg of strategy `name`
+pub fn close_strategy_message(owner: &Pubkey, name: &str, timestamp: i64) -> String {
+    format!("Close strategy {} for {} at {}", name, owner, timestamp)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Strategy {
+    pub name: String,
+    pub owner: Pubkey,
+    pub positions: Vec<Pubkey>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// PnL, margin and exposure summed over a strategy's legs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StrategyTotals {
+    pub open_legs: usize,
+    pub unrealized_pnl: Decimal,
+    pub realized_pnl: Decimal,
+    /// Margin posted on the open legs
+    pub margin: Decimal,
+    /// Sum of open notional regardless of side
+    pub gross_exposure: Decimal,
+    /// Long notional minus short notional, near zero for a hedged structure
+    pub net_exposure: Decimal,
+}
+
+impl StrategyTotals {
+    /// Closed legs only contribute realized PnL
+    pub fn from_legs<'a>(legs: impl IntoIterator<Item = &'a Position>) -> Self {
+        let mut totals = Self::default();
+
+        for leg in legs {
+            totals.realized_pnl += leg.realized_pnl;
+            if !leg.is_open() {
+                continue;
+            }
+
+            let notional = leg.size * leg.mark_price;
+            totals.open_legs += 1;
+            totals.unrealized_pnl += leg.unrealized_pnl;
+            totals.margin += leg.margin;
+            totals.gross_exposure += notional;
+            totals.net_exposure += match leg.side {
+                Side::Long => notional,
+                Side::Short => -notional,
+            };
+        }
+
+        totals
+    }
+}
+
+pub struct StrategyService {
+    redis_client: redis::Client,
+}
+
+impl StrategyService {
+    pub fn new(redis_client: redis::Client) -> Self {
+        Self { redis_client }
+    }
+
+    /// An owner's strategies, oldest first
+    pub async fn list(&self, owner: &Pubkey) -> Result<Vec<Strategy>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let stored: HashMap<String, String> = conn.hgetall(strategies_key(owner)).await?;
+
+        let mut strategies: Vec<Strategy> = stored
+            .values()
+            .filter_map(|raw| match serde_json::from_str(raw) {
+                Ok(strategy) => Some(strategy),
+                Err(e) => {
+                    warn!("Skipping undecodable strategy: {}", e);
+                    None
+                }
+            })
+            .collect();
+        strategies.sort_by_key(|strategy| strategy.created_at);
+        Ok(strategies)
+    }
+
+    pub async fn get(&self, owner: &Pubkey, name: &str) -> Result<Option<Strategy>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let stored: Option<String> = conn.hget(strategies_key(owner), name).await?;
+
+        stored
+            .map(|raw| serde_json::from_str(&raw).context("Failed to decode strategy"))
+            .transpose()
+    }
+
+    /// Create or replace the named strategy's legs, keeping its creation time
+    pub async fn save(&self, owner: Pubkey, name: &str, positions: Vec<Pubkey>) -> Result<Strategy> {
+        let now = Utc::now();
+        let created_at = self
+            .get(&owner, name)
+            .await?
+            .map_or(now, |existing| existing.created_at);
+
+        let strategy = Strategy {
+            name: name.to_string(),
+            owner,
+            positions,
+            created_at,
+            updated_at: now,
+        };
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        conn.hset::<_, _, _, ()>(strategies_key(&owner), name, serde_json::to_string(&strategy)?)
+            .await
+            .context("Failed to store strategy")?;
+
+        Ok(strategy)
+    }
+
+    /// Delete the group, its positions are untouched. Returns it when it existed
+    pub async fn remove(&self, owner: &Pubkey, name: &str) -> Result<Option<Strategy>> {
+        let Some(strategy) = self.get(owner, name).await? else {
+            return Ok(None);
+        };
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        conn.hdel::<_, _, ()>(strategies_key(owner), name).await?;
+
+        Ok(Some(strategy))
+    }
+
+    /// Delete every group of the owner, returns how many there were
+    pub async fn clear(&self, owner: &Pubkey) -> Result<usize> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let key = strategies_key(owner);
+        let (count, _): (usize, ()) = redis::pipe()
+            .atomic()
+            .hlen(&key)
+            .del(&key)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::PositionStatus;
+    use crate::services::verify_wallet_signature;
+    use rust_decimal_macros::dec;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::signer::Signer;
+
+    #[test]
+    fn test_strategy_signatures() {
+        let wallet = Keypair::new();
+        let owner = wallet.pubkey();
+        let now = Utc::now();
+        let message = close_strategy_message(&owner, "btc-basis", now.timestamp());
+        let signature = wallet.sign_message(message.as_bytes()).to_string();
+        assert!(verify_wallet_signature(&owner, &message, &signature, now.timestamp(), now).is_ok());
+
+        // Unsigned, or signed by another wallet
+        assert!(verify_wallet_signature(&owner, &message, "", now.timestamp(), now).is_err());
+        let stranger = Keypair::new().sign_message(message.as_bytes()).to_string();
+        assert!(verify_wallet_signature(&owner, &message, &stranger, now.timestamp(), now).is_err());
+
+        // Closing one strategy doesn't close, replace or delete another
+        for other in [
+            close_strategy_message(&owner, "eth-hedge", now.timestamp()),
+            put_strategy_message(&owner, "btc-basis", now.timestamp()),
+            delete_strategy_message(&owner, "btc-basis", now.timestamp()),
+        ] {
+            assert!(verify_wallet_signature(&owner, &other, &signature, now.timestamp(), now).is_err());
+        }
+    }
+
+    fn leg(side: Side, size: Decimal, mark_price: Decimal, status: PositionStatus) -> Position {
+        Position {
+            symbol: "BTC-USD".to_string(),
+            side,
+            size,
+            mark_price,
+            margin: dec!(50),
+            unrealized_pnl: dec!(5),
+            realized_pnl: dec!(2),
+            liquidation_price: dec!(91),
+            status,
+            ..Position::for_test()
+        }
+    }
+
+    #[test]
+    fn test_totals_net_hedged_legs() {
+        let legs = [
+            leg(Side::Long, dec!(2), dec!(100), PositionStatus::Open),
+            leg(Side::Short, dec!(1.5), dec!(100), PositionStatus::Open),
+            leg(Side::Long, dec!(1), dec!(100), PositionStatus::Closed),
+        ];
+
+        let totals = StrategyTotals::from_legs(&legs);
+
+        assert_eq!(totals.open_legs, 2);
+        assert_eq!(totals.unrealized_pnl, dec!(10));
+        assert_eq!(totals.realized_pnl, dec!(6));
+        assert_eq!(totals.margin, dec!(100));
+        assert_eq!(totals.gross_exposure, dec!(350));
+        assert_eq!(totals.net_exposure, dec!(50));
+    }
+}
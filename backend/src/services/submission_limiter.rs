@@ -0,0 +1,226 @@
+/// Submission Limiter
+/// Transactions for one owner are built and sent one at a time, so a burst
+/// of opens can't read the same `position_count_total` and collide on the
+/// position PDA; later requests wait their turn instead of failing
+/// Each chain-submitting route also has a cap on transactions in flight
+/// The owner's next position index is reserved in memory across the queue,
+/// since the RPC may still serve the user account from before the last open
+use anyhow::{anyhow, Result};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+
+// Idle owner slots are pruned once the map grows past this
+const OWNER_SLOT_PRUNE_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct SubmissionLimitConfig {
+    /// Transactions in flight per route across all owners
+    pub max_in_flight_per_route: usize,
+}
+
+impl Default for SubmissionLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight_per_route: 16,
+        }
+    }
+}
+
+impl SubmissionLimitConfig {
+    /// MAX_IN_FLIGHT_SUBMISSIONS, the default when unset
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("MAX_IN_FLIGHT_SUBMISSIONS") {
+            Ok(value) => Self::with_max_in_flight(&value),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Zero would leave every submission waiting on an empty semaphore
+    fn with_max_in_flight(value: &str) -> Result<Self> {
+        match value.trim().parse::<usize>() {
+            Ok(max_in_flight_per_route) if max_in_flight_per_route > 0 => Ok(Self {
+                max_in_flight_per_route,
+            }),
+            _ => Err(anyhow!(
+                "Invalid MAX_IN_FLIGHT_SUBMISSIONS: {}, expected a positive integer",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubmissionRoute {
+    InitializeUser,
+    AddCollateral,
+    OpenPosition,
+    ModifyPosition,
+    ClosePosition,
+    UserSettings,
+    CloseUser,
+}
+
+const ROUTES: [SubmissionRoute; 7] = [
+    SubmissionRoute::InitializeUser,
+    SubmissionRoute::AddCollateral,
+    SubmissionRoute::OpenPosition,
+    SubmissionRoute::ModifyPosition,
+    SubmissionRoute::ClosePosition,
+    SubmissionRoute::UserSettings,
+    SubmissionRoute::CloseUser,
+];
+
+/// Per-owner state guarded by the owner's queue
+#[derive(Debug, Default)]
+pub struct OwnerSlot {
+    /// Index after the last open this node sent, none until one succeeds
+    next_position_index: Option<u32>,
+}
+
+/// Held while building and sending one owner's transaction
+pub struct SubmissionPermit {
+    owner_slot: OwnedMutexGuard<OwnerSlot>,
+    _route: OwnedSemaphorePermit,
+}
+
+impl SubmissionPermit {
+    /// Index for the next position, never below one this node already used
+    pub fn reserve_position_index(&self, chain_next: u32) -> u32 {
+        self.owner_slot
+            .next_position_index
+            .map_or(chain_next, |reserved| reserved.max(chain_next))
+    }
+
+    /// Drop the reservation after the program rejected it, the chain decides again
+    pub fn forget_position_index(&mut self) {
+        self.owner_slot.next_position_index = None;
+    }
+
+    /// Record a sent open so the next one skips its index
+    pub fn commit_position_index(&mut self, index: u32) {
+        self.owner_slot.next_position_index = Some(index + 1);
+    }
+}
+
+pub struct SubmissionLimiter {
+    routes: HashMap<SubmissionRoute, Arc<Semaphore>>,
+    owners: Mutex<HashMap<Pubkey, Arc<tokio::sync::Mutex<OwnerSlot>>>>,
+}
+
+impl SubmissionLimiter {
+    pub fn new(config: SubmissionLimitConfig) -> Self {
+        let routes = ROUTES
+            .into_iter()
+            .map(|route| (route, Arc::new(Semaphore::new(config.max_in_flight_per_route))))
+            .collect();
+
+        Self {
+            routes,
+            owners: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait for the owner's turn, then for a slot on the route
+    pub async fn acquire(&self, route: SubmissionRoute, owner: &Pubkey) -> SubmissionPermit {
+        let slot = self.owner_slot(owner);
+        let owner_slot = slot.lock_owned().await;
+
+        // Queued owners don't hold route slots while they wait
+        let route_permit = Arc::clone(&self.routes[&route])
+            .acquire_owned()
+            .await
+            .expect("route semaphore is never closed");
+
+        SubmissionPermit {
+            owner_slot,
+            _route: route_permit,
+        }
+    }
+
+    fn owner_slot(&self, owner: &Pubkey) -> Arc<tokio::sync::Mutex<OwnerSlot>> {
+        let mut owners = self.owners.lock().unwrap();
+
+        // A pruned slot only loses its reservation, the chain has caught up by then
+        if owners.len() > OWNER_SLOT_PRUNE_THRESHOLD {
+            owners.retain(|_, slot| Arc::strong_count(slot) > 1);
+        }
+
+        Arc::clone(owners.entry(*owner).or_default())
+    }
+}
+
+impl Default for SubmissionLimiter {
+    fn default() -> Self {
+        Self::new(SubmissionLimitConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_max_in_flight_must_be_positive() {
+        assert_eq!(
+            SubmissionLimitConfig::with_max_in_flight("4").unwrap().max_in_flight_per_route,
+            4
+        );
+        assert!(SubmissionLimitConfig::with_max_in_flight("0").is_err());
+        assert!(SubmissionLimitConfig::with_max_in_flight("many").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_owner_submissions_are_serialized() {
+        let limiter = Arc::new(SubmissionLimiter::default());
+        let owner = Pubkey::new_unique();
+
+        // The RPC keeps serving a stale count of 3
+        let mut handles = Vec::new();
+        for _ in 0..4 {
+            let limiter = Arc::clone(&limiter);
+            handles.push(tokio::spawn(async move {
+                let mut permit = limiter.acquire(SubmissionRoute::OpenPosition, &owner).await;
+                let index = permit.reserve_position_index(3);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                permit.commit_position_index(index);
+                index
+            }));
+        }
+
+        let mut indexes = Vec::new();
+        for handle in handles {
+            indexes.push(handle.await.unwrap());
+        }
+        indexes.sort();
+        assert_eq!(indexes, vec![3, 4, 5, 6]);
+
+        // The chain moving ahead wins over the reservation
+        let permit = limiter.acquire(SubmissionRoute::OpenPosition, &owner).await;
+        assert_eq!(permit.reserve_position_index(9), 9);
+    }
+
+    #[tokio::test]
+    async fn test_route_in_flight_limit() {
+        let limiter = SubmissionLimiter::new(SubmissionLimitConfig {
+            max_in_flight_per_route: 1,
+        });
+
+        let _held = limiter
+            .acquire(SubmissionRoute::ClosePosition, &Pubkey::new_unique())
+            .await;
+
+        let other_owner = Pubkey::new_unique();
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(20),
+            limiter.acquire(SubmissionRoute::ClosePosition, &other_owner),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        // Other routes are unaffected
+        limiter.acquire(SubmissionRoute::OpenPosition, &other_owner).await;
+    }
+}
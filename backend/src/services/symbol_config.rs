@@ -0,0 +1,292 @@
+/// Symbol Config
+/// Per-symbol overrides of the monitor's PnL cadence, maintenance ratio,
+/// liquidation alert threshold and open interest cap, since SOL and BTC don't
+/// move alike
+/// Overrides set in MonitorConfig apply from startup; overrides set through
+/// the admin API live in a Redis hash, replace the startup ones for their
+/// symbol and are picked up by every replica on its next reload
+/// A symbol's on-chain Market rate, when set, floors the maintenance ratio
+/// whatever the overrides say, since the program liquidates at it
+use anyhow::{Context, Result};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::domain::symbols;
+
+/// Symbol -> overrides JSON
+const OVERRIDES_KEY: &str = "monitor:symbol_config";
+
+/// Fields left unset use the monitor-wide default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SymbolOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pnl_update_interval_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance_margin_ratio: Option<Decimal>,
+    /// Distance above liquidation at which Liquidating alerts start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert_threshold_pct: Option<Decimal>,
+    /// Cap on the summed size of open positions, in base units
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_open_interest: Option<Decimal>,
+}
+
+impl SymbolOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Values in effect for one symbol
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolSettings {
+    pub pnl_update_interval_ms: u64,
+    pub maintenance_margin_ratio: Decimal,
+    pub alert_threshold_pct: Decimal,
+    /// None leaves open interest uncapped
+    pub max_open_interest: Option<Decimal>,
+}
+
+impl SymbolSettings {
+    pub fn with_overrides(self, overrides: &SymbolOverrides) -> Self {
+        Self {
+            pnl_update_interval_ms: overrides
+                .pnl_update_interval_ms
+                .unwrap_or(self.pnl_update_interval_ms),
+            maintenance_margin_ratio: overrides
+                .maintenance_margin_ratio
+                .unwrap_or(self.maintenance_margin_ratio),
+            alert_threshold_pct: overrides
+                .alert_threshold_pct
+                .unwrap_or(self.alert_threshold_pct),
+            max_open_interest: overrides.max_open_interest.or(self.max_open_interest),
+        }
+    }
+}
+
+pub struct SymbolConfigs {
+    redis_client: redis::Client,
+    defaults: SymbolSettings,
+    /// From MonitorConfig, fixed for the life of the process
+    startup: HashMap<String, SymbolOverrides>,
+    /// From the admin API, mirrored from Redis
+    runtime: RwLock<HashMap<String, SymbolOverrides>>,
+    /// Maintenance ratios of the on-chain Markets
+    market_floors: RwLock<HashMap<String, Decimal>>,
+}
+
+impl SymbolConfigs {
+    pub fn new(
+        redis_client: redis::Client,
+        defaults: SymbolSettings,
+        startup: &HashMap<String, SymbolOverrides>,
+    ) -> Self {
+        Self {
+            redis_client,
+            defaults,
+            startup: startup
+                .iter()
+                .map(|(symbol, overrides)| (symbols::normalize(symbol), overrides.clone()))
+                .collect(),
+            runtime: RwLock::new(HashMap::new()),
+            market_floors: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn defaults(&self) -> SymbolSettings {
+        self.defaults
+    }
+
+    pub fn settings(&self, symbol: &str) -> SymbolSettings {
+        let mut settings = match self.overrides_for(symbol) {
+            Some(overrides) => self.defaults.with_overrides(&overrides),
+            None => self.defaults,
+        };
+
+        if let Some(floor) = self.market_floors.read().unwrap().get(&symbols::normalize(symbol)) {
+            settings.maintenance_margin_ratio = settings.maintenance_margin_ratio.max(*floor);
+        }
+
+        settings
+    }
+
+    /// Record a symbol's on-chain Market maintenance ratio
+    pub fn set_market_floor(&self, symbol: &str, maintenance_margin_ratio: Decimal) {
+        self.market_floors
+            .write()
+            .unwrap()
+            .insert(symbols::normalize(symbol), maintenance_margin_ratio);
+    }
+
+    /// Runtime overrides win over startup ones for the same symbol
+    pub fn overrides_for(&self, symbol: &str) -> Option<SymbolOverrides> {
+        let symbol = symbols::normalize(symbol);
+
+        self.runtime
+            .read()
+            .unwrap()
+            .get(&symbol)
+            .or_else(|| self.startup.get(&symbol))
+            .cloned()
+    }
+
+    /// Every symbol with overrides in effect, by symbol
+    pub fn overrides(&self) -> BTreeMap<String, SymbolOverrides> {
+        let mut overrides: BTreeMap<String, SymbolOverrides> = self
+            .startup
+            .iter()
+            .map(|(symbol, overrides)| (symbol.clone(), overrides.clone()))
+            .collect();
+
+        for (symbol, runtime) in self.runtime.read().unwrap().iter() {
+            overrides.insert(symbol.clone(), runtime.clone());
+        }
+
+        overrides
+    }
+
+    /// Shortest PnL interval of any symbol, the PnL updater's tick
+    pub fn min_pnl_update_interval(&self) -> Duration {
+        let min_ms = self
+            .overrides()
+            .values()
+            .filter_map(|overrides| overrides.pnl_update_interval_ms)
+            .fold(self.defaults.pnl_update_interval_ms, u64::min);
+
+        Duration::from_millis(min_ms)
+    }
+
+    /// Store overrides for `symbol`, replacing any it had
+    pub async fn set(&self, symbol: &str, overrides: SymbolOverrides) -> Result<()> {
+        let symbol = symbols::normalize(symbol);
+        let json = serde_json::to_string(&overrides)?;
+
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")?;
+        conn.hset::<_, _, _, ()>(OVERRIDES_KEY, &symbol, json).await?;
+
+        self.runtime.write().unwrap().insert(symbol, overrides);
+
+        Ok(())
+    }
+
+    /// Drop the runtime overrides for `symbol`, returning whether it had any.
+    /// Startup overrides, if any, apply again
+    pub async fn remove(&self, symbol: &str) -> Result<bool> {
+        let symbol = symbols::normalize(symbol);
+
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")?;
+        let removed: u32 = conn.hdel(OVERRIDES_KEY, &symbol).await?;
+
+        self.runtime.write().unwrap().remove(&symbol);
+
+        Ok(removed > 0)
+    }
+
+    /// Pick up overrides set through other replicas
+    pub async fn reload(&self) -> Result<()> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .context("Failed to get Redis connection")?;
+        let stored: HashMap<String, String> = conn.hgetall(OVERRIDES_KEY).await?;
+
+        let runtime = stored
+            .into_iter()
+            .filter_map(|(symbol, json)| match serde_json::from_str(&json) {
+                Ok(overrides) => Some((symbol, overrides)),
+                Err(e) => {
+                    warn!("Skipping unreadable symbol config for {}: {}", symbol, e);
+                    None
+                }
+            })
+            .collect();
+
+        *self.runtime.write().unwrap() = runtime;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn configs() -> SymbolConfigs {
+        let startup = HashMap::from([(
+            "sol/usd".to_string(),
+            SymbolOverrides {
+                pnl_update_interval_ms: Some(500),
+                maintenance_margin_ratio: Some(dec!(0.05)),
+                alert_threshold_pct: None,
+                max_open_interest: Some(dec!(1000)),
+            },
+        )]);
+
+        SymbolConfigs::new(
+            redis::Client::open("redis://127.0.0.1:6379").unwrap(),
+            SymbolSettings {
+                pnl_update_interval_ms: 2_000,
+                maintenance_margin_ratio: dec!(0.025),
+                alert_threshold_pct: dec!(0.10),
+                max_open_interest: None,
+            },
+            &startup,
+        )
+    }
+
+    #[test]
+    fn test_overrides_fall_back_to_defaults() {
+        let configs = configs();
+
+        let sol = configs.settings("SOL-USD");
+        assert_eq!(sol.pnl_update_interval_ms, 500);
+        assert_eq!(sol.maintenance_margin_ratio, dec!(0.05));
+        assert_eq!(sol.alert_threshold_pct, dec!(0.10));
+        assert_eq!(sol.max_open_interest, Some(dec!(1000)));
+
+        assert_eq!(configs.settings("BTC-USD"), configs.defaults());
+        assert_eq!(configs.min_pnl_update_interval(), Duration::from_millis(500));
+
+        // A runtime entry replaces the startup one outright
+        configs.runtime.write().unwrap().insert(
+            "SOL-USD".to_string(),
+            SymbolOverrides {
+                alert_threshold_pct: Some(dec!(0.2)),
+                ..Default::default()
+            },
+        );
+        let sol = configs.settings("sol-usd");
+        assert_eq!(sol.pnl_update_interval_ms, 2_000);
+        assert_eq!(sol.maintenance_margin_ratio, dec!(0.025));
+        assert_eq!(sol.alert_threshold_pct, dec!(0.2));
+        assert_eq!(sol.max_open_interest, None);
+        assert_eq!(configs.min_pnl_update_interval(), Duration::from_millis(2_000));
+    }
+
+    #[test]
+    fn test_market_floor_raises_maintenance() {
+        let configs = configs();
+
+        configs.set_market_floor("sol-usd", dec!(0.04));
+        configs.set_market_floor("BTC-USD", dec!(0.03));
+
+        // Overrides above the floor stand, defaults below it are raised
+        assert_eq!(configs.settings("SOL-USD").maintenance_margin_ratio, dec!(0.05));
+        assert_eq!(configs.settings("BTC-USD").maintenance_margin_ratio, dec!(0.03));
+    }
+}
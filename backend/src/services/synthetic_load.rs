@@ -0,0 +1,217 @@
+/// Synthetic Load
+/// Fake positions and replayed price ticks for the monitor benchmark and the
+/// monitor_load binary, so the hot paths can be timed without RPC or Pyth
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rust_decimal::Decimal;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{Position, PositionStatus, Side};
+use crate::infrastructure::{CachedQuote, OracleQuote, PriceSource, SolanaClient};
+use crate::services::{MarginCalculator, MonitorConfig, PositionMonitor};
+
+/// Markets and starting prices used for synthetic positions
+pub const SYNTHETIC_MARKETS: [(&str, i64); 3] =
+    [("BTC-USD", 60_000), ("ETH-USD", 3_000), ("SOL-USD", 150)];
+
+// Synthetic positions per owner, so per-user indexes get realistic fan-out
+const POSITIONS_PER_OWNER: usize = 10;
+
+/// Prices set by the caller instead of fetched from an oracle
+pub struct ReplayPriceSource {
+    prices: RwLock<HashMap<String, Decimal>>,
+}
+
+impl ReplayPriceSource {
+    pub fn new(prices: impl IntoIterator<Item = (String, Decimal)>) -> Self {
+        Self {
+            prices: RwLock::new(prices.into_iter().collect()),
+        }
+    }
+
+    /// Replay source seeded with `SYNTHETIC_MARKETS`
+    pub fn synthetic() -> Self {
+        Self::new(
+            SYNTHETIC_MARKETS
+                .iter()
+                .map(|(symbol, price)| (symbol.to_string(), Decimal::from(*price))),
+        )
+    }
+
+    pub async fn set_price(&self, symbol: &str, price: Decimal) {
+        self.prices.write().await.insert(symbol.to_string(), price);
+    }
+
+    pub async fn apply_tick(&self, tick: &[(String, Decimal)]) {
+        let mut prices = self.prices.write().await;
+        for (symbol, price) in tick {
+            prices.insert(symbol.clone(), *price);
+        }
+    }
+}
+
+impl PriceSource for ReplayPriceSource {
+    fn get_symbols(&self) -> BoxFuture<'_, Vec<String>> {
+        Box::pin(async move { self.prices.read().await.keys().cloned().collect() })
+    }
+
+    // Replayed prices are exact and published as they are read
+    fn fetch_quote<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Result<OracleQuote>> {
+        Box::pin(async move {
+            let price = self
+                .get_cached_price(symbol)
+                .await
+                .ok_or_else(|| anyhow!("No replayed price for {}", symbol))?;
+
+            Ok(OracleQuote {
+                price,
+                conf: Decimal::ZERO,
+                publish_time: Utc::now(),
+            })
+        })
+    }
+
+    fn get_cached_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>> {
+        Box::pin(async move { self.prices.read().await.get(symbol).copied() })
+    }
+
+    fn get_cached_quote<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<CachedQuote>> {
+        Box::pin(async move {
+            let now = Utc::now();
+            self.get_cached_price(symbol).await.map(|price| CachedQuote {
+                quote: OracleQuote {
+                    price,
+                    conf: Decimal::ZERO,
+                    publish_time: now,
+                },
+                fetched_at: now,
+            })
+        })
+    }
+}
+
+/// Random walk over `SYNTHETIC_MARKETS`, deterministic for a given seed
+pub struct PriceWalk {
+    rng: StdRng,
+    prices: Vec<(String, Decimal)>,
+    max_step_bps: i64,
+}
+
+impl PriceWalk {
+    pub fn new(seed: u64, max_step_bps: i64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            prices: SYNTHETIC_MARKETS
+                .iter()
+                .map(|(symbol, price)| (symbol.to_string(), Decimal::from(*price)))
+                .collect(),
+            max_step_bps,
+        }
+    }
+
+    /// Move every market by up to `max_step_bps` and return the new prices
+    pub fn next_tick(&mut self) -> Vec<(String, Decimal)> {
+        for (_, price) in self.prices.iter_mut() {
+            let step_bps = self.rng.gen_range(-self.max_step_bps..=self.max_step_bps);
+            *price = (*price * (Decimal::ONE + Decimal::new(step_bps, 4))).round_dp(6);
+        }
+
+        self.prices.clone()
+    }
+}
+
+/// `count` open positions spread over `SYNTHETIC_MARKETS`, deterministic for a given seed
+pub fn synthetic_positions(count: usize, seed: u64) -> Vec<Position> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let maintenance_margin_ratio = MonitorConfig::default().maintenance_margin_ratio;
+    let mut owner = Pubkey::new_unique();
+    let now = Utc::now();
+
+    (0..count)
+        .map(|index| {
+            if index % POSITIONS_PER_OWNER == 0 {
+                owner = Pubkey::new_unique();
+            }
+
+            let (symbol, base_price) = SYNTHETIC_MARKETS[index % SYNTHETIC_MARKETS.len()];
+            let side = if rng.gen_bool(0.5) { Side::Long } else { Side::Short };
+            let leverage: u16 = rng.gen_range(1..=50);
+            let size = Decimal::new(rng.gen_range(10..10_000), 3);
+            let entry_price = Decimal::from(base_price)
+                * (Decimal::ONE + Decimal::new(rng.gen_range(-200..=200), 4));
+            let margin = MarginCalculator::calculate_initial_margin(size, entry_price, leverage)
+                .unwrap_or(Decimal::ZERO);
+            let liquidation_price = MarginCalculator::calculate_liquidation_price(
+                side,
+                entry_price,
+                leverage,
+                maintenance_margin_ratio,
+            )
+            .unwrap_or(Decimal::ZERO);
+
+            Position {
+                position_index: (index % POSITIONS_PER_OWNER) as u32,
+                owner,
+                position_account: Pubkey::new_unique(),
+                symbol: symbol.to_string(),
+                side,
+                size,
+                entry_price,
+                mark_price: entry_price,
+                margin,
+                leverage,
+                unrealized_pnl: Decimal::ZERO,
+                realized_pnl: Decimal::ZERO,
+                funding_accrued: Decimal::ZERO,
+                liquidation_price,
+                effective_liquidation_price: None,
+                status: PositionStatus::Open,
+                opened_at: now,
+                last_update: now,
+                closed_at: None,
+                margin_call_at: None,
+            }
+        })
+        .collect()
+}
+
+/// Monitor wired to a replay source. Background tasks are never started, so
+/// the RPC and Redis endpoints are placeholders and never contacted
+pub fn synthetic_monitor(price_source: Arc<ReplayPriceSource>) -> Result<PositionMonitor> {
+    let solana_client = Arc::new(SolanaClient::new(
+        Pubkey::new_unique(),
+        Keypair::new(),
+        "http://127.0.0.1:8899".to_string(),
+    ));
+
+    PositionMonitor::new(
+        solana_client,
+        price_source,
+        MonitorConfig::default(),
+        "redis://127.0.0.1:6379".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_positions_are_valid() {
+        let positions = synthetic_positions(30, 7);
+
+        assert_eq!(positions.len(), 30);
+        assert!(positions.iter().all(|p| p.is_open() && p.margin > Decimal::ZERO));
+        assert_eq!(positions[0].owner, positions[9].owner);
+        assert_ne!(positions[9].owner, positions[10].owner);
+
+        let sizes: Vec<_> = synthetic_positions(5, 7).iter().map(|p| p.size).collect();
+        let again: Vec<_> = synthetic_positions(5, 7).iter().map(|p| p.size).collect();
+        assert_eq!(sizes, again);
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::domain::{RealizedPnL, Side};
+use crate::infrastructure::PositionOpenRecord;
+
+pub const TAX_CSV_HEADER: &str = "position_account,symbol,side,size,opened_at,open_price,\
+open_signature,closed_at,close_price,close_signature,fee,funding,realized_pnl,liquidation";
+
+/// One position from open to close. Either leg may be missing: the open when it
+/// predates open recording, the close when the position is still open at year end
+#[derive(Debug, Clone)]
+pub struct TaxLot {
+    pub position_account: Pubkey,
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub opened_at: Option<DateTime<Utc>>,
+    pub open_price: Decimal,
+    pub open_signature: Option<String>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub close_price: Option<Decimal>,
+    pub close_signature: Option<String>,
+    pub fee: Decimal,
+    pub funding: Decimal,
+    pub realized_pnl: Decimal,
+    pub liquidation: bool,
+}
+
+impl TaxLot {
+    pub fn csv_row(&self) -> String {
+        let side = match self.side {
+            Side::Long => "long",
+            Side::Short => "short",
+        };
+        let fields = [
+            self.position_account.to_string(),
+            self.symbol.clone(),
+            side.to_string(),
+            self.size.to_string(),
+            self.opened_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            self.open_price.to_string(),
+            self.open_signature.clone().unwrap_or_default(),
+            self.closed_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            self.close_price.map(|p| p.to_string()).unwrap_or_default(),
+            self.close_signature.clone().unwrap_or_default(),
+            self.fee.to_string(),
+            self.funding.to_string(),
+            self.realized_pnl.to_string(),
+            self.liquidation.to_string(),
+        ];
+
+        let mut row = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",");
+        row.push('\n');
+        row
+    }
+}
+
+/// [start, end) of a calendar year in UTC
+pub fn year_range(year: i32) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).single();
+    let end = Utc.with_ymd_and_hms(year + 1, 1, 1, 0, 0, 0).single();
+
+    match (start, end) {
+        (Some(start), Some(end)) => Ok((start, end)),
+        _ => Err(anyhow!("Invalid year: {}", year)),
+    }
+}
+
+/// Pair opens with the closes realized in `year`. Lots closed in the year are
+/// reported with their open wherever it happened; lots opened in the year and
+/// not closed by its end are reported open. Ordered by open time, then close time
+pub fn build_tax_lots(
+    opens: Vec<PositionOpenRecord>,
+    closes: Vec<RealizedPnL>,
+    year: i32,
+) -> Result<Vec<TaxLot>> {
+    let (start, end) = year_range(year)?;
+
+    let mut opens: HashMap<Pubkey, PositionOpenRecord> = opens
+        .into_iter()
+        .map(|open| (open.position_account, open))
+        .collect();
+
+    let mut lots = Vec::new();
+
+    for close in closes.into_iter().filter(|c| c.closed_at >= start && c.closed_at < end) {
+        let open = opens.remove(&close.position_account);
+
+        lots.push(TaxLot {
+            position_account: close.position_account,
+            symbol: close.symbol,
+            side: close.side,
+            size: close.size_closed,
+            opened_at: open.as_ref().map(|o| o.opened_at),
+            open_price: close.entry_price,
+            open_signature: open.map(|o| o.signature),
+            closed_at: Some(close.closed_at),
+            close_price: Some(close.close_price),
+            close_signature: Some(close.signature),
+            fee: close.fee,
+            funding: close.funding,
+            realized_pnl: close.amount,
+            liquidation: close.liquidation,
+        });
+    }
+
+    for open in opens.into_values().filter(|o| o.opened_at >= start && o.opened_at < end) {
+        lots.push(TaxLot {
+            position_account: open.position_account,
+            symbol: open.symbol,
+            side: open.side,
+            size: open.size,
+            opened_at: Some(open.opened_at),
+            open_price: open.entry_price,
+            open_signature: Some(open.signature),
+            closed_at: None,
+            close_price: None,
+            close_signature: None,
+            fee: Decimal::ZERO,
+            funding: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
+            liquidation: false,
+        });
+    }
+
+    lots.sort_by_key(|lot| (lot.opened_at.or(lot.closed_at), lot.closed_at));
+
+    Ok(lots)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn open(account: Pubkey, opened_at: DateTime<Utc>) -> PositionOpenRecord {
+        PositionOpenRecord {
+            position_account: account,
+            owner: Pubkey::default(),
+            symbol: "SOL".to_string(),
+            side: Side::Long,
+            size: dec!(2),
+            entry_price: dec!(100),
+            margin: dec!(20),
+            signature: "open-sig".to_string(),
+            opened_at,
+        }
+    }
+
+    fn close(account: Pubkey, closed_at: DateTime<Utc>) -> RealizedPnL {
+        RealizedPnL {
+            id: uuid::Uuid::new_v4(),
+            position_account: account,
+            owner: Pubkey::default(),
+            symbol: "SOL".to_string(),
+            side: Side::Long,
+            size_closed: dec!(2),
+            entry_price: dec!(100),
+            close_price: dec!(110),
+            fee: Decimal::ZERO,
+            funding: dec!(-0.5),
+            amount: dec!(19.5),
+            liquidation: false,
+            signature: "close-sig".to_string(),
+            closed_at,
+        }
+    }
+
+    #[test]
+    fn test_build_tax_lots_pairs_and_filters_by_year() {
+        let t = |y, m| Utc.with_ymd_and_hms(y, m, 1, 0, 0, 0).unwrap();
+        let carried = Pubkey::new_unique();
+        let still_open = Pubkey::new_unique();
+        let closed_last_year = Pubkey::new_unique();
+        let unmatched = Pubkey::new_unique();
+
+        let opens = vec![
+            open(carried, t(2024, 11)),
+            open(still_open, t(2025, 6)),
+            open(closed_last_year, t(2024, 2)),
+        ];
+        let closes = vec![
+            close(carried, t(2025, 3)),
+            close(unmatched, t(2025, 4)),
+            close(closed_last_year, t(2024, 5)),
+        ];
+
+        let lots = build_tax_lots(opens, closes, 2025).unwrap();
+
+        let accounts: Vec<_> = lots.iter().map(|l| l.position_account).collect();
+        assert_eq!(accounts, vec![carried, unmatched, still_open]);
+        assert_eq!(lots[0].opened_at, Some(t(2024, 11)));
+        assert_eq!(lots[0].realized_pnl, dec!(19.5));
+        assert_eq!(lots[1].opened_at, None);
+        assert_eq!(lots[2].closed_at, None);
+    }
+
+    #[test]
+    fn test_csv_row_escapes_fields() {
+        let lot = TaxLot {
+            position_account: Pubkey::default(),
+            symbol: "WEIRD,\"X\"".to_string(),
+            side: Side::Short,
+            size: dec!(1.5),
+            opened_at: None,
+            open_price: dec!(10),
+            open_signature: None,
+            closed_at: None,
+            close_price: None,
+            close_signature: None,
+            fee: Decimal::ZERO,
+            funding: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
+            liquidation: false,
+        };
+
+        let row = lot.csv_row();
+        assert!(row.contains(",\"WEIRD,\"\"X\"\"\",short,1.5,,10,"));
+        assert_eq!(row.matches(',').count() - 1, TAX_CSV_HEADER.matches(',').count());
+        assert!(row.ends_with("false\n"));
+    }
+}
@@ -0,0 +1,321 @@
+/// Tenants
+/// One backend serving several white-label frontends. A tenant is an operator
+/// from TENANTS_FILE with its own key, sent as `X-Tenant-Key`, its own fee
+/// tier thresholds and its own webhook endpoints. A wallet belongs to the
+/// tenant whose frontend initialized it, first come. Requests without a key,
+/// and wallets no tenant claimed, are the default tenant's, which runs on the
+/// deployment's own settings. Tenant data in Redis lives under a
+/// `tenant:<id>:` prefix, the default tenant's keys have none
+use anyhow::{anyhow, Context, Result};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::services::{WebhookEndpoint, FEE_TIER_COUNT};
+
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Header carrying a tenant's key
+pub const TENANT_KEY_HEADER: &str = "x-tenant-key";
+
+pub const MAX_TENANT_ID_LEN: usize = 32;
+
+/// owner -> tenant id, for wallets a tenant claimed
+const OWNER_TENANTS_KEY: &str = "tenants:owners";
+
+/// `key` in `tenant`'s namespace
+pub fn tenant_key(tenant: &str, key: &str) -> String {
+    if tenant == DEFAULT_TENANT {
+        key.to_string()
+    } else {
+        format!("tenant:{}:{}", tenant, key)
+    }
+}
+
+/// Lowercase letters, digits and dashes, so ids are safe in keys and paths
+pub fn is_valid_tenant_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_TENANT_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn key_digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+fn parse_digest(hex: &str) -> Result<[u8; 32]> {
+    if hex.len() != 64 {
+        return Err(anyhow!("key_sha256 must be 64 hex characters"));
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| anyhow!("key_sha256 must be 64 hex characters"))?;
+    }
+    Ok(digest)
+}
+
+/// A tenant as written in TENANTS_FILE
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantConfig {
+    pub id: String,
+    pub name: String,
+    /// Hex SHA-256 of the tenant's key, the key itself isn't kept
+    pub key_sha256: String,
+    /// Minimum 30-day volume of each fee tier for the tenant's users, the
+    /// deployment's when unset
+    #[serde(default)]
+    pub fee_tier_volumes: Option<[Decimal; FEE_TIER_COUNT]>,
+    /// Sent only events of the tenant's users
+    #[serde(default)]
+    pub webhooks: Vec<WebhookEndpoint>,
+    /// Signs the tenant's webhooks, needed when it has any
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+}
+
+/// A configured tenant
+#[derive(Debug, Clone)]
+pub struct Tenant {
+    pub id: String,
+    pub name: String,
+    pub fee_tier_volumes: [Decimal; FEE_TIER_COUNT],
+    pub webhooks: Vec<WebhookEndpoint>,
+    pub webhook_secret: Arc<[u8]>,
+    key_digest: [u8; 32],
+}
+
+impl Tenant {
+    /// Compares digests without short-circuiting, so timing doesn't leak a prefix
+    fn verify(&self, digest: &[u8; 32]) -> bool {
+        self.key_digest
+            .iter()
+            .zip(digest.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+}
+
+pub struct Tenants {
+    redis_client: redis::Client,
+    tenants: Vec<Tenant>,
+    /// The default tenant's thresholds
+    fee_tier_volumes: [Decimal; FEE_TIER_COUNT],
+}
+
+impl Tenants {
+    pub fn new(
+        redis_client: redis::Client,
+        configs: Vec<TenantConfig>,
+        fee_tier_volumes: [Decimal; FEE_TIER_COUNT],
+    ) -> Result<Self> {
+        let mut ids = HashSet::new();
+        let mut tenants = Vec::with_capacity(configs.len());
+
+        for config in configs {
+            if !is_valid_tenant_id(&config.id) || config.id == DEFAULT_TENANT {
+                return Err(anyhow!("Invalid tenant id {:?}", config.id));
+            }
+            if !ids.insert(config.id.clone()) {
+                return Err(anyhow!("Tenant {} is configured twice", config.id));
+            }
+            if !config.webhooks.is_empty() && config.webhook_secret.is_none() {
+                return Err(anyhow!("Tenant {} has webhooks but no webhook_secret", config.id));
+            }
+
+            let key_digest = parse_digest(&config.key_sha256)
+                .with_context(|| format!("Invalid key of tenant {}", config.id))?;
+            tenants.push(Tenant {
+                fee_tier_volumes: config.fee_tier_volumes.unwrap_or(fee_tier_volumes),
+                webhook_secret: config.webhook_secret.unwrap_or_default().into_bytes().into(),
+                id: config.id,
+                name: config.name,
+                webhooks: config.webhooks,
+                key_digest,
+            });
+        }
+
+        Ok(Self {
+            redis_client,
+            tenants,
+            fee_tier_volumes,
+        })
+    }
+
+    /// Tenants in a TENANTS_FILE, a JSON array of `TenantConfig`
+    pub fn load(path: &str) -> Result<Vec<TenantConfig>> {
+        let raw = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+        serde_json::from_str(&raw).with_context(|| format!("Failed to parse {}", path))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Tenant> {
+        self.tenants.iter().find(|tenant| tenant.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Tenant> {
+        self.tenants.iter()
+    }
+
+    /// Every tenant id, the default one first
+    pub fn ids(&self) -> Vec<&str> {
+        std::iter::once(DEFAULT_TENANT)
+            .chain(self.tenants.iter().map(|tenant| tenant.id.as_str()))
+            .collect()
+    }
+
+    /// The tenant a key belongs to
+    pub fn authenticate(&self, key: &str) -> Option<&Tenant> {
+        let digest = key_digest(key);
+        self.tenants.iter().find(|tenant| tenant.verify(&digest))
+    }
+
+    pub fn fee_tier_volumes(&self, tenant: &str) -> [Decimal; FEE_TIER_COUNT] {
+        self.get(tenant)
+            .map_or(self.fee_tier_volumes, |tenant| tenant.fee_tier_volumes)
+    }
+
+    /// Claim `owner` for `tenant` unless another tenant has. Whether the
+    /// owner is now the tenant's
+    pub async fn claim_owner(&self, tenant: &str, owner: &Pubkey) -> Result<bool> {
+        if tenant == DEFAULT_TENANT {
+            return Ok(self.owner_tenant(owner).await? == DEFAULT_TENANT);
+        }
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let _: bool = conn.hset_nx(OWNER_TENANTS_KEY, owner.to_string(), tenant).await?;
+        let claimed: Option<String> = conn.hget(OWNER_TENANTS_KEY, owner.to_string()).await?;
+
+        Ok(claimed.as_deref() == Some(tenant))
+    }
+
+    /// Forget which tenant `owner` belongs to, when their account closes
+    pub async fn release_owner(&self, owner: &Pubkey) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        conn.hdel::<_, _, ()>(OWNER_TENANTS_KEY, owner.to_string()).await?;
+        Ok(())
+    }
+
+    /// Tenant `owner` belongs to
+    pub async fn owner_tenant(&self, owner: &Pubkey) -> Result<String> {
+        if self.is_empty() {
+            return Ok(DEFAULT_TENANT.to_string());
+        }
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let tenant: Option<String> = conn.hget(OWNER_TENANTS_KEY, owner.to_string()).await?;
+        // A tenant dropped from the file hands its users back
+        Ok(tenant
+            .filter(|tenant| self.get(tenant).is_some())
+            .unwrap_or_else(|| DEFAULT_TENANT.to_string()))
+    }
+
+    /// Owners claimed by a tenant, the default tenant's aren't listed
+    pub async fn owner_tenants(&self) -> Result<HashMap<Pubkey, String>> {
+        if self.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let claimed: HashMap<String, String> = conn.hgetall(OWNER_TENANTS_KEY).await?;
+
+        Ok(claimed
+            .into_iter()
+            .filter(|(_, tenant)| self.get(tenant).is_some())
+            .filter_map(|(owner, tenant)| Some((Pubkey::from_str(&owner).ok()?, tenant)))
+            .collect())
+    }
+
+    /// Owners of a non-default tenant
+    pub async fn owners(&self, tenant: &str) -> Result<HashSet<Pubkey>> {
+        Ok(self
+            .owner_tenants()
+            .await?
+            .into_iter()
+            .filter(|(_, owner_tenant)| owner_tenant == tenant)
+            .map(|(owner, _)| owner)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::default_fee_tier_volumes;
+    use rust_decimal_macros::dec;
+
+    fn config(id: &str, key: &str) -> TenantConfig {
+        TenantConfig {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            key_sha256: key_digest(key).iter().map(|byte| format!("{:02x}", byte)).collect(),
+            fee_tier_volumes: None,
+            webhooks: Vec::new(),
+            webhook_secret: None,
+        }
+    }
+
+    fn tenants(configs: Vec<TenantConfig>) -> Result<Tenants> {
+        let redis_client = redis::Client::open("redis://127.0.0.1/").unwrap();
+        Tenants::new(redis_client, configs, default_fee_tier_volumes())
+    }
+
+    #[test]
+    fn test_tenant_key() {
+        assert_eq!(tenant_key(DEFAULT_TENANT, "api_tokens:abc"), "api_tokens:abc");
+        assert_eq!(tenant_key("acme", "api_tokens:abc"), "tenant:acme:api_tokens:abc");
+
+        assert!(is_valid_tenant_id("acme-perps2"));
+        assert!(!is_valid_tenant_id("Acme"));
+        assert!(!is_valid_tenant_id("acme:perps"));
+        assert!(!is_valid_tenant_id(""));
+    }
+
+    #[test]
+    fn test_authenticate_and_fee_tiers() {
+        let mut acme = config("acme", "tk_acme");
+        acme.fee_tier_volumes = Some([dec!(0), dec!(10), dec!(20), dec!(30)]);
+        let tenants = tenants(vec![acme, config("globex", "tk_globex")]).unwrap();
+
+        assert_eq!(tenants.authenticate("tk_acme").map(|tenant| tenant.id.as_str()), Some("acme"));
+        assert_eq!(tenants.authenticate("tk_globex").map(|tenant| tenant.id.as_str()), Some("globex"));
+        assert!(tenants.authenticate("tk_initech").is_none());
+
+        assert_eq!(tenants.fee_tier_volumes("acme")[1], dec!(10));
+        assert_eq!(tenants.fee_tier_volumes("globex"), default_fee_tier_volumes());
+        assert_eq!(tenants.fee_tier_volumes(DEFAULT_TENANT), default_fee_tier_volumes());
+        assert_eq!(tenants.ids(), vec![DEFAULT_TENANT, "acme", "globex"]);
+    }
+
+    #[test]
+    fn test_rejects_bad_config() {
+        assert!(tenants(vec![config("default", "tk_a")]).is_err());
+        assert!(tenants(vec![config("acme", "tk_a"), config("acme", "tk_b")]).is_err());
+
+        let mut bad_key = config("acme", "tk_a");
+        bad_key.key_sha256 = "abc".to_string();
+        assert!(tenants(vec![bad_key]).is_err());
+
+        let mut no_secret = config("acme", "tk_a");
+        no_secret.webhooks.push(WebhookEndpoint {
+            url: "https://acme.example/hooks".to_string(),
+            events: Vec::new(),
+        });
+        assert!(tenants(vec![no_secret]).is_err());
+    }
+}
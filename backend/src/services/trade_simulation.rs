@@ -0,0 +1,321 @@
+/// Trade Simulation
+/// "If I had opened here": replays archived price ticks against a position
+/// that was never opened. Entry fills at the first tick in the window and exit
+/// at the last, liquidation at the first tick whose margin ratio is below
+/// maintenance, the same test the monitor runs. Fees are left out, and there
+/// is no funding to charge since the program doesn't accrue any
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+
+use crate::domain::Side;
+use crate::error::Error;
+use crate::infrastructure::{bucket_start, HistoryStore, PriceTick};
+use crate::services::MarginCalculator;
+
+// Ticks read from the history store per query
+const TICK_PAGE_SIZE: i64 = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct TradeSimulationParams {
+    pub side: Side,
+    pub size: Decimal,
+    pub leverage: u16,
+    pub maintenance_margin_ratio: Decimal,
+    /// Spacing of `pnl_path` points
+    pub interval: Duration,
+}
+
+/// The position marked at the last tick of one `interval` bucket
+#[derive(Debug, Clone, PartialEq)]
+pub struct PnlPathPoint {
+    pub at: DateTime<Utc>,
+    pub price: Decimal,
+    pub unrealized_pnl: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct TradeSimulation {
+    pub entry_price: Decimal,
+    pub entry_at: DateTime<Utc>,
+    /// Liquidation fill when liquidated
+    pub exit_price: Decimal,
+    pub exit_at: DateTime<Utc>,
+    pub initial_margin: Decimal,
+    pub liquidation_price: Decimal,
+    pub liquidated: bool,
+    /// Never below minus the margin, a liquidation loses at most that
+    pub realized_pnl: Decimal,
+    /// Realized PnL as a percentage of margin
+    pub roi: Decimal,
+    pub peak_pnl: Decimal,
+    /// Largest fall in equity (margin plus PnL) from an earlier high
+    pub max_drawdown: Decimal,
+    /// That fall as a percentage of the high
+    pub max_drawdown_pct: Decimal,
+    pub funding_paid: Decimal,
+    pub ticks: usize,
+    pub pnl_path: Vec<PnlPathPoint>,
+}
+
+#[derive(Debug)]
+struct Opened {
+    entry_price: Decimal,
+    entry_at: DateTime<Utc>,
+    initial_margin: Decimal,
+    liquidation_price: Decimal,
+    last: PnlPathPoint,
+    peak_equity: Decimal,
+    peak_pnl: Decimal,
+    max_drawdown: Decimal,
+    max_drawdown_pct: Decimal,
+    /// Bucket of `last`, whose point is added to the path when the bucket ends
+    bucket: DateTime<Utc>,
+    liquidated: bool,
+    ticks: usize,
+}
+
+/// Ticks go in oldest first, a page at a time, so long windows are never
+/// held in memory
+#[derive(Debug)]
+pub struct TradeReplay {
+    params: TradeSimulationParams,
+    opened: Option<Opened>,
+    pnl_path: Vec<PnlPathPoint>,
+}
+
+impl TradeReplay {
+    pub fn new(params: TradeSimulationParams) -> Self {
+        Self {
+            params,
+            opened: None,
+            pnl_path: Vec::new(),
+        }
+    }
+
+    /// Mark the position at `tick`. False once liquidated, later ticks are ignored
+    pub fn push(&mut self, tick: &PriceTick) -> Result<bool> {
+        let params = &self.params;
+        let bucket = bucket_start(tick.recorded_at, params.interval);
+
+        let opened = match &mut self.opened {
+            Some(opened) if opened.liquidated => return Ok(false),
+            Some(opened) => opened,
+            None => {
+                let initial_margin =
+                    MarginCalculator::calculate_initial_margin(params.size, tick.price, params.leverage)?;
+                self.opened.insert(Opened {
+                    entry_price: tick.price,
+                    entry_at: tick.recorded_at,
+                    initial_margin,
+                    liquidation_price: MarginCalculator::calculate_liquidation_price(
+                        params.side,
+                        tick.price,
+                        params.leverage,
+                        params.maintenance_margin_ratio,
+                    )?,
+                    last: PnlPathPoint {
+                        at: tick.recorded_at,
+                        price: tick.price,
+                        unrealized_pnl: Decimal::ZERO,
+                    },
+                    peak_equity: initial_margin,
+                    peak_pnl: Decimal::ZERO,
+                    max_drawdown: Decimal::ZERO,
+                    max_drawdown_pct: Decimal::ZERO,
+                    bucket,
+                    liquidated: false,
+                    ticks: 0,
+                })
+            }
+        };
+
+        let unrealized_pnl =
+            MarginCalculator::calculate_unrealized_pnl(params.side, params.size, tick.price, opened.entry_price)?;
+
+        if bucket != opened.bucket {
+            self.pnl_path.push(opened.last.clone());
+            opened.bucket = bucket;
+        }
+        opened.last = PnlPathPoint {
+            at: tick.recorded_at,
+            price: tick.price,
+            unrealized_pnl,
+        };
+        opened.ticks += 1;
+
+        let equity = opened.initial_margin + unrealized_pnl;
+        opened.peak_equity = opened.peak_equity.max(equity);
+        opened.peak_pnl = opened.peak_pnl.max(unrealized_pnl);
+        let drawdown = opened.peak_equity - equity;
+        if drawdown > opened.max_drawdown {
+            opened.max_drawdown = drawdown;
+            opened.max_drawdown_pct = drawdown * Decimal::from(100) / opened.peak_equity;
+        }
+
+        opened.liquidated = MarginCalculator::should_liquidate(
+            opened.initial_margin,
+            unrealized_pnl,
+            params.size,
+            tick.price,
+            params.maintenance_margin_ratio,
+        )?;
+
+        Ok(!opened.liquidated)
+    }
+
+    /// Close at the last tick pushed, an error when there were none
+    pub fn finish(mut self) -> Result<TradeSimulation> {
+        let opened = self
+            .opened
+            .ok_or_else(|| Error::NotFound("Price ticks in the simulated window".to_string()))?;
+        self.pnl_path.push(opened.last.clone());
+
+        let realized_pnl = opened.last.unrealized_pnl.max(-opened.initial_margin);
+
+        Ok(TradeSimulation {
+            entry_price: opened.entry_price,
+            entry_at: opened.entry_at,
+            exit_price: opened.last.price,
+            exit_at: opened.last.at,
+            initial_margin: opened.initial_margin,
+            liquidation_price: opened.liquidation_price,
+            liquidated: opened.liquidated,
+            realized_pnl,
+            roi: MarginCalculator::calculate_roi(realized_pnl, opened.initial_margin)?,
+            peak_pnl: opened.peak_pnl,
+            max_drawdown: opened.max_drawdown,
+            max_drawdown_pct: opened.max_drawdown_pct,
+            funding_paid: Decimal::ZERO,
+            ticks: opened.ticks,
+            pnl_path: self.pnl_path,
+        })
+    }
+}
+
+/// Replay `symbol`'s archived ticks in [from, to) against the position
+pub async fn replay_trade(
+    history_store: &dyn HistoryStore,
+    symbol: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    params: TradeSimulationParams,
+) -> Result<TradeSimulation> {
+    if params.interval <= Duration::zero() {
+        return Err(anyhow!("Interval must be positive"));
+    }
+
+    let mut replay = TradeReplay::new(params);
+    let mut page_from = from;
+
+    loop {
+        let page = history_store
+            .symbol_price_ticks(symbol, page_from, to, TICK_PAGE_SIZE)
+            .await?;
+
+        let mut open = true;
+        for tick in &page {
+            open = replay.push(tick)?;
+            if !open {
+                break;
+            }
+        }
+
+        match page.last() {
+            // Store timestamps keep microseconds
+            Some(last) if open && page.len() as i64 == TICK_PAGE_SIZE => {
+                page_from = last.recorded_at + Duration::microseconds(1);
+            }
+            _ => break,
+        }
+    }
+
+    replay.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn tick(secs: i64, price: Decimal) -> PriceTick {
+        PriceTick {
+            symbol: "SOL-USD".to_string(),
+            price,
+            conf: None,
+            publish_time: None,
+            recorded_at: DateTime::from_timestamp(1_700_000_040 + secs, 0).unwrap(),
+        }
+    }
+
+    fn params(side: Side) -> TradeSimulationParams {
+        TradeSimulationParams {
+            side,
+            size: dec!(10),
+            leverage: 10,
+            maintenance_margin_ratio: dec!(0.05),
+            interval: Duration::minutes(1),
+        }
+    }
+
+    fn replay(side: Side, ticks: &[PriceTick]) -> TradeSimulation {
+        let mut replay = TradeReplay::new(params(side));
+        for tick in ticks {
+            if !replay.push(tick).unwrap() {
+                break;
+            }
+        }
+        replay.finish().unwrap()
+    }
+
+    #[test]
+    fn test_replay_tracks_pnl_and_drawdown() {
+        let ticks = [
+            tick(0, dec!(100)),
+            tick(30, dec!(104)),
+            tick(60, dec!(98)),
+            tick(90, dec!(102)),
+        ];
+        let simulation = replay(Side::Long, &ticks);
+
+        assert_eq!(simulation.entry_price, dec!(100));
+        assert_eq!(simulation.exit_price, dec!(102));
+        assert_eq!(simulation.initial_margin, dec!(100));
+        assert!(!simulation.liquidated);
+        assert_eq!(simulation.realized_pnl, dec!(20));
+        assert_eq!(simulation.roi, dec!(20));
+        assert_eq!(simulation.peak_pnl, dec!(40));
+        // Equity 140 down to 80
+        assert_eq!(simulation.max_drawdown, dec!(60));
+        assert_eq!(simulation.funding_paid, Decimal::ZERO);
+        assert_eq!(simulation.ticks, 4);
+
+        // Last tick of each minute
+        let path: Vec<_> = simulation.pnl_path.iter().map(|point| point.unrealized_pnl).collect();
+        assert_eq!(path, vec![dec!(40), dec!(20)]);
+    }
+
+    #[test]
+    fn test_replay_stops_at_liquidation() {
+        let ticks = [
+            tick(0, dec!(100)),
+            tick(1, dec!(104)),
+            // Margin ratio (100 - 100) / 1100 is below maintenance
+            tick(2, dec!(110)),
+            tick(3, dec!(90)),
+        ];
+        let simulation = replay(Side::Short, &ticks);
+
+        assert!(simulation.liquidated);
+        assert_eq!(simulation.exit_price, dec!(110));
+        assert_eq!(simulation.exit_at, ticks[2].recorded_at);
+        assert_eq!(simulation.realized_pnl, dec!(-100));
+        assert_eq!(simulation.roi, dec!(-100));
+        assert_eq!(simulation.ticks, 3);
+    }
+
+    #[test]
+    fn test_replay_without_ticks() {
+        assert!(TradeReplay::new(params(Side::Long)).finish().is_err());
+    }
+}
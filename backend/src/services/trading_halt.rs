@@ -0,0 +1,289 @@
+/// Trading Halt
+/// Kill switch mirrored from the program's Config `paused` flag, plus a halt
+/// of its own when RPC health degrades badly enough that orders and keeper
+/// transactions would act on stale state
+/// Handlers refuse trade-mutating calls while halted, the liquidation
+/// executor drops alerts and cancels in-flight liquidations, and WebSocket
+/// clients get `trading_halted` / `trading_resumed`
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::error::Error;
+use crate::services::PositionManager;
+
+#[derive(Debug, Clone)]
+pub struct TradingHaltConfig {
+    /// How often the Config account is read
+    pub poll_interval_ms: u64,
+    /// Reads slower than this count as failures
+    pub max_rpc_latency_ms: u64,
+    /// Consecutive failed reads before halting
+    pub failure_threshold: u32,
+    /// Consecutive healthy reads before an RPC halt is lifted
+    pub recovery_threshold: u32,
+}
+
+impl Default for TradingHaltConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 1_000,
+            max_rpc_latency_ms: 5_000,
+            failure_threshold: 3,
+            recovery_threshold: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HaltReason {
+    /// The program's kill switch is set
+    ProtocolPaused,
+    /// Config reads keep failing or are too slow
+    RpcDegraded,
+}
+
+impl std::fmt::Display for HaltReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HaltReason::ProtocolPaused => write!(f, "protocol paused"),
+            HaltReason::RpcDegraded => write!(f, "RPC degraded"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HaltStatus {
+    /// None while trading
+    pub reason: Option<HaltReason>,
+    /// When the current halt, or the trading since the last one, started
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl HaltStatus {
+    pub fn is_halted(&self) -> bool {
+        self.reason.is_some()
+    }
+}
+
+/// Outcome of one Config read
+#[derive(Debug, Clone, Copy)]
+pub enum HealthProbe {
+    Healthy { paused: bool, latency: Duration },
+    Failed,
+}
+
+/// Halt state from successive probes, the on-chain pause wins over RPC health
+#[derive(Debug)]
+pub struct HaltStateMachine {
+    config: TradingHaltConfig,
+    paused: bool,
+    rpc_degraded: bool,
+    failures: u32,
+    successes: u32,
+    status: HaltStatus,
+}
+
+impl HaltStateMachine {
+    pub fn new(config: TradingHaltConfig) -> Self {
+        Self {
+            config,
+            paused: false,
+            rpc_degraded: false,
+            failures: 0,
+            successes: 0,
+            status: HaltStatus::default(),
+        }
+    }
+
+    pub fn status(&self) -> &HaltStatus {
+        &self.status
+    }
+
+    /// Apply a probe, returning the new status when the halt reason changed
+    pub fn observe(&mut self, probe: HealthProbe, at: DateTime<Utc>) -> Option<HaltStatus> {
+        let max_latency = Duration::from_millis(self.config.max_rpc_latency_ms);
+
+        match probe {
+            HealthProbe::Healthy { paused, latency } if latency <= max_latency => {
+                self.paused = paused;
+                self.failures = 0;
+                self.successes += 1;
+                if self.successes >= self.config.recovery_threshold {
+                    self.rpc_degraded = false;
+                }
+            }
+            // A slow read still tells us the flag, the pause applies right away
+            HealthProbe::Healthy { paused, .. } => {
+                self.paused = paused;
+                self.record_failure();
+            }
+            // Keep the last known flag
+            HealthProbe::Failed => self.record_failure(),
+        }
+
+        let reason = if self.paused {
+            Some(HaltReason::ProtocolPaused)
+        } else if self.rpc_degraded {
+            Some(HaltReason::RpcDegraded)
+        } else {
+            None
+        };
+
+        if reason == self.status.reason {
+            return None;
+        }
+
+        // Switching between halt reasons doesn't restart the halt
+        let since = match (self.status.is_halted(), reason.is_some()) {
+            (true, true) => self.status.since,
+            _ => Some(at),
+        };
+
+        self.status = HaltStatus { reason, since };
+        Some(self.status.clone())
+    }
+
+    fn record_failure(&mut self) {
+        self.successes = 0;
+        self.failures += 1;
+        if self.failures >= self.config.failure_threshold {
+            self.rpc_degraded = true;
+        }
+    }
+}
+
+pub struct TradingHalt {
+    position_manager: Arc<PositionManager>,
+    config: TradingHaltConfig,
+    status_tx: watch::Sender<HaltStatus>,
+}
+
+impl TradingHalt {
+    pub fn new(position_manager: Arc<PositionManager>, config: TradingHaltConfig) -> Self {
+        let (status_tx, _) = watch::channel(HaltStatus::default());
+
+        Self {
+            position_manager,
+            config,
+            status_tx,
+        }
+    }
+
+    pub fn status(&self) -> HaltStatus {
+        self.status_tx.borrow().clone()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.status_tx.borrow().is_halted()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<HaltStatus> {
+        self.status_tx.subscribe()
+    }
+
+    /// Resolves once trading is halted, for racing keeper actions against
+    pub async fn halted(&self) {
+        let mut status_rx = self.subscribe();
+        let _ = status_rx.wait_for(HaltStatus::is_halted).await;
+    }
+
+    /// Poll the Config account in the background
+    pub fn spawn(self: &Arc<Self>) {
+        let halt = Arc::clone(self);
+
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(Duration::from_millis(halt.config.poll_interval_ms));
+            let mut state = HaltStateMachine::new(halt.config.clone());
+
+            info!("Trading halt monitor started");
+
+            loop {
+                ticker.tick().await;
+
+                let probe = halt.probe().await;
+                if let Some(status) = state.observe(probe, Utc::now()) {
+                    match status.reason {
+                        Some(reason) => warn!("Trading halted: {}", reason),
+                        None => info!("Trading resumed"),
+                    }
+                    halt.status_tx.send_replace(status);
+                }
+            }
+        });
+    }
+
+    async fn probe(&self) -> HealthProbe {
+        let started = Instant::now();
+
+        match self.position_manager.is_trading_paused().await {
+            Ok(paused) => HealthProbe::Healthy {
+                paused,
+                latency: started.elapsed(),
+            },
+            // No Config yet, the RPC answered fine and nothing is paused
+            Err(e) if matches!(e.downcast_ref::<Error>(), Some(Error::NotFound(_))) => {
+                HealthProbe::Healthy {
+                    paused: false,
+                    latency: started.elapsed(),
+                }
+            }
+            Err(e) => {
+                warn!("Trading halt probe failed: {}", e);
+                HealthProbe::Failed
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy(paused: bool) -> HealthProbe {
+        HealthProbe::Healthy {
+            paused,
+            latency: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn test_halt_state_machine() {
+        let mut state = HaltStateMachine::new(TradingHaltConfig {
+            failure_threshold: 2,
+            recovery_threshold: 2,
+            ..Default::default()
+        });
+        let t0 = Utc::now();
+
+        assert_eq!(state.observe(healthy(false), t0), None);
+
+        // Pause applies on the first read
+        let halted = state.observe(healthy(true), t0).unwrap();
+        assert_eq!(halted.reason, Some(HaltReason::ProtocolPaused));
+        assert_eq!(halted.since, Some(t0));
+
+        // RPC degrading under a pause keeps the pause
+        assert_eq!(state.observe(HealthProbe::Failed, t0), None);
+        assert_eq!(state.observe(HealthProbe::Failed, t0), None);
+
+        // Unpaused on a slow read, still degraded, the halt carries on
+        let slow = HealthProbe::Healthy {
+            paused: false,
+            latency: Duration::from_secs(10),
+        };
+        let degraded = state.observe(slow, Utc::now()).unwrap();
+        assert_eq!(degraded.reason, Some(HaltReason::RpcDegraded));
+        assert_eq!(degraded.since, Some(t0));
+
+        // Lifted after enough healthy reads
+        assert_eq!(state.observe(healthy(false), t0), None);
+        let resumed = state.observe(healthy(false), t0).unwrap();
+        assert!(!resumed.is_halted());
+    }
+}
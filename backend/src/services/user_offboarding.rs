@@ -0,0 +1,123 @@
+/// User Offboarding
+/// DELETE /users/:id closes the owner's user account on chain, withdrawing
+/// any collateral left when asked to, then drops what the backend keeps on
+/// the owner in Redis: API tokens, strategies, external positions, risk
+/// limits, price alerts and the alert rules on their positions
+/// Positions and trade history stay, they're the record of what was traded
+use anyhow::{anyhow, Result};
+use solana_sdk::pubkey::Pubkey;
+use tracing::info;
+
+use crate::infrastructure::ProgramError;
+use crate::services::{ApiTokenService, PositionMonitor, RiskLimitService, RiskLimits, Tenants, UserAccountData};
+
+/// What the owner signs to delete their account
+pub fn close_user_message(owner: &Pubkey, timestamp: i64) -> String {
+    format!("Close user account {} at {}", owner, timestamp)
+}
+
+fn program_error(code: &str) -> anyhow::Error {
+    match ProgramError::from_code(code) {
+        Some(error) => error.into(),
+        None => anyhow!("{}", code),
+    }
+}
+
+/// Collateral to withdraw before closing, refused the way the program would
+/// refuse the close: while positions are open, or while collateral is left
+/// and the owner didn't ask to withdraw it
+pub fn collateral_to_withdraw(account: &UserAccountData, withdraw_collateral: bool) -> Result<u64> {
+    if account.position_count > 0 || account.locked_collateral > 0 {
+        return Err(program_error("UserHasOpenPositions"));
+    }
+    if account.total_collateral > 0 && !withdraw_collateral {
+        return Err(program_error("UserHasCollateral"));
+    }
+
+    Ok(account.total_collateral)
+}
+
+/// Counts of what `purge_user_data` removed
+#[derive(Debug, Clone, Default)]
+pub struct PurgedUserData {
+    pub api_tokens: usize,
+    pub strategies: usize,
+    pub external_positions: usize,
+    pub price_alerts: usize,
+    /// Positions whose alert rules were dropped
+    pub alerted_positions: usize,
+}
+
+/// Drop the owner's backend-side data, called once their account is closed
+pub async fn purge_user_data(
+    monitor: &PositionMonitor,
+    api_tokens: &ApiTokenService,
+    risk_limits: &RiskLimitService,
+    tenants: &Tenants,
+    owner: &Pubkey,
+) -> Result<PurgedUserData> {
+    let position_accounts: Vec<Pubkey> = monitor
+        .get_user_positions(owner)
+        .await?
+        .iter()
+        .map(|position| position.position_account)
+        .collect();
+    monitor.position_alerts().remove_rules_for(&position_accounts).await?;
+
+    let owner_str = owner.to_string();
+    let price_alerts = monitor.price_alerts().list(None, Some(&owner_str)).await?;
+    for alert in &price_alerts {
+        monitor.price_alerts().remove(alert.id).await?;
+    }
+
+    risk_limits.set(owner, &RiskLimits::default()).await?;
+
+    // Tokens may have been minted through any tenant's frontend
+    let mut revoked_tokens = 0;
+    for tenant in tenants.ids() {
+        revoked_tokens += api_tokens.revoke_all(tenant, owner).await?;
+    }
+    tenants.release_owner(owner).await?;
+
+    let purged = PurgedUserData {
+        api_tokens: revoked_tokens,
+        strategies: monitor.strategies().clear(owner).await?,
+        external_positions: monitor.external_positions().clear(owner).await?,
+        price_alerts: price_alerts.len(),
+        alerted_positions: position_accounts.len(),
+    };
+
+    info!("Purged data of offboarded user {}: {:?}", owner, purged);
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(position_count: u32, total_collateral: u64, locked_collateral: u64) -> UserAccountData {
+        UserAccountData {
+            owner: Pubkey::new_unique(),
+            total_collateral,
+            locked_collateral,
+            total_pnl: 0,
+            position_count,
+            position_count_total: 3,
+            bump: 255,
+        }
+    }
+
+    fn refusal(result: Result<u64>) -> String {
+        result.unwrap_err().downcast::<ProgramError>().unwrap().code
+    }
+
+    #[test]
+    fn test_collateral_to_withdraw() {
+        assert_eq!(collateral_to_withdraw(&account(0, 0, 0), false).unwrap(), 0);
+        assert_eq!(collateral_to_withdraw(&account(0, 500, 0), true).unwrap(), 500);
+
+        assert_eq!(refusal(collateral_to_withdraw(&account(0, 500, 0), false)), "UserHasCollateral");
+        assert_eq!(refusal(collateral_to_withdraw(&account(1, 500, 100), true)), "UserHasOpenPositions");
+        assert_eq!(refusal(collateral_to_withdraw(&account(0, 500, 100), true)), "UserHasOpenPositions");
+    }
+}
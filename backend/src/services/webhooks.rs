@@ -0,0 +1,475 @@
+/// Webhooks
+/// Operator-configured endpoints that receive service events, each one
+/// subscribed to some event types. Every delivery carries an HMAC-SHA256
+/// signature over its timestamp and body, and is retried with exponential
+/// backoff on network errors, 408, 429 and 5xx responses
+/// Trade reports are a FIX-like execution report of each close and
+/// liquidation this service submits, for post-trade reporting. Liquidation
+/// alerts come in batches, see `WebhookNotifier`
+/// Tenants' endpoints get the events of their own users, signed with their
+/// own secret, while the deployment's endpoints get every event
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::domain::{RealizedPnL, Side};
+use crate::services::{Tenants, DEFAULT_TENANT};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const WEBHOOK_ID_HEADER: &str = "x-webhook-id";
+pub const WEBHOOK_EVENT_HEADER: &str = "x-webhook-event";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventType {
+    TradeReport,
+    LiquidationAlerts,
+}
+
+impl WebhookEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::TradeReport => "trade_report",
+            WebhookEventType::LiquidationAlerts => "liquidation_alerts",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    pub events: Vec<WebhookEventType>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WebhookConfig {
+    /// Tries per delivery, the first one included
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled by each later one
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Body of every delivery
+#[derive(Debug, Serialize)]
+pub struct WebhookEnvelope<'a, T> {
+    /// Same on every retry, so receivers can dedupe
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: WebhookEventType,
+    pub created_at: DateTime<Utc>,
+    pub data: &'a T,
+}
+
+/// Execution report of a close or liquidation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeReport {
+    /// The realized PnL record id, one per execution
+    pub exec_id: Uuid,
+    /// `close` or `liquidation`
+    pub exec_type: String,
+    pub symbol: String,
+    /// Side of the closing execution, `sell` for a long
+    pub side: String,
+    pub position_side: Side,
+    /// Base58 position account
+    pub position_account: String,
+    /// Base58 owner wallet
+    pub account: String,
+    /// Size closed by this execution
+    pub qty: Decimal,
+    pub avg_px: Decimal,
+    pub entry_px: Decimal,
+    pub fees: Decimal,
+    pub funding: Decimal,
+    /// Net of fees and funding
+    pub realized_pnl: Decimal,
+    pub opened_at: DateTime<Utc>,
+    pub transact_time: DateTime<Utc>,
+    pub signature: String,
+}
+
+impl TradeReport {
+    pub fn new(record: &RealizedPnL, opened_at: DateTime<Utc>) -> Self {
+        Self {
+            exec_id: record.id,
+            exec_type: if record.liquidation { "liquidation" } else { "close" }.to_string(),
+            symbol: record.symbol.clone(),
+            side: match record.side {
+                Side::Long => "sell",
+                Side::Short => "buy",
+            }
+            .to_string(),
+            position_side: record.side,
+            position_account: record.position_account.to_string(),
+            account: record.owner.to_string(),
+            qty: record.size_closed,
+            avg_px: record.close_price,
+            entry_px: record.entry_price,
+            fees: record.fee,
+            funding: record.funding,
+            realized_pnl: record.amount,
+            opened_at,
+            transact_time: record.closed_at,
+            signature: record.signature.clone(),
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 of `<timestamp>.<body>`, sent as `sha256=<hex>`
+pub fn sign_payload(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Responses worth another try, anything else is final
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Wait after failed attempt `attempt`, counted from 1
+fn retry_backoff(config: &WebhookConfig, attempt: u32) -> Duration {
+    config
+        .base_backoff
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(config.max_backoff)
+}
+
+pub struct WebhookDispatcher {
+    endpoints: Vec<WebhookEndpoint>,
+    secret: Arc<[u8]>,
+    /// Whose endpoints also get their users' events
+    tenants: Option<Arc<Tenants>>,
+    config: WebhookConfig,
+    http_client: reqwest::Client,
+}
+
+impl WebhookDispatcher {
+    pub fn new(endpoints: Vec<WebhookEndpoint>, secret: impl Into<Vec<u8>>, config: WebhookConfig) -> Self {
+        Self {
+            endpoints,
+            secret: secret.into().into(),
+            tenants: None,
+            config,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Send events of tenants' users to the tenants' endpoints too
+    pub fn with_tenants(mut self, tenants: Arc<Tenants>) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    pub fn tenants(&self) -> Option<&Arc<Tenants>> {
+        self.tenants.as_ref()
+    }
+
+    /// Whether any endpoint, a tenant's included, takes `event_type`
+    pub fn subscribed(&self, event_type: WebhookEventType) -> bool {
+        let tenant_endpoints = self
+            .tenants
+            .iter()
+            .flat_map(|tenants| tenants.iter())
+            .flat_map(|tenant| tenant.webhooks.iter());
+
+        self.endpoints
+            .iter()
+            .chain(tenant_endpoints)
+            .any(|endpoint| endpoint.events.contains(&event_type))
+    }
+
+    /// Deliver `data` to every deployment endpoint subscribed to
+    /// `event_type` in the background, retrying each one on its own
+    pub fn dispatch<T: Serialize>(&self, event_type: WebhookEventType, data: &T) {
+        let targets = self.targets(event_type, true, None);
+        for delivery in self.deliveries(targets, event_type, Uuid::new_v4(), Utc::now(), data) {
+            tokio::spawn(delivery.run());
+        }
+    }
+
+    /// Like `dispatch`, to `tenant`'s endpoints only
+    pub fn dispatch_to_tenant<T: Serialize>(&self, tenant: &str, event_type: WebhookEventType, data: &T) {
+        let targets = self.targets(event_type, false, Some(tenant));
+        for delivery in self.deliveries(targets, event_type, Uuid::new_v4(), Utc::now(), data) {
+            tokio::spawn(delivery.run());
+        }
+    }
+
+    /// Like `dispatch`, to the endpoints of `owner`'s tenant as well
+    pub async fn dispatch_for_owner<T: Serialize>(
+        &self,
+        owner: &Pubkey,
+        event_type: WebhookEventType,
+        data: &T,
+    ) -> Result<()> {
+        let tenant = self.owner_tenant(owner).await?;
+        let targets = self.targets(event_type, true, Some(&tenant));
+        for delivery in self.deliveries(targets, event_type, Uuid::new_v4(), Utc::now(), data) {
+            tokio::spawn(delivery.run());
+        }
+        Ok(())
+    }
+
+    /// Deliver an event under a known `id`, e.g. an outbox entry's, to the
+    /// deployment's endpoints and those of `owner`'s tenant, waiting until
+    /// every endpoint accepted it or ran out of retries
+    pub async fn deliver<T: Serialize>(
+        &self,
+        owner: Option<&Pubkey>,
+        event_type: WebhookEventType,
+        id: Uuid,
+        created_at: DateTime<Utc>,
+        data: &T,
+    ) -> Result<()> {
+        let tenant = match owner {
+            Some(owner) => Some(self.owner_tenant(owner).await?),
+            None => None,
+        };
+        let targets = self.targets(event_type, true, tenant.as_deref());
+        let deliveries = self.deliveries(targets, event_type, id, created_at, data);
+        futures::future::join_all(deliveries.into_iter().map(Delivery::run)).await;
+        Ok(())
+    }
+
+    async fn owner_tenant(&self, owner: &Pubkey) -> Result<String> {
+        match &self.tenants {
+            Some(tenants) => tenants.owner_tenant(owner).await,
+            None => Ok(DEFAULT_TENANT.to_string()),
+        }
+    }
+
+    /// URL and signing secret of the endpoints taking `event_type`: the
+    /// deployment's when `global`, and `tenant`'s
+    fn targets(&self, event_type: WebhookEventType, global: bool, tenant: Option<&str>) -> Vec<(String, Arc<[u8]>)> {
+        let mut targets: Vec<(String, Arc<[u8]>)> = Vec::new();
+
+        if global {
+            targets.extend(
+                self.endpoints
+                    .iter()
+                    .filter(|endpoint| endpoint.events.contains(&event_type))
+                    .map(|endpoint| (endpoint.url.clone(), Arc::clone(&self.secret))),
+            );
+        }
+
+        let tenant = tenant.and_then(|tenant| self.tenants.as_ref()?.get(tenant));
+        if let Some(tenant) = tenant {
+            targets.extend(
+                tenant
+                    .webhooks
+                    .iter()
+                    .filter(|endpoint| endpoint.events.contains(&event_type))
+                    .map(|endpoint| (endpoint.url.clone(), Arc::clone(&tenant.webhook_secret))),
+            );
+        }
+
+        targets
+    }
+
+    fn deliveries<T: Serialize>(
+        &self,
+        targets: Vec<(String, Arc<[u8]>)>,
+        event_type: WebhookEventType,
+        id: Uuid,
+        created_at: DateTime<Utc>,
+        data: &T,
+    ) -> Vec<Delivery> {
+        let envelope = WebhookEnvelope {
+            id,
+            event_type,
+            created_at,
+            data,
+        };
+        let body: Arc<[u8]> = match serde_json::to_vec(&envelope) {
+            Ok(body) => body.into(),
+            Err(e) => {
+                warn!("Failed to serialize {} webhook: {}", event_type.as_str(), e);
+                return Vec::new();
+            }
+        };
+
+        targets
+            .into_iter()
+            .map(|(url, secret)| Delivery {
+                http_client: self.http_client.clone(),
+                config: self.config,
+                secret,
+                url,
+                id,
+                event_type,
+                body: Arc::clone(&body),
+            })
+            .collect()
+    }
+}
+
+struct Delivery {
+    http_client: reqwest::Client,
+    config: WebhookConfig,
+    secret: Arc<[u8]>,
+    url: String,
+    id: Uuid,
+    event_type: WebhookEventType,
+    body: Arc<[u8]>,
+}
+
+impl Delivery {
+    async fn run(self) {
+        for attempt in 1..=self.config.max_attempts {
+            // Signed per attempt so receivers can reject stale timestamps
+            let timestamp = Utc::now().timestamp();
+            let signature = sign_payload(&self.secret, timestamp, &self.body);
+
+            let result = self
+                .http_client
+                .post(&self.url)
+                .timeout(self.config.timeout)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(WEBHOOK_ID_HEADER, self.id.to_string())
+                .header(WEBHOOK_EVENT_HEADER, self.event_type.as_str())
+                .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+                .header(WEBHOOK_SIGNATURE_HEADER, format!("sha256={}", signature))
+                .body(self.body.to_vec())
+                .send()
+                .await;
+
+            let error = match result {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response) if !is_retryable(response.status()) => {
+                    warn!(
+                        "{} webhook {} to {} rejected with {}, not retrying",
+                        self.event_type.as_str(),
+                        self.id,
+                        self.url,
+                        response.status()
+                    );
+                    return;
+                }
+                Ok(response) => response.status().to_string(),
+                Err(e) => e.to_string(),
+            };
+
+            if attempt == self.config.max_attempts {
+                warn!(
+                    "{} webhook {} to {} failed after {} attempts: {}",
+                    self.event_type.as_str(),
+                    self.id,
+                    self.url,
+                    attempt,
+                    error
+                );
+                return;
+            }
+
+            let backoff = retry_backoff(&self.config, attempt);
+            info!(
+                "{} webhook {} to {} failed ({}), retrying in {:?}",
+                self.event_type.as_str(),
+                self.id,
+                self.url,
+                error,
+                backoff
+            );
+            tokio::time::sleep(backoff).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn test_sign_payload() {
+        // echo -n '1700000000.{"a":1}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign_payload(b"secret", 1_700_000_000, br#"{"a":1}"#),
+            "49f24e537407743fa4a0242bb63b94b9a47ee99cbbe071ccd8a22550ae411686"
+        );
+        assert_ne!(
+            sign_payload(b"secret", 1_700_000_001, br#"{"a":1}"#),
+            sign_payload(b"secret", 1_700_000_000, br#"{"a":1}"#)
+        );
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        assert!(is_retryable(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable(StatusCode::UNAUTHORIZED));
+
+        let config = WebhookConfig {
+            max_attempts: 5,
+            base_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            timeout: Duration::from_secs(5),
+        };
+        let backoffs: Vec<u64> = (1..=4).map(|attempt| retry_backoff(&config, attempt).as_secs()).collect();
+        assert_eq!(backoffs, vec![1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_trade_report_from_liquidation() {
+        let record = RealizedPnL {
+            id: Uuid::new_v4(),
+            position_account: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Short,
+            size_closed: dec!(4),
+            entry_price: dec!(100),
+            close_price: dec!(105),
+            fee: Decimal::ZERO,
+            funding: dec!(-0.5),
+            amount: dec!(-20.5),
+            liquidation: true,
+            signature: "5wHu1qwD".to_string(),
+            closed_at: Utc::now(),
+        };
+
+        let report = TradeReport::new(&record, Utc::now());
+        assert_eq!(report.exec_id, record.id);
+        assert_eq!(report.exec_type, "liquidation");
+        // Closing a short buys it back
+        assert_eq!(report.side, "buy");
+        assert_eq!(report.qty, dec!(4));
+        assert_eq!(report.avg_px, dec!(105));
+        assert_eq!(report.realized_pnl, dec!(-20.5));
+    }
+}
@@ -0,0 +1,187 @@
+//! Full position lifecycle against a local validator, driven through the REST
+//! API: open, modify, a price move that raises a liquidation alert, close, and
+//! reopening a closed user account
+//!
+//! cargo test --test e2e -- --ignored
+
+mod harness;
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use harness::{decimal, wait_until, TestEnv, WsStream};
+use perpetual_backend::services::close_user_message;
+use rust_decimal_macros::dec;
+use serde_json::{json, Value};
+use solana_sdk::signer::Signer;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+const SYMBOL: &str = "SOL-USD";
+
+// Monitor passes run every 2s, allow a handful
+const MONITOR_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs solana-test-validator, redis-server and the program built with anchor build"]
+async fn test_position_lifecycle() -> Result<()> {
+    tracing_subscriber::fmt().with_max_level(tracing::Level::INFO).try_init().ok();
+
+    let env = TestEnv::start(&[
+        ("BTC-USD", dec!(60000)),
+        ("ETH-USD", dec!(3000)),
+        (SYMBOL, dec!(100)),
+    ])
+    .await?;
+    let owner = env.owner();
+    tracing::info!("Environment up, logs in {}", env.dir.display());
+
+    env.post("/users/initialize", json!({ "owner": owner })).await?;
+    // 1000 USDT
+    env.post(&format!("/users/{}/collateral", owner), json!({ "amount": 1_000_000_000u64 }))
+        .await?;
+
+    // Wait for the price the open is checked against
+    wait_until("oracle price", MONITOR_TIMEOUT, || async {
+        Ok(env.get(&format!("/prices/{}", SYMBOL)).await.is_ok())
+    })
+    .await?;
+
+    // 10 margin on 100 notional
+    let opened = env
+        .post(
+            "/positions/open",
+            json!({
+                "owner": owner,
+                "symbol": SYMBOL,
+                "side": "Long",
+                "size": "1",
+                "leverage": 10,
+                "entry_price": "100",
+            }),
+        )
+        .await?;
+    let position_account = opened["position"]["position_account"]
+        .as_str()
+        .ok_or_else(|| anyhow!("No position_account in {}", opened))?
+        .to_string();
+    let position_path = format!("/positions/{}", position_account);
+
+    wait_until("open position in the monitor", MONITOR_TIMEOUT, || async {
+        Ok(env.get(&position_path).await.is_ok())
+    })
+    .await?;
+
+    // Top up to 15 margin
+    env.put(&format!("{}/modify", position_path), json!({ "margin_delta": 5_000_000 }))
+        .await?;
+    wait_until("modified margin", MONITOR_TIMEOUT, || async {
+        let position = env.get(&position_path).await?;
+        Ok(decimal(&position["margin"])? == dec!(15))
+    })
+    .await?;
+
+    // Past the liquidation price, the monitor alerts on its next pass
+    let mut ws = env.ws().await?;
+    env.hermes.set_price(SYMBOL, dec!(80))?;
+    let alert = next_liquidation_alert(&mut ws, &position_account).await?;
+    assert_eq!(alert["symbol"], SYMBOL);
+    assert_eq!(alert["side"], "Long");
+
+    // Back at the entry price before closing, nothing gained or lost
+    env.hermes.set_price(SYMBOL, dec!(100))?;
+    wait_until("price recovery", MONITOR_TIMEOUT, || async {
+        let price = env.get(&format!("/prices/{}", SYMBOL)).await?;
+        Ok(decimal(&price["price"])? == dec!(100))
+    })
+    .await?;
+
+    let closed = env
+        .delete(&format!("{}/close", position_path), json!({ "final_price": "100" }))
+        .await?;
+    assert_eq!(decimal(&closed["pnl"])?, dec!(0));
+
+    wait_until("closed position in the monitor", MONITOR_TIMEOUT, || async {
+        let position = env.get(&position_path).await?;
+        Ok(position["status"] == "Closed")
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs solana-test-validator, redis-server and the program built with anchor build"]
+async fn test_reopen_after_close() -> Result<()> {
+    tracing_subscriber::fmt().with_max_level(tracing::Level::INFO).try_init().ok();
+
+    let env = TestEnv::start(&[(SYMBOL, dec!(100))]).await?;
+    let owner = env.owner();
+
+    wait_until("oracle price", MONITOR_TIMEOUT, || async {
+        Ok(env.get(&format!("/prices/{}", SYMBOL)).await.is_ok())
+    })
+    .await?;
+
+    let open = || async {
+        env.post("/users/initialize", json!({ "owner": owner })).await?;
+        env.post(&format!("/users/{}/collateral", owner), json!({ "amount": 100_000_000u64 }))
+            .await?;
+        let opened = env
+            .post(
+                "/positions/open",
+                json!({
+                    "owner": owner,
+                    "symbol": SYMBOL,
+                    "side": "Long",
+                    "size": "1",
+                    "leverage": 10,
+                    "entry_price": "100",
+                }),
+            )
+            .await?;
+        opened["position"]["position_account"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("No position_account in {}", opened))
+    };
+
+    let first = open().await?;
+    env.delete(&format!("/positions/{}/close", first), json!({ "final_price": "100" }))
+        .await?;
+
+    let timestamp = chrono::Utc::now().timestamp();
+    let message = close_user_message(&env.payer.pubkey(), timestamp);
+    env.delete(
+        &format!("/users/{}", owner),
+        json!({
+            "timestamp": timestamp,
+            "signature": env.payer.sign_message(message.as_bytes()).to_string(),
+            "withdraw_collateral": true,
+        }),
+    )
+    .await?;
+
+    // Indexes start over at 0, on the address the closed position freed
+    let second = open().await?;
+    assert_eq!(second, first);
+
+    Ok(())
+}
+
+/// The first `liquidation_alert` for `position_account`
+async fn next_liquidation_alert(ws: &mut WsStream, position_account: &str) -> Result<Value> {
+    tokio::time::timeout(MONITOR_TIMEOUT, async {
+        while let Some(message) = ws.next().await {
+            let Message::Text(text) = message? else {
+                continue;
+            };
+            let message: Value = serde_json::from_str(&text)?;
+            if message["type"] == "liquidation_alert" && message["position_account"] == position_account {
+                return Ok(message);
+            }
+        }
+        Err(anyhow!("WebSocket closed before a liquidation alert"))
+    })
+    .await
+    .map_err(|_| anyhow!("No liquidation alert for {} within {:?}", position_account, MONITOR_TIMEOUT))?
+}
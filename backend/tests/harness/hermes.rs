@@ -0,0 +1,109 @@
+/// Mock Hermes
+/// Serves `/v2/updates/price/latest` in Hermes' response format from prices
+/// the test sets, standing in for Pyth so a price move is one call away
+use anyhow::{anyhow, Result};
+use axum::extract::{RawQuery, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::net::TcpListener;
+
+/// The feeds `OracleClient::with_mainnet_defaults` polls
+const FEEDS: [(&str, &str); 3] = [
+    ("BTC-USD", "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43"),
+    ("ETH-USD", "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace"),
+    ("SOL-USD", "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d"),
+];
+
+// Hermes publishes USD feeds with 8 decimals
+const EXPO: i32 = -8;
+
+type Prices = Arc<RwLock<HashMap<String, Decimal>>>;
+
+pub struct MockHermes {
+    url: String,
+    prices: Prices,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl MockHermes {
+    /// Listen on a free local port, quoting the symbols in `initial`
+    pub async fn start(initial: &[(&str, Decimal)]) -> Result<Self> {
+        let prices: Prices = Arc::new(RwLock::new(HashMap::new()));
+        for (symbol, price) in initial {
+            prices.write().unwrap().insert(feed_id(symbol)?.to_string(), *price);
+        }
+
+        let app = Router::new()
+            .route("/v2/updates/price/latest", get(latest))
+            .with_state(Arc::clone(&prices));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        Ok(Self { url, prices, server })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Quote `symbol` at `price` from the next poll on
+    pub fn set_price(&self, symbol: &str, price: Decimal) -> Result<()> {
+        self.prices.write().unwrap().insert(feed_id(symbol)?.to_string(), price);
+        Ok(())
+    }
+}
+
+impl Drop for MockHermes {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+fn feed_id(symbol: &str) -> Result<&'static str> {
+    FEEDS
+        .iter()
+        .find(|(feed_symbol, _)| *feed_symbol == symbol)
+        .map(|(_, id)| *id)
+        .ok_or_else(|| anyhow!("No Pyth feed for {}", symbol))
+}
+
+async fn latest(State(prices): State<Prices>, RawQuery(query): RawQuery) -> Result<Json<Value>, StatusCode> {
+    let prices = prices.read().unwrap();
+    let now = chrono::Utc::now().timestamp();
+
+    // ids[]=0x<feed id>, one per requested feed
+    let parsed: Vec<Value> = query
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .filter_map(|(_, id)| {
+            let id = id.trim_start_matches("0x");
+            let price = prices.get(id)?;
+            let mantissa = (price * Decimal::from(10i64.pow(EXPO.unsigned_abs()))).trunc().to_i64()?;
+            Some(json!({
+                "id": id,
+                "price": {
+                    "price": mantissa.to_string(),
+                    "conf": "100000",
+                    "expo": EXPO,
+                    "publish_time": now,
+                },
+            }))
+        })
+        .collect();
+
+    if parsed.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(json!({ "parsed": parsed })))
+}
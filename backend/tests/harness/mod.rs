@@ -0,0 +1,397 @@
+/// End-to-end harness
+/// Starts solana-test-validator with the program deployed, a Redis server, a
+/// mock Hermes and the backend binary pointed at all three, each on a free
+/// local port, and tears them down when the environment is dropped
+/// Needs `solana-test-validator` and `redis-server` on PATH and the program
+/// built with `anchor build`, or PROGRAM_SO pointing at the .so
+pub mod hermes;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use perpetual_backend::infrastructure::{price_update_address, pyth_feed_id, SolanaClient};
+use reqwest::Method;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::bpf_loader_upgradeable;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::system_program;
+use std::fs::File;
+use std::future::Future;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use hermes::MockHermes;
+
+pub const PROGRAM_ID: &str = "9bca4kbDn7uyQWQaqfKpe8hCdbBh6KqJFNbkzwHhieC3";
+
+const DISCRIMINATOR_INITIALIZE_CONFIG: [u8; 8] = [208, 127, 21, 1, 194, 190, 196, 70];
+
+const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+const PYTH_RECEIVER_PROGRAM_ID: &str = "rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ";
+// Longer than a test runs
+const PRICE_UPDATE_LEAD_SECS: i64 = 600;
+
+// The validator takes a few seconds to produce its first block, the backend
+// waits for its first chain scan
+const VALIDATOR_START_TIMEOUT: Duration = Duration::from_secs(60);
+const BACKEND_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// A spawned process, killed on drop
+struct Process {
+    name: &'static str,
+    child: Child,
+}
+
+impl Process {
+    fn spawn(name: &'static str, command: &mut Command, dir: &Path) -> Result<Self> {
+        let log = File::create(dir.join(format!("{}.log", name)))?;
+        let child = command
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()
+            .with_context(|| format!("Failed to start {}, is it on PATH?", name))?;
+        Ok(Self { name, child })
+    }
+
+    fn ensure_running(&mut self) -> Result<()> {
+        if let Some(status) = self.child.try_wait()? {
+            bail!("{} exited with {}", self.name, status);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// Validator, Redis, mock Hermes and backend for one test
+pub struct TestEnv {
+    /// In start order, stopped in reverse
+    processes: Vec<Process>,
+    pub hermes: MockHermes,
+    pub payer: Arc<Keypair>,
+    api_url: String,
+    http: reqwest::Client,
+    /// Ledger and process logs, left behind for a failed run to be read
+    pub dir: PathBuf,
+}
+
+impl TestEnv {
+    /// Bring everything up with the program's config initialized and the
+    /// payer funded. The backend signs with the payer, so it is also the
+    /// owner of every account the test creates
+    pub async fn start(prices: &[(&str, Decimal)]) -> Result<Self> {
+        let program_so = program_so()?;
+        let dir = std::env::temp_dir().join(format!("perps-e2e-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+
+        // Upgrade authority of the program, which initialize_config requires as admin
+        let payer = Arc::new(Keypair::new());
+
+        let mut price_accounts = Vec::new();
+        for (symbol, price) in prices {
+            price_accounts.push(write_price_update_account(&dir, symbol, *price)?);
+        }
+
+        let rpc_port = free_port()?;
+        let rpc_url = format!("http://127.0.0.1:{}", rpc_port);
+        let mut validator_command = Command::new("solana-test-validator");
+        for (address, file) in &price_accounts {
+            validator_command.arg("--account").arg(address.to_string()).arg(file);
+        }
+        let mut validator = Process::spawn(
+            "solana-test-validator",
+            validator_command
+                .arg("--reset")
+                .arg("--quiet")
+                .arg("--ledger")
+                .arg(dir.join("ledger"))
+                .args(["--bind-address", "127.0.0.1"])
+                .args(["--rpc-port", &rpc_port.to_string()])
+                .args(["--faucet-port", &free_port()?.to_string()])
+                .args(["--gossip-port", &free_port()?.to_string()])
+                .arg("--upgradeable-program")
+                .arg(PROGRAM_ID)
+                .arg(&program_so)
+                .arg(payer.pubkey().to_string()),
+            &dir,
+        )?;
+
+        let redis_port = free_port()?;
+        let mut redis = Process::spawn(
+            "redis-server",
+            Command::new("redis-server")
+                .args(["--port", &redis_port.to_string()])
+                .args(["--save", ""])
+                .args(["--appendonly", "no"])
+                .arg("--dir")
+                .arg(&dir),
+            &dir,
+        )?;
+
+        let rpc = RpcClient::new(rpc_url.clone());
+        let health = &rpc;
+        wait_for_process(&mut validator, VALIDATOR_START_TIMEOUT, move || async move {
+            health.get_health().await.is_ok()
+        })
+        .await?;
+        wait_for_process(&mut redis, VALIDATOR_START_TIMEOUT, move || async move {
+            TcpStream::connect(("127.0.0.1", redis_port)).is_ok()
+        })
+        .await?;
+
+        fund(&rpc, &payer.pubkey(), 100 * LAMPORTS_PER_SOL).await?;
+        initialize_config(&rpc_url, &payer)?;
+
+        let hermes = MockHermes::start(prices).await?;
+
+        let port = free_port()?;
+        let api_url = format!("http://127.0.0.1:{}", port);
+        // Run from the ledger directory so a developer's .env isn't picked up
+        let mut backend = Process::spawn(
+            "perpetual-backend",
+            Command::new(env!("CARGO_BIN_EXE_perpetual-backend"))
+                .current_dir(&dir)
+                .env("RPC_URL", &rpc_url)
+                .env("PROGRAM_ID", PROGRAM_ID)
+                .env("SIGNER_TYPE", "private_key")
+                .env("SOLANA_PRIVATE_KEY", payer.to_base58_string())
+                .env("REDIS_URL", format!("redis://127.0.0.1:{}", redis_port))
+                .env("PYTH_HERMES_URL", hermes.url())
+                .env("PORT", port.to_string())
+                .env("RUST_LOG", "info")
+                .stdin(Stdio::null()),
+            &dir,
+        )?;
+
+        let http = reqwest::Client::new();
+        let ready_url = format!("{}/ready", api_url);
+        wait_for_process(&mut backend, BACKEND_START_TIMEOUT, || {
+            let ready = http.get(&ready_url).send();
+            async move {
+                ready
+                    .await
+                    .map(|response| response.status().is_success())
+                    .unwrap_or(false)
+            }
+        })
+        .await
+        .with_context(|| format!("Backend log in {}", dir.display()))?;
+
+        Ok(Self {
+            processes: vec![validator, redis, backend],
+            hermes,
+            payer,
+            api_url,
+            http,
+            dir,
+        })
+    }
+
+    pub fn owner(&self) -> String {
+        self.payer.pubkey().to_string()
+    }
+
+    pub async fn get(&self, path: &str) -> Result<Value> {
+        self.request(Method::GET, path, None).await
+    }
+
+    pub async fn post(&self, path: &str, body: Value) -> Result<Value> {
+        self.request(Method::POST, path, Some(body)).await
+    }
+
+    pub async fn put(&self, path: &str, body: Value) -> Result<Value> {
+        self.request(Method::PUT, path, Some(body)).await
+    }
+
+    pub async fn delete(&self, path: &str, body: Value) -> Result<Value> {
+        self.request(Method::DELETE, path, Some(body)).await
+    }
+
+    /// Call the API, failing on anything but a 2xx with the error body
+    async fn request(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let mut request = self.http.request(method.clone(), format!("{}{}", self.api_url, path));
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            bail!("{} {} returned {}: {}", method, path, status, text);
+        }
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Connect to the backend's WebSocket
+    pub async fn ws(&self) -> Result<WsStream> {
+        let url = format!("{}/ws", self.api_url.replacen("http", "ws", 1));
+        let (stream, _) = connect_async(url.as_str()).await?;
+        Ok(stream)
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        // The backend stops before what it talks to
+        while let Some(process) = self.processes.pop() {
+            drop(process);
+        }
+    }
+}
+
+/// Where `anchor build` puts the program, unless PROGRAM_SO says otherwise
+fn program_so() -> Result<PathBuf> {
+    let path = match std::env::var("PROGRAM_SO") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../position-management-system/target/deploy/position_management_system.so"),
+    };
+    if !path.exists() {
+        bail!(
+            "{} not found, run anchor build in position-management-system or set PROGRAM_SO",
+            path.display()
+        );
+    }
+    Ok(path)
+}
+
+fn free_port() -> Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+/// Poll `check` every 250ms until it holds
+pub async fn wait_until<F, Fut>(what: &str, timeout: Duration, mut check: F) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<bool>>,
+{
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if check().await? {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    Err(anyhow!("Timed out after {:?} waiting for {}", timeout, what))
+}
+
+/// Poll `ready` every 250ms until it holds, failing early if `process` exits
+async fn wait_for_process<F, Fut>(process: &mut Process, timeout: Duration, mut ready: F) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        process.ensure_running()?;
+        if ready().await {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    Err(anyhow!("Timed out after {:?} waiting for {}", timeout, process.name))
+}
+
+async fn fund(rpc: &RpcClient, account: &Pubkey, lamports: u64) -> Result<()> {
+    rpc.request_airdrop(account, lamports).await?;
+    wait_until("airdrop", VALIDATOR_START_TIMEOUT, move || async move {
+        Ok(rpc.get_balance(account).await? >= lamports)
+    })
+    .await
+}
+
+/// The backend has no route for the one-off config setup, send it directly
+fn initialize_config(rpc_url: &str, admin: &Arc<Keypair>) -> Result<()> {
+    let program_id = Pubkey::from_str(PROGRAM_ID)?;
+    let client = SolanaClient::new(program_id, Arc::clone(admin), rpc_url.to_string());
+
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(client.derive_config_pda().0, false),
+            AccountMeta::new(client.derive_liquidator_whitelist_pda().0, false),
+            AccountMeta::new(admin.pubkey(), true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(
+                Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0,
+                false,
+            ),
+        ],
+        data: DISCRIMINATOR_INITIALIZE_CONFIG.to_vec(),
+    };
+
+    client
+        .send_transaction(&[instruction])
+        .context("Failed to initialize config")?;
+    Ok(())
+}
+
+/// Write a Pyth PriceUpdateV2 quoting `symbol` at `price` for the validator to
+/// load at the push oracle's address, as the program reads prices from there.
+/// Nothing updates it, so it is stamped ahead of the clock to stay within
+/// the program's maximum price age for the length of a test
+fn write_price_update_account(dir: &Path, symbol: &str, price: Decimal) -> Result<(Pubkey, PathBuf)> {
+    let address = price_update_address(symbol)?;
+    let publish_time = chrono::Utc::now().timestamp() + PRICE_UPDATE_LEAD_SECS;
+    // Hermes' 8 decimals
+    let price = (price * Decimal::from(100_000_000u64))
+        .to_i64()
+        .ok_or_else(|| anyhow!("{} price out of range", symbol))?;
+
+    let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&[0; 32]); // write authority
+    data.push(1); // VerificationLevel::Full
+    data.extend_from_slice(&pyth_feed_id(symbol)?);
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes()); // conf
+    data.extend_from_slice(&(-8i32).to_le_bytes());
+    data.extend_from_slice(&publish_time.to_le_bytes());
+    data.extend_from_slice(&publish_time.to_le_bytes()); // prev_publish_time
+    data.extend_from_slice(&price.to_le_bytes()); // ema_price
+    data.extend_from_slice(&0u64.to_le_bytes()); // ema_conf
+    data.extend_from_slice(&0u64.to_le_bytes()); // posted_slot
+
+    let account = json!({
+        "pubkey": address.to_string(),
+        "account": {
+            "lamports": LAMPORTS_PER_SOL,
+            "data": [BASE64_STANDARD.encode(&data), "base64"],
+            "owner": PYTH_RECEIVER_PROGRAM_ID,
+            "executable": false,
+            "rentEpoch": 0,
+            "space": data.len(),
+        },
+    });
+    let file = dir.join(format!("price-update-{}.json", symbol));
+    std::fs::write(&file, serde_json::to_vec(&account)?)?;
+    Ok((address, file))
+}
+
+/// A decimal field, serialized as a string or a number
+pub fn decimal(value: &Value) -> Result<Decimal> {
+    match value {
+        Value::String(s) => Ok(Decimal::from_str(s)?),
+        Value::Number(n) => Ok(Decimal::from_str(&n.to_string())?),
+        other => Err(anyhow!("Expected a decimal, got {}", other)),
+    }
+}
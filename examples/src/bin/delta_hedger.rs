@@ -0,0 +1,226 @@
+//! Delta hedger against the paper trading sandbox
+//!
+//! Holds `--inventory` of the base asset off the exchange and keeps a perp
+//! position of `-inventory × hedge ratio` against it. Prices come from the
+//! backend WebSocket; every `--interval-secs` the session's positions are
+//! read back and opened, resized or closed towards the target at the last
+//! price. Logs the quotes a market maker would show with what is left unhedged.
+//! The backend needs PAPER_TRADING_ENABLED=true, nothing is sent to Solana
+//!
+//! cargo run --bin delta-hedger -- --symbol BTC-USD --inventory 0.5 --band 0.01
+
+use anyhow::{anyhow, Result};
+use futures_util::{SinkExt, StreamExt};
+use perps_examples::{
+    net_size, plan_hedge, quotes, HedgeStep, OpenPositionRequest, PerpsClient,
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{error, info, warn};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+struct HedgerConfig {
+    api_url: String,
+    session: String,
+    /// Paper positions need a well-formed owner, no collateral is checked
+    owner: String,
+    symbol: String,
+    /// Base asset held elsewhere
+    inventory: Decimal,
+    hedge_ratio: Decimal,
+    /// Net size drift tolerated before trading
+    band: Decimal,
+    leverage: u16,
+    interval: Duration,
+    spread_bps: u32,
+    /// Quote shift per unit of unhedged delta
+    skew_bps: u32,
+}
+
+impl HedgerConfig {
+    fn from_args() -> Result<Self> {
+        let mut config = Self {
+            api_url: "http://localhost:3000".to_string(),
+            session: "delta-hedger".to_string(),
+            owner: "8dQ3YCcpvmHvbEVqLwp3zPh5Xo7GJR8NbBbdzFXwZLbz".to_string(),
+            symbol: "BTC-USD".to_string(),
+            inventory: Decimal::ONE,
+            hedge_ratio: Decimal::ONE,
+            band: Decimal::new(1, 2),
+            leverage: 5,
+            interval: Duration::from_secs(5),
+            spread_bps: 20,
+            skew_bps: 10,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("Missing value for {}", flag))?;
+
+            match flag.as_str() {
+                "--api-url" => config.api_url = value,
+                "--session" => config.session = value,
+                "--owner" => config.owner = value,
+                "--symbol" => config.symbol = value,
+                "--inventory" => config.inventory = value.parse()?,
+                "--hedge-ratio" => config.hedge_ratio = value.parse()?,
+                "--band" => config.band = value.parse()?,
+                "--leverage" => config.leverage = value.parse()?,
+                "--interval-secs" => config.interval = Duration::from_secs(value.parse()?),
+                "--spread-bps" => config.spread_bps = value.parse()?,
+                "--skew-bps" => config.skew_bps = value.parse()?,
+                _ => return Err(anyhow!("Unknown flag {}", flag)),
+            }
+        }
+
+        if config.band < Decimal::ZERO {
+            return Err(anyhow!("--band must not be negative"));
+        }
+
+        Ok(config)
+    }
+
+    fn target(&self) -> Decimal {
+        -self.inventory * self.hedge_ratio
+    }
+}
+
+/// The price updates out of the backend's WebSocket messages
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamMessage {
+    PriceUpdate { symbol: String, price: Decimal },
+    #[serde(other)]
+    Other,
+}
+
+struct Hedger {
+    config: HedgerConfig,
+    client: PerpsClient,
+    mid: Option<Decimal>,
+}
+
+impl Hedger {
+    /// Track prices and rebalance on the interval until the stream drops
+    async fn run_connection(&mut self) -> Result<()> {
+        let (stream, _) = connect_async(self.client.ws_url()).await?;
+        let (mut sender, mut receiver) = stream.split();
+
+        let hello = serde_json::json!({ "type": "hello", "protocol_version": 1 });
+        sender.send(Message::Text(hello.to_string())).await?;
+        let subscribe = serde_json::json!({ "type": "subscribe_symbol", "symbol": self.config.symbol });
+        sender.send(Message::Text(subscribe.to_string())).await?;
+        info!("Streaming {} prices", self.config.symbol);
+
+        let mut rebalance_tick = tokio::time::interval(self.config.interval);
+        loop {
+            tokio::select! {
+                message = receiver.next() => {
+                    let Some(message) = message else {
+                        return Ok(());
+                    };
+                    let Message::Text(text) = message? else {
+                        continue;
+                    };
+                    if let Ok(StreamMessage::PriceUpdate { symbol, price }) = serde_json::from_str(&text) {
+                        if symbol == self.config.symbol {
+                            self.mid = Some(price);
+                        }
+                    }
+                }
+                _ = rebalance_tick.tick() => {
+                    if let Err(e) = self.rebalance().await {
+                        error!("Rebalance failed: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn rebalance(&mut self) -> Result<()> {
+        let Some(mid) = self.mid else {
+            return Ok(());
+        };
+
+        let positions = self.client.open_positions(&self.config.symbol).await?;
+        let residual = self.config.inventory + net_size(&positions);
+        let (bid, ask) = quotes(mid, self.config.spread_bps, residual, self.config.skew_bps);
+        info!(
+            "{} mid {} | bid {} ask {} | perp net {} unhedged {}",
+            self.config.symbol,
+            mid,
+            bid.round_dp(6),
+            ask.round_dp(6),
+            net_size(&positions),
+            residual
+        );
+
+        for step in plan_hedge(&positions, self.config.target(), self.config.band) {
+            match &step {
+                HedgeStep::Open { side, size } => {
+                    let position = self
+                        .client
+                        .open_position(&OpenPositionRequest {
+                            owner: self.config.owner.clone(),
+                            symbol: self.config.symbol.clone(),
+                            side: *side,
+                            size: *size,
+                            leverage: self.config.leverage,
+                            entry_price: mid,
+                        })
+                        .await?;
+                    info!("Opened {:?} {} at {}: {}", side, size, mid, position.position_account);
+                }
+                HedgeStep::Resize {
+                    position_account,
+                    new_size,
+                } => {
+                    self.client.resize_position(position_account, *new_size).await?;
+                    info!("Resized {} to {}", position_account, new_size);
+                }
+                HedgeStep::Close { position_account } => {
+                    let pnl = self.client.close_position(position_account, mid).await?;
+                    info!("Closed {} at {}, PnL {}", position_account, mid, pnl);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+
+    let config = HedgerConfig::from_args()?;
+    info!(
+        "Hedging {} {} at ratio {} in paper session {}",
+        config.inventory, config.symbol, config.hedge_ratio, config.session
+    );
+
+    let client = PerpsClient::new(config.api_url.clone()).with_paper_session(config.session.clone());
+    let mut hedger = Hedger {
+        config,
+        client,
+        mid: None,
+    };
+
+    loop {
+        match hedger.run_connection().await {
+            Ok(()) => warn!("Price stream closed"),
+            Err(e) => error!("Price stream failed: {}", e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
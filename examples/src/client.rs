@@ -0,0 +1,168 @@
+/// REST Client
+/// The calls the examples make against the backend, with the paper trading
+/// session header added to every request when one is set. Types mirror the
+/// API's JSON, only the fields the examples read
+use anyhow::{anyhow, Result};
+use reqwest::{RequestBuilder, Response};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub const PAPER_SESSION_HEADER: &str = "x-paper-session";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Long,
+    Short,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Position {
+    pub position_account: String,
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub entry_price: Decimal,
+    pub mark_price: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub status: String,
+}
+
+impl Position {
+    pub fn is_open(&self) -> bool {
+        matches!(self.status.as_str(), "Open" | "Opening" | "MarginCall")
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpenPositionRequest {
+    pub owner: String,
+    pub symbol: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub leverage: u16,
+    pub entry_price: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenPositionResponse {
+    position: Position,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClosePositionResponse {
+    pnl: Decimal,
+}
+
+/// Error body every endpoint returns
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    code: String,
+    message: Option<String>,
+}
+
+pub struct PerpsClient {
+    http: reqwest::Client,
+    base_url: String,
+    paper_session: Option<String>,
+}
+
+impl PerpsClient {
+    /// `base_url` like http://localhost:3000
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            paper_session: None,
+        }
+    }
+
+    /// Run every call against a paper trading session instead of the chain
+    pub fn with_paper_session(mut self, session: impl Into<String>) -> Self {
+        self.paper_session = Some(session.into());
+        self
+    }
+
+    /// WebSocket URL, streaming the session's positions when there is one
+    pub fn ws_url(&self) -> String {
+        let base = self
+            .base_url
+            .replacen("https://", "wss://", 1)
+            .replacen("http://", "ws://", 1);
+
+        match &self.paper_session {
+            Some(session) => format!("{}/ws?paper_session={}", base, session),
+            None => format!("{}/ws", base),
+        }
+    }
+
+    /// Open positions on `symbol`, oldest first
+    pub async fn open_positions(&self, symbol: &str) -> Result<Vec<Position>> {
+        let request = self
+            .http
+            .get(format!("{}/positions", self.base_url))
+            .query(&[("symbol", symbol)]);
+        let positions: Vec<Position> = self.send(request).await?;
+
+        Ok(positions.into_iter().filter(Position::is_open).collect())
+    }
+
+    pub async fn open_position(&self, request: &OpenPositionRequest) -> Result<Position> {
+        let request = self
+            .http
+            .post(format!("{}/positions/open", self.base_url))
+            .json(request);
+        let response: OpenPositionResponse = self.send(request).await?;
+
+        Ok(response.position)
+    }
+
+    /// Resize a position, its entry price stays the same
+    pub async fn resize_position(&self, position_account: &str, new_size: Decimal) -> Result<()> {
+        let request = self
+            .http
+            .put(format!("{}/positions/{}/modify", self.base_url, position_account))
+            .json(&serde_json::json!({ "new_size": new_size }));
+        let _: serde_json::Value = self.send(request).await?;
+
+        Ok(())
+    }
+
+    /// Close at `final_price`, returns the realized PnL
+    pub async fn close_position(&self, position_account: &str, final_price: Decimal) -> Result<Decimal> {
+        let request = self
+            .http
+            .delete(format!("{}/positions/{}/close", self.base_url, position_account))
+            .json(&serde_json::json!({ "final_price": final_price }));
+        let response: ClosePositionResponse = self.send(request).await?;
+
+        Ok(response.pnl)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let request = match &self.paper_session {
+            Some(session) => request.header(PAPER_SESSION_HEADER, session),
+            None => request,
+        };
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+async fn api_error(response: Response) -> anyhow::Error {
+    let status = response.status();
+    match response.json::<ErrorBody>().await {
+        Ok(body) => anyhow!(
+            "{} {}: {}",
+            status.as_u16(),
+            body.code,
+            body.message.unwrap_or_default()
+        ),
+        Err(_) => anyhow!("{}", status),
+    }
+}
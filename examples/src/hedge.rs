@@ -0,0 +1,180 @@
+/// Delta Hedging
+/// Steps that move a set of perp positions on one symbol to a target net
+/// size, and the quotes a market maker would show around the mark given
+/// what is left unhedged. Pure functions, the binary does the calls
+use rust_decimal::Decimal;
+
+use crate::client::{Position, Side};
+
+const BPS_DENOMINATOR: u32 = 10_000;
+
+/// One call towards the target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HedgeStep {
+    Open { side: Side, size: Decimal },
+    Resize { position_account: String, new_size: Decimal },
+    Close { position_account: String },
+}
+
+/// Long minus short size
+pub fn net_size(positions: &[Position]) -> Decimal {
+    positions
+        .iter()
+        .map(|position| match position.side {
+            Side::Long => position.size,
+            Side::Short => -position.size,
+        })
+        .sum()
+}
+
+/// Steps taking `positions` (open, oldest first) to a net size of `target`.
+/// Nothing when the net is already within `band` of it. Positions against the
+/// target's direction are closed; the rest are shrunk newest first, or topped
+/// up with a new position
+pub fn plan_hedge(positions: &[Position], target: Decimal, band: Decimal) -> Vec<HedgeStep> {
+    if (net_size(positions) - target).abs() <= band {
+        return Vec::new();
+    }
+
+    let side = if target > Decimal::ZERO {
+        Side::Long
+    } else {
+        Side::Short
+    };
+    let wanted = target.abs();
+
+    let mut steps = Vec::new();
+    let mut kept = Decimal::ZERO;
+    let mut same_side = Vec::new();
+
+    for position in positions {
+        if target.is_zero() || position.side != side {
+            steps.push(HedgeStep::Close {
+                position_account: position.position_account.clone(),
+            });
+        } else {
+            kept += position.size;
+            same_side.push(position);
+        }
+    }
+
+    if kept < wanted {
+        steps.push(HedgeStep::Open {
+            side,
+            size: wanted - kept,
+        });
+    }
+
+    for position in same_side.into_iter().rev() {
+        if kept <= wanted {
+            break;
+        }
+
+        let excess = kept - wanted;
+        if position.size <= excess {
+            steps.push(HedgeStep::Close {
+                position_account: position.position_account.clone(),
+            });
+            kept -= position.size;
+        } else {
+            steps.push(HedgeStep::Resize {
+                position_account: position.position_account.clone(),
+                new_size: position.size - excess,
+            });
+            kept = wanted;
+        }
+    }
+
+    steps
+}
+
+/// Bid and ask `spread_bps` apart around `mid`, both shifted by `skew_bps`
+/// for each unit of `residual` delta, so a long book quotes lower and sells first
+pub fn quotes(mid: Decimal, spread_bps: u32, residual: Decimal, skew_bps: u32) -> (Decimal, Decimal) {
+    let bps = Decimal::from(BPS_DENOMINATOR);
+    let half_spread = mid * Decimal::from(spread_bps) / bps / Decimal::TWO;
+    let skew = mid * residual * Decimal::from(skew_bps) / bps;
+
+    (mid - half_spread - skew, mid + half_spread - skew)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn position(account: &str, side: Side, size: Decimal) -> Position {
+        Position {
+            position_account: account.to_string(),
+            symbol: "BTC-USD".to_string(),
+            side,
+            size,
+            entry_price: dec!(100),
+            mark_price: dec!(100),
+            unrealized_pnl: Decimal::ZERO,
+            status: "Open".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_plan_within_band() {
+        let positions = [position("a", Side::Short, dec!(1))];
+        assert!(plan_hedge(&positions, dec!(-1.05), dec!(0.1)).is_empty());
+    }
+
+    #[test]
+    fn test_plan_opens_and_tops_up() {
+        assert_eq!(
+            plan_hedge(&[], dec!(-2), dec!(0.1)),
+            vec![HedgeStep::Open { side: Side::Short, size: dec!(2) }]
+        );
+
+        let positions = [position("a", Side::Short, dec!(1))];
+        assert_eq!(
+            plan_hedge(&positions, dec!(-1.5), dec!(0.1)),
+            vec![HedgeStep::Open { side: Side::Short, size: dec!(0.5) }]
+        );
+    }
+
+    #[test]
+    fn test_plan_shrinks_newest_first() {
+        let positions = [
+            position("old", Side::Short, dec!(1)),
+            position("mid", Side::Short, dec!(0.5)),
+            position("new", Side::Short, dec!(0.25)),
+        ];
+
+        assert_eq!(
+            plan_hedge(&positions, dec!(-1.2), dec!(0.1)),
+            vec![
+                HedgeStep::Close { position_account: "new".to_string() },
+                HedgeStep::Resize { position_account: "mid".to_string(), new_size: dec!(0.2) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_flips_side() {
+        let positions = [position("a", Side::Long, dec!(1))];
+        assert_eq!(
+            plan_hedge(&positions, dec!(-0.5), dec!(0.1)),
+            vec![
+                HedgeStep::Close { position_account: "a".to_string() },
+                HedgeStep::Open { side: Side::Short, size: dec!(0.5) },
+            ]
+        );
+
+        assert_eq!(
+            plan_hedge(&positions, Decimal::ZERO, dec!(0.1)),
+            vec![HedgeStep::Close { position_account: "a".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_quotes_skew_against_residual() {
+        assert_eq!(quotes(dec!(100), 20, Decimal::ZERO, 10), (dec!(99.9), dec!(100.1)));
+
+        // Long 2 units: both sides 20 bps lower
+        assert_eq!(quotes(dec!(100), 20, dec!(2), 10), (dec!(99.7), dec!(99.9)));
+    }
+}
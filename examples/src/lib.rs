@@ -0,0 +1,5 @@
+pub mod client;
+pub mod hedge;
+
+pub use client::*;
+pub use hedge::*;
@@ -3,6 +3,41 @@ use anchor_lang::prelude::*;
 pub const PRICE_PRECISION: u64 = 1_000_000;
 pub const SUPPORTED_ASSET_DECIMALS: u64 = 1_000_000;
 pub const MAX_SYMBOL_LENGTH: usize = 32;
+pub const MAX_WHITELISTED_LIQUIDATORS: usize = 32;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const DEFAULT_LIQUIDATION_BUFFER_BPS: u16 = 100; // 1% above maintenance
+pub const DEFAULT_MARGIN_CALL_BUFFER_BPS: u16 = 100; // margin call 1% above maintenance
+pub const SHARE_DECIMALS: u8 = 6;                     // vault share mint, same scale as collateral
+pub const MAX_BACKSTOP_LEVERAGE: u16 = 10;            // absorbed positions are held at most 10x
+pub const MAX_DEFAULT_SLIPPAGE_BPS: u16 = 2_000;      // a user's default slippage is at most 20%
+pub const MAX_MARKET_MAINTENANCE_MARGIN_BPS: u16 = 5_000; // a market's maintenance rate is at most 50%
+pub const MAX_MAINTENANCE_MARGIN_STEP_BPS: u16 = 50;      // one crank moves a market's maintenance rate at most 0.5%
+pub const MAINTENANCE_MARGIN_WINDOW_SECS: i64 = 3_600;    // window the crank's total move is capped over
+pub const MAX_MAINTENANCE_MARGIN_WINDOW_BPS: u16 = 150;   // cranks move a market's maintenance rate at most 1.5% per window
+pub const POSITION_HISTORY_LEN: usize = 8;               // size/margin changes kept in a Position, oldest overwritten
+pub const FEE_TIER_COUNT: usize = 4;                     // volume tiers of the taker fee schedule, 0 is the base rate
+pub const MAX_TAKER_FEE_BPS: u16 = 100;                  // a taker fee is at most 1% of notional
+pub const MAX_BATCH_LIQUIDATIONS: usize = 10;             // positions per liquidate_many, bounded by the transaction's account list
+pub const DEFAULT_MAX_PRICE_AGE_SECS: u32 = 60;            // oldest oracle publish time accepted until the admin sets one
+
+/// Pyth receiver program, owner of the PriceUpdateV2 accounts prices are read from
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Pyth feed id of each tradable symbol, a price update for any other feed is refused
+pub const PRICE_FEEDS: [(&str, [u8; 32]); 3] = [
+    ("BTC-USD", [
+        230, 45, 246, 200, 180, 168, 95, 225, 166, 125, 180, 77, 193, 45, 229, 219,
+        51, 15, 122, 198, 107, 114, 220, 101, 138, 254, 223, 15, 74, 65, 91, 67,
+    ]),
+    ("ETH-USD", [
+        255, 97, 73, 26, 147, 17, 18, 221, 241, 189, 129, 71, 205, 27, 100, 19,
+        117, 247, 159, 88, 37, 18, 109, 102, 84, 128, 135, 70, 52, 253, 10, 206,
+    ]),
+    ("SOL-USD", [
+        239, 13, 139, 111, 218, 44, 235, 164, 29, 161, 93, 64, 149, 209, 218, 57,
+        42, 13, 47, 142, 208, 198, 199, 188, 15, 76, 250, 200, 194, 128, 181, 109,
+    ]),
+];
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
 pub struct LeverageTier {
@@ -34,4 +34,121 @@ pub enum PositionError {
     
     #[msg("Unauthorized")]
     Unauthorized,
+
+    #[msg("Position is above maintenance margin and cannot be liquidated")]
+    PositionNotLiquidatable,
+
+    #[msg("Liquidator is not whitelisted")]
+    LiquidatorNotWhitelisted,
+
+    #[msg("Liquidator is already whitelisted")]
+    LiquidatorAlreadyWhitelisted,
+
+    #[msg("Liquidator not found in whitelist")]
+    LiquidatorNotFound,
+
+    #[msg("Liquidator whitelist is full")]
+    LiquidatorWhitelistFull,
+
+    #[msg("Partial liquidation is disabled")]
+    PartialLiquidationDisabled,
+
+    #[msg("Close fraction must be between 1 and 10000 basis points")]
+    InvalidCloseFraction,
+
+    #[msg("Liquidation buffer must be at most 10000 basis points")]
+    InvalidLiquidationBuffer,
+
+    #[msg("Partial liquidation leaves the position below maintenance plus buffer")]
+    PartialLiquidationInsufficient,
+
+    #[msg("Backstop vault discount must be at most 10000 basis points")]
+    InvalidBackstopDiscount,
+
+    #[msg("Backstop vault lacks free assets for this operation")]
+    BackstopInsufficientLiquidity,
+
+    #[msg("Not enough vault shares")]
+    InsufficientShares,
+
+    #[msg("No vault earnings to claim")]
+    NothingToClaim,
+
+    #[msg("Position is not held by the backstop vault")]
+    NotBackstopPosition,
+
+    #[msg("Trading is paused")]
+    TradingPaused,
+
+    #[msg("Position index does not match the user's position count")]
+    PositionIndexMismatch,
+
+    #[msg("Position is under a margin call, only top-ups and size reductions are allowed")]
+    MarginCallActive,
+
+    #[msg("Margin call grace period has not elapsed")]
+    MarginCallGracePeriod,
+
+    #[msg("Margin calls are disabled")]
+    MarginCallDisabled,
+
+    #[msg("Margin call status already matches the margin ratio")]
+    MarginCallUnchanged,
+
+    #[msg("Margin call buffer must be at most 10000 basis points")]
+    InvalidMarginCallBuffer,
+
+    #[msg("Default slippage must be at most 2000 basis points")]
+    InvalidSlippage,
+
+    #[msg("Maintenance margin bounds must be min <= max <= 5000 basis points")]
+    InvalidMarketBounds,
+
+    #[msg("Maintenance margin is outside the market's bounds")]
+    MaintenanceMarginOutOfBounds,
+
+    #[msg("Opened on this market too recently, wait for the cooldown")]
+    OpenCooldownActive,
+
+    #[msg("Too many opens on this market in this slot")]
+    MarketOpenLimitReached,
+
+    #[msg("Position account is already at the current size")]
+    PositionUpToDate,
+
+    #[msg("User still has open positions")]
+    UserHasOpenPositions,
+
+    #[msg("User still has collateral, withdraw it first")]
+    UserHasCollateral,
+
+    #[msg("Taker fees must be at most 100 basis points and not rise with the tier")]
+    InvalidFeeSchedule,
+
+    #[msg("Fee tier is out of range")]
+    InvalidFeeTier,
+
+    #[msg("Oracle price is older than the configured maximum age")]
+    StaleOracle,
+
+    #[msg("Liquidation batch must hold 1 to 10 position and user account pairs on the market")]
+    InvalidLiquidationBatch,
+
+    #[msg("Oracle account is not a fully verified Pyth price update")]
+    InvalidOracleAccount,
+
+    #[msg("Oracle price update is not for this market's feed")]
+    OracleFeedMismatch,
+
+    #[msg("Oracle price is not positive or out of range")]
+    InvalidOraclePrice,
+
+    #[msg("Max oracle price age must be greater than 0")]
+    InvalidMaxPriceAge,
+
+    #[msg("Position accounts must be every position of the owner, in index order")]
+    InvalidPositionAccounts,
+
+    #[msg("Maintenance margin moves at most 50 basis points per crank and 150 per hour")]
+    MaintenanceMarginStepTooLarge,
 }
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, FreezeAccount, Mint, MintTo, ThawAccount, Token, TokenAccount};
 use crate::state::*;
+use crate::constants::{FEE_TIER_COUNT, SHARE_DECIMALS};
 use crate::errors::PositionError;
 
 #[derive(Accounts)]
@@ -19,8 +21,20 @@ pub struct InitializeUser<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// `position_index` must be the user's `position_count_total`, checked
+/// before the position PDA derived from it is created
 #[derive(Accounts)]
+#[instruction(position_index: u32, symbol: String)]
 pub struct OpenPosition<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.position_count_total == position_index
+            @ PositionError::PositionIndexMismatch
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
     #[account(
         init,
         payer = user,
@@ -28,23 +42,51 @@ pub struct OpenPosition<'info> {
         seeds = [
             b"position",
             user.key().as_ref(),
-            user_account.position_count_total.to_le_bytes().as_ref()
+            position_index.to_le_bytes().as_ref()
         ],
         bump
     )]
     pub position: Account<'info, Position>,
-    
+
     #[account(
-        mut,
-        seeds = [b"user", user.key().as_ref()],
-        bump = user_account.bump
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ PositionError::TradingPaused
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub config: Account<'info, Config>,
     
     #[account(mut)]
     pub user: Signer<'info>,
     
     pub system_program: Program<'info, System>,
+
+    /// CHECK: the symbol's Market PDA, empty until the admin initializes it
+    #[account(
+        mut,
+        seeds = [b"market", symbol.as_bytes()],
+        bump
+    )]
+    pub market: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = OpenThrottle::LEN,
+        seeds = [b"open_throttle", user.key().as_ref(), symbol.as_bytes()],
+        bump
+    )]
+    pub open_throttle: Account<'info, OpenThrottle>,
+
+    /// Owners without an assigned tier pay the base taker fee
+    #[account(
+        mut,
+        seeds = [b"fee_tier", user.key().as_ref()],
+        bump = fee_tier.bump
+    )]
+    pub fee_tier: Option<Account<'info, UserFeeTier>>,
+
+    /// CHECK: a Pyth price update, owner and feed checked by read_oracle_price
+    pub price_update: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
@@ -61,8 +103,22 @@ pub struct ModifyPosition<'info> {
         bump = user_account.bump
     )]
     pub user_account: Account<'info, UserAccount>,
+
+    /// Paused trading only lets reduce-only changes through, checked in the handler
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
     
     pub owner: Signer<'info>,
+
+    /// CHECK: the symbol's Market PDA, empty until the admin initializes it
+    #[account(
+        seeds = [b"market", position.symbol.as_bytes()],
+        bump
+    )]
+    pub market: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
@@ -83,6 +139,22 @@ pub struct ClosePosition<'info> {
     
     #[account(mut)]
     pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_tier", owner.key().as_ref()],
+        bump = fee_tier.bump
+    )]
+    pub fee_tier: Option<Account<'info, UserFeeTier>>,
+
+    /// CHECK: a Pyth price update, owner and feed checked by read_oracle_price
+    pub price_update: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
@@ -97,7 +169,581 @@ pub struct ModifyUserCollateral<'info> {
     pub owner: Signer<'info>,
 }
 
+/// The rent of both accounts and of the owner's Positions, passed in
+/// remaining_accounts, goes back to the owner
+#[derive(Accounts)]
+pub struct CloseUserAccount<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", owner.key().as_ref()],
+        bump = user_account.bump,
+        has_one = owner @ PositionError::Unauthorized,
+        close = owner
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    /// Closed with the user account when the owner saved settings
+    #[account(
+        mut,
+        seeds = [b"user_settings", owner.key().as_ref()],
+        bump = user_settings.bump,
+        close = owner
+    )]
+    pub user_settings: Option<Account<'info, UserSettings>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+/// Created on the owner's first write, paid by the owner
+#[derive(Accounts)]
+pub struct SetUserSettings<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = UserSettings::LEN,
+        seeds = [b"user_settings", owner.key().as_ref()],
+        bump
+    )]
+    pub user_settings: Account<'info, UserSettings>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Only the program's upgrade authority may initialize, so the deployer
+/// becomes admin rather than whoever gets there first
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = Config::LEN,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = LiquidatorWhitelist::LEN,
+        seeds = [b"liquidator_whitelist"],
+        bump
+    )]
+    pub liquidator_whitelist: Account<'info, LiquidatorWhitelist>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::PositionManagementSystem>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ PositionError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ PositionError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetUserFeeTier<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ PositionError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = UserFeeTier::LEN,
+        seeds = [b"fee_tier", owner.key().as_ref()],
+        bump
+    )]
+    pub fee_tier: Account<'info, UserFeeTier>,
+
+    /// CHECK: the wallet the tier is for, only its key is used
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(symbol: String)]
+pub struct InitializeMarket<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ PositionError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = Market::LEN,
+        seeds = [b"market", symbol.as_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarket<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ PositionError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"market", market.symbol.as_bytes()],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageLiquidatorWhitelist<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ PositionError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"liquidator_whitelist"],
+        bump = liquidator_whitelist.bump
+    )]
+    pub liquidator_whitelist: Account<'info, LiquidatorWhitelist>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct LiquidatePosition<'info> {
+    #[account(mut)]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        seeds = [b"user", position.owner.as_ref()],
+        bump = user_account.bump
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"liquidator_whitelist"],
+        bump = liquidator_whitelist.bump
+    )]
+    pub liquidator_whitelist: Account<'info, LiquidatorWhitelist>,
+
+    pub liquidator: Signer<'info>,
+
+    /// CHECK: the symbol's Market PDA, empty until the admin initializes it
+    #[account(
+        seeds = [b"market", position.symbol.as_bytes()],
+        bump
+    )]
+    pub market: UncheckedAccount<'info>,
+
+    /// CHECK: a Pyth price update, owner and feed checked by read_oracle_price
+    pub price_update: UncheckedAccount<'info>,
+}
+
+/// Full liquidations of several positions on one market, passed with their
+/// owners' user accounts as (position, user_account) pairs in remaining_accounts
+#[derive(Accounts)]
+#[instruction(symbol: String)]
+pub struct LiquidateMany<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"liquidator_whitelist"],
+        bump = liquidator_whitelist.bump
+    )]
+    pub liquidator_whitelist: Account<'info, LiquidatorWhitelist>,
+
+    pub liquidator: Signer<'info>,
+
+    /// CHECK: the symbol's Market PDA, empty until the admin initializes it
+    #[account(
+        seeds = [b"market", symbol.as_bytes()],
+        bump
+    )]
+    pub market: UncheckedAccount<'info>,
+
+    /// CHECK: a Pyth price update, owner and feed checked by read_oracle_price
+    pub price_update: UncheckedAccount<'info>,
+}
+
+/// Keepers issue and clear margin calls under the same rules as liquidation
+#[derive(Accounts)]
+pub struct UpdateMarginCall<'info> {
+    #[account(mut)]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"liquidator_whitelist"],
+        bump = liquidator_whitelist.bump
+    )]
+    pub liquidator_whitelist: Account<'info, LiquidatorWhitelist>,
+
+    pub liquidator: Signer<'info>,
+
+    /// CHECK: the symbol's Market PDA, empty until the admin initializes it
+    #[account(
+        seeds = [b"market", position.symbol.as_bytes()],
+        bump
+    )]
+    pub market: UncheckedAccount<'info>,
+
+    /// CHECK: a Pyth price update, owner and feed checked by read_oracle_price
+    pub price_update: UncheckedAccount<'info>,
+}
+
+/// The position may be too short to deserialize, `migrate_position` checks
+/// its discriminator itself
+#[derive(Accounts)]
+pub struct MigratePosition<'info> {
+    /// CHECK: a Position, possibly from before the current layout
+    #[account(mut, owner = crate::ID)]
+    pub position: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeBackstopVault<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ PositionError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = BackstopVault::LEN,
+        seeds = [b"backstop_vault"],
+        bump
+    )]
+    pub backstop_vault: Account<'info, BackstopVault>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"vault_shares"],
+        bump,
+        mint::decimals = SHARE_DECIMALS,
+        mint::authority = backstop_vault,
+        mint::freeze_authority = backstop_vault
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLpAccount<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = LpAccount::LEN,
+        seeds = [b"lp", owner.key().as_ref()],
+        bump
+    )]
+    pub lp_account: Account<'info, LpAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Deposit, withdraw and claim move collateral between the LP's user account
+/// and the vault, minting or burning share tokens in the LP's associated token
+/// account. The vault keeps that account frozen while it holds shares, so the
+/// shares can't leave it and the LP account's cost basis always covers them
+#[derive(Accounts)]
+pub struct ManageBackstopDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"backstop_vault"],
+        bump = backstop_vault.bump,
+        has_one = share_mint
+    )]
+    pub backstop_vault: Account<'info, BackstopVault>,
+
+    #[account(mut)]
+    pub share_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = share_mint,
+        associated_token::authority = owner
+    )]
+    pub lp_shares: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"lp", owner.key().as_ref()],
+        bump = lp_account.bump,
+        has_one = owner @ PositionError::Unauthorized
+    )]
+    pub lp_account: Account<'info, LpAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"user", owner.key().as_ref()],
+        bump = user_account.bump
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> ManageBackstopDeposit<'info> {
+    /// Mint shares to the LP, signed by the vault PDA, leaving the account frozen
+    pub fn mint_shares(&self, shares: u64) -> Result<()> {
+        let bump = [self.backstop_vault.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"backstop_vault", &bump]];
+
+        if self.lp_shares.is_frozen() {
+            self.thaw_shares()?;
+        }
+
+        token::mint_to(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                MintTo {
+                    mint: self.share_mint.to_account_info(),
+                    to: self.lp_shares.to_account_info(),
+                    authority: self.backstop_vault.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            shares,
+        )?;
+
+        self.freeze_shares()
+    }
+
+    /// Burn the LP's shares, signed by the LP. The account stays frozen while
+    /// shares are left in it
+    pub fn burn_shares(&self, shares: u64) -> Result<()> {
+        if self.lp_shares.is_frozen() {
+            self.thaw_shares()?;
+        }
+
+        token::burn(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                Burn {
+                    mint: self.share_mint.to_account_info(),
+                    from: self.lp_shares.to_account_info(),
+                    authority: self.owner.to_account_info(),
+                },
+            ),
+            shares,
+        )?;
+
+        if self.lp_shares.amount > shares {
+            self.freeze_shares()?;
+        }
+        Ok(())
+    }
+
+    fn freeze_shares(&self) -> Result<()> {
+        let bump = [self.backstop_vault.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"backstop_vault", &bump]];
+
+        token::freeze_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            FreezeAccount {
+                account: self.lp_shares.to_account_info(),
+                mint: self.share_mint.to_account_info(),
+                authority: self.backstop_vault.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    }
+
+    fn thaw_shares(&self) -> Result<()> {
+        let bump = [self.backstop_vault.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"backstop_vault", &bump]];
+
+        token::thaw_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            ThawAccount {
+                account: self.lp_shares.to_account_info(),
+                mint: self.share_mint.to_account_info(),
+                authority: self.backstop_vault.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    }
+}
+
+#[derive(Accounts)]
+pub struct BackstopLiquidation<'info> {
+    #[account(mut)]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        seeds = [b"user", position.owner.as_ref()],
+        bump = user_account.bump
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"liquidator_whitelist"],
+        bump = liquidator_whitelist.bump
+    )]
+    pub liquidator_whitelist: Account<'info, LiquidatorWhitelist>,
+
+    #[account(
+        mut,
+        seeds = [b"backstop_vault"],
+        bump = backstop_vault.bump
+    )]
+    pub backstop_vault: Account<'info, BackstopVault>,
+
+    #[account(
+        init,
+        payer = liquidator,
+        space = Position::MAX_SIZE,
+        seeds = [
+            b"position",
+            backstop_vault.key().as_ref(),
+            backstop_vault.position_count_total.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub vault_position: Account<'info, Position>,
+
+    #[account(mut)]
+    pub liquidator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: the symbol's Market PDA, empty until the admin initializes it
+    #[account(
+        seeds = [b"market", position.symbol.as_bytes()],
+        bump
+    )]
+    pub market: UncheckedAccount<'info>,
+
+    /// CHECK: a Pyth price update, owner and feed checked by read_oracle_price
+    pub price_update: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBackstopPosition<'info> {
+    #[account(
+        mut,
+        constraint = position.owner == backstop_vault.key() @ PositionError::NotBackstopPosition
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        seeds = [b"backstop_vault"],
+        bump = backstop_vault.bump
+    )]
+    pub backstop_vault: Account<'info, BackstopVault>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"liquidator_whitelist"],
+        bump = liquidator_whitelist.bump
+    )]
+    pub liquidator_whitelist: Account<'info, LiquidatorWhitelist>,
+
+    pub closer: Signer<'info>,
+
+    /// CHECK: a Pyth price update, owner and feed checked by read_oracle_price
+    pub price_update: UncheckedAccount<'info>,
+}
+
 // Events
+#[event]
+pub struct TradingPauseSet {
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct PositionOpened {
     pub position: Pubkey,
@@ -121,10 +767,147 @@ pub struct PositionModified {
     pub timestamp: i64,
 }
 
+/// Margin, leverage or liquidation price of a position changed. Old values are
+/// zero when the position was just created
+#[event]
+pub struct RiskParamsChanged {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub old_margin: u64,
+    pub new_margin: u64,
+    pub old_leverage: u16,
+    pub new_leverage: u16,
+    pub old_liquidation_price: u64,
+    pub new_liquidation_price: u64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct PositionClosed {
     pub position: Pubkey,
     pub owner: Pubkey,
     pub realized_pnl: i64,
     pub timestamp: i64,
-}
\ No newline at end of file
+}
+
+#[event]
+pub struct PositionLiquidated {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub liquidator: Pubkey,
+    pub mark_price: u64,
+    pub realized_pnl: i64,
+    pub closed_size: u64,
+    pub remaining_size: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarginCallIssued {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub margin_ratio_bps: u64,
+    pub deadline: i64,              // liquidation allowed from here on
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarginCallCleared {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub margin_ratio_bps: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidatorWhitelistUpdated {
+    pub liquidator: Pubkey,
+    pub added: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionAbsorbed {
+    pub position: Pubkey,
+    pub vault_position: Pubkey,
+    pub mark_price: u64,
+    pub takeover_price: u64,
+    pub size: u64,
+    pub margin: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BackstopDeposit {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BackstopWithdrawal {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeScheduleUpdated {
+    pub taker_fee_bps: [u16; FEE_TIER_COUNT],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserFeeTierUpdated {
+    pub owner: Pubkey,
+    pub old_tier: u8,
+    pub new_tier: u8,
+    pub volume_30d: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TakerFeeCharged {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub tier: u8,
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserAccountClosed {
+    pub owner: Pubkey,
+    /// Positions the owner ever opened, their accounts closed with it
+    pub positions_opened: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserSettingsUpdated {
+    pub owner: Pubkey,
+    pub default_slippage_bps: u16,
+    pub default_leverage: u16,
+    pub margin_mode: MarginMode,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketThrottleUpdated {
+    pub market: Pubkey,
+    pub symbol: String,
+    pub open_cooldown_slots: u64,
+    pub max_opens_per_slot: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MaintenanceMarginUpdated {
+    pub market: Pubkey,
+    pub symbol: String,
+    pub old_maintenance_margin_bps: u16,
+    pub new_maintenance_margin_bps: u16,
+    pub timestamp: i64,
+}
@@ -1,14 +1,17 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 
 pub mod constants;
 pub mod errors;
 pub mod instructions;
+pub mod oracle;
 pub mod state;
 pub mod utils;
 
 use constants::*;
 use errors::*;
 use instructions::*;
+use oracle::*;
 use state::*;
 use utils::*;
 
@@ -35,6 +38,7 @@ pub mod position_management_system {
 
     pub fn open_position(
         ctx: Context<OpenPosition>,
+        _position_index: u32,
         symbol: String,
         side: Side,
         size: u64,
@@ -43,7 +47,7 @@ pub mod position_management_system {
     ) -> Result<()> {
         require!(size > 0, PositionError::InvalidPositionSize);
         require!(
-            leverage >= 1 && leverage <= 1000,
+            (1..=1000).contains(&leverage),
             PositionError::InvalidLeverage
         );
         require!(
@@ -52,6 +56,16 @@ pub mod position_management_system {
         );
 
         validate_leverage_and_size(leverage, size, entry_price)?;
+
+        let clock = Clock::get()?;
+        let oracle = read_oracle_price(&ctx.accounts.price_update, &symbol)?;
+        require_fresh_price(&ctx.accounts.config, oracle.publish_time, clock.unix_timestamp)?;
+
+        let slot = clock.slot;
+        let open_throttle = &mut ctx.accounts.open_throttle;
+        throttle_open(&ctx.accounts.market, open_throttle.last_open_slot, slot)?;
+        open_throttle.last_open_slot = slot;
+        open_throttle.bump = ctx.bumps.open_throttle;
         
         let required_margin = calculate_initial_margin(size, entry_price, leverage)?;
 
@@ -63,6 +77,7 @@ pub mod position_management_system {
 
         let position_key = ctx.accounts.position.key();
         let user_key = ctx.accounts.user.key();
+        let fee = calculate_taker_fee(&ctx.accounts.config, ctx.accounts.fee_tier.as_deref(), size, entry_price)?;
 
         // Auto-increment position index
         let user_account = &mut ctx.accounts.user_account;
@@ -83,7 +98,7 @@ pub mod position_management_system {
             .ok_or(error!(PositionError::InsufficientCollateral))?;
 
         require!(
-            available_collateral >= required_margin,
+            available_collateral >= required_margin.saturating_add(fee),
             PositionError::InsufficientCollateral
         );
 
@@ -91,6 +106,11 @@ pub mod position_management_system {
             .locked_collateral
             .checked_add(required_margin)
             .ok_or(error!(PositionError::ArithmeticOverflow))?;
+        user_account.total_collateral = user_account
+            .total_collateral
+            .checked_sub(fee)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+        charge_taker_fee(ctx.accounts.fee_tier.as_deref_mut(), position_key, user_key, fee)?;
 
 
         msg!("Opening position for user: {} with position key: {}", user_key, position_key);
@@ -107,10 +127,12 @@ pub mod position_management_system {
         position.unrealized_pnl = 0;
         position.realized_pnl = 0;
         position.funding_accrued = 0;
+        position.funding_settled = 0;
         position.liquidation_price = liquidation_price;
         position.last_update = Clock::get()?.unix_timestamp;
         position.status = PositionStatus::Open;
         position.bump = ctx.bumps.position;
+        position.record_change(entry_price, slot);
 
         emit!(PositionOpened {
             position: position_key,
@@ -123,6 +145,7 @@ pub mod position_management_system {
             margin: required_margin,
             timestamp: position.last_update,
         });
+        emit_risk_params_changed(position_key, position, RiskParams::default(), position.last_update);
 
         msg!(
             "Position opened: {} with {}x leverage, by {} user",
@@ -143,13 +166,24 @@ pub mod position_management_system {
         let position = &mut ctx.accounts.position;
         let user_account = &mut ctx.accounts.user_account;
 
+        require!(position.is_active(), PositionError::PositionNotOpen);
+
+        // Shrinking or topping up only takes risk off
+        let reduce_only = new_size.is_none_or(|size| size <= position.size)
+            && margin_delta.is_none_or(|delta| delta >= 0);
         require!(
-            position.status == PositionStatus::Open,
-            PositionError::PositionNotOpen
+            !ctx.accounts.config.paused || reduce_only,
+            PositionError::TradingPaused
         );
 
+        let margin_called = position.status == PositionStatus::MarginCall;
+        if margin_called {
+            require!(reduce_only, PositionError::MarginCallActive);
+        }
+
         let old_size = position.size;
         let old_margin = position.margin;
+        let old_risk = position.risk_params();
 
         if let Some(size) = new_size {
             require!(size > 0, PositionError::InvalidPositionSize);
@@ -200,7 +234,7 @@ pub mod position_management_system {
                     .checked_div(SUPPORTED_ASSET_DECIMALS)
                     .ok_or(error!(PositionError::ArithmeticOverflow))?;
 
-                position.leverage = (position_value / position.margin).min(1000) as u16;
+                position.leverage = (position_value / position.margin).clamp(1, 1000) as u16;
             } else {
                 let remove_amount = (-delta) as u64;
 
@@ -223,8 +257,23 @@ pub mod position_management_system {
             }
         }
 
-        position.last_update = Clock::get()?.unix_timestamp;
-        position.status = PositionStatus::Open;
+        // New size, margin and leverage before the risk event reports it
+        position.liquidation_price = position_liquidation_price(
+            position.size,
+            position.entry_price,
+            position.leverage,
+            position.side,
+            market_maintenance_margin_floor(&ctx.accounts.market)?,
+        )?;
+
+        // A margin call stays until a keeper clears it, keeping its start time
+        let now = Clock::get()?.unix_timestamp;
+        if !margin_called {
+            position.last_update = now;
+            position.status = PositionStatus::Open;
+        }
+        let entry_price = position.entry_price;
+        position.record_change(entry_price, Clock::get()?.slot);
 
         emit!(PositionModified {
             position: position_key,
@@ -232,8 +281,9 @@ pub mod position_management_system {
             new_size: position.size,
             old_margin,
             new_margin: position.margin,
-            timestamp: position.last_update,
+            timestamp: now,
         });
+        emit_risk_params_changed(position_key, position, old_risk, now);
 
         msg!("Position modified");
 
@@ -243,24 +293,19 @@ pub mod position_management_system {
         let position_key = ctx.accounts.position.key();
         let owner_key = ctx.accounts.owner.key();
 
+        let oracle = read_oracle_price(&ctx.accounts.price_update, &ctx.accounts.position.symbol)?;
+        require_fresh_price(&ctx.accounts.config, oracle.publish_time, Clock::get()?.unix_timestamp)?;
+
         let position = &mut ctx.accounts.position;
         let user_account = &mut ctx.accounts.user_account;
 
-        require!(
-            position.status == PositionStatus::Open,
-            PositionError::PositionNotOpen
-        );
-
-        let final_pnl = calculate_unrealized_pnl(
-            position.size,
-            position.entry_price,
-            final_price,
-            position.side,
-        )?;
+        require!(position.is_active(), PositionError::PositionNotOpen);
 
-        let total_pnl = final_pnl
-            .checked_add(position.funding_accrued)
+        let fee = calculate_taker_fee(&ctx.accounts.config, ctx.accounts.fee_tier.as_deref(), position.size, final_price)?;
+        let total_pnl = close_pnl(position, final_price)?
+            .checked_sub(fee as i64)
             .ok_or(error!(PositionError::ArithmeticOverflow))?;
+        charge_taker_fee(ctx.accounts.fee_tier.as_deref_mut(), position_key, owner_key, fee)?;
 
         position.realized_pnl = total_pnl;
 
@@ -277,7 +322,7 @@ pub mod position_management_system {
         } else {
             let loss = (-total_pnl) as u64;
             user_account.total_collateral =
-                user_account.total_collateral.checked_sub(loss).unwrap_or(0);
+                user_account.total_collateral.saturating_sub(loss);
         }
 
         user_account.total_pnl = user_account
@@ -317,4 +362,1192 @@ pub mod position_management_system {
 
         Ok(())
     }
+
+    /// Take back collateral not locked as position margin
+    pub fn withdraw_collateral(ctx: Context<ModifyUserCollateral>, amount: u64) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+
+        let available_collateral = user_account
+            .total_collateral
+            .checked_sub(user_account.locked_collateral)
+            .ok_or(error!(PositionError::InsufficientCollateral))?;
+        require!(
+            amount > 0 && available_collateral >= amount,
+            PositionError::InsufficientCollateral
+        );
+
+        user_account.total_collateral -= amount;
+
+        msg!("Withdrew {} collateral", amount);
+
+        Ok(())
+    }
+
+    /// Offboard an owner with nothing open and no collateral left, returning
+    /// the rent of their user account, settings and settled positions.
+    /// remaining_accounts holds every Position the owner opened, in index
+    /// order, so registering again can reuse the indexes from 0
+    pub fn close_user_account<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseUserAccount<'info>>,
+    ) -> Result<()> {
+        let user_account = &ctx.accounts.user_account;
+
+        require!(user_account.position_count == 0, PositionError::UserHasOpenPositions);
+        require!(
+            user_account.total_collateral == 0 && user_account.locked_collateral == 0,
+            PositionError::UserHasCollateral
+        );
+
+        let positions = ctx.remaining_accounts;
+        require!(
+            positions.len() == user_account.position_count_total as usize,
+            PositionError::InvalidPositionAccounts
+        );
+        for (position_index, position) in (0u32..).zip(positions) {
+            let (expected_position, _) = Pubkey::find_program_address(
+                &[b"position", user_account.owner.as_ref(), &position_index.to_le_bytes()],
+                &crate::ID,
+            );
+            require_keys_eq!(position.key(), expected_position, PositionError::InvalidPositionAccounts);
+
+            // With no positions open every one is settled, whatever its layout
+            if position.owner == &crate::ID {
+                require!(position.is_writable, PositionError::InvalidPositionAccounts);
+                close_program_account(position, &ctx.accounts.owner.to_account_info())?;
+            }
+        }
+
+        emit!(UserAccountClosed {
+            owner: user_account.owner,
+            positions_opened: user_account.position_count_total,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("User account closed for: {}", user_account.owner);
+
+        Ok(())
+    }
+
+    /// Write the owner's defaults for new positions, creating the account on first use
+    pub fn set_user_settings(
+        ctx: Context<SetUserSettings>,
+        default_slippage_bps: u16,
+        default_leverage: u16,
+        margin_mode: MarginMode,
+    ) -> Result<()> {
+        require!(
+            default_slippage_bps <= MAX_DEFAULT_SLIPPAGE_BPS,
+            PositionError::InvalidSlippage
+        );
+        require!(
+            (1..=1000).contains(&default_leverage),
+            PositionError::InvalidLeverage
+        );
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let user_settings = &mut ctx.accounts.user_settings;
+        user_settings.owner = ctx.accounts.owner.key();
+        user_settings.default_slippage_bps = default_slippage_bps;
+        user_settings.default_leverage = default_leverage;
+        user_settings.margin_mode = margin_mode;
+        user_settings.updated_at = timestamp;
+        user_settings.bump = ctx.bumps.user_settings;
+
+        emit!(UserSettingsUpdated {
+            owner: user_settings.owner,
+            default_slippage_bps,
+            default_leverage,
+            margin_mode,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set the taker fee of each volume tier, tier 0 being every owner without
+    /// an assigned tier. All zeroes charges no fees
+    pub fn set_fee_schedule(
+        ctx: Context<UpdateConfig>,
+        taker_fee_bps: [u16; FEE_TIER_COUNT],
+    ) -> Result<()> {
+        validate_fee_schedule(&taker_fee_bps)?;
+
+        ctx.accounts.config.taker_fee_bps = taker_fee_bps;
+
+        emit!(FeeScheduleUpdated {
+            taker_fee_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Taker fee schedule set to {:?} bps", taker_fee_bps);
+
+        Ok(())
+    }
+
+    /// Assign an owner's fee tier from their 30-day taker volume, creating
+    /// the tier account on first use with the admin paying rent
+    pub fn set_user_fee_tier(ctx: Context<SetUserFeeTier>, tier: u8, volume_30d: u64) -> Result<()> {
+        require!((tier as usize) < FEE_TIER_COUNT, PositionError::InvalidFeeTier);
+
+        let fee_tier = &mut ctx.accounts.fee_tier;
+        let old_tier = fee_tier.tier;
+        fee_tier.owner = ctx.accounts.owner.key();
+        fee_tier.tier = tier;
+        fee_tier.volume_30d = volume_30d;
+        fee_tier.updated_at = Clock::get()?.unix_timestamp;
+        fee_tier.bump = ctx.bumps.fee_tier;
+
+        emit!(UserFeeTierUpdated {
+            owner: fee_tier.owner,
+            old_tier,
+            new_tier: tier,
+            volume_30d,
+            timestamp: fee_tier.updated_at,
+        });
+
+        Ok(())
+    }
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.liquidator_whitelist_enabled = false;
+        config.bump = ctx.bumps.config;
+        config.partial_liquidation_enabled = false;
+        config.liquidation_buffer_bps = DEFAULT_LIQUIDATION_BUFFER_BPS;
+        config.paused = false;
+        config.margin_call_buffer_bps = DEFAULT_MARGIN_CALL_BUFFER_BPS;
+        config.margin_call_grace_secs = 0;
+        config.taker_fee_bps = [0; FEE_TIER_COUNT];
+        config.max_price_age_secs = DEFAULT_MAX_PRICE_AGE_SECS;
+
+        let liquidator_whitelist = &mut ctx.accounts.liquidator_whitelist;
+        liquidator_whitelist.liquidators = Vec::new();
+        liquidator_whitelist.bump = ctx.bumps.liquidator_whitelist;
+
+        msg!("Config initialized with admin: {}", config.admin);
+
+        Ok(())
+    }
+
+    pub fn set_liquidator_whitelist_enabled(
+        ctx: Context<UpdateConfig>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.config.liquidator_whitelist_enabled = enabled;
+
+        msg!("Liquidator whitelist enabled: {}", enabled);
+
+        Ok(())
+    }
+
+    /// Kill switch: while paused positions can't be opened or grown, closes,
+    /// reductions, margin top-ups and liquidations still go through
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        ctx.accounts.config.paused = paused;
+
+        emit!(TradingPauseSet {
+            paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Trading paused: {}", paused);
+
+        Ok(())
+    }
+
+    /// Partial liquidations must leave the position at or above the tier's
+    /// maintenance rate plus `buffer_bps`
+    pub fn set_partial_liquidation(
+        ctx: Context<UpdateConfig>,
+        enabled: bool,
+        buffer_bps: u16,
+    ) -> Result<()> {
+        require!(
+            buffer_bps as u64 <= BPS_DENOMINATOR,
+            PositionError::InvalidLiquidationBuffer
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.partial_liquidation_enabled = enabled;
+        config.liquidation_buffer_bps = buffer_bps;
+
+        msg!("Partial liquidation enabled: {}, buffer: {} bps", enabled, buffer_bps);
+
+        Ok(())
+    }
+
+    /// Oldest Pyth publish time opens, closes and liquidations accept, in
+    /// seconds behind the cluster clock. The check can't be turned off
+    pub fn set_max_price_age(ctx: Context<UpdateConfig>, max_price_age_secs: u32) -> Result<()> {
+        require!(max_price_age_secs > 0, PositionError::InvalidMaxPriceAge);
+
+        ctx.accounts.config.max_price_age_secs = max_price_age_secs;
+
+        msg!("Max oracle price age set to {}s", max_price_age_secs);
+
+        Ok(())
+    }
+
+    /// Margin calls fire below the tier's maintenance rate plus `buffer_bps`,
+    /// liquidation then waits `grace_secs` for a top-up; 0 turns calls off
+    pub fn set_margin_call(
+        ctx: Context<UpdateConfig>,
+        buffer_bps: u16,
+        grace_secs: u32,
+    ) -> Result<()> {
+        require!(
+            buffer_bps as u64 <= BPS_DENOMINATOR,
+            PositionError::InvalidMarginCallBuffer
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.margin_call_buffer_bps = buffer_bps;
+        config.margin_call_grace_secs = grace_secs;
+
+        msg!("Margin call buffer: {} bps, grace: {}s", buffer_bps, grace_secs);
+
+        Ok(())
+    }
+
+    /// Create a symbol's Market with the bounds its maintenance rate is
+    /// cranked within, starting at the lower bound
+    pub fn initialize_market(
+        ctx: Context<InitializeMarket>,
+        symbol: String,
+        min_maintenance_margin_bps: u16,
+        max_maintenance_margin_bps: u16,
+    ) -> Result<()> {
+        require!(
+            symbol.len() <= MAX_SYMBOL_LENGTH,
+            PositionError::InvalidSymbol
+        );
+        validate_market_bounds(min_maintenance_margin_bps, max_maintenance_margin_bps)?;
+
+        let market = &mut ctx.accounts.market;
+        market.symbol = symbol;
+        market.maintenance_margin_bps = min_maintenance_margin_bps;
+        market.min_maintenance_margin_bps = min_maintenance_margin_bps;
+        market.max_maintenance_margin_bps = max_maintenance_margin_bps;
+        market.updated_at = Clock::get()?.unix_timestamp;
+        market.bump = ctx.bumps.market;
+        market.open_cooldown_slots = 0;
+        market.max_opens_per_slot = 0;
+        market.open_counter_slot = 0;
+        market.opens_in_slot = 0;
+        market.rate_window_start = 0;
+        market.rate_window_start_bps = min_maintenance_margin_bps;
+
+        msg!(
+            "Market {} initialized, maintenance {}-{} bps",
+            market.symbol,
+            min_maintenance_margin_bps,
+            max_maintenance_margin_bps
+        );
+
+        Ok(())
+    }
+
+    /// Move a market's bounds, the current rate is clamped into them
+    pub fn set_market_bounds(
+        ctx: Context<UpdateMarket>,
+        min_maintenance_margin_bps: u16,
+        max_maintenance_margin_bps: u16,
+    ) -> Result<()> {
+        validate_market_bounds(min_maintenance_margin_bps, max_maintenance_margin_bps)?;
+
+        let market_key = ctx.accounts.market.key();
+        let market = &mut ctx.accounts.market;
+        let old_rate = market.maintenance_margin_bps;
+        market.min_maintenance_margin_bps = min_maintenance_margin_bps;
+        market.max_maintenance_margin_bps = max_maintenance_margin_bps;
+        market.maintenance_margin_bps =
+            old_rate.clamp(min_maintenance_margin_bps, max_maintenance_margin_bps);
+
+        if market.maintenance_margin_bps != old_rate {
+            market.updated_at = Clock::get()?.unix_timestamp;
+
+            emit!(MaintenanceMarginUpdated {
+                market: market_key,
+                symbol: market.symbol.clone(),
+                old_maintenance_margin_bps: old_rate,
+                new_maintenance_margin_bps: market.maintenance_margin_bps,
+                timestamp: market.updated_at,
+            });
+        }
+
+        msg!(
+            "Market {} bounds: {}-{} bps",
+            market.symbol,
+            min_maintenance_margin_bps,
+            max_maintenance_margin_bps
+        );
+
+        Ok(())
+    }
+
+    /// Throttle opens on a market: slots each user waits between opens, and
+    /// opens allowed per slot across users. 0 disables either
+    pub fn set_market_throttle(
+        ctx: Context<UpdateMarket>,
+        open_cooldown_slots: u64,
+        max_opens_per_slot: u16,
+    ) -> Result<()> {
+        let market_key = ctx.accounts.market.key();
+        let market = &mut ctx.accounts.market;
+        market.open_cooldown_slots = open_cooldown_slots;
+        market.max_opens_per_slot = max_opens_per_slot;
+        market.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(MarketThrottleUpdated {
+            market: market_key,
+            symbol: market.symbol.clone(),
+            open_cooldown_slots,
+            max_opens_per_slot,
+            timestamp: market.updated_at,
+        });
+
+        msg!(
+            "Market {} throttle: {} slot cooldown, {} opens per slot",
+            market.symbol,
+            open_cooldown_slots,
+            max_opens_per_slot
+        );
+
+        Ok(())
+    }
+
+    /// Crank a market's maintenance rate, e.g. from realized volatility, a
+    /// capped step at a time
+    pub fn set_maintenance_margin(
+        ctx: Context<UpdateMarket>,
+        maintenance_margin_bps: u16,
+    ) -> Result<()> {
+        let market_key = ctx.accounts.market.key();
+        let market = &mut ctx.accounts.market;
+
+        let old_rate = market.maintenance_margin_bps;
+        apply_maintenance_margin_step(market, maintenance_margin_bps, Clock::get()?.unix_timestamp)?;
+
+        emit!(MaintenanceMarginUpdated {
+            market: market_key,
+            symbol: market.symbol.clone(),
+            old_maintenance_margin_bps: old_rate,
+            new_maintenance_margin_bps: maintenance_margin_bps,
+            timestamp: market.updated_at,
+        });
+
+        msg!(
+            "Market {} maintenance margin: {} -> {} bps",
+            market.symbol,
+            old_rate,
+            maintenance_margin_bps
+        );
+
+        Ok(())
+    }
+
+    pub fn add_liquidator(ctx: Context<ManageLiquidatorWhitelist>, liquidator: Pubkey) -> Result<()> {
+        let liquidator_whitelist = &mut ctx.accounts.liquidator_whitelist;
+
+        require!(
+            !liquidator_whitelist.liquidators.contains(&liquidator),
+            PositionError::LiquidatorAlreadyWhitelisted
+        );
+        require!(
+            liquidator_whitelist.liquidators.len() < MAX_WHITELISTED_LIQUIDATORS,
+            PositionError::LiquidatorWhitelistFull
+        );
+
+        liquidator_whitelist.liquidators.push(liquidator);
+
+        emit!(LiquidatorWhitelistUpdated {
+            liquidator,
+            added: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Liquidator whitelisted: {}", liquidator);
+
+        Ok(())
+    }
+
+    pub fn remove_liquidator(
+        ctx: Context<ManageLiquidatorWhitelist>,
+        liquidator: Pubkey,
+    ) -> Result<()> {
+        let liquidator_whitelist = &mut ctx.accounts.liquidator_whitelist;
+
+        let index = liquidator_whitelist
+            .liquidators
+            .iter()
+            .position(|key| *key == liquidator)
+            .ok_or(error!(PositionError::LiquidatorNotFound))?;
+        liquidator_whitelist.liquidators.swap_remove(index);
+
+        emit!(LiquidatorWhitelistUpdated {
+            liquidator,
+            added: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Liquidator removed from whitelist: {}", liquidator);
+
+        Ok(())
+    }
+
+    /// Liquidate `close_bps` of an underwater position at the oracle's price,
+    /// 10000 closes it entirely
+    /// A partial close realizes the closed share's PnL against the position's
+    /// margin, so the remaining size carries the same equity on less notional
+    pub fn liquidate_position(ctx: Context<LiquidatePosition>, close_bps: u16) -> Result<()> {
+        let position_key = ctx.accounts.position.key();
+        let liquidator_key = ctx.accounts.liquidator.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        require_liquidator(
+            &ctx.accounts.config,
+            &ctx.accounts.liquidator_whitelist,
+            &liquidator_key,
+        )?;
+
+        let position = &mut ctx.accounts.position;
+        let user_account = &mut ctx.accounts.user_account;
+
+        require!(position.is_active(), PositionError::PositionNotOpen);
+        let oracle = read_oracle_price(&ctx.accounts.price_update, &position.symbol)?;
+        require_fresh_price(&ctx.accounts.config, oracle.publish_time, now)?;
+        let mark_price = oracle.price;
+        require_margin_call_elapsed(&ctx.accounts.config, position, now)?;
+        require!(
+            close_bps > 0 && close_bps as u64 <= BPS_DENOMINATOR,
+            PositionError::InvalidCloseFraction
+        );
+
+        let unrealized_pnl = calculate_unrealized_pnl(
+            position.size,
+            position.entry_price,
+            mark_price,
+            position.side,
+        )?;
+
+        let position_value = calculate_position_value_for_tiers(position.size, position.entry_price)?;
+        let tier = get_leverage_tier(position.leverage, position_value)?;
+        let maintenance_rate =
+            effective_maintenance_margin_rate(tier.maintenance_margin_rate, &ctx.accounts.market)?;
+
+        require!(
+            check_liquidation(
+                position.margin,
+                unrealized_pnl,
+                position.size,
+                mark_price,
+                maintenance_rate,
+            )?,
+            PositionError::PositionNotLiquidatable
+        );
+
+        let close_size = calculate_liquidation_close_size(position.size, close_bps)?;
+
+        if close_size < position.size {
+            require!(
+                ctx.accounts.config.partial_liquidation_enabled,
+                PositionError::PartialLiquidationDisabled
+            );
+
+            let old_risk = position.risk_params();
+            let remaining_size = position.size - close_size;
+            let closed_funding = settle_funding(position, close_size)?;
+
+            // Losses are capped at the margin locked by the position
+            let closed_pnl = calculate_pro_rata(unrealized_pnl, close_size, position.size)?
+                .checked_add(closed_funding)
+                .ok_or(error!(PositionError::ArithmeticOverflow))?
+                .max(-(position.margin as i64));
+
+            let remaining_margin = if closed_pnl >= 0 {
+                user_account.total_collateral = user_account
+                    .total_collateral
+                    .checked_add(closed_pnl as u64)
+                    .ok_or(error!(PositionError::ArithmeticOverflow))?;
+                position.margin
+            } else {
+                let loss = (-closed_pnl) as u64;
+                user_account.locked_collateral = user_account
+                    .locked_collateral
+                    .checked_sub(loss)
+                    .ok_or(error!(PositionError::ArithmeticOverflow))?;
+                user_account.total_collateral = user_account.total_collateral.saturating_sub(loss);
+                position.margin - loss
+            };
+
+            let remaining_pnl = calculate_unrealized_pnl(
+                remaining_size,
+                position.entry_price,
+                mark_price,
+                position.side,
+            )?;
+            let target_ratio = maintenance_rate
+                .checked_add(ctx.accounts.config.liquidation_buffer_bps as u64)
+                .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+            require!(
+                remaining_margin > 0
+                    && calculate_signed_margin_ratio(remaining_margin, remaining_pnl, remaining_size, mark_price)?
+                        >= target_ratio as i64,
+                PositionError::PartialLiquidationInsufficient
+            );
+
+            user_account.total_pnl = user_account
+                .total_pnl
+                .checked_add(closed_pnl)
+                .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+            // Effective leverage of what's left, as when margin is added
+            let remaining_value =
+                calculate_position_value_for_tiers(remaining_size, position.entry_price)?;
+            position.leverage = (remaining_value / remaining_margin).clamp(1, 1000) as u16;
+            position.liquidation_price = calculate_liquidation_price(
+                position.entry_price,
+                position.leverage,
+                position.side,
+                maintenance_rate,
+            )?;
+
+            position.size = remaining_size;
+            position.margin = remaining_margin;
+            position.unrealized_pnl = remaining_pnl;
+            position.realized_pnl = position
+                .realized_pnl
+                .checked_add(closed_pnl)
+                .ok_or(error!(PositionError::ArithmeticOverflow))?;
+            // Back above maintenance plus buffer, a new call starts a new grace period
+            position.status = PositionStatus::Open;
+            position.last_update = Clock::get()?.unix_timestamp;
+            position.record_change(mark_price, Clock::get()?.slot);
+
+            emit!(PositionLiquidated {
+                position: position_key,
+                owner: position.owner,
+                liquidator: liquidator_key,
+                mark_price,
+                realized_pnl: closed_pnl,
+                closed_size: close_size,
+                remaining_size,
+                timestamp: position.last_update,
+            });
+            emit_risk_params_changed(position_key, position, old_risk, position.last_update);
+
+            msg!(
+                "Position {} partially liquidated by {} at {}, {} remaining",
+                position_key,
+                liquidator_key,
+                mark_price,
+                remaining_size
+            );
+
+            return Ok(());
+        }
+
+        let total_pnl = settle_liquidation(position, user_account, unrealized_pnl)?;
+
+        emit!(PositionLiquidated {
+            position: position_key,
+            owner: position.owner,
+            liquidator: liquidator_key,
+            mark_price,
+            realized_pnl: total_pnl,
+            closed_size: position.size,
+            remaining_size: 0,
+            timestamp: position.last_update,
+        });
+
+        msg!("Position {} liquidated by {} at {}", position_key, liquidator_key, mark_price);
+
+        Ok(())
+    }
+
+    /// Fully liquidate every position of a batch on `symbol` that is
+    /// liquidatable at the oracle price, for cascades where one transaction per
+    /// position is too slow. remaining_accounts holds (position, user_account)
+    /// pairs. Positions another liquidator got to first, or that recovered,
+    /// are skipped; the batch fails only when none could be liquidated
+    pub fn liquidate_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LiquidateMany<'info>>,
+        symbol: String,
+    ) -> Result<()> {
+        let liquidator_key = ctx.accounts.liquidator.key();
+        let config = &ctx.accounts.config;
+        let now = Clock::get()?.unix_timestamp;
+
+        require_liquidator(config, &ctx.accounts.liquidator_whitelist, &liquidator_key)?;
+        let oracle = read_oracle_price(&ctx.accounts.price_update, &symbol)?;
+        require_fresh_price(config, oracle.publish_time, now)?;
+        let mark_price = oracle.price;
+
+        let pairs = ctx.remaining_accounts;
+        require!(
+            !pairs.is_empty() && pairs.len() % 2 == 0 && pairs.len() / 2 <= MAX_BATCH_LIQUIDATIONS,
+            PositionError::InvalidLiquidationBatch
+        );
+
+        let market_floor = market_maintenance_margin_floor(&ctx.accounts.market)?;
+        let mut liquidated = 0;
+
+        for pair in pairs.chunks(2) {
+            require!(
+                pair[0].is_writable && pair[1].is_writable,
+                PositionError::InvalidLiquidationBatch
+            );
+
+            let mut position: Account<Position> = Account::try_from(&pair[0])?;
+            require!(position.symbol == symbol, PositionError::InvalidLiquidationBatch);
+
+            // Loaded after the previous pair was written back, so two
+            // positions of one owner both count against the same account
+            let mut user_account: Account<UserAccount> = Account::try_from(&pair[1])?;
+            let expected_user_account = Pubkey::create_program_address(
+                &[b"user", position.owner.as_ref(), &[user_account.bump]],
+                &crate::ID,
+            )
+            .map_err(|_| error!(PositionError::InvalidLiquidationBatch))?;
+            require_keys_eq!(
+                user_account.key(),
+                expected_user_account,
+                PositionError::InvalidLiquidationBatch
+            );
+
+            let position_key = position.key();
+            let Some(unrealized_pnl) = liquidatable_pnl(config, &position, mark_price, market_floor, now)? else {
+                msg!("Skipping position {}, not liquidatable", position_key);
+                continue;
+            };
+
+            let closed_size = position.size;
+            let total_pnl = settle_liquidation(&mut position, &mut user_account, unrealized_pnl)?;
+
+            emit!(PositionLiquidated {
+                position: position_key,
+                owner: position.owner,
+                liquidator: liquidator_key,
+                mark_price,
+                realized_pnl: total_pnl,
+                closed_size,
+                remaining_size: 0,
+                timestamp: position.last_update,
+            });
+
+            position.exit(&crate::ID)?;
+            user_account.exit(&crate::ID)?;
+            liquidated += 1;
+        }
+
+        require!(liquidated > 0, PositionError::PositionNotLiquidatable);
+
+        msg!(
+            "Liquidated {} of {} {} positions by {} at {}",
+            liquidated,
+            pairs.len() / 2,
+            symbol,
+            liquidator_key,
+            mark_price
+        );
+
+        Ok(())
+    }
+
+    /// Issue a margin call on a position whose margin ratio at the oracle
+    /// price is below maintenance plus the Config buffer, or clear one the
+    /// owner has topped up past it
+    pub fn update_margin_call(ctx: Context<UpdateMarginCall>) -> Result<()> {
+        let position_key = ctx.accounts.position.key();
+        let config = &ctx.accounts.config;
+        let now = Clock::get()?.unix_timestamp;
+
+        require_liquidator(
+            config,
+            &ctx.accounts.liquidator_whitelist,
+            &ctx.accounts.liquidator.key(),
+        )?;
+
+        let position = &mut ctx.accounts.position;
+
+        require!(position.is_active(), PositionError::PositionNotOpen);
+
+        let oracle = read_oracle_price(&ctx.accounts.price_update, &position.symbol)?;
+        require_fresh_price(config, oracle.publish_time, now)?;
+        let mark_price = oracle.price;
+
+        let unrealized_pnl = calculate_unrealized_pnl(
+            position.size,
+            position.entry_price,
+            mark_price,
+            position.side,
+        )?;
+
+        let position_value = calculate_position_value_for_tiers(position.size, position.entry_price)?;
+        let tier = get_leverage_tier(position.leverage, position_value)?;
+        let warning_ratio =
+            effective_maintenance_margin_rate(tier.maintenance_margin_rate, &ctx.accounts.market)?
+                .checked_add(config.margin_call_buffer_bps as u64)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+        let signed_margin_ratio =
+            calculate_signed_margin_ratio(position.margin, unrealized_pnl, position.size, mark_price)?;
+        // Events carry the ratio floored at 0
+        let margin_ratio = signed_margin_ratio.max(0) as u64;
+
+        match (position.status, signed_margin_ratio < warning_ratio as i64) {
+            (PositionStatus::Open, true) => {
+                // Calls can still be cleared after they are turned off
+                require!(config.margin_call_grace_secs > 0, PositionError::MarginCallDisabled);
+
+                position.status = PositionStatus::MarginCall;
+                position.last_update = now;
+
+                emit!(MarginCallIssued {
+                    position: position_key,
+                    owner: position.owner,
+                    margin_ratio_bps: margin_ratio,
+                    deadline: now.saturating_add(config.margin_call_grace_secs as i64),
+                    timestamp: now,
+                });
+
+                msg!("Margin call on {} at {} bps", position_key, margin_ratio);
+            }
+            (PositionStatus::MarginCall, false) => {
+                position.status = PositionStatus::Open;
+                position.last_update = now;
+
+                emit!(MarginCallCleared {
+                    position: position_key,
+                    owner: position.owner,
+                    margin_ratio_bps: margin_ratio,
+                    timestamp: now,
+                });
+
+                msg!("Margin call on {} cleared at {} bps", position_key, margin_ratio);
+            }
+            _ => return err!(PositionError::MarginCallUnchanged),
+        }
+
+        Ok(())
+    }
+
+    /// Grow a Position created before its latest fields to the current size,
+    /// the payer covering the extra rent. Anyone may pay, the new bytes read
+    /// as zeroes
+    pub fn migrate_position(ctx: Context<MigratePosition>) -> Result<()> {
+        let position = ctx.accounts.position.to_account_info();
+        let old_len = position.data_len();
+        require!(old_len < Position::MAX_SIZE, PositionError::PositionUpToDate);
+        require!(
+            position.try_borrow_data()?.starts_with(Position::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+
+        let rent_due = Rent::get()?
+            .minimum_balance(Position::MAX_SIZE)
+            .saturating_sub(position.lamports());
+        if rent_due > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: position.clone(),
+                    },
+                ),
+                rent_due,
+            )?;
+        }
+
+        position.resize(Position::MAX_SIZE)?;
+        // Fails for anything but a Position that reads back at the new size
+        Position::try_deserialize(&mut &position.try_borrow_data()?[..])?;
+
+        msg!("Position {} grown from {} to {} bytes", position.key(), old_len, Position::MAX_SIZE);
+
+        Ok(())
+    }
+
+    /// Liquidate a whole position into the backstop vault instead of the market
+    /// The owner is settled at the takeover price, `discount_bps` worse than
+    /// the oracle's mark, and the vault opens the same position at that price,
+    /// so the discount is the LPs' spread
+    pub fn backstop_liquidation(ctx: Context<BackstopLiquidation>) -> Result<()> {
+        let position_key = ctx.accounts.position.key();
+        let vault_position_key = ctx.accounts.vault_position.key();
+        let vault_key = ctx.accounts.backstop_vault.key();
+        let liquidator_key = ctx.accounts.liquidator.key();
+
+        require_liquidator(
+            &ctx.accounts.config,
+            &ctx.accounts.liquidator_whitelist,
+            &liquidator_key,
+        )?;
+
+        let position = &mut ctx.accounts.position;
+        let user_account = &mut ctx.accounts.user_account;
+        let backstop_vault = &mut ctx.accounts.backstop_vault;
+
+        require!(position.is_active(), PositionError::PositionNotOpen);
+        let now = Clock::get()?.unix_timestamp;
+        require_margin_call_elapsed(&ctx.accounts.config, position, now)?;
+
+        let oracle = read_oracle_price(&ctx.accounts.price_update, &position.symbol)?;
+        require_fresh_price(&ctx.accounts.config, oracle.publish_time, now)?;
+        let mark_price = oracle.price;
+
+        let unrealized_pnl = calculate_unrealized_pnl(
+            position.size,
+            position.entry_price,
+            mark_price,
+            position.side,
+        )?;
+
+        let position_value = calculate_position_value_for_tiers(position.size, position.entry_price)?;
+        let tier = get_leverage_tier(position.leverage, position_value)?;
+        let maintenance_rate =
+            effective_maintenance_margin_rate(tier.maintenance_margin_rate, &ctx.accounts.market)?;
+
+        require!(
+            check_liquidation(
+                position.margin,
+                unrealized_pnl,
+                position.size,
+                mark_price,
+                maintenance_rate,
+            )?,
+            PositionError::PositionNotLiquidatable
+        );
+
+        let takeover_price =
+            calculate_takeover_price(mark_price, position.side, backstop_vault.discount_bps)?;
+
+        let vault_leverage = position.leverage.min(MAX_BACKSTOP_LEVERAGE);
+        let vault_margin = calculate_initial_margin(position.size, takeover_price, vault_leverage)?;
+
+        require!(
+            backstop_vault.available_assets() >= vault_margin,
+            PositionError::BackstopInsufficientLiquidity
+        );
+
+        let takeover_pnl = calculate_unrealized_pnl(
+            position.size,
+            position.entry_price,
+            takeover_price,
+            position.side,
+        )?;
+        let total_pnl = settle_liquidation(position, user_account, takeover_pnl)?;
+
+        backstop_vault.locked_margin = backstop_vault
+            .locked_margin
+            .checked_add(vault_margin)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+        backstop_vault.position_count = backstop_vault
+            .position_count
+            .checked_add(1)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+        backstop_vault.position_count_total = backstop_vault
+            .position_count_total
+            .checked_add(1)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+        let vault_position = &mut ctx.accounts.vault_position;
+        vault_position.owner = vault_key;
+        vault_position.symbol = position.symbol.clone();
+        vault_position.side = position.side;
+        vault_position.size = position.size;
+        vault_position.entry_price = takeover_price;
+        vault_position.margin = vault_margin;
+        vault_position.leverage = vault_leverage;
+        vault_position.unrealized_pnl = 0;
+        vault_position.realized_pnl = 0;
+        vault_position.funding_accrued = 0;
+        vault_position.funding_settled = 0;
+        vault_position.liquidation_price = calculate_liquidation_price(
+            takeover_price,
+            vault_leverage,
+            position.side,
+            maintenance_rate,
+        )?;
+        vault_position.last_update = position.last_update;
+        vault_position.status = PositionStatus::Open;
+        vault_position.bump = ctx.bumps.vault_position;
+        vault_position.record_change(takeover_price, Clock::get()?.slot);
+
+        emit!(PositionLiquidated {
+            position: position_key,
+            owner: position.owner,
+            liquidator: liquidator_key,
+            mark_price: takeover_price,
+            realized_pnl: total_pnl,
+            closed_size: position.size,
+            remaining_size: 0,
+            timestamp: position.last_update,
+        });
+
+        emit_risk_params_changed(
+            vault_position_key,
+            vault_position,
+            RiskParams::default(),
+            vault_position.last_update,
+        );
+
+        emit!(PositionAbsorbed {
+            position: position_key,
+            vault_position: vault_position_key,
+            mark_price,
+            takeover_price,
+            size: vault_position.size,
+            margin: vault_margin,
+            timestamp: vault_position.last_update,
+        });
+
+        msg!(
+            "Position {} absorbed by backstop vault at {} (mark {})",
+            position_key,
+            takeover_price,
+            mark_price
+        );
+
+        Ok(())
+    }
+
+    /// Unwind a position the vault absorbed at the oracle price, PnL goes to the LPs
+    /// Only the admin or a whitelisted liquidator may close, whatever the whitelist flag
+    pub fn close_backstop_position(ctx: Context<CloseBackstopPosition>) -> Result<()> {
+        let position_key = ctx.accounts.position.key();
+        let closer_key = ctx.accounts.closer.key();
+
+        require!(
+            closer_key == ctx.accounts.config.admin
+                || ctx.accounts.liquidator_whitelist.liquidators.contains(&closer_key),
+            PositionError::Unauthorized
+        );
+
+        let position = &mut ctx.accounts.position;
+        let backstop_vault = &mut ctx.accounts.backstop_vault;
+
+        require!(position.is_active(), PositionError::PositionNotOpen);
+
+        let oracle = read_oracle_price(&ctx.accounts.price_update, &position.symbol)?;
+        require_fresh_price(&ctx.accounts.config, oracle.publish_time, Clock::get()?.unix_timestamp)?;
+        let final_price = oracle.price;
+
+        // Losses are capped at the margin the vault locked for the position
+        let total_pnl = close_pnl(position, final_price)?.max(-(position.margin as i64));
+
+        backstop_vault.locked_margin = backstop_vault
+            .locked_margin
+            .checked_sub(position.margin)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+        if total_pnl >= 0 {
+            backstop_vault.total_assets = backstop_vault
+                .total_assets
+                .checked_add(total_pnl as u64)
+                .ok_or(error!(PositionError::ArithmeticOverflow))?;
+        } else {
+            let loss = (-total_pnl) as u64;
+            backstop_vault.total_assets = backstop_vault.total_assets.saturating_sub(loss);
+        }
+
+        backstop_vault.realized_pnl = backstop_vault
+            .realized_pnl
+            .checked_add(total_pnl)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+        backstop_vault.position_count = backstop_vault
+            .position_count
+            .checked_sub(1)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+        position.unrealized_pnl = 0;
+        position.realized_pnl = total_pnl;
+        position.status = PositionStatus::Closed;
+        position.last_update = Clock::get()?.unix_timestamp;
+
+        emit!(PositionClosed {
+            position: position_key,
+            owner: position.owner,
+            realized_pnl: total_pnl,
+            timestamp: position.last_update,
+        });
+
+        msg!("Backstop position {} closed at {}", position_key, final_price);
+
+        Ok(())
+    }
+
+    pub fn initialize_backstop_vault(
+        ctx: Context<InitializeBackstopVault>,
+        discount_bps: u16,
+    ) -> Result<()> {
+        require!(
+            discount_bps as u64 <= BPS_DENOMINATOR,
+            PositionError::InvalidBackstopDiscount
+        );
+
+        let backstop_vault = &mut ctx.accounts.backstop_vault;
+        backstop_vault.total_assets = 0;
+        backstop_vault.total_shares = 0;
+        backstop_vault.locked_margin = 0;
+        backstop_vault.realized_pnl = 0;
+        backstop_vault.discount_bps = discount_bps;
+        backstop_vault.position_count = 0;
+        backstop_vault.position_count_total = 0;
+        backstop_vault.share_mint = ctx.accounts.share_mint.key();
+        backstop_vault.bump = ctx.bumps.backstop_vault;
+
+        msg!("Backstop vault initialized with {} bps discount", discount_bps);
+
+        Ok(())
+    }
+
+    pub fn initialize_lp_account(ctx: Context<InitializeLpAccount>) -> Result<()> {
+        let lp_account = &mut ctx.accounts.lp_account;
+        lp_account.owner = ctx.accounts.owner.key();
+        lp_account.cost_basis = 0;
+        lp_account.bump = ctx.bumps.lp_account;
+
+        msg!("LP account initialized for: {}", lp_account.owner);
+
+        Ok(())
+    }
+
+    /// Move free collateral into the vault for shares at the current share price
+    pub fn deposit_backstop(ctx: Context<ManageBackstopDeposit>, amount: u64) -> Result<()> {
+        let backstop_vault = &mut ctx.accounts.backstop_vault;
+        let lp_account = &mut ctx.accounts.lp_account;
+        let user_account = &mut ctx.accounts.user_account;
+
+        let available_collateral = user_account
+            .total_collateral
+            .checked_sub(user_account.locked_collateral)
+            .ok_or(error!(PositionError::InsufficientCollateral))?;
+
+        require!(
+            amount > 0 && available_collateral >= amount,
+            PositionError::InsufficientCollateral
+        );
+
+        let shares = calculate_shares_for_deposit(
+            amount,
+            backstop_vault.total_assets,
+            backstop_vault.total_shares,
+        )?;
+
+        user_account.total_collateral -= amount;
+
+        backstop_vault.total_assets = backstop_vault
+            .total_assets
+            .checked_add(amount)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+        backstop_vault.total_shares = backstop_vault
+            .total_shares
+            .checked_add(shares)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+        lp_account.cost_basis = lp_account
+            .cost_basis
+            .checked_add(amount)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+        let owner = lp_account.owner;
+        ctx.accounts.mint_shares(shares)?;
+
+        emit!(BackstopDeposit {
+            owner,
+            amount,
+            shares,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Deposited {} into backstop vault for {} shares", amount, shares);
+
+        Ok(())
+    }
+
+    /// Redeem `shares` for collateral, limited to assets not backing absorbed positions
+    pub fn withdraw_backstop(ctx: Context<ManageBackstopDeposit>, shares: u64) -> Result<()> {
+        let backstop_vault = &mut ctx.accounts.backstop_vault;
+        let lp_account = &mut ctx.accounts.lp_account;
+
+        let held = ctx.accounts.lp_shares.amount;
+
+        require!(
+            shares > 0 && held >= shares,
+            PositionError::InsufficientShares
+        );
+
+        let amount = calculate_share_value(
+            shares,
+            backstop_vault.total_assets,
+            backstop_vault.total_shares,
+        )?;
+        let cost = calculate_pro_rata(lp_account.cost_basis as i64, shares, held)? as u64;
+
+        lp_account.cost_basis -= cost;
+        let owner = lp_account.owner;
+
+        redeem_shares(backstop_vault, &mut ctx.accounts.user_account, shares, amount)?;
+        ctx.accounts.burn_shares(shares)?;
+
+        emit!(BackstopWithdrawal {
+            owner,
+            amount,
+            shares,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Withdrew {} from backstop vault for {} shares", amount, shares);
+
+        Ok(())
+    }
+
+    /// Take out earnings above the cost basis, keeping the deposited principal in the vault
+    pub fn claim_backstop(ctx: Context<ManageBackstopDeposit>) -> Result<()> {
+        let backstop_vault = &mut ctx.accounts.backstop_vault;
+        let lp_account = &mut ctx.accounts.lp_account;
+
+        let held = ctx.accounts.lp_shares.amount;
+        let value = calculate_share_value(
+            held,
+            backstop_vault.total_assets,
+            backstop_vault.total_shares,
+        )?;
+        let earnings = value.saturating_sub(lp_account.cost_basis);
+
+        // Shares worth the earnings, rounded down so principal stays put
+        let shares = calculate_shares_for_deposit(
+            earnings,
+            backstop_vault.total_assets,
+            backstop_vault.total_shares,
+        )?
+        .min(held);
+        let amount = calculate_share_value(
+            shares,
+            backstop_vault.total_assets,
+            backstop_vault.total_shares,
+        )?;
+
+        require!(amount > 0, PositionError::NothingToClaim);
+
+        let owner = lp_account.owner;
+
+        redeem_shares(backstop_vault, &mut ctx.accounts.user_account, shares, amount)?;
+        ctx.accounts.burn_shares(shares)?;
+
+        emit!(BackstopWithdrawal {
+            owner,
+            amount,
+            shares,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Claimed {} of backstop vault earnings", amount);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,177 @@
+use anchor_lang::prelude::*;
+use crate::constants::{PRICE_FEEDS, PRICE_PRECISION, PYTH_RECEIVER_PROGRAM_ID};
+use crate::errors::PositionError;
+
+/// Anchor discriminator of the receiver's PriceUpdateV2 account
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+/// Wormhole guardian signatures the receiver checked before posting
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+/// Pyth receiver PriceUpdateV2 after its discriminator, the account the push
+/// oracle keeps current for each sponsored feed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+/// A Pyth price in PRICE_PRECISION and the time Pyth published it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub price: u64,
+    pub publish_time: i64,
+}
+
+/// Pyth feed id of `symbol`
+pub fn price_feed_id(symbol: &str) -> Result<[u8; 32]> {
+    PRICE_FEEDS
+        .iter()
+        .find(|(feed_symbol, _)| *feed_symbol == symbol)
+        .map(|(_, feed_id)| *feed_id)
+        .ok_or(error!(PositionError::OracleFeedMismatch))
+}
+
+/// `symbol`'s price from a price update the Pyth receiver owns. Its age is
+/// left to `require_fresh_price`
+pub fn read_oracle_price(price_update: &AccountInfo, symbol: &str) -> Result<OraclePrice> {
+    require_keys_eq!(
+        *price_update.owner,
+        PYTH_RECEIVER_PROGRAM_ID,
+        PositionError::InvalidOracleAccount
+    );
+    parse_price_update(&price_update.try_borrow_data()?, symbol)
+}
+
+/// Decode a PriceUpdateV2, fully verified and for `symbol`'s feed
+pub fn parse_price_update(data: &[u8], symbol: &str) -> Result<OraclePrice> {
+    require!(
+        data.starts_with(&PRICE_UPDATE_V2_DISCRIMINATOR),
+        PositionError::InvalidOracleAccount
+    );
+    let update = PriceUpdateV2::deserialize(&mut &data[PRICE_UPDATE_V2_DISCRIMINATOR.len()..])
+        .map_err(|_| error!(PositionError::InvalidOracleAccount))?;
+
+    require!(
+        update.verification_level == VerificationLevel::Full,
+        PositionError::InvalidOracleAccount
+    );
+    require!(
+        update.price_message.feed_id == price_feed_id(symbol)?,
+        PositionError::OracleFeedMismatch
+    );
+
+    Ok(OraclePrice {
+        price: scale_price(update.price_message.price, update.price_message.exponent)?,
+        publish_time: update.price_message.publish_time,
+    })
+}
+
+/// A Pyth price of `price × 10^exponent` in PRICE_PRECISION, rounded down
+pub fn scale_price(price: i64, exponent: i32) -> Result<u64> {
+    require!(price > 0, PositionError::InvalidOraclePrice);
+
+    // PRICE_PRECISION is 10^6
+    let shift = exponent
+        .checked_add(PRICE_PRECISION.ilog10() as i32)
+        .ok_or(error!(PositionError::InvalidOraclePrice))?;
+    let factor = 10u128
+        .checked_pow(shift.unsigned_abs())
+        .ok_or(error!(PositionError::InvalidOraclePrice))?;
+    let scaled = if shift >= 0 {
+        (price as u128).checked_mul(factor)
+    } else {
+        (price as u128).checked_div(factor)
+    }
+    .ok_or(error!(PositionError::InvalidOraclePrice))?;
+
+    require!(scaled > 0, PositionError::InvalidOraclePrice);
+    u64::try_from(scaled).map_err(|_| error!(PositionError::InvalidOraclePrice))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRICE_UPDATE_V2_LEN: usize = 134;
+
+    fn price_update(feed_id: [u8; 32], verification_level: VerificationLevel) -> Vec<u8> {
+        let update = PriceUpdateV2 {
+            write_authority: Pubkey::default(),
+            verification_level,
+            price_message: PriceFeedMessage {
+                feed_id,
+                price: 15_012_345_678,
+                conf: 1_000_000,
+                exponent: -8,
+                publish_time: 1_700_000_000,
+                prev_publish_time: 1_699_999_999,
+                ema_price: 15_000_000_000,
+                ema_conf: 1_000_000,
+            },
+            posted_slot: 42,
+        };
+
+        // Sized by the receiver for a Partial level, a Full one leaves a byte spare
+        let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        update.serialize(&mut data).unwrap();
+        assert!(data.len() <= PRICE_UPDATE_V2_LEN);
+        data.resize(PRICE_UPDATE_V2_LEN, 0);
+        data
+    }
+
+    #[test]
+    fn test_parse_price_update() {
+        let sol = price_feed_id("SOL-USD").unwrap();
+        let data = price_update(sol, VerificationLevel::Full);
+
+        let oracle = parse_price_update(&data, "SOL-USD").unwrap();
+        assert_eq!(oracle, OraclePrice { price: 150_123_456, publish_time: 1_700_000_000 });
+
+        // Another market's feed, a partially verified update, not a price update
+        assert_eq!(
+            parse_price_update(&data, "BTC-USD").unwrap_err(),
+            error!(PositionError::OracleFeedMismatch)
+        );
+        assert!(parse_price_update(&data, "DOGE-USD").is_err());
+        assert_eq!(
+            parse_price_update(&price_update(sol, VerificationLevel::Partial { num_signatures: 5 }), "SOL-USD")
+                .unwrap_err(),
+            error!(PositionError::InvalidOracleAccount)
+        );
+        assert_eq!(
+            parse_price_update(&data[1..], "SOL-USD").unwrap_err(),
+            error!(PositionError::InvalidOracleAccount)
+        );
+    }
+
+    #[test]
+    fn test_scale_price() {
+        assert_eq!(scale_price(15_012_345_678, -8).unwrap(), 150_123_456);
+        assert_eq!(scale_price(150, 0).unwrap(), 150_000_000);
+        assert_eq!(scale_price(15, 1).unwrap(), 150_000_000);
+        assert!(scale_price(0, -8).is_err());
+        assert!(scale_price(-1, -8).is_err());
+        // Rounds to nothing, or past u64
+        assert!(scale_price(1, -8).is_err());
+        assert!(scale_price(i64::MAX, 10).is_err());
+    }
+}
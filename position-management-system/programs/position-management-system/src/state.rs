@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
+use crate::constants::{FEE_TIER_COUNT, MAX_SYMBOL_LENGTH, MAX_WHITELISTED_LIQUIDATORS, POSITION_HISTORY_LEN};
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, InitSpace)]
 pub enum Side {
     Long,
     Short,
@@ -15,18 +16,24 @@ impl Side {
     }
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, InitSpace)]
 pub enum PositionStatus {
     Opening,
     Open,
     Modifying,
     Closing,
     Closed,
+    Liquidated,
+    MarginCall,     // below the warning ratio, owner may only top up or reduce
 }
 
+/// Sized by InitSpace. New fields go at the end: accounts created before
+/// them are grown by `migrate_position` and read the new bytes as zeroes
 #[account]
+#[derive(InitSpace)]
 pub struct Position {
     pub owner: Pubkey,
+    #[max_len(MAX_SYMBOL_LENGTH)]
     pub symbol: String,
     pub side: Side,
     pub size: u64,
@@ -37,27 +44,71 @@ pub struct Position {
     pub realized_pnl: i64,
     pub funding_accrued: i64,
     pub liquidation_price: u64,
-    pub last_update: i64,
+    pub last_update: i64,           // while MarginCall, when the call was issued
     pub status: PositionStatus,
     pub bump: u8,
+    pub history: [PositionChange; POSITION_HISTORY_LEN], // ring buffer, next write at history_count % LEN
+    pub history_count: u32,         // changes ever recorded
+    pub funding_settled: i64,       // funding paid out so far, funding_accrued is only what isn't
+}
+
+/// A Position's size, margin and leverage after an open, modify or partial
+/// liquidation, with the price it was made at and its slot
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace)]
+pub struct PositionChange {
+    pub size: u64,
+    pub margin: u64,
+    pub leverage: u16,
+    pub price: u64,                 // entry price for opens and modifies, mark price for liquidations
+    pub slot: u64,
+}
+
+/// What `RiskParamsChanged` reports, taken before and after an instruction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RiskParams {
+    pub margin: u64,
+    pub leverage: u16,
+    pub liquidation_price: u64,
 }
 
 impl Position {
-    pub const MAX_SIZE: usize = 8 +      // discriminator
-        32 +       // owner
-        4 + 32 +   // symbol (String with max 32 chars)
-        1 +        // side
-        8 +        // size
-        8 +        // entry_price
-        8 +        // margin
-        2 +        // leverage (u16)
-        8 +        // unrealized_pnl
-        8 +        // realized_pnl
-        8 +        // funding_accrued
-        8 +        // liquidation_price
-        8 +        // last_update
-        1 +        // status
-        1;         // bump
+    pub const MAX_SIZE: usize = Position::DISCRIMINATOR.len() + Position::INIT_SPACE;
+
+    pub fn risk_params(&self) -> RiskParams {
+        RiskParams {
+            margin: self.margin,
+            leverage: self.leverage,
+            liquidation_price: self.liquidation_price,
+        }
+    }
+
+    /// Open, or open under a margin call
+    pub fn is_active(&self) -> bool {
+        matches!(self.status, PositionStatus::Open | PositionStatus::MarginCall)
+    }
+
+    /// Record the current size, margin and leverage, overwriting the oldest
+    /// change once the history is full
+    pub fn record_change(&mut self, price: u64, slot: u64) {
+        let index = self.history_count as usize % POSITION_HISTORY_LEN;
+        self.history[index] = PositionChange {
+            size: self.size,
+            margin: self.margin,
+            leverage: self.leverage,
+            price,
+            slot,
+        };
+        self.history_count = self.history_count.wrapping_add(1);
+    }
+
+    /// Recorded changes, oldest first
+    pub fn changes(&self) -> Vec<PositionChange> {
+        let count = (self.history_count as usize).min(POSITION_HISTORY_LEN);
+        let start = self.history_count as usize - count;
+        (start..start + count)
+            .map(|i| self.history[i % POSITION_HISTORY_LEN])
+            .collect()
+    }
 }
 
 #[account]
@@ -80,4 +131,253 @@ impl UserAccount {
         4 +    // position_count
         4 +    // position_count_total
         1;     // bump
-}
\ No newline at end of file
+}
+
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub liquidator_whitelist_enabled: bool, // restricts liquidate_position to whitelisted keys
+    pub bump: u8,
+    pub partial_liquidation_enabled: bool,  // lets liquidators close less than the full size
+    pub liquidation_buffer_bps: u16,        // margin ratio above maintenance a partial must restore
+    pub paused: bool,                       // blocks opening and modifying positions
+    pub margin_call_buffer_bps: u16,        // margin ratio above maintenance that triggers a margin call
+    pub margin_call_grace_secs: u32,        // wait after a margin call before liquidation, 0 disables calls
+    pub taker_fee_bps: [u16; FEE_TIER_COUNT], // taker fee per volume tier on opens and closes, non-increasing
+    pub max_price_age_secs: u32,            // oldest oracle publish time accepted on opens, closes and liquidations
+}
+
+impl Config {
+    pub const LEN: usize = 8 +
+        32 +   // admin
+        1 +    // liquidator_whitelist_enabled
+        1 +    // bump
+        1 +    // partial_liquidation_enabled
+        2 +    // liquidation_buffer_bps
+        1 +    // paused
+        2 +    // margin_call_buffer_bps
+        4 +    // margin_call_grace_secs
+        2 * FEE_TIER_COUNT + // taker_fee_bps
+        4;     // max_price_age_secs
+}
+
+#[account]
+pub struct LiquidatorWhitelist {
+    pub liquidators: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl LiquidatorWhitelist {
+    pub const LEN: usize = 8 +
+        4 + 32 * MAX_WHITELISTED_LIQUIDATORS + // liquidators (Vec with max entries)
+        1;     // bump
+}
+
+/// Backstop LP vault, absorbs liquidated positions at a discount to mark
+#[account]
+pub struct BackstopVault {
+    pub total_assets: u64,          // LP deposits plus realized PnL, 6 decimals
+    pub total_shares: u64,
+    pub locked_margin: u64,         // margin backing absorbed positions
+    pub realized_pnl: i64,
+    pub discount_bps: u16,          // takeover price discount to mark
+    pub position_count: u32,        // number of open absorbed positions
+    pub position_count_total: u32,  // index for next absorbed position
+    pub share_mint: Pubkey,         // SPL mint of LP shares, the vault is its authority
+    pub bump: u8,
+}
+
+impl BackstopVault {
+    pub const LEN: usize = 8 +
+        8 +    // total_assets
+        8 +    // total_shares
+        8 +    // locked_margin
+        8 +    // realized_pnl
+        2 +    // discount_bps
+        4 +    // position_count
+        4 +    // position_count_total
+        32 +   // share_mint
+        1;     // bump
+
+    /// Assets not backing absorbed positions, what LPs can withdraw
+    pub fn available_assets(&self) -> u64 {
+        self.total_assets.saturating_sub(self.locked_margin)
+    }
+}
+
+#[account]
+pub struct LpAccount {
+    pub owner: Pubkey,
+    pub cost_basis: u64,            // collateral deposited for the shares held
+    pub bump: u8,
+}
+
+impl LpAccount {
+    pub const LEN: usize = 8 +
+        32 +   // owner
+        8 +    // cost_basis
+        1;     // bump
+}
+
+/// How a user prefers new positions to be margined. Every position is
+/// isolated today, the preference is kept for clients
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum MarginMode {
+    Isolated,
+    Cross,
+}
+
+/// Defaults the backend fills into a user's open requests
+#[account]
+pub struct UserSettings {
+    pub owner: Pubkey,
+    pub default_slippage_bps: u16,
+    pub default_leverage: u16,
+    pub margin_mode: MarginMode,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl UserSettings {
+    pub const LEN: usize = 8 +
+        32 +   // owner
+        2 +    // default_slippage_bps
+        2 +    // default_leverage
+        1 +    // margin_mode
+        8 +    // updated_at
+        1;     // bump
+}
+
+/// An owner's volume tier, assigned by the admin from their rolling 30-day
+/// taker volume. Owners without one pay the base rate
+#[account]
+pub struct UserFeeTier {
+    pub owner: Pubkey,
+    pub tier: u8,
+    pub volume_30d: u64,       // taker volume the tier was assigned on, 6 decimals
+    pub fees_paid: u64,        // taker fees charged since the account was created
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl UserFeeTier {
+    pub const LEN: usize = 8 +
+        32 +   // owner
+        1 +    // tier
+        8 +    // volume_30d
+        8 +    // fees_paid
+        8 +    // updated_at
+        1;     // bump
+}
+
+/// A symbol's risk parameters, cranked by the admin as the market's regime
+/// changes. Maintenance checks use the higher of this rate and the tier's
+#[account]
+pub struct Market {
+    pub symbol: String,
+    pub maintenance_margin_bps: u16,
+    pub min_maintenance_margin_bps: u16,
+    pub max_maintenance_margin_bps: u16,
+    pub updated_at: i64,
+    pub bump: u8,
+    pub open_cooldown_slots: u64,   // slots a user waits between opens on this market, 0 disables
+    pub max_opens_per_slot: u16,    // opens on this market per slot across users, 0 disables
+    pub open_counter_slot: u64,     // slot `opens_in_slot` counts for
+    pub opens_in_slot: u16,
+    pub rate_window_start: i64,     // start of the window the crank's move is capped over
+    pub rate_window_start_bps: u16, // maintenance rate when the window started
+}
+
+impl Market {
+    pub const LEN: usize = 8 +
+        4 + 32 +   // symbol (String with max 32 chars)
+        2 +        // maintenance_margin_bps
+        2 +        // min_maintenance_margin_bps
+        2 +        // max_maintenance_margin_bps
+        8 +        // updated_at
+        1 +        // bump
+        8 +        // open_cooldown_slots
+        2 +        // max_opens_per_slot
+        8 +        // open_counter_slot
+        2 +        // opens_in_slot
+        8 +        // rate_window_start
+        2;         // rate_window_start_bps
+}
+
+/// A user's last open on one market, for the market's open cooldown
+#[account]
+pub struct OpenThrottle {
+    pub last_open_slot: u64,
+    pub bump: u8,
+}
+
+impl OpenThrottle {
+    pub const LEN: usize = 8 +
+        8 +    // last_open_slot
+        1;     // bump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position() -> Position {
+        Position {
+            owner: Pubkey::default(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size: 0,
+            entry_price: 100_000_000,
+            margin: 0,
+            leverage: 10,
+            unrealized_pnl: 0,
+            realized_pnl: 0,
+            funding_accrued: 0,
+            liquidation_price: 0,
+            last_update: 0,
+            status: PositionStatus::Open,
+            bump: 0,
+            history: [PositionChange::default(); POSITION_HISTORY_LEN],
+            history_count: 0,
+            funding_settled: 0,
+        }
+    }
+
+    #[test]
+    fn test_max_size_fits_longest_symbol() {
+        let mut position = position();
+        position.symbol = "A".repeat(MAX_SYMBOL_LENGTH);
+        position.history_count = u32::MAX;
+
+        let mut data = Vec::new();
+        position.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), Position::MAX_SIZE);
+
+        position.symbol.push('A');
+        let mut data = Vec::new();
+        position.try_serialize(&mut data).unwrap();
+        assert!(data.len() > Position::MAX_SIZE);
+    }
+
+    #[test]
+    fn test_record_change_ring_buffer() {
+        let mut position = position();
+        assert!(position.changes().is_empty());
+
+        for slot in 1..=3u64 {
+            position.size = slot;
+            position.record_change(position.entry_price, slot);
+        }
+        let slots: Vec<u64> = position.changes().iter().map(|change| change.slot).collect();
+        assert_eq!(slots, vec![1, 2, 3]);
+        assert_eq!(position.changes()[2].size, 3);
+
+        // Full: the oldest changes are overwritten, order stays oldest first
+        for slot in 4..=(POSITION_HISTORY_LEN as u64 + 2) {
+            position.record_change(position.entry_price, slot);
+        }
+        let slots: Vec<u64> = position.changes().iter().map(|change| change.slot).collect();
+        assert_eq!(slots, (3..=POSITION_HISTORY_LEN as u64 + 2).collect::<Vec<_>>());
+        assert_eq!(position.history_count, POSITION_HISTORY_LEN as u32 + 2);
+    }
+}
@@ -1,7 +1,21 @@
 use anchor_lang::prelude::*;
-use crate::constants::{PRICE_PRECISION, SUPPORTED_ASSET_DECIMALS, get_leverage_tier};
-use crate::state::Side;
+use crate::constants::{BPS_DENOMINATOR, DEFAULT_MAX_PRICE_AGE_SECS, FEE_TIER_COUNT, MAINTENANCE_MARGIN_WINDOW_SECS, MAX_MAINTENANCE_MARGIN_STEP_BPS, MAX_MAINTENANCE_MARGIN_WINDOW_BPS, MAX_MARKET_MAINTENANCE_MARGIN_BPS, MAX_TAKER_FEE_BPS, PRICE_PRECISION, SUPPORTED_ASSET_DECIMALS, get_leverage_tier};
+use crate::state::{BackstopVault, Config, LiquidatorWhitelist, Market, Position, PositionStatus, RiskParams, Side, UserAccount, UserFeeTier};
 use crate::errors::PositionError;
+use crate::instructions::{RiskParamsChanged, TakerFeeCharged};
+
+/// `a × b / denominator` with the intermediate product in u128, so sizes
+/// times 6-decimal prices can't overflow before the division. The only
+/// downcast is of the result
+pub fn mul_div(a: u64, b: u64, denominator: u64) -> Result<u64> {
+    let value = (a as u128)
+        .checked_mul(b as u128)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?
+        .checked_div(denominator as u128)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    u64::try_from(value).map_err(|_| error!(PositionError::ArithmeticOverflow))
+}
 
 /// Calculate Initial Margin
 /// Formula: Initial Margin = (Position Size × Entry Price) / Leverage
@@ -10,21 +24,16 @@ pub fn calculate_initial_margin(
     entry_price: u64,
     leverage: u16,
 ) -> Result<u64> {
-    // Step 1: Calculate position value in base units
-    let position_value_base = size
-        .checked_mul(entry_price)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_div(SUPPORTED_ASSET_DECIMALS)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    // Step 2: Divide by leverage
-    let margin = position_value_base
-        .checked_div(leverage as u64)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+    // Dividing once by decimals × leverage floors the same as dividing by each in turn
+    let margin = mul_div(
+        size,
+        entry_price,
+        SUPPORTED_ASSET_DECIMALS
+            .checked_mul(leverage as u64)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?,
+    )?;
 
     msg!("Calculated initial margin: {}", margin);
-
-    msg!("Position value (base units): {}", position_value_base);
     
     // margin has 6 decimals (matches collateral storage)
     Ok(margin)
@@ -36,13 +45,7 @@ pub fn calculate_position_value_for_tiers(
     entry_price: u64,
 ) -> Result<u64> {
     // Calculate as whole USDT for tier validation
-    let position_value = size
-        .checked_mul(entry_price)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_div(SUPPORTED_ASSET_DECIMALS)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    Ok(position_value)
+    mul_div(size, entry_price, SUPPORTED_ASSET_DECIMALS)
 }
 
 /// Calculate Average Entry Price
@@ -52,27 +55,27 @@ pub fn calculate_average_entry_price(
     additional_size: u64,
     new_entry_price: u64,
 ) -> Result<u64> {
-    let old_value = old_size
-        .checked_mul(old_entry_price)
+    let old_value = (old_size as u128)
+        .checked_mul(old_entry_price as u128)
         .ok_or(error!(PositionError::ArithmeticOverflow))?;
     
-    let new_value = additional_size
-        .checked_mul(new_entry_price)
+    let new_value = (additional_size as u128)
+        .checked_mul(new_entry_price as u128)
         .ok_or(error!(PositionError::ArithmeticOverflow))?;
     
     let total_value = old_value
         .checked_add(new_value)
         .ok_or(error!(PositionError::ArithmeticOverflow))?;
     
-    let total_size = old_size
-        .checked_add(additional_size)
+    let total_size = (old_size as u128)
+        .checked_add(additional_size as u128)
         .ok_or(error!(PositionError::ArithmeticOverflow))?;
     
     let average_price = total_value
         .checked_div(total_size)
         .ok_or(error!(PositionError::ArithmeticOverflow))?;
     
-    Ok(average_price)
+    u64::try_from(average_price).map_err(|_| error!(PositionError::ArithmeticOverflow))
 }
 
 /// Calculate Unrealized PnL
@@ -84,74 +87,82 @@ pub fn calculate_unrealized_pnl(
     mark_price: u64,
     side: Side,
 ) -> Result<i64> {
-    let price_diff = match side {
-        Side::Long => {
-            if mark_price >= entry_price {
-                ((mark_price - entry_price) as i64, 1)
-            } else {
-                ((entry_price - mark_price) as i64, -1)
-            }
-        },
-        Side::Short => {
-            if entry_price >= mark_price {
-                ((entry_price - mark_price) as i64, 1)
-            } else {
-                ((mark_price - entry_price) as i64, -1)
-            }
-        }
+    let profit = match side {
+        Side::Long => mark_price >= entry_price,
+        Side::Short => entry_price >= mark_price,
     };
     
-    // PnL = size × price_diff
-    let pnl = (size as i64)
-        .checked_mul(price_diff.0)
+    // |PnL| = size × |price_diff|, rounded toward zero either way
+    let magnitude = (size as u128)
+        .checked_mul(mark_price.abs_diff(entry_price) as u128)
         .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_mul(price_diff.1)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_div(SUPPORTED_ASSET_DECIMALS as i64)
+        .checked_div(SUPPORTED_ASSET_DECIMALS as u128)
         .ok_or(error!(PositionError::ArithmeticOverflow))?;
+    let magnitude = i64::try_from(magnitude).map_err(|_| error!(PositionError::ArithmeticOverflow))?;
     
     // pnl has 6 decimals
-    Ok(pnl)
+    Ok(if profit { magnitude } else { -magnitude })
 }
 
 /// Calculate Margin Ratio
 /// Formula: (collateral + unrealized_pnl) / position_value
-/// Returns ratio in basis points (10000 = 100%)
-pub fn calculate_margin_ratio(
+/// Returns ratio in basis points (10000 = 100%), negative once losses exceed
+/// the margin so underwater positions rank by how far under they are
+pub fn calculate_signed_margin_ratio(
     margin: u64,           // Base units (6 decimals)
     unrealized_pnl: i64,   // Base units (6 decimals)
     size: u64,
     mark_price: u64,
-) -> Result<u64> {
-    let position_value = size
-        .checked_mul(mark_price)
+) -> Result<i64> {
+    let position_value = (size as i128)
+        .checked_mul(mark_price as i128)
         .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_div(SUPPORTED_ASSET_DECIMALS)
+        .checked_div(SUPPORTED_ASSET_DECIMALS as i128)
         .ok_or(error!(PositionError::ArithmeticOverflow))?;
     // position_value has 6 decimals
     
     // Calculate: collateral + unrealized_pnl
-    let effective_margin = if unrealized_pnl >= 0 {
-        margin
-            .checked_add(unrealized_pnl as u64)
-            .ok_or(error!(PositionError::ArithmeticOverflow))?
-    } else {
-        let loss = (-unrealized_pnl) as u64;
-        if margin >= loss {
-            margin - loss
-        } else {
-            0
-        }
-    };
+    let effective_margin = (margin as i128) + unrealized_pnl as i128;
     
     // Margin Ratio = effective_margin / position_value (in basis points)
     let margin_ratio = effective_margin
-        .checked_mul(10000)
+        .checked_mul(BPS_DENOMINATOR as i128)
         .ok_or(error!(PositionError::ArithmeticOverflow))?
         .checked_div(position_value)
         .ok_or(error!(PositionError::ArithmeticOverflow))?;
     
-    Ok(margin_ratio)
+    i64::try_from(margin_ratio).map_err(|_| error!(PositionError::ArithmeticOverflow))
+}
+
+/// Margin ratio floored at 0, for events and logs only. Checks use
+/// `calculate_signed_margin_ratio`
+pub fn calculate_margin_ratio(
+    margin: u64,
+    unrealized_pnl: i64,
+    size: u64,
+    mark_price: u64,
+) -> Result<u64> {
+    Ok(calculate_signed_margin_ratio(margin, unrealized_pnl, size, mark_price)?.max(0) as u64)
+}
+
+/// Price factor (PRICE_PRECISION = 1) a liquidation price is the entry price times
+/// Long: 1 - 1/leverage + mm, Short: 1 + 1/leverage - mm
+fn liquidation_price_factor(leverage: u16, side: Side, maintenance_margin_rate: u64) -> Result<u64> {
+    let mm_factor = mul_div(maintenance_margin_rate, PRICE_PRECISION, BPS_DENOMINATOR)?;
+    
+    let leverage_factor = PRICE_PRECISION
+        .checked_div(leverage as u64)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+    
+    match side {
+        Side::Long => PRICE_PRECISION
+            .checked_sub(leverage_factor)
+            .and_then(|factor| factor.checked_add(mm_factor)),
+        Side::Short => PRICE_PRECISION
+            .checked_add(leverage_factor)
+            .and_then(|factor| factor.checked_sub(mm_factor)),
+    }
+    .ok_or(error!(PositionError::ArithmeticOverflow))
 }
 
 /// Calculate Liquidation Price (Long)
@@ -160,31 +171,8 @@ pub fn calculate_liquidation_price_long(
     leverage: u16,
     maintenance_margin_rate: u64,
 ) -> Result<u64> {
-    let leverage_u64 = leverage as u64;
-    
-    let mm_factor = maintenance_margin_rate
-        .checked_mul(PRICE_PRECISION)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_div(10000)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    let leverage_factor = PRICE_PRECISION
-        .checked_div(leverage_u64)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    let adjustment = PRICE_PRECISION
-        .checked_sub(leverage_factor)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_add(mm_factor)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    let liq_price = entry_price
-        .checked_mul(adjustment)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_div(PRICE_PRECISION)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    Ok(liq_price)
+    let adjustment = liquidation_price_factor(leverage, Side::Long, maintenance_margin_rate)?;
+    mul_div(entry_price, adjustment, PRICE_PRECISION)
 }
 
 /// Calculate Liquidation Price (Short)
@@ -193,31 +181,8 @@ pub fn calculate_liquidation_price_short(
     leverage: u16,
     maintenance_margin_rate: u64,
 ) -> Result<u64> {
-    let leverage_u64 = leverage as u64;
-    
-    let mm_factor = maintenance_margin_rate
-        .checked_mul(PRICE_PRECISION)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_div(10000)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    let leverage_factor = PRICE_PRECISION
-        .checked_div(leverage_u64)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    let adjustment = PRICE_PRECISION
-        .checked_add(leverage_factor)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_sub(mm_factor)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    let liq_price = entry_price
-        .checked_mul(adjustment)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?
-        .checked_div(PRICE_PRECISION)
-        .ok_or(error!(PositionError::ArithmeticOverflow))?;
-    
-    Ok(liq_price)
+    let adjustment = liquidation_price_factor(leverage, Side::Short, maintenance_margin_rate)?;
+    mul_div(entry_price, adjustment, PRICE_PRECISION)
 }
 
 /// Unified liquidation price calculation
@@ -233,6 +198,20 @@ pub fn calculate_liquidation_price(
     }
 }
 
+/// Liquidation price after a size or margin change, at the maintenance rate
+/// of the tier the new size and leverage fall in, raised to the Market's floor
+pub fn position_liquidation_price(
+    size: u64,
+    entry_price: u64,
+    leverage: u16,
+    side: Side,
+    market_floor: u64,
+) -> Result<u64> {
+    let position_value = calculate_position_value_for_tiers(size, entry_price)?;
+    let tier = get_leverage_tier(leverage, position_value)?;
+    calculate_liquidation_price(entry_price, leverage, side, tier.maintenance_margin_rate.max(market_floor))
+}
+
 /// Check if position should be liquidated
 pub fn check_liquidation(
     margin: u64,
@@ -241,8 +220,391 @@ pub fn check_liquidation(
     mark_price: u64,
     maintenance_margin_rate: u64,
 ) -> Result<bool> {
-    let margin_ratio = calculate_margin_ratio(margin, unrealized_pnl, size, mark_price)?;
-    Ok(margin_ratio < maintenance_margin_rate)
+    let margin_ratio = calculate_signed_margin_ratio(margin, unrealized_pnl, size, mark_price)?;
+    Ok((margin_ratio as i128) < maintenance_margin_rate as i128)
+}
+
+/// Size closed by a liquidation of `close_bps` of the position
+/// Rounds up so the margin ratio ends no lower than the liquidator computed
+pub fn calculate_liquidation_close_size(size: u64, close_bps: u16) -> Result<u64> {
+    let close_size = (size as u128)
+        .checked_mul(close_bps as u128)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?
+        .div_ceil(BPS_DENOMINATOR as u128);
+
+    Ok(close_size.min(size as u128) as u64)
+}
+
+/// Share of `value` belonging to `part` out of `whole`, rounded toward zero
+pub fn calculate_pro_rata(value: i64, part: u64, whole: u64) -> Result<i64> {
+    let share = (value as i128)
+        .checked_mul(part as i128)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?
+        .checked_div(whole as i128)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    i64::try_from(share).map_err(|_| error!(PositionError::ArithmeticOverflow))
+}
+
+/// Take the share of unsettled funding that `close_size` of the position
+/// carries, moving it from `funding_accrued` to `funding_settled`, for the
+/// caller to pay out. Closes, liquidations and funding settlement all go
+/// through here, so funding is paid once whichever runs first
+pub fn settle_funding(position: &mut Position, close_size: u64) -> Result<i64> {
+    let funding = if close_size >= position.size {
+        position.funding_accrued
+    } else {
+        calculate_pro_rata(position.funding_accrued, close_size, position.size)?
+    };
+
+    position.funding_accrued = position
+        .funding_accrued
+        .checked_sub(funding)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+    position.funding_settled = position
+        .funding_settled
+        .checked_add(funding)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    Ok(funding)
+}
+
+/// PnL of closing the whole position at `final_price`: price PnL plus the
+/// funding not yet settled, which is settled
+pub fn close_pnl(position: &mut Position, final_price: u64) -> Result<i64> {
+    let price_pnl = calculate_unrealized_pnl(
+        position.size,
+        position.entry_price,
+        final_price,
+        position.side,
+    )?;
+
+    price_pnl
+        .checked_add(settle_funding(position, position.size)?)
+        .ok_or(error!(PositionError::ArithmeticOverflow))
+}
+
+/// Shares minted for a vault deposit, 1:1 into an empty vault
+pub fn calculate_shares_for_deposit(amount: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+    if total_shares == 0 || total_assets == 0 {
+        return Ok(amount);
+    }
+
+    let shares = (amount as u128)
+        .checked_mul(total_shares as u128)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?
+        .checked_div(total_assets as u128)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    u64::try_from(shares).map_err(|_| error!(PositionError::ArithmeticOverflow))
+}
+
+/// Collateral redeemable for `shares`, rounded down
+pub fn calculate_share_value(shares: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+    if total_shares == 0 {
+        return Ok(0);
+    }
+
+    let value = (shares as u128)
+        .checked_mul(total_assets as u128)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?
+        .checked_div(total_shares as u128)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    u64::try_from(value).map_err(|_| error!(PositionError::ArithmeticOverflow))
+}
+
+/// Burn `shares` from the vault and pay `amount` into the LP's user account
+pub fn redeem_shares(
+    backstop_vault: &mut BackstopVault,
+    user_account: &mut UserAccount,
+    shares: u64,
+    amount: u64,
+) -> Result<()> {
+    require!(
+        backstop_vault.available_assets() >= amount,
+        PositionError::BackstopInsufficientLiquidity
+    );
+
+    backstop_vault.total_assets -= amount;
+    backstop_vault.total_shares = backstop_vault
+        .total_shares
+        .checked_sub(shares)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    user_account.total_collateral = user_account
+        .total_collateral
+        .checked_add(amount)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    Ok(())
+}
+
+/// Price the backstop vault takes a liquidated position over at
+/// Long: mark × (1 - discount), Short: mark × (1 + discount)
+pub fn calculate_takeover_price(mark_price: u64, side: Side, discount_bps: u16) -> Result<u64> {
+    let factor = match side {
+        Side::Long => BPS_DENOMINATOR - discount_bps as u64,
+        Side::Short => BPS_DENOMINATOR + discount_bps as u64,
+    };
+
+    mul_div(mark_price, factor, BPS_DENOMINATOR)
+}
+
+/// Permissioned mode: only vetted liquidators may liquidate
+pub fn require_liquidator(
+    config: &Config,
+    liquidator_whitelist: &LiquidatorWhitelist,
+    liquidator: &Pubkey,
+) -> Result<()> {
+    if config.liquidator_whitelist_enabled {
+        require!(
+            liquidator_whitelist.liquidators.contains(liquidator),
+            PositionError::LiquidatorNotWhitelisted
+        );
+    }
+    Ok(())
+}
+
+/// Maintenance rate the checks use: the tier's, raised to the symbol's
+/// Market rate once the admin has initialized its Market account
+pub fn effective_maintenance_margin_rate(tier_rate: u64, market: &AccountInfo) -> Result<u64> {
+    Ok(tier_rate.max(market_maintenance_margin_floor(market)?))
+}
+
+/// Maintenance rate the symbol's Market sets as a floor, 0 until it is initialized
+pub fn market_maintenance_margin_floor(market: &AccountInfo) -> Result<u64> {
+    if market.data_is_empty() {
+        return Ok(0);
+    }
+
+    require_keys_eq!(*market.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+    let market = Market::try_deserialize(&mut &market.try_borrow_data()?[..])?;
+    Ok(market.maintenance_margin_bps as u64)
+}
+
+/// Crank the market's maintenance rate, at most MAX_MAINTENANCE_MARGIN_STEP_BPS
+/// from the current rate and MAX_MAINTENANCE_MARGIN_WINDOW_BPS from the rate
+/// the window started at, so healthy positions are not made liquidatable at
+/// once. A window starts with the first crank after the last one ended
+pub fn apply_maintenance_margin_step(market: &mut Market, maintenance_margin_bps: u16, now: i64) -> Result<()> {
+    require!(
+        (market.min_maintenance_margin_bps..=market.max_maintenance_margin_bps)
+            .contains(&maintenance_margin_bps),
+        PositionError::MaintenanceMarginOutOfBounds
+    );
+
+    if now >= market.rate_window_start.saturating_add(MAINTENANCE_MARGIN_WINDOW_SECS) {
+        market.rate_window_start = now;
+        market.rate_window_start_bps = market.maintenance_margin_bps;
+    }
+    require!(
+        maintenance_margin_bps.abs_diff(market.maintenance_margin_bps) <= MAX_MAINTENANCE_MARGIN_STEP_BPS
+            && maintenance_margin_bps.abs_diff(market.rate_window_start_bps)
+                <= MAX_MAINTENANCE_MARGIN_WINDOW_BPS,
+        PositionError::MaintenanceMarginStepTooLarge
+    );
+
+    market.maintenance_margin_bps = maintenance_margin_bps;
+    market.updated_at = now;
+    Ok(())
+}
+
+/// Check a user's open against the market's cooldown and per-slot limit,
+/// counting it when allowed. `last_open_slot` is 0 for a user's first open
+pub fn apply_open_throttle(market: &mut Market, last_open_slot: u64, slot: u64) -> Result<()> {
+    if market.open_cooldown_slots > 0 && last_open_slot > 0 {
+        require!(
+            slot >= last_open_slot.saturating_add(market.open_cooldown_slots),
+            PositionError::OpenCooldownActive
+        );
+    }
+
+    if market.open_counter_slot != slot {
+        market.open_counter_slot = slot;
+        market.opens_in_slot = 0;
+    }
+    if market.max_opens_per_slot > 0 {
+        require!(
+            market.opens_in_slot < market.max_opens_per_slot,
+            PositionError::MarketOpenLimitReached
+        );
+    }
+    market.opens_in_slot = market.opens_in_slot.saturating_add(1);
+
+    Ok(())
+}
+
+/// `apply_open_throttle` on a market that may not be initialized yet, in
+/// which case opens aren't throttled
+pub fn throttle_open(market: &AccountInfo, last_open_slot: u64, slot: u64) -> Result<()> {
+    if market.data_is_empty() {
+        return Ok(());
+    }
+
+    require_keys_eq!(*market.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+    let mut data = market.try_borrow_mut_data()?;
+    let mut state = Market::try_deserialize(&mut &data[..])?;
+    apply_open_throttle(&mut state, last_open_slot, slot)?;
+    state.try_serialize(&mut &mut data[..])?;
+    Ok(())
+}
+
+/// Bounds a market's maintenance rate is cranked within
+pub fn validate_market_bounds(min_bps: u16, max_bps: u16) -> Result<()> {
+    require!(
+        min_bps <= max_bps && max_bps <= MAX_MARKET_MAINTENANCE_MARGIN_BPS,
+        PositionError::InvalidMarketBounds
+    );
+    Ok(())
+}
+
+/// Rates capped at MAX_TAKER_FEE_BPS, each tier no dearer than the one below
+pub fn validate_fee_schedule(taker_fee_bps: &[u16; FEE_TIER_COUNT]) -> Result<()> {
+    require!(
+        taker_fee_bps.iter().all(|bps| *bps <= MAX_TAKER_FEE_BPS)
+            && taker_fee_bps.windows(2).all(|pair| pair[1] <= pair[0]),
+        PositionError::InvalidFeeSchedule
+    );
+    Ok(())
+}
+
+/// Taker fee on a fill of `size` at `price`, at the rate of the owner's tier
+pub fn calculate_taker_fee(
+    config: &Config,
+    fee_tier: Option<&UserFeeTier>,
+    size: u64,
+    price: u64,
+) -> Result<u64> {
+    let tier = fee_tier.map_or(0, |fee_tier| fee_tier.tier as usize).min(FEE_TIER_COUNT - 1);
+    let notional = calculate_position_value_for_tiers(size, price)?;
+
+    mul_div(notional, config.taker_fee_bps[tier] as u64, BPS_DENOMINATOR)
+}
+
+/// Close an account the program owns without deserializing it, its rent
+/// going to `destination`
+pub fn close_program_account<'info>(account: &AccountInfo<'info>, destination: &AccountInfo<'info>) -> Result<()> {
+    let lamports = account.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+    **account.try_borrow_mut_lamports()? = 0;
+
+    account.assign(&system_program::ID);
+    account.resize(0)?;
+    Ok(())
+}
+
+/// With margin calls enabled, liquidation waits for a margin call to run its grace period
+pub fn require_margin_call_elapsed(config: &Config, position: &Position, now: i64) -> Result<()> {
+    if config.margin_call_grace_secs == 0 {
+        return Ok(());
+    }
+
+    require!(
+        position.status == PositionStatus::MarginCall
+            && now >= position.last_update.saturating_add(config.margin_call_grace_secs as i64),
+        PositionError::MarginCallGracePeriod
+    );
+    Ok(())
+}
+
+/// Unrealized PnL of a position a full liquidation at `mark_price` may take,
+/// none when it isn't open, is above maintenance or its margin call is still
+/// in its grace period. `market_floor` is the Market's maintenance rate
+pub fn liquidatable_pnl(
+    config: &Config,
+    position: &Position,
+    mark_price: u64,
+    market_floor: u64,
+    now: i64,
+) -> Result<Option<i64>> {
+    if !position.is_active() || require_margin_call_elapsed(config, position, now).is_err() {
+        return Ok(None);
+    }
+
+    let unrealized_pnl = calculate_unrealized_pnl(
+        position.size,
+        position.entry_price,
+        mark_price,
+        position.side,
+    )?;
+    let position_value = calculate_position_value_for_tiers(position.size, position.entry_price)?;
+    let maintenance_rate = get_leverage_tier(position.leverage, position_value)?
+        .maintenance_margin_rate
+        .max(market_floor);
+
+    Ok(check_liquidation(position.margin, unrealized_pnl, position.size, mark_price, maintenance_rate)?
+        .then_some(unrealized_pnl))
+}
+
+/// Prices must come from an oracle update published within the configured age,
+/// the default age for a Config that never had one set
+/// Updates stamped ahead of the cluster clock are accepted, validators lag Pyth
+pub fn require_fresh_price(config: &Config, price_publish_time: i64, now: i64) -> Result<()> {
+    let max_age = match config.max_price_age_secs {
+        0 => DEFAULT_MAX_PRICE_AGE_SECS,
+        max_age => max_age,
+    };
+
+    require!(
+        now.saturating_sub(price_publish_time) <= max_age as i64,
+        PositionError::StaleOracle
+    );
+    Ok(())
+}
+
+/// Settle a whole position as liquidated against its owner's account
+/// Losses are capped at the margin locked by the position, returns the PnL realized
+pub fn settle_liquidation(
+    position: &mut Position,
+    user_account: &mut UserAccount,
+    unrealized_pnl: i64,
+) -> Result<i64> {
+    let funding = settle_funding(position, position.size)?;
+    let total_pnl = unrealized_pnl
+        .checked_add(funding)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?
+        .max(-(position.margin as i64));
+
+    position.unrealized_pnl = 0;
+    // Adds to whatever earlier partial liquidations realized
+    position.realized_pnl = position
+        .realized_pnl
+        .checked_add(total_pnl)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    user_account.locked_collateral = user_account
+        .locked_collateral
+        .checked_sub(position.margin)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    if total_pnl >= 0 {
+        user_account.total_collateral = user_account
+            .total_collateral
+            .checked_add(total_pnl as u64)
+            .ok_or(error!(PositionError::ArithmeticOverflow))?;
+    } else {
+        let loss = (-total_pnl) as u64;
+        user_account.total_collateral = user_account.total_collateral.saturating_sub(loss);
+    }
+
+    user_account.total_pnl = user_account
+        .total_pnl
+        .checked_add(total_pnl)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    user_account.position_count = user_account
+        .position_count
+        .checked_sub(1)
+        .ok_or(error!(PositionError::ArithmeticOverflow))?;
+
+    position.status = PositionStatus::Liquidated;
+    position.last_update = Clock::get()?.unix_timestamp;
+
+    Ok(total_pnl)
 }
 
 /// Validate leverage and position size against tier limits
@@ -257,6 +619,58 @@ pub fn validate_leverage_and_size(
     Ok(())
 }
 
+/// Emit `RiskParamsChanged` when `position` no longer has the `old` risk params
+pub fn emit_risk_params_changed(position_key: Pubkey, position: &Position, old: RiskParams, timestamp: i64) {
+    let new = position.risk_params();
+    if new == old {
+        return;
+    }
+
+    emit!(RiskParamsChanged {
+        position: position_key,
+        owner: position.owner,
+        old_margin: old.margin,
+        new_margin: new.margin,
+        old_leverage: old.leverage,
+        new_leverage: new.leverage,
+        old_liquidation_price: old.liquidation_price,
+        new_liquidation_price: new.liquidation_price,
+        timestamp,
+    });
+}
+
+/// Add a charged taker fee to the owner's tier account, if they have one, and emit it
+pub fn charge_taker_fee(
+    fee_tier: Option<&mut UserFeeTier>,
+    position: Pubkey,
+    owner: Pubkey,
+    fee: u64,
+) -> Result<()> {
+    if fee == 0 {
+        return Ok(());
+    }
+
+    let tier = match fee_tier {
+        Some(fee_tier) => {
+            fee_tier.fees_paid = fee_tier
+                .fees_paid
+                .checked_add(fee)
+                .ok_or(error!(PositionError::ArithmeticOverflow))?;
+            fee_tier.tier
+        }
+        None => 0,
+    };
+
+    emit!(TakerFeeCharged {
+        position,
+        owner,
+        tier,
+        fee,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -264,23 +678,34 @@ mod tests {
     #[test]
     fn test_unrealized_pnl_long() {
         // Long: 1 BTC @ 50k, now 55k
-        let size = 100_000_000;
+        let size = 1_000_000;
         let entry = 50_000_000_000;
         let mark = 55_000_000_000;
         
         let pnl = calculate_unrealized_pnl(size, entry, mark, Side::Long).unwrap();
-        assert_eq!(pnl, 5_000); // 5k USDT profit
+        assert_eq!(pnl, 5_000_000_000); // 5k USDT profit
     }
     
     #[test]
     fn test_unrealized_pnl_short() {
         // Short: 1 BTC @ 50k, now 45k
-        let size = 100_000_000;
+        let size = 1_000_000;
         let entry = 50_000_000_000;
         let mark = 45_000_000_000;
         
         let pnl = calculate_unrealized_pnl(size, entry, mark, Side::Short).unwrap();
-        assert_eq!(pnl, 5_000); // 5k USDT profit
+        assert_eq!(pnl, 5_000_000_000); // 5k USDT profit
+    }
+
+    #[test]
+    fn test_unrealized_pnl_loss_is_negative() {
+        // 1 BTC moving 5k against the position, 6-decimal size and PnL
+        let size = SUPPORTED_ASSET_DECIMALS;
+        let low = 45_000 * PRICE_PRECISION;
+        let high = 50_000 * PRICE_PRECISION;
+
+        assert_eq!(calculate_unrealized_pnl(size, high, low, Side::Long).unwrap(), -5_000_000_000);
+        assert_eq!(calculate_unrealized_pnl(size, low, high, Side::Short).unwrap(), -5_000_000_000);
     }
     
     #[test]
@@ -296,16 +721,342 @@ mod tests {
         assert_eq!(avg, 55_000_000_000); // 55k
     }
     
+    #[test]
+    fn test_liquidation_close_size() {
+        assert_eq!(calculate_liquidation_close_size(1_000, 2_500).unwrap(), 250);
+        // Rounds up
+        assert_eq!(calculate_liquidation_close_size(999, 2_500).unwrap(), 250);
+        assert_eq!(calculate_liquidation_close_size(1_000, 10_000).unwrap(), 1_000);
+
+        assert_eq!(calculate_pro_rata(-900, 250, 1_000).unwrap(), -225);
+        assert_eq!(calculate_pro_rata(-1, 1, 3).unwrap(), 0);
+    }
+
+    fn funded_position(funding_accrued: i64) -> Position {
+        Position {
+            owner: Pubkey::default(),
+            symbol: "SOL-USD".to_string(),
+            side: Side::Long,
+            size: 1_000,
+            entry_price: 100_000_000,
+            margin: 10_000_000,
+            leverage: 10,
+            unrealized_pnl: 0,
+            realized_pnl: 0,
+            funding_accrued,
+            liquidation_price: 90_000_000,
+            last_update: 0,
+            status: PositionStatus::Open,
+            bump: 255,
+            history: Default::default(),
+            history_count: 0,
+            funding_settled: 0,
+        }
+    }
+
+    #[test]
+    fn test_funding_settled_then_close() {
+        // Settlement pays the funding first, the close adds none of it again
+        let mut position = funded_position(-2_000_000);
+        assert_eq!(settle_funding(&mut position, 1_000).unwrap(), -2_000_000);
+        assert_eq!(close_pnl(&mut position, 100_000_000).unwrap(), 0);
+        assert_eq!(position.funding_accrued, 0);
+        assert_eq!(position.funding_settled, -2_000_000);
+
+        // Funding accrued after the settlement is the only funding the close pays
+        let mut position = funded_position(-2_000_000);
+        settle_funding(&mut position, 1_000).unwrap();
+        position.funding_accrued = 300_000;
+        assert_eq!(close_pnl(&mut position, 100_000_000).unwrap(), 300_000);
+        assert_eq!(position.funding_settled, -1_700_000);
+    }
+
+    #[test]
+    fn test_funding_closed_then_settled() {
+        // The close pays the funding, a settlement after it finds nothing left
+        let mut position = funded_position(-2_000_000);
+        assert_eq!(close_pnl(&mut position, 100_000_000).unwrap(), -2_000_000);
+        assert_eq!(settle_funding(&mut position, 1_000).unwrap(), 0);
+        assert_eq!(position.funding_settled, -2_000_000);
+
+        // A partial liquidation settles its share, the close the rest
+        let mut position = funded_position(-2_000_000);
+        assert_eq!(settle_funding(&mut position, 250).unwrap(), -500_000);
+        position.size = 750;
+        assert_eq!(close_pnl(&mut position, 100_000_000).unwrap(), -1_500_000);
+        assert_eq!(position.funding_settled, -2_000_000);
+    }
+
+    #[test]
+    fn test_liquidatable_pnl() {
+        let mut config = test_config();
+        let mut position = funded_position(0);
+        position.size = 1_000_000;
+
+        // 1 at 100 on 10 margin, the 2.5% tier maintenance is crossed below ~92.3
+        assert_eq!(liquidatable_pnl(&config, &position, 91_000_000, 0, 0).unwrap(), Some(-9_000_000));
+        assert_eq!(liquidatable_pnl(&config, &position, 95_000_000, 0, 0).unwrap(), None);
+
+        // A market floor of 6% takes it at 95 too
+        assert_eq!(liquidatable_pnl(&config, &position, 95_000_000, 600, 0).unwrap(), Some(-5_000_000));
+
+        // Margin calls on, an uncalled position waits for its call
+        config.margin_call_grace_secs = 60;
+        assert_eq!(liquidatable_pnl(&config, &position, 91_000_000, 0, 0).unwrap(), None);
+        position.status = PositionStatus::MarginCall;
+        assert_eq!(liquidatable_pnl(&config, &position, 91_000_000, 0, 60).unwrap(), Some(-9_000_000));
+
+        position.status = PositionStatus::Liquidated;
+        assert_eq!(liquidatable_pnl(&config, &position, 91_000_000, 0, 60).unwrap(), None);
+    }
+
+    #[test]
+    fn test_vault_shares() {
+        // Empty vault mints 1:1
+        assert_eq!(calculate_shares_for_deposit(1_000, 0, 0).unwrap(), 1_000);
+
+        // Vault grew 10%, same deposit buys fewer shares
+        assert_eq!(calculate_shares_for_deposit(1_100, 1_100, 1_000).unwrap(), 1_000);
+        assert_eq!(calculate_share_value(500, 1_100, 1_000).unwrap(), 550);
+
+        assert_eq!(calculate_takeover_price(100_000_000, Side::Long, 200).unwrap(), 98_000_000);
+        assert_eq!(calculate_takeover_price(100_000_000, Side::Short, 200).unwrap(), 102_000_000);
+    }
+
     #[test]
     fn test_margin_ratio() {
         // Margin: 5k, PnL: +2k, Position: 1 BTC @ 55k
-        let margin = 5_000;
-        let pnl = 2_000;
-        let size = 100_000_000;
+        let margin = 5_000_000_000;
+        let pnl = 2_000_000_000;
+        let size = 1_000_000;
         let mark_price = 55_000_000_000;
         
         let ratio = calculate_margin_ratio(margin, pnl, size, mark_price).unwrap();
         // (5000 + 2000) / 55000 = 0.127 = 1272 basis points
         assert!(ratio > 1200 && ratio < 1300);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_signed_margin_ratio() {
+        // 1 BTC at 55k, 5k margin down 7k: 2k under, -363 bps rounded toward zero
+        let margin = 5_000_000_000;
+        let pnl = -7_000_000_000;
+        let size = 1_000_000;
+        let mark_price = 55_000_000_000;
+
+        assert_eq!(calculate_signed_margin_ratio(margin, pnl, size, mark_price).unwrap(), -363);
+        assert_eq!(calculate_margin_ratio(margin, pnl, size, mark_price).unwrap(), 0);
+        assert!(check_liquidation(margin, pnl, size, mark_price, 250).unwrap());
+
+        // Further under ranks lower
+        assert!(
+            calculate_signed_margin_ratio(margin, 2 * pnl, size, mark_price).unwrap()
+                < calculate_signed_margin_ratio(margin, pnl, size, mark_price).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_liquidation_price_follows_modification() {
+        // 1 BTC long at 50k, 10x: 5k margin, tier 1 maintenance of 2.5%
+        let size = SUPPORTED_ASSET_DECIMALS;
+        let entry = 50_000 * PRICE_PRECISION;
+        let opened = position_liquidation_price(size, entry, 10, Side::Long, 0).unwrap();
+        assert_eq!(opened, 46_250 * PRICE_PRECISION);
+
+        // Topping up to 10k margin halves the leverage and moves the price away
+        let leverage = (calculate_position_value_for_tiers(size, entry).unwrap() / (10_000 * PRICE_PRECISION)) as u16;
+        let topped_up = position_liquidation_price(size, entry, leverage, Side::Long, 0).unwrap();
+        assert_eq!(topped_up, 41_250 * PRICE_PRECISION);
+
+        // A Market floor above the tier's rate pulls it back in
+        let floored = position_liquidation_price(size, entry, leverage, Side::Long, 500).unwrap();
+        assert_eq!(floored, 42_500 * PRICE_PRECISION);
+
+        // Past tier 1's max leverage the size's tier applies
+        let short = position_liquidation_price(size, entry, 40, Side::Short, 0).unwrap();
+        assert_eq!(short, 50_750 * PRICE_PRECISION);
+    }
+
+    #[test]
+    fn test_tier_max_notional_boundaries() {
+        use crate::constants::LEVERAGE_TIERS;
+
+        // 1 BTC at 100k, each tier's max notional at its max leverage
+        let price = 100_000 * PRICE_PRECISION;
+        for tier in &LEVERAGE_TIERS[1..] {
+            let size = mul_div(tier.max_position_size, SUPPORTED_ASSET_DECIMALS, price).unwrap();
+            assert_eq!(calculate_position_value_for_tiers(size, price).unwrap(), tier.max_position_size);
+            assert!(validate_leverage_and_size(tier.max_leverage, size, price).is_ok());
+            assert_eq!(
+                validate_leverage_and_size(tier.max_leverage, size + 1, price).unwrap_err(),
+                error!(PositionError::LeverageExceeded)
+            );
+        }
+    }
+
+    #[test]
+    fn test_large_notional_does_not_overflow() {
+        // 1,000 BTC at 100k: size × price is 1e20, past u64 before the division
+        let size = 1_000 * SUPPORTED_ASSET_DECIMALS;
+        let price = 100_000 * PRICE_PRECISION;
+        let value = 100_000_000 * PRICE_PRECISION;
+
+        assert_eq!(calculate_position_value_for_tiers(size, price).unwrap(), value);
+        assert!(validate_leverage_and_size(20, size, price).is_ok());
+        assert_eq!(
+            validate_leverage_and_size(21, size, price).unwrap_err(),
+            error!(PositionError::LeverageExceeded)
+        );
+        assert_eq!(calculate_initial_margin(size, price, 20).unwrap(), value / 20);
+        assert_eq!(calculate_average_entry_price(size, price, size, 2 * price).unwrap(), 150_000 * PRICE_PRECISION);
+
+        // 1% move is 1M, leaving 4M of the 5M margin
+        let pnl = calculate_unrealized_pnl(size, price, 99_000 * PRICE_PRECISION, Side::Long).unwrap();
+        assert_eq!(pnl, -1_000_000 * PRICE_PRECISION as i64);
+        assert_eq!(calculate_margin_ratio(value / 20, pnl, size, price).unwrap(), 400);
+
+        // Only a result that doesn't fit errors
+        assert_eq!(
+            calculate_position_value_for_tiers(u64::MAX, u64::MAX).unwrap_err(),
+            error!(PositionError::ArithmeticOverflow)
+        );
+    }
+
+    fn test_config() -> Config {
+        Config {
+            admin: Pubkey::default(),
+            liquidator_whitelist_enabled: false,
+            bump: 255,
+            partial_liquidation_enabled: false,
+            liquidation_buffer_bps: 0,
+            paused: false,
+            margin_call_buffer_bps: 0,
+            margin_call_grace_secs: 0,
+            taker_fee_bps: [0; FEE_TIER_COUNT],
+            max_price_age_secs: 0,
+        }
+    }
+
+    #[test]
+    fn test_taker_fee() {
+        assert!(validate_fee_schedule(&[5, 4, 3, 2]).is_ok());
+        assert!(validate_fee_schedule(&[0; FEE_TIER_COUNT]).is_ok());
+        assert!(validate_fee_schedule(&[5, 6, 3, 2]).is_err());
+        assert!(validate_fee_schedule(&[101, 4, 3, 2]).is_err());
+
+        let config = Config {
+            taker_fee_bps: [5, 4, 3, 2],
+            ..test_config()
+        };
+        let mut fee_tier = UserFeeTier {
+            owner: Pubkey::default(),
+            tier: 2,
+            volume_30d: 0,
+            fees_paid: 0,
+            updated_at: 0,
+            bump: 255,
+        };
+
+        // 10 at 100 is 1,000 notional
+        assert_eq!(calculate_taker_fee(&config, None, 10_000_000, 100_000_000).unwrap(), 500_000);
+        assert_eq!(calculate_taker_fee(&config, Some(&fee_tier), 10_000_000, 100_000_000).unwrap(), 300_000);
+        fee_tier.tier = 9;
+        assert_eq!(calculate_taker_fee(&config, Some(&fee_tier), 10_000_000, 100_000_000).unwrap(), 200_000);
+    }
+
+    #[test]
+    fn test_require_fresh_price() {
+        let now = 1_700_000_000;
+        let mut config = test_config();
+        // Unset, the default applies
+        assert!(require_fresh_price(&config, now - DEFAULT_MAX_PRICE_AGE_SECS as i64, now).is_ok());
+        assert!(require_fresh_price(&config, now - DEFAULT_MAX_PRICE_AGE_SECS as i64 - 1, now).is_err());
+        assert!(require_fresh_price(&config, 0, now).is_err());
+
+        config.max_price_age_secs = 30;
+        assert!(require_fresh_price(&config, now - 30, now).is_ok());
+        assert!(require_fresh_price(&config, now + 2, now).is_ok());
+        assert!(require_fresh_price(&config, now - 31, now).is_err());
+        assert!(require_fresh_price(&config, 0, now).is_err());
+    }
+
+    #[test]
+    fn test_validate_market_bounds() {
+        assert!(validate_market_bounds(250, 1_000).is_ok());
+        assert!(validate_market_bounds(500, 500).is_ok());
+        assert!(validate_market_bounds(1_000, 250).is_err());
+        assert!(validate_market_bounds(250, 5_001).is_err());
+    }
+
+    #[test]
+    fn test_apply_open_throttle() {
+        let mut market = Market {
+            symbol: "SOL-USD".to_string(),
+            maintenance_margin_bps: 500,
+            min_maintenance_margin_bps: 500,
+            max_maintenance_margin_bps: 1_000,
+            updated_at: 0,
+            bump: 255,
+            open_cooldown_slots: 10,
+            max_opens_per_slot: 2,
+            open_counter_slot: 0,
+            opens_in_slot: 0,
+            rate_window_start: 0,
+            rate_window_start_bps: 0,
+        };
+
+        // First open, then the cooldown holds until slot 110
+        assert!(apply_open_throttle(&mut market, 0, 100).is_ok());
+        assert!(apply_open_throttle(&mut market, 100, 109).is_err());
+        assert!(apply_open_throttle(&mut market, 100, 110).is_ok());
+
+        // Two opens per slot across users, the counter resets each slot
+        assert!(apply_open_throttle(&mut market, 0, 110).is_ok());
+        assert!(apply_open_throttle(&mut market, 0, 110).is_err());
+        assert!(apply_open_throttle(&mut market, 0, 111).is_ok());
+        assert_eq!(market.opens_in_slot, 1);
+
+        market.open_cooldown_slots = 0;
+        market.max_opens_per_slot = 0;
+        for _ in 0..5 {
+            assert!(apply_open_throttle(&mut market, 111, 111).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_apply_maintenance_margin_step() {
+        let mut market = Market {
+            symbol: "SOL-USD".to_string(),
+            maintenance_margin_bps: 500,
+            min_maintenance_margin_bps: 500,
+            max_maintenance_margin_bps: 1_000,
+            updated_at: 0,
+            bump: 255,
+            open_cooldown_slots: 0,
+            max_opens_per_slot: 0,
+            open_counter_slot: 0,
+            opens_in_slot: 0,
+            rate_window_start: 0,
+            rate_window_start_bps: 0,
+        };
+        let now = 1_700_000_000;
+
+        // Straight from min to max is refused, as is leaving the bounds
+        assert!(apply_maintenance_margin_step(&mut market, 1_000, now).is_err());
+        assert!(apply_maintenance_margin_step(&mut market, 450, now).is_err());
+
+        // Three full steps use up the window
+        assert!(apply_maintenance_margin_step(&mut market, 550, now).is_ok());
+        assert!(apply_maintenance_margin_step(&mut market, 600, now + 60).is_ok());
+        assert!(apply_maintenance_margin_step(&mut market, 650, now + 120).is_ok());
+        assert!(apply_maintenance_margin_step(&mut market, 660, now + 180).is_err());
+        assert_eq!(market.maintenance_margin_bps, 650);
+        assert_eq!(market.rate_window_start_bps, 500);
+
+        // Moving back within the window is allowed, the next window starts over
+        assert!(apply_maintenance_margin_step(&mut market, 600, now + 240).is_ok());
+        assert!(apply_maintenance_margin_step(&mut market, 650, now + MAINTENANCE_MARGIN_WINDOW_SECS).is_ok());
+        assert_eq!(market.rate_window_start_bps, 600);
+        assert_eq!(market.updated_at, now + MAINTENANCE_MARGIN_WINDOW_SECS);
+    }
+}
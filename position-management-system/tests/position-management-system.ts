@@ -3,6 +3,18 @@ import { Program } from "@coral-xyz/anchor";
 import { PositionManagementSystem } from "../target/types/position_management_system";
 import { expect } from "chai";
 
+// Pyth push oracle price update accounts the program reads publish times from
+const PYTH_PUSH_ORACLE = new anchor.web3.PublicKey("pythWSnswVUd12oZpeFP8e9CVaEqJg25g1Vtc2biRsT");
+const PYTH_FEEDS: Record<string, string> = {
+  "BTC-USD": "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43",
+  "ETH-USD": "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace",
+};
+const priceUpdate = (symbol: string) =>
+  anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from([0, 0]), Buffer.from(PYTH_FEEDS[symbol], "hex")],
+    PYTH_PUSH_ORACLE
+  )[0];
+
 describe("position-management-system", () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
@@ -211,16 +223,18 @@ describe("position-management-system", () => {
     const leverage = 10;
 
     try {
-      const tx = await program.methods
-        .openPosition(symbol, { long: {} }, size, leverage, entryPrice)
-        .rpc(); // PDAs auto-resolved!
-
-      console.log("Open position tx:", tx);
-
       const [userAccountPda] = anchor.web3.PublicKey.findProgramAddressSync(
         [Buffer.from("user"), user.publicKey.toBuffer()],
         program.programId
       );
+      const { positionCountTotal } = await program.account.userAccount.fetch(userAccountPda);
+
+      const tx = await program.methods
+        .openPosition(positionCountTotal, symbol, { long: {} }, size, leverage, entryPrice)
+        .accounts({ priceUpdate: priceUpdate(symbol) })
+        .rpc(); // PDAs auto-resolved!
+
+      console.log("Open position tx:", tx);
 
       const userAccount = await program.account.userAccount.fetch(userAccountPda);
       const [positionPda] = anchor.web3.PublicKey.findProgramAddressSync(
@@ -357,6 +371,7 @@ describe("position-management-system", () => {
         .closePosition(finalPrice)
         .accounts({
           position: positionPda,
+          priceUpdate: priceUpdate("BTC-USD"),
           // owner: user.publicKey,
         })
         .rpc();
@@ -393,7 +408,7 @@ describe("position-management-system", () => {
       program.programId
     );
 
-    const symbol = "ETH-USDT";
+    const symbol = "ETH-USD";
     const size = new anchor.BN(.1 * 100_000_000); // 10 ETH
     const entryPrice = new anchor.BN(3000 * 1_000_000); // 3,000 USDT
     const leverage = 50;
@@ -401,12 +416,14 @@ describe("position-management-system", () => {
     try {
       const tx = await program.methods
         .openPosition(
+          userAccount.positionCountTotal,
           symbol,
           { short: {} },
           size,
           leverage,
           entryPrice
         )
+        .accounts({ priceUpdate: priceUpdate(symbol) })
         .rpc();
 
       console.log("Open short position tx:", tx);